@@ -0,0 +1,41 @@
+//! Cold and warm start of a small conversion with the signature cache.
+//!
+//! Usage: cargo bench --features signature-cache --bench signature_cache
+//!
+//! Each iteration starts from an empty signature table, as a new process
+//! would. The warm iterations load the cache file before the conversion,
+//! so they include the cost of reading it.
+
+use std::hint::black_box;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use hugr_jeff::doctest_support::qubits;
+use hugr_jeff::extension::signature_cache;
+use hugr_jeff::jeff_to_hugr;
+
+fn cold_start(c: &mut Criterion) {
+    let jeff = qubits();
+    let dir = std::env::temp_dir().join("hugr-jeff-signature-cache-bench");
+    signature_cache::clear();
+    jeff_to_hugr(&jeff).unwrap();
+    signature_cache::store(&dir).unwrap();
+
+    let mut group = c.benchmark_group("qubits");
+    group.bench_function("cold", |b| {
+        b.iter(|| {
+            signature_cache::clear();
+            black_box(jeff_to_hugr(&jeff).unwrap())
+        })
+    });
+    group.bench_function("warm", |b| {
+        b.iter(|| {
+            signature_cache::clear();
+            assert!(signature_cache::load(&dir));
+            black_box(jeff_to_hugr(&jeff).unwrap())
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, cold_start);
+criterion_main!(benches);
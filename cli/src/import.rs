@@ -0,0 +1,225 @@
+//! Read a _jeff_ program from stdin and write it to stdout as a HUGR envelope.
+//!
+//! Usage: hugr-import-jeff [--record-provenance] [--no-validate] [--debug-dir DIR] [--check-gate-set FILE] [--target FILE] < program.jeff > program.hugr
+//!        hugr-import-jeff --footprint [table|json] < program.jeff
+//!        hugr-import-jeff --interaction-graph FUNCTION [--graph-format dot|json] < program.jeff
+//!
+//! Diagnostics are written to stderr as JSON lines, one object per message,
+//! including a `"warning"` line for each approximation made by the
+//! translation.
+
+use std::io::{BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Parser, ValueEnum};
+use hugr::envelope::EnvelopeConfig;
+use hugr_jeff::analysis::{GateSet, check_gate_set, conversion_footprint, interaction_graph};
+use hugr_jeff::hugr_importer::{ImportOptions, import_with_warnings};
+use hugr_jeff::{JeffToHugrConfig, TargetInfo};
+use jeff::Jeff;
+
+/// Command-line arguments
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Record the originating jeff operation of each translated node.
+    #[arg(long, env = "HUGR_JEFF_RECORD_PROVENANCE")]
+    record_provenance: bool,
+
+    /// Skip validation of the translated HUGR.
+    #[arg(long, env = "HUGR_JEFF_NO_VALIDATE")]
+    no_validate: bool,
+
+    /// Write a human-readable text envelope instead of a binary one.
+    #[arg(long)]
+    text: bool,
+
+    /// Directory to write the intermediate translation state to if the
+    /// translation fails.
+    #[arg(long, value_name = "DIR")]
+    debug_dir: Option<PathBuf>,
+
+    /// Fail if the translated HUGR uses operations outside the gate set
+    /// described by this JSON file.
+    #[arg(long, value_name = "FILE")]
+    check_gate_set: Option<PathBuf>,
+
+    /// Warn about the functions that may use more qubits than the device
+    /// described by this JSON file.
+    #[arg(long, value_name = "FILE")]
+    target: Option<PathBuf>,
+
+    /// Print an estimate of the size of the translated HUGR, without
+    /// translating the program.
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        default_missing_value = "table"
+    )]
+    footprint: Option<FootprintFormat>,
+
+    /// Print the graph of the qubits interacting in the multi-qubit gates of
+    /// a function, without translating the program.
+    #[arg(long, value_name = "FUNCTION")]
+    interaction_graph: Option<String>,
+
+    /// Output format of the `--interaction-graph` report.
+    #[arg(long, value_name = "FORMAT", default_value = "dot", requires = "interaction_graph")]
+    graph_format: GraphFormat,
+}
+
+/// Output format of the `--footprint` report.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum FootprintFormat {
+    /// A human-readable table.
+    Table,
+    /// A JSON object.
+    Json,
+}
+
+/// Output format of the `--interaction-graph` report.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum GraphFormat {
+    /// A Graphviz DOT graph.
+    Dot,
+    /// A JSON object.
+    Json,
+}
+
+/// Print a diagnostic message to stderr as a JSON line.
+fn report(level: &str, message: impl ToString) {
+    let line = serde_json::json!({ "level": level, "message": message.to_string() });
+    eprintln!("{line}");
+}
+
+/// Read a gate set from a JSON file.
+fn read_gate_set(path: &Path) -> Result<GateSet, String> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read gate set {}: {e}", path.display()))?;
+    serde_json::from_str(&json).map_err(|e| format!("Invalid gate set {}: {e}", path.display()))
+}
+
+/// Read a target device description from a JSON file.
+fn read_target(path: &Path) -> Result<TargetInfo, String> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read target {}: {e}", path.display()))?;
+    serde_json::from_str(&json).map_err(|e| format!("Invalid target {}: {e}", path.display()))
+}
+
+/// Print the estimated size of the translation of the program read from stdin.
+fn print_footprint(format: FootprintFormat) -> Result<(), String> {
+    let jeff = Jeff::read(BufReader::new(std::io::stdin().lock())).map_err(|e| e.to_string())?;
+    let report = conversion_footprint(&jeff).map_err(|e| e.to_string())?;
+    match format {
+        FootprintFormat::Table => println!("{report}"),
+        FootprintFormat::Json => {
+            let json = serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?;
+            println!("{json}");
+        }
+    }
+    Ok(())
+}
+
+/// Print the qubit interaction graph of a function of the program read from
+/// stdin.
+fn print_interaction_graph(function: &str, format: GraphFormat) -> Result<(), String> {
+    let jeff = Jeff::read(BufReader::new(std::io::stdin().lock())).map_err(|e| e.to_string())?;
+    let graph = interaction_graph(&jeff, function).map_err(|e| e.to_string())?;
+    match format {
+        GraphFormat::Dot => println!("{}", graph.to_dot()),
+        GraphFormat::Json => {
+            let json = serde_json::to_string_pretty(&graph.to_json()).map_err(|e| e.to_string())?;
+            println!("{json}");
+        }
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    if let Some(format) = args.footprint {
+        return match print_footprint(format) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                report("error", e);
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    if let Some(function) = &args.interaction_graph {
+        return match print_interaction_graph(function, args.graph_format) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                report("error", e);
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    let gate_set = match args.check_gate_set.as_deref().map(read_gate_set).transpose() {
+        Ok(gate_set) => gate_set,
+        Err(e) => {
+            report("error", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let target = match args.target.as_deref().map(read_target).transpose() {
+        Ok(target) => target,
+        Err(e) => {
+            report("error", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let translation = JeffToHugrConfig::default()
+        .with_record_provenance(args.record_provenance)
+        .with_validate(!args.no_validate)
+        .with_debug_snapshots(args.debug_dir)
+        .with_target(target);
+    let opts = ImportOptions::default()
+        .with_translation(translation)
+        .with_validate(!args.no_validate);
+
+    let hugr = match import_with_warnings(&mut std::io::stdin().lock(), &opts) {
+        Ok((hugr, warnings)) => {
+            for warning in &warnings {
+                report("warning", warning);
+            }
+            hugr
+        }
+        Err(e) => {
+            report("error", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let Some(gate_set) = &gate_set {
+        let violations = check_gate_set(&hugr, gate_set);
+        for violation in &violations {
+            report("error", violation);
+        }
+        if !violations.is_empty() {
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let config = match args.text {
+        true => EnvelopeConfig::text(),
+        false => EnvelopeConfig::binary(),
+    };
+    let mut stdout = std::io::stdout().lock();
+    if let Err(e) = hugr.store(&mut stdout, config) {
+        report("error", e);
+        return ExitCode::FAILURE;
+    }
+    if let Err(e) = stdout.flush() {
+        report("error", e);
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
@@ -1,54 +1,199 @@
 //! Convert the jeff file passed as parameter into HUGR and print it as mermaid.
 //!
-//! Usage: jeff_to_hugr <jeff_file>
+//! Usage: jeff_to_hugr [--mermaid | --pretty] [--sig-cache [DIR]] [--trace FILE] <jeff_file>
+//!        jeff_to_hugr diff [--json] <a.jeff> <b.jeff>
+//!        jeff_to_hugr --typecheck <jeff_file>
+//!        jeff_to_hugr --capabilities
 
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
 use core::panic;
 use hugr::envelope::EnvelopeConfig;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use hugr::HugrView;
-use hugr_jeff::jeff_to_hugr;
+use hugr_jeff::analysis::{check_types, diff_jeff};
+use hugr_jeff::capabilities::capability_manifest;
+use hugr_jeff::envelope::{HugrEnvelopeTarget, store_hugr_compat};
+use hugr_jeff::extension::signature_cache;
+use hugr_jeff::render::mermaid_pretty;
+use hugr_jeff::{JeffToHugrConfig, jeff_to_hugr_with_config};
 use jeff::Jeff;
 
 /// Command-line arguments
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// The _jeff_ file to convert
-    file: String,
+    #[arg(required_unless_present = "capabilities")]
+    file: Option<String>,
 
     /// Sets an optional output file for HUGR JSON
     #[arg(short, long)]
     output: Option<String>,
 
+    /// Write the output file for an older hugr release.
+    ///
+    /// Fails if the HUGR uses features that the release does not support.
+    #[arg(long, value_enum)]
+    target: Option<Target>,
+
     /// Print the hugr as mermaid.
     #[arg(short, long)]
     mermaid: bool,
+
+    /// Print the hugr as mermaid, with stable node ids and named qubit wires.
+    #[arg(long)]
+    pretty: bool,
+
+    /// Reuse the extension operation signatures cached in a directory, and
+    /// cache the new ones.
+    ///
+    /// Defaults to `hugr-jeff` in the XDG cache directory.
+    #[arg(long, value_name = "DIR", num_args = 0..=1)]
+    sig_cache: Option<Option<PathBuf>>,
+
+    /// Write the steps of the conversion to a file, as JSON lines.
+    #[arg(long, value_name = "FILE")]
+    trace: Option<PathBuf>,
+
+    /// Only check the types of the values of the _jeff_ file, printing the
+    /// inconsistencies instead of converting it.
+    #[arg(long)]
+    typecheck: bool,
+
+    /// Print the _jeff_ operations and types supported by the translation,
+    /// as JSON.
+    #[arg(long, exclusive = true)]
+    capabilities: bool,
+}
+
+/// Commands other than the conversion.
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Print the operation-level differences between two _jeff_ files.
+    Diff {
+        /// The original _jeff_ file.
+        a: PathBuf,
+        /// The modified _jeff_ file.
+        b: PathBuf,
+        /// Print the differences as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+/// The hugr releases that the output file can target.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Target {
+    #[value(name = "0.15")]
+    Hugr0_15,
+    #[value(name = "0.20")]
+    Hugr0_20,
+    #[value(name = "0.21")]
+    Hugr0_21,
+    Current,
+}
+
+impl From<Target> for HugrEnvelopeTarget {
+    fn from(target: Target) -> Self {
+        match target {
+            Target::Hugr0_15 => HugrEnvelopeTarget::Hugr0_15,
+            Target::Hugr0_20 => HugrEnvelopeTarget::Hugr0_20,
+            Target::Hugr0_21 => HugrEnvelopeTarget::Hugr0_21,
+            Target::Current => HugrEnvelopeTarget::Current,
+        }
+    }
+}
+
+/// Read a _jeff_ file, panicking on failure.
+fn read_jeff(path: &Path) -> Jeff<'static> {
+    let file = std::fs::File::open(path).unwrap();
+    let buffer = std::io::BufReader::new(file);
+    Jeff::read(buffer).unwrap_or_else(|e| panic!("Failed to read example program:\n {}", e))
 }
 
 fn main() {
     // Parse command-line arguments
     let args = Args::parse();
 
+    if let Some(Command::Diff { a, b, json }) = args.command {
+        let diff = diff_jeff(&read_jeff(&a), &read_jeff(&b))
+            .unwrap_or_else(|e| panic!("Failed to compare the jeff files:\n {}", e));
+        match json {
+            true => println!("{}", serde_json::to_string_pretty(&diff).unwrap()),
+            false => print!("{diff}"),
+        }
+        return;
+    }
+    if args.capabilities {
+        let manifest = capability_manifest();
+        println!("{}", serde_json::to_string_pretty(&manifest).unwrap());
+        return;
+    }
+
     // Read _jeff_ file
-    let path = PathBuf::from(args.file);
-    let file = std::fs::File::open(&path).unwrap();
-    let buffer = std::io::BufReader::new(file);
-    let jeff =
-        Jeff::read(buffer).unwrap_or_else(|e| panic!("Failed to read example program:\n {}", e));
+    let path = PathBuf::from(args.file.unwrap());
+    let jeff = read_jeff(&path);
 
-    // Convert _jeff_ to HUGR
-    let hugr =
-        jeff_to_hugr(&jeff).unwrap_or_else(|e| panic!("Failed to convert jeff to HUGR:\n {}", e));
+    if args.typecheck {
+        let errors = check_types(&jeff)
+            .unwrap_or_else(|e| panic!("Failed to check the jeff types:\n {}", e));
+        for error in &errors {
+            println!("{error}");
+        }
+        if !errors.is_empty() {
+            std::process::exit(1);
+        }
+        println!("No type errors");
+        return;
+    }
+
+    // Convert _jeff_ to HUGR, reusing the cached signatures
+    let sig_cache = args
+        .sig_cache
+        .map(|dir| dir.or_else(signature_cache::default_dir));
+    if let Some(Some(dir)) = &sig_cache {
+        signature_cache::load(dir);
+    }
+    let config = JeffToHugrConfig::default()
+        .with_name_wires(args.pretty)
+        .with_record_trace(args.trace.is_some());
+    let converted = jeff_to_hugr_with_config(&jeff, &config)
+        .unwrap_or_else(|e| panic!("Failed to convert jeff to HUGR:\n {}", e));
+    if let Some(Some(dir)) = &sig_cache
+        && let Err(e) = signature_cache::store(dir)
+    {
+        eprintln!(
+            "Failed to write the signature cache in {}: {e}",
+            dir.display()
+        );
+    }
+    if let (Some(path), Some(trace)) = (&args.trace, &converted.trace) {
+        let file = std::fs::File::create(path).unwrap();
+        trace
+            .write_json_lines(std::io::BufWriter::new(file))
+            .unwrap_or_else(|e| panic!("Failed to write the conversion trace:\n {}", e));
+    }
+    let hugr = converted.hugr;
 
     // Print HUGR as mermaid
-    if args.mermaid || args.output.is_none() {
+    if args.pretty {
+        println!("{}", mermaid_pretty(&hugr));
+    } else if args.mermaid || args.output.is_none() {
         println!("{}", hugr.mermaid_string());
     }
 
-    // Optionally write HUGR JSON to output file
-    if let Some(output) = args.output {
+    // Optionally write HUGR to output file, for an older hugr release
+    if let (Some(output), Some(target)) = (&args.output, args.target) {
+        let file = std::fs::File::create(output).unwrap();
+        store_hugr_compat(&hugr, target.into(), file).unwrap_or_else(|e| {
+            panic!("Failed to serialize HUGR:\n {}", e);
+        });
+    } else if let Some(output) = args.output {
         let json = hugr.store_str(EnvelopeConfig::text()).unwrap_or_else(|e| {
             panic!("Failed to serialize HUGR:\n {}", e);
         });
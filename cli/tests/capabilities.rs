@@ -0,0 +1,32 @@
+//! Integration tests for the `--capabilities` option of the `hugr-jeff` binary.
+
+use std::process::Command;
+
+#[test]
+fn capabilities_json() {
+    let output = Command::new(env!("CARGO_BIN_EXE_hugr-jeff"))
+        .arg("--capabilities")
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let manifest: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(manifest["version"], hugr_jeff_version());
+    let for_loop = manifest["ops"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|op| op["category"] == "control_flow" && op["name"] == "For")
+        .unwrap();
+    assert_eq!(for_loop["support"]["kind"], "full");
+}
+
+/// The version of the `hugr-jeff` library the binary is built with.
+fn hugr_jeff_version() -> String {
+    let manifest = hugr_jeff::capabilities::capability_manifest();
+    manifest.version.to_string()
+}
@@ -0,0 +1,38 @@
+//! Integration tests for the `diff` command of the `hugr-jeff` binary.
+
+use std::process::Command;
+
+fn fixture(name: &str) -> String {
+    format!(
+        "{}/../test_files/{name}/{name}.jeff",
+        env!("CARGO_MANIFEST_DIR")
+    )
+}
+
+/// Run the `diff` command, returning its stdout.
+fn run_diff(a: &str, b: &str, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_hugr-jeff"))
+        .arg("diff")
+        .args(args)
+        .args([fixture(a), fixture(b)])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn diff_identical() {
+    assert_eq!(run_diff("qubits", "qubits", &[]), "No differences\n");
+}
+
+#[test]
+fn diff_json() {
+    let stdout = run_diff("qubits", "entangled_qs", &["--json"]);
+    let diff: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(diff["removed_functions"], serde_json::json!(["Circuit"]));
+}
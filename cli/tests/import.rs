@@ -0,0 +1,125 @@
+//! Integration tests for the `hugr-import-jeff` adapter binary.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use hugr::{Hugr, HugrView};
+use hugr_jeff::hugr_importer::extension_registry;
+
+/// Pipe the input through the adapter, returning its exit status, stdout and stderr.
+fn run_import(input: &[u8], args: &[&str]) -> (bool, Vec<u8>, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_hugr-import-jeff"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input).unwrap();
+    let output = child.wait_with_output().unwrap();
+    (
+        output.status.success(),
+        output.stdout,
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+fn fixture(name: &str) -> Vec<u8> {
+    let path = format!(
+        "{}/../test_files/{name}/{name}.jeff",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    std::fs::read(path).unwrap()
+}
+
+#[test]
+fn import_to_envelope() {
+    for name in ["qubits", "catalyst_simple", "catalyst_tket_opt"] {
+        let (success, stdout, stderr) = run_import(&fixture(name), &[]);
+        assert!(success, "{name}: {stderr}");
+
+        let hugr = Hugr::load(stdout.as_slice(), Some(&extension_registry())).unwrap();
+        hugr.validate().unwrap();
+    }
+}
+
+#[test]
+fn import_with_provenance() {
+    let (success, stdout, stderr) =
+        run_import(&fixture("qubits"), &["--record-provenance", "--text"]);
+    assert!(success, "{stderr}");
+
+    let hugr = Hugr::load(stdout.as_slice(), Some(&extension_registry())).unwrap();
+    assert!(
+        hugr.nodes()
+            .any(|n| hugr.get_metadata(n, hugr_jeff::metadata::OP_INDEX).is_some())
+    );
+}
+
+#[test]
+fn import_reports_errors_as_json() {
+    let (success, stdout, stderr) = run_import(b"not a jeff file", &[]);
+    assert!(!success);
+    assert!(stdout.is_empty());
+
+    let line: serde_json::Value = serde_json::from_str(stderr.lines().next().unwrap()).unwrap();
+    assert_eq!(line["level"], "error");
+    assert!(line["message"].is_string());
+}
+
+#[test]
+fn import_checks_gate_set() {
+    let path = std::env::temp_dir().join(format!("hugr-jeff-gate-set-{}.json", std::process::id()));
+    let gate_set = r#"{
+        "tket_ops": ["QAlloc"],
+        "jeff_ops": [{ "op": "QGateN", "gates": ["X", "CX", "Rx"] }]
+    }"#;
+    std::fs::write(&path, gate_set).unwrap();
+    let args = ["--check-gate-set", path.to_str().unwrap()];
+    let (success, stdout, stderr) = run_import(&fixture("qubits"), &args);
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(!success);
+    assert!(stdout.is_empty());
+    let lines = stderr.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 1, "{stderr}");
+    let line: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+    assert!(line["message"].as_str().unwrap().contains("Operation H"));
+}
+
+#[test]
+fn import_footprint() {
+    let (success, stdout, stderr) = run_import(&fixture("qubits"), &["--footprint", "json"]);
+    assert!(success, "{stderr}");
+
+    let report: serde_json::Value = serde_json::from_slice(&stdout).unwrap();
+    assert!(report["nodes"].as_u64().unwrap() > 0);
+    assert_eq!(report["unsupported_ops"], 0);
+    assert!(report["extension_ops"].is_object());
+
+    let (success, stdout, stderr) = run_import(&fixture("qubits"), &["--footprint"]);
+    assert!(success, "{stderr}");
+    assert!(String::from_utf8(stdout).unwrap().starts_with("Nodes"));
+}
+
+#[test]
+fn import_reports_warnings_as_json() {
+    let path = std::env::temp_dir().join(format!("hugr-jeff-target-{}.json", std::process::id()));
+    std::fs::write(&path, r#"{ "name": "empty", "num_qubits": 0 }"#).unwrap();
+    let args = ["--target", path.to_str().unwrap()];
+    let (success, stdout, stderr) = run_import(&fixture("qubits"), &args);
+    std::fs::remove_file(&path).unwrap();
+
+    // Warnings do not stop the translation.
+    assert!(success, "{stderr}");
+    let hugr = Hugr::load(stdout.as_slice(), Some(&extension_registry())).unwrap();
+    hugr.validate().unwrap();
+
+    let lines = stderr.lines().collect::<Vec<_>>();
+    assert!(!lines.is_empty());
+    for line in lines {
+        let line: serde_json::Value = serde_json::from_str(line).unwrap();
+        assert_eq!(line["level"], "warning");
+        assert!(line["message"].as_str().unwrap().contains("target 'empty'"));
+    }
+}
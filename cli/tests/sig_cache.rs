@@ -0,0 +1,40 @@
+//! Integration tests for the `--sig-cache` option of the `hugr-jeff` binary.
+
+use std::path::Path;
+use std::process::Command;
+
+fn fixture(name: &str) -> String {
+    format!(
+        "{}/../test_files/{name}/{name}.jeff",
+        env!("CARGO_MANIFEST_DIR")
+    )
+}
+
+/// Convert a fixture with a signature cache, returning the printed HUGR.
+fn run_cached(name: &str, cache: &Path) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_hugr-jeff"))
+        .arg("--sig-cache")
+        .arg(cache)
+        .arg(fixture(name))
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn cold_and_warm_cache() {
+    let cache = std::env::temp_dir().join(format!("hugr-jeff-cli-sigs-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&cache);
+
+    let cold = run_cached("qubits", &cache);
+    assert!(std::fs::read_dir(&cache).unwrap().next().is_some());
+    let warm = run_cached("qubits", &cache);
+    assert_eq!(cold, warm);
+
+    std::fs::remove_dir_all(&cache).unwrap();
+}
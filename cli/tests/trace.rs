@@ -0,0 +1,36 @@
+//! Integration tests for the `--trace` option of the `hugr-jeff` binary.
+
+use std::process::Command;
+
+fn fixture(name: &str) -> String {
+    format!(
+        "{}/../test_files/{name}/{name}.jeff",
+        env!("CARGO_MANIFEST_DIR")
+    )
+}
+
+#[test]
+fn trace_json_lines() {
+    let trace = std::env::temp_dir().join(format!("hugr-jeff-cli-trace-{}", std::process::id()));
+    let output = Command::new(env!("CARGO_BIN_EXE_hugr-jeff"))
+        .arg("--trace")
+        .arg(&trace)
+        .arg(fixture("qubits"))
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let lines = std::fs::read_to_string(&trace).unwrap();
+    let events: Vec<serde_json::Value> = lines
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(events[0]["event"], "node_added");
+    assert!(events.iter().any(|event| event["event"] == "op_converted"));
+
+    std::fs::remove_file(&trace).unwrap();
+}
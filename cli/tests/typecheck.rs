@@ -0,0 +1,28 @@
+//! Integration tests for the `--typecheck` flag of the `hugr-jeff` binary.
+
+use std::process::Command;
+
+fn fixture(name: &str) -> String {
+    format!(
+        "{}/../test_files/{name}/{name}.jeff",
+        env!("CARGO_MANIFEST_DIR")
+    )
+}
+
+#[test]
+fn typecheck_clean_program() {
+    let output = Command::new(env!("CARGO_BIN_EXE_hugr-jeff"))
+        .arg("--typecheck")
+        .arg(fixture("qubits"))
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "No type errors\n"
+    );
+}
@@ -0,0 +1,23 @@
+//! Import a _jeff_ file into a HUGR without copying it into memory.
+//!
+//! Usage: cargo run --release --features mmap --example mmap_import -- program.jeff
+//!
+//! The file is memory-mapped and read in place, so the resident memory of
+//! the import is dominated by the translated HUGR rather than by the input.
+
+use hugr_jeff::hugr::HugrView;
+use hugr_jeff::hugr_importer::{ImportOptions, jeff_to_hugr_mmap};
+
+fn main() {
+    let Some(path) = std::env::args().nth(1) else {
+        eprintln!("Usage: mmap_import <program.jeff>");
+        std::process::exit(2);
+    };
+    match jeff_to_hugr_mmap(&path, &ImportOptions::default()) {
+        Ok(hugr) => println!("{path}: {} nodes, {} edges", hugr.num_nodes(), hugr.num_edges()),
+        Err(e) => {
+            eprintln!("{path}: {e}");
+            std::process::exit(1);
+        }
+    }
+}
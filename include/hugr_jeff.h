@@ -0,0 +1,68 @@
+#ifndef HUGR_JEFF_H
+#define HUGR_JEFF_H
+
+/* Generated with cbindgen from src/capi.rs. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// The conversion succeeded.
+#define HUGR_JEFF_OK 0
+
+// A required pointer argument was null.
+#define HUGR_JEFF_INVALID_ARGUMENT 1
+
+// The input could not be read as a _jeff_ program.
+#define HUGR_JEFF_READ_ERROR 2
+
+// The _jeff_ program could not be translated.
+#define HUGR_JEFF_TRANSLATE_ERROR 3
+
+// The translated HUGR is not valid.
+#define HUGR_JEFF_VALIDATE_ERROR 4
+
+// The HUGR could not be written as an envelope.
+#define HUGR_JEFF_ENVELOPE_ERROR 5
+
+// The conversion panicked.
+#define HUGR_JEFF_PANIC 6
+
+// Convert a _jeff_ program into a binary HUGR envelope.
+//
+// On success, `*out_envelope` points to a buffer of `*out_len` bytes that
+// must be released with [`hugr_jeff_free_buffer`].
+//
+// On failure, `*out_envelope` is null and, if `out_error` is not null,
+// `*out_error` points to a NUL-terminated message that must be released with
+// [`hugr_jeff_free_string`].
+//
+// # Safety
+//
+// `jeff_bytes` must point to `len` readable bytes, or be null with `len`
+// equal to zero. The `out_*` pointers must be null or valid for writes.
+int32_t hugr_jeff_convert(const uint8_t *jeff_bytes,
+                          size_t len,
+                          uint8_t **out_envelope,
+                          size_t *out_len,
+                          char **out_error);
+
+// Release a buffer returned by [`hugr_jeff_convert`].
+//
+// # Safety
+//
+// `buffer` must be null, or a buffer of `len` bytes returned by
+// [`hugr_jeff_convert`] that has not been released yet.
+void hugr_jeff_free_buffer(uint8_t *buffer, size_t len);
+
+// Release an error message returned by [`hugr_jeff_convert`].
+//
+// # Safety
+//
+// `string` must be null, or a message returned by [`hugr_jeff_convert`] that
+// has not been released yet.
+void hugr_jeff_free_string(char *string);
+
+#endif  /* HUGR_JEFF_H */
@@ -0,0 +1,24 @@
+//! Analyses of HUGR programs produced by the _jeff_ translation.
+
+mod ancilla;
+mod borrow;
+mod depth;
+mod diff;
+mod footprint;
+mod free_zero;
+mod gate_set;
+mod interaction;
+mod typecheck;
+
+pub(crate) use ancilla::reaches;
+pub use ancilla::{AncillaRegion, find_ancilla_reuse, peak_qubits_bound};
+pub use borrow::{BorrowViolation, BorrowViolationKind, check_borrow_scopes};
+pub(crate) use depth::module_depths;
+pub use depth::{DepthReport, circuit_depth};
+pub use diff::{FunctionDiff, GateEdit, GateToken, JeffDiff, diff_jeff};
+pub use footprint::{FootprintReport, conversion_footprint};
+pub use free_zero::{UnverifiedFreeZero, check_free_zero};
+pub use gate_set::{GateSet, GateSetViolation, JeffOpRule, check_gate_set};
+pub use interaction::{InteractionGraph, QubitNode, interaction_graph};
+pub(crate) use typecheck::module_type_errors;
+pub use typecheck::{TypeCheckError, TypePosition, check_types};
@@ -0,0 +1,284 @@
+//! Detection of ancilla qubits returned to the |0⟩ state before being freed.
+
+use std::collections::BTreeSet;
+
+use hugr::extension::prelude::qb_t;
+use hugr::ops::OpType;
+use hugr::{HugrView, Node};
+use itertools::Itertools;
+
+use crate::extension::JeffOp;
+use crate::metadata;
+use crate::optype::tket_ops::{self, QuantumOp};
+
+/// A qubit allocated, used, and freed in the |0⟩ state, whose wire can be
+/// reused by a later allocation.
+///
+/// See [`find_ancilla_reuse`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct AncillaRegion {
+    /// The `QAlloc` node allocating the qubit.
+    pub alloc: Node,
+    /// The `QFree` node releasing the qubit.
+    pub free: Node,
+    /// The operations acting on the qubit between its allocation and its
+    /// release, in order.
+    pub ops: Vec<Node>,
+}
+
+/// Find the qubits known to be back in the |0⟩ state when they are freed.
+///
+/// A qubit is recognized when its `QFree` carries the
+/// [`metadata::FREE_ZERO`] mark of a _jeff_ `FreeZero`, or when the gates
+/// applied to it form nested pairs of identical self-inverse gates acting on
+/// the same wires, e.g. `CX · H · H · CX`. The detection is conservative:
+/// the qubit wire is only followed through tket operations and _jeff_
+/// `QGateN` gates.
+pub fn find_ancilla_reuse(hugr: &impl HugrView<Node = Node>) -> Vec<AncillaRegion> {
+    hugr.nodes()
+        .filter(|&node| QuantumOp::of(hugr.get_optype(node)) == Some(QuantumOp::QAlloc))
+        .filter_map(|alloc| {
+            let (ops, free) = qubit_lifetime(hugr, alloc)?;
+            let free_zero = hugr
+                .get_metadata(free, metadata::FREE_ZERO)
+                .and_then(|mark| mark.as_bool())
+                == Some(true);
+            (free_zero || uncomputes(hugr, &ops)).then(|| AncillaRegion {
+                alloc,
+                free,
+                ops: ops.into_iter().map(|(node, _)| node).collect(),
+            })
+        })
+        .collect()
+}
+
+/// An upper bound on the number of qubits live at once in a dataflow region.
+///
+/// Counts the qubit inputs of the region and the `QAlloc` nodes in it and in
+/// its nested regions, less the allocations that must happen after a qubit
+/// of the same region is released by a `QFree` or a destructive measurement,
+/// as they can take its place. Called functions are not taken into account.
+pub fn peak_qubits_bound(hugr: &impl HugrView<Node = Node>, region: Node) -> usize {
+    let inputs = hugr
+        .get_io(region)
+        .and_then(|[input, _]| hugr.signature(input))
+        .map_or(0, |sig| sig.output_types().iter().filter(|&ty| *ty == qb_t()).count());
+    let of_op = |node, ops: &[QuantumOp]| {
+        QuantumOp::of(hugr.get_optype(node)).is_some_and(|op| ops.contains(&op))
+    };
+    let allocs = hugr
+        .descendants(region)
+        .filter(|&node| of_op(node, &[QuantumOp::QAlloc]))
+        .collect_vec();
+
+    // Each release is taken over by at most one later allocation.
+    let mut releases = BTreeSet::new();
+    let mut reused = 0;
+    for &alloc in &allocs {
+        let parent = hugr.get_parent(alloc).unwrap();
+        let release = hugr.children(parent).find(|&node| {
+            of_op(node, &[QuantumOp::QFree, QuantumOp::MeasureDestructive])
+                && !releases.contains(&node)
+                && reaches(hugr, parent, node, alloc)
+        });
+        if let Some(release) = release {
+            releases.insert(release);
+            reused += 1;
+        }
+    }
+    inputs + allocs.len() - reused
+}
+
+/// Whether `to` depends on `from` in a dataflow region, through value or
+/// order edges.
+///
+/// Nodes of nested regions are treated as part of their container, so the
+/// answer is conservative.
+pub(crate) fn reaches(
+    hugr: &impl HugrView<Node = Node>,
+    region: Node,
+    from: Node,
+    to: Node,
+) -> bool {
+    let mut visited = BTreeSet::new();
+    let mut stack = vec![from];
+    while let Some(node) = stack.pop() {
+        if node == to {
+            return true;
+        }
+        if visited.insert(node) {
+            stack.extend(hugr.output_neighbours(node));
+            stack.extend(hugr.get_parent(node).filter(|&parent| parent != region));
+        }
+    }
+    false
+}
+
+/// Follow the qubit allocated by `alloc` up to the `QFree` releasing it.
+///
+/// Returns the operations on the way with the port of the qubit, and the
+/// `QFree` node.
+fn qubit_lifetime(
+    hugr: &impl HugrView<Node = Node>,
+    alloc: Node,
+) -> Option<(Vec<(Node, usize)>, Node)> {
+    let mut ops = Vec::new();
+    let (mut node, mut port) = hugr.single_linked_input(alloc, 0)?;
+    loop {
+        let op = hugr.get_optype(node);
+        if QuantumOp::of(op) == Some(QuantumOp::QFree) {
+            return Some((ops, node));
+        }
+        let is_gate = matches!(op.cast::<JeffOp>(), Some(JeffOp::QGate { .. }));
+        if !tket_ops::is_tket_op(op) && !is_gate {
+            return None;
+        }
+        let signature = op.dataflow_signature()?;
+        if signature.output_types().get(port.index()) != Some(&qb_t()) {
+            return None;
+        }
+        ops.push((node, port.index()));
+        (node, port) = hugr.single_linked_input(node, port.index())?;
+    }
+}
+
+/// Whether the operations on a qubit wire form nested pairs of identical
+/// self-inverse gates, each pair acting on the same wires.
+pub(super) fn uncomputes(hugr: &impl HugrView<Node = Node>, ops: &[(Node, usize)]) -> bool {
+    if ops.len() % 2 != 0 {
+        return false;
+    }
+    let mut cancelled = BTreeSet::new();
+    for i in (0..ops.len() / 2).rev() {
+        let (first, port) = ops[i];
+        let (second, second_port) = ops[ops.len() - 1 - i];
+        let Some(gate) = self_inverse(hugr.get_optype(first)) else {
+            return false;
+        };
+        if port != second_port || self_inverse(hugr.get_optype(second)) != Some(gate) {
+            return false;
+        }
+        let qubits = OpType::from(gate).dataflow_signature().unwrap().output_count();
+        let cancels =
+            (0..qubits).all(|p| next_gate(hugr, first, p, &cancelled) == Some((second, p)));
+        if !cancels {
+            return false;
+        }
+        cancelled.extend([first, second]);
+    }
+    true
+}
+
+/// The operation consuming an output of `node`, skipping the already
+/// cancelled gates.
+fn next_gate(
+    hugr: &impl HugrView<Node = Node>,
+    node: Node,
+    port: usize,
+    cancelled: &BTreeSet<Node>,
+) -> Option<(Node, usize)> {
+    let (mut node, mut port) = hugr.single_linked_input(node, port)?;
+    while cancelled.contains(&node) {
+        (node, port) = hugr.single_linked_input(node, port.index())?;
+    }
+    Some((node, port.index()))
+}
+
+/// The tket gate of an operation, if it is its own inverse.
+fn self_inverse(op: &OpType) -> Option<QuantumOp> {
+    QuantumOp::of(op).filter(|gate| gate.is_self_inverse())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use QuantumOp::{CX, CY, CZ, H, X};
+    use hugr::Hugr;
+    use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr};
+    use hugr::hugr::hugrmut::HugrMut;
+    use hugr::types::Signature;
+    use rstest::rstest;
+
+    /// A DFG applying gates to its qubit input `0` and an ancilla `1`, and
+    /// freeing the ancilla.
+    fn ancilla_dfg(gates: &[(QuantumOp, &[usize])], free_zero: bool) -> Hugr {
+        let mut builder = DFGBuilder::new(Signature::new_endo(vec![qb_t()])).unwrap();
+        let [q] = builder.input_wires_arr();
+        let [a] = builder.add_dataflow_op(QuantumOp::QAlloc, []).unwrap().outputs_arr();
+        let mut qubits = [q, a];
+        for &(gate, wires) in gates {
+            let outputs = builder
+                .add_dataflow_op(gate, wires.iter().map(|&i| qubits[i]))
+                .unwrap()
+                .outputs();
+            for (&i, wire) in wires.iter().zip(outputs) {
+                qubits[i] = wire;
+            }
+        }
+        let free = builder.add_dataflow_op(QuantumOp::QFree, [qubits[1]]).unwrap().node();
+        let mut hugr = builder.finish_hugr_with_outputs([qubits[0]]).unwrap();
+        if free_zero {
+            hugr.set_metadata(free, metadata::FREE_ZERO, true);
+        }
+        hugr
+    }
+
+    #[rstest]
+    #[case::unused(&[], false, true)]
+    #[case::pair(&[(CX, &[0, 1]), (CX, &[0, 1])], false, true)]
+    #[case::nested(
+        &[(H, &[1]), (CZ, &[0, 1]), (CZ, &[0, 1]), (H, &[1])],
+        false,
+        true
+    )]
+    #[case::odd(&[(H, &[1]), (CX, &[0, 1]), (H, &[1])], false, false)]
+    #[case::different(&[(CX, &[0, 1]), (CY, &[0, 1])], false, false)]
+    #[case::swapped(&[(CX, &[1, 0]), (CX, &[0, 1])], false, false)]
+    #[case::control_flipped(
+        &[(CX, &[0, 1]), (X, &[0]), (CX, &[0, 1])],
+        false,
+        false
+    )]
+    #[case::free_zero(&[(CX, &[0, 1])], true, true)]
+    fn ancillas(
+        #[case] gates: &[(QuantumOp, &[usize])],
+        #[case] free_zero: bool,
+        #[case] found: bool,
+    ) {
+        let hugr = ancilla_dfg(gates, free_zero);
+        let ancillas = find_ancilla_reuse(&hugr);
+        assert_eq!(ancillas.len(), found as usize);
+        if let Some(ancilla) = ancillas.first() {
+            assert_eq!(ancilla.ops.len(), gates.iter().filter(|(_, w)| w.contains(&1)).count());
+        }
+        assert_eq!(peak_qubits_bound(&hugr, hugr.entrypoint()), 2);
+    }
+
+    /// An ancilla measured destructively before a second one is allocated,
+    /// with the allocation ordered after the measurement or not.
+    #[rstest]
+    #[case::ordered(true, 2)]
+    #[case::unordered(false, 3)]
+    fn peak_after_release(#[case] ordered: bool, #[case] peak: usize) {
+        let mut builder = DFGBuilder::new(Signature::new_endo(vec![qb_t()])).unwrap();
+        let [q] = builder.input_wires_arr();
+        let a = builder.add_dataflow_op(QuantumOp::QAlloc, []).unwrap();
+        let measure = builder
+            .add_dataflow_op(QuantumOp::MeasureDestructive, a.outputs())
+            .unwrap();
+        let b = builder.add_dataflow_op(QuantumOp::QAlloc, []).unwrap();
+        if ordered {
+            builder.set_order(&measure, &b);
+        }
+        let [q, b] = builder
+            .add_dataflow_op(QuantumOp::CX, [q, b.out_wire(0)])
+            .unwrap()
+            .outputs_arr();
+        builder.add_dataflow_op(QuantumOp::QFree, [b]).unwrap();
+        let hugr = builder.finish_hugr_with_outputs([q]).unwrap();
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        assert_eq!(peak_qubits_bound(&hugr, hugr.entrypoint()), peak);
+    }
+}
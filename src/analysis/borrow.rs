@@ -0,0 +1,320 @@
+//! Scopes of the qubits borrowed from the runtime pool.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use itertools::Itertools;
+use jeff::Jeff;
+use jeff::reader::optype::{ControlFlowOp, OpType as JeffOpType};
+use jeff::reader::value::ValueId;
+use jeff::reader::{Function, FunctionId, Region};
+use jeff::types::Type as JeffType;
+use serde::Serialize;
+
+use crate::extension::JeffOp;
+use crate::{BuildContext, JeffToHugrError};
+
+/// A borrowed qubit leaving the region it was borrowed in.
+///
+/// See [`check_borrow_scopes`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+pub struct BorrowViolation {
+    /// The name of the function borrowing the qubit.
+    pub function: String,
+    /// The position of the borrowing operation, counting from the start of
+    /// its function.
+    pub borrow: usize,
+    /// How the qubit leaves its region.
+    pub kind: BorrowViolationKind,
+}
+
+/// The ways a borrowed qubit can leave the region it was borrowed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum BorrowViolationKind {
+    /// The qubit is returned in a nested region, such as a branch of a
+    /// switch, rather than in its own region.
+    ReturnedElsewhere {
+        /// The position of the returning operation.
+        op_index: usize,
+    },
+    /// The qubit is freed, measured destructively, or otherwise consumed by
+    /// an operation other than a return.
+    Consumed {
+        /// The position of the consuming operation.
+        op_index: usize,
+    },
+    /// The qubit is an output of its region.
+    Leaked,
+}
+
+impl fmt::Display for BorrowViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Qubit borrowed by operation {} in function '{}'",
+            self.borrow, self.function
+        )?;
+        match self.kind {
+            BorrowViolationKind::ReturnedElsewhere { op_index } => {
+                write!(f, " is returned by operation {op_index} in another region")
+            }
+            BorrowViolationKind::Consumed { op_index } => {
+                write!(f, " is consumed by operation {op_index} without being returned")
+            }
+            BorrowViolationKind::Leaked => write!(f, " leaves its region without being returned"),
+        }
+    }
+}
+
+/// Check that the qubits borrowed in a _jeff_ program are returned in the
+/// region they were borrowed in.
+///
+/// Qubits are borrowed and returned by calls to the `qubit_borrow` and
+/// `qubit_return` intrinsics, see [`JeffOp::qubit_borrow`]. A borrowed qubit
+/// may be used by gates and passed through calls and control flow, as long
+/// as it comes back to its region to be returned. It must not be returned
+/// inside a nested region, freed, measured destructively, or be an output
+/// of its region.
+///
+/// The operations are identified by their position from the start of their
+/// function, counting the operations of the nested regions, as in
+/// [`crate::JeffHugrMap::node_origin`].
+///
+/// # Errors
+///
+/// - [`JeffToHugrError::MalformedJeffFile`] if the program cannot be read.
+pub fn check_borrow_scopes(jeff: &Jeff) -> Result<Vec<BorrowViolation>, JeffToHugrError> {
+    let module = jeff.module();
+    let intrinsics = BuildContext::intrinsics(&module)?;
+    let mut violations = Vec::new();
+    for func in module.functions() {
+        let Function::Definition(def) = &func else {
+            continue;
+        };
+        let mut scopes = BorrowScopes {
+            intrinsics: &intrinsics,
+            function: func.name(),
+            op_count: 0,
+            region_count: 0,
+            violations: &mut violations,
+        };
+        let body = def.body();
+        let sources = vec![None; body.sources().count()];
+        scopes.region(&body, sources)?;
+    }
+    Ok(violations)
+}
+
+/// A borrowed qubit, by the operation borrowing it and its region.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Borrowed {
+    op_index: usize,
+    region: usize,
+}
+
+/// State of a borrow scope check over a function.
+struct BorrowScopes<'a> {
+    /// The intrinsics of the module, see [`BuildContext::intrinsics`].
+    intrinsics: &'a BTreeMap<FunctionId, JeffOp>,
+    /// The name of the checked function.
+    function: &'a str,
+    /// The number of operations visited in the function.
+    op_count: usize,
+    /// The number of regions visited in the function, identifying them.
+    region_count: usize,
+    violations: &'a mut Vec<BorrowViolation>,
+}
+
+impl BorrowScopes<'_> {
+    /// Check a region whose sources carry the given borrowed qubits.
+    ///
+    /// Returns the borrowed qubits carried by the region targets, except
+    /// those borrowed in the region itself, which are reported as leaked.
+    fn region(
+        &mut self,
+        region: &Region<'_>,
+        sources: Vec<Option<Borrowed>>,
+    ) -> Result<Vec<Option<Borrowed>>, JeffToHugrError> {
+        let region_id = self.region_count;
+        self.region_count += 1;
+
+        let mut values: BTreeMap<ValueId, Borrowed> = BTreeMap::new();
+        for (source, borrowed) in region.sources().zip(sources) {
+            if let Some(borrowed) = borrowed {
+                values.insert(source?.id(), borrowed);
+            }
+        }
+
+        for op in region.operations() {
+            let op_index = self.op_count;
+            self.op_count += 1;
+            let inputs = op
+                .inputs()
+                .map(|value| Ok(values.remove(&value?.id())))
+                .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+            let output_types = op
+                .outputs()
+                .map(|value| Ok(value?.ty()))
+                .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+
+            let outputs = match op.op_type() {
+                JeffOpType::ControlFlowOp(cf_op) => self.control_flow(&cf_op, inputs)?,
+                JeffOpType::FuncOp(func_op) => {
+                    match self.intrinsics.get(&(func_op.func_idx as FunctionId)) {
+                        Some(JeffOp::QubitBorrow) => vec![Some(Borrowed {
+                            op_index,
+                            region: region_id,
+                        })],
+                        Some(JeffOp::QubitReturn) => {
+                            for borrowed in inputs.into_iter().flatten() {
+                                if borrowed.region != region_id {
+                                    let kind = BorrowViolationKind::ReturnedElsewhere { op_index };
+                                    self.report(borrowed, kind);
+                                }
+                            }
+                            vec![]
+                        }
+                        _ => self.thread(inputs, &output_types, op_index),
+                    }
+                }
+                _ => self.thread(inputs, &output_types, op_index),
+            };
+            for (output, borrowed) in op.outputs().zip(outputs) {
+                if let Some(borrowed) = borrowed {
+                    values.insert(output?.id(), borrowed);
+                }
+            }
+        }
+
+        region
+            .targets()
+            .map(|value| {
+                let borrowed = values.remove(&value?.id());
+                Ok(match borrowed {
+                    Some(borrowed) if borrowed.region == region_id => {
+                        self.report(borrowed, BorrowViolationKind::Leaked);
+                        None
+                    }
+                    borrowed => borrowed,
+                })
+            })
+            .collect()
+    }
+
+    /// Thread the borrowed qubits through an operation, from each input to
+    /// the qubit output at the same position.
+    ///
+    /// The borrowed qubits without such an output are reported as consumed.
+    fn thread(
+        &mut self,
+        inputs: Vec<Option<Borrowed>>,
+        output_types: &[JeffType],
+        op_index: usize,
+    ) -> Vec<Option<Borrowed>> {
+        let mut outputs = vec![None; output_types.len()];
+        for (position, borrowed) in inputs.into_iter().enumerate() {
+            let Some(borrowed) = borrowed else {
+                continue;
+            };
+            match output_types.get(position) {
+                Some(JeffType::Qubit) => outputs[position] = Some(borrowed),
+                _ => self.report(borrowed, BorrowViolationKind::Consumed { op_index }),
+            }
+        }
+        outputs
+    }
+
+    /// Check the regions of a control flow operation.
+    ///
+    /// Returns the borrowed qubits of its outputs, which keep the qubit of
+    /// an input when every branch moves it to the same position.
+    fn control_flow(
+        &mut self,
+        cf_op: &ControlFlowOp<'_>,
+        inputs: Vec<Option<Borrowed>>,
+    ) -> Result<Vec<Option<Borrowed>>, JeffToHugrError> {
+        match cf_op {
+            ControlFlowOp::Switch(switch_op) => {
+                let args = inputs.get(1..).unwrap_or_default().to_vec();
+                let mut branches = (0..switch_op.branch_count())
+                    .map(|idx| switch_op.branch(idx))
+                    .collect_vec();
+                branches.extend(switch_op.default_branch());
+                let mut outputs: Option<Vec<Option<Borrowed>>> = None;
+                for branch in &branches {
+                    let targets = self.region(branch, args.clone())?;
+                    outputs = Some(match outputs {
+                        None => targets,
+                        Some(outputs) => agree(outputs, &targets),
+                    });
+                }
+                Ok(outputs.unwrap_or_default())
+            }
+            ControlFlowOp::DoWhile { body, condition }
+            | ControlFlowOp::While { body, condition } => {
+                let targets = self.region(body, inputs.clone())?;
+                self.region(condition, targets.clone())?;
+                Ok(agree(targets, &inputs))
+            }
+            ControlFlowOp::For { region } => {
+                let args = inputs.get(3..).unwrap_or_default();
+                // The loop counter carries no qubits.
+                let sources = std::iter::once(None).chain(args.iter().copied()).collect();
+                let targets = self.region(region, sources)?;
+                Ok(agree(targets, args))
+            }
+        }
+    }
+
+    /// Record a violation of the current function.
+    fn report(&mut self, borrowed: Borrowed, kind: BorrowViolationKind) {
+        self.violations.push(BorrowViolation {
+            function: self.function.to_string(),
+            borrow: borrowed.op_index,
+            kind,
+        });
+    }
+}
+
+/// The borrowed qubits of `values` that are also carried by `others` in the
+/// same position.
+fn agree(values: Vec<Option<Borrowed>>, others: &[Option<Borrowed>]) -> Vec<Option<Borrowed>> {
+    values
+        .into_iter()
+        .enumerate()
+        .map(|(idx, value)| value.filter(|value| others.get(idx) == Some(&Some(*value))))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{borrowed_qubit, entangled_calls};
+    use rstest::rstest;
+
+    #[rstest]
+    fn no_borrows(entangled_calls: Jeff<'static>) {
+        assert_eq!(check_borrow_scopes(&entangled_calls).unwrap(), []);
+    }
+
+    #[rstest]
+    fn returned_in_branches(borrowed_qubit: Jeff<'static>) {
+        let violations = check_borrow_scopes(&borrowed_qubit).unwrap();
+        // `scoped` returns its qubit in its own region, `escaping` in the
+        // branches of a switch.
+        let escaping = |op_index| BorrowViolation {
+            function: "escaping".to_string(),
+            borrow: 0,
+            kind: BorrowViolationKind::ReturnedElsewhere { op_index },
+        };
+        assert_eq!(violations, [escaping(2), escaping(4)]);
+        assert_eq!(
+            violations[0].to_string(),
+            "Qubit borrowed by operation 0 in function 'escaping' is returned by operation 2 in \
+             another region"
+        );
+    }
+}
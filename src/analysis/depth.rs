@@ -0,0 +1,472 @@
+//! Circuit depth of the _jeff_ functions, computed without translating them.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::{fmt, mem};
+
+use itertools::Itertools;
+use jeff::Jeff;
+use jeff::reader::optype::{self as jeff_optype, ControlFlowOp, OpType as JeffOpType};
+use jeff::reader::value::ValueId;
+use jeff::reader::{Function, FunctionId, Module, Operation, Region};
+use serde::Serialize;
+
+use crate::JeffToHugrError;
+
+/// The depth of the quantum operations of a _jeff_ function.
+///
+/// See [`circuit_depth`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+pub struct DepthReport {
+    /// Length of the longest chain of dependent gates, measurements and
+    /// resets.
+    pub depth: usize,
+    /// Length of the longest chain of dependent gates acting on two qubits
+    /// or more, controls included.
+    pub depth_2q: usize,
+    /// Whether the function runs quantum operations in a loop without a
+    /// statically known trip count, or in a recursive call.
+    ///
+    /// The body of such loops is counted once, and recursive calls are not
+    /// counted, so the depths are lower bounds.
+    pub unbounded: bool,
+}
+
+impl DepthReport {
+    /// The componentwise maximum of two depths.
+    fn join(self, other: Self) -> Self {
+        Self {
+            depth: self.depth.max(other.depth),
+            depth_2q: self.depth_2q.max(other.depth_2q),
+            unbounded: self.unbounded || other.unbounded,
+        }
+    }
+
+    /// The depth of `other` following `self`.
+    fn then(self, other: Self) -> Self {
+        Self {
+            depth: self.depth + other.depth,
+            depth_2q: self.depth_2q + other.depth_2q,
+            unbounded: self.unbounded || other.unbounded,
+        }
+    }
+
+    /// The depth of `count` repetitions of `self`.
+    fn repeat(self, count: u64) -> Self {
+        let count = usize::try_from(count).unwrap_or(usize::MAX);
+        Self {
+            depth: self.depth.saturating_mul(count),
+            depth_2q: self.depth_2q.saturating_mul(count),
+            unbounded: self.unbounded,
+        }
+    }
+}
+
+impl fmt::Display for DepthReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<24} {}", "Depth", self.depth)?;
+        writeln!(f, "{:<24} {}", "Two-qubit depth", self.depth_2q)?;
+        write!(f, "{:<24} {}", "Unbounded", self.unbounded)
+    }
+}
+
+/// Compute the circuit depth of a function of a _jeff_ program.
+///
+/// The depth is the longest path through the quantum operations of the
+/// function, following the values between the operations. Calls add the
+/// depth of the callee. Control flow operations are counted conservatively:
+/// a switch adds the depth of its deepest branch, a `for` loop the depth of
+/// its body times its trip count when the loop bounds are constants, and
+/// other loops the depth of a single iteration, flagging the report as
+/// [`DepthReport::unbounded`].
+///
+/// # Errors
+///
+/// - [`JeffToHugrError::UnknownFunction`] if the first module of the program
+///   has no function named `function`.
+/// - [`JeffToHugrError::MalformedJeffFile`] if the program cannot be read.
+///
+/// # Examples
+///
+/// ```
+/// use hugr_jeff::analysis::circuit_depth;
+/// use hugr_jeff::doctest_support::{QUBITS_FUNCTION, qubits};
+///
+/// let report = circuit_depth(&qubits(), QUBITS_FUNCTION).unwrap();
+/// assert_eq!(report.depth, 3);
+/// // The two `CX` gates share a qubit.
+/// assert_eq!(report.depth_2q, 2);
+/// assert!(!report.unbounded);
+/// ```
+pub fn circuit_depth(jeff: &Jeff, function: &str) -> Result<DepthReport, JeffToHugrError> {
+    let module = jeff.module();
+    let functions = module.functions().collect_vec();
+    let Some(func_id) = functions.iter().position(|func| func.name() == function) else {
+        return Err(JeffToHugrError::UnknownFunction {
+            function: function.to_string(),
+        });
+    };
+    Depths::new(&functions).function(func_id as FunctionId)
+}
+
+/// The circuit depth of each function of a module, indexed by
+/// [`FunctionId`], or `None` for the declared functions.
+pub(crate) fn module_depths(
+    module: &Module<'_>,
+) -> Result<Vec<Option<DepthReport>>, JeffToHugrError> {
+    let functions = module.functions().collect_vec();
+    let mut depths = Depths::new(&functions);
+    functions
+        .iter()
+        .enumerate()
+        .map(|(func_id, func)| match func {
+            Function::Definition(_) => depths.function(func_id as FunctionId).map(Some),
+            Function::Declaration(_) => Ok(None),
+        })
+        .collect()
+}
+
+/// State of a circuit depth computation.
+struct Depths<'a> {
+    /// The functions of the module, indexed by [`FunctionId`].
+    functions: &'a [Function<'a>],
+    /// The depths of the functions computed so far.
+    reports: BTreeMap<FunctionId, DepthReport>,
+    /// The functions whose depth is being computed, to detect recursion.
+    in_progress: BTreeSet<FunctionId>,
+    /// The integer constants of the current function, as signed values.
+    constants: BTreeMap<ValueId, i64>,
+}
+
+/// The values used and defined by an operation, with the depth it adds.
+struct OpDepth<V> {
+    inputs: Vec<V>,
+    outputs: Vec<V>,
+    cost: DepthReport,
+}
+
+impl<'a> Depths<'a> {
+    fn new(functions: &'a [Function<'a>]) -> Self {
+        Self {
+            functions,
+            reports: BTreeMap::new(),
+            in_progress: BTreeSet::new(),
+            constants: BTreeMap::new(),
+        }
+    }
+
+    /// The depth of a function. Declared functions have no depth.
+    fn function(&mut self, func_id: FunctionId) -> Result<DepthReport, JeffToHugrError> {
+        if let Some(&report) = self.reports.get(&func_id) {
+            return Ok(report);
+        }
+        if !self.in_progress.insert(func_id) {
+            // A recursive call, of unknown depth.
+            return Ok(DepthReport {
+                unbounded: true,
+                ..DepthReport::default()
+            });
+        }
+        let functions = self.functions;
+        let report = match functions.get(func_id as usize) {
+            Some(Function::Definition(def)) => {
+                // Value ids are only unique within a function.
+                let caller_constants = mem::take(&mut self.constants);
+                let report = self.region(&def.body());
+                self.constants = caller_constants;
+                report?
+            }
+            _ => DepthReport::default(),
+        };
+        self.in_progress.remove(&func_id);
+        self.reports.insert(func_id, report);
+        Ok(report)
+    }
+
+    /// The depth of a region, whose sources are at depth zero.
+    fn region(&mut self, region: &Region<'_>) -> Result<DepthReport, JeffToHugrError> {
+        let ops = region.operations().collect_vec();
+        for op in &ops {
+            if let Some(value) = int_constant(&op.op_type())
+                && let Some(output) = op.output(0)
+            {
+                self.constants.insert(output?.id(), value);
+            }
+        }
+        let ops = ops
+            .iter()
+            .map(|op| {
+                let inputs = op
+                    .inputs()
+                    .map(|value| Ok(value?.id()))
+                    .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+                let outputs = op
+                    .outputs()
+                    .map(|value| Ok(value?.id()))
+                    .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+                let cost = self.operation(op, &inputs)?;
+                Ok(OpDepth {
+                    inputs,
+                    outputs,
+                    cost,
+                })
+            })
+            .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+        Ok(longest_path(&ops))
+    }
+
+    /// The depth added by an operation, on top of the depth of its inputs.
+    fn operation(
+        &mut self,
+        op: &Operation<'_>,
+        inputs: &[ValueId],
+    ) -> Result<DepthReport, JeffToHugrError> {
+        let layer = |two_qubit: bool| DepthReport {
+            depth: 1,
+            depth_2q: two_qubit as usize,
+            unbounded: false,
+        };
+        let cost = match op.op_type() {
+            JeffOpType::QubitOp(qubit_op) => match qubit_op {
+                jeff_optype::QubitOp::Measure
+                | jeff_optype::QubitOp::MeasureNd
+                | jeff_optype::QubitOp::Reset => layer(false),
+                jeff_optype::QubitOp::Gate(gate) => match gate.num_qubits() {
+                    0 => DepthReport::default(),
+                    qubits => layer(qubits >= 2),
+                },
+                _ => DepthReport::default(),
+            },
+            JeffOpType::FuncOp(func_op) => self.function(func_op.func_idx as FunctionId)?,
+            JeffOpType::ControlFlowOp(cf_op) => self.control_flow(&cf_op, inputs)?,
+            _ => DepthReport::default(),
+        };
+        Ok(cost)
+    }
+
+    /// The depth added by a control flow operation.
+    fn control_flow(
+        &mut self,
+        cf_op: &ControlFlowOp<'_>,
+        inputs: &[ValueId],
+    ) -> Result<DepthReport, JeffToHugrError> {
+        let (span, iterations) = match cf_op {
+            ControlFlowOp::Switch(switch_op) => {
+                let mut span = DepthReport::default();
+                for idx in 0..switch_op.branch_count() {
+                    span = span.join(self.region(&switch_op.branch(idx))?);
+                }
+                if let Some(default_branch) = switch_op.default_branch() {
+                    span = span.join(self.region(&default_branch)?);
+                }
+                (span, Some(1))
+            }
+            ControlFlowOp::DoWhile { body, condition }
+            | ControlFlowOp::While { body, condition } => {
+                let span = self.region(body)?.then(self.region(condition)?);
+                (span, None)
+            }
+            ControlFlowOp::For { region } => {
+                let bounds = inputs
+                    .iter()
+                    .take(3)
+                    .map(|value| self.constants.get(value).copied())
+                    .collect::<Option<Vec<_>>>();
+                let iterations = match bounds.as_deref() {
+                    Some(&[start, stop, step]) => trip_count(start, stop, step),
+                    _ => None,
+                };
+                (self.region(region)?, iterations)
+            }
+        };
+        Ok(iterate(span, iterations))
+    }
+}
+
+/// The depth of `iterations` runs of a region, or of a single run flagged
+/// as unbounded if the number of runs is unknown and the region has quantum
+/// operations.
+fn iterate(span: DepthReport, iterations: Option<u64>) -> DepthReport {
+    match iterations {
+        Some(count) => span.repeat(count),
+        None => DepthReport {
+            unbounded: span.unbounded || span.depth > 0,
+            ..span
+        },
+    }
+}
+
+/// The longest path through the operations of a region, as the maximum
+/// depth of their outputs.
+///
+/// _jeff_ regions may list an operation before the producers of its inputs,
+/// so the operations are visited in dependency order. Operations in a
+/// dependency cycle are not visited.
+fn longest_path<V: Ord + Copy>(ops: &[OpDepth<V>]) -> DepthReport {
+    let producers: BTreeMap<V, usize> = ops
+        .iter()
+        .enumerate()
+        .flat_map(|(idx, op)| op.outputs.iter().map(move |&value| (value, idx)))
+        .collect();
+    let mut consumers = vec![Vec::new(); ops.len()];
+    let mut pending = vec![0usize; ops.len()];
+    for (idx, op) in ops.iter().enumerate() {
+        for value in &op.inputs {
+            if let Some(&producer) = producers.get(value) {
+                consumers[producer].push(idx);
+                pending[idx] += 1;
+            }
+        }
+    }
+
+    let mut ready: VecDeque<usize> = (0..ops.len()).filter(|&idx| pending[idx] == 0).collect();
+    let mut depths: BTreeMap<V, DepthReport> = BTreeMap::new();
+    let mut report = DepthReport::default();
+    while let Some(idx) = ready.pop_front() {
+        let op = &ops[idx];
+        let start = op
+            .inputs
+            .iter()
+            .filter_map(|value| depths.get(value))
+            .fold(DepthReport::default(), |acc, &depth| acc.join(depth));
+        let end = start.then(op.cost);
+        report = report.join(end);
+        for &value in &op.outputs {
+            depths.insert(value, end);
+        }
+        for &consumer in &consumers[idx] {
+            pending[consumer] -= 1;
+            if pending[consumer] == 0 {
+                ready.push_back(consumer);
+            }
+        }
+    }
+    report
+}
+
+/// The number of iterations of a `for` loop, which runs while the counter
+/// is lower than `stop` in signed comparison.
+///
+/// Returns `None` if the loop does not terminate.
+pub(super) fn trip_count(start: i64, stop: i64, step: i64) -> Option<u64> {
+    if start >= stop {
+        return Some(0);
+    }
+    if step <= 0 {
+        return None;
+    }
+    Some(stop.abs_diff(start).div_ceil(step as u64))
+}
+
+/// Returns the value of an integer constant operation, interpreted as a
+/// signed integer of its width.
+pub(super) fn int_constant(op_type: &JeffOpType<'_>) -> Option<i64> {
+    match op_type {
+        JeffOpType::IntOp(jeff_optype::IntOp::Const8(n)) => Some(*n as i8 as i64),
+        JeffOpType::IntOp(jeff_optype::IntOp::Const16(n)) => Some(*n as i16 as i64),
+        JeffOpType::IntOp(jeff_optype::IntOp::Const32(n)) => Some(*n as i32 as i64),
+        JeffOpType::IntOp(jeff_optype::IntOp::Const64(n)) => Some(*n as i64),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{catalyst_tket_opt, entangled_qs, qubits};
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::qubits(qubits(), "Circuit", 3, 2)]
+    #[case::entangled_qs(entangled_qs(), "qarray", 6, 4)]
+    #[case::static_for_loop(catalyst_tket_opt(), "optimize_me", 9, 6)]
+    fn exact_depths(
+        #[case] jeff: Jeff<'static>,
+        #[case] function: &str,
+        #[case] depth: usize,
+        #[case] depth_2q: usize,
+    ) {
+        let report = circuit_depth(&jeff, function).unwrap();
+        assert_eq!(
+            report,
+            DepthReport {
+                depth,
+                depth_2q,
+                unbounded: false
+            }
+        );
+    }
+
+    #[rstest]
+    fn unknown_function(qubits: Jeff<'static>) {
+        let err = circuit_depth(&qubits, "missing").unwrap_err();
+        assert!(matches!(
+            err,
+            JeffToHugrError::UnknownFunction { function } if function == "missing"
+        ));
+    }
+
+    #[rstest]
+    #[case::ascending(0, 3, 1, Some(3))]
+    #[case::strided(1, 8, 3, Some(3))]
+    #[case::negative_start(-2, 2, 2, Some(2))]
+    #[case::empty(3, 3, 1, Some(0))]
+    #[case::zero_step(0, 3, 0, None)]
+    #[case::descending(0, 3, -1, None)]
+    fn trip_counts(
+        #[case] start: i64,
+        #[case] stop: i64,
+        #[case] step: i64,
+        #[case] expected: Option<u64>,
+    ) {
+        assert_eq!(trip_count(start, stop, step), expected);
+    }
+
+    const GATE_2Q: DepthReport = DepthReport {
+        depth: 1,
+        depth_2q: 1,
+        unbounded: false,
+    };
+
+    #[rstest]
+    fn loop_iterations() {
+        let body = longest_path(&[OpDepth {
+            inputs: vec![2u32],
+            outputs: vec![3],
+            cost: GATE_2Q,
+        }]);
+        let static_loop = iterate(body, Some(3));
+        assert_eq!((static_loop.depth, static_loop.depth_2q), (3, 3));
+        assert!(!static_loop.unbounded);
+
+        // A loop with unknown bounds is counted once.
+        let unknown_loop = iterate(body, None);
+        assert_eq!((unknown_loop.depth, unknown_loop.depth_2q), (1, 1));
+        assert!(unknown_loop.unbounded);
+        // Loops without quantum operations do not change the depth.
+        assert!(!iterate(DepthReport::default(), None).unbounded);
+    }
+
+    #[rstest]
+    fn unordered_region() {
+        let gate_1q = DepthReport {
+            depth_2q: 0,
+            ..GATE_2Q
+        };
+        // The second gate is listed before the first one.
+        let ops = [
+            OpDepth {
+                inputs: vec![1u32, 2],
+                outputs: vec![3, 4],
+                cost: GATE_2Q,
+            },
+            OpDepth {
+                inputs: vec![0],
+                outputs: vec![1],
+                cost: gate_1q,
+            },
+        ];
+        let report = longest_path(&ops);
+        assert_eq!((report.depth, report.depth_2q), (2, 1));
+    }
+}
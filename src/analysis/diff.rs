@@ -0,0 +1,541 @@
+//! Operation-level differences between two _jeff_ programs.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use itertools::Itertools;
+use jeff::Jeff;
+use jeff::reader::optype::{self as jeff_optype, ControlFlowOp, OpType as JeffOpType};
+use jeff::reader::value::ValueId;
+use jeff::reader::{Function, Module, Operation, Region};
+use jeff::types::Type as JeffType;
+use serde::Serialize;
+
+use crate::JeffToHugrError;
+use crate::optype::{WellKnownTranslation, classify_well_known};
+
+/// Number of decimal digits the constant gate parameters are rounded to.
+const PARAM_DIGITS: usize = 6;
+
+/// The differences between the first modules of two _jeff_ programs.
+///
+/// See [`diff_jeff`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+pub struct JeffDiff {
+    /// Functions only present in the second program.
+    pub added_functions: Vec<String>,
+    /// Functions only present in the first program.
+    pub removed_functions: Vec<String>,
+    /// Functions present in both programs that changed, by name.
+    pub changed_functions: BTreeMap<String, FunctionDiff>,
+}
+
+impl JeffDiff {
+    /// Returns `true` if the programs have no differences.
+    pub fn is_empty(&self) -> bool {
+        self.added_functions.is_empty()
+            && self.removed_functions.is_empty()
+            && self.changed_functions.is_empty()
+    }
+}
+
+impl fmt::Display for JeffDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No differences");
+        }
+        for name in &self.added_functions {
+            writeln!(f, "Added function '{name}'")?;
+        }
+        for name in &self.removed_functions {
+            writeln!(f, "Removed function '{name}'")?;
+        }
+        for (name, diff) in &self.changed_functions {
+            writeln!(f, "Changed function '{name}'")?;
+            write!(f, "{diff}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The differences between two versions of a function.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+pub struct FunctionDiff {
+    /// The signatures before and after, if they differ.
+    pub signature: Option<(String, String)>,
+    /// The number of operations before and after, for the operation kinds
+    /// whose count changed.
+    pub op_counts: BTreeMap<String, (usize, usize)>,
+    /// The gates removed from and added to the gate sequence of the
+    /// function, in sequence order.
+    pub gate_edits: Vec<GateEdit>,
+}
+
+impl FunctionDiff {
+    /// Returns `true` if the function did not change.
+    pub fn is_empty(&self) -> bool {
+        self.signature.is_none() && self.op_counts.is_empty() && self.gate_edits.is_empty()
+    }
+}
+
+impl fmt::Display for FunctionDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some((before, after)) = &self.signature {
+            writeln!(f, "  Signature: {before} => {after}")?;
+        }
+        for (kind, (before, after)) in &self.op_counts {
+            writeln!(f, "  {kind:<22} {before} => {after}")?;
+        }
+        for edit in &self.gate_edits {
+            writeln!(f, "  {edit}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A change in the gate sequence of a function.
+///
+/// The positions count the gates of the function, including those in nested
+/// regions, in the order they are read.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "edit", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum GateEdit {
+    /// A gate of the first program that is not in the second one.
+    Removed {
+        /// The position of the gate in the first program.
+        position: usize,
+        /// The removed gate.
+        gate: GateToken,
+    },
+    /// A gate of the second program that is not in the first one.
+    Added {
+        /// The position of the gate in the second program.
+        position: usize,
+        /// The added gate.
+        gate: GateToken,
+    },
+}
+
+impl fmt::Display for GateEdit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GateEdit::Removed { position, gate } => write!(f, "- #{position} {gate}"),
+            GateEdit::Added { position, gate } => write!(f, "+ #{position} {gate}"),
+        }
+    }
+}
+
+/// A gate, normalized for comparison.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[non_exhaustive]
+pub struct GateToken {
+    /// The name of the gate.
+    ///
+    /// Well-known gates translated to a `tket.quantum` operation take its
+    /// name, with the controls included. Other gates are named after their
+    /// _jeff_ name, with their modifiers.
+    pub name: String,
+    /// The number of qubits the gate acts on, controls included.
+    pub arity: usize,
+    /// The float parameters, rounded to 6 decimals, or `?` if they are not
+    /// constants.
+    pub params: Vec<String>,
+}
+
+impl fmt::Display for GateToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}]", self.name, self.arity)?;
+        if !self.params.is_empty() {
+            write!(f, "({})", self.params.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Compare the first modules of two _jeff_ programs.
+///
+/// Functions are matched by name. For each function present in both
+/// programs, the diff reports the signature change, the change in the number
+/// of operations of each kind, and the gates added and removed according to
+/// a longest common subsequence of the gate sequences.
+pub fn diff_jeff(a: &Jeff, b: &Jeff) -> Result<JeffDiff, JeffToHugrError> {
+    let before = summarize_module(&a.module())?;
+    let after = summarize_module(&b.module())?;
+    Ok(diff_summaries(&before, &after))
+}
+
+/// The comparable contents of a function.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct FunctionSummary {
+    /// The signature of the function.
+    signature: String,
+    /// The number of operations of each kind, see [`op_kind`].
+    op_counts: BTreeMap<String, usize>,
+    /// The gates of the function, in order.
+    gates: Vec<GateToken>,
+}
+
+/// Summarize the functions of a module, by name.
+///
+/// Only the first function with a given name is kept.
+fn summarize_module(
+    module: &Module<'_>,
+) -> Result<BTreeMap<String, FunctionSummary>, JeffToHugrError> {
+    let mut summaries = BTreeMap::new();
+    for func in module.functions() {
+        if summaries.contains_key(func.name()) {
+            continue;
+        }
+        let types = |types: Vec<String>| types.join(", ");
+        let inputs = func
+            .input_types()
+            .map(|port| Ok(port?.ty().to_string()))
+            .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+        let outputs = func
+            .output_types()
+            .map(|port| Ok(port?.ty().to_string()))
+            .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+        let mut summary = FunctionSummary {
+            signature: format!("({}) -> ({})", types(inputs), types(outputs)),
+            ..Default::default()
+        };
+        if let Function::Definition(def) = &func {
+            summary.region(&def.body())?;
+        }
+        summaries.insert(func.name().to_string(), summary);
+    }
+    Ok(summaries)
+}
+
+impl FunctionSummary {
+    /// Add the operations of a region, and of its nested regions.
+    fn region(&mut self, region: &Region<'_>) -> Result<(), JeffToHugrError> {
+        let mut floats = BTreeMap::new();
+        for op in region.operations() {
+            *self.op_counts.entry(op_kind(&op)).or_default() += 1;
+            match op.op_type() {
+                JeffOpType::FloatOp(jeff_optype::FloatOp::Const32(f)) => {
+                    record_float(&mut floats, &op, f as f64)
+                }
+                JeffOpType::FloatOp(jeff_optype::FloatOp::Const64(f)) => {
+                    record_float(&mut floats, &op, f)
+                }
+                JeffOpType::QubitOp(jeff_optype::QubitOp::Gate(gate)) => {
+                    self.gates.push(gate_token(gate, &op, &floats)?);
+                }
+                JeffOpType::ControlFlowOp(cf_op) => match cf_op {
+                    ControlFlowOp::Switch(switch_op) => {
+                        for idx in 0..switch_op.branch_count() {
+                            self.region(&switch_op.branch(idx))?;
+                        }
+                        if let Some(default_branch) = switch_op.default_branch() {
+                            self.region(&default_branch)?;
+                        }
+                    }
+                    ControlFlowOp::DoWhile { body, condition }
+                    | ControlFlowOp::While { body, condition } => {
+                        self.region(&condition)?;
+                        self.region(&body)?;
+                    }
+                    ControlFlowOp::For { region } => self.region(&region)?,
+                },
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Record the value of a float constant.
+fn record_float(floats: &mut BTreeMap<ValueId, f64>, op: &Operation<'_>, value: f64) {
+    if let Some(Ok(output)) = op.output(0) {
+        floats.insert(output.id(), value);
+    }
+}
+
+/// The kind of an operation, as `<category>.<variant>` of its _jeff_
+/// operation type.
+///
+/// Gates are counted by their [`GateToken::name`], as `Gate.<name>`.
+fn op_kind(op: &Operation<'_>) -> String {
+    let kind = match op.op_type() {
+        JeffOpType::QubitOp(jeff_optype::QubitOp::Gate(gate)) => {
+            return format!("Gate.{}", gate_name(gate.normalize()));
+        }
+        JeffOpType::FuncOp(_) => "FuncOp.Call",
+        JeffOpType::ControlFlowOp(cf_op) => match cf_op {
+            ControlFlowOp::Switch(_) => "ControlFlowOp.Switch",
+            ControlFlowOp::For { .. } => "ControlFlowOp.For",
+            ControlFlowOp::While { .. } => "ControlFlowOp.While",
+            ControlFlowOp::DoWhile { .. } => "ControlFlowOp.DoWhile",
+        },
+        op_type => {
+            // The category and variant names, without their payload.
+            return format!("{op_type:?}")
+                .split(|c: char| !c.is_ascii_alphanumeric())
+                .filter(|name| !name.is_empty())
+                .take(2)
+                .join(".");
+        }
+    };
+    kind.to_string()
+}
+
+/// The name of a normalized gate, see [`GateToken::name`].
+fn gate_name(gate: jeff_optype::GateOp<'_>) -> String {
+    let name = match gate.gate_type {
+        jeff_optype::GateOpType::WellKnown(well_known) => {
+            match classify_well_known(well_known, gate) {
+                WellKnownTranslation::Tket(tket_op) | WellKnownTranslation::Rotation(tket_op) => {
+                    return format!("{tket_op:?}");
+                }
+                _ => well_known.to_string(),
+            }
+        }
+        jeff_optype::GateOpType::PauliProdRotation { pauli_string } => pauli_string.to_string(),
+        jeff_optype::GateOpType::Custom { name, .. } => name.to_string(),
+    };
+    let mut modified = name;
+    if gate.adjoint {
+        modified = format!("adj {modified}");
+    }
+    if gate.control_qubits > 0 {
+        modified = format!("c{} {modified}", gate.control_qubits);
+    }
+    if gate.power > 1 {
+        modified = format!("{modified}^{}", gate.power);
+    }
+    modified
+}
+
+/// Normalize a gate for comparison.
+fn gate_token(
+    gate: jeff_optype::GateOp<'_>,
+    op: &Operation<'_>,
+    floats: &BTreeMap<ValueId, f64>,
+) -> Result<GateToken, JeffToHugrError> {
+    let gate = gate.normalize();
+    let mut params = Vec::new();
+    for value in op.inputs() {
+        let value = value?;
+        if let JeffType::Float { .. } = value.ty() {
+            let param = match floats.get(&value.id()) {
+                Some(f) => format!("{f:.PARAM_DIGITS$}"),
+                None => "?".to_string(),
+            };
+            params.push(param);
+        }
+    }
+    Ok(GateToken {
+        name: gate_name(gate),
+        arity: gate.num_qubits(),
+        params,
+    })
+}
+
+/// Compare the summaries of two modules.
+fn diff_summaries(
+    before: &BTreeMap<String, FunctionSummary>,
+    after: &BTreeMap<String, FunctionSummary>,
+) -> JeffDiff {
+    let mut diff = JeffDiff {
+        added_functions: after
+            .keys()
+            .filter(|name| !before.contains_key(*name))
+            .cloned()
+            .collect(),
+        removed_functions: before
+            .keys()
+            .filter(|name| !after.contains_key(*name))
+            .cloned()
+            .collect(),
+        ..Default::default()
+    };
+    for (name, old) in before {
+        let Some(new) = after.get(name) else {
+            continue;
+        };
+        let function = FunctionDiff {
+            signature: (old.signature != new.signature)
+                .then(|| (old.signature.clone(), new.signature.clone())),
+            op_counts: old
+                .op_counts
+                .keys()
+                .chain(new.op_counts.keys())
+                .unique()
+                .filter_map(|kind| {
+                    let counts = (
+                        old.op_counts.get(kind).copied().unwrap_or_default(),
+                        new.op_counts.get(kind).copied().unwrap_or_default(),
+                    );
+                    (counts.0 != counts.1).then(|| (kind.clone(), counts))
+                })
+                .collect(),
+            gate_edits: gate_edits(&old.gates, &new.gates),
+        };
+        if !function.is_empty() {
+            diff.changed_functions.insert(name.clone(), function);
+        }
+    }
+    diff
+}
+
+/// The gates to remove from `before` and add to get `after`, keeping a
+/// longest common subsequence.
+///
+/// The common prefix and suffix are skipped before filling the quadratic
+/// subsequence table.
+fn gate_edits(before: &[GateToken], after: &[GateToken]) -> Vec<GateEdit> {
+    let prefix = before
+        .iter()
+        .zip(after)
+        .take_while(|(old, new)| old == new)
+        .count();
+    let suffix = before[prefix..]
+        .iter()
+        .rev()
+        .zip(after[prefix..].iter().rev())
+        .take_while(|(old, new)| old == new)
+        .count();
+    let old = &before[prefix..before.len() - suffix];
+    let new = &after[prefix..after.len() - suffix];
+
+    // `lcs[i][j]` is the length of the longest common subsequence of
+    // `old[i..]` and `new[j..]`.
+    let width = new.len() + 1;
+    let mut lcs = vec![0usize; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i * width + j] = match old[i] == new[j] {
+                true => lcs[(i + 1) * width + j + 1] + 1,
+                false => lcs[(i + 1) * width + j].max(lcs[i * width + j + 1]),
+            };
+        }
+    }
+
+    let mut edits = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if j == new.len()
+            || (i < old.len() && lcs[(i + 1) * width + j] >= lcs[i * width + j + 1])
+        {
+            edits.push(GateEdit::Removed {
+                position: prefix + i,
+                gate: old[i].clone(),
+            });
+            i += 1;
+        } else {
+            edits.push(GateEdit::Added {
+                position: prefix + j,
+                gate: new[j].clone(),
+            });
+            j += 1;
+        }
+    }
+    edits
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{catalyst_tket_opt, entangled_qs, qubits};
+    use rstest::rstest;
+
+    #[rstest]
+    fn identical_programs(qubits: Jeff<'static>) {
+        let diff = diff_jeff(&qubits, &qubits).unwrap();
+        assert!(diff.is_empty());
+        assert_eq!(diff.to_string(), "No differences\n");
+    }
+
+    #[rstest]
+    fn different_programs(qubits: Jeff<'static>, entangled_qs: Jeff<'static>) {
+        let diff = diff_jeff(&qubits, &entangled_qs).unwrap();
+        assert_eq!(diff.removed_functions, ["Circuit"]);
+        assert!(diff.added_functions.contains(&"qarray".to_string()));
+    }
+
+    /// A fixture against a copy with one gate changed and one function
+    /// added.
+    #[rstest]
+    fn modified_copy(catalyst_tket_opt: Jeff<'static>) {
+        let before = summarize_module(&catalyst_tket_opt.module()).unwrap();
+        let mut after = before.clone();
+        let optimized = after.get_mut("optimize_me").unwrap();
+        let position = optimized.gates.len() / 2;
+        let old_gate = optimized.gates[position].clone();
+        let new_gate = GateToken {
+            name: format!("{}_changed", old_gate.name),
+            ..old_gate.clone()
+        };
+        optimized.gates[position] = new_gate.clone();
+        let old_kind = format!("Gate.{}", old_gate.name);
+        let new_kind = format!("Gate.{}", new_gate.name);
+        *optimized.op_counts.get_mut(&old_kind).unwrap() -= 1;
+        optimized.op_counts.insert(new_kind.clone(), 1);
+        after.insert("helper".to_string(), FunctionSummary::default());
+
+        let diff = diff_summaries(&before, &after);
+        assert_eq!(diff.added_functions, ["helper"]);
+        assert!(diff.removed_functions.is_empty());
+        assert_eq!(diff.changed_functions.len(), 1);
+
+        let function = &diff.changed_functions["optimize_me"];
+        assert_eq!(function.signature, None);
+        let old_count = before["optimize_me"].op_counts[&old_kind];
+        let expected_counts = BTreeMap::from([
+            (old_kind, (old_count, old_count - 1)),
+            (new_kind, (0, 1)),
+        ]);
+        assert_eq!(function.op_counts, expected_counts);
+        assert_eq!(
+            function.gate_edits,
+            [
+                GateEdit::Removed {
+                    position,
+                    gate: old_gate,
+                },
+                GateEdit::Added {
+                    position,
+                    gate: new_gate,
+                },
+            ]
+        );
+        assert!(diff.to_string().contains("Added function 'helper'"));
+    }
+
+    #[rstest]
+    fn subsequence_edits() {
+        let gate = |name: &str| GateToken {
+            name: name.to_string(),
+            arity: 1,
+            params: Vec::new(),
+        };
+        let before = ["H", "X", "Z", "H", "S"].map(gate);
+        let after = ["H", "Z", "Y", "H", "S", "T"].map(gate);
+        let edits = gate_edits(&before, &after);
+        assert_eq!(
+            edits,
+            [
+                GateEdit::Removed {
+                    position: 1,
+                    gate: gate("X"),
+                },
+                GateEdit::Added {
+                    position: 2,
+                    gate: gate("Y"),
+                },
+                GateEdit::Added {
+                    position: 5,
+                    gate: gate("T"),
+                },
+            ]
+        );
+    }
+}
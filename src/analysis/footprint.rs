@@ -0,0 +1,811 @@
+//! Estimation of the size of a translated HUGR, without building it.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use hugr::IncomingPort;
+use hugr::ops::OpType;
+use hugr::std_extensions::arithmetic::float_ops::FloatOps;
+use hugr::std_extensions::arithmetic::int_ops::IntOpDef;
+use hugr::std_extensions::logic::LogicOp;
+use jeff::Jeff;
+use jeff::reader::optype::{self as jeff_optype, ControlFlowOp, OpType as JeffOpType};
+use jeff::reader::value::ValueId;
+use jeff::reader::{FunctionId, Operation, Region};
+use jeff::types::Type as JeffType;
+use serde::Serialize;
+use tket::extension::rotation::{RotationOp, rotation_type};
+
+use crate::extension::{ConstIntReg, JeffOp};
+use crate::optype::tket_ops::{self, QuantumOp};
+use crate::optype::{
+    DO_WHILE_NODES, FOR_NODES, INT_SWITCH_CASE_NODES, INT_SWITCH_COMPARISON_NODES,
+    INT_SWITCH_NODES, IntTranslation, LOADED_CONSTANT_NODES, ROTATION_CONVERSION_NODES,
+    SELECT_FUNCTION_NODES, SWITCH_NODES, WHILE_NODES, WellKnownTranslation, classify_well_known,
+    forwarded_sources, gate_name, has_param_array, int_translation, precision_bits,
+    unpacked_gate_op,
+};
+use crate::to_hugr::{BuildContext, dead_trailing_ops};
+use crate::types::jeff_int_width_to_hugr_width;
+use crate::{ExactAngleConfig, JeffToHugrConfig, JeffToHugrError, types};
+
+/// Number of edges inside a select function.
+const SELECT_FUNCTION_EDGES: usize = 6;
+
+/// The estimated size of the HUGR translated from a _jeff_ program.
+///
+/// See [`conversion_footprint`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+pub struct FootprintReport {
+    /// Estimated number of nodes, including the module root and the input
+    /// and output nodes of each region.
+    pub nodes: usize,
+    /// Estimated number of edges, including the static edges of calls and
+    /// constants.
+    pub edges: usize,
+    /// Number of extension operations, by extension and operation name.
+    pub extension_ops: BTreeMap<String, BTreeMap<String, usize>>,
+    /// Maximum nesting depth of the _jeff_ regions. Function bodies have
+    /// depth 1.
+    pub max_depth: usize,
+    /// Number of _jeff_ operations the translation does not support.
+    ///
+    /// Each one is counted as a single node.
+    pub unsupported_ops: usize,
+}
+
+impl fmt::Display for FootprintReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<24} {}", "Nodes", self.nodes)?;
+        writeln!(f, "{:<24} {}", "Edges", self.edges)?;
+        writeln!(f, "{:<24} {}", "Max depth", self.max_depth)?;
+        write!(f, "{:<24} {}", "Unsupported ops", self.unsupported_ops)?;
+        for (extension, ops) in &self.extension_ops {
+            write!(f, "\n\n{extension}")?;
+            for (op, count) in ops {
+                write!(f, "\n  {op:<22} {count}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Estimate the size of the HUGR translated from a _jeff_ program with the
+/// default [`JeffToHugrConfig`], without building it.
+///
+/// The operations are classified as in the translation, and the nodes added
+/// around them (control flow, angle conversions, constants) are counted from
+/// the same constants. Edges between translated operations are estimated
+/// from the uses of each _jeff_ value.
+///
+/// # Errors
+///
+/// - [`JeffToHugrError::MalformedJeffFile`] if the program cannot be read.
+///
+/// # Examples
+///
+/// ```
+/// use hugr_jeff::analysis::conversion_footprint;
+/// use hugr_jeff::doctest_support::qubits;
+///
+/// let report = conversion_footprint(&qubits()).unwrap();
+/// assert_eq!(report.unsupported_ops, 0);
+/// // The five gates of the example have no `tket.quantum` equivalent.
+/// assert_eq!(report.extension_ops["jeff"]["QGateN"], 5);
+/// ```
+pub fn conversion_footprint(jeff: &Jeff) -> Result<FootprintReport, JeffToHugrError> {
+    let module = jeff.module();
+    let mut footprint = Footprint {
+        report: FootprintReport::default(),
+        exact_angles: JeffToHugrConfig::default().exact_angles,
+        intrinsics: BuildContext::intrinsics(&module)?,
+        shared_constants: BTreeSet::new(),
+        select_types: BTreeSet::new(),
+    };
+
+    // The module root.
+    footprint.report.nodes += 1;
+    for func in module.functions() {
+        match func {
+            jeff::reader::Function::Definition(def) => {
+                // The function definition, with its input and output nodes.
+                footprint.report.nodes += 3;
+                footprint.region(&def.body(), 1)?;
+            }
+            jeff::reader::Function::Declaration(_) => footprint.report.nodes += 1,
+        }
+    }
+
+    let selects = footprint.select_types.len();
+    footprint.report.nodes += footprint.shared_constants.len() + selects * SELECT_FUNCTION_NODES;
+    footprint.report.edges += selects * SELECT_FUNCTION_EDGES;
+    Ok(footprint.report)
+}
+
+/// State of a footprint estimation.
+struct Footprint {
+    /// The estimate so far.
+    report: FootprintReport,
+    /// See [`JeffToHugrConfig::exact_angles`].
+    exact_angles: Option<ExactAngleConfig>,
+    /// The declared intrinsics translated as extension operations.
+    intrinsics: BTreeMap<FunctionId, JeffOp>,
+    /// The constant arrays, emitted once at the module level.
+    shared_constants: BTreeSet<ConstIntReg>,
+    /// The types of the select functions.
+    select_types: BTreeSet<String>,
+}
+
+/// The constants defined in a region, whose uses as gate parameters are
+/// translated differently.
+#[derive(Default)]
+struct RegionConstants {
+    /// The float constants.
+    floats: BTreeMap<ValueId, f64>,
+    /// The elements of the constant float arrays.
+    float_arrays: BTreeMap<ValueId, Vec<f64>>,
+}
+
+impl Footprint {
+    /// Count the operations of a region at the given nesting depth.
+    fn region(&mut self, region: &Region<'_>, depth: usize) -> Result<(), JeffToHugrError> {
+        self.report.max_depth = self.report.max_depth.max(depth);
+        // The edges to the region outputs.
+        self.report.edges += region.targets().count();
+
+        let dead_ops = dead_trailing_ops(region)?;
+        let mut constants = RegionConstants::default();
+        for (position, op) in region.operations().enumerate() {
+            if !dead_ops.contains(&position) {
+                self.operation(&op, depth, &mut constants)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Count a _jeff_ operation.
+    fn operation(
+        &mut self,
+        op: &Operation<'_>,
+        depth: usize,
+        constants: &mut RegionConstants,
+    ) -> Result<(), JeffToHugrError> {
+        match op.op_type() {
+            JeffOpType::QubitOp(qubit_op) => match qubit_op {
+                jeff_optype::QubitOp::Alloc => self.single_op(tket_ops::qalloc(), op),
+                jeff_optype::QubitOp::Free | jeff_optype::QubitOp::FreeZero => {
+                    self.single_op(tket_ops::qfree(), op)
+                }
+                jeff_optype::QubitOp::Measure => {
+                    self.single_op(tket_ops::measure_destructive(), op)
+                }
+                jeff_optype::QubitOp::MeasureNd => self.single_op(tket_ops::measure(), op),
+                jeff_optype::QubitOp::Reset => self.single_op(tket_ops::reset(), op),
+                jeff_optype::QubitOp::Gate(gate) => self.gate(gate, op, constants)?,
+                _ => self.unsupported(op),
+            },
+            JeffOpType::QubitRegisterOp(reg_op) => {
+                use jeff_optype::QubitRegisterOp::*;
+                match reg_op {
+                    Alloc => self.single_op(JeffOp::QuregAlloc, op),
+                    Free | FreeZero => self.single_op(JeffOp::QuregFree, op),
+                    ExtractIndex => self.single_op(JeffOp::QuregExtractIndex, op),
+                    InsertIndex => self.single_op(JeffOp::QuregInsertIndex, op),
+                    ExtractSlice => self.single_op(JeffOp::QuregExtractSlice, op),
+                    InsertSlice => self.single_op(JeffOp::QuregInsertSlice, op),
+                    Length => self.single_op(JeffOp::QuregLength, op),
+                    Split => self.single_op(JeffOp::QuregSplit, op),
+                    Join => self.single_op(JeffOp::QuregJoin, op),
+                    Measure => self.single_op(JeffOp::QuregMeasure, op),
+                    MeasureNd => self.single_op(JeffOp::QuregMeasureNd, op),
+                    Create => {
+                        let qubits = op.input_count();
+                        self.single_op(JeffOp::QuregCreate { qubits }, op)
+                    }
+                    _ => self.unsupported(op),
+                }
+            }
+            JeffOpType::IntOp(int_op) => match int_op {
+                jeff_optype::IntOp::Const1(_)
+                | jeff_optype::IntOp::Const8(_)
+                | jeff_optype::IntOp::Const16(_)
+                | jeff_optype::IntOp::Const32(_)
+                | jeff_optype::IntOp::Const64(_) => self.constant(),
+                jeff_optype::IntOp::Add => self.int_arithmetic(IntOpDef::iadd, op)?,
+                jeff_optype::IntOp::Sub => self.int_arithmetic(IntOpDef::isub, op)?,
+                jeff_optype::IntOp::Mul => self.int_arithmetic(IntOpDef::imul, op)?,
+                _ => self.int_op(&int_op, op)?,
+            },
+            JeffOpType::FloatOp(float_op) => self.float_op(float_op, op, constants)?,
+            JeffOpType::IntArrayOp(array_op) => self.int_array_op(&array_op, op)?,
+            JeffOpType::FloatArrayOp(array_op) => {
+                self.float_array_op(&array_op, op, constants)?
+            }
+            JeffOpType::FuncOp(func_op) => {
+                match self.intrinsics.get(&(func_op.func_idx as FunctionId)) {
+                    Some(intrinsic) => self.single_op(intrinsic.clone(), op),
+                    None => {
+                        // A call, with a static edge from the function.
+                        self.report.nodes += 1;
+                        self.report.edges += op.input_count() + 1;
+                    }
+                }
+            }
+            JeffOpType::ControlFlowOp(cf_op) => self.control_flow(&cf_op, op, depth)?,
+            _ => self.unsupported(op),
+        }
+        Ok(())
+    }
+
+    /// Count a gate, see [`crate::optype::classify_well_known`].
+    fn gate(
+        &mut self,
+        gate: jeff_optype::GateOp<'_>,
+        op: &Operation<'_>,
+        constants: &RegionConstants,
+    ) -> Result<(), JeffToHugrError> {
+        let gate = gate.normalize();
+        if has_param_array(gate, op)? {
+            return self.unpacked_gate(gate, op, constants);
+        }
+        match gate.gate_type {
+            jeff_optype::GateOpType::WellKnown(well_known) => {
+                match classify_well_known(well_known, gate) {
+                    WellKnownTranslation::Transparent | WellKnownTranslation::Swap => {}
+                    WellKnownTranslation::Tket(tket_op) => self.single_op(tket_op, op),
+                    WellKnownTranslation::Rotation(tket_op) => {
+                        self.rotation(tket_op, op, constants)?
+                    }
+                    WellKnownTranslation::GlobalPhase => self.single_op(JeffOp::GlobalPhase, op),
+                    WellKnownTranslation::Opaque => {
+                        self.single_op(JeffOp::jeff_gate_op(well_known, gate), op)
+                    }
+                }
+            }
+            jeff_optype::GateOpType::PauliProdRotation { pauli_string } => {
+                self.single_op(JeffOp::jeff_gate_op(pauli_string, gate), op)
+            }
+            jeff_optype::GateOpType::Custom { name, .. } => {
+                self.single_op(JeffOp::jeff_gate_op(name, gate), op)
+            }
+        }
+        Ok(())
+    }
+
+    /// Count a rotation gate, with the conversion of its angles to half-turns.
+    fn rotation(
+        &mut self,
+        tket_op: QuantumOp,
+        op: &Operation<'_>,
+        constants: &RegionConstants,
+    ) -> Result<(), JeffToHugrError> {
+        let hugr_op = OpType::from(tket_op);
+        let sig = hugr_op.dataflow_signature().unwrap().into_owned();
+        self.report.nodes += 1;
+        self.count_op(&hugr_op);
+
+        let rotation_t = rotation_type();
+        let mut pi_loaded = false;
+        for (index, value) in op.inputs().enumerate() {
+            let value = value?;
+            if sig.in_port_type(IncomingPort::from(index)) == Some(&rotation_t) {
+                let radians = constants.floats.get(&value.id()).copied();
+                if self.rotation_input(radians, &mut pi_loaded) {
+                    // The angle, divided by π.
+                    self.report.edges += 1;
+                }
+            } else {
+                self.report.edges += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Count the connection of an angle in radians to a rotation input,
+    /// see [`crate::optype::ROTATION_CONVERSION_NODES`].
+    ///
+    /// Returns whether the angle is converted at runtime, in which case its
+    /// edge to the conversion is not counted.
+    fn rotation_input(&mut self, radians: Option<f64>, pi_loaded: &mut bool) -> bool {
+        let exact = self
+            .exact_angles
+            .zip(radians)
+            .and_then(|(config, radians)| config.exact_half_turns(radians));
+        if exact.is_some() {
+            // A rotation constant, connected to the gate.
+            self.report.nodes += LOADED_CONSTANT_NODES;
+            self.report.edges += 2;
+            return false;
+        }
+        if !*pi_loaded {
+            self.report.nodes += LOADED_CONSTANT_NODES;
+            self.report.edges += 1;
+            *pi_loaded = true;
+        }
+        // The π division input, and the conversion chain to the gate.
+        self.report.nodes += ROTATION_CONVERSION_NODES;
+        self.report.edges += 3;
+        self.count_op(&FloatOps::fdiv.into());
+        self.count_op(&RotationOp::from_halfturns_unchecked.into());
+        true
+    }
+
+    /// Count a gate whose parameters are passed as a float array.
+    ///
+    /// The elements of constant arrays are loaded as constants, the others
+    /// are read from the array.
+    fn unpacked_gate(
+        &mut self,
+        gate: jeff_optype::GateOp<'_>,
+        op: &Operation<'_>,
+        constants: &RegionConstants,
+    ) -> Result<(), JeffToHugrError> {
+        let num_qubits = gate.num_qubits();
+        let array = op.input(num_qubits).unwrap()?;
+        let (JeffType::FloatArray { precision }, Ok((hugr_op, _, params))) =
+            (array.ty(), unpacked_gate_op(gate, gate_name(gate)))
+        else {
+            self.unsupported(op);
+            return Ok(());
+        };
+        let sig = hugr_op.dataflow_signature().unwrap().into_owned();
+        self.report.nodes += 1;
+        self.report.edges += num_qubits;
+        self.count_op(&hugr_op);
+
+        let values = constants.float_arrays.get(&array.id());
+        let rotation_t = rotation_type();
+        let mut pi_loaded = false;
+        for param in 0..params {
+            let constant = values.and_then(|values| values.get(param).copied());
+            let port = IncomingPort::from(num_qubits + param);
+            if sig.in_port_type(port) == Some(&rotation_t)
+                && !self.rotation_input(constant, &mut pi_loaded)
+            {
+                continue;
+            }
+            match constant {
+                Some(_) => self.constant(),
+                None => {
+                    // The loaded index and the array read.
+                    self.constant();
+                    self.report.nodes += 1;
+                    self.report.edges += 2;
+                    let get = JeffOp::FloatArrayGet {
+                        bits: precision_bits(precision),
+                    };
+                    self.count_op(&get.into());
+                }
+            }
+            // The element, connected to the gate or its angle conversion.
+            self.report.edges += 1;
+        }
+        Ok(())
+    }
+
+    /// Count a float operation, recording the constants.
+    fn float_op(
+        &mut self,
+        float_op: jeff_optype::FloatOp,
+        op: &Operation<'_>,
+        constants: &mut RegionConstants,
+    ) -> Result<(), JeffToHugrError> {
+        use jeff_optype::FloatOp::*;
+
+        let hugr_op = match float_op {
+            Const32(_) | Const64(_) => {
+                let value = match float_op {
+                    Const32(f) => f as f64,
+                    Const64(f) => f,
+                    _ => unreachable!(),
+                };
+                if let Some(Ok(output)) = op.output(0) {
+                    constants.floats.insert(output.id(), value);
+                }
+                self.constant();
+                return Ok(());
+            }
+            Add => FloatOps::fadd,
+            Sub => FloatOps::fsub,
+            Mul => FloatOps::fmul,
+            Pow => FloatOps::fpow,
+            Eq => FloatOps::feq,
+            Lt => FloatOps::flt,
+            Lte => FloatOps::fle,
+            Abs => FloatOps::fabs,
+            Ceil => FloatOps::fceil,
+            Floor => FloatOps::ffloor,
+            Max => FloatOps::fmax,
+            Min => FloatOps::fmin,
+            _ => {
+                match JeffOp::float_function(&float_op) {
+                    Some(function) => self.single_op(function, op),
+                    None => self.unsupported(op),
+                }
+                return Ok(());
+            }
+        };
+        self.single_op(hugr_op, op);
+        Ok(())
+    }
+
+    /// Count an integer array operation.
+    fn int_array_op(
+        &mut self,
+        array_op: &jeff_optype::IntArrayOp<'_>,
+        op: &Operation<'_>,
+    ) -> Result<(), JeffToHugrError> {
+        use jeff_optype::IntArrayOp::*;
+
+        let input_bits = match op.input(0).transpose()?.map(|value| value.ty()) {
+            Some(JeffType::Int { bits } | JeffType::IntArray { bits }) => Some(bits),
+            _ => None,
+        };
+        let constant = match array_op {
+            ConstArray1(array) => ConstIntReg::new(array.values().map(|v| v as u64), 1),
+            ConstArray8(array) => ConstIntReg::new(array.values().map(|v| v as u64), 8),
+            ConstArray16(array) => ConstIntReg::new(array.values().map(|v| v as u64), 16),
+            ConstArray32(array) => ConstIntReg::new(array.values().map(|v| v as u64), 32),
+            ConstArray64(array) => ConstIntReg::new(array.values(), 64),
+            _ => {
+                let hugr_op = match (array_op, input_bits) {
+                    (Create, Some(bits)) => JeffOp::IntArrayCreate {
+                        bits,
+                        inputs: op.input_count(),
+                    },
+                    (GetIndex, Some(bits)) => JeffOp::IntArrayGet { bits },
+                    (SetIndex, Some(bits)) => JeffOp::IntArraySet { bits },
+                    (Zero { bits }, _) => JeffOp::IntArrayZero { bits: *bits },
+                    _ => {
+                        self.unsupported(op);
+                        return Ok(());
+                    }
+                };
+                self.single_op(hugr_op, op);
+                return Ok(());
+            }
+        };
+        // A load of the module-level constant.
+        self.report.nodes += 1;
+        self.report.edges += 1;
+        self.shared_constants.insert(constant);
+        Ok(())
+    }
+
+    /// Count a float array operation, recording the constant arrays.
+    fn float_array_op(
+        &mut self,
+        array_op: &jeff_optype::FloatArrayOp<'_>,
+        op: &Operation<'_>,
+        constants: &mut RegionConstants,
+    ) -> Result<(), JeffToHugrError> {
+        let Some(output) = op.output(0).transpose()? else {
+            self.unsupported(op);
+            return Ok(());
+        };
+        let (bits, values) = match array_op {
+            jeff_optype::FloatArrayOp::Create => {
+                let JeffType::FloatArray { precision } = output.ty() else {
+                    self.unsupported(op);
+                    return Ok(());
+                };
+                let bits = precision_bits(precision);
+                let inputs = op.input_count();
+                self.single_op(JeffOp::FloatArrayCreate { bits, inputs }, op);
+
+                let values = op
+                    .inputs()
+                    .map(|value| Ok(constants.floats.get(&value?.id()).copied()))
+                    .collect::<Result<Option<Vec<_>>, JeffToHugrError>>()?;
+                if let Some(values) = values {
+                    constants.float_arrays.insert(output.id(), values);
+                }
+                return Ok(());
+            }
+            jeff_optype::FloatArrayOp::GetIndex => {
+                match op.input(0).transpose()?.map(|array| array.ty()) {
+                    Some(JeffType::FloatArray { precision }) => {
+                        let bits = precision_bits(precision);
+                        self.single_op(JeffOp::FloatArrayGet { bits }, op);
+                    }
+                    _ => self.unsupported(op),
+                }
+                return Ok(());
+            }
+            jeff_optype::FloatArrayOp::ConstArray32(array) => {
+                (32, array.values().map(|v| v as f64).collect::<Vec<_>>())
+            }
+            jeff_optype::FloatArrayOp::ConstArray64(array) => (64, array.values().collect()),
+            _ => {
+                self.unsupported(op);
+                return Ok(());
+            }
+        };
+        // The loaded elements, and the array creation.
+        for _ in &values {
+            self.constant();
+        }
+        self.report.nodes += 1;
+        self.report.edges += values.len();
+        let create = JeffOp::FloatArrayCreate {
+            bits,
+            inputs: values.len(),
+        };
+        self.count_op(&create.into());
+        constants.float_arrays.insert(output.id(), values);
+        Ok(())
+    }
+
+    /// Count a control flow operation and its regions.
+    fn control_flow(
+        &mut self,
+        cf_op: &ControlFlowOp<'_>,
+        op: &Operation<'_>,
+        depth: usize,
+    ) -> Result<(), JeffToHugrError> {
+        let inputs = op.input_count();
+        let outputs = op.output_count();
+        match cf_op {
+            ControlFlowOp::Switch(switch_op) => {
+                let input_types = op
+                    .input_types()
+                    .map(|ty| Ok(types::jeff_to_hugr(ty?)))
+                    .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+                let branches = match (switch_op.branch_count(), switch_op.default_branch()) {
+                    (2, _) => Some([switch_op.branch(0), switch_op.branch(1)]),
+                    (1, Some(default_branch)) => Some([switch_op.branch(0), default_branch]),
+                    _ => None,
+                };
+                if let Some(branches) = branches
+                    && let Some(forwarded) = forwarded_sources(&branches, &input_types)?
+                {
+                    for (output, &[first, second]) in op.outputs().zip(&forwarded) {
+                        let output = output?;
+                        if first != second {
+                            // A call to the select function of the output type.
+                            self.report.nodes += 1;
+                            self.report.edges += 4;
+                            self.select_types
+                                .insert(types::jeff_to_hugr(output.ty()).to_string());
+                        }
+                    }
+                    return Ok(());
+                }
+
+                let Some(JeffType::Int { bits }) = op.input_types().next().transpose()? else {
+                    self.unsupported(op);
+                    return Ok(());
+                };
+                if bits > 1 {
+                    let branches = (0..switch_op.branch_count())
+                        .map(|idx| switch_op.branch(idx))
+                        .collect::<Vec<_>>();
+                    return self.int_switch(&branches, switch_op.default_branch(), op, bits, depth);
+                }
+                self.report.nodes += SWITCH_NODES;
+                self.report.edges += inputs;
+                self.region(&switch_op.branch(0), depth + 1)?;
+                if switch_op.branch_count() > 1 {
+                    self.region(&switch_op.branch(1), depth + 1)?;
+                } else if let Some(default_branch) = switch_op.default_branch() {
+                    self.region(&default_branch, depth + 1)?;
+                } else {
+                    // The second case forwards its inputs.
+                    self.report.edges += outputs;
+                }
+            }
+            ControlFlowOp::DoWhile { body, condition } => {
+                self.report.nodes += DO_WHILE_NODES;
+                // The state goes through the body, the condition and the loop
+                // outputs, and the condition is negated.
+                self.report.edges += inputs + 3 * inputs + 2;
+                self.count_op(&LogicOp::Not.into());
+                self.region(body, depth + 1)?;
+                self.region(condition, depth + 1)?;
+            }
+            ControlFlowOp::While { body, condition } => {
+                self.report.nodes += WHILE_NODES;
+                // The state goes through the condition, both cases and the
+                // loop outputs, and the condition is negated.
+                self.report.edges += inputs + 4 * inputs + 3;
+                self.count_op(&LogicOp::Not.into());
+                self.region(condition, depth + 1)?;
+                self.region(body, depth + 1)?;
+            }
+            ControlFlowOp::For { region } => {
+                let Some(JeffType::Int { bits }) = op.input_types().next().transpose()? else {
+                    self.unsupported(op);
+                    return Ok(());
+                };
+                let log_width = jeff_int_width_to_hugr_width(bits);
+                self.report.nodes += FOR_NODES;
+                // The counters and the state go through the bound check, both
+                // cases and the loop outputs.
+                self.report.edges += inputs + 15 + 5 * outputs;
+                self.count_op(&IntOpDef::ilt_s.with_log_width(log_width).into());
+                self.count_op(&IntOpDef::iadd.with_log_width(log_width).into());
+                self.region(region, depth + 1)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Count a single operation connected to the _jeff_ operation inputs.
+    fn single_op(&mut self, hugr_op: impl Into<OpType>, op: &Operation<'_>) {
+        self.report.nodes += 1;
+        self.report.edges += op.input_count();
+        self.count_op(&hugr_op.into());
+    }
+
+    /// Count a wrapping integer arithmetic operation, at the width of its
+    /// first input.
+    fn int_arithmetic(
+        &mut self,
+        arith: IntOpDef,
+        op: &Operation<'_>,
+    ) -> Result<(), JeffToHugrError> {
+        match op.input_types().next().transpose()? {
+            Some(JeffType::Int { bits }) if bits > 1 => {
+                let log_width = jeff_int_width_to_hugr_width(bits);
+                self.single_op(arith.with_log_width(log_width), op);
+            }
+            _ => self.unsupported(op),
+        }
+        Ok(())
+    }
+
+    /// Count an integer operation other than the constants and the
+    /// arithmetic, see [`crate::optype::int_translation`].
+    fn int_op(
+        &mut self,
+        int_op: &jeff_optype::IntOp,
+        op: &Operation<'_>,
+    ) -> Result<(), JeffToHugrError> {
+        let Some(JeffType::Int { bits }) = op.input_types().next().transpose()? else {
+            self.unsupported(op);
+            return Ok(());
+        };
+        let log_width = jeff_int_width_to_hugr_width(bits);
+        match int_translation(int_op, bits) {
+            Some(IntTranslation::Logic(logic_op)) => self.single_op(logic_op, op),
+            Some(IntTranslation::Int(int_op)) => {
+                self.single_op(int_op.with_log_width(log_width), op)
+            }
+            Some(IntTranslation::Signed(signed_op)) => {
+                self.report.nodes += signed_op.node_count();
+                self.report.edges += op.input_count();
+            }
+            None => self.unsupported(op),
+        }
+        Ok(())
+    }
+
+    /// Count a loaded constant.
+    fn constant(&mut self) {
+        self.report.nodes += LOADED_CONSTANT_NODES;
+        self.report.edges += 1;
+    }
+
+    /// Count an unsupported operation as a single node.
+    fn unsupported(&mut self, op: &Operation<'_>) {
+        self.report.unsupported_ops += 1;
+        self.report.nodes += 1;
+        self.report.edges += op.input_count();
+    }
+
+    /// Count a `Switch` on a wider integer and its branches.
+    ///
+    /// The selector is compared with each branch index, and the default
+    /// branch runs for the other values.
+    fn int_switch(
+        &mut self,
+        branches: &[Region<'_>],
+        default_branch: Option<Region<'_>>,
+        op: &Operation<'_>,
+        bits: u8,
+        depth: usize,
+    ) -> Result<(), JeffToHugrError> {
+        let selector_values = 1usize.checked_shl(bits.into()).unwrap_or(usize::MAX);
+        let branches = &branches[..branches.len().min(selector_values)];
+        let branch_count = branches.len();
+        let default_branch = default_branch.filter(|_| branch_count < selector_values);
+        if default_branch.is_none() && branch_count < selector_values {
+            self.unsupported(op);
+            return Ok(());
+        }
+        let cases = branch_count + usize::from(default_branch.is_some());
+        let comparisons = cases - 1;
+        let log_width = jeff_int_width_to_hugr_width(bits);
+        self.report.nodes += INT_SWITCH_NODES
+            + cases * INT_SWITCH_CASE_NODES
+            + comparisons * INT_SWITCH_COMPARISON_NODES;
+        // The tag goes through each comparison into the `Conditional`, and
+        // the selector and its index into each `ieq`.
+        self.report.edges += op.input_count() + 1 + 5 * comparisons;
+        for _ in 0..comparisons {
+            self.count_op(&IntOpDef::ieq.with_log_width(log_width).into());
+        }
+        for branch in branches.iter().chain(&default_branch) {
+            self.region(branch, depth + 1)?;
+        }
+        Ok(())
+    }
+
+    /// Add an extension operation to the histogram.
+    fn count_op(&mut self, hugr_op: &OpType) {
+        let OpType::ExtensionOp(ext_op) = hugr_op else {
+            return;
+        };
+        let def = ext_op.def();
+        *self
+            .report
+            .extension_ops
+            .entry(def.extension_id().to_string())
+            .or_default()
+            .entry(def.name().to_string())
+            .or_default() += 1;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::jeff_to_hugr;
+    use crate::test::{catalyst_simple, catalyst_tket_opt, qubits};
+    use hugr::HugrView;
+    use rstest::rstest;
+
+    /// Check that an estimate is within 10% of the actual value.
+    fn assert_close(what: &str, estimate: usize, actual: usize) {
+        assert!(
+            estimate.abs_diff(actual) * 10 <= actual,
+            "Estimated {estimate} {what}, but the translation has {actual}"
+        );
+    }
+
+    #[rstest]
+    #[case::qubits(qubits())]
+    #[case::catalyst_simple(catalyst_simple())]
+    #[case::catalyst_tket_opt(catalyst_tket_opt())]
+    fn matches_translation(#[case] jeff: Jeff<'static>) {
+        let report = conversion_footprint(&jeff).unwrap();
+        let hugr = jeff_to_hugr(&jeff).unwrap();
+
+        assert_close("nodes", report.nodes, hugr.num_nodes());
+        assert_close("edges", report.edges, hugr.num_edges());
+        assert_eq!(report.unsupported_ops, 0);
+
+        let extensions: BTreeSet<String> = hugr
+            .nodes()
+            .filter_map(|node| match hugr.get_optype(node) {
+                OpType::ExtensionOp(op) => Some(op.def().extension_id().to_string()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            report.extension_ops.keys().cloned().collect::<BTreeSet<_>>(),
+            extensions
+        );
+    }
+
+    #[rstest]
+    fn qubits_histogram(qubits: Jeff<'static>) {
+        let report = conversion_footprint(&qubits).unwrap();
+        assert_eq!(report.max_depth, 1);
+
+        let hugr = jeff_to_hugr(&qubits).unwrap();
+        let mut expected: BTreeMap<String, BTreeMap<String, usize>> = BTreeMap::new();
+        for node in hugr.nodes() {
+            if let OpType::ExtensionOp(op) = hugr.get_optype(node) {
+                let def = op.def();
+                *expected
+                    .entry(def.extension_id().to_string())
+                    .or_default()
+                    .entry(def.name().to_string())
+                    .or_default() += 1;
+            }
+        }
+        assert_eq!(report.extension_ops, expected);
+    }
+}
@@ -0,0 +1,152 @@
+//! Verification of the qubits asserted to be in the |0⟩ state when freed.
+
+use std::fmt;
+
+use hugr::extension::prelude::qb_t;
+use hugr::{HugrView, Node};
+
+use super::ancilla::uncomputes;
+use super::gate_set::containing_function;
+use crate::extension::JeffOp;
+use crate::metadata;
+use crate::optype::tket_ops::{self, QuantumOp};
+
+/// A `QFree` marked with [`metadata::FREE_ZERO`] whose qubit is not known
+/// to be in the |0⟩ state.
+///
+/// See [`check_free_zero`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct UnverifiedFreeZero {
+    /// The `QFree` node.
+    pub free: Node,
+    /// The operations acting on the qubit since it was last known to be in
+    /// the |0⟩ state, or since its wire could not be followed, in order.
+    pub ops: Vec<Node>,
+    /// The name of the function containing the `QFree`, if any.
+    pub function: Option<String>,
+}
+
+impl fmt::Display for UnverifiedFreeZero {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FreeZero at {}", self.free)?;
+        if let Some(function) = &self.function {
+            write!(f, " in function '{function}'")?;
+        }
+        write!(f, " releases a qubit not known to be in the |0⟩ state")
+    }
+}
+
+/// Find the _jeff_ `FreeZero` operations whose qubit is not structurally
+/// known to be in the |0⟩ state.
+///
+/// The `QFree` nodes marked with [`metadata::FREE_ZERO`] are verified when
+/// their qubit wire comes from a `QAlloc` or a `Reset`, either directly or
+/// through gates forming nested pairs of identical self-inverse gates, as
+/// recognized by [`crate::analysis::find_ancilla_reuse`]. The wire is only
+/// followed through tket operations and _jeff_ `QGateN` gates.
+///
+/// The unverified frees can be repaired with
+/// [`crate::passes::repair_free_zero`].
+pub fn check_free_zero(hugr: &impl HugrView<Node = Node>) -> Vec<UnverifiedFreeZero> {
+    hugr.nodes()
+        .filter(|&node| is_free_zero(hugr, node))
+        .filter_map(|free| {
+            let (ops, zero) = zero_history(hugr, free);
+            let verified = zero && uncomputes(hugr, &ops);
+            (!verified).then(|| UnverifiedFreeZero {
+                free,
+                ops: ops.into_iter().map(|(node, _)| node).collect(),
+                function: containing_function(hugr, free),
+            })
+        })
+        .collect()
+}
+
+/// Whether a node is a `QFree` marked with [`metadata::FREE_ZERO`].
+pub(crate) fn is_free_zero(hugr: &impl HugrView<Node = Node>, node: Node) -> bool {
+    QuantumOp::of(hugr.get_optype(node)) == Some(QuantumOp::QFree)
+        && hugr
+            .get_metadata(node, metadata::FREE_ZERO)
+            .and_then(|mark| mark.as_bool())
+            == Some(true)
+}
+
+/// Follow the qubit freed by `free` back to a `QAlloc` or a `Reset`.
+///
+/// Returns the operations on the way with the port of the qubit, in order,
+/// and whether the wire starts in the |0⟩ state.
+fn zero_history(hugr: &impl HugrView<Node = Node>, free: Node) -> (Vec<(Node, usize)>, bool) {
+    let mut ops = Vec::new();
+    let mut source = hugr.single_linked_output(free, 0);
+    let zero = loop {
+        let Some((node, port)) = source else {
+            break false;
+        };
+        let op = hugr.get_optype(node);
+        if matches!(QuantumOp::of(op), Some(QuantumOp::QAlloc | QuantumOp::Reset)) {
+            break true;
+        }
+        let is_gate = matches!(op.cast::<JeffOp>(), Some(JeffOp::QGate { .. }));
+        if !tket_ops::is_tket_op(op) && !is_gate {
+            break false;
+        }
+        let is_qubit = op
+            .dataflow_signature()
+            .is_some_and(|sig| sig.input_types().get(port.index()) == Some(&qb_t()));
+        if !is_qubit {
+            break false;
+        }
+        ops.push((node, port.index()));
+        source = hugr.single_linked_output(node, port.index());
+    };
+    ops.reverse();
+    (ops, zero)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{free_zero_dfg, free_zero_entangled, free_zero_input, free_zero_verified};
+    use QuantumOp::{CX, H, Reset};
+    use hugr::Hugr;
+    use hugr::hugr::hugrmut::HugrMut;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::verified(free_zero_verified(), None)]
+    #[case::entangled(free_zero_entangled(), Some(2))]
+    #[case::input(free_zero_input(), Some(1))]
+    fn verify_fixtures(#[case] hugr: Hugr, #[case] unverified_ops: Option<usize>) {
+        let unverified = check_free_zero(&hugr);
+        assert!(unverified.len() <= 1);
+        assert_eq!(unverified.first().map(|free| free.ops.len()), unverified_ops);
+        if let Some(free) = unverified.first() {
+            assert!(is_free_zero(&hugr, free.free));
+            assert_eq!(free.function, None);
+        }
+    }
+
+    #[rstest]
+    #[case::untouched(&[], true, true)]
+    #[case::reset(&[(CX, &[0, 1]), (Reset, &[1])], true, true)]
+    #[case::reset_uncomputed(&[(H, &[1]), (Reset, &[1]), (H, &[1]), (H, &[1])], true, true)]
+    #[case::not_nested(&[(H, &[1]), (CX, &[1, 0]), (H, &[1]), (CX, &[1, 0])], true, false)]
+    #[case::input_reset(&[(Reset, &[1])], false, true)]
+    fn verify_free_zero(
+        #[case] gates: &[(QuantumOp, &[usize])],
+        #[case] allocated: bool,
+        #[case] verified: bool,
+    ) {
+        let hugr = free_zero_dfg(gates, allocated);
+        assert_eq!(check_free_zero(&hugr).is_empty(), verified);
+    }
+
+    #[rstest]
+    fn unmarked_free(free_zero_entangled: Hugr) {
+        let mut hugr = free_zero_entangled;
+        let free = check_free_zero(&hugr)[0].free;
+        hugr.remove_metadata(free, metadata::FREE_ZERO);
+        assert!(check_free_zero(&hugr).is_empty());
+    }
+}
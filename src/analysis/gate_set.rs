@@ -0,0 +1,356 @@
+//! Checking the quantum operations of a HUGR against a target gate set.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use hugr::extension::prelude::qb_t;
+use hugr::extension::simple_op::MakeOpDef;
+use hugr::ops::OpType;
+use hugr::{HugrView, Node};
+use serde::{Deserialize, Serialize};
+use tket::TketOp;
+
+use crate::TargetInfo;
+use crate::extension::{JeffOp, JeffOpDef, qureg_type};
+
+/// A set of quantum operations supported by a target backend.
+///
+/// Gate sets can be loaded from JSON, e.g.
+///
+/// ```json
+/// {
+///   "tket_ops": ["Rz", "Rx", "CZ", "Measure", "QAlloc", "QFree"],
+///   "jeff_ops": [{ "op": "QGateN", "gates": ["U"], "max_controls": 0 }]
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct GateSet {
+    /// Names of the allowed `tket.quantum` operations.
+    pub tket_ops: BTreeSet<String>,
+    /// Allowed operations of the _jeff_ extension.
+    pub jeff_ops: Vec<JeffOpRule>,
+}
+
+/// An allowed operation of the _jeff_ extension, optionally restricted
+/// to some of its instances.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct JeffOpRule {
+    /// Name of the operation definition, e.g. `QGateN`.
+    pub op: String,
+    /// Allowed gate names of [`JeffOp::QGate`] and [`JeffOp::CondGate`]
+    /// operations. All the gates are allowed if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gates: Option<BTreeSet<String>>,
+    /// Maximum number of control qubits of [`JeffOp::QGate`] operations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_controls: Option<usize>,
+}
+
+impl JeffOpRule {
+    /// Allow all the instances of a _jeff_ operation.
+    pub fn new(op: impl ToString) -> Self {
+        Self {
+            op: op.to_string(),
+            gates: None,
+            max_controls: None,
+        }
+    }
+
+    /// Only allow some gate names, see [`JeffOpRule::gates`].
+    pub fn with_gates(mut self, gates: impl IntoIterator<Item = impl ToString>) -> Self {
+        self.gates = Some(gates.into_iter().map(|gate| gate.to_string()).collect());
+        self
+    }
+
+    /// Limit the number of control qubits, see [`JeffOpRule::max_controls`].
+    pub fn with_max_controls(mut self, max_controls: usize) -> Self {
+        self.max_controls = Some(max_controls);
+        self
+    }
+
+    /// Whether the rule allows a _jeff_ operation.
+    fn allows(&self, op: &JeffOp) -> bool {
+        if self.op != op.opdef().opdef_id().as_str() {
+            return false;
+        }
+        let (gate, controls) = match op {
+            JeffOp::QGate { name, control, .. } => (Some(name), *control),
+            JeffOp::CondGate { gate, .. } => (Some(gate), 0),
+            _ => (None, 0),
+        };
+        let gate_allowed = match (&self.gates, gate) {
+            (Some(gates), Some(gate)) => gates.contains(gate),
+            _ => true,
+        };
+        gate_allowed && self.max_controls.is_none_or(|max| controls <= max)
+    }
+}
+
+impl GateSet {
+    /// A gate set allowing the given `tket.quantum` operations only.
+    pub fn from_tket_ops(ops: impl IntoIterator<Item = TketOp>) -> Self {
+        Self {
+            tket_ops: ops
+                .into_iter()
+                .map(|op| op.opdef_id().to_string())
+                .collect(),
+            jeff_ops: Vec::new(),
+        }
+    }
+
+    /// The native gates of IBM-like backends: `Rz`, `X`, `V` (the square
+    /// root of `X`) and `CX`, along with measurements, allocation and reset.
+    pub fn ibm_like() -> Self {
+        Self::from_tket_ops([TketOp::Rz, TketOp::X, TketOp::V, TketOp::CX])
+            .with_tket_ops(Self::non_unitary_ops())
+    }
+
+    /// The native gates of Quantinuum-like backends: `Rz`, `Rx`, `Ry` and
+    /// `CZ`, along with measurements, allocation and reset.
+    pub fn quantinuum_like() -> Self {
+        Self::from_tket_ops([TketOp::Rz, TketOp::Rx, TketOp::Ry, TketOp::CZ])
+            .with_tket_ops(Self::non_unitary_ops())
+    }
+
+    /// The native gates of a target device, along with measurements,
+    /// allocation and reset.
+    ///
+    /// The native gates named after a `tket.quantum` operation allow it.
+    /// All the native gates allow the opaque [`JeffOp::QGate`]s with their
+    /// name.
+    pub fn from_target(target: &TargetInfo) -> Self {
+        let tket_ops = target
+            .native_gates
+            .iter()
+            .filter_map(|gate| gate.parse::<TketOp>().ok());
+        let gate_set = Self::from_tket_ops(tket_ops).with_tket_ops(Self::non_unitary_ops());
+        if target.native_gates.is_empty() {
+            return gate_set;
+        }
+        let qgate = JeffOpDef::QGate.opdef_id();
+        gate_set.with_jeff_op(JeffOpRule::new(qgate).with_gates(&target.native_gates))
+    }
+
+    /// Allow some `tket.quantum` operations in addition to the current ones.
+    pub fn with_tket_ops(mut self, ops: impl IntoIterator<Item = TketOp>) -> Self {
+        self.tket_ops
+            .extend(ops.into_iter().map(|op| op.opdef_id().to_string()));
+        self
+    }
+
+    /// Allow a _jeff_ operation in addition to the current ones.
+    pub fn with_jeff_op(mut self, rule: JeffOpRule) -> Self {
+        self.jeff_ops.push(rule);
+        self
+    }
+
+    /// The measurement, allocation and reset operations.
+    fn non_unitary_ops() -> [TketOp; 5] {
+        [
+            TketOp::Measure,
+            TketOp::MeasureFree,
+            TketOp::QAlloc,
+            TketOp::QFree,
+            TketOp::Reset,
+        ]
+    }
+
+    /// Whether the gate set allows an operation.
+    ///
+    /// Returns `None` for operations that are not checked.
+    fn allows(&self, op: &OpType) -> Option<bool> {
+        if let Some(tket_op) = op.cast::<TketOp>() {
+            return Some(self.tket_ops.contains(tket_op.opdef_id().as_str()));
+        }
+        let jeff_op = op.cast::<JeffOp>()?;
+        let sig = op.dataflow_signature()?;
+        let qureg_t = qureg_type();
+        let is_quantum = sig
+            .input_types()
+            .iter()
+            .chain(sig.output_types())
+            .any(|ty| *ty == qb_t() || *ty == qureg_t);
+        is_quantum.then(|| self.jeff_ops.iter().any(|rule| rule.allows(&jeff_op)))
+    }
+}
+
+/// A quantum operation not allowed by a [`GateSet`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct GateSetViolation {
+    /// The operation node.
+    pub node: Node,
+    /// The operation name.
+    ///
+    /// Gates of the _jeff_ extension are reported by their gate name.
+    pub op_name: String,
+    /// The name of the function containing the operation, if any.
+    pub function: Option<String>,
+}
+
+impl fmt::Display for GateSetViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Operation {} at {}", self.op_name, self.node)?;
+        if let Some(function) = &self.function {
+            write!(f, " in function '{function}'")?;
+        }
+        write!(f, " is not in the target gate set")
+    }
+}
+
+/// Find the quantum operations of a HUGR that are not allowed by a gate set.
+///
+/// All the `tket.quantum` operations are checked, along with the operations
+/// of the _jeff_ extension acting on qubits or qubit registers. Classical
+/// operations are always allowed.
+///
+/// # Examples
+///
+/// ```
+/// use hugr_jeff::analysis::{GateSet, check_gate_set};
+/// use hugr_jeff::doctest_support::{QUBITS_FUNCTION, qubits};
+/// use hugr_jeff::jeff_to_hugr;
+///
+/// let hugr = jeff_to_hugr(&qubits()).unwrap();
+/// let gate_set: GateSet = serde_json::from_str(
+///     r#"{
+///         "tket_ops": ["QAlloc"],
+///         "jeff_ops": [{ "op": "QGateN", "gates": ["H", "X", "Rx"] }]
+///     }"#,
+/// )
+/// .unwrap();
+///
+/// // The two `CX` gates are not allowed.
+/// let violations = check_gate_set(&hugr, &gate_set);
+/// assert_eq!(violations.len(), 2);
+/// assert!(violations.iter().all(|violation| violation.op_name == "CX"));
+/// assert_eq!(violations[0].function.as_deref(), Some(QUBITS_FUNCTION));
+/// ```
+pub fn check_gate_set(
+    hugr: &impl HugrView<Node = Node>,
+    gate_set: &GateSet,
+) -> Vec<GateSetViolation> {
+    hugr.nodes()
+        .filter(|&node| gate_set.allows(hugr.get_optype(node)) == Some(false))
+        .map(|node| GateSetViolation {
+            node,
+            op_name: op_name(hugr.get_optype(node)),
+            function: containing_function(hugr, node),
+        })
+        .collect()
+}
+
+/// The reported name of an operation.
+fn op_name(op: &OpType) -> String {
+    if let Some(tket_op) = op.cast::<TketOp>() {
+        return tket_op.opdef_id().to_string();
+    }
+    match op.cast::<JeffOp>() {
+        Some(JeffOp::QGate { name, .. }) => name,
+        Some(JeffOp::CondGate { gate, .. }) => format!("CondGate({gate})"),
+        Some(jeff_op) => jeff_op.opdef().opdef_id().to_string(),
+        None => unreachable!("Only tket.quantum and jeff operations are checked"),
+    }
+}
+
+/// The name of the function definition containing a node.
+pub(super) fn containing_function(hugr: &impl HugrView<Node = Node>, node: Node) -> Option<String> {
+    let mut parent = hugr.get_parent(node);
+    while let Some(p) = parent {
+        if let OpType::FuncDefn(defn) = hugr.get_optype(p) {
+            return Some(defn.func_name().to_string());
+        }
+        parent = hugr.get_parent(p);
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::jeff_to_hugr;
+    use crate::test::qubits;
+    use jeff::Jeff;
+    use rstest::rstest;
+
+    /// The gate set of the `qubits` example, possibly without some gates.
+    fn qubits_gate_set(excluded: &[&str]) -> GateSet {
+        let gates = ["H", "X", "CX", "Rx"]
+            .into_iter()
+            .filter(|gate| !excluded.contains(gate))
+            .map(String::from)
+            .collect();
+        GateSet::from_tket_ops([TketOp::QAlloc]).with_jeff_op(JeffOpRule {
+            gates: Some(gates),
+            ..JeffOpRule::new("QGateN")
+        })
+    }
+
+    #[rstest]
+    #[case::compatible(&[], 0)]
+    #[case::no_h(&["H"], 1)]
+    #[case::no_cx(&["CX"], 2)]
+    fn qubits_violations(qubits: Jeff<'static>, #[case] excluded: &[&str], #[case] count: usize) {
+        let hugr = jeff_to_hugr(&qubits).unwrap();
+        let violations = check_gate_set(&hugr, &qubits_gate_set(excluded));
+
+        assert_eq!(violations.len(), count);
+        for violation in violations {
+            assert!(excluded.contains(&violation.op_name.as_str()));
+            assert_eq!(
+                hugr.get_optype(violation.node).cast::<JeffOp>().map(|op| op.opdef()),
+                Some(crate::extension::JeffOpDef::QGate)
+            );
+            assert_eq!(violation.function.as_deref(), Some("Circuit"));
+        }
+    }
+
+    #[rstest]
+    fn presets(qubits: Jeff<'static>) {
+        let hugr = jeff_to_hugr(&qubits).unwrap();
+        // The example only uses opaque gates.
+        for preset in [GateSet::ibm_like(), GateSet::quantinuum_like()] {
+            assert_eq!(check_gate_set(&hugr, &preset).len(), 5);
+        }
+    }
+
+    #[rstest]
+    #[case::compatible(&["H", "X", "CX", "Rx"], 0)]
+    #[case::no_rx(&["H", "X", "CX", "Rz"], 1)]
+    #[case::no_gates(&[], 5)]
+    fn target_violations(qubits: Jeff<'static>, #[case] native: &[&str], #[case] count: usize) {
+        let hugr = jeff_to_hugr(&qubits).unwrap();
+        let target = TargetInfo::new("device", 3).with_native_gates(native);
+        let gate_set = GateSet::from_target(&target);
+        // Native gates with a `tket.quantum` equivalent allow it.
+        assert_eq!(
+            gate_set.tket_ops.contains("CX"),
+            native.contains(&"CX"),
+            "{gate_set:?}"
+        );
+
+        let violations = check_gate_set(&hugr, &gate_set);
+        assert_eq!(violations.len(), count);
+        assert!(violations.iter().all(|violation| !native.contains(&violation.op_name.as_str())));
+    }
+
+    #[rstest]
+    fn serde_roundtrip() {
+        let json = r#"{
+            "tket_ops": ["Rz", "CZ"],
+            "jeff_ops": [{ "op": "QGateN", "max_controls": 1 }]
+        }"#;
+        let gate_set: GateSet = serde_json::from_str(json).unwrap();
+        let expected = GateSet::from_tket_ops([TketOp::Rz, TketOp::CZ]).with_jeff_op(JeffOpRule {
+            max_controls: Some(1),
+            ..JeffOpRule::new("QGateN")
+        });
+        assert_eq!(gate_set, expected);
+
+        let json = serde_json::to_string(&gate_set).unwrap();
+        assert_eq!(serde_json::from_str::<GateSet>(&json).unwrap(), gate_set);
+    }
+}
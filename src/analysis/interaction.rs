@@ -0,0 +1,641 @@
+//! Qubit interaction graph of the _jeff_ functions, computed without translating them.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::{fmt, mem};
+
+use itertools::Itertools;
+use jeff::Jeff;
+use jeff::reader::optype::{self as jeff_optype, ControlFlowOp, OpType as JeffOpType};
+use jeff::reader::value::ValueId;
+use jeff::reader::{Function, FunctionId, Operation, Region};
+use jeff::types::Type as JeffType;
+
+use super::depth::{int_constant, trip_count};
+use crate::{JeffToHugrError, TargetInfo};
+
+/// A qubit of an [`InteractionGraph`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum QubitNode {
+    /// A qubit whose origin is statically known, numbered in allocation
+    /// order.
+    ///
+    /// The qubit arguments of the function come first, followed by the
+    /// qubits allocated by its operations. The elements of a register of
+    /// constant size are numbered consecutively.
+    Qubit(usize),
+    /// A summary of the qubits whose origin is not statically known, such
+    /// as the elements of registers of dynamic size, or the qubits extracted
+    /// at dynamic indices.
+    Dynamic,
+}
+
+impl fmt::Display for QubitNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QubitNode::Qubit(index) => write!(f, "q{index}"),
+            QubitNode::Dynamic => write!(f, "dynamic"),
+        }
+    }
+}
+
+/// The pairs of qubits interacting in the multi-qubit gates of a _jeff_
+/// function.
+///
+/// See [`interaction_graph`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct InteractionGraph {
+    /// Number of qubits numbered by [`QubitNode::Qubit`].
+    pub qubits: usize,
+    /// Whether some qubits are summarized by [`QubitNode::Dynamic`].
+    pub dynamic: bool,
+    /// Number of gates acting on each pair of qubits, with the lowest node
+    /// first.
+    ///
+    /// Interactions between the qubits summarized by [`QubitNode::Dynamic`]
+    /// are counted on the `(Dynamic, Dynamic)` pair.
+    pub edges: BTreeMap<(QubitNode, QubitNode), u64>,
+    /// Whether some gates run in a loop without a statically known trip
+    /// count, or in a recursive call.
+    ///
+    /// The body of such loops is counted once, and recursive calls are not
+    /// counted, so the weights are lower bounds.
+    pub unbounded: bool,
+}
+
+impl InteractionGraph {
+    /// The qubits of the graph.
+    pub fn nodes(&self) -> impl Iterator<Item = QubitNode> + '_ {
+        (0..self.qubits)
+            .map(QubitNode::Qubit)
+            .chain(self.dynamic.then_some(QubitNode::Dynamic))
+    }
+
+    /// The interactions between qubits that are not coupled on a target
+    /// device, see [`TargetInfo::is_coupled`].
+    ///
+    /// The qubits numbered by [`QubitNode::Qubit`] are taken as the physical
+    /// qubits of the device. The interactions of the qubits summarized by
+    /// [`QubitNode::Dynamic`] are not reported, as their placement is not
+    /// known.
+    pub fn uncoupled(&self, target: &TargetInfo) -> Vec<(QubitNode, QubitNode)> {
+        self.edges
+            .keys()
+            .copied()
+            .filter(|&pair| match pair {
+                (QubitNode::Qubit(a), QubitNode::Qubit(b)) => !target.is_coupled(a, b),
+                _ => false,
+            })
+            .collect()
+    }
+
+    /// The graph in the DOT format, with the weights as edge labels.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("graph interactions {\n");
+        for node in self.nodes() {
+            dot.push_str(&format!("    {node};\n"));
+        }
+        for (&(a, b), weight) in &self.edges {
+            dot.push_str(&format!("    {a} -- {b} [weight={weight}, label=\"{weight}\"];\n"));
+        }
+        dot.push('}');
+        dot
+    }
+
+    /// The graph as a JSON object, with a list of node names and a list of
+    /// weighted edges between them.
+    pub fn to_json(&self) -> serde_json::Value {
+        let nodes = self.nodes().map(|node| node.to_string()).collect_vec();
+        let edges = self
+            .edges
+            .iter()
+            .map(|(&(a, b), weight)| {
+                serde_json::json!({
+                    "source": a.to_string(),
+                    "target": b.to_string(),
+                    "weight": weight,
+                })
+            })
+            .collect_vec();
+        serde_json::json!({
+            "nodes": nodes,
+            "edges": edges,
+            "unbounded": self.unbounded,
+        })
+    }
+}
+
+/// Compute the qubit interaction graph of a function of a _jeff_ program.
+///
+/// Each gate acting on several qubits, controls included, adds an
+/// interaction between every pair of its qubits. The qubits are followed
+/// through calls and register accesses at constant indices. Control flow
+/// operations are counted as in [`super::circuit_depth`]: the interactions
+/// of the branches of a switch are summed, those of a `for` loop body are
+/// multiplied by its trip count when the loop bounds are constants, and
+/// those of other loops are counted once, flagging the graph as
+/// [`InteractionGraph::unbounded`].
+///
+/// # Errors
+///
+/// - [`JeffToHugrError::UnknownFunction`] if the first module of the program
+///   has no function named `function`.
+/// - [`JeffToHugrError::MalformedJeffFile`] if the program cannot be read.
+///
+/// # Examples
+///
+/// ```
+/// use hugr_jeff::analysis::{QubitNode, interaction_graph};
+/// use hugr_jeff::doctest_support::{QUBITS_FUNCTION, qubits};
+///
+/// let graph = interaction_graph(&qubits(), QUBITS_FUNCTION).unwrap();
+/// // The two arguments, then the allocated qubit.
+/// assert_eq!(graph.qubits, 3);
+/// // Each argument is entangled with the allocated qubit by a `CX`.
+/// let [q0, q1, q2] = [0, 1, 2].map(QubitNode::Qubit);
+/// assert_eq!(graph.edges.get(&(q0, q2)), Some(&1));
+/// assert_eq!(graph.edges.get(&(q1, q2)), Some(&1));
+/// assert_eq!(graph.edges.len(), 2);
+/// ```
+pub fn interaction_graph(jeff: &Jeff, function: &str) -> Result<InteractionGraph, JeffToHugrError> {
+    let module = jeff.module();
+    let functions = module.functions().collect_vec();
+    let Some(func_id) = functions.iter().position(|func| func.name() == function) else {
+        return Err(JeffToHugrError::UnknownFunction {
+            function: function.to_string(),
+        });
+    };
+    let mut interactions = Interactions::new(&functions);
+    let inputs = functions[func_id]
+        .input_types()
+        .map(|port| Ok(interactions.fresh(port?.ty())))
+        .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+    interactions.call(func_id as FunctionId, inputs, 1)?;
+    Ok(interactions.graph)
+}
+
+/// The qubits carried by a _jeff_ value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Tracked {
+    /// A qubit.
+    Qubit(QubitNode),
+    /// A qubit register, with its elements if its size is statically known.
+    ///
+    /// Extracted elements are `None` until they are inserted back.
+    Register(Option<Vec<Option<QubitNode>>>),
+}
+
+/// State of an interaction graph computation.
+struct Interactions<'a> {
+    /// The functions of the module, indexed by [`FunctionId`].
+    functions: &'a [Function<'a>],
+    /// The graph so far.
+    graph: InteractionGraph,
+    /// The functions being analysed, to detect recursion.
+    in_progress: BTreeSet<FunctionId>,
+    /// The qubits carried by the values of the current function.
+    values: BTreeMap<ValueId, Tracked>,
+    /// The integer constants of the current function, as signed values.
+    constants: BTreeMap<ValueId, i64>,
+}
+
+impl<'a> Interactions<'a> {
+    fn new(functions: &'a [Function<'a>]) -> Self {
+        Self {
+            functions,
+            graph: InteractionGraph::default(),
+            in_progress: BTreeSet::new(),
+            values: BTreeMap::new(),
+            constants: BTreeMap::new(),
+        }
+    }
+
+    /// A newly allocated qubit.
+    fn alloc(&mut self) -> QubitNode {
+        self.graph.qubits += 1;
+        QubitNode::Qubit(self.graph.qubits - 1)
+    }
+
+    /// A qubit whose origin is not known.
+    fn dynamic(&mut self) -> QubitNode {
+        self.graph.dynamic = true;
+        QubitNode::Dynamic
+    }
+
+    /// A newly allocated register of `size` qubits, if known.
+    fn alloc_register(&mut self, size: Option<i64>) -> Tracked {
+        match size.and_then(|size| usize::try_from(size).ok()) {
+            Some(size) => Tracked::Register(Some((0..size).map(|_| Some(self.alloc())).collect())),
+            None => {
+                self.dynamic();
+                Tracked::Register(None)
+            }
+        }
+    }
+
+    /// The qubits of a value of type `ty` coming from outside of the
+    /// analysed operations, or `None` if it carries no qubits.
+    fn fresh(&mut self, ty: JeffType) -> Option<Tracked> {
+        match ty {
+            JeffType::Qubit => Some(Tracked::Qubit(self.alloc())),
+            JeffType::QubitRegister => Some(self.alloc_register(None)),
+            _ => None,
+        }
+    }
+
+    /// The qubits of a value of type `ty` produced by an operation that is
+    /// not followed, or `None` if it carries no qubits.
+    fn unknown(&mut self, ty: JeffType) -> Option<Tracked> {
+        match ty {
+            JeffType::Qubit => Some(Tracked::Qubit(self.dynamic())),
+            JeffType::QubitRegister => Some(self.alloc_register(None)),
+            _ => None,
+        }
+    }
+
+    /// Record a gate acting on `qubits`, run `weight` times.
+    fn interact(&mut self, qubits: &[QubitNode], weight: u64) {
+        if weight == 0 {
+            return;
+        }
+        for (i, &a) in qubits.iter().enumerate() {
+            for &b in &qubits[i + 1..] {
+                let edge = self.graph.edges.entry((a.min(b), a.max(b))).or_default();
+                *edge = edge.saturating_add(weight);
+            }
+        }
+    }
+
+    /// The interactions of a call to a function, run `weight` times, given
+    /// the qubits of its inputs. Returns the qubits of its outputs.
+    fn call(
+        &mut self,
+        func_id: FunctionId,
+        inputs: Vec<Option<Tracked>>,
+        weight: u64,
+    ) -> Result<Vec<Option<Tracked>>, JeffToHugrError> {
+        let functions = self.functions;
+        let Some(func) = functions.get(func_id as usize) else {
+            return Ok(Vec::new());
+        };
+        let body = match func {
+            Function::Definition(def) if !self.in_progress.contains(&func_id) => def.body(),
+            _ => {
+                // A declaration or a recursive call, whose interactions are unknown.
+                if matches!(func, Function::Definition(_)) {
+                    self.graph.unbounded = true;
+                }
+                return func
+                    .output_types()
+                    .map(|port| Ok(self.unknown(port?.ty())))
+                    .collect();
+            }
+        };
+        self.in_progress.insert(func_id);
+        // Value ids are only unique within a function.
+        let caller_values = mem::take(&mut self.values);
+        let caller_constants = mem::take(&mut self.constants);
+        let outputs = self.nested(&body, inputs, weight);
+        self.values = caller_values;
+        self.constants = caller_constants;
+        self.in_progress.remove(&func_id);
+        outputs
+    }
+
+    /// The interactions of a region, run `weight` times, given the qubits of
+    /// its sources. Returns the qubits of its targets.
+    fn nested(
+        &mut self,
+        region: &Region<'_>,
+        inputs: Vec<Option<Tracked>>,
+        weight: u64,
+    ) -> Result<Vec<Option<Tracked>>, JeffToHugrError> {
+        for (source, tracked) in region.sources().zip(inputs) {
+            if let Some(tracked) = tracked {
+                self.values.insert(source?.id(), tracked);
+            }
+        }
+        self.region(region, weight)?;
+        region
+            .targets()
+            .map(|target| Ok(self.values.remove(&target?.id())))
+            .collect()
+    }
+
+    /// The interactions of the operations of a region, run `weight` times.
+    ///
+    /// _jeff_ regions may list an operation before the producers of its
+    /// inputs, so the operations are visited in dependency order. Operations
+    /// in a dependency cycle are not visited.
+    fn region(&mut self, region: &Region<'_>, weight: u64) -> Result<(), JeffToHugrError> {
+        let ops = region.operations().collect_vec();
+        for op in &ops {
+            if let Some(value) = int_constant(&op.op_type())
+                && let Some(output) = op.output(0)
+            {
+                self.constants.insert(output?.id(), value);
+            }
+        }
+        let mut producers = BTreeMap::new();
+        for (idx, op) in ops.iter().enumerate() {
+            for value in op.outputs() {
+                producers.insert(value?.id(), idx);
+            }
+        }
+        let mut consumers = vec![Vec::new(); ops.len()];
+        let mut pending = vec![0usize; ops.len()];
+        for (idx, op) in ops.iter().enumerate() {
+            for value in op.inputs() {
+                if let Some(&producer) = producers.get(&value?.id()) {
+                    consumers[producer].push(idx);
+                    pending[idx] += 1;
+                }
+            }
+        }
+        let mut ready: VecDeque<usize> = (0..ops.len()).filter(|&idx| pending[idx] == 0).collect();
+        while let Some(idx) = ready.pop_front() {
+            self.operation(&ops[idx], weight)?;
+            for &consumer in &consumers[idx] {
+                pending[consumer] -= 1;
+                if pending[consumer] == 0 {
+                    ready.push_back(consumer);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The interactions of an operation, run `weight` times.
+    ///
+    /// Moves the qubits of its inputs to its outputs.
+    fn operation(&mut self, op: &Operation<'_>, weight: u64) -> Result<(), JeffToHugrError> {
+        let input_ids = op
+            .inputs()
+            .map(|value| Ok(value?.id()))
+            .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+        let inputs = input_ids
+            .iter()
+            .map(|id| self.values.remove(id))
+            .collect_vec();
+        let constant = |position: usize| {
+            input_ids
+                .get(position)
+                .and_then(|id| self.constants.get(id).copied())
+        };
+
+        let outputs = match op.op_type() {
+            JeffOpType::QubitOp(qubit_op) => match qubit_op {
+                jeff_optype::QubitOp::Alloc => vec![Some(Tracked::Qubit(self.alloc()))],
+                jeff_optype::QubitOp::Gate(gate) => {
+                    let mut qubits = inputs.into_iter().take(gate.num_qubits()).collect_vec();
+                    for tracked in &mut qubits {
+                        if !matches!(tracked, Some(Tracked::Qubit(_))) {
+                            *tracked = Some(Tracked::Qubit(self.dynamic()));
+                        }
+                    }
+                    let nodes = qubits
+                        .iter()
+                        .filter_map(|tracked| match tracked {
+                            Some(Tracked::Qubit(qubit)) => Some(*qubit),
+                            _ => None,
+                        })
+                        .collect_vec();
+                    self.interact(&nodes, weight);
+                    qubits
+                }
+                jeff_optype::QubitOp::MeasureNd | jeff_optype::QubitOp::Reset => inputs,
+                _ => Vec::new(),
+            },
+            JeffOpType::QubitRegisterOp(reg_op) => match reg_op {
+                jeff_optype::QubitRegisterOp::Alloc => {
+                    vec![Some(self.alloc_register(constant(0)))]
+                }
+                jeff_optype::QubitRegisterOp::Create => {
+                    let qubits = inputs
+                        .into_iter()
+                        .map(|tracked| match tracked {
+                            Some(Tracked::Qubit(qubit)) => Some(qubit),
+                            _ => None,
+                        })
+                        .collect();
+                    vec![Some(Tracked::Register(Some(qubits)))]
+                }
+                jeff_optype::QubitRegisterOp::ExtractIndex => {
+                    // The slot stays empty until the qubit is inserted back, see
+                    // `crate::extension::register_semantics`.
+                    let mut inputs = inputs.into_iter();
+                    let mut register = inputs.next().flatten();
+                    let element = match (&mut register, constant(1)) {
+                        (Some(Tracked::Register(Some(elements))), Some(index)) => {
+                            usize::try_from(index)
+                                .ok()
+                                .and_then(|index| elements.get_mut(index))
+                                .and_then(Option::take)
+                        }
+                        _ => None,
+                    };
+                    let element = element.unwrap_or_else(|| self.dynamic());
+                    vec![register, Some(Tracked::Qubit(element))]
+                }
+                jeff_optype::QubitRegisterOp::InsertIndex => {
+                    let mut inputs = inputs.into_iter();
+                    let mut register = inputs.next().flatten();
+                    let qubit = inputs.next().flatten();
+                    if let (Some(Tracked::Register(Some(elements))), Some(index)) =
+                        (&mut register, constant(2))
+                        && let Some(slot) = usize::try_from(index)
+                            .ok()
+                            .and_then(|index| elements.get_mut(index))
+                    {
+                        *slot = match qubit {
+                            Some(Tracked::Qubit(qubit)) => Some(qubit),
+                            _ => None,
+                        };
+                    }
+                    vec![register]
+                }
+                jeff_optype::QubitRegisterOp::Length => inputs,
+                _ => self.unknown_outputs(op)?,
+            },
+            JeffOpType::FuncOp(func_op) => {
+                self.call(func_op.func_idx as FunctionId, inputs, weight)?
+            }
+            JeffOpType::ControlFlowOp(cf_op) => {
+                self.control_flow(&cf_op, inputs, &input_ids, weight)?
+            }
+            _ => Vec::new(),
+        };
+
+        for (output, tracked) in op.outputs().zip(outputs) {
+            if let Some(tracked) = tracked {
+                self.values.insert(output?.id(), tracked);
+            }
+        }
+        Ok(())
+    }
+
+    /// The qubits of the outputs of an operation that is not followed.
+    fn unknown_outputs(
+        &mut self,
+        op: &Operation<'_>,
+    ) -> Result<Vec<Option<Tracked>>, JeffToHugrError> {
+        op.outputs()
+            .map(|value| Ok(self.unknown(value?.ty())))
+            .collect()
+    }
+
+    /// The interactions of a control flow operation, run `weight` times.
+    /// Returns the qubits of its outputs.
+    ///
+    /// An output keeps the qubits of an input only if every branch, or
+    /// every iteration, moves them to the same position.
+    fn control_flow(
+        &mut self,
+        cf_op: &ControlFlowOp<'_>,
+        inputs: Vec<Option<Tracked>>,
+        input_ids: &[ValueId],
+        weight: u64,
+    ) -> Result<Vec<Option<Tracked>>, JeffToHugrError> {
+        let before = self.graph.edges.values().sum::<u64>();
+        let (outputs, iterations) = match cf_op {
+            ControlFlowOp::Switch(switch_op) => {
+                let args = inputs.get(1..).unwrap_or_default();
+                let mut branches = (0..switch_op.branch_count())
+                    .map(|idx| switch_op.branch(idx))
+                    .collect_vec();
+                branches.extend(switch_op.default_branch());
+                let mut outputs: Option<Vec<Option<Tracked>>> = None;
+                for branch in &branches {
+                    let targets = self.nested(branch, args.to_vec(), weight)?;
+                    outputs = Some(match outputs {
+                        None => targets,
+                        Some(outputs) => agree(outputs, &targets),
+                    });
+                }
+                (outputs.unwrap_or_default(), Some(1))
+            }
+            ControlFlowOp::DoWhile { body, condition }
+            | ControlFlowOp::While { body, condition } => {
+                let targets = self.nested(body, inputs.clone(), weight)?;
+                self.nested(condition, targets.clone(), weight)?;
+                (agree(targets, &inputs), None)
+            }
+            ControlFlowOp::For { region } => {
+                let bounds = input_ids
+                    .iter()
+                    .take(3)
+                    .map(|value| self.constants.get(value).copied())
+                    .collect::<Option<Vec<_>>>();
+                let iterations = match bounds.as_deref() {
+                    Some(&[start, stop, step]) => trip_count(start, stop, step),
+                    _ => None,
+                };
+                let args = inputs.get(3..).unwrap_or_default();
+                let body_weight = weight.saturating_mul(iterations.unwrap_or(1));
+                // The loop counter carries no qubits.
+                let sources = std::iter::once(None).chain(args.iter().cloned()).collect();
+                let targets = self.nested(region, sources, body_weight)?;
+                (agree(targets, args), iterations)
+            }
+        };
+        if iterations.is_none() && self.graph.edges.values().sum::<u64>() > before {
+            self.graph.unbounded = true;
+        }
+        Ok(outputs)
+    }
+}
+
+/// The qubits of `values` that are also carried by `others` in the same
+/// position.
+fn agree(values: Vec<Option<Tracked>>, others: &[Option<Tracked>]) -> Vec<Option<Tracked>> {
+    values
+        .into_iter()
+        .enumerate()
+        .map(|(idx, value)| {
+            value.filter(|value| others.get(idx).is_some_and(|other| other.as_ref() == Some(value)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{catalyst_simple, entangled_qs};
+    use rstest::rstest;
+
+    #[rstest]
+    fn entangled_chain(entangled_qs: Jeff<'static>) {
+        let graph = interaction_graph(&entangled_qs, "qarray").unwrap();
+        assert_eq!(graph.qubits, 5);
+        assert!(!graph.dynamic && !graph.unbounded);
+        let chain = (0..4)
+            .map(|i| ((QubitNode::Qubit(i), QubitNode::Qubit(i + 1)), 1))
+            .collect();
+        assert_eq!(graph.edges, chain);
+    }
+
+    #[rstest]
+    fn uncoupled_pairs(entangled_qs: Jeff<'static>) {
+        let graph = interaction_graph(&entangled_qs, "qarray").unwrap();
+        let line = TargetInfo::new("line-5", 5).with_coupling([(0, 1), (1, 2), (2, 3), (3, 4)]);
+        assert!(graph.uncoupled(&line).is_empty());
+
+        let split = TargetInfo::new("split-5", 5).with_coupling([(0, 1), (1, 2), (3, 4)]);
+        let pair = (QubitNode::Qubit(2), QubitNode::Qubit(3));
+        assert_eq!(graph.uncoupled(&split), [pair]);
+        // The qubits beyond the device are not coupled.
+        assert_eq!(graph.uncoupled(&TargetInfo::new("small", 4)).len(), 1);
+    }
+
+    #[rstest]
+    fn constant_register(catalyst_simple: Jeff<'static>) {
+        // The register is allocated with a constant size, and its single
+        // qubit gates add no interactions.
+        let graph = interaction_graph(&catalyst_simple, "hello").unwrap();
+        assert_eq!(graph.qubits, 5);
+        assert!(!graph.dynamic);
+        assert!(graph.edges.is_empty());
+    }
+
+    #[rstest]
+    fn unknown_function(entangled_qs: Jeff<'static>) {
+        let err = interaction_graph(&entangled_qs, "missing").unwrap_err();
+        assert!(matches!(
+            err,
+            JeffToHugrError::UnknownFunction { function } if function == "missing"
+        ));
+    }
+
+    #[test]
+    fn dynamic_register() {
+        let mut interactions = Interactions::new(&[]);
+        let Tracked::Register(None) = interactions.alloc_register(None) else {
+            panic!("a register of unknown size has no tracked elements");
+        };
+        let qubit = interactions.alloc();
+        let element = interactions.dynamic();
+        interactions.interact(&[qubit, element], 3);
+        interactions.interact(&[element, element], 1);
+        let graph = interactions.graph;
+
+        assert_eq!(graph.nodes().collect_vec(), [QubitNode::Qubit(0), QubitNode::Dynamic]);
+        assert_eq!(
+            graph.to_dot(),
+            "graph interactions {\n    q0;\n    dynamic;\n    \
+             q0 -- dynamic [weight=3, label=\"3\"];\n    \
+             dynamic -- dynamic [weight=1, label=\"1\"];\n}"
+        );
+        assert_eq!(
+            graph.to_json(),
+            serde_json::json!({
+                "nodes": ["q0", "dynamic"],
+                "edges": [
+                    {"source": "q0", "target": "dynamic", "weight": 3},
+                    {"source": "dynamic", "target": "dynamic", "weight": 1},
+                ],
+                "unbounded": false,
+            })
+        );
+    }
+}
@@ -0,0 +1,482 @@
+//! Consistency of the value types of a _jeff_ program.
+
+use std::fmt;
+
+use itertools::Itertools;
+use jeff::Jeff;
+use jeff::reader::optype::{
+    ControlFlowOp, FloatArrayOp, IntArrayOp, OpType as JeffOpType, QubitOp,
+};
+use jeff::reader::{Function, Module, Operation, Region};
+use jeff::types::Type as JeffType;
+use serde::Serialize;
+
+use crate::JeffToHugrError;
+use crate::optype::{gate_param_count, has_param_array};
+
+/// A value whose type differs from the type its operation expects.
+///
+/// See [`check_types`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+pub struct TypeCheckError {
+    /// The name of the function containing the operation.
+    pub function: String,
+    /// The position of the operation, counting from the start of its
+    /// function.
+    pub op_index: usize,
+    /// The expected type, or `nothing` if the value is not expected.
+    pub expected: String,
+    /// The type of the value, or `nothing` if the value is missing.
+    pub found: String,
+    /// The position of the value in the operation.
+    pub position: TypePosition,
+}
+
+/// The position of a value checked by [`check_types`].
+///
+/// The regions of an operation are numbered in order: the branches of a
+/// `Switch` followed by its default branch, the body of a loop followed by
+/// its condition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum TypePosition {
+    /// An input of the operation.
+    Input(usize),
+    /// An output of the operation.
+    Output(usize),
+    /// A source of a region of the operation.
+    Source {
+        /// The index of the region.
+        region: usize,
+        /// The index of the source.
+        index: usize,
+    },
+    /// A target of a region of the operation.
+    Target {
+        /// The index of the region.
+        region: usize,
+        /// The index of the target.
+        index: usize,
+    },
+}
+
+impl fmt::Display for TypePosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Input(index) => write!(f, "input {index}"),
+            Self::Output(index) => write!(f, "output {index}"),
+            Self::Source { region, index } => write!(f, "source {index} of region {region}"),
+            Self::Target { region, index } => write!(f, "target {index} of region {region}"),
+        }
+    }
+}
+
+impl fmt::Display for TypeCheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Operation {} in function '{}' has {} of type {}, expected {}",
+            self.op_index, self.function, self.position, self.found, self.expected
+        )
+    }
+}
+
+/// Check that the values of a _jeff_ program have the types their
+/// operations expect.
+///
+/// The following operations are checked, and all the inconsistencies are
+/// reported:
+/// - Gates act on `qubit` values, and take `float` parameters, as many as
+///   their translation expects, or a single `float` array.
+/// - Array accesses read and write elements of the width of the array, at
+///   an `int(32)` index.
+/// - Calls pass and return the values of the signature of the called
+///   function.
+/// - The regions of the control flow operations have the sources and
+///   targets matching the inputs and outputs of the operation.
+///
+/// The operations are identified by their position from the start of their
+/// function, counting the operations of the nested regions, as in
+/// [`crate::JeffHugrMap::node_origin`].
+///
+/// The check can be run before each translation with
+/// [`crate::JeffToHugrConfig::typecheck`].
+///
+/// # Errors
+///
+/// - [`JeffToHugrError::MalformedJeffFile`] if the program cannot be read.
+pub fn check_types(jeff: &Jeff) -> Result<Vec<TypeCheckError>, JeffToHugrError> {
+    module_type_errors(&jeff.module())
+}
+
+/// The type errors of a module, see [`check_types`].
+pub(crate) fn module_type_errors(
+    module: &Module<'_>,
+) -> Result<Vec<TypeCheckError>, JeffToHugrError> {
+    let functions = module.functions().collect_vec();
+    let signatures = functions
+        .iter()
+        .map(|func| {
+            let inputs = func
+                .input_types()
+                .map(|port| Ok(port?.ty()))
+                .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+            let outputs = func
+                .output_types()
+                .map(|port| Ok(port?.ty()))
+                .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+            Ok((inputs, outputs))
+        })
+        .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+
+    let mut errors = Vec::new();
+    for func in &functions {
+        let Function::Definition(def) = func else {
+            continue;
+        };
+        let mut checker = TypeChecker {
+            signatures: &signatures,
+            function: func.name(),
+            op_count: 0,
+            errors: &mut errors,
+        };
+        checker.region(&def.body())?;
+    }
+    Ok(errors)
+}
+
+/// A type expected for a value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expected {
+    /// A value of this exact type.
+    Type(JeffType),
+    /// An integer of any width.
+    Int,
+    /// A float of any precision.
+    Float,
+}
+
+impl Expected {
+    fn matches(&self, ty: &JeffType) -> bool {
+        match self {
+            Self::Type(expected) => expected == ty,
+            Self::Int => matches!(ty, JeffType::Int { .. }),
+            Self::Float => matches!(ty, JeffType::Float { .. }),
+        }
+    }
+}
+
+impl From<JeffType> for Expected {
+    fn from(ty: JeffType) -> Self {
+        Self::Type(ty)
+    }
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Type(ty) => write!(f, "{ty}"),
+            Self::Int => write!(f, "int"),
+            Self::Float => write!(f, "float"),
+        }
+    }
+}
+
+/// The type of the `int(32)` array indices.
+const INDEX: Expected = Expected::Type(JeffType::Int { bits: 32 });
+
+/// The type reported for a missing or unexpected value.
+const NOTHING: &str = "nothing";
+
+/// State of a type check over a function.
+struct TypeChecker<'a> {
+    /// The input and output types of the functions of the module, in order.
+    signatures: &'a [(Vec<JeffType>, Vec<JeffType>)],
+    /// The name of the checked function.
+    function: &'a str,
+    /// The number of operations visited in the function.
+    op_count: usize,
+    errors: &'a mut Vec<TypeCheckError>,
+}
+
+impl TypeChecker<'_> {
+    /// Check the operations of a region and of its nested regions.
+    fn region(&mut self, region: &Region<'_>) -> Result<(), JeffToHugrError> {
+        for op in region.operations() {
+            let op_index = self.op_count;
+            self.op_count += 1;
+            self.operation(&op, op_index)?;
+        }
+        Ok(())
+    }
+
+    /// Check the values of an operation.
+    fn operation(&mut self, op: &Operation<'_>, op_index: usize) -> Result<(), JeffToHugrError> {
+        let inputs = op
+            .inputs()
+            .map(|value| Ok(value?.ty()))
+            .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+        let outputs = op
+            .outputs()
+            .map(|value| Ok(value?.ty()))
+            .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+        let op_type = op.op_type();
+        match &op_type {
+            JeffOpType::QubitOp(QubitOp::Gate(gate)) => {
+                let qubits = vec![Expected::Type(JeffType::Qubit); gate.num_qubits()];
+                let mut expected_inputs = qubits.clone();
+                match has_param_array(*gate, op)? {
+                    true => expected_inputs.push(inputs[gate.num_qubits()].clone().into()),
+                    false => match gate_param_count(*gate) {
+                        Some(params) => expected_inputs.extend(vec![Expected::Float; params]),
+                        // Only the qubits can be checked.
+                        None => expected_inputs
+                            .extend(inputs.iter().skip(gate.num_qubits()).cloned().map_into()),
+                    },
+                }
+                self.compare(op_index, TypePosition::Input, &expected_inputs, &inputs);
+                self.compare(op_index, TypePosition::Output, &qubits, &outputs);
+            }
+            JeffOpType::IntArrayOp(array_op @ (IntArrayOp::GetIndex | IntArrayOp::SetIndex)) => {
+                let Some(&JeffType::IntArray { bits }) = inputs.first() else {
+                    self.mismatch(op_index, TypePosition::Input(0), "int array", &inputs);
+                    return Ok(());
+                };
+                let array = Expected::Type(JeffType::IntArray { bits });
+                let element = Expected::Type(JeffType::Int { bits });
+                let (expected_inputs, expected_outputs) = match array_op {
+                    IntArrayOp::GetIndex => (vec![array, INDEX], vec![element]),
+                    _ => (vec![array.clone(), INDEX, element], vec![array]),
+                };
+                self.compare(op_index, TypePosition::Input, &expected_inputs, &inputs);
+                self.compare(op_index, TypePosition::Output, &expected_outputs, &outputs);
+            }
+            JeffOpType::FloatArrayOp(FloatArrayOp::GetIndex) => {
+                let Some(&JeffType::FloatArray { precision }) = inputs.first() else {
+                    self.mismatch(op_index, TypePosition::Input(0), "float array", &inputs);
+                    return Ok(());
+                };
+                let expected_inputs = [Expected::Type(JeffType::FloatArray { precision }), INDEX];
+                let expected_outputs = [Expected::Type(JeffType::Float { precision })];
+                self.compare(op_index, TypePosition::Input, &expected_inputs, &inputs);
+                self.compare(op_index, TypePosition::Output, &expected_outputs, &outputs);
+            }
+            JeffOpType::FuncOp(call) => {
+                let Some((callee_inputs, callee_outputs)) =
+                    self.signatures.get(call.func_idx as usize)
+                else {
+                    return Ok(());
+                };
+                let expected_inputs = callee_inputs.iter().cloned().map_into().collect_vec();
+                let expected_outputs = callee_outputs.iter().cloned().map_into().collect_vec();
+                self.compare(op_index, TypePosition::Input, &expected_inputs, &inputs);
+                self.compare(op_index, TypePosition::Output, &expected_outputs, &outputs);
+            }
+            JeffOpType::ControlFlowOp(cf_op) => {
+                self.control_flow(cf_op, op_index, &inputs, &outputs)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Check the regions of a control flow operation against its inputs and
+    /// outputs, then the operations of the regions.
+    fn control_flow(
+        &mut self,
+        cf_op: &ControlFlowOp<'_>,
+        op_index: usize,
+        inputs: &[JeffType],
+        outputs: &[JeffType],
+    ) -> Result<(), JeffToHugrError> {
+        let exact = |types: &[JeffType]| types.iter().cloned().map_into().collect_vec();
+        // The regions with their expected sources and targets.
+        let regions: Vec<(Region<'_>, Vec<Expected>, Vec<Expected>)> = match cf_op {
+            ControlFlowOp::Switch(switch_op) => {
+                let selector = inputs.get(..1).unwrap_or_default();
+                self.compare(op_index, TypePosition::Input, &[Expected::Int], selector);
+                let args = exact(inputs.get(1..).unwrap_or_default());
+                (0..switch_op.branch_count())
+                    .map(|idx| switch_op.branch(idx))
+                    .chain(switch_op.default_branch())
+                    .map(|branch| (branch, args.clone(), exact(outputs)))
+                    .collect()
+            }
+            ControlFlowOp::DoWhile { body, condition }
+            | ControlFlowOp::While { body, condition } => {
+                let state = exact(inputs);
+                self.compare(op_index, TypePosition::Output, &state, outputs);
+                // The condition may return the state after its flag.
+                let flag = Expected::Type(JeffType::Int { bits: 1 });
+                let condition_targets = match condition.targets().count() {
+                    1 => vec![flag],
+                    _ => std::iter::once(flag).chain(state.clone()).collect(),
+                };
+                vec![
+                    (*body, state.clone(), state.clone()),
+                    (*condition, state, condition_targets),
+                ]
+            }
+            ControlFlowOp::For { region } => {
+                // The start, stop and step values have the type of the counter.
+                let counter = inputs
+                    .first()
+                    .cloned()
+                    .map_or(Expected::Int, Expected::from);
+                let state = exact(inputs.get(3..).unwrap_or_default());
+                let expected_inputs = vec![counter.clone(); 3]
+                    .into_iter()
+                    .chain(state.clone())
+                    .collect_vec();
+                self.compare(op_index, TypePosition::Input, &expected_inputs, inputs);
+                self.compare(op_index, TypePosition::Output, &state, outputs);
+                let sources = std::iter::once(counter).chain(state.clone()).collect();
+                vec![(*region, sources, state)]
+            }
+        };
+
+        for (idx, (region, sources, targets)) in regions.iter().enumerate() {
+            let source_types = region
+                .sources()
+                .map(|value| Ok(value?.ty()))
+                .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+            let target_types = region
+                .targets()
+                .map(|value| Ok(value?.ty()))
+                .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+            let source = |index| TypePosition::Source { region: idx, index };
+            let target = |index| TypePosition::Target { region: idx, index };
+            self.compare(op_index, source, sources, &source_types);
+            self.compare(op_index, target, targets, &target_types);
+        }
+        for (region, _, _) in &regions {
+            self.region(region)?;
+        }
+        Ok(())
+    }
+
+    /// Report the values whose type differs from the expected one, and the
+    /// missing and unexpected values.
+    fn compare(
+        &mut self,
+        op_index: usize,
+        position: impl Fn(usize) -> TypePosition,
+        expected: &[Expected],
+        found: &[JeffType],
+    ) {
+        for index in 0..expected.len().max(found.len()) {
+            let (expected, found) = (expected.get(index), found.get(index));
+            if let (Some(expected), Some(found)) = (expected, found)
+                && expected.matches(found)
+            {
+                continue;
+            }
+            self.errors.push(TypeCheckError {
+                function: self.function.to_string(),
+                op_index,
+                expected: expected.map_or_else(|| NOTHING.to_string(), ToString::to_string),
+                found: found.map_or_else(|| NOTHING.to_string(), ToString::to_string),
+                position: position(index),
+            });
+        }
+    }
+
+    /// Report a value that is not of the expected kind.
+    fn mismatch(
+        &mut self,
+        op_index: usize,
+        position: TypePosition,
+        expected: &str,
+        found: &[JeffType],
+    ) {
+        self.errors.push(TypeCheckError {
+            function: self.function.to_string(),
+            op_index,
+            expected: expected.to_string(),
+            found: found
+                .first()
+                .map_or_else(|| NOTHING.to_string(), ToString::to_string),
+            position,
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{catalyst_simple, catalyst_tket_opt, entangled_qs, qubits, type_errors};
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::qubits(qubits())]
+    #[case::catalyst_simple(catalyst_simple())]
+    #[case::catalyst_tket_opt(catalyst_tket_opt())]
+    fn consistent_programs(#[case] jeff: Jeff<'static>) {
+        assert_eq!(check_types(&jeff).unwrap(), vec![]);
+    }
+
+    /// The measurement results are written as `int1` values into an array of
+    /// `int8` elements, at `int8` indices.
+    #[rstest]
+    fn array_accesses(entangled_qs: Jeff<'static>) {
+        let errors = check_types(&entangled_qs).unwrap();
+        let positions = errors
+            .iter()
+            .map(|error| (error.op_index, error.position))
+            .collect_vec();
+        let expected = [13, 16, 19, 22, 25]
+            .into_iter()
+            .flat_map(|op_index| {
+                [
+                    (op_index, TypePosition::Input(1)),
+                    (op_index, TypePosition::Input(2)),
+                ]
+            })
+            .collect_vec();
+        assert_eq!(positions, expected);
+        assert_eq!(errors[0].function, "qarray");
+        assert_eq!(errors[0].expected, JeffType::Int { bits: 32 }.to_string());
+        assert_eq!(errors[0].found, JeffType::Int { bits: 8 }.to_string());
+        assert_eq!(errors[1].expected, JeffType::Int { bits: 8 }.to_string());
+        assert_eq!(errors[1].found, JeffType::Int { bits: 1 }.to_string());
+    }
+
+    #[rstest]
+    fn all_errors_reported(type_errors: Jeff<'static>) {
+        let error = |op_index, position, expected: &str, found: JeffType| TypeCheckError {
+            function: "main".to_string(),
+            op_index,
+            expected: expected.to_string(),
+            found: found.to_string(),
+            position,
+        };
+        let qubit = JeffType::Qubit.to_string();
+        let int8 = JeffType::Int { bits: 8 }.to_string();
+        const INT32: JeffType = JeffType::Int { bits: 32 };
+        assert_eq!(
+            check_types(&type_errors).unwrap(),
+            vec![
+                error(2, TypePosition::Input(0), &qubit, INT32),
+                error(6, TypePosition::Input(2), &int8, INT32),
+                error(7, TypePosition::Input(1), NOTHING, JeffType::Qubit),
+            ]
+        );
+    }
+
+    #[test]
+    fn error_display() {
+        let error = TypeCheckError {
+            function: "main".to_string(),
+            op_index: 3,
+            expected: "qubit".to_string(),
+            found: NOTHING.to_string(),
+            position: TypePosition::Output(1),
+        };
+        assert_eq!(
+            error.to_string(),
+            "Operation 3 in function 'main' has output 1 of type nothing, expected qubit"
+        );
+    }
+}
@@ -0,0 +1,748 @@
+//! Queries on the _jeff_ constructs supported by the translation.
+//!
+//! Frontends emitting _jeff_ can check whether an operation or a type is
+//! translated before emitting it, with [`supports_jeff_op`] and
+//! [`supports_jeff_type`], and pick another encoding for the unsupported
+//! ones. [`capability_manifest`] lists the whole support matrix, e.g. to be
+//! vendored by a frontend and checked at its build time.
+//!
+//! Consumers of the translated HUGRs can load the extensions listed by
+//! [`conversion_extension_allowlist`], which the translation never exceeds.
+//!
+//! Gates are classified with the same rules as in the translation. The
+//! answers are for the default [`crate::JeffToHugrConfig`], without
+//! conversion hooks nor type codecs.
+
+use hugr::extension::ExtensionId;
+use hugr::extension::prelude::PRELUDE_ID;
+use hugr::std_extensions::arithmetic::{conversions, float_ops, float_types, int_ops, int_types};
+use hugr::std_extensions::collections::{borrow_array, list};
+use hugr::std_extensions::logic;
+use hugr::{HugrView, Node};
+use itertools::Itertools;
+use jeff::types::{FloatPrecision, Type as JeffType};
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use tket::extension::TKET_EXTENSION_ID;
+use tket::extension::rotation::ROTATION_EXTENSION_ID;
+
+use crate::extension::{JEFF_EXTENSION_ID, JeffOpDef};
+use crate::optype::catalog::CATALOG;
+use crate::optype::{WellKnownTranslation, classify_gate, precision_bits};
+
+/// How the translation supports a _jeff_ construct.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Support {
+    /// The construct is translated to its HUGR equivalent.
+    Full,
+    /// The construct is translated to a more general encoding.
+    Fallback {
+        /// The encoding used instead, e.g. the [`JeffOpDef::QGate`] of the
+        /// gates without `tket.quantum` equivalent.
+        to: &'static str,
+    },
+    /// The translation fails on the construct.
+    Unsupported,
+}
+
+/// The fallback of the gates translated as opaque [`JeffOpDef::QGate`]s.
+const QGATE: Support = Support::Fallback { to: "QGate" };
+
+/// The fallback of the 32-bit floats, widened to 64 bits.
+const FLOAT64: Support = Support::Fallback { to: "float64" };
+
+/// The fallback of the integers whose width is not a power of two, widened
+/// to the next power of two.
+const WIDER_INT: Support = Support::Fallback { to: "int" };
+
+/// The kind of a _jeff_ operation, as in the `jeff` operation types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum OpCategory {
+    /// Operations on single qubits, other than gates.
+    Qubit,
+    /// Quantum gates, named after their well-known gate or custom name.
+    Gate,
+    /// Operations on qubit registers.
+    QubitRegister,
+    /// Integer operations.
+    Int,
+    /// Integer array operations.
+    IntArray,
+    /// Float operations.
+    Float,
+    /// Float array operations.
+    FloatArray,
+    /// Function calls.
+    Function,
+    /// Structured control flow.
+    ControlFlow,
+}
+
+/// A description of a _jeff_ operation, see [`supports_jeff_op`].
+///
+/// Queries can be loaded from JSON, e.g.
+///
+/// ```json
+/// { "category": "gate", "name": "X", "operands": ["qubit", "qubit"], "controls": 1 }
+/// ```
+///
+/// Types are named `qubit`, `qureg`, `int<bits>`, `float<bits>`,
+/// `int<bits>_array` and `float<bits>_array`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct OpKindQuery {
+    /// The kind of the operation.
+    pub category: OpCategory,
+    /// The name of the operation variant, e.g. `ExtractIndex`, or the name
+    /// of the gate.
+    pub name: String,
+    /// The types of the operation inputs, if known.
+    #[serde(default, with = "type_names")]
+    pub operands: Vec<JeffType>,
+    /// Whether the gate is adjoint.
+    #[serde(default)]
+    pub adjoint: bool,
+    /// The number of control qubits of the gate.
+    #[serde(default)]
+    pub controls: usize,
+    /// The power the gate is raised to.
+    #[serde(default = "default_power")]
+    pub power: usize,
+}
+
+fn default_power() -> usize {
+    1
+}
+
+impl OpKindQuery {
+    /// A query for an operation without modifiers nor known operand types.
+    pub fn new(category: OpCategory, name: impl ToString) -> Self {
+        Self {
+            category,
+            name: name.to_string(),
+            operands: Vec::new(),
+            adjoint: false,
+            controls: 0,
+            power: default_power(),
+        }
+    }
+
+    /// Set [`OpKindQuery::operands`].
+    pub fn with_operands(mut self, operands: impl IntoIterator<Item = JeffType>) -> Self {
+        self.operands = operands.into_iter().collect();
+        self
+    }
+
+    /// Set [`OpKindQuery::adjoint`].
+    pub fn with_adjoint(mut self, adjoint: bool) -> Self {
+        self.adjoint = adjoint;
+        self
+    }
+
+    /// Set [`OpKindQuery::controls`].
+    pub fn with_controls(mut self, controls: usize) -> Self {
+        self.controls = controls;
+        self
+    }
+
+    /// Set [`OpKindQuery::power`].
+    pub fn with_power(mut self, power: usize) -> Self {
+        self.power = power;
+        self
+    }
+}
+
+/// The support of an operation, as listed in a [`CapabilityManifest`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct OpSupport {
+    /// The operation.
+    #[serde(flatten)]
+    pub query: OpKindQuery,
+    /// Its support by the translation.
+    pub support: Support,
+}
+
+/// The support of a type, as listed in a [`CapabilityManifest`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct TypeSupport {
+    /// The type.
+    #[serde(rename = "type", serialize_with = "type_names::serialize_one")]
+    pub jeff_type: JeffType,
+    /// Its support by the translation.
+    pub support: Support,
+}
+
+/// The support matrix of the translation, see [`capability_manifest`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct CapabilityManifest {
+    /// The version of the `hugr-jeff` crate.
+    pub version: &'static str,
+    /// The non-gate operations, and the well-known gates alone, adjoint and
+    /// controlled once. Other gates fall back to [`JeffOpDef::QGate`].
+    pub ops: Vec<OpSupport>,
+    /// The types, with the usual integer widths.
+    pub types: Vec<TypeSupport>,
+    /// The operations of the _jeff_ HUGR extension, emitted by the fallbacks.
+    pub extension_ops: Vec<JeffOpDef>,
+}
+
+/// The operations other than gates, by category and variant name.
+const OP_SUPPORT: &[(OpCategory, &[(&str, Support)])] = {
+    use OpCategory::*;
+    use Support::{Full, Unsupported};
+
+    &[
+        (
+            Qubit,
+            &[
+                ("Alloc", Full),
+                ("Free", Full),
+                ("FreeZero", Full),
+                ("Measure", Full),
+                ("MeasureNd", Full),
+                ("Reset", Full),
+            ],
+        ),
+        (
+            QubitRegister,
+            &[
+                ("Alloc", Full),
+                ("Free", Full),
+                ("FreeZero", Full),
+                ("ExtractIndex", Full),
+                ("InsertIndex", Full),
+                ("ExtractSlice", Full),
+                ("InsertSlice", Full),
+                ("Length", Full),
+                ("Split", Full),
+                ("Join", Full),
+                ("Measure", Full),
+                ("MeasureNd", Full),
+                ("Create", Full),
+            ],
+        ),
+        (
+            Int,
+            &[
+                ("Const1", Full),
+                ("Const8", Full),
+                ("Const16", Full),
+                ("Const32", Full),
+                ("Const64", Full),
+                ("Add", Full),
+                ("Sub", Full),
+                ("Mul", Full),
+                ("DivS", Full),
+                ("DivU", Full),
+                ("RemS", Full),
+                ("RemU", Full),
+                ("And", Full),
+                ("Or", Full),
+                ("Xor", Full),
+                ("Not", Full),
+                ("Shl", Full),
+                ("ShrS", Full),
+                ("ShrU", Full),
+                ("Eq", Full),
+                ("LtS", Full),
+                ("LteS", Full),
+                ("LtU", Full),
+                ("LteU", Full),
+            ],
+        ),
+        (
+            IntArray,
+            &[
+                ("Create", Full),
+                ("GetIndex", Full),
+                ("SetIndex", Full),
+                ("Zero", Full),
+                ("ConstArray1", Full),
+                ("ConstArray8", Full),
+                ("ConstArray16", Full),
+                ("ConstArray32", Full),
+                ("ConstArray64", Full),
+            ],
+        ),
+        (
+            Float,
+            &[
+                ("Const32", FLOAT64),
+                ("Const64", Full),
+                ("Add", Full),
+                ("Sub", Full),
+                ("Mul", Full),
+                ("Pow", Full),
+                ("Eq", Full),
+                ("Lt", Full),
+                ("Lte", Full),
+                ("Abs", Full),
+                ("Ceil", Full),
+                ("Floor", Full),
+                ("Exp", Full),
+                ("Max", Full),
+                ("Min", Full),
+                ("Sqrt", Full),
+                ("IsNan", Unsupported),
+                ("IsInf", Unsupported),
+                ("Log", Full),
+                ("Sin", Full),
+                ("Cos", Full),
+                ("Tan", Full),
+                ("Asin", Full),
+                ("Acos", Full),
+                ("Atan", Full),
+                ("Atan2", Full),
+                ("Sinh", Full),
+                ("Cosh", Full),
+                ("Tanh", Full),
+                ("Asinh", Full),
+                ("Acosh", Full),
+                ("Atanh", Full),
+            ],
+        ),
+        (
+            FloatArray,
+            &[
+                ("Create", Full),
+                ("GetIndex", Full),
+                ("ConstArray32", Full),
+                ("ConstArray64", Full),
+            ],
+        ),
+        (Function, &[("Call", Full)]),
+        (
+            ControlFlow,
+            &[
+                ("Switch", Full),
+                ("For", Full),
+                ("While", Full),
+                ("DoWhile", Full),
+            ],
+        ),
+    ]
+};
+
+/// Whether the translation supports a _jeff_ operation.
+///
+/// Well-known gates are classified with the translation rules. Gates with
+/// another name are translated as opaque [`JeffOpDef::QGate`]s. Other
+/// operations are unsupported unless their variant name is known.
+///
+/// Float operations without translation, e.g. `IsNan`, are listed as
+/// [`Support::Unsupported`] in the [`capability_manifest`].
+///
+/// # Examples
+///
+/// ```
+/// use hugr_jeff::capabilities::{OpCategory, OpKindQuery, Support, supports_jeff_op};
+///
+/// let cnot = OpKindQuery::new(OpCategory::Gate, "X").with_controls(1);
+/// assert_eq!(supports_jeff_op(&cnot), Support::Full);
+///
+/// let is_nan = OpKindQuery::new(OpCategory::Float, "IsNan");
+/// assert_eq!(supports_jeff_op(&is_nan), Support::Unsupported);
+/// ```
+pub fn supports_jeff_op(kind: &OpKindQuery) -> Support {
+    if kind.category == OpCategory::Gate {
+        return gate_support(kind);
+    }
+    OP_SUPPORT
+        .iter()
+        .filter(|(category, _)| *category == kind.category)
+        .flat_map(|(_, ops)| ops.iter())
+        .find(|(name, _)| *name == kind.name)
+        .map_or(Support::Unsupported, |(_, support)| *support)
+}
+
+/// The support of a gate, see [`supports_jeff_op`].
+///
+/// Well-known gates acting on an unexpected number of qubits are opaque,
+/// as in [`crate::optype`].
+fn gate_support(kind: &OpKindQuery) -> Support {
+    let Some(info) = CATALOG
+        .iter()
+        .find(|info| info.gate.to_string() == kind.name)
+    else {
+        return QGATE;
+    };
+    let qubits = kind
+        .operands
+        .iter()
+        .filter(|ty| matches!(ty, JeffType::Qubit))
+        .count();
+    if !kind.operands.is_empty() && qubits != info.arity + kind.controls {
+        return QGATE;
+    }
+    match classify_gate(info.gate, kind.adjoint, kind.controls, kind.power) {
+        WellKnownTranslation::Opaque => QGATE,
+        _ => Support::Full,
+    }
+}
+
+/// Whether the translation supports a _jeff_ type.
+///
+/// # Examples
+///
+/// ```
+/// use hugr_jeff::capabilities::{Support, supports_jeff_type};
+/// use hugr_jeff::{FloatPrecision, JeffType};
+///
+/// assert_eq!(supports_jeff_type(&JeffType::Qubit), Support::Full);
+/// let f32 = JeffType::Float { precision: FloatPrecision::Float32 };
+/// assert_eq!(supports_jeff_type(&f32), Support::Fallback { to: "float64" });
+/// ```
+pub fn supports_jeff_type(jeff_type: &JeffType) -> Support {
+    match jeff_type {
+        JeffType::Int { bits } if bits.is_power_of_two() => Support::Full,
+        JeffType::Int { .. } => WIDER_INT,
+        JeffType::Float {
+            precision: FloatPrecision::Float32,
+        } => FLOAT64,
+        JeffType::Qubit
+        | JeffType::Float { .. }
+        | JeffType::QubitRegister
+        | JeffType::IntArray { .. }
+        | JeffType::FloatArray { .. } => Support::Full,
+    }
+}
+
+/// The support matrix of the translation.
+///
+/// # Examples
+///
+/// ```
+/// use hugr_jeff::capabilities::capability_manifest;
+///
+/// let manifest = capability_manifest();
+/// let json = serde_json::to_value(&manifest).unwrap();
+/// assert_eq!(json["version"], env!("CARGO_PKG_VERSION"));
+/// ```
+pub fn capability_manifest() -> CapabilityManifest {
+    let ops = OP_SUPPORT
+        .iter()
+        .flat_map(|&(category, ops)| ops.iter().map(move |(name, _)| (category, name)))
+        .map(|(category, name)| OpKindQuery::new(category, name))
+        .chain(CATALOG.iter().flat_map(|info| {
+            let gate = OpKindQuery::new(OpCategory::Gate, info.gate);
+            [
+                gate.clone(),
+                gate.clone().with_adjoint(true),
+                gate.with_controls(1),
+            ]
+        }))
+        .map(|query| OpSupport {
+            support: supports_jeff_op(&query),
+            query,
+        })
+        .collect();
+    let precisions = [FloatPrecision::Float32, FloatPrecision::Float64];
+    let types = [JeffType::Qubit, JeffType::QubitRegister]
+        .into_iter()
+        .chain([1, 8, 16, 32, 64].map(|bits| JeffType::Int { bits }))
+        .chain([1, 8, 16, 32, 64].map(|bits| JeffType::IntArray { bits }))
+        .chain(precisions.map(|precision| JeffType::Float { precision }))
+        .chain(precisions.map(|precision| JeffType::FloatArray { precision }))
+        .map(|jeff_type| TypeSupport {
+            support: supports_jeff_type(&jeff_type),
+            jeff_type,
+        })
+        .collect();
+    CapabilityManifest {
+        version: env!("CARGO_PKG_VERSION"),
+        ops,
+        types,
+        extension_ops: JeffOpDef::iter().collect(),
+    }
+}
+
+/// The extensions referenced by the translation, see
+/// [`conversion_extension_allowlist`].
+static CONVERSION_EXTENSIONS: [ExtensionId; 12] = [
+    PRELUDE_ID,
+    int_types::EXTENSION_ID,
+    float_types::EXTENSION_ID,
+    int_ops::EXTENSION_ID,
+    float_ops::EXTENSION_ID,
+    conversions::EXTENSION_ID,
+    logic::EXTENSION_ID,
+    TKET_EXTENSION_ID,
+    ROTATION_EXTENSION_ID,
+    JEFF_EXTENSION_ID,
+    list::EXTENSION_ID,
+    borrow_array::EXTENSION_ID,
+];
+
+/// The extensions that the operations, types and constants of a translated
+/// HUGR may reference.
+///
+/// - `prelude`, `arithmetic.int.types` and `arithmetic.float.types`, for
+///   the qubits, booleans, integers and floats, and the runtime panics.
+/// - `arithmetic.int`, `arithmetic.float`, `arithmetic.conversions` and
+///   `logic`, for the classical operations.
+/// - `tket.quantum` and `tket.rotation`, for the well-known gates and their
+///   angles.
+/// - `jeff`, for the registers, the arrays and the other operations without
+///   a standard equivalent, see [`JeffOpDef`].
+/// - `collections.list`, for the bit arrays, only with
+///   [`crate::BitArrays::BoolCollection`].
+/// - `collections.borrow_arr`, for the qubit registers, only with
+///   [`crate::QuregLowering::StaticArray`].
+///
+/// The operations and types emitted by the conversion hooks and the type
+/// codecs are not accounted for. Emitting anything from another extension
+/// breaks the consumers loading a fixed set of extensions, so extending the
+/// list must be called out in the changelog. The translation checks its
+/// output against the list with [`crate::JeffToHugrConfig::check_extensions`].
+///
+/// # Examples
+///
+/// ```
+/// use hugr_jeff::capabilities::conversion_extension_allowlist;
+/// use hugr_jeff::extension::JEFF_EXTENSION_ID;
+///
+/// assert!(conversion_extension_allowlist().contains(&JEFF_EXTENSION_ID));
+/// ```
+pub fn conversion_extension_allowlist() -> &'static [ExtensionId] {
+    &CONVERSION_EXTENSIONS
+}
+
+/// The first extension outside of the [`conversion_extension_allowlist`]
+/// referenced by a HUGR, with the node referencing it.
+///
+/// The operation of each node is checked, along with the types of its ports
+/// and of its constant value.
+pub(crate) fn disallowed_extension(
+    hugr: &impl HugrView<Node = Node>,
+) -> Option<(ExtensionId, Node)> {
+    let allowlist = conversion_extension_allowlist();
+    hugr.nodes().find_map(|node| {
+        let used = hugr
+            .get_optype(node)
+            .used_extensions()
+            .expect("The extensions of the translated operations are alive");
+        used.ids()
+            .find(|ext| !allowlist.contains(ext))
+            .map(|ext| (ext.clone(), node))
+    })
+}
+
+/// The names of the _jeff_ types in the queries, see [`OpKindQuery`].
+mod type_names {
+    use super::*;
+    use serde::de::Error;
+    use serde::{Deserializer, Serializer};
+
+    /// The name of a type.
+    pub(super) fn name(jeff_type: &JeffType) -> String {
+        match jeff_type {
+            JeffType::Qubit => "qubit".to_string(),
+            JeffType::QubitRegister => "qureg".to_string(),
+            JeffType::Int { bits } => format!("int{bits}"),
+            JeffType::IntArray { bits } => format!("int{bits}_array"),
+            JeffType::Float { precision } => format!("float{}", precision_bits(*precision)),
+            JeffType::FloatArray { precision } => {
+                format!("float{}_array", precision_bits(*precision))
+            }
+        }
+    }
+
+    /// The type with a name, if any.
+    pub(super) fn parse(name: &str) -> Option<JeffType> {
+        let precision = |bits: &str| match bits {
+            "32" => Some(FloatPrecision::Float32),
+            "64" => Some(FloatPrecision::Float64),
+            _ => None,
+        };
+        let (name, is_array) = match name.strip_suffix("_array") {
+            Some(element) => (element, true),
+            None => (name, false),
+        };
+        if let Some(bits) = name.strip_prefix("int") {
+            let bits = bits.parse().ok().filter(|&bits| bits > 0)?;
+            return Some(match is_array {
+                true => JeffType::IntArray { bits },
+                false => JeffType::Int { bits },
+            });
+        }
+        if let Some(bits) = name.strip_prefix("float") {
+            let precision = precision(bits)?;
+            return Some(match is_array {
+                true => JeffType::FloatArray { precision },
+                false => JeffType::Float { precision },
+            });
+        }
+        match (name, is_array) {
+            ("qubit", false) => Some(JeffType::Qubit),
+            ("qureg", false) => Some(JeffType::QubitRegister),
+            _ => None,
+        }
+    }
+
+    pub(super) fn serialize<S: Serializer>(
+        types: &[JeffType],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(types.iter().map(name))
+    }
+
+    pub(super) fn serialize_one<S: Serializer>(
+        jeff_type: &JeffType,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&name(jeff_type))
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<JeffType>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|ty| parse(ty).ok_or_else(|| D::Error::custom(format!("unknown type {ty}"))))
+            .try_collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+    use std::collections::BTreeSet;
+
+    #[rstest]
+    #[case::for_loop(OpKindQuery::new(OpCategory::ControlFlow, "For"), Support::Full)]
+    #[case::qureg_split(OpKindQuery::new(OpCategory::QubitRegister, "Split"), Support::Full)]
+    #[case::float_const32(OpKindQuery::new(OpCategory::Float, "Const32"), FLOAT64)]
+    #[case::float_sin(OpKindQuery::new(OpCategory::Float, "Sin"), Support::Full)]
+    #[case::float_is_nan(OpKindQuery::new(OpCategory::Float, "IsNan"), Support::Unsupported)]
+    #[case::unknown_custom_gate(OpKindQuery::new(OpCategory::Gate, "iswap"), QGATE)]
+    #[case::cnot(OpKindQuery::new(OpCategory::Gate, "X").with_controls(1), Support::Full)]
+    #[case::toffoli(OpKindQuery::new(OpCategory::Gate, "X").with_controls(2), QGATE)]
+    #[case::s_adjoint(OpKindQuery::new(OpCategory::Gate, "S").with_adjoint(true), Support::Full)]
+    #[case::even_power(OpKindQuery::new(OpCategory::Gate, "H").with_power(2), Support::Full)]
+    #[case::u(OpKindQuery::new(OpCategory::Gate, "U"), QGATE)]
+    #[case::wrong_arity(
+        OpKindQuery::new(OpCategory::Gate, "H").with_operands([JeffType::Qubit, JeffType::Qubit]),
+        QGATE
+    )]
+    fn op_support(#[case] query: OpKindQuery, #[case] expected: Support) {
+        assert_eq!(supports_jeff_op(&query), expected);
+    }
+
+    #[rstest]
+    #[case::qubit(JeffType::Qubit, Support::Full)]
+    #[case::f32(JeffType::Float { precision: FloatPrecision::Float32 }, FLOAT64)]
+    #[case::f64(JeffType::Float { precision: FloatPrecision::Float64 }, Support::Full)]
+    #[case::int24(JeffType::Int { bits: 24 }, WIDER_INT)]
+    #[case::bit_array(JeffType::IntArray { bits: 1 }, Support::Full)]
+    fn type_support(#[case] jeff_type: JeffType, #[case] expected: Support) {
+        assert_eq!(supports_jeff_type(&jeff_type), expected);
+    }
+
+    #[rstest]
+    fn manifest_is_complete() {
+        let manifest = capability_manifest();
+        assert_eq!(manifest.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(manifest.extension_ops, JeffOpDef::iter().collect_vec());
+
+        let type_variants: BTreeSet<_> = manifest
+            .types
+            .iter()
+            .map(|entry| match entry.jeff_type {
+                JeffType::Qubit => "Qubit",
+                JeffType::QubitRegister => "QubitRegister",
+                JeffType::Int { .. } => "Int",
+                JeffType::IntArray { .. } => "IntArray",
+                JeffType::Float { .. } => "Float",
+                JeffType::FloatArray { .. } => "FloatArray",
+            })
+            .collect();
+        assert_eq!(type_variants.len(), 6);
+
+        for info in &CATALOG {
+            let name = info.gate.to_string();
+            assert!(manifest.ops.iter().any(|entry| entry.query.name == name));
+        }
+        let for_loop = manifest
+            .ops
+            .iter()
+            .find(|entry| entry.query == OpKindQuery::new(OpCategory::ControlFlow, "For"))
+            .unwrap();
+        assert_eq!(for_loop.support, Support::Full);
+    }
+
+    #[rstest]
+    fn manifest_json() {
+        let json = serde_json::to_value(capability_manifest()).unwrap();
+        let f32 = json["types"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|entry| entry["type"] == "float32")
+            .unwrap();
+        assert_eq!(
+            f32["support"],
+            serde_json::json!({"kind": "fallback", "to": "float64"})
+        );
+        assert!(
+            json["extension_ops"]
+                .as_array()
+                .unwrap()
+                .contains(&"QGate".into())
+        );
+    }
+
+    #[rstest]
+    fn query_roundtrip() {
+        let json = r#"{"category": "gate", "name": "X", "operands": ["qubit", "qubit"],
+            "controls": 1}"#;
+        let query: OpKindQuery = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            query,
+            OpKindQuery::new(OpCategory::Gate, "X")
+                .with_operands([JeffType::Qubit, JeffType::Qubit])
+                .with_controls(1)
+        );
+        assert_eq!(supports_jeff_op(&query), Support::Full);
+        let reloaded: OpKindQuery =
+            serde_json::from_value(serde_json::to_value(&query).unwrap()).unwrap();
+        assert_eq!(reloaded, query);
+
+        for entry in capability_manifest().types {
+            let name = type_names::name(&entry.jeff_type);
+            assert_eq!(type_names::parse(&name), Some(entry.jeff_type), "{name}");
+        }
+        assert_eq!(type_names::parse("int0"), None);
+        assert_eq!(type_names::parse("qubit_array"), None);
+    }
+
+    /// The types of the ports are checked along with the operations.
+    #[rstest]
+    fn disallowed_port_type() {
+        use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr};
+        use hugr::extension::prelude::qb_t;
+        use hugr::std_extensions::collections::array::{self, array_type};
+        use hugr::types::Signature;
+
+        let builder = DFGBuilder::new(Signature::new_endo(vec![qb_t()])).unwrap();
+        let [q] = builder.input_wires_arr();
+        let hugr = builder.finish_hugr_with_outputs([q]).unwrap();
+        assert_eq!(disallowed_extension(&hugr), None);
+
+        let signature = Signature::new_endo(vec![array_type(2, qb_t())]);
+        let builder = DFGBuilder::new(signature).unwrap();
+        let [qs] = builder.input_wires_arr();
+        let hugr = builder.finish_hugr_with_outputs([qs]).unwrap();
+        assert_eq!(
+            disallowed_extension(&hugr),
+            Some((array::EXTENSION_ID, hugr.entrypoint()))
+        );
+    }
+}
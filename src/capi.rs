@@ -0,0 +1,164 @@
+//! C ABI for the _jeff_ to HUGR conversion.
+//!
+//! Enabled with the `ffi` feature. The matching C header is checked in at
+//! `include/hugr_jeff.h`, and can be regenerated with
+//! `cbindgen --config cbindgen.toml --output include/hugr_jeff.h`.
+//!
+//! Every function returns one of the `HUGR_JEFF_*` status codes. Panics are
+//! caught at the boundary and reported as [`HUGR_JEFF_PANIC`].
+
+use std::ffi::{CString, c_char};
+use std::panic::catch_unwind;
+
+use hugr::envelope::EnvelopeConfig;
+
+use crate::hugr_importer::{ImportError, ImportOptions, import};
+
+/// The conversion succeeded.
+pub const HUGR_JEFF_OK: i32 = 0;
+/// A required pointer argument was null.
+pub const HUGR_JEFF_INVALID_ARGUMENT: i32 = 1;
+/// The input could not be read as a _jeff_ program.
+pub const HUGR_JEFF_READ_ERROR: i32 = 2;
+/// The _jeff_ program could not be translated.
+pub const HUGR_JEFF_TRANSLATE_ERROR: i32 = 3;
+/// The translated HUGR is not valid.
+pub const HUGR_JEFF_VALIDATE_ERROR: i32 = 4;
+/// The HUGR could not be written as an envelope.
+pub const HUGR_JEFF_ENVELOPE_ERROR: i32 = 5;
+/// The conversion panicked.
+pub const HUGR_JEFF_PANIC: i32 = 6;
+
+/// Convert a _jeff_ program into a binary HUGR envelope.
+///
+/// On success, `*out_envelope` points to a buffer of `*out_len` bytes that
+/// must be released with [`hugr_jeff_free_buffer`].
+///
+/// On failure, `*out_envelope` is null and, if `out_error` is not null,
+/// `*out_error` points to a NUL-terminated message that must be released with
+/// [`hugr_jeff_free_string`].
+///
+/// # Safety
+///
+/// `jeff_bytes` must point to `len` readable bytes, or be null with `len`
+/// equal to zero. The `out_*` pointers must be null or valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn hugr_jeff_convert(
+    jeff_bytes: *const u8,
+    len: usize,
+    out_envelope: *mut *mut u8,
+    out_len: *mut usize,
+    out_error: *mut *mut c_char,
+) -> i32 {
+    if !out_error.is_null() {
+        // SAFETY: `out_error` is valid for writes.
+        unsafe { *out_error = std::ptr::null_mut() };
+    }
+    if out_envelope.is_null() || out_len.is_null() || (jeff_bytes.is_null() && len != 0) {
+        // SAFETY: `out_error` is null or valid for writes.
+        unsafe { write_error(out_error, "Null pointer argument") };
+        return HUGR_JEFF_INVALID_ARGUMENT;
+    }
+    // SAFETY: Both output pointers are valid for writes.
+    unsafe {
+        *out_envelope = std::ptr::null_mut();
+        *out_len = 0;
+    }
+
+    let bytes: &[u8] = match jeff_bytes.is_null() {
+        true => &[],
+        // SAFETY: `jeff_bytes` points to `len` readable bytes.
+        false => unsafe { std::slice::from_raw_parts(jeff_bytes, len) },
+    };
+    let result = catch_unwind(|| convert(bytes))
+        .unwrap_or_else(|payload| Err((HUGR_JEFF_PANIC, panic_message(payload.as_ref()))));
+
+    match result {
+        Ok(envelope) => {
+            let envelope = Box::into_raw(envelope.into_boxed_slice());
+            // SAFETY: Both output pointers are valid for writes.
+            unsafe {
+                *out_len = envelope.len();
+                *out_envelope = envelope.cast();
+            }
+            HUGR_JEFF_OK
+        }
+        Err((status, message)) => {
+            // SAFETY: `out_error` is null or valid for writes.
+            unsafe { write_error(out_error, &message) };
+            status
+        }
+    }
+}
+
+/// Release a buffer returned by [`hugr_jeff_convert`].
+///
+/// # Safety
+///
+/// `buffer` must be null, or a buffer of `len` bytes returned by
+/// [`hugr_jeff_convert`] that has not been released yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn hugr_jeff_free_buffer(buffer: *mut u8, len: usize) {
+    if buffer.is_null() {
+        return;
+    }
+    // SAFETY: The buffer was allocated as a boxed slice of `len` bytes.
+    drop(unsafe { Box::from_raw(std::ptr::slice_from_raw_parts_mut(buffer, len)) });
+}
+
+/// Release an error message returned by [`hugr_jeff_convert`].
+///
+/// # Safety
+///
+/// `string` must be null, or a message returned by [`hugr_jeff_convert`] that
+/// has not been released yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn hugr_jeff_free_string(string: *mut c_char) {
+    if string.is_null() {
+        return;
+    }
+    // SAFETY: The string was allocated by `CString::into_raw`.
+    drop(unsafe { CString::from_raw(string) });
+}
+
+/// Convert a _jeff_ program into a binary envelope, returning the status
+/// code and message on failure.
+fn convert(mut bytes: &[u8]) -> Result<Vec<u8>, (i32, String)> {
+    let hugr = import(&mut bytes, &ImportOptions::default()).map_err(|e| {
+        let status = match e {
+            ImportError::Io(_) | ImportError::Read(_) => HUGR_JEFF_READ_ERROR,
+            ImportError::Translate(_) => HUGR_JEFF_TRANSLATE_ERROR,
+            ImportError::Validate(_) => HUGR_JEFF_VALIDATE_ERROR,
+        };
+        (status, e.to_string())
+    })?;
+
+    let mut envelope = Vec::new();
+    hugr.store(&mut envelope, EnvelopeConfig::binary())
+        .map_err(|e| (HUGR_JEFF_ENVELOPE_ERROR, e.to_string()))?;
+    Ok(envelope)
+}
+
+/// Write an error message to `out_error`, if it is not null.
+///
+/// # Safety
+///
+/// `out_error` must be null or valid for writes.
+unsafe fn write_error(out_error: *mut *mut c_char, message: &str) {
+    if out_error.is_null() {
+        return;
+    }
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    // SAFETY: `out_error` is valid for writes.
+    unsafe { *out_error = message.into_raw() };
+}
+
+/// Extract the message of a caught panic.
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic");
+    format!("Conversion panicked: {message}")
+}
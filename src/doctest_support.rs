@@ -0,0 +1,71 @@
+//! Small programs for the examples of the documentation.
+//!
+//! Doc-tests are built outside of the crate directory, so they cannot read
+//! the example programs in `test_files/`. This module embeds one of them,
+//! and builds small HUGRs using the _jeff_ extension.
+//!
+//! Only available with the `testing` feature, which the doc-tests of this
+//! crate enable.
+//!
+//! ```
+//! use hugr_jeff::doctest_support::{QUBITS_FUNCTION, qubits};
+//!
+//! let jeff = qubits();
+//! let names = jeff
+//!     .module()
+//!     .functions()
+//!     .map(|func| func.name().to_string())
+//!     .collect::<Vec<_>>();
+//! assert_eq!(names, [QUBITS_FUNCTION]);
+//! ```
+
+use hugr::Hugr;
+use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr};
+use hugr::std_extensions::arithmetic::int_types::ConstInt;
+use hugr::types::Signature;
+use jeff::Jeff;
+
+use crate::extension::{JeffOp, intreg_type};
+
+/// Log width of the `int(32)` register sizes.
+const INDEX_LOG_WIDTH: u8 = 5;
+
+/// The binary _jeff_ program of the `qubits` example.
+///
+/// It has a single function, [`QUBITS_FUNCTION`], taking two qubits and
+/// allocating a third one. It applies `X` and `H` to the arguments, entangles
+/// each of them with the allocated qubit with a `CX`, and rotates the first
+/// one with `Rx(0.5)`. The three qubits are returned.
+pub const QUBITS_JEFF: &[u8] = include_bytes!("../test_files/qubits/qubits.jeff");
+
+/// The name of the function of [`QUBITS_JEFF`].
+pub const QUBITS_FUNCTION: &str = "Circuit";
+
+/// Read the [`QUBITS_JEFF`] program.
+pub fn qubits() -> Jeff<'static> {
+    Jeff::read(QUBITS_JEFF).expect("the embedded program is valid")
+}
+
+/// A dataflow graph allocating a register of `size` qubits and measuring
+/// it with a [`JeffOp::QuregMeasure`], returning the bits.
+///
+/// # Panics
+///
+/// If `size` does not fit in 32 bits.
+pub fn measured_register(size: u64) -> Hugr {
+    let signature = Signature::new(vec![], vec![intreg_type(1)]);
+    let mut builder = DFGBuilder::new(signature).expect("the signature is valid");
+    let size = ConstInt::new_u(INDEX_LOG_WIDTH, size).expect("the size fits in 32 bits");
+    let size = builder.add_load_value(size);
+    let [reg] = builder
+        .add_dataflow_op(JeffOp::QuregAlloc.into_extension_op(), [size])
+        .expect("the allocation is valid")
+        .outputs_arr();
+    let [bits] = builder
+        .add_dataflow_op(JeffOp::QuregMeasure.into_extension_op(), [reg])
+        .expect("the measurement is valid")
+        .outputs_arr();
+    builder
+        .finish_hugr_with_outputs([bits])
+        .expect("the graph is valid")
+}
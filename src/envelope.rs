@@ -0,0 +1,286 @@
+//! Storing HUGRs for consumers pinned to older hugr releases.
+//!
+//! The envelopes written by the linked hugr version may use features that
+//! older readers do not know about. [`store_hugr_compat`] picks an envelope
+//! configuration readable by a [`HugrEnvelopeTarget`], and refuses to store
+//! HUGRs using a feature listed in [`FEATURES`] that the target predates.
+//!
+//! The check only covers the listed features. Targets older than the linked
+//! hugr release always get an uncompressed JSON package, the most stable of
+//! the envelope formats.
+
+use std::fmt;
+use std::io::Write;
+
+use derive_more::{Display, Error, From};
+use hugr::envelope::{EnvelopeConfig, EnvelopeError};
+use hugr::extension::Version;
+use hugr::ops::OpType;
+use hugr::types::Term;
+use hugr::{HugrView, Node};
+use itertools::Itertools;
+
+/// The oldest hugr release that must be able to read a stored envelope.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum HugrEnvelopeTarget {
+    /// hugr 0.15, the first release reading envelopes.
+    Hugr0_15,
+    /// hugr 0.20, which introduced the HUGR entrypoint.
+    Hugr0_20,
+    /// hugr 0.21, which merged the type arguments and parameters into terms.
+    Hugr0_21,
+    /// The hugr release linked by this crate.
+    #[default]
+    Current,
+}
+
+impl HugrEnvelopeTarget {
+    /// All the targets, oldest first.
+    pub const ALL: [Self; 4] = [Self::Hugr0_15, Self::Hugr0_20, Self::Hugr0_21, Self::Current];
+
+    /// The hugr release of the target.
+    pub fn hugr_version(self) -> Version {
+        match self {
+            Self::Hugr0_15 => Version::new(0, 15, 0),
+            Self::Hugr0_20 => Version::new(0, 20, 0),
+            Self::Hugr0_21 => Version::new(0, 21, 0),
+            Self::Current => Version::new(0, 22, 0),
+        }
+    }
+
+    /// The envelope configuration used to store HUGRs for the target.
+    pub fn envelope_config(self) -> EnvelopeConfig {
+        match self {
+            Self::Current => EnvelopeConfig::binary(),
+            _ => EnvelopeConfig::text(),
+        }
+    }
+}
+
+/// A HUGR feature that is not available in all the hugr releases.
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum HugrFeature {
+    /// An entrypoint other than the module root.
+    #[display("non-module entrypoint")]
+    Entrypoint,
+    /// A static float or bytes operation argument.
+    #[display("float or bytes term")]
+    FloatBytesTerm,
+    /// A list or tuple concatenation in an operation argument.
+    #[display("concatenation term")]
+    ConcatTerm,
+}
+
+/// The features checked by [`check_compatibility`], with the first hugr
+/// release supporting them.
+pub const FEATURES: &[(HugrFeature, HugrEnvelopeTarget)] = &[
+    (HugrFeature::Entrypoint, HugrEnvelopeTarget::Hugr0_20),
+    (HugrFeature::FloatBytesTerm, HugrEnvelopeTarget::Hugr0_21),
+    (HugrFeature::ConcatTerm, HugrEnvelopeTarget::Hugr0_21),
+];
+
+/// A feature of a HUGR that is not available in a [`HugrEnvelopeTarget`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CompatibilityError {
+    /// The unavailable feature.
+    pub feature: HugrFeature,
+    /// The first hugr release supporting the feature.
+    pub required_version: Version,
+    /// The nodes using the feature.
+    pub nodes: Vec<Node>,
+}
+
+impl fmt::Display for CompatibilityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The {} used at {} requires hugr {}",
+            self.feature,
+            self.nodes.iter().join(", "),
+            self.required_version
+        )
+    }
+}
+
+impl std::error::Error for CompatibilityError {}
+
+/// Error raised by [`store_hugr_compat`].
+#[derive(Debug, Display, Error, From)]
+#[non_exhaustive]
+pub enum StoreCompatError {
+    /// The HUGR uses features that are not available in the target.
+    #[display("HUGR is not compatible with the target: {}", offenders.iter().join("; "))]
+    #[from(ignore)]
+    Incompatible {
+        /// The unavailable features.
+        offenders: Vec<CompatibilityError>,
+    },
+    /// The envelope could not be written.
+    #[display("Failed to store HUGR envelope: {_0}")]
+    Envelope(EnvelopeError),
+}
+
+/// Find the features of a HUGR that are not available in a target.
+///
+/// Returns one error per feature of [`FEATURES`] newer than the target and
+/// used by the HUGR.
+///
+/// # Examples
+///
+/// ```
+/// use hugr_jeff::doctest_support::measured_register;
+/// use hugr_jeff::envelope::{HugrEnvelopeTarget, HugrFeature, check_compatibility};
+///
+/// // The entrypoint of the HUGR is a dataflow graph, not the module root.
+/// let hugr = measured_register(2);
+/// let errors = check_compatibility(&hugr, HugrEnvelopeTarget::Hugr0_15);
+/// assert_eq!(errors.len(), 1);
+/// assert_eq!(errors[0].feature, HugrFeature::Entrypoint);
+///
+/// assert!(check_compatibility(&hugr, HugrEnvelopeTarget::Hugr0_20).is_empty());
+/// ```
+pub fn check_compatibility(
+    hugr: &impl HugrView<Node = Node>,
+    target: HugrEnvelopeTarget,
+) -> Vec<CompatibilityError> {
+    let unavailable = FEATURES
+        .iter()
+        .filter(|&&(_, since)| since > target)
+        .collect_vec();
+    if unavailable.is_empty() {
+        return Vec::new();
+    }
+    let used = used_features(hugr);
+    unavailable
+        .into_iter()
+        .filter_map(|&(feature, since)| {
+            let nodes = used
+                .iter()
+                .filter(|&&(_, used)| used == feature)
+                .map(|&(node, _)| node)
+                .collect_vec();
+            (!nodes.is_empty()).then(|| CompatibilityError {
+                feature,
+                required_version: since.hugr_version(),
+                nodes,
+            })
+        })
+        .collect()
+}
+
+/// Store a HUGR in an envelope readable by a target hugr release.
+///
+/// # Errors
+///
+/// - [`StoreCompatError::Incompatible`] if the HUGR uses features not
+///   available in the target, see [`check_compatibility`].
+/// - [`StoreCompatError::Envelope`] if the envelope could not be written.
+pub fn store_hugr_compat(
+    hugr: &impl HugrView<Node = Node>,
+    target: HugrEnvelopeTarget,
+    writer: impl Write,
+) -> Result<(), StoreCompatError> {
+    let offenders = check_compatibility(hugr, target);
+    if !offenders.is_empty() {
+        return Err(StoreCompatError::Incompatible { offenders });
+    }
+    hugr.store(writer, target.envelope_config())?;
+    Ok(())
+}
+
+/// The checked features used by a HUGR, with the nodes using them.
+fn used_features(hugr: &impl HugrView<Node = Node>) -> Vec<(Node, HugrFeature)> {
+    let mut used = Vec::new();
+    if hugr.entrypoint() != hugr.module_root() {
+        used.push((hugr.entrypoint(), HugrFeature::Entrypoint));
+    }
+    for node in hugr.nodes() {
+        let args = match hugr.get_optype(node) {
+            OpType::ExtensionOp(op) => op.args(),
+            OpType::OpaqueOp(op) => op.args(),
+            _ => continue,
+        };
+        let mut features = Vec::new();
+        args.iter().for_each(|arg| term_features(arg, &mut features));
+        used.extend(features.into_iter().unique().map(|feature| (node, feature)));
+    }
+    used
+}
+
+/// Collect the checked features used by a term.
+fn term_features(term: &Term, features: &mut Vec<HugrFeature>) {
+    match term {
+        Term::Float(_) | Term::Bytes(_) => features.push(HugrFeature::FloatBytesTerm),
+        Term::ListConcat(lists) | Term::TupleConcat(lists) => {
+            features.push(HugrFeature::ConcatTerm);
+            lists.iter().for_each(|list| term_features(list, features));
+        }
+        Term::List(items) | Term::Tuple(items) => {
+            items.iter().for_each(|item| term_features(item, features));
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hugr_importer::extension_registry;
+    use crate::jeff_to_hugr;
+    use hugr::Hugr;
+    use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr};
+    use hugr::extension::prelude::qb_t;
+    use hugr::types::Signature;
+    use jeff::Jeff;
+    use rstest::rstest;
+
+    fn qubits() -> Hugr {
+        let file = std::fs::File::open("test_files/qubits/qubits.jeff").unwrap();
+        let jeff = Jeff::read(std::io::BufReader::new(file)).unwrap();
+        jeff_to_hugr(&jeff).unwrap()
+    }
+
+    #[rstest]
+    #[case::oldest(HugrEnvelopeTarget::Hugr0_15)]
+    #[case::current(HugrEnvelopeTarget::Current)]
+    fn store_qubits(#[case] target: HugrEnvelopeTarget) {
+        let hugr = qubits();
+        let mut envelope = Vec::new();
+        store_hugr_compat(&hugr, target, &mut envelope).unwrap();
+        let loaded = Hugr::load(envelope.as_slice(), Some(&extension_registry())).unwrap();
+        loaded.validate().unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(loaded.num_nodes(), hugr.num_nodes());
+    }
+
+    #[rstest]
+    fn dfg_entrypoint() {
+        let builder = DFGBuilder::new(Signature::new_endo(vec![qb_t()])).unwrap();
+        let [q] = builder.input_wires_arr();
+        let hugr = builder.finish_hugr_with_outputs([q]).unwrap();
+
+        let err = store_hugr_compat(&hugr, HugrEnvelopeTarget::Hugr0_15, Vec::new()).unwrap_err();
+        let StoreCompatError::Incompatible { offenders } = err else {
+            panic!("unexpected error: {err}");
+        };
+        assert_eq!(offenders.len(), 1);
+        assert_eq!(offenders[0].feature, HugrFeature::Entrypoint);
+        assert_eq!(offenders[0].required_version, Version::new(0, 20, 0));
+        assert_eq!(offenders[0].nodes, [hugr.entrypoint()]);
+
+        for target in [HugrEnvelopeTarget::Hugr0_20, HugrEnvelopeTarget::Current] {
+            store_hugr_compat(&hugr, target, Vec::new()).unwrap();
+        }
+    }
+
+    #[rstest]
+    fn feature_table() {
+        for &(_, since) in FEATURES {
+            assert_ne!(since, HugrEnvelopeTarget::Hugr0_15);
+            assert!(since.hugr_version() <= HugrEnvelopeTarget::Current.hugr_version());
+        }
+        assert!(HugrEnvelopeTarget::ALL.is_sorted());
+    }
+}
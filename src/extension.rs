@@ -3,9 +3,12 @@
 
 mod jeff_op;
 mod jeff_type;
+pub mod register_semantics;
+#[cfg(feature = "signature-cache")]
+pub mod signature_cache;
 
 use hugr::types::{Term, TypeBound};
-pub use jeff_op::{JeffOp, JeffOpDef};
+pub use jeff_op::{JeffOp, JeffOpDef, OpBuildError, compat, signature};
 pub use jeff_type::{
     ConstIntReg, FLOATREG_TYPE_ID, INTREG_TYPE_ID, QUREG_TYPE_ID, floatreg_custom_type,
     floatreg_type, intreg_custom_type, intreg_parametric_custom_type, intreg_parametric_type,
@@ -22,8 +25,10 @@ use std::sync::Arc;
 /// The ID of the hugr-jeff extension.
 pub const JEFF_EXTENSION_ID: ExtensionId = IdentList::new_unchecked("jeff");
 
-/// Current version of the TKET 1 extension
-pub const JEFF_EXTENSION_VERSION: Version = Version::new(0, 1, 0);
+/// Current version of the _jeff_ extension.
+///
+/// See [`compat::TYPE_ARG_LAYOUTS`] for the operation arguments of each version.
+pub const JEFF_EXTENSION_VERSION: Version = Version::new(0, 2, 0);
 
 lazy_static! {
     /// The extension definition for TKET ops and types.
@@ -1,25 +1,25 @@
 //! Quantum gate in the _jeff_ hugr extension.
 
+pub mod compat;
+pub mod signature;
+
 use std::num::NonZero;
 use std::sync::{Arc, Weak};
 
+use derive_more::{Display, Error};
 use hugr::Extension;
-use hugr::extension::prelude::qb_t;
 use hugr::extension::simple_op::{
     HasConcrete, HasDef, MakeExtensionOp, MakeOpDef, MakeRegisteredOp, OpLoadError, try_from_name,
 };
 use hugr::extension::{CustomSignatureFunc, ExtensionId, OpDef, SignatureError, SignatureFunc};
 use hugr::ops::ExtensionOp;
-use hugr::std_extensions::arithmetic::float_types::float64_type;
-use hugr::types::{PolyFuncType, PolyFuncTypeRV, Signature, Term};
-use itertools::Itertools;
+use hugr::types::{PolyFuncType, PolyFuncTypeRV, Signature, Term, Type as HugrType, TypeBound};
+use jeff::types::{FloatPrecision, Type as JeffType};
+use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 use strum_macros::{EnumIter, EnumString};
 
-use super::{
-    JEFF_EXTENSION, JEFF_EXTENSION_ID, intreg_parametric_custom_type, intreg_type,
-    qureg_custom_type,
-};
+use super::{JEFF_EXTENSION, JEFF_EXTENSION_ID};
 
 #[derive(
     Clone,
@@ -48,6 +48,28 @@ pub enum JeffOpDef {
     /// - Whether the gate is adjoint
     /// - A power value (how many times to apply it in sequence)
     QGate,
+    /// Apply a global phase `e^{iθ}` to the whole state, given the angle in radians.
+    GlobalPhase,
+    /// Apply a gate only if a classical bit has a given value.
+    ///
+    /// Used for fast feedforward on a measurement outcome, see
+    /// [`crate::passes::fuse_conditional_gates`].
+    ///
+    /// Operation arguments:
+    /// - The name of the `tket.quantum` gate
+    /// - The number of qubits of the gate
+    /// - The value of the bit for which the gate is applied
+    CondGate,
+
+    /// Borrow a qubit from the runtime pool, in an unknown state.
+    ///
+    /// Unlike an allocated qubit, a borrowed qubit may be dirty. It must be
+    /// given back with a [`JeffOpDef::QubitReturn`] in the same region, see
+    /// [`crate::analysis::check_borrow_scopes`].
+    QubitBorrow,
+    /// Return a borrowed qubit to the runtime pool.
+    QubitReturn,
+
     /// Allocate a new qubit register with a size parameter.
     QuregAlloc,
     /// Free a qubit register.
@@ -70,6 +92,30 @@ pub enum JeffOpDef {
     QuregJoin,
     /// Returns the length of a qubit register.
     QuregLength,
+    /// Measure all the qubits in a register into an array of bits, consuming the register.
+    QuregMeasure,
+    /// Measure all the qubits in a register into an array of bits, returning the register.
+    QuregMeasureNd,
+    /// Swap the qubits at two indices of a register.
+    QuregSwapElems,
+    /// Reverse the order of the qubits in a register.
+    QuregReverse,
+
+    /// Prepare qubits in the state given by an amplitude array.
+    ///
+    /// Takes the qubits followed by the amplitudes as a `float(64)` array, and
+    /// returns the qubits. An array of `2^n` floats gives real amplitudes,
+    /// and an array of `2^(n+1)` floats gives complex amplitudes interleaved
+    /// as `(real, imaginary)` pairs.
+    ///
+    /// Operation arguments:
+    /// - The number of qubits
+    StatePrep,
+    /// Prepare a qubit register in the state given by an amplitude array.
+    ///
+    /// The amplitudes follow the convention of [`JeffOpDef::StatePrep`], for
+    /// the length of the register.
+    QuregStatePrep,
 
     /// Allocate a new IntArray with the given length.
     IntArrayCreate,
@@ -81,11 +127,88 @@ pub enum JeffOpDef {
     IntArraySet,
     /// Create a zeroed integer array of a given bitwidth with dynamic length.
     IntArrayZero,
+    /// Reverse the order of the elements of an IntArray.
+    ///
+    /// Converts bit arrays between [`crate::BitOrder`] conventions.
+    IntArrayReverse,
+
+    /// Create a new FloatArray from a list of floats.
+    FloatArrayCreate,
+    /// Get the value at a given index in a FloatArray.
+    FloatArrayGet,
+
+    /// Reinterpret the bits of an integer as a float of the same width.
+    ///
+    /// HUGR has no 32-bit float type, so the float of a 32-bit bitcast is
+    /// widened to a `float64` holding the same value.
+    ///
+    /// Operation arguments:
+    /// - The bitwidth, either 32 or 64
+    BitcastIntToFloat,
+    /// Reinterpret the bits of a float as an integer of the same width.
+    ///
+    /// The input of a 32-bit bitcast is a `float64` holding a 32-bit float
+    /// value, see [`JeffOpDef::BitcastIntToFloat`].
+    ///
+    /// Operation arguments:
+    /// - The bitwidth, either 32 or 64
+    BitcastFloatToInt,
+
+    /// Apply a float function with no equivalent in `arithmetic.float`, such
+    /// as `sqrt` or `sin`, to a `float64`.
+    ///
+    /// Operation arguments:
+    /// - The name of the function, see [`JeffOp::float_function`]
+    FloatUnaryFn,
+    /// Compute the arctangent `atan2(y, x)` of two `float64`s, given `y` then
+    /// `x`.
+    FloatAtan2,
+
+    /// Print a formatted message with a list of classical values.
+    ///
+    /// The printed values are threaded through the operation to preserve their ordering.
+    ///
+    /// Operation arguments:
+    /// - The format string
+    /// - The list of types of the printed values
+    DebugPrint,
+
+    /// Query the number of qubits available on the device.
+    NumQubits,
+    /// Query the number of shots the program is executed for.
+    ShotCount,
+    /// Query a named runtime property, returning an integer.
+    ///
+    /// Operation arguments:
+    /// - The name of the queried property
+    RuntimeQuery,
 }
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 #[non_exhaustive]
 /// A concrete _jeff_ operations with no direct equivalent in `tket2.quantum`.
+///
+/// # Examples
+///
+/// Build a circuit applying a two-qubit gate unknown to `tket.quantum`:
+///
+/// ```
+/// use hugr_jeff::Signature;
+/// use hugr_jeff::extension::JeffOp;
+/// use hugr_jeff::hugr::HugrView;
+/// use hugr_jeff::hugr::builder::{DFGBuilder, Dataflow, DataflowHugr};
+/// use hugr_jeff::hugr::extension::prelude::qb_t;
+///
+/// let iswap = JeffOp::quantum_gate("iswap".to_string(), 2, 0, 0, false, 1);
+///
+/// let mut builder = DFGBuilder::new(Signature::new_endo(vec![qb_t(), qb_t()])).unwrap();
+/// let [a, b] = builder.input_wires_arr();
+/// let gate = builder
+///     .add_dataflow_op(iswap.into_extension_op(), [a, b])
+///     .unwrap();
+/// let hugr = builder.finish_hugr_with_outputs(gate.outputs()).unwrap();
+/// hugr.validate().unwrap();
+/// ```
 pub enum JeffOp {
     /// Quantum gate with an arbitrary number of qubits and parameters.
     ///
@@ -104,14 +227,38 @@ pub enum JeffOp {
         /// How many times in a row to apply the gate.
         power: usize,
     },
+    /// Apply a global phase `e^{iθ}` to the whole state, given the angle in radians.
+    GlobalPhase,
+    /// Apply a gate only if a classical bit has a given value.
+    ///
+    /// Takes the bit followed by the qubits of the gate.
+    CondGate {
+        /// The name of the `tket.quantum` gate.
+        gate: String,
+        /// The number of qubits of the gate.
+        qubits: usize,
+        /// The value of the bit for which the gate is applied.
+        outcome: bool,
+    },
+
+    /// Borrow a qubit from the runtime pool, in an unknown state.
+    ///
+    /// See [`JeffOpDef::QubitBorrow`].
+    QubitBorrow,
+    /// Return a borrowed qubit to the runtime pool.
+    QubitReturn,
 
     /// Allocate a new qubit register with a size parameter.
     QuregAlloc,
     /// Free a qubit register.
     QuregFree,
     /// Extract a qubit at the given index from a register.
+    ///
+    /// The register keeps its length, see [`crate::extension::register_semantics`].
     QuregExtractIndex,
     /// Insert a qubit at the given index into a register.
+    ///
+    /// Fills the slot emptied by a [`JeffOp::QuregExtractIndex`].
     QuregInsertIndex,
     /// Create a register of qubits from a variable number of input qubits.
     QuregCreate {
@@ -130,6 +277,24 @@ pub enum JeffOp {
     QuregJoin,
     /// Returns the length of a qubit register.
     QuregLength,
+    /// Measure all the qubits in a register into an array of bits, consuming the register.
+    QuregMeasure,
+    /// Measure all the qubits in a register into an array of bits, returning the register.
+    QuregMeasureNd,
+    /// Swap the qubits at two indices of a register.
+    QuregSwapElems,
+    /// Reverse the order of the qubits in a register.
+    QuregReverse,
+
+    /// Prepare qubits in the state given by an amplitude array.
+    ///
+    /// See [`JeffOpDef::StatePrep`] for the amplitude convention.
+    StatePrep {
+        /// The number of qubits.
+        qubits: usize,
+    },
+    /// Prepare a qubit register in the state given by an amplitude array.
+    QuregStatePrep,
 
     /// Allocate a new IntArray with the given length.
     IntArrayCreate {
@@ -158,6 +323,67 @@ pub enum JeffOp {
         /// The bitwidth of the integers in the array.
         bits: u8,
     },
+    /// Reverse the order of the elements of an IntArray.
+    ///
+    /// Converts bit arrays between [`crate::BitOrder`] conventions.
+    IntArrayReverse {
+        /// The bitwidth of the integers in the array.
+        bits: u8,
+    },
+
+    /// Create a new FloatArray from a list of floats.
+    FloatArrayCreate {
+        /// The precision of the floats in the array, either 32 or 64 bits.
+        bits: u8,
+        /// The number of input floats.
+        inputs: usize,
+    },
+    /// Get the value at a given index in a FloatArray.
+    FloatArrayGet {
+        /// The precision of the floats in the array, either 32 or 64 bits.
+        bits: u8,
+    },
+
+    /// Reinterpret the bits of an integer as a float of the same width.
+    ///
+    /// See [`JeffOpDef::BitcastIntToFloat`] for the 32-bit floats.
+    BitcastIntToFloat {
+        /// The bitwidth of the values, either 32 or 64.
+        bits: u8,
+    },
+    /// Reinterpret the bits of a float as an integer of the same width.
+    BitcastFloatToInt {
+        /// The bitwidth of the values, either 32 or 64.
+        bits: u8,
+    },
+
+    /// Apply a float function with no equivalent in `arithmetic.float`.
+    FloatUnaryFn {
+        /// The name of the function.
+        name: String,
+    },
+    /// Compute the arctangent `atan2(y, x)` of two floats.
+    FloatAtan2,
+
+    /// Print a formatted message with a list of classical values.
+    ///
+    /// The printed values are threaded through the operation to preserve their ordering.
+    DebugPrint {
+        /// The format string of the message.
+        format: String,
+        /// The types of the printed values.
+        types: Vec<HugrType>,
+    },
+
+    /// Query the number of qubits available on the device.
+    NumQubits,
+    /// Query the number of shots the program is executed for.
+    ShotCount,
+    /// Query a named runtime property, returning an integer.
+    RuntimeQuery {
+        /// The name of the queried property.
+        name: String,
+    },
 }
 
 impl JeffOp {
@@ -206,6 +432,10 @@ impl JeffOp {
     pub fn opdef(&self) -> JeffOpDef {
         match self {
             JeffOp::QGate { .. } => JeffOpDef::QGate,
+            JeffOp::GlobalPhase => JeffOpDef::GlobalPhase,
+            JeffOp::CondGate { .. } => JeffOpDef::CondGate,
+            JeffOp::QubitBorrow => JeffOpDef::QubitBorrow,
+            JeffOp::QubitReturn => JeffOpDef::QubitReturn,
             JeffOp::QuregAlloc => JeffOpDef::QuregAlloc,
             JeffOp::QuregFree => JeffOpDef::QuregFree,
             JeffOp::QuregExtractIndex => JeffOpDef::QuregExtractIndex,
@@ -216,100 +446,301 @@ impl JeffOp {
             JeffOp::QuregSplit => JeffOpDef::QuregSplit,
             JeffOp::QuregJoin => JeffOpDef::QuregJoin,
             JeffOp::QuregLength => JeffOpDef::QuregLength,
+            JeffOp::QuregMeasure => JeffOpDef::QuregMeasure,
+            JeffOp::QuregMeasureNd => JeffOpDef::QuregMeasureNd,
+            JeffOp::QuregSwapElems => JeffOpDef::QuregSwapElems,
+            JeffOp::QuregReverse => JeffOpDef::QuregReverse,
+            JeffOp::StatePrep { .. } => JeffOpDef::StatePrep,
+            JeffOp::QuregStatePrep => JeffOpDef::QuregStatePrep,
             JeffOp::IntArrayCreate { .. } => JeffOpDef::IntArrayCreate,
             JeffOp::IntArrayLength { .. } => JeffOpDef::IntArrayLength,
             JeffOp::IntArrayGet { .. } => JeffOpDef::IntArrayGet,
             JeffOp::IntArraySet { .. } => JeffOpDef::IntArraySet,
             JeffOp::IntArrayZero { .. } => JeffOpDef::IntArrayZero,
+            JeffOp::IntArrayReverse { .. } => JeffOpDef::IntArrayReverse,
+            JeffOp::FloatArrayCreate { .. } => JeffOpDef::FloatArrayCreate,
+            JeffOp::FloatArrayGet { .. } => JeffOpDef::FloatArrayGet,
+            JeffOp::BitcastIntToFloat { .. } => JeffOpDef::BitcastIntToFloat,
+            JeffOp::BitcastFloatToInt { .. } => JeffOpDef::BitcastFloatToInt,
+            JeffOp::FloatUnaryFn { .. } => JeffOpDef::FloatUnaryFn,
+            JeffOp::FloatAtan2 => JeffOpDef::FloatAtan2,
+            JeffOp::DebugPrint { .. } => JeffOpDef::DebugPrint,
+            JeffOp::NumQubits => JeffOpDef::NumQubits,
+            JeffOp::ShotCount => JeffOpDef::ShotCount,
+            JeffOp::RuntimeQuery { .. } => JeffOpDef::RuntimeQuery,
+        }
+    }
+
+    /// Returns the runtime query corresponding to a _jeff_ intrinsic function name, if any.
+    ///
+    /// Intrinsics named `runtime.<name>` are translated into a generic
+    /// [`JeffOp::RuntimeQuery`].
+    pub fn runtime_query(intrinsic: &str) -> Option<Self> {
+        match intrinsic {
+            "num_qubits" => Some(JeffOp::NumQubits),
+            "shot_count" => Some(JeffOp::ShotCount),
+            _ => intrinsic
+                .strip_prefix("runtime.")
+                .filter(|name| !name.is_empty())
+                .map(|name| JeffOp::RuntimeQuery {
+                    name: name.to_string(),
+                }),
+        }
+    }
+
+    /// Returns `true` if the operation reads a runtime property.
+    ///
+    /// These are effectful reads: two queries must never be merged, and the
+    /// result is only known ahead of time if bound with
+    /// [`crate::passes::bind_runtime_queries`].
+    pub fn is_runtime_query(&self) -> bool {
+        matches!(
+            self,
+            JeffOp::NumQubits | JeffOp::ShotCount | JeffOp::RuntimeQuery { .. }
+        )
+    }
+
+    /// Returns the operation computing a _jeff_ float function with no
+    /// equivalent in `arithmetic.float`, if any.
+    ///
+    /// The single-argument functions are named after the lowercase _jeff_
+    /// operation, with `log` for the natural logarithm.
+    pub fn float_function(jeff_op: &jeff::reader::optype::FloatOp) -> Option<Self> {
+        use jeff::reader::optype::FloatOp::*;
+
+        let name = match jeff_op {
+            Sqrt => "sqrt",
+            Log => "log",
+            Exp => "exp",
+            Sin => "sin",
+            Cos => "cos",
+            Tan => "tan",
+            Asin => "asin",
+            Acos => "acos",
+            Atan => "atan",
+            Sinh => "sinh",
+            Cosh => "cosh",
+            Tanh => "tanh",
+            Asinh => "asinh",
+            Acosh => "acosh",
+            Atanh => "atanh",
+            Atan2 => return Some(JeffOp::FloatAtan2),
+            _ => return None,
+        };
+        Some(JeffOp::FloatUnaryFn {
+            name: name.to_string(),
+        })
+    }
+
+    /// Returns the state preparation of a _jeff_ intrinsic function, given
+    /// its input and output types.
+    ///
+    /// The `state_prep` intrinsic takes either `n` qubits or a qubit register,
+    /// followed by the amplitudes as a `float(64)` array, and returns the
+    /// qubits. _jeff_ has no complex type, see [`JeffOpDef::StatePrep`] for
+    /// the layout of the amplitudes.
+    pub fn state_prep(intrinsic: &str, inputs: &[JeffType], outputs: &[JeffType]) -> Option<Self> {
+        let amplitudes_t = JeffType::FloatArray {
+            precision: FloatPrecision::Float64,
+        };
+        let (amplitudes, targets) = inputs.split_last()?;
+        if intrinsic != "state_prep" || *amplitudes != amplitudes_t || targets != outputs {
+            return None;
+        }
+        match targets {
+            [JeffType::QubitRegister] => Some(JeffOp::QuregStatePrep),
+            [_, ..] if targets.iter().all(|ty| *ty == JeffType::Qubit) => Some(JeffOp::StatePrep {
+                qubits: targets.len(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Returns the bitcast of a _jeff_ intrinsic function, given its input
+    /// and output types.
+    ///
+    /// The `bitcast` intrinsic takes an `int(n)` and returns a `float(n)`, or
+    /// the other way around, for `n` either 32 or 64.
+    pub fn bitcast(intrinsic: &str, inputs: &[JeffType], outputs: &[JeffType]) -> Option<Self> {
+        if intrinsic != "bitcast" {
+            return None;
+        }
+        let width = |precision| match precision {
+            FloatPrecision::Float32 => 32,
+            FloatPrecision::Float64 => 64,
+        };
+        match (inputs, outputs) {
+            ([JeffType::Int { bits }], [JeffType::Float { precision }])
+                if *bits == width(*precision) =>
+            {
+                Some(JeffOp::BitcastIntToFloat { bits: *bits })
+            }
+            ([JeffType::Float { precision }], [JeffType::Int { bits }])
+                if *bits == width(*precision) =>
+            {
+                Some(JeffOp::BitcastFloatToInt { bits: *bits })
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the qubit borrowing operation of a _jeff_ intrinsic function,
+    /// given its input and output types.
+    ///
+    /// The `qubit_borrow` intrinsic takes no inputs and returns a qubit, and
+    /// the `qubit_return` intrinsic takes a qubit and returns nothing.
+    pub fn qubit_borrow(
+        intrinsic: &str,
+        inputs: &[JeffType],
+        outputs: &[JeffType],
+    ) -> Option<Self> {
+        match (intrinsic, inputs, outputs) {
+            ("qubit_borrow", [], [JeffType::Qubit]) => Some(JeffOp::QubitBorrow),
+            ("qubit_return", [JeffType::Qubit], []) => Some(JeffOp::QubitReturn),
+            _ => None,
         }
     }
 
-    /// Wraps the operation in an [`ExtensionOp`]
+    /// Wraps the operation in an [`ExtensionOp`].
+    ///
+    /// # Panics
+    ///
+    /// If the operation cannot be built, see [`JeffOp::try_into_extension_op`].
     pub fn into_extension_op(self) -> ExtensionOp {
-        <Self as MakeRegisteredOp>::to_extension_op(self)
-            .expect("Failed to convert to extension op.")
+        self.try_into_extension_op()
+            .unwrap_or_else(|e| panic!("Cannot build the jeff operation: {e}"))
+    }
+
+    /// Wraps the operation in an [`ExtensionOp`].
+    ///
+    /// # Errors
+    ///
+    /// - [`OpBuildError::ExtensionDropped`] if the _jeff_ extension is no longer alive.
+    /// - [`OpBuildError::InvalidOp`] if the extension does not define the
+    ///   operation, or its type arguments are invalid.
+    pub fn try_into_extension_op(self) -> Result<ExtensionOp, OpBuildError> {
+        let op = format!("{self:?}");
+        let Some(extension) = <Self as MakeRegisteredOp>::extension_ref(&self).upgrade() else {
+            return Err(OpBuildError::ExtensionDropped { op });
+        };
+        let name = self.op_id();
+        let Some(def) = extension.get_op(&name) else {
+            let source = OpLoadError::NotMember(name.to_string());
+            return Err(OpBuildError::InvalidOp { op, source });
+        };
+        ExtensionOp::new(Arc::clone(def), self.type_args()).map_err(|e| OpBuildError::InvalidOp {
+            op,
+            source: e.into(),
+        })
     }
 }
 
+/// Error building the [`ExtensionOp`] of a [`JeffOp`].
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum OpBuildError {
+    /// The _jeff_ extension was dropped before building the operation.
+    #[display("The jeff extension is not available to build {op}")]
+    ExtensionDropped {
+        /// The operation, formatted with [`Debug`].
+        op: String,
+    },
+    /// The operation is not valid in the _jeff_ extension.
+    #[display("Invalid jeff operation {op}: {source}")]
+    InvalidOp {
+        /// The operation, formatted with [`Debug`].
+        op: String,
+        /// The error raised by the extension.
+        source: OpLoadError,
+    },
+}
+
+/// The signature function of an operation with type arguments.
+///
+/// With the `signature-cache` feature, the computed signatures are shared
+/// with the `signature_cache` module.
+fn custom_signature(func: impl CustomSignatureFunc + 'static) -> SignatureFunc {
+    #[cfg(feature = "signature-cache")]
+    let func = super::signature_cache::Cached(func);
+    func.into()
+}
+
 impl MakeOpDef for JeffOpDef {
     fn init_signature(&self, extension_ref: &std::sync::Weak<hugr::Extension>) -> SignatureFunc {
-        let qreg_t = || qureg_custom_type(extension_ref).into();
-        let int32_t = || crate::types::jeff_to_hugr(jeff::types::Type::Int { bits: 32 });
-
-        let intarr_type = |var_idx| {
-            intreg_parametric_custom_type(
-                extension_ref,
-                Term::new_var_use(var_idx, Term::max_nat_type()),
-            )
-            .into()
-        };
+        let simple = |sig: Signature| -> SignatureFunc { PolyFuncType::new(vec![], sig).into() };
 
         match self {
-            JeffOpDef::QGate => JeffGateNSignature.into(),
+            JeffOpDef::QGate => custom_signature(JeffGateNSignature),
+            JeffOpDef::GlobalPhase => simple(signature::global_phase_signature()),
+            JeffOpDef::CondGate => custom_signature(JeffCondGateSignature),
+            // Borrowed qubits
+            JeffOpDef::QubitBorrow => simple(signature::qubit_borrow_signature()),
+            JeffOpDef::QubitReturn => simple(signature::qubit_return_signature()),
             // Registers
-            JeffOpDef::QuregAlloc => {
-                PolyFuncType::new(vec![], Signature::new(vec![int32_t()], vec![qreg_t()])).into()
+            JeffOpDef::QuregAlloc => simple(signature::qureg_alloc_signature(extension_ref)),
+            JeffOpDef::QuregFree => simple(signature::qureg_free_signature(extension_ref)),
+            JeffOpDef::QuregExtractIndex => {
+                simple(signature::qureg_extract_index_signature(extension_ref))
             }
-            JeffOpDef::QuregFree => {
-                PolyFuncType::new(vec![], Signature::new(vec![qreg_t()], vec![])).into()
+            JeffOpDef::QuregInsertIndex => {
+                simple(signature::qureg_insert_index_signature(extension_ref))
             }
-            JeffOpDef::QuregExtractIndex => PolyFuncType::new(
-                vec![],
-                Signature::new(vec![qreg_t(), int32_t()], vec![qreg_t(), qb_t()]),
-            )
-            .into(),
-            JeffOpDef::QuregInsertIndex => PolyFuncType::new(
-                vec![],
-                Signature::new(vec![qreg_t(), qb_t(), int32_t()], vec![qreg_t()]),
-            )
-            .into(),
-            JeffOpDef::QuregCreate => JeffQuregCreateSignature.into(),
-            JeffOpDef::QuregExtractSlice => PolyFuncType::new(
-                vec![],
-                Signature::new(
-                    vec![qreg_t(), int32_t(), int32_t()],
-                    vec![qreg_t(), qreg_t()],
-                ),
-            )
-            .into(),
-            JeffOpDef::QuregInsertSlice => PolyFuncType::new(
-                vec![],
-                Signature::new(vec![qreg_t(), qreg_t(), int32_t()], vec![qreg_t()]),
-            )
-            .into(),
-            JeffOpDef::QuregSplit => PolyFuncType::new(
-                vec![],
-                Signature::new(vec![qreg_t(), int32_t()], vec![qreg_t(), qreg_t()]),
-            )
-            .into(),
-            JeffOpDef::QuregJoin => PolyFuncType::new(
-                vec![],
-                Signature::new(vec![qreg_t(), qreg_t()], vec![qreg_t()]),
-            )
-            .into(),
-            JeffOpDef::QuregLength => {
-                PolyFuncType::new(vec![], Signature::new(vec![qreg_t()], vec![int32_t()])).into()
+            JeffOpDef::QuregCreate => custom_signature(JeffQuregCreateSignature),
+            JeffOpDef::QuregExtractSlice => {
+                simple(signature::qureg_extract_slice_signature(extension_ref))
+            }
+            JeffOpDef::QuregInsertSlice => {
+                simple(signature::qureg_insert_slice_signature(extension_ref))
+            }
+            JeffOpDef::QuregSplit => simple(signature::qureg_split_signature(extension_ref)),
+            JeffOpDef::QuregJoin => simple(signature::qureg_join_signature(extension_ref)),
+            JeffOpDef::QuregLength => simple(signature::qureg_length_signature(extension_ref)),
+            JeffOpDef::QuregMeasure => simple(signature::qureg_measure_signature(extension_ref)),
+            JeffOpDef::QuregMeasureNd => {
+                simple(signature::qureg_measure_nd_signature(extension_ref))
+            }
+            JeffOpDef::QuregSwapElems => {
+                simple(signature::qureg_swap_elems_signature(extension_ref))
+            }
+            JeffOpDef::QuregReverse => simple(signature::qureg_reverse_signature(extension_ref)),
+            // State preparation
+            JeffOpDef::StatePrep => custom_signature(JeffStatePrepSignature),
+            JeffOpDef::QuregStatePrep => {
+                simple(signature::qureg_state_prep_signature(extension_ref))
             }
             // IntArrays
-            JeffOpDef::IntArrayCreate => JeffIntArrayCreateSignature.into(),
-            JeffOpDef::IntArrayLength => PolyFuncType::new(
-                vec![Term::max_nat_type()],
-                Signature::new(vec![intarr_type(0)], vec![int32_t()]),
-            )
-            .into(),
-            JeffOpDef::IntArrayGet => JeffIntArrayGetSignature.into(),
-            JeffOpDef::IntArraySet => JeffIntArraySetSignature.into(),
-            JeffOpDef::IntArrayZero => PolyFuncType::new(
-                vec![Term::max_nat_type()],
-                Signature::new(vec![int32_t()], vec![intarr_type(0)]),
-            )
-            .into(),
+            JeffOpDef::IntArrayCreate => custom_signature(JeffIntArrayCreateSignature),
+            JeffOpDef::IntArrayLength => custom_signature(JeffIntArrayLengthSignature),
+            JeffOpDef::IntArrayGet => custom_signature(JeffIntArrayGetSignature),
+            JeffOpDef::IntArraySet => custom_signature(JeffIntArraySetSignature),
+            JeffOpDef::IntArrayZero => custom_signature(JeffIntArrayZeroSignature),
+            JeffOpDef::IntArrayReverse => custom_signature(JeffIntArrayReverseSignature),
+            // FloatArrays
+            JeffOpDef::FloatArrayCreate => custom_signature(JeffFloatArrayCreateSignature),
+            JeffOpDef::FloatArrayGet => custom_signature(JeffFloatArrayGetSignature),
+            // Bitcasts
+            JeffOpDef::BitcastIntToFloat => custom_signature(JeffBitcastIntToFloatSignature),
+            JeffOpDef::BitcastFloatToInt => custom_signature(JeffBitcastFloatToIntSignature),
+            // Float functions
+            JeffOpDef::FloatUnaryFn => custom_signature(JeffFloatUnaryFnSignature),
+            JeffOpDef::FloatAtan2 => simple(signature::float_atan2_signature()),
+            // Debugging
+            JeffOpDef::DebugPrint => custom_signature(JeffDebugPrintSignature),
+            // Runtime queries
+            JeffOpDef::NumQubits | JeffOpDef::ShotCount => {
+                simple(signature::runtime_query_signature())
+            }
+            JeffOpDef::RuntimeQuery => custom_signature(JeffRuntimeQuerySignature),
         }
     }
 
     fn opdef_id(&self) -> hugr::ops::OpName {
         match self {
             JeffOpDef::QGate => "QGateN".into(),
+            JeffOpDef::GlobalPhase => "GlobalPhase".into(),
+            JeffOpDef::CondGate => "CondGate".into(),
+            JeffOpDef::QubitBorrow => "QubitBorrow".into(),
+            JeffOpDef::QubitReturn => "QubitReturn".into(),
             JeffOpDef::QuregAlloc => "QuregAlloc".into(),
             JeffOpDef::QuregFree => "QuregFree".into(),
             JeffOpDef::QuregExtractIndex => "QuregExtractIndex".into(),
@@ -320,17 +751,38 @@ impl MakeOpDef for JeffOpDef {
             JeffOpDef::QuregSplit => "QuregSplit".into(),
             JeffOpDef::QuregJoin => "QuregJoin".into(),
             JeffOpDef::QuregLength => "QuregLength".into(),
+            JeffOpDef::QuregMeasure => "QuregMeasure".into(),
+            JeffOpDef::QuregMeasureNd => "QuregMeasureNd".into(),
+            JeffOpDef::QuregSwapElems => "QuregSwapElems".into(),
+            JeffOpDef::QuregReverse => "QuregReverse".into(),
+            JeffOpDef::StatePrep => "StatePrep".into(),
+            JeffOpDef::QuregStatePrep => "QuregStatePrep".into(),
             JeffOpDef::IntArrayCreate => "IntArrayCreate".into(),
             JeffOpDef::IntArrayLength => "IntArrayLength".into(),
             JeffOpDef::IntArrayGet => "IntArrayGet".into(),
             JeffOpDef::IntArraySet => "IntArraySet".into(),
             JeffOpDef::IntArrayZero => "IntArrayZero".into(),
+            JeffOpDef::IntArrayReverse => "IntArrayReverse".into(),
+            JeffOpDef::FloatArrayCreate => "FloatArrayCreate".into(),
+            JeffOpDef::FloatArrayGet => "FloatArrayGet".into(),
+            JeffOpDef::BitcastIntToFloat => "BitcastIntToFloat".into(),
+            JeffOpDef::BitcastFloatToInt => "BitcastFloatToInt".into(),
+            JeffOpDef::FloatUnaryFn => "FloatUnaryFn".into(),
+            JeffOpDef::FloatAtan2 => "FloatAtan2".into(),
+            JeffOpDef::DebugPrint => "DebugPrint".into(),
+            JeffOpDef::NumQubits => "NumQubits".into(),
+            JeffOpDef::ShotCount => "ShotCount".into(),
+            JeffOpDef::RuntimeQuery => "RuntimeQuery".into(),
         }
     }
 
     fn description(&self) -> String {
         match self {
             JeffOpDef::QGate => "A jeff n-qubit gate.".to_string(),
+            JeffOpDef::GlobalPhase => "Apply a global phase to the state.".to_string(),
+            JeffOpDef::CondGate => "Apply a gate conditioned on a bit.".to_string(),
+            JeffOpDef::QubitBorrow => "Borrow a qubit in an unknown state.".to_string(),
+            JeffOpDef::QubitReturn => "Return a borrowed qubit.".to_string(),
             JeffOpDef::QuregAlloc => "Allocate a new qubit register.".to_string(),
             JeffOpDef::QuregFree => "Free a qubit register.".to_string(),
             JeffOpDef::QuregExtractIndex => "Extract a qubit from a register.".to_string(),
@@ -343,11 +795,28 @@ impl MakeOpDef for JeffOpDef {
             JeffOpDef::QuregSplit => "Split a register of qubits.".to_string(),
             JeffOpDef::QuregJoin => "Join two registers of qubits.".to_string(),
             JeffOpDef::QuregLength => "Get the length of a qubit register.".to_string(),
+            JeffOpDef::QuregMeasure => "Measure and free a qubit register.".to_string(),
+            JeffOpDef::QuregMeasureNd => "Measure a qubit register.".to_string(),
+            JeffOpDef::QuregSwapElems => "Swap two qubits of a register.".to_string(),
+            JeffOpDef::QuregReverse => "Reverse a qubit register.".to_string(),
+            JeffOpDef::StatePrep => "Prepare qubits in a given state.".to_string(),
+            JeffOpDef::QuregStatePrep => "Prepare a qubit register in a given state.".to_string(),
             JeffOpDef::IntArrayCreate => "Create a new IntArray.".to_string(),
             JeffOpDef::IntArrayLength => "Get the length of an IntArray.".to_string(),
             JeffOpDef::IntArrayGet => "Get the value at an index in an IntArray.".to_string(),
             JeffOpDef::IntArraySet => "Set the value at an index in an IntArray.".to_string(),
             JeffOpDef::IntArrayZero => "Create a zeroed IntArray.".to_string(),
+            JeffOpDef::IntArrayReverse => "Reverse the elements of an IntArray.".to_string(),
+            JeffOpDef::FloatArrayCreate => "Create a new FloatArray.".to_string(),
+            JeffOpDef::FloatArrayGet => "Get the value at an index in a FloatArray.".to_string(),
+            JeffOpDef::BitcastIntToFloat => "Reinterpret an integer as a float.".to_string(),
+            JeffOpDef::BitcastFloatToInt => "Reinterpret a float as an integer.".to_string(),
+            JeffOpDef::FloatUnaryFn => "Apply a named function to a float.".to_string(),
+            JeffOpDef::FloatAtan2 => "Compute the arctangent of two floats.".to_string(),
+            JeffOpDef::DebugPrint => "Print a message with a list of values.".to_string(),
+            JeffOpDef::NumQubits => "Query the number of device qubits.".to_string(),
+            JeffOpDef::ShotCount => "Query the number of shots.".to_string(),
+            JeffOpDef::RuntimeQuery => "Query a named runtime property.".to_string(),
         }
     }
 
@@ -364,7 +833,7 @@ impl MakeOpDef for JeffOpDef {
     }
 }
 
-/// A signature computation function for [`JeffOp::QGateN`].
+/// A signature computation function for [`JeffOp::QGate`].
 #[derive(Debug, Clone, Copy)]
 pub struct JeffGateNSignature;
 
@@ -386,17 +855,10 @@ impl CustomSignatureFunc for JeffGateNSignature {
             return Err(SignatureError::InvalidTypeArgs);
         };
 
-        let qubits = itertools::repeat_n(qb_t(), *num_qubits as usize);
-        let controls = itertools::repeat_n(qb_t(), *num_controls as usize);
-        let params = itertools::repeat_n(float64_type(), *num_params as usize);
-
-        let sig: PolyFuncType = Signature::new(
-            qubits
-                .clone()
-                .chain(controls.clone())
-                .chain(params)
-                .collect_vec(),
-            qubits.chain(controls).collect_vec(),
+        let sig: PolyFuncType = signature::qgate_signature(
+            *num_qubits as usize,
+            *num_params as usize,
+            *num_controls as usize,
         )
         .into();
         Ok(sig.into())
@@ -415,6 +877,39 @@ impl CustomSignatureFunc for JeffGateNSignature {
     }
 }
 
+/// A signature computation function for [`JeffOp::CondGate`].
+#[derive(Debug, Clone, Copy)]
+pub struct JeffCondGateSignature;
+
+impl CustomSignatureFunc for JeffCondGateSignature {
+    fn compute_signature<'o, 'a: 'o>(
+        &'a self,
+        arg_values: &[Term],
+        _def: &'o OpDef,
+    ) -> Result<PolyFuncTypeRV, SignatureError> {
+        let [
+            Term::String(_gate),
+            Term::BoundedNat(qubits),
+            Term::BoundedNat(_outcome),
+        ] = arg_values
+        else {
+            return Err(SignatureError::InvalidTypeArgs);
+        };
+
+        let sig: PolyFuncType = signature::cond_gate_signature(*qubits as usize).into();
+        Ok(sig.into())
+    }
+
+    fn static_params(&self) -> &[Term] {
+        static PARAMS: [Term; 3] = [
+            Term::StringType,
+            Term::max_nat_type(),
+            Term::bounded_nat_type(NonZero::new(2).unwrap()),
+        ];
+        &PARAMS
+    }
+}
+
 /// A signature computation function for [`JeffOp::QuregCreate`].
 #[derive(Debug, Clone, Copy)]
 pub struct JeffQuregCreateSignature;
@@ -423,13 +918,37 @@ impl CustomSignatureFunc for JeffQuregCreateSignature {
     fn compute_signature<'o, 'a: 'o>(
         &'a self,
         arg_values: &[Term],
-        _def: &'o OpDef,
+        def: &'o OpDef,
     ) -> Result<PolyFuncTypeRV, SignatureError> {
         let qubits = arg_values[0].as_nat().expect("JeffOp arg should be a nat") as usize;
 
-        let inputs = vec![qb_t(); qubits];
-        let outputs = vec![crate::types::jeff_to_hugr(jeff::types::Type::QubitRegister)];
-        let sig: PolyFuncType = Signature::new(inputs, outputs).into();
+        let sig: PolyFuncType =
+            signature::qureg_create_signature(def.extension_weak(), qubits).into();
+        Ok(sig.into())
+    }
+
+    fn static_params(&self) -> &[Term] {
+        static PARAMS: [Term; 1] = [Term::max_nat_type()];
+        &PARAMS
+    }
+}
+
+/// A signature computation function for [`JeffOp::StatePrep`].
+#[derive(Debug, Clone, Copy)]
+pub struct JeffStatePrepSignature;
+
+impl CustomSignatureFunc for JeffStatePrepSignature {
+    fn compute_signature<'o, 'a: 'o>(
+        &'a self,
+        arg_values: &[Term],
+        def: &'o OpDef,
+    ) -> Result<PolyFuncTypeRV, SignatureError> {
+        let [Term::BoundedNat(qubits)] = arg_values else {
+            return Err(SignatureError::InvalidTypeArgs);
+        };
+
+        let sig: PolyFuncType =
+            signature::state_prep_signature(def.extension_weak(), *qubits as usize).into();
         Ok(sig.into())
     }
 
@@ -447,15 +966,13 @@ impl CustomSignatureFunc for JeffIntArrayCreateSignature {
     fn compute_signature<'o, 'a: 'o>(
         &'a self,
         arg_values: &[Term],
-        _def: &'o OpDef,
+        def: &'o OpDef,
     ) -> Result<PolyFuncTypeRV, SignatureError> {
         let bits = arg_values[0].as_nat().expect("JeffOp arg should be a nat") as u8;
         let input_count = arg_values[1].as_nat().expect("JeffOp arg should be a nat") as usize;
 
-        let int_type = crate::types::jeff_to_hugr(jeff::types::Type::Int { bits });
-        let inputs = vec![int_type; input_count];
-        let outputs = vec![intreg_type(bits)];
-        let sig: PolyFuncType = Signature::new(inputs, outputs).into();
+        let sig: PolyFuncType =
+            signature::intarray_create_signature(def.extension_weak(), bits, input_count).into();
         Ok(sig.into())
     }
 
@@ -465,6 +982,29 @@ impl CustomSignatureFunc for JeffIntArrayCreateSignature {
     }
 }
 
+/// A signature computation function for [`JeffOp::IntArrayLength`].
+#[derive(Debug, Clone, Copy)]
+pub struct JeffIntArrayLengthSignature;
+
+impl CustomSignatureFunc for JeffIntArrayLengthSignature {
+    fn compute_signature<'o, 'a: 'o>(
+        &'a self,
+        arg_values: &[Term],
+        def: &'o OpDef,
+    ) -> Result<PolyFuncTypeRV, SignatureError> {
+        let bits = arg_values[0].as_nat().expect("JeffOp arg should be a nat") as u8;
+
+        let sig: PolyFuncType =
+            signature::intarray_length_signature(def.extension_weak(), bits).into();
+        Ok(sig.into())
+    }
+
+    fn static_params(&self) -> &[Term] {
+        static PARAMS: [Term; 1] = [Term::max_nat_type()];
+        &PARAMS
+    }
+}
+
 /// A signature computation function for [`JeffOp::IntArrayGet`].
 #[derive(Debug, Clone, Copy)]
 pub struct JeffIntArrayGetSignature;
@@ -473,16 +1013,12 @@ impl CustomSignatureFunc for JeffIntArrayGetSignature {
     fn compute_signature<'o, 'a: 'o>(
         &'a self,
         arg_values: &[Term],
-        _def: &'o OpDef,
+        def: &'o OpDef,
     ) -> Result<PolyFuncTypeRV, SignatureError> {
         let bits = arg_values[0].as_nat().expect("JeffOp arg should be a nat") as u8;
 
-        let int_type = crate::types::jeff_to_hugr(jeff::types::Type::Int { bits });
-        let int32_t = crate::types::jeff_to_hugr(jeff::types::Type::Int { bits: 32 });
-
-        let inputs = vec![intreg_type(bits), int32_t];
-        let outputs = vec![int_type];
-        let sig: PolyFuncType = Signature::new(inputs, outputs).into();
+        let sig: PolyFuncType =
+            signature::intarray_get_signature(def.extension_weak(), bits).into();
         Ok(sig.into())
     }
 
@@ -500,16 +1036,105 @@ impl CustomSignatureFunc for JeffIntArraySetSignature {
     fn compute_signature<'o, 'a: 'o>(
         &'a self,
         arg_values: &[Term],
-        _def: &'o OpDef,
+        def: &'o OpDef,
+    ) -> Result<PolyFuncTypeRV, SignatureError> {
+        let bits = arg_values[0].as_nat().expect("JeffOp arg should be a nat") as u8;
+
+        let sig: PolyFuncType =
+            signature::intarray_set_signature(def.extension_weak(), bits).into();
+        Ok(sig.into())
+    }
+
+    fn static_params(&self) -> &[Term] {
+        static PARAMS: [Term; 1] = [Term::max_nat_type()];
+        &PARAMS
+    }
+}
+
+/// A signature computation function for [`JeffOp::IntArrayZero`].
+#[derive(Debug, Clone, Copy)]
+pub struct JeffIntArrayZeroSignature;
+
+impl CustomSignatureFunc for JeffIntArrayZeroSignature {
+    fn compute_signature<'o, 'a: 'o>(
+        &'a self,
+        arg_values: &[Term],
+        def: &'o OpDef,
+    ) -> Result<PolyFuncTypeRV, SignatureError> {
+        let bits = arg_values[0].as_nat().expect("JeffOp arg should be a nat") as u8;
+
+        let sig: PolyFuncType =
+            signature::intarray_zero_signature(def.extension_weak(), bits).into();
+        Ok(sig.into())
+    }
+
+    fn static_params(&self) -> &[Term] {
+        static PARAMS: [Term; 1] = [Term::max_nat_type()];
+        &PARAMS
+    }
+}
+
+/// A signature computation function for [`JeffOp::IntArrayReverse`].
+#[derive(Debug, Clone, Copy)]
+pub struct JeffIntArrayReverseSignature;
+
+impl CustomSignatureFunc for JeffIntArrayReverseSignature {
+    fn compute_signature<'o, 'a: 'o>(
+        &'a self,
+        arg_values: &[Term],
+        def: &'o OpDef,
+    ) -> Result<PolyFuncTypeRV, SignatureError> {
+        let bits = arg_values[0].as_nat().expect("JeffOp arg should be a nat") as u8;
+
+        let sig: PolyFuncType =
+            signature::intarray_reverse_signature(def.extension_weak(), bits).into();
+        Ok(sig.into())
+    }
+
+    fn static_params(&self) -> &[Term] {
+        static PARAMS: [Term; 1] = [Term::max_nat_type()];
+        &PARAMS
+    }
+}
+
+/// A signature computation function for [`JeffOp::FloatArrayCreate`].
+#[derive(Debug, Clone, Copy)]
+pub struct JeffFloatArrayCreateSignature;
+
+impl CustomSignatureFunc for JeffFloatArrayCreateSignature {
+    fn compute_signature<'o, 'a: 'o>(
+        &'a self,
+        arg_values: &[Term],
+        def: &'o OpDef,
     ) -> Result<PolyFuncTypeRV, SignatureError> {
         let bits = arg_values[0].as_nat().expect("JeffOp arg should be a nat") as u8;
+        let input_count = arg_values[1].as_nat().expect("JeffOp arg should be a nat") as usize;
+
+        let sig: PolyFuncType =
+            signature::floatarray_create_signature(def.extension_weak(), bits, input_count).into();
+        Ok(sig.into())
+    }
+
+    fn static_params(&self) -> &[Term] {
+        static PARAMS: [Term; 2] = [Term::max_nat_type(), Term::max_nat_type()];
+        &PARAMS
+    }
+}
 
-        let int_type = crate::types::jeff_to_hugr(jeff::types::Type::Int { bits });
-        let int32_t = crate::types::jeff_to_hugr(jeff::types::Type::Int { bits: 32 });
+/// A signature computation function for [`JeffOp::FloatArrayGet`].
+#[derive(Debug, Clone, Copy)]
+pub struct JeffFloatArrayGetSignature;
+
+impl CustomSignatureFunc for JeffFloatArrayGetSignature {
+    fn compute_signature<'o, 'a: 'o>(
+        &'a self,
+        arg_values: &[Term],
+        def: &'o OpDef,
+    ) -> Result<PolyFuncTypeRV, SignatureError> {
+        let bits = arg_values[0].as_nat().expect("JeffOp arg should be a nat") as u8;
 
-        let inputs = vec![intreg_type(bits), int32_t, int_type];
-        let outputs = vec![intreg_type(bits)];
-        let sig: PolyFuncType = Signature::new(inputs, outputs).into();
+        let sig: PolyFuncType =
+            signature::floatarray_get_signature(def.extension_weak(), bits).into();
         Ok(sig.into())
     }
 
@@ -519,6 +1144,146 @@ impl CustomSignatureFunc for JeffIntArraySetSignature {
     }
 }
 
+/// A signature computation function for [`JeffOp::BitcastIntToFloat`].
+#[derive(Debug, Clone, Copy)]
+pub struct JeffBitcastIntToFloatSignature;
+
+impl CustomSignatureFunc for JeffBitcastIntToFloatSignature {
+    fn compute_signature<'o, 'a: 'o>(
+        &'a self,
+        arg_values: &[Term],
+        _def: &'o OpDef,
+    ) -> Result<PolyFuncTypeRV, SignatureError> {
+        let [Term::BoundedNat(bits @ (32 | 64))] = arg_values else {
+            return Err(SignatureError::InvalidTypeArgs);
+        };
+
+        let sig: PolyFuncType = signature::bitcast_int_to_float_signature(*bits as u8).into();
+        Ok(sig.into())
+    }
+
+    fn static_params(&self) -> &[Term] {
+        static PARAMS: [Term; 1] = [Term::max_nat_type()];
+        &PARAMS
+    }
+}
+
+/// A signature computation function for [`JeffOp::BitcastFloatToInt`].
+#[derive(Debug, Clone, Copy)]
+pub struct JeffBitcastFloatToIntSignature;
+
+impl CustomSignatureFunc for JeffBitcastFloatToIntSignature {
+    fn compute_signature<'o, 'a: 'o>(
+        &'a self,
+        arg_values: &[Term],
+        _def: &'o OpDef,
+    ) -> Result<PolyFuncTypeRV, SignatureError> {
+        let [Term::BoundedNat(bits @ (32 | 64))] = arg_values else {
+            return Err(SignatureError::InvalidTypeArgs);
+        };
+
+        let sig: PolyFuncType = signature::bitcast_float_to_int_signature(*bits as u8).into();
+        Ok(sig.into())
+    }
+
+    fn static_params(&self) -> &[Term] {
+        static PARAMS: [Term; 1] = [Term::max_nat_type()];
+        &PARAMS
+    }
+}
+
+/// A signature computation function for [`JeffOp::FloatUnaryFn`].
+#[derive(Debug, Clone, Copy)]
+pub struct JeffFloatUnaryFnSignature;
+
+impl CustomSignatureFunc for JeffFloatUnaryFnSignature {
+    fn compute_signature<'o, 'a: 'o>(
+        &'a self,
+        arg_values: &[Term],
+        _def: &'o OpDef,
+    ) -> Result<PolyFuncTypeRV, SignatureError> {
+        let [Term::String(_name)] = arg_values else {
+            return Err(SignatureError::InvalidTypeArgs);
+        };
+
+        let sig: PolyFuncType = signature::float_unary_fn_signature().into();
+        Ok(sig.into())
+    }
+
+    fn static_params(&self) -> &[Term] {
+        static PARAMS: [Term; 1] = [Term::StringType];
+        &PARAMS
+    }
+}
+
+/// A signature computation function for [`JeffOp::DebugPrint`].
+#[derive(Debug, Clone, Copy)]
+pub struct JeffDebugPrintSignature;
+
+impl CustomSignatureFunc for JeffDebugPrintSignature {
+    fn compute_signature<'o, 'a: 'o>(
+        &'a self,
+        arg_values: &[Term],
+        _def: &'o OpDef,
+    ) -> Result<PolyFuncTypeRV, SignatureError> {
+        let [Term::String(_format), types] = arg_values else {
+            return Err(SignatureError::InvalidTypeArgs);
+        };
+        let types = runtime_type_list(types)?;
+
+        let sig: PolyFuncType = signature::debug_print_signature(types).into();
+        Ok(sig.into())
+    }
+
+    fn static_params(&self) -> &[Term] {
+        lazy_static! {
+            static ref PARAMS: [Term; 2] = [
+                Term::StringType,
+                Term::new_list_type(TypeBound::Copyable),
+            ];
+        }
+        PARAMS.as_slice()
+    }
+}
+
+/// A signature computation function for [`JeffOp::RuntimeQuery`].
+#[derive(Debug, Clone, Copy)]
+pub struct JeffRuntimeQuerySignature;
+
+impl CustomSignatureFunc for JeffRuntimeQuerySignature {
+    fn compute_signature<'o, 'a: 'o>(
+        &'a self,
+        arg_values: &[Term],
+        _def: &'o OpDef,
+    ) -> Result<PolyFuncTypeRV, SignatureError> {
+        let [Term::String(_name)] = arg_values else {
+            return Err(SignatureError::InvalidTypeArgs);
+        };
+
+        let sig: PolyFuncType = signature::runtime_query_signature().into();
+        Ok(sig.into())
+    }
+
+    fn static_params(&self) -> &[Term] {
+        static PARAMS: [Term; 1] = [Term::StringType];
+        &PARAMS
+    }
+}
+
+/// Read a list of runtime types from a type argument.
+fn runtime_type_list(arg: &Term) -> Result<Vec<HugrType>, SignatureError> {
+    let Term::List(elems) = arg else {
+        return Err(SignatureError::InvalidTypeArgs);
+    };
+    elems
+        .iter()
+        .map(|elem| match elem {
+            Term::Runtime(ty) => Ok(ty.clone()),
+            _ => Err(SignatureError::InvalidTypeArgs),
+        })
+        .collect()
+}
+
 impl MakeExtensionOp for JeffOp {
     fn from_extension_op(ext_op: &ExtensionOp) -> Result<Self, OpLoadError> {
         let def = JeffOpDef::from_def(ext_op.def())?;
@@ -542,6 +1307,18 @@ impl MakeExtensionOp for JeffOp {
                 Term::BoundedNat(*adjoint as u64),
                 Term::BoundedNat(*power as u64),
             ],
+            JeffOp::GlobalPhase => vec![],
+            JeffOp::CondGate {
+                gate,
+                qubits,
+                outcome,
+            } => vec![
+                Term::String(gate.clone()),
+                Term::BoundedNat(*qubits as u64),
+                Term::BoundedNat(*outcome as u64),
+            ],
+            JeffOp::QubitBorrow => vec![],
+            JeffOp::QubitReturn => vec![],
             JeffOp::QuregAlloc => vec![],
             JeffOp::QuregFree => vec![],
             JeffOp::QuregExtractIndex => vec![],
@@ -552,6 +1329,12 @@ impl MakeExtensionOp for JeffOp {
             JeffOp::QuregSplit => vec![],
             JeffOp::QuregJoin => vec![],
             JeffOp::QuregLength => vec![],
+            JeffOp::QuregMeasure => vec![],
+            JeffOp::QuregMeasureNd => vec![],
+            JeffOp::QuregSwapElems => vec![],
+            JeffOp::QuregReverse => vec![],
+            JeffOp::StatePrep { qubits } => vec![Term::BoundedNat(*qubits as u64)],
+            JeffOp::QuregStatePrep => vec![],
             JeffOp::IntArrayCreate { bits, inputs } => vec![
                 Term::BoundedNat(*bits as u64),
                 Term::BoundedNat(*inputs as u64),
@@ -560,6 +1343,23 @@ impl MakeExtensionOp for JeffOp {
             JeffOp::IntArrayGet { bits } => vec![Term::BoundedNat(*bits as u64)],
             JeffOp::IntArraySet { bits } => vec![Term::BoundedNat(*bits as u64)],
             JeffOp::IntArrayZero { bits } => vec![Term::BoundedNat(*bits as u64)],
+            JeffOp::IntArrayReverse { bits } => vec![Term::BoundedNat(*bits as u64)],
+            JeffOp::FloatArrayCreate { bits, inputs } => vec![
+                Term::BoundedNat(*bits as u64),
+                Term::BoundedNat(*inputs as u64),
+            ],
+            JeffOp::FloatArrayGet { bits } => vec![Term::BoundedNat(*bits as u64)],
+            JeffOp::BitcastIntToFloat { bits } => vec![Term::BoundedNat(*bits as u64)],
+            JeffOp::BitcastFloatToInt { bits } => vec![Term::BoundedNat(*bits as u64)],
+            JeffOp::FloatUnaryFn { name } => vec![Term::String(name.clone())],
+            JeffOp::FloatAtan2 => vec![],
+            JeffOp::DebugPrint { format, types } => vec![
+                Term::String(format.clone()),
+                Term::new_list(types.iter().cloned().map(Term::from)),
+            ],
+            JeffOp::NumQubits => vec![],
+            JeffOp::ShotCount => vec![],
+            JeffOp::RuntimeQuery { name } => vec![Term::String(name.clone())],
         }
     }
 
@@ -582,7 +1382,9 @@ impl HasConcrete for JeffOpDef {
     type Concrete = JeffOp;
 
     fn instantiate(&self, type_args: &[Term]) -> Result<Self::Concrete, OpLoadError> {
-        match (self, type_args) {
+        // Arguments stored by older versions of the extension.
+        let type_args = compat::upgrade_args(*self, type_args);
+        match (self, type_args.as_ref()) {
             (
                 JeffOpDef::QGate,
                 [
@@ -601,6 +1403,21 @@ impl HasConcrete for JeffOpDef {
                 *adjoint != 0,
                 *power as usize,
             )),
+            (JeffOpDef::GlobalPhase, []) => Ok(JeffOp::GlobalPhase),
+            (
+                JeffOpDef::CondGate,
+                [
+                    Term::String(gate),
+                    Term::BoundedNat(qubits),
+                    Term::BoundedNat(outcome),
+                ],
+            ) => Ok(JeffOp::CondGate {
+                gate: gate.clone(),
+                qubits: *qubits as usize,
+                outcome: *outcome != 0,
+            }),
+            (JeffOpDef::QubitBorrow, []) => Ok(JeffOp::QubitBorrow),
+            (JeffOpDef::QubitReturn, []) => Ok(JeffOp::QubitReturn),
             (JeffOpDef::QuregAlloc, []) => Ok(JeffOp::QuregAlloc),
             (JeffOpDef::QuregFree, []) => Ok(JeffOp::QuregFree),
             (JeffOpDef::QuregExtractIndex, []) => Ok(JeffOp::QuregExtractIndex),
@@ -613,6 +1430,14 @@ impl HasConcrete for JeffOpDef {
             (JeffOpDef::QuregSplit, []) => Ok(JeffOp::QuregSplit),
             (JeffOpDef::QuregJoin, []) => Ok(JeffOp::QuregJoin),
             (JeffOpDef::QuregLength, []) => Ok(JeffOp::QuregLength),
+            (JeffOpDef::QuregMeasure, []) => Ok(JeffOp::QuregMeasure),
+            (JeffOpDef::QuregMeasureNd, []) => Ok(JeffOp::QuregMeasureNd),
+            (JeffOpDef::QuregSwapElems, []) => Ok(JeffOp::QuregSwapElems),
+            (JeffOpDef::QuregReverse, []) => Ok(JeffOp::QuregReverse),
+            (JeffOpDef::StatePrep, [Term::BoundedNat(qubits)]) => Ok(JeffOp::StatePrep {
+                qubits: *qubits as usize,
+            }),
+            (JeffOpDef::QuregStatePrep, []) => Ok(JeffOp::QuregStatePrep),
             (JeffOpDef::IntArrayCreate, [Term::BoundedNat(bits), Term::BoundedNat(inputs)]) => {
                 Ok(JeffOp::IntArrayCreate {
                     bits: *bits as u8,
@@ -631,6 +1456,37 @@ impl HasConcrete for JeffOpDef {
             (JeffOpDef::IntArrayZero, [Term::BoundedNat(bits)]) => {
                 Ok(JeffOp::IntArrayZero { bits: *bits as u8 })
             }
+            (JeffOpDef::IntArrayReverse, [Term::BoundedNat(bits)]) => {
+                Ok(JeffOp::IntArrayReverse { bits: *bits as u8 })
+            }
+            (JeffOpDef::FloatArrayCreate, [Term::BoundedNat(bits), Term::BoundedNat(inputs)]) => {
+                Ok(JeffOp::FloatArrayCreate {
+                    bits: *bits as u8,
+                    inputs: *inputs as usize,
+                })
+            }
+            (JeffOpDef::FloatArrayGet, [Term::BoundedNat(bits)]) => {
+                Ok(JeffOp::FloatArrayGet { bits: *bits as u8 })
+            }
+            (JeffOpDef::BitcastIntToFloat, [Term::BoundedNat(bits @ (32 | 64))]) => {
+                Ok(JeffOp::BitcastIntToFloat { bits: *bits as u8 })
+            }
+            (JeffOpDef::BitcastFloatToInt, [Term::BoundedNat(bits @ (32 | 64))]) => {
+                Ok(JeffOp::BitcastFloatToInt { bits: *bits as u8 })
+            }
+            (JeffOpDef::FloatUnaryFn, [Term::String(name)]) => {
+                Ok(JeffOp::FloatUnaryFn { name: name.clone() })
+            }
+            (JeffOpDef::FloatAtan2, []) => Ok(JeffOp::FloatAtan2),
+            (JeffOpDef::DebugPrint, [Term::String(format), types]) => Ok(JeffOp::DebugPrint {
+                format: format.clone(),
+                types: runtime_type_list(types)?,
+            }),
+            (JeffOpDef::NumQubits, []) => Ok(JeffOp::NumQubits),
+            (JeffOpDef::ShotCount, []) => Ok(JeffOp::ShotCount),
+            (JeffOpDef::RuntimeQuery, [Term::String(name)]) => {
+                Ok(JeffOp::RuntimeQuery { name: name.clone() })
+            }
             _ => Err(SignatureError::InvalidTypeArgs.into()),
         }
     }
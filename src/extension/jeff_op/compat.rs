@@ -0,0 +1,296 @@
+//! Compatibility of the operation type arguments across extension versions.
+//!
+//! HUGRs stored by older versions of this crate encode the operations with the
+//! type arguments of their extension version. The arguments of the older
+//! layouts listed in [`TYPE_ARG_LAYOUTS`] are upgraded to the current ones
+//! when the operations are loaded.
+
+use std::borrow::Cow;
+
+use hugr::extension::Version;
+use hugr::types::Term;
+
+use super::JeffOpDef;
+
+/// The kind of an operation type argument.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ArgKind {
+    /// A [`Term::String`].
+    String,
+    /// A [`Term::BoundedNat`].
+    Nat,
+    /// A [`Term::List`].
+    List,
+}
+
+impl ArgKind {
+    /// The kind of a type argument, if it is used by the operations.
+    pub(crate) fn of(arg: &Term) -> Option<Self> {
+        match arg {
+            Term::String(_) => Some(Self::String),
+            Term::BoundedNat(_) => Some(Self::Nat),
+            Term::List(_) => Some(Self::List),
+            _ => None,
+        }
+    }
+}
+
+/// The type argument layout of an operation, since an extension version.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ArgLayout {
+    /// The operation definition.
+    pub def: JeffOpDef,
+    /// The first extension version using the layout.
+    pub since: Version,
+    /// The kinds of the type arguments.
+    pub args: &'static [ArgKind],
+    /// Convert arguments of this layout to the next layout of the operation.
+    ///
+    /// Only the current layout of an operation has no upgrade.
+    pub upgrade: Option<fn(&[Term]) -> Vec<Term>>,
+}
+
+/// The first version of the extension.
+const V0_1_0: Version = Version::new(0, 1, 0);
+
+/// The version adding the operations after the register and integer array
+/// ones.
+///
+/// It also changes the signature of [`JeffOpDef::QuregLength`], which returns
+/// the register along with its length. Its type arguments are unchanged.
+const V0_2_0: Version = Version::new(0, 2, 0);
+
+/// The type argument layouts of the operations, oldest first.
+///
+/// Changing the arguments of an operation requires adding its new layout
+/// here, bumping [`JEFF_EXTENSION_VERSION`](crate::extension::JEFF_EXTENSION_VERSION),
+/// and giving the previous layout an upgrade filling in the new arguments with
+/// their defaults. The `current_layouts` test fails until the table is updated.
+pub const TYPE_ARG_LAYOUTS: &[ArgLayout] = {
+    use ArgKind::*;
+
+    const fn layout(def: JeffOpDef, args: &'static [ArgKind]) -> ArgLayout {
+        ArgLayout {
+            def,
+            since: V0_1_0,
+            args,
+            upgrade: None,
+        }
+    }
+
+    const fn added(def: JeffOpDef, args: &'static [ArgKind]) -> ArgLayout {
+        ArgLayout {
+            since: V0_2_0,
+            ..layout(def, args)
+        }
+    }
+
+    &[
+        layout(JeffOpDef::QGate, &[String, Nat, Nat, Nat, Nat, Nat]),
+        added(JeffOpDef::GlobalPhase, &[]),
+        added(JeffOpDef::CondGate, &[String, Nat, Nat]),
+        added(JeffOpDef::QubitBorrow, &[]),
+        added(JeffOpDef::QubitReturn, &[]),
+        layout(JeffOpDef::QuregAlloc, &[]),
+        layout(JeffOpDef::QuregFree, &[]),
+        layout(JeffOpDef::QuregExtractIndex, &[]),
+        layout(JeffOpDef::QuregInsertIndex, &[]),
+        layout(JeffOpDef::QuregCreate, &[Nat]),
+        layout(JeffOpDef::QuregExtractSlice, &[]),
+        layout(JeffOpDef::QuregInsertSlice, &[]),
+        layout(JeffOpDef::QuregSplit, &[]),
+        layout(JeffOpDef::QuregJoin, &[]),
+        layout(JeffOpDef::QuregLength, &[]),
+        added(JeffOpDef::QuregMeasure, &[]),
+        added(JeffOpDef::QuregMeasureNd, &[]),
+        added(JeffOpDef::QuregSwapElems, &[]),
+        added(JeffOpDef::QuregReverse, &[]),
+        added(JeffOpDef::StatePrep, &[Nat]),
+        added(JeffOpDef::QuregStatePrep, &[]),
+        layout(JeffOpDef::IntArrayCreate, &[Nat, Nat]),
+        layout(JeffOpDef::IntArrayLength, &[Nat]),
+        layout(JeffOpDef::IntArrayGet, &[Nat]),
+        layout(JeffOpDef::IntArraySet, &[Nat]),
+        layout(JeffOpDef::IntArrayZero, &[Nat]),
+        added(JeffOpDef::IntArrayReverse, &[Nat]),
+        added(JeffOpDef::FloatArrayCreate, &[Nat, Nat]),
+        added(JeffOpDef::FloatArrayGet, &[Nat]),
+        added(JeffOpDef::BitcastIntToFloat, &[Nat]),
+        added(JeffOpDef::BitcastFloatToInt, &[Nat]),
+        added(JeffOpDef::FloatUnaryFn, &[String]),
+        added(JeffOpDef::FloatAtan2, &[]),
+        added(JeffOpDef::DebugPrint, &[String, List]),
+        added(JeffOpDef::NumQubits, &[]),
+        added(JeffOpDef::ShotCount, &[]),
+        added(JeffOpDef::RuntimeQuery, &[String]),
+    ]
+};
+
+/// Convert the type arguments of an operation to its current layout.
+///
+/// Arguments matching an older layout are upgraded through the following
+/// ones. Other arguments are returned unchanged, to be checked when
+/// instantiating the operation.
+pub(crate) fn upgrade_args(def: JeffOpDef, args: &[Term]) -> Cow<'_, [Term]> {
+    let Some(kinds) = args.iter().map(ArgKind::of).collect::<Option<Vec<_>>>() else {
+        return Cow::Borrowed(args);
+    };
+    let mut layouts = TYPE_ARG_LAYOUTS
+        .iter()
+        .filter(|layout| layout.def == def)
+        .skip_while(|layout| layout.args != kinds.as_slice());
+    let mut args = Cow::Borrowed(args);
+    while let Some(upgrade) = layouts.next().and_then(|layout| layout.upgrade) {
+        args = Cow::Owned(upgrade(&args));
+    }
+    args
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::extension::signature::test::instances;
+    use crate::extension::{JEFF_EXTENSION, JEFF_EXTENSION_VERSION, JeffOp};
+    use crate::hugr_importer::extension_registry;
+    use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr};
+    use hugr::envelope::EnvelopeConfig;
+    use hugr::extension::prelude::bool_t;
+    use hugr::extension::simple_op::MakeExtensionOp;
+    use hugr::ops::{ExtensionOp, OpType};
+    use hugr::std_extensions::arithmetic::float_types::float64_type;
+    use hugr::{Hugr, HugrView};
+    use rstest::rstest;
+    use strum::IntoEnumIterator;
+
+    /// The current type argument layout of an operation.
+    fn current_layout(def: JeffOpDef) -> Option<&'static ArgLayout> {
+        TYPE_ARG_LAYOUTS.iter().rfind(|layout| layout.def == def)
+    }
+
+    /// The layouts must describe the arguments of every operation.
+    #[test]
+    fn current_layouts() {
+        for def in JeffOpDef::iter() {
+            let layout = current_layout(def)
+                .unwrap_or_else(|| panic!("No type argument layout for {def:?}"));
+            assert!(layout.since <= JEFF_EXTENSION_VERSION);
+            assert!(layout.upgrade.is_none());
+            let older = TYPE_ARG_LAYOUTS
+                .iter()
+                .filter(|older| older.def == def && !std::ptr::eq(*older, layout));
+            for older in older {
+                assert!(older.since < layout.since, "Layouts of {def:?} out of order");
+                assert!(older.upgrade.is_some(), "No upgrade of {def:?} since {}", older.since);
+            }
+            for op in instances(def) {
+                let kinds = op
+                    .type_args()
+                    .iter()
+                    .map(ArgKind::of)
+                    .collect::<Option<Vec<_>>>();
+                assert_eq!(
+                    kinds.as_deref(),
+                    Some(layout.args),
+                    "The type arguments of {op:?} changed, update TYPE_ARG_LAYOUTS"
+                );
+            }
+        }
+    }
+
+    /// Store an operation with the given arguments in a HUGR envelope.
+    fn stored_op(name: &str, args: Vec<Term>) -> String {
+        let def = JEFF_EXTENSION.get_op(name).unwrap();
+        let op = ExtensionOp::new(Arc::clone(def), args).unwrap();
+        let sig = OpType::from(op.clone())
+            .dataflow_signature()
+            .unwrap()
+            .into_owned();
+        let mut builder = DFGBuilder::new(sig).unwrap();
+        let inputs = builder.input_wires();
+        let outputs = builder.add_dataflow_op(op, inputs).unwrap().outputs();
+        let hugr = builder.finish_hugr_with_outputs(outputs).unwrap();
+        hugr.store_str(EnvelopeConfig::text()).unwrap()
+    }
+
+    /// The operations of the loaded HUGR.
+    fn loaded_ops(envelope: &str) -> Vec<JeffOp> {
+        let hugr = Hugr::load_str(envelope, Some(&extension_registry())).unwrap();
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        hugr.nodes()
+            .filter_map(|node| hugr.get_optype(node).cast::<JeffOp>())
+            .collect()
+    }
+
+    /// Store an operation with the given arguments, and check it loads as
+    /// the expected operation.
+    fn assert_loads(name: &str, args: Vec<Term>, expected: JeffOp) {
+        assert_eq!(
+            current_layout(expected.opdef()).unwrap().args.len(),
+            args.len()
+        );
+        let envelope = stored_op(name, args);
+        assert_eq!(loaded_ops(&envelope), [expected]);
+    }
+
+    /// Operations stored with the arguments of extension version 0.1.0.
+    #[rstest]
+    #[case::qgate("QGateN", vec![
+        Term::String("G".into()),
+        Term::BoundedNat(2),
+        Term::BoundedNat(1),
+        Term::BoundedNat(1),
+        Term::BoundedNat(0),
+        Term::BoundedNat(1),
+    ], JeffOp::quantum_gate("G".to_string(), 2, 1, 1, false, 1))]
+    #[case::qureg_create("QuregCreate", vec![Term::BoundedNat(3)], JeffOp::QuregCreate { qubits: 3 })]
+    #[case::qureg_length("QuregLength", vec![], JeffOp::QuregLength)]
+    #[case::int_array_create(
+        "IntArrayCreate",
+        vec![Term::BoundedNat(8), Term::BoundedNat(2)],
+        JeffOp::IntArrayCreate { bits: 8, inputs: 2 }
+    )]
+    #[case::int_array_length(
+        "IntArrayLength",
+        vec![Term::BoundedNat(32)],
+        JeffOp::IntArrayLength { bits: 32 }
+    )]
+    fn load_v0_1_0(#[case] name: &str, #[case] args: Vec<Term>, #[case] expected: JeffOp) {
+        assert_eq!(current_layout(expected.opdef()).unwrap().since, V0_1_0);
+        assert_loads(name, args, expected);
+    }
+
+    /// Operations added in extension version 0.2.0.
+    #[rstest]
+    #[case::cond_gate("CondGate", vec![
+        Term::String("X".into()),
+        Term::BoundedNat(1),
+        Term::BoundedNat(1),
+    ], JeffOp::CondGate { gate: "X".to_string(), qubits: 1, outcome: true })]
+    #[case::float_array_get(
+        "FloatArrayGet",
+        vec![Term::BoundedNat(64)],
+        JeffOp::FloatArrayGet { bits: 64 }
+    )]
+    #[case::debug_print("DebugPrint", vec![
+        Term::String("{} {}".into()),
+        Term::new_list([Term::from(bool_t()), Term::from(float64_type())]),
+    ], JeffOp::DebugPrint {
+        format: "{} {}".to_string(),
+        types: vec![bool_t(), float64_type()],
+    })]
+    fn load_v0_2_0(#[case] name: &str, #[case] args: Vec<Term>, #[case] expected: JeffOp) {
+        assert_eq!(current_layout(expected.opdef()).unwrap().since, V0_2_0);
+        assert_loads(name, args, expected);
+    }
+
+    /// Adding operations or layouts bumps the version of the extension.
+    #[test]
+    fn version_covers_layouts() {
+        let latest = TYPE_ARG_LAYOUTS.iter().map(|layout| &layout.since).max();
+        assert_eq!(latest, Some(&JEFF_EXTENSION_VERSION));
+    }
+}
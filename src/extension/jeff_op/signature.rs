@@ -0,0 +1,494 @@
+//! Signatures of the concrete _jeff_ extension operations.
+//!
+//! These functions are the single definition of the operation signatures,
+//! used by the [`JeffOpDef`](crate::extension::JeffOpDef) signature computations. They
+//! can be called without instantiating [`JEFF_EXTENSION`](crate::extension::JEFF_EXTENSION).
+//!
+//! Functions returning _jeff_ register types take the extension reference
+//! stored in those types. Introspection code that does not need to validate
+//! the result may pass a detached [`Weak::new`] reference.
+
+use std::sync::Weak;
+
+use hugr::Extension;
+use hugr::extension::prelude::{bool_t, qb_t};
+use hugr::std_extensions::arithmetic::float_types::float64_type;
+use hugr::types::{Signature, Type as HugrType};
+use itertools::Itertools;
+use jeff::types::FloatPrecision;
+
+use crate::extension::{floatreg_custom_type, intreg_custom_type, qureg_custom_type};
+
+/// The `int(32)` type used for indices and lengths.
+fn int32_t() -> HugrType {
+    crate::types::jeff_to_hugr(jeff::types::Type::Int { bits: 32 })
+}
+
+/// The integer type stored in an integer array of the given bitwidth.
+fn int_t(bits: u8) -> HugrType {
+    crate::types::jeff_to_hugr(jeff::types::Type::Int { bits })
+}
+
+/// The precision of the floats stored in a float array, given in bits.
+fn float_precision(bits: u8) -> FloatPrecision {
+    match bits {
+        32 => FloatPrecision::Float32,
+        _ => FloatPrecision::Float64,
+    }
+}
+
+/// Signature of a [`JeffOp::QGate`](crate::extension::JeffOp::QGate).
+///
+/// The gate takes the target qubits, the control qubits and the float
+/// parameters, and returns the target and control qubits.
+pub fn qgate_signature(qubits: usize, params: usize, controls: usize) -> Signature {
+    let qubits = itertools::repeat_n(qb_t(), qubits + controls);
+    let params = itertools::repeat_n(float64_type(), params);
+    Signature::new(
+        qubits.clone().chain(params).collect_vec(),
+        qubits.collect_vec(),
+    )
+}
+
+/// Signature of a [`JeffOp::GlobalPhase`](crate::extension::JeffOp::GlobalPhase).
+///
+/// The phase takes its angle in radians, and has no outputs.
+pub fn global_phase_signature() -> Signature {
+    Signature::new(vec![float64_type()], vec![])
+}
+
+/// Signature of a [`JeffOp::CondGate`](crate::extension::JeffOp::CondGate).
+///
+/// The gate takes the bit it is conditioned on followed by its qubits, and
+/// returns the qubits.
+pub fn cond_gate_signature(qubits: usize) -> Signature {
+    let qubits = vec![qb_t(); qubits];
+    Signature::new(
+        std::iter::once(bool_t()).chain(qubits.clone()).collect_vec(),
+        qubits,
+    )
+}
+
+/// Signature of a [`JeffOp::QubitBorrow`](crate::extension::JeffOp::QubitBorrow).
+///
+/// The borrow takes no inputs and returns a qubit.
+pub fn qubit_borrow_signature() -> Signature {
+    Signature::new(vec![], vec![qb_t()])
+}
+
+/// Signature of a [`JeffOp::QubitReturn`](crate::extension::JeffOp::QubitReturn).
+///
+/// The return takes a qubit and has no outputs.
+pub fn qubit_return_signature() -> Signature {
+    Signature::new(vec![qb_t()], vec![])
+}
+
+/// Signature of a [`JeffOp::QuregAlloc`](crate::extension::JeffOp::QuregAlloc).
+pub fn qureg_alloc_signature(extension_ref: &Weak<Extension>) -> Signature {
+    let qreg_t: HugrType = qureg_custom_type(extension_ref).into();
+    Signature::new(vec![int32_t()], vec![qreg_t])
+}
+
+/// Signature of a [`JeffOp::QuregFree`](crate::extension::JeffOp::QuregFree).
+pub fn qureg_free_signature(extension_ref: &Weak<Extension>) -> Signature {
+    let qreg_t: HugrType = qureg_custom_type(extension_ref).into();
+    Signature::new(vec![qreg_t], vec![])
+}
+
+/// Signature of a [`JeffOp::QuregExtractIndex`](crate::extension::JeffOp::QuregExtractIndex).
+pub fn qureg_extract_index_signature(extension_ref: &Weak<Extension>) -> Signature {
+    let qreg_t: HugrType = qureg_custom_type(extension_ref).into();
+    Signature::new(vec![qreg_t.clone(), int32_t()], vec![qreg_t, qb_t()])
+}
+
+/// Signature of a [`JeffOp::QuregInsertIndex`](crate::extension::JeffOp::QuregInsertIndex).
+pub fn qureg_insert_index_signature(extension_ref: &Weak<Extension>) -> Signature {
+    let qreg_t: HugrType = qureg_custom_type(extension_ref).into();
+    Signature::new(vec![qreg_t.clone(), qb_t(), int32_t()], vec![qreg_t])
+}
+
+/// Signature of a [`JeffOp::QuregCreate`](crate::extension::JeffOp::QuregCreate).
+pub fn qureg_create_signature(extension_ref: &Weak<Extension>, qubits: usize) -> Signature {
+    let qreg_t: HugrType = qureg_custom_type(extension_ref).into();
+    Signature::new(vec![qb_t(); qubits], vec![qreg_t])
+}
+
+/// Signature of a [`JeffOp::QuregExtractSlice`](crate::extension::JeffOp::QuregExtractSlice).
+pub fn qureg_extract_slice_signature(extension_ref: &Weak<Extension>) -> Signature {
+    let qreg_t: HugrType = qureg_custom_type(extension_ref).into();
+    Signature::new(
+        vec![qreg_t.clone(), int32_t(), int32_t()],
+        vec![qreg_t.clone(), qreg_t],
+    )
+}
+
+/// Signature of a [`JeffOp::QuregInsertSlice`](crate::extension::JeffOp::QuregInsertSlice).
+pub fn qureg_insert_slice_signature(extension_ref: &Weak<Extension>) -> Signature {
+    let qreg_t: HugrType = qureg_custom_type(extension_ref).into();
+    Signature::new(vec![qreg_t.clone(), qreg_t.clone(), int32_t()], vec![qreg_t])
+}
+
+/// Signature of a [`JeffOp::QuregSplit`](crate::extension::JeffOp::QuregSplit).
+pub fn qureg_split_signature(extension_ref: &Weak<Extension>) -> Signature {
+    let qreg_t: HugrType = qureg_custom_type(extension_ref).into();
+    Signature::new(vec![qreg_t.clone(), int32_t()], vec![qreg_t.clone(), qreg_t])
+}
+
+/// Signature of a [`JeffOp::QuregJoin`](crate::extension::JeffOp::QuregJoin).
+pub fn qureg_join_signature(extension_ref: &Weak<Extension>) -> Signature {
+    let qreg_t: HugrType = qureg_custom_type(extension_ref).into();
+    Signature::new(vec![qreg_t.clone(), qreg_t.clone()], vec![qreg_t])
+}
+
+/// Signature of a [`JeffOp::QuregLength`](crate::extension::JeffOp::QuregLength).
+///
+/// The register is returned along with its length.
+pub fn qureg_length_signature(extension_ref: &Weak<Extension>) -> Signature {
+    let qreg_t: HugrType = qureg_custom_type(extension_ref).into();
+    Signature::new(vec![qreg_t.clone()], vec![qreg_t, int32_t()])
+}
+
+/// Signature of a [`JeffOp::QuregMeasure`](crate::extension::JeffOp::QuregMeasure).
+///
+/// The register is consumed, and the outcomes returned as an `intArray<1>`.
+pub fn qureg_measure_signature(extension_ref: &Weak<Extension>) -> Signature {
+    let qreg_t: HugrType = qureg_custom_type(extension_ref).into();
+    let bits_t: HugrType = intreg_custom_type(extension_ref, 1).into();
+    Signature::new(vec![qreg_t], vec![bits_t])
+}
+
+/// Signature of a [`JeffOp::QuregMeasureNd`](crate::extension::JeffOp::QuregMeasureNd).
+///
+/// The register is returned along with the outcomes.
+pub fn qureg_measure_nd_signature(extension_ref: &Weak<Extension>) -> Signature {
+    let qreg_t: HugrType = qureg_custom_type(extension_ref).into();
+    let bits_t: HugrType = intreg_custom_type(extension_ref, 1).into();
+    Signature::new(vec![qreg_t.clone()], vec![qreg_t, bits_t])
+}
+
+/// Signature of a [`JeffOp::QuregSwapElems`](crate::extension::JeffOp::QuregSwapElems).
+///
+/// Takes the register followed by the two indices to swap.
+pub fn qureg_swap_elems_signature(extension_ref: &Weak<Extension>) -> Signature {
+    let qreg_t: HugrType = qureg_custom_type(extension_ref).into();
+    Signature::new(vec![qreg_t.clone(), int32_t(), int32_t()], vec![qreg_t])
+}
+
+/// Signature of a [`JeffOp::QuregReverse`](crate::extension::JeffOp::QuregReverse).
+pub fn qureg_reverse_signature(extension_ref: &Weak<Extension>) -> Signature {
+    let qreg_t: HugrType = qureg_custom_type(extension_ref).into();
+    Signature::new(vec![qreg_t.clone()], vec![qreg_t])
+}
+
+/// The `float(64)` array type of the state preparation amplitudes.
+fn amplitudes_t(extension_ref: &Weak<Extension>) -> HugrType {
+    floatreg_custom_type(extension_ref, FloatPrecision::Float64).into()
+}
+
+/// Signature of a [`JeffOp::StatePrep`](crate::extension::JeffOp::StatePrep).
+///
+/// Takes the qubits followed by the `float(64)` amplitude array, and returns the qubits.
+pub fn state_prep_signature(extension_ref: &Weak<Extension>, qubits: usize) -> Signature {
+    let qubits = vec![qb_t(); qubits];
+    Signature::new(
+        qubits.iter().cloned().chain([amplitudes_t(extension_ref)]).collect_vec(),
+        qubits,
+    )
+}
+
+/// Signature of a [`JeffOp::QuregStatePrep`](crate::extension::JeffOp::QuregStatePrep).
+///
+/// Takes the register followed by the `float(64)` amplitude array.
+pub fn qureg_state_prep_signature(extension_ref: &Weak<Extension>) -> Signature {
+    let qreg_t: HugrType = qureg_custom_type(extension_ref).into();
+    Signature::new(vec![qreg_t.clone(), amplitudes_t(extension_ref)], vec![qreg_t])
+}
+
+/// Signature of a [`JeffOp::IntArrayCreate`](crate::extension::JeffOp::IntArrayCreate).
+pub fn intarray_create_signature(
+    extension_ref: &Weak<Extension>,
+    bits: u8,
+    inputs: usize,
+) -> Signature {
+    let intarr_t: HugrType = intreg_custom_type(extension_ref, bits).into();
+    Signature::new(vec![int_t(bits); inputs], vec![intarr_t])
+}
+
+/// Signature of a [`JeffOp::IntArrayLength`](crate::extension::JeffOp::IntArrayLength).
+pub fn intarray_length_signature(extension_ref: &Weak<Extension>, bits: u8) -> Signature {
+    let intarr_t: HugrType = intreg_custom_type(extension_ref, bits).into();
+    Signature::new(vec![intarr_t], vec![int32_t()])
+}
+
+/// Signature of a [`JeffOp::IntArrayGet`](crate::extension::JeffOp::IntArrayGet).
+pub fn intarray_get_signature(extension_ref: &Weak<Extension>, bits: u8) -> Signature {
+    let intarr_t: HugrType = intreg_custom_type(extension_ref, bits).into();
+    Signature::new(vec![intarr_t, int32_t()], vec![int_t(bits)])
+}
+
+/// Signature of a [`JeffOp::IntArraySet`](crate::extension::JeffOp::IntArraySet).
+pub fn intarray_set_signature(extension_ref: &Weak<Extension>, bits: u8) -> Signature {
+    let intarr_t: HugrType = intreg_custom_type(extension_ref, bits).into();
+    Signature::new(vec![intarr_t.clone(), int32_t(), int_t(bits)], vec![intarr_t])
+}
+
+/// Signature of a [`JeffOp::IntArrayZero`](crate::extension::JeffOp::IntArrayZero).
+pub fn intarray_zero_signature(extension_ref: &Weak<Extension>, bits: u8) -> Signature {
+    let intarr_t: HugrType = intreg_custom_type(extension_ref, bits).into();
+    Signature::new(vec![int32_t()], vec![intarr_t])
+}
+
+/// Signature of a [`JeffOp::IntArrayReverse`](crate::extension::JeffOp::IntArrayReverse).
+pub fn intarray_reverse_signature(extension_ref: &Weak<Extension>, bits: u8) -> Signature {
+    let intarr_t: HugrType = intreg_custom_type(extension_ref, bits).into();
+    Signature::new_endo(vec![intarr_t])
+}
+
+/// Signature of a [`JeffOp::FloatArrayCreate`](crate::extension::JeffOp::FloatArrayCreate).
+pub fn floatarray_create_signature(
+    extension_ref: &Weak<Extension>,
+    bits: u8,
+    inputs: usize,
+) -> Signature {
+    let floatarr_t: HugrType = floatreg_custom_type(extension_ref, float_precision(bits)).into();
+    Signature::new(vec![float64_type(); inputs], vec![floatarr_t])
+}
+
+/// Signature of a [`JeffOp::FloatArrayGet`](crate::extension::JeffOp::FloatArrayGet).
+pub fn floatarray_get_signature(extension_ref: &Weak<Extension>, bits: u8) -> Signature {
+    let floatarr_t: HugrType = floatreg_custom_type(extension_ref, float_precision(bits)).into();
+    Signature::new(vec![floatarr_t, int32_t()], vec![float64_type()])
+}
+
+/// Signature of a [`JeffOp::BitcastIntToFloat`](crate::extension::JeffOp::BitcastIntToFloat).
+///
+/// The float is a `float64`, also for 32-bit bitcasts.
+pub fn bitcast_int_to_float_signature(bits: u8) -> Signature {
+    Signature::new(vec![int_t(bits)], vec![float64_type()])
+}
+
+/// Signature of a [`JeffOp::BitcastFloatToInt`](crate::extension::JeffOp::BitcastFloatToInt).
+///
+/// The float is a `float64`, also for 32-bit bitcasts.
+pub fn bitcast_float_to_int_signature(bits: u8) -> Signature {
+    Signature::new(vec![float64_type()], vec![int_t(bits)])
+}
+
+/// Signature of a [`JeffOp::FloatUnaryFn`](crate::extension::JeffOp::FloatUnaryFn).
+pub fn float_unary_fn_signature() -> Signature {
+    Signature::new(vec![float64_type()], vec![float64_type()])
+}
+
+/// Signature of a [`JeffOp::FloatAtan2`](crate::extension::JeffOp::FloatAtan2).
+///
+/// The operation takes `y` then `x`.
+pub fn float_atan2_signature() -> Signature {
+    Signature::new(vec![float64_type(), float64_type()], vec![float64_type()])
+}
+
+/// Signature of a [`JeffOp::DebugPrint`](crate::extension::JeffOp::DebugPrint).
+///
+/// The printed values are threaded through the operation.
+pub fn debug_print_signature(types: Vec<HugrType>) -> Signature {
+    Signature::new_endo(types)
+}
+
+/// Signature of the runtime queries, such as [`JeffOp::NumQubits`](crate::extension::JeffOp::NumQubits).
+///
+/// Queries take no inputs and return an `int(64)`.
+pub fn runtime_query_signature() -> Signature {
+    Signature::new(vec![], vec![int_t(64)])
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::extension::{JEFF_EXTENSION, JeffOp, JeffOpDef, OpBuildError};
+    use hugr::ops::{OpTrait, OpType};
+    use strum::IntoEnumIterator;
+
+    /// The signature of an operation, computed with the free functions.
+    fn free_signature(op: &JeffOp) -> Signature {
+        let ext = &Arc::downgrade(&JEFF_EXTENSION);
+        match op {
+            JeffOp::QGate {
+                qubits,
+                params,
+                control,
+                ..
+            } => qgate_signature(*qubits, *params, *control),
+            JeffOp::GlobalPhase => global_phase_signature(),
+            JeffOp::CondGate { qubits, .. } => cond_gate_signature(*qubits),
+            JeffOp::QubitBorrow => qubit_borrow_signature(),
+            JeffOp::QubitReturn => qubit_return_signature(),
+            JeffOp::QuregAlloc => qureg_alloc_signature(ext),
+            JeffOp::QuregFree => qureg_free_signature(ext),
+            JeffOp::QuregExtractIndex => qureg_extract_index_signature(ext),
+            JeffOp::QuregInsertIndex => qureg_insert_index_signature(ext),
+            JeffOp::QuregCreate { qubits } => qureg_create_signature(ext, *qubits),
+            JeffOp::QuregExtractSlice => qureg_extract_slice_signature(ext),
+            JeffOp::QuregInsertSlice => qureg_insert_slice_signature(ext),
+            JeffOp::QuregSplit => qureg_split_signature(ext),
+            JeffOp::QuregJoin => qureg_join_signature(ext),
+            JeffOp::QuregLength => qureg_length_signature(ext),
+            JeffOp::QuregMeasure => qureg_measure_signature(ext),
+            JeffOp::QuregMeasureNd => qureg_measure_nd_signature(ext),
+            JeffOp::QuregSwapElems => qureg_swap_elems_signature(ext),
+            JeffOp::QuregReverse => qureg_reverse_signature(ext),
+            JeffOp::StatePrep { qubits } => state_prep_signature(ext, *qubits),
+            JeffOp::QuregStatePrep => qureg_state_prep_signature(ext),
+            JeffOp::IntArrayCreate { bits, inputs } => intarray_create_signature(ext, *bits, *inputs),
+            JeffOp::IntArrayLength { bits } => intarray_length_signature(ext, *bits),
+            JeffOp::IntArrayGet { bits } => intarray_get_signature(ext, *bits),
+            JeffOp::IntArraySet { bits } => intarray_set_signature(ext, *bits),
+            JeffOp::IntArrayZero { bits } => intarray_zero_signature(ext, *bits),
+            JeffOp::IntArrayReverse { bits } => intarray_reverse_signature(ext, *bits),
+            JeffOp::FloatArrayCreate { bits, inputs } => {
+                floatarray_create_signature(ext, *bits, *inputs)
+            }
+            JeffOp::FloatArrayGet { bits } => floatarray_get_signature(ext, *bits),
+            JeffOp::BitcastIntToFloat { bits } => bitcast_int_to_float_signature(*bits),
+            JeffOp::BitcastFloatToInt { bits } => bitcast_float_to_int_signature(*bits),
+            JeffOp::FloatUnaryFn { .. } => float_unary_fn_signature(),
+            JeffOp::FloatAtan2 => float_atan2_signature(),
+            JeffOp::DebugPrint { types, .. } => debug_print_signature(types.clone()),
+            JeffOp::NumQubits | JeffOp::ShotCount | JeffOp::RuntimeQuery { .. } => {
+                runtime_query_signature()
+            }
+        }
+    }
+
+    /// Representative instances of an operation definition.
+    pub(crate) fn instances(def: JeffOpDef) -> Vec<JeffOp> {
+        let bit_widths = [1, 8, 16, 32, 64];
+        match def {
+            JeffOpDef::QGate => (0..3)
+                .flat_map(|qubits| (0..3).map(move |params| (qubits, params)))
+                .flat_map(|(qubits, params)| {
+                    (0..2).map(move |control| {
+                        JeffOp::quantum_gate("G".to_string(), qubits, params, control, false, 1)
+                    })
+                })
+                .collect(),
+            JeffOpDef::GlobalPhase => vec![JeffOp::GlobalPhase],
+            JeffOpDef::CondGate => vec![
+                JeffOp::CondGate {
+                    gate: "X".to_string(),
+                    qubits: 1,
+                    outcome: true,
+                },
+                JeffOp::CondGate {
+                    gate: "CZ".to_string(),
+                    qubits: 2,
+                    outcome: false,
+                },
+            ],
+            JeffOpDef::QubitBorrow => vec![JeffOp::QubitBorrow],
+            JeffOpDef::QubitReturn => vec![JeffOp::QubitReturn],
+            JeffOpDef::QuregAlloc => vec![JeffOp::QuregAlloc],
+            JeffOpDef::QuregFree => vec![JeffOp::QuregFree],
+            JeffOpDef::QuregExtractIndex => vec![JeffOp::QuregExtractIndex],
+            JeffOpDef::QuregInsertIndex => vec![JeffOp::QuregInsertIndex],
+            JeffOpDef::QuregCreate => (0..4).map(|qubits| JeffOp::QuregCreate { qubits }).collect(),
+            JeffOpDef::QuregExtractSlice => vec![JeffOp::QuregExtractSlice],
+            JeffOpDef::QuregInsertSlice => vec![JeffOp::QuregInsertSlice],
+            JeffOpDef::QuregSplit => vec![JeffOp::QuregSplit],
+            JeffOpDef::QuregJoin => vec![JeffOp::QuregJoin],
+            JeffOpDef::QuregLength => vec![JeffOp::QuregLength],
+            JeffOpDef::QuregMeasure => vec![JeffOp::QuregMeasure],
+            JeffOpDef::QuregMeasureNd => vec![JeffOp::QuregMeasureNd],
+            JeffOpDef::QuregSwapElems => vec![JeffOp::QuregSwapElems],
+            JeffOpDef::QuregReverse => vec![JeffOp::QuregReverse],
+            JeffOpDef::StatePrep => (1..4).map(|qubits| JeffOp::StatePrep { qubits }).collect(),
+            JeffOpDef::QuregStatePrep => vec![JeffOp::QuregStatePrep],
+            JeffOpDef::IntArrayCreate => bit_widths
+                .into_iter()
+                .flat_map(|bits| (0..3).map(move |inputs| JeffOp::IntArrayCreate { bits, inputs }))
+                .collect(),
+            JeffOpDef::IntArrayLength => bit_widths
+                .map(|bits| JeffOp::IntArrayLength { bits })
+                .to_vec(),
+            JeffOpDef::IntArrayGet => bit_widths.map(|bits| JeffOp::IntArrayGet { bits }).to_vec(),
+            JeffOpDef::IntArraySet => bit_widths.map(|bits| JeffOp::IntArraySet { bits }).to_vec(),
+            JeffOpDef::IntArrayZero => bit_widths.map(|bits| JeffOp::IntArrayZero { bits }).to_vec(),
+            JeffOpDef::IntArrayReverse => bit_widths
+                .map(|bits| JeffOp::IntArrayReverse { bits })
+                .to_vec(),
+            JeffOpDef::FloatArrayCreate => [32, 64]
+                .into_iter()
+                .flat_map(|bits| {
+                    (0..3).map(move |inputs| JeffOp::FloatArrayCreate { bits, inputs })
+                })
+                .collect(),
+            JeffOpDef::FloatArrayGet => vec![
+                JeffOp::FloatArrayGet { bits: 32 },
+                JeffOp::FloatArrayGet { bits: 64 },
+            ],
+            JeffOpDef::BitcastIntToFloat => [32, 64]
+                .map(|bits| JeffOp::BitcastIntToFloat { bits })
+                .to_vec(),
+            JeffOpDef::BitcastFloatToInt => [32, 64]
+                .map(|bits| JeffOp::BitcastFloatToInt { bits })
+                .to_vec(),
+            JeffOpDef::FloatUnaryFn => ["sqrt", "sin"]
+                .map(|name| JeffOp::FloatUnaryFn {
+                    name: name.to_string(),
+                })
+                .to_vec(),
+            JeffOpDef::FloatAtan2 => vec![JeffOp::FloatAtan2],
+            JeffOpDef::DebugPrint => vec![
+                JeffOp::DebugPrint {
+                    format: String::new(),
+                    types: vec![],
+                },
+                JeffOp::DebugPrint {
+                    format: "{} {}".to_string(),
+                    types: vec![bool_t(), float64_type()],
+                },
+            ],
+            JeffOpDef::NumQubits => vec![JeffOp::NumQubits],
+            JeffOpDef::ShotCount => vec![JeffOp::ShotCount],
+            JeffOpDef::RuntimeQuery => vec![JeffOp::RuntimeQuery {
+                name: "device_id".to_string(),
+            }],
+        }
+    }
+
+    #[test]
+    fn free_signatures_match_extension() {
+        for def in JeffOpDef::iter() {
+            let ops = instances(def);
+            assert!(!ops.is_empty(), "No instances for {def:?}");
+            for op in ops {
+                let expected = free_signature(&op);
+                let optype: OpType = op.clone().into_extension_op().into();
+                let actual = optype.dataflow_signature().unwrap().into_owned();
+                assert_eq!(actual, expected, "Signature mismatch for {op:?}");
+            }
+        }
+    }
+
+    /// Bitcasts are only defined between values of 32 or 64 bits.
+    #[test]
+    fn invalid_bitcast_width() {
+        for bits in [1, 8, 16, 128] {
+            assert!(JeffOp::BitcastIntToFloat { bits }.try_into_extension_op().is_err());
+            assert!(JeffOp::BitcastFloatToInt { bits }.try_into_extension_op().is_err());
+        }
+    }
+
+    /// Printed values must be copyable.
+    #[test]
+    fn invalid_op() {
+        let op = JeffOp::DebugPrint {
+            format: "{}".to_string(),
+            types: vec![qb_t()],
+        };
+        let err = op.try_into_extension_op().unwrap_err();
+        assert!(
+            matches!(err, OpBuildError::InvalidOp { ref op, .. } if op.starts_with("DebugPrint"))
+        );
+    }
+}
@@ -3,7 +3,7 @@
 use std::sync::{Arc, Weak};
 
 use hugr::Extension;
-use hugr::ops::constant::{CustomConst, TryHash, ValueName};
+use hugr::ops::constant::{CustomConst, ValueName};
 use hugr::types::{CustomType, Term, Type, TypeArg, TypeBound, TypeName};
 use itertools::Itertools;
 use jeff::types::FloatPrecision;
@@ -80,13 +80,145 @@ pub fn intreg_parametric_type(bitwidth_arg: TypeArg) -> Type {
     intreg_parametric_custom_type(&Arc::downgrade(&JEFF_EXTENSION), bitwidth_arg).into()
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 /// A constant array value.
+///
+/// The values are stored in the narrowest unsigned integer type that fits the
+/// bitwidth, and serialized as little-endian packed bytes.
+///
+/// Elements are kept in _jeff_ index order. For bit arrays, element `i` is the
+/// bit of weight `2^i`, i.e. [`crate::BitOrder::LsbFirst`].
+#[derive(
+    Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+)]
+#[serde(try_from = "ConstIntRegRepr", into = "ConstIntRegRepr")]
 pub struct ConstIntReg {
     /// The bitwidth of the integers in the array.
     bits: u8,
-    /// The values, stored as u64s.
-    values: Vec<u64>,
+    /// The values, stored in the narrowest type that fits `bits`.
+    values: IntRegValues,
+}
+
+/// Storage for the values of a [`ConstIntReg`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum IntRegValues {
+    U8(Vec<u8>),
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+    U64(Vec<u64>),
+}
+
+impl IntRegValues {
+    /// Store the values in the narrowest type that fits `bits`.
+    fn new(values: impl IntoIterator<Item = u64>, bits: u8) -> Self {
+        let values = values.into_iter();
+        match bits {
+            0..=8 => Self::U8(values.map(|v| v as u8).collect()),
+            9..=16 => Self::U16(values.map(|v| v as u16).collect()),
+            17..=32 => Self::U32(values.map(|v| v as u32).collect()),
+            _ => Self::U64(values.collect()),
+        }
+    }
+
+    /// Number of bytes used to store each value.
+    fn width(&self) -> usize {
+        match self {
+            Self::U8(_) => 1,
+            Self::U16(_) => 2,
+            Self::U32(_) => 4,
+            Self::U64(_) => 8,
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Self::U8(v) => v.len(),
+            Self::U16(v) => v.len(),
+            Self::U32(v) => v.len(),
+            Self::U64(v) => v.len(),
+        }
+    }
+
+    fn get(&self, idx: usize) -> Option<u64> {
+        match self {
+            Self::U8(v) => v.get(idx).map(|&x| x as u64),
+            Self::U16(v) => v.get(idx).map(|&x| x as u64),
+            Self::U32(v) => v.get(idx).map(|&x| x as u64),
+            Self::U64(v) => v.get(idx).copied(),
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = u64> + '_ {
+        (0..self.len()).map(|i| self.get(i).unwrap())
+    }
+
+    /// Encode the values as little-endian packed bytes, in hexadecimal.
+    fn to_packed_hex(&self) -> String {
+        let width = self.width();
+        let mut hex = String::with_capacity(self.len() * width * 2);
+        for v in self.iter() {
+            for byte in &v.to_le_bytes()[..width] {
+                hex.push_str(&format!("{byte:02x}"));
+            }
+        }
+        hex
+    }
+
+    /// Decode values from their little-endian packed hexadecimal encoding.
+    fn from_packed_hex(hex: &str, bits: u8) -> Result<Self, String> {
+        let width = Self::new([], bits).width();
+        if !hex.is_ascii() || hex.len() % (width * 2) != 0 {
+            return Err(format!(
+                "packed int array data must be a multiple of {width} hexadecimal bytes"
+            ));
+        }
+        let values = hex
+            .as_bytes()
+            .chunks(width * 2)
+            .map(|chunk| {
+                let mut bytes = [0u8; 8];
+                for (i, byte) in chunk.chunks(2).enumerate() {
+                    let byte = std::str::from_utf8(byte).unwrap();
+                    bytes[i] = u8::from_str_radix(byte, 16).map_err(|e| e.to_string())?;
+                }
+                Ok(u64::from_le_bytes(bytes))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(Self::new(values, bits))
+    }
+}
+
+/// Serialized representation of a [`ConstIntReg`].
+///
+/// Older versions stored the values as a list of u64s, which is still accepted
+/// when deserializing.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+enum ConstIntRegRepr {
+    Packed { bits: u8, packed: String },
+    Legacy { bits: u8, values: Vec<u64> },
+}
+
+impl From<ConstIntReg> for ConstIntRegRepr {
+    fn from(value: ConstIntReg) -> Self {
+        Self::Packed {
+            bits: value.bits,
+            packed: value.values.to_packed_hex(),
+        }
+    }
+}
+
+impl TryFrom<ConstIntRegRepr> for ConstIntReg {
+    type Error = String;
+
+    fn try_from(repr: ConstIntRegRepr) -> Result<Self, Self::Error> {
+        match repr {
+            ConstIntRegRepr::Packed { bits, packed } => Ok(Self {
+                bits,
+                values: IntRegValues::from_packed_hex(&packed, bits)?,
+            }),
+            ConstIntRegRepr::Legacy { bits, values } => Ok(Self::new(values, bits)),
+        }
+    }
 }
 
 impl ConstIntReg {
@@ -94,16 +226,33 @@ impl ConstIntReg {
     pub const CTR_NAME: &'static str = "jeff.const-intreg";
 
     /// Create a new [`ConstIntReg`]
+    ///
+    /// Values are truncated to the storage width for `bits`.
     pub fn new(values: impl IntoIterator<Item = u64>, bits: u8) -> Self {
         Self {
             bits,
-            values: values.into_iter().collect_vec(),
+            values: IntRegValues::new(values, bits),
         }
     }
 
-    /// Returns the value of the constant
-    pub fn values(&self) -> &[u64] {
-        &self.values
+    /// Returns the values of the constant
+    pub fn values(&self) -> impl Iterator<Item = u64> + '_ {
+        self.values.iter()
+    }
+
+    /// Returns the value at the given index, if it exists.
+    pub fn get(&self, idx: usize) -> Option<u64> {
+        self.values.get(idx)
+    }
+
+    /// Returns the number of values in the constant.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns `true` if the constant array is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
 
     /// Returns the bitwidth of the constant
@@ -112,12 +261,10 @@ impl ConstIntReg {
     }
 }
 
-impl TryHash for ConstIntReg {}
-
 #[typetag::serde]
 impl CustomConst for ConstIntReg {
     fn name(&self) -> ValueName {
-        format!("[{}]", self.values.iter().join(", ")).into()
+        format!("[{}]", self.values().join(", ")).into()
     }
 
     fn get_type(&self) -> Type {
@@ -155,3 +302,59 @@ pub fn floatreg_custom_type(
 pub fn floatreg_type(precision: FloatPrecision) -> Type {
     floatreg_custom_type(&Arc::downgrade(&JEFF_EXTENSION), precision).into()
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::bit(1, vec![0, 1, 1, 0])]
+    #[case::int8(8, vec![0, 255, 17])]
+    #[case::int7(7, vec![0, 127, 3])]
+    #[case::int16(16, vec![65535, 0, 256])]
+    #[case::int32(32, vec![u32::MAX as u64, 1 << 20])]
+    #[case::int64(64, vec![u64::MAX, 0, 1 << 40])]
+    #[case::empty(8, vec![])]
+    fn const_intreg_serde_roundtrip(#[case] bits: u8, #[case] values: Vec<u64>) {
+        let reg = ConstIntReg::new(values.iter().copied(), bits);
+        assert_eq!(reg.values().collect_vec(), values);
+
+        let json = serde_json::to_string(&reg).unwrap();
+        let deser: ConstIntReg = serde_json::from_str(&json).unwrap();
+        assert_eq!(deser, reg);
+    }
+
+    #[rstest]
+    fn const_intreg_legacy_format() {
+        let json = r#"{"bits": 8, "values": [1, 2, 255]}"#;
+        let reg: ConstIntReg = serde_json::from_str(json).unwrap();
+        assert_eq!(reg, ConstIntReg::new([1, 2, 255], 8));
+    }
+
+    #[rstest]
+    fn const_intreg_invalid_packed_data() {
+        let json = r#"{"bits": 16, "packed": "abc"}"#;
+        assert!(serde_json::from_str::<ConstIntReg>(json).is_err());
+    }
+
+    /// The packed encoding must be significantly smaller than the legacy list of u64s.
+    #[rstest]
+    fn const_intreg_envelope_size() {
+        let values = (0..10_000u64).map(|i| (i * 37) % 256).collect_vec();
+        let reg = ConstIntReg::new(values.iter().copied(), 8);
+
+        let packed = serde_json::to_string(&reg).unwrap();
+        let legacy = serde_json::to_string(&serde_json::json!({
+            "bits": 8,
+            "values": values,
+        }))
+        .unwrap();
+        assert!(
+            packed.len() * 3 / 2 < legacy.len(),
+            "packed {} bytes, legacy {} bytes",
+            packed.len(),
+            legacy.len()
+        );
+    }
+}
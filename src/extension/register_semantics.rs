@@ -0,0 +1,352 @@
+//! The effect of the register operations on the length of a qubit register.
+//!
+//! A _jeff_ register has a fixed number of slots. [`JeffOp::QuregExtractIndex`]
+//! borrows the qubit at an index: the register keeps its length, and the slot
+//! stays empty until a qubit is put back with [`JeffOp::QuregInsertIndex`].
+//! A register can thus be used while qubits extracted from it are alive, and
+//! [`JeffOp::QuregLength`] counts the empty slots. This matches the
+//! `quantum.extract` and `quantum.insert` operations of Catalyst, which its
+//! exporter maps to these operations.
+//!
+//! Slices move qubits between registers instead: [`JeffOp::QuregInsertSlice`]
+//! shifts the qubits after the index to make room for the inserted register,
+//! and [`JeffOp::QuregExtractSlice`] removes the extracted slots. Likewise,
+//! [`JeffOp::QuregSplit`] and [`JeffOp::QuregJoin`] divide and concatenate
+//! the slots of registers.
+//!
+//! The analyses and passes that follow register lengths use this module, so
+//! they agree on the lengths of registers mixing qubit-level and
+//! register-level operations.
+
+use std::collections::{BTreeMap, HashMap};
+
+use hugr::ops::OpType;
+use hugr::std_extensions::arithmetic::int_types::ConstInt;
+use hugr::{HugrView, Node, OutgoingPort};
+use itertools::Itertools;
+
+use super::{JeffOp, qureg_type};
+use crate::metadata;
+
+/// The change in length of a register threaded through an operation, from
+/// its first input to its first output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LengthChange {
+    /// The output register has the length of the input register.
+    Unchanged,
+    /// The output register is shorter, by the length of the extracted slice.
+    Shrinks,
+    /// The output register is longer, by the length of the inserted register.
+    Grows,
+}
+
+/// Returns the change in length of the register threaded through an
+/// operation, or `None` if the operation does not return its register input.
+pub fn length_change(op: &JeffOp) -> Option<LengthChange> {
+    match op {
+        JeffOp::QuregExtractIndex
+        | JeffOp::QuregInsertIndex
+        | JeffOp::QuregLength
+        | JeffOp::QuregMeasureNd
+        | JeffOp::QuregSwapElems
+        | JeffOp::QuregReverse
+        | JeffOp::QuregStatePrep => Some(LengthChange::Unchanged),
+        JeffOp::QuregExtractSlice => Some(LengthChange::Shrinks),
+        JeffOp::QuregInsertSlice => Some(LengthChange::Grows),
+        _ => None,
+    }
+}
+
+/// Returns the length of a register value, if it is statically known.
+///
+/// See [`register_sizes`] for how the lengths are computed.
+pub fn static_length(
+    hugr: &impl HugrView<Node = Node>,
+    node: Node,
+    port: OutgoingPort,
+) -> Option<u64> {
+    RegisterSizes::new(hugr).size(node, port)
+}
+
+/// Returns the statically known lengths of the registers of a HUGR, by the
+/// port producing them.
+///
+/// The lengths are propagated from the [`JeffOp::QuregAlloc`]s of constant
+/// size and the [`JeffOp::QuregCreate`]s through the register operations:
+///
+/// - [`JeffOp::QuregSplit`] at a constant index `k` returns the first `k`
+///   qubits and the rest of the register.
+/// - [`JeffOp::QuregExtractSlice`] with constant bounds `start..end` returns
+///   the register without the slice and the slice.
+/// - [`JeffOp::QuregJoin`] and [`JeffOp::QuregInsertSlice`] add the lengths
+///   of their registers.
+/// - The operations of [`LengthChange::Unchanged`] keep the length.
+///
+/// The registers cross the boundaries of the nested dataflow regions. A
+/// register carried by a `TailLoop` has a known length only if the loop body
+/// preserves it, and the register outputs of a `Conditional` only if all the
+/// cases agree. The register parameters of a function have the lengths of
+/// its [`metadata::PARAM_SIZES`], if any.
+pub fn register_sizes(hugr: &impl HugrView<Node = Node>) -> BTreeMap<(Node, OutgoingPort), u64> {
+    let qureg = qureg_type();
+    let mut sizes = RegisterSizes::new(hugr);
+    let mut known = BTreeMap::new();
+    for node in hugr.nodes() {
+        let Some(signature) = hugr.signature(node) else {
+            continue;
+        };
+        for (port, ty) in signature.output_types().iter().enumerate() {
+            if *ty != qureg {
+                continue;
+            }
+            let port = OutgoingPort::from(port);
+            if let Some(size) = sizes.size(node, port) {
+                known.insert((node, port), size);
+            }
+        }
+    }
+    known
+}
+
+/// Memoized computation of the register lengths, see [`register_sizes`].
+struct RegisterSizes<'h, H> {
+    hugr: &'h H,
+    /// The computed lengths, `None` when unknown.
+    known: HashMap<(Node, OutgoingPort), Option<u64>>,
+    /// The lengths assumed for the registers carried by the loops being
+    /// checked.
+    assumed: HashMap<(Node, OutgoingPort), u64>,
+}
+
+impl<'h, H: HugrView<Node = Node>> RegisterSizes<'h, H> {
+    fn new(hugr: &'h H) -> Self {
+        Self {
+            hugr,
+            known: HashMap::new(),
+            assumed: HashMap::new(),
+        }
+    }
+
+    /// The length of the register produced at a port.
+    fn size(&mut self, node: Node, port: OutgoingPort) -> Option<u64> {
+        if let Some(&size) = self.assumed.get(&(node, port)) {
+            return Some(size);
+        }
+        if let Some(&size) = self.known.get(&(node, port)) {
+            return size;
+        }
+        let size = self.compute(node, port);
+        self.known.insert((node, port), size);
+        size
+    }
+
+    /// The length of the register consumed at an input port.
+    fn input_size(&mut self, node: Node, input: usize) -> Option<u64> {
+        let (src, src_port) = self.hugr.single_linked_output(node, input)?;
+        self.size(src, src_port)
+    }
+
+    fn compute(&mut self, node: Node, port: OutgoingPort) -> Option<u64> {
+        let hugr = self.hugr;
+        match hugr.get_optype(node) {
+            OpType::Input(_) => self.region_input(node, port),
+            OpType::DFG(_) => {
+                let [_, output] = hugr.get_io(node)?;
+                self.input_size(output, port.index())
+            }
+            OpType::TailLoop(tail_loop) => {
+                let rest = port.index().checked_sub(tail_loop.just_outputs.len())?;
+                let [_, output] = hugr.get_io(node)?;
+                self.input_size(output, 1 + rest)
+            }
+            OpType::Conditional(_) => {
+                let sizes = hugr
+                    .children(node)
+                    .map(|case| {
+                        let [_, output] = hugr.get_io(case)?;
+                        self.input_size(output, port.index())
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                sizes.into_iter().all_equal_value().ok()
+            }
+            optype => {
+                let op = optype.cast::<JeffOp>()?;
+                self.register_op(node, op, port)
+            }
+        }
+    }
+
+    /// The length of a register entering a nested region.
+    fn region_input(&mut self, input: Node, port: OutgoingPort) -> Option<u64> {
+        let hugr = self.hugr;
+        let parent = hugr.get_parent(input)?;
+        match hugr.get_optype(parent) {
+            OpType::DFG(_) => self.input_size(parent, port.index()),
+            OpType::Case(_) => {
+                let conditional = hugr.get_parent(parent)?;
+                let OpType::Conditional(cond) = hugr.get_optype(conditional) else {
+                    return None;
+                };
+                let case = hugr.children(conditional).position(|case| case == parent)?;
+                let other = port.index().checked_sub(cond.sum_rows.get(case)?.len())?;
+                self.input_size(conditional, 1 + other)
+            }
+            OpType::FuncDefn(_) => {
+                let sizes = hugr.get_metadata(parent, metadata::PARAM_SIZES)?;
+                sizes.get(port.index())?.as_u64()
+            }
+            OpType::TailLoop(tail_loop) => {
+                let rest = port.index().checked_sub(tail_loop.just_inputs.len())?;
+                let [_, output] = hugr.get_io(parent)?;
+                let initial = self.input_size(parent, port.index())?;
+
+                // Assume the length is preserved, and check it on the value
+                // passed to the next iteration.
+                let snapshot = self.known.clone();
+                self.assumed.insert((input, port), initial);
+                let carried = self.input_size(output, 1 + rest);
+                self.assumed.remove(&(input, port));
+                if carried != Some(initial) {
+                    // Forget the lengths computed under the assumption.
+                    self.known = snapshot;
+                    return None;
+                }
+                Some(initial)
+            }
+            _ => None,
+        }
+    }
+
+    /// The length of a register produced by a _jeff_ operation.
+    fn register_op(&mut self, node: Node, op: JeffOp, port: OutgoingPort) -> Option<u64> {
+        let hugr = self.hugr;
+        let constant = |input: usize| constant_input(hugr, node, input);
+        match (op, port.index()) {
+            (JeffOp::QuregAlloc, 0) => constant(0),
+            (JeffOp::QuregCreate { qubits }, 0) => Some(qubits as u64),
+            (JeffOp::QuregJoin | JeffOp::QuregInsertSlice, 0) => {
+                Some(self.input_size(node, 0)? + self.input_size(node, 1)?)
+            }
+            (JeffOp::QuregSplit, 0) => constant(1),
+            (JeffOp::QuregSplit, 1) => {
+                let first = constant(1)?;
+                self.input_size(node, 0)?.checked_sub(first)
+            }
+            (JeffOp::QuregExtractSlice, _) => {
+                let slice = constant(2)?.checked_sub(constant(1)?)?;
+                match port.index() {
+                    0 => self.input_size(node, 0)?.checked_sub(slice),
+                    _ => Some(slice),
+                }
+            }
+            (op, 0) if length_change(&op) == Some(LengthChange::Unchanged) => {
+                self.input_size(node, 0)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Returns the value of a constant integer input.
+pub(crate) fn constant_input(
+    hugr: &impl HugrView<Node = Node>,
+    node: Node,
+    input: usize,
+) -> Option<u64> {
+    let (load, _) = hugr.single_linked_output(node, input)?;
+    if !hugr.get_optype(load).is_load_constant() {
+        return None;
+    }
+    let const_node = hugr.static_source(load)?;
+    let value = hugr.get_optype(const_node).as_const()?;
+    Some(value.get_custom_value::<ConstInt>()?.value_u())
+}
+
+#[cfg(test)]
+mod test {
+    use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr, DataflowSubContainer};
+    use hugr::extension::prelude::qb_t;
+    use hugr::ops::Value;
+    use hugr::ops::handle::NodeHandle;
+    use hugr::types::Signature;
+    use hugr::{Wire, type_row};
+    use rstest::rstest;
+
+    use super::*;
+
+    fn add_op(
+        builder: &mut impl Dataflow,
+        op: JeffOp,
+        inputs: impl IntoIterator<Item = Wire>,
+    ) -> Vec<Wire> {
+        builder
+            .add_dataflow_op(op.into_extension_op(), inputs)
+            .unwrap()
+            .outputs()
+            .collect()
+    }
+
+    #[test]
+    fn static_lengths() {
+        let mut builder =
+            DFGBuilder::new(Signature::new(vec![qb_t(), qb_t()], vec![qureg_type()])).unwrap();
+        let qubits = builder.input_wires();
+        let create = builder
+            .add_dataflow_op(JeffOp::QuregCreate { qubits: 2 }.into_extension_op(), qubits)
+            .unwrap();
+        let size = builder.add_load_value(ConstInt::new_u(5, 3).unwrap());
+        let alloc = builder
+            .add_dataflow_op(JeffOp::QuregAlloc.into_extension_op(), [size])
+            .unwrap();
+        let join = builder
+            .add_dataflow_op(
+                JeffOp::QuregJoin.into_extension_op(),
+                [create.out_wire(0), alloc.out_wire(0)],
+            )
+            .unwrap();
+        let reverse = builder
+            .add_dataflow_op(JeffOp::QuregReverse.into_extension_op(), [join.out_wire(0)])
+            .unwrap();
+        let hugr = builder.finish_hugr_with_outputs(reverse.outputs()).unwrap();
+
+        let length = |node: Node| static_length(&hugr, node, 0.into());
+        assert_eq!(length(create.node()), Some(2));
+        assert_eq!(length(alloc.node()), Some(3));
+        assert_eq!(length(join.node()), Some(5));
+        assert_eq!(length(reverse.node()), Some(5));
+    }
+
+    /// A loop carrying a register of 4 qubits, whose body reverses the
+    /// register or joins it with a new qubit.
+    #[rstest]
+    #[case::invariant(false, Some(4))]
+    #[case::growing(true, None)]
+    fn loop_carried(#[case] grow: bool, #[case] expected: Option<u64>) {
+        let mut builder = DFGBuilder::new(Signature::new(vec![], vec![qureg_type()])).unwrap();
+        let size = builder.add_load_value(ConstInt::new_u(5, 4).unwrap());
+        let reg = add_op(&mut builder, JeffOp::QuregAlloc, [size])[0];
+        let mut tail_loop = builder
+            .tail_loop_builder([], [(qureg_type(), reg)], type_row![])
+            .unwrap();
+        let [reg] = tail_loop.input_wires_arr();
+        let reg = match grow {
+            false => add_op(&mut tail_loop, JeffOp::QuregReverse, [reg])[0],
+            true => {
+                let one = tail_loop.add_load_value(ConstInt::new_u(5, 1).unwrap());
+                let new = add_op(&mut tail_loop, JeffOp::QuregAlloc, [one])[0];
+                add_op(&mut tail_loop, JeffOp::QuregJoin, [reg, new])[0]
+            }
+        };
+        let done = tail_loop.add_load_value(Value::true_val());
+        let tail_loop = tail_loop.finish_with_outputs(done, [reg]).unwrap();
+        let hugr = builder
+            .finish_hugr_with_outputs(tail_loop.outputs())
+            .unwrap();
+
+        let [input, _] = hugr.get_io(tail_loop.node()).unwrap();
+        assert_eq!(static_length(&hugr, input, 0.into()), expected);
+        assert_eq!(static_length(&hugr, tail_loop.node(), 0.into()), expected);
+        assert_eq!(register_sizes(&hugr).len(), if grow { 2 } else { 4 });
+    }
+}
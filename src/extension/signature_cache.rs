@@ -0,0 +1,275 @@
+//! On-disk cache of the signatures of the _jeff_ extension operations.
+//!
+//! The signatures of the operations with type arguments, such as `QGateN`,
+//! are computed by the [`JEFF_EXTENSION`](super::JEFF_EXTENSION) each time
+//! an operation is built. With the `signature-cache` feature, the computed
+//! signatures are kept in a table shared by the process, which can be
+//! [`store`]d in a cache directory and [`load`]ed by the next process, so
+//! that short-lived processes converting small programs skip the
+//! computations.
+//!
+//! The cache file is tied to [`JEFF_EXTENSION_VERSION`], the version of
+//! this crate and the version of hugr it is built with. Cache files from
+//! other versions, and files that cannot be read, are ignored.
+//!
+//! # Examples
+//!
+//! ```
+//! use hugr_jeff::doctest_support::qubits;
+//! use hugr_jeff::extension::signature_cache;
+//! use hugr_jeff::jeff_to_hugr;
+//!
+//! let dir = std::env::temp_dir().join("hugr-jeff-signature-cache-doctest");
+//! signature_cache::load(&dir);
+//! jeff_to_hugr(&qubits()).unwrap();
+//! signature_cache::store(&dir).unwrap();
+//! assert!(signature_cache::load(&dir));
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::{fs, io};
+
+use hugr::builder::{Container, HugrBuilder, ModuleBuilder};
+use hugr::envelope::EnvelopeConfig;
+use hugr::extension::{CustomSignatureFunc, OpDef, SignatureError};
+use hugr::ops::OpType;
+use hugr::ops::handle::NodeHandle;
+use hugr::types::{PolyFuncType, PolyFuncTypeRV, Term};
+use hugr::{Hugr, HugrView};
+use lazy_static::lazy_static;
+
+use super::JEFF_EXTENSION_VERSION;
+use crate::hugr_importer::extension_registry;
+
+/// The version of hugr the signatures are computed with.
+///
+/// Keep in sync with the `hugr` dependency of the workspace manifest.
+const HUGR_VERSION: &str = "0.22.2";
+
+/// Metadata key of the versions a cache file was written with, on its
+/// module root.
+const VERSIONS_KEY: &str = "jeff.signature_cache";
+
+/// Metadata key of the operation name and the type arguments of a cached
+/// signature, as a `[op, args]` pair with the arguments serialized to JSON,
+/// on the declaration holding the signature.
+const ENTRY_KEY: &str = "jeff.signature_entry";
+
+lazy_static! {
+    /// The signatures computed or loaded by the process.
+    static ref SIGNATURES: RwLock<SignatureTable> = RwLock::default();
+}
+
+/// Signatures of the operations, by operation name and type arguments.
+#[derive(Debug, Default)]
+struct SignatureTable {
+    signatures: BTreeMap<String, BTreeMap<String, PolyFuncTypeRV>>,
+    /// Whether signatures were computed since the last [`load`] or
+    /// [`store`].
+    modified: bool,
+}
+
+/// The default cache directory, `hugr-jeff` in `$XDG_CACHE_HOME` or in
+/// `$HOME/.cache`.
+pub fn default_dir() -> Option<PathBuf> {
+    let cache_home = std::env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| Some(PathBuf::from(std::env::var_os("HOME")?).join(".cache")))?;
+    Some(cache_home.join("hugr-jeff"))
+}
+
+/// Load the signatures cached in a directory.
+///
+/// Returns `false`, leaving the signatures of the process unchanged, if the
+/// directory has no cache file for the current versions or if it cannot be
+/// read.
+pub fn load(dir: &Path) -> bool {
+    let Some(loaded) = read_cache_file(&cache_file(dir)) else {
+        return false;
+    };
+    let mut table = SIGNATURES.write().unwrap();
+    for (op, signatures) in loaded {
+        table.signatures.entry(op).or_default().extend(signatures);
+    }
+    table.modified = false;
+    true
+}
+
+/// Store the signatures of the process in a directory, creating it if
+/// needed.
+///
+/// The cache file is only written if signatures were computed since the
+/// last [`load`] or [`store`]. Returns the path of the cache file.
+///
+/// # Errors
+///
+/// If the cache file cannot be written.
+pub fn store(dir: &Path) -> io::Result<PathBuf> {
+    let path = cache_file(dir);
+    let mut table = SIGNATURES.write().unwrap();
+    if !table.modified && path.exists() {
+        return Ok(path);
+    }
+
+    let mut module = ModuleBuilder::new();
+    for (op, signatures) in &table.signatures {
+        for (args, signature) in signatures {
+            let Ok(signature) = PolyFuncType::try_from(signature.clone()) else {
+                continue;
+            };
+            // Declarations are named uniquely, the name is not read back.
+            let name = format!("{op}{args}");
+            let decl = module.declare(name, signature).map_err(io::Error::other)?;
+            module
+                .hugr_mut()
+                .set_metadata(decl.node(), ENTRY_KEY, serde_json::json!([op, args]));
+        }
+    }
+    let mut hugr = module.finish_hugr().map_err(io::Error::other)?;
+    hugr.set_metadata(hugr.module_root(), VERSIONS_KEY, versions());
+    let envelope = hugr
+        .store_str(EnvelopeConfig::text())
+        .map_err(io::Error::other)?;
+
+    fs::create_dir_all(dir)?;
+    fs::write(&path, envelope)?;
+    table.modified = false;
+    Ok(path)
+}
+
+/// Forget the signatures of the process.
+pub fn clear() {
+    *SIGNATURES.write().unwrap() = SignatureTable::default();
+}
+
+/// The number of signatures known to the process.
+pub fn cached_signatures() -> usize {
+    let table = SIGNATURES.read().unwrap();
+    table.signatures.values().map(BTreeMap::len).sum()
+}
+
+/// The cache file of the current extension version in a directory.
+fn cache_file(dir: &Path) -> PathBuf {
+    dir.join(format!("jeff-signatures-{JEFF_EXTENSION_VERSION}.hugr"))
+}
+
+/// The versions a cache file must be written with to be loaded.
+fn versions() -> serde_json::Value {
+    serde_json::json!({
+        "extension": JEFF_EXTENSION_VERSION.to_string(),
+        "hugr": HUGR_VERSION,
+        "hugr_jeff": env!("CARGO_PKG_VERSION"),
+    })
+}
+
+/// Read the signatures of a cache file, if it can be read and has the
+/// current versions.
+fn read_cache_file(path: &Path) -> Option<BTreeMap<String, BTreeMap<String, PolyFuncTypeRV>>> {
+    let envelope = fs::read_to_string(path).ok()?;
+    let hugr = Hugr::load_str(envelope, Some(&extension_registry())).ok()?;
+    if hugr.get_metadata(hugr.module_root(), VERSIONS_KEY) != Some(&versions()) {
+        return None;
+    }
+    let mut signatures: BTreeMap<_, BTreeMap<_, _>> = BTreeMap::new();
+    for node in hugr.children(hugr.module_root()) {
+        let OpType::FuncDecl(decl) = hugr.get_optype(node) else {
+            return None;
+        };
+        let entry = hugr.get_metadata(node, ENTRY_KEY)?;
+        let (op, args) = serde_json::from_value::<(String, String)>(entry.clone()).ok()?;
+        signatures
+            .entry(op)
+            .or_default()
+            .insert(args, decl.signature().clone().into());
+    }
+    Some(signatures)
+}
+
+/// A signature computation reusing the signatures known to the process.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Cached<S>(pub S);
+
+impl<S: CustomSignatureFunc> CustomSignatureFunc for Cached<S> {
+    fn compute_signature<'o, 'a: 'o>(
+        &'a self,
+        arg_values: &[Term],
+        def: &'o OpDef,
+    ) -> Result<PolyFuncTypeRV, SignatureError> {
+        let Ok(args) = serde_json::to_string(arg_values) else {
+            return self.0.compute_signature(arg_values, def);
+        };
+        let op = def.name().as_str();
+        let cached = SIGNATURES
+            .read()
+            .unwrap()
+            .signatures
+            .get(op)
+            .and_then(|signatures| signatures.get(&args))
+            .cloned();
+        if let Some(signature) = cached {
+            return Ok(signature);
+        }
+
+        let signature = self.0.compute_signature(arg_values, def)?;
+        let mut table = SIGNATURES.write().unwrap();
+        table
+            .signatures
+            .entry(op.to_string())
+            .or_default()
+            .insert(args, signature.clone());
+        table.modified = true;
+        Ok(signature)
+    }
+
+    fn static_params(&self) -> &[Term] {
+        self.0.static_params()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::jeff_to_hugr;
+    use crate::test::{assert_hugr_equivalent, qubits};
+    use jeff::Jeff;
+    use rstest::rstest;
+
+    #[rstest]
+    fn hugr_version_matches_manifest() {
+        let manifest = include_str!("../../Cargo.toml");
+        assert!(manifest.contains(&format!("hugr = \"{HUGR_VERSION}\"")));
+    }
+
+    /// Conversions with a cold, warm and corrupted cache give the same
+    /// result.
+    #[rstest]
+    fn cold_warm_corrupted(qubits: Jeff<'static>) {
+        let dir = std::env::temp_dir().join(format!("hugr-jeff-sigs-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        clear();
+        assert!(!load(&dir));
+        let cold = jeff_to_hugr(&qubits).unwrap();
+        let path = store(&dir).unwrap();
+        assert!(path.exists());
+
+        clear();
+        assert!(load(&dir));
+        assert!(cached_signatures() > 0);
+        let warm = jeff_to_hugr(&qubits).unwrap();
+        warm.validate().unwrap_or_else(|e| panic!("{e}"));
+        assert_hugr_equivalent(&warm, &cold);
+
+        fs::write(&path, "not a hugr envelope").unwrap();
+        clear();
+        assert!(!load(&dir));
+        let corrupted = jeff_to_hugr(&qubits).unwrap();
+        corrupted.validate().unwrap_or_else(|e| panic!("{e}"));
+        assert_hugr_equivalent(&corrupted, &cold);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,258 @@
+//! Entry point for tools that import _jeff_ programs as HUGRs.
+//!
+//! This exposes a stable, reader-based signature that can be plugged into
+//! HUGR tooling without depending on the _jeff_ reader types directly. The
+//! `hugr-import-jeff` binary in the CLI crate wraps [`import`] to read _jeff_
+//! from stdin and write a HUGR envelope to stdout.
+//!
+//! With the `mmap` feature, `jeff_to_hugr_mmap` imports a program from a
+//! memory-mapped file, for services converting many large programs.
+
+use std::io::Read;
+
+use derive_more::{Display, Error, From};
+use hugr::extension::ExtensionRegistry;
+use hugr::hugr::ValidationError;
+use hugr::{Hugr, HugrView, Node};
+use jeff::Jeff;
+
+use crate::extension::JEFF_EXTENSION;
+use crate::provenance::Provenance;
+use crate::{
+    ConversionWarning, ConvertedHugr, JeffToHugrConfig, JeffToHugrError, jeff_to_hugr_with_config,
+};
+
+/// Options for [`import`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct ImportOptions {
+    /// Configuration for the _jeff_ to HUGR translation.
+    pub translation: JeffToHugrConfig,
+    /// Validate the resulting HUGR before returning it.
+    pub validate: bool,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            translation: JeffToHugrConfig::default(),
+            validate: true,
+        }
+    }
+}
+
+impl ImportOptions {
+    /// Set the configuration for the _jeff_ to HUGR translation.
+    pub fn with_translation(mut self, translation: JeffToHugrConfig) -> Self {
+        self.translation = translation;
+        self
+    }
+
+    /// Set whether the resulting HUGR should be validated.
+    pub fn with_validate(mut self, validate: bool) -> Self {
+        self.validate = validate;
+        self
+    }
+}
+
+/// Error raised while importing a _jeff_ program.
+#[derive(Debug, Display, Error, From)]
+#[non_exhaustive]
+pub enum ImportError {
+    /// The input could not be read.
+    #[display("Failed to read input: {_0}")]
+    Io(std::io::Error),
+    /// The input could not be read as a _jeff_ program.
+    #[display("Failed to read jeff program: {_0}")]
+    Read(jeff::reader::ReadError),
+    /// The _jeff_ program could not be translated.
+    #[display("Failed to translate jeff program: {_0}")]
+    Translate(JeffToHugrError),
+    /// The translated HUGR is not valid.
+    #[display("Translated HUGR is invalid: {_0}")]
+    Validate(ValidationError<Node>),
+}
+
+/// Import a _jeff_ program from a reader as a HUGR.
+///
+/// The SHA-256 of the input bytes is recorded in the [`Provenance`] of the
+/// result.
+///
+/// # Examples
+///
+/// ```
+/// use hugr_jeff::JeffToHugrConfig;
+/// use hugr_jeff::doctest_support::QUBITS_JEFF;
+/// use hugr_jeff::hugr_importer::{ImportOptions, import};
+/// use hugr_jeff::provenance::Provenance;
+///
+/// let translation = JeffToHugrConfig::default().with_record_provenance(true);
+/// let opts = ImportOptions::default().with_translation(translation);
+/// let hugr = import(&mut &QUBITS_JEFF[..], &opts).unwrap();
+///
+/// let provenance = Provenance::from_hugr(&hugr).unwrap();
+/// assert_eq!(provenance.input_sha256.len(), 64);
+/// assert!(provenance.passes.is_empty());
+/// ```
+pub fn import(reader: &mut dyn Read, opts: &ImportOptions) -> Result<Hugr, ImportError> {
+    let (hugr, _warnings) = import_with_warnings(reader, opts)?;
+    Ok(hugr)
+}
+
+/// Import a _jeff_ program from a reader as a HUGR, returning the
+/// approximations made by the translation along with it.
+///
+/// [`import`] discards the warnings.
+///
+/// # Examples
+///
+/// ```
+/// use hugr_jeff::doctest_support::QUBITS_JEFF;
+/// use hugr_jeff::hugr::HugrView;
+/// use hugr_jeff::hugr_importer::{ImportOptions, import_with_warnings};
+///
+/// let (hugr, warnings) = import_with_warnings(&mut &QUBITS_JEFF[..], &ImportOptions::default())
+///     .unwrap();
+/// assert!(hugr.num_nodes() > 0);
+/// assert!(warnings.is_empty());
+/// ```
+pub fn import_with_warnings(
+    reader: &mut dyn Read,
+    opts: &ImportOptions,
+) -> Result<(Hugr, Vec<ConversionWarning>), ImportError> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    import_bytes(&bytes, opts)
+}
+
+/// Import a _jeff_ program from a memory-mapped file as a HUGR.
+///
+/// The program is read in place from the mapped file, without copying it
+/// into memory, and the map is released once the program is translated. The
+/// SHA-256 of the file is recorded in the [`Provenance`] of the result.
+///
+/// The file must not be modified while it is being imported. The warnings of
+/// the translation are discarded, as with [`import`].
+///
+/// # Examples
+///
+/// ```
+/// use hugr_jeff::doctest_support::QUBITS_JEFF;
+/// use hugr_jeff::hugr::HugrView;
+/// use hugr_jeff::hugr_importer::{ImportOptions, jeff_to_hugr_mmap};
+///
+/// let path = std::env::temp_dir().join("hugr_jeff_mmap_example.jeff");
+/// std::fs::write(&path, QUBITS_JEFF).unwrap();
+///
+/// let hugr = jeff_to_hugr_mmap(&path, &ImportOptions::default()).unwrap();
+/// assert!(hugr.num_nodes() > 0);
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+#[cfg(feature = "mmap")]
+pub fn jeff_to_hugr_mmap(
+    path: impl AsRef<std::path::Path>,
+    opts: &ImportOptions,
+) -> Result<Hugr, ImportError> {
+    let file = std::fs::File::open(path)?;
+    // SAFETY: The map is only read during the import, and dropped before
+    // returning. The caller must not modify the file in the meantime.
+    let map = unsafe { memmap2::Mmap::map(&file)? };
+    let (hugr, _warnings) = import_bytes(&map, opts)?;
+    Ok(hugr)
+}
+
+/// Import a _jeff_ program read in place from a buffer, along with the
+/// warnings of its translation.
+fn import_bytes(
+    bytes: &[u8],
+    opts: &ImportOptions,
+) -> Result<(Hugr, Vec<ConversionWarning>), ImportError> {
+    let jeff = Jeff::read_slice(bytes)?;
+    let ConvertedHugr {
+        mut hugr, warnings, ..
+    } = jeff_to_hugr_with_config(&jeff, &opts.translation)?;
+    if let Some(provenance) = Provenance::from_hugr(&hugr) {
+        provenance.with_input(bytes).store(&mut hugr);
+    }
+    if opts.validate {
+        hugr.validate()?;
+    }
+    Ok((hugr, warnings))
+}
+
+/// The extensions that may be used by an imported HUGR.
+///
+/// Includes the standard HUGR extensions, the tket extensions, and the
+/// _jeff_ extension. This is needed to load envelopes produced by [`import`].
+///
+/// # Examples
+///
+/// ```
+/// use hugr_jeff::doctest_support::measured_register;
+/// use hugr_jeff::hugr::envelope::EnvelopeConfig;
+/// use hugr_jeff::hugr::{Hugr, HugrView};
+/// use hugr_jeff::hugr_importer::extension_registry;
+///
+/// let hugr = measured_register(4);
+/// let envelope = hugr.store_str(EnvelopeConfig::text()).unwrap();
+///
+/// let loaded = Hugr::load_str(&envelope, Some(&extension_registry())).unwrap();
+/// loaded.validate().unwrap();
+/// assert_eq!(loaded.num_nodes(), hugr.num_nodes());
+/// ```
+pub fn extension_registry() -> ExtensionRegistry {
+    let mut registry = tket::extension::REGISTRY.clone();
+    registry.register_updated(JEFF_EXTENSION.clone());
+    registry
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::TargetInfo;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::qubits("qubits")]
+    #[case::catalyst_simple("catalyst_simple")]
+    #[case::catalyst_tket_opt("catalyst_tket_opt")]
+    fn import_fixture(#[case] name: &str) {
+        let path = format!("test_files/{name}/{name}.jeff");
+        let mut file = std::fs::File::open(path).unwrap();
+        let hugr = import(&mut file, &ImportOptions::default()).unwrap();
+        assert!(hugr.num_nodes() > 0);
+    }
+
+    #[rstest]
+    fn import_returns_warnings() {
+        let translation = JeffToHugrConfig::default().with_target(TargetInfo::new("empty", 0));
+        let opts = ImportOptions::default().with_translation(translation);
+        let mut file = std::fs::File::open("test_files/qubits/qubits.jeff").unwrap();
+        let (hugr, warnings) = import_with_warnings(&mut file, &opts).unwrap();
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        assert!(!warnings.is_empty());
+        assert!(warnings.iter().all(|warning| matches!(
+            warning,
+            ConversionWarning::TargetQubitsExceeded { available: 0, .. }
+        )));
+    }
+
+    #[rstest]
+    fn borrowed_program() {
+        // The translation does not borrow from the program bytes.
+        let bytes = std::fs::read("test_files/qubits/qubits.jeff").unwrap();
+        let jeff = Jeff::read_slice(&bytes).unwrap();
+        let hugr = crate::jeff_to_hugr(&jeff).unwrap();
+        drop(jeff);
+        drop(bytes);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+    }
+
+    #[rstest]
+    fn import_invalid_input() {
+        let mut input: &[u8] = b"not a jeff file";
+        let err = import(&mut input, &ImportOptions::default()).unwrap_err();
+        assert!(matches!(err, ImportError::Read(_)));
+    }
+}
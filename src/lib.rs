@@ -6,6 +6,53 @@
 //! See
 //! - hugr: github.com/cqcl/hugr
 //! - _jeff_: github.com/jeff-org/jeff
+//!
+//! The `hugr`, `jeff` and `tket` dependencies are re-exported, so downstream
+//! crates can name the types in this crate's public API without depending on
+//! a matching version of them.
+//!
+//! The `ffi` feature enables a C ABI for the _jeff_ to HUGR conversion, see
+//! the `capi` module. The `mmap` feature enables importing memory-mapped
+//! _jeff_ files without copying them, see
+//! `hugr_importer::jeff_to_hugr_mmap`. The `testing` feature provides the
+//! programs used by the examples, see `doctest_support`. The
+//! `signature-cache` feature keeps the extension operation signatures
+//! across processes, see `extension::signature_cache`.
+//!
+//! # Examples
+//!
+//! Translate a _jeff_ program into a HUGR, and check it against the native
+//! gates of a backend:
+//!
+//! ```
+//! use hugr_jeff::analysis::{GateSet, check_gate_set};
+//! use hugr_jeff::doctest_support::qubits;
+//! use hugr_jeff::hugr::HugrView;
+//! use hugr_jeff::jeff_to_hugr;
+//!
+//! let jeff = qubits();
+//! let hugr = jeff_to_hugr(&jeff).unwrap();
+//! hugr.validate().unwrap();
+//!
+//! // The example only uses gates without a `tket.quantum` equivalent.
+//! let violations = check_gate_set(&hugr, &GateSet::quantinuum_like());
+//! assert_eq!(violations.len(), 5);
+//! ```
+//!
+//! Import a binary program, and store the result in an envelope readable by
+//! an older hugr release:
+//!
+//! ```
+//! use hugr_jeff::doctest_support::QUBITS_JEFF;
+//! use hugr_jeff::envelope::{HugrEnvelopeTarget, store_hugr_compat};
+//! use hugr_jeff::hugr_importer::{ImportOptions, import};
+//!
+//! let hugr = import(&mut &QUBITS_JEFF[..], &ImportOptions::default()).unwrap();
+//!
+//! let mut envelope = Vec::new();
+//! store_hugr_compat(&hugr, HugrEnvelopeTarget::Hugr0_21, &mut envelope).unwrap();
+//! assert!(!envelope.is_empty());
+//! ```
 
 mod to_hugr;
 mod to_jeff;
@@ -13,9 +60,47 @@ mod to_jeff;
 #[cfg(test)]
 mod test;
 
+#[cfg(feature = "ffi")]
+pub mod capi;
+
+#[cfg(feature = "testing")]
+pub mod doctest_support;
+
+pub mod analysis;
+pub mod capabilities;
+pub mod envelope;
 pub mod extension;
+pub mod hugr_importer;
+pub mod metadata;
 pub mod optype;
+pub mod passes;
+pub mod provenance;
+pub mod render;
+pub mod text;
 pub mod types;
 
-pub use to_hugr::{JeffToHugrError, jeff_to_hugr};
-pub use to_jeff::HugrToJeffError;
+pub use to_hugr::{
+    ArrayBounds, BitArrays, BitOrder, BuildContext, ConversionCache, ConversionStats,
+    ConversionTrace, ConversionWarning, ConvertedHugr, ExactAngleConfig, FunctionDedup, HandleAbi,
+    IntOverflow, JeffHugrMap, JeffToHugrConfig, JeffToHugrError, LinkError, ModuleInfo,
+    ModuleSelector, NanAngles, Progress, ProgressHook, Protection, QuregLowering, ResourceLimit,
+    ResourceLimits, TargetInfo, TraceEvent, WireNames, jeff_into_hugr_module, jeff_modules,
+    jeff_to_hugr, jeff_to_hugr_incremental, jeff_to_hugr_incremental_with_config,
+    jeff_to_hugr_module, jeff_to_hugr_split, jeff_to_hugr_split_with_config,
+    jeff_to_hugr_with_config, link_hugrs,
+};
+pub use to_hugr::hooks::{ConversionHook, ConversionHooks, DataflowDyn, SeqIo};
+pub use to_hugr::type_codec::{TypeCodec, TypeCodecs, ValueAttributes};
+pub use to_jeff::{
+    FunctionTable, HugrToJeffError, JeffMeasure, fuse_destructive_measures, protected_ops,
+};
+
+// Dependencies appearing in the public API.
+pub use hugr;
+pub use jeff;
+pub use tket;
+
+pub use hugr::types::Signature;
+pub use hugr::{Hugr, Node};
+pub use jeff::Jeff;
+pub use jeff::types::{FloatPrecision, Type as JeffType};
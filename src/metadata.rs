@@ -0,0 +1,168 @@
+//! Metadata keys attached to HUGR nodes by the _jeff_ translation.
+
+/// The originating _jeff_ operation of a node, as a `[function_id, op_index]` pair.
+///
+/// Only set when [`crate::JeffToHugrConfig::record_provenance`] is enabled.
+/// See [`crate::JeffHugrMap::node_origin`].
+pub const OP_INDEX: &str = "jeff.op_index";
+
+/// The exact value of a constant rotation parameter, as a `"k/d"` fraction of
+/// half-turns where `d` is a power of two.
+///
+/// Set on gates whose angle was recognized as an exact fraction of π.
+/// See [`crate::JeffToHugrConfig::exact_angles`].
+pub const EXACT_ANGLE: &str = "jeff.exact_angle";
+
+/// Marks nodes added to check or clamp array indices, or to check integer
+/// arithmetic for overflow.
+///
+/// Set to `true` on the nodes emitted for [`crate::ArrayBounds::Panic`],
+/// [`crate::ArrayBounds::Clamp`] and [`crate::IntOverflow::Trap`]. The panic
+/// checks can be removed with [`crate::passes::strip`].
+pub const BOUNDS_CHECK: &str = "jeff.bounds_check";
+
+/// Marks `QFree` nodes releasing a qubit known to be in the |0⟩ state.
+///
+/// Set to `true` on the translation of _jeff_ `FreeZero` operations. The
+/// freed qubits can be reused by [`crate::passes::reuse_ancillas`]. The mark
+/// is checked by [`crate::analysis::check_free_zero`].
+pub const FREE_ZERO: &str = "jeff.free_zero";
+
+/// The [`crate::BitOrder`] of the bit arrays in the function signatures of
+/// a module, as `"lsb_first"` or `"msb_first"`.
+///
+/// Set on the module root by the translation. Consumers and exporters
+/// should read it with [`crate::BitOrder::from_metadata`] before
+/// interpreting bit arrays crossing function boundaries.
+pub const BIT_ORDER: &str = "jeff.bit_order";
+
+/// The float parameters replacing the array parameters of a function, as a
+/// list of `[array_param_idx, elem_idx, new_param_idx]` triples.
+///
+/// Set on the functions rewritten by
+/// [`crate::JeffToHugrConfig::symbolize_angle_params`]. Callers passing the
+/// array must pass element `elem_idx` of parameter `array_param_idx` as
+/// the new parameter `new_param_idx`.
+pub const ANGLE_PARAMS: &str = "jeff.angle_params";
+
+/// How the HUGR was produced, as a serialized
+/// [`crate::provenance::Provenance`].
+///
+/// Set on the module root by the translation, and extended by the passes of
+/// [`crate::passes`]. Read it with [`crate::provenance::Provenance::from_hugr`].
+pub const PROVENANCE: &str = "jeff.provenance";
+
+/// The circuit depth of a function, counting its gates, measurements and
+/// resets.
+///
+/// Set on the translated function definitions when
+/// [`crate::JeffToHugrConfig::annotate_depths`] is enabled. See
+/// [`crate::analysis::circuit_depth`] for how control flow is counted.
+pub const DEPTH: &str = "jeff.depth";
+
+/// The circuit depth of a function, counting only its gates acting on two
+/// qubits or more.
+///
+/// Set alongside [`DEPTH`].
+pub const DEPTH_2Q: &str = "jeff.depth_2q";
+
+/// The _jeff_ name of a function whose HUGR name was normalized.
+///
+/// Set on the function definitions and declarations renamed by the
+/// translation, see [`crate::JeffToHugrConfig::strict_names`].
+pub const ORIGINAL_NAME: &str = "jeff.original_name";
+
+/// The number of iterations of a `TailLoop`, when statically known.
+///
+/// Set by [`crate::passes::annotate_counter_loops`] on the recognized
+/// counted loops whose counter starts and ends at constants.
+pub const TRIP_COUNT: &str = "jeff.trip_count";
+
+/// The counter of a `TailLoop`, as a `{"port": p, "step": s}` object.
+///
+/// The counter is the value at position `p` of the loop state, incremented
+/// by `s` at each iteration. Set by [`crate::passes::annotate_counter_loops`].
+pub const INDUCTION_VAR: &str = "jeff.induction_var";
+
+/// The lengths of the registers produced by a node, as an array with one
+/// entry per output port, `null` for the ports that are not registers of
+/// statically known length.
+///
+/// Set by [`crate::passes::annotate_register_sizes`], see
+/// [`crate::extension::register_semantics::register_sizes`].
+pub const STATIC_SIZE: &str = "jeff.static_size";
+
+/// The lengths of the registers passed to a function, as an array with one
+/// entry per input, `null` for the inputs that are not registers of
+/// statically known length.
+///
+/// Set on the function definitions whose calls all agree on the lengths by
+/// [`crate::passes::propagate_register_sizes`]. The lengths are known
+/// inside the function body, see
+/// [`crate::extension::register_semantics::register_sizes`].
+pub const PARAM_SIZES: &str = "jeff.param_sizes";
+
+/// Protection of operations from optimization.
+///
+/// Set by [`crate::passes::protect_ops`] to `true` on the protected
+/// operations, to `"container"` on the `DFG` containers enclosing them, and
+/// to `"begin"` or `"end"` on the barriers fencing them. Passes should
+/// leave the operations and the contents of the containers untouched, see
+/// [`crate::protected_ops`].
+///
+/// Conversion hooks may set it to `true` on the nodes they add, which are
+/// then encoded following [`crate::JeffToHugrConfig::protect`].
+pub const PROTECTED: &str = "jeff.protected";
+
+/// The functions merged into an identical one, as an object mapping their
+/// HUGR names to the HUGR name of the function whose body they share.
+///
+/// Set on the module root by the translation when
+/// [`crate::JeffToHugrConfig::dedup_functions`] is enabled.
+pub const DEDUPLICATED: &str = "jeff.deduplicated";
+
+/// The names of the wires produced by a node, as an array with one entry
+/// per output port, `null` for the ports without a name.
+///
+/// Set by the translation when [`crate::JeffToHugrConfig::name_wires`] is
+/// enabled, see [`crate::WireNames`].
+pub const WIRE_NAMES: &str = "jeff.wire_names";
+
+/// Marks the `DFG` containers holding a straight-line quantum segment of a
+/// _jeff_ region, with the position of the first operation of the segment
+/// counting from the start of its function.
+///
+/// Set by the translation when [`crate::JeffToHugrConfig::circuit_boxes`]
+/// is enabled, see [`crate::passes::box_circuit_segments`]. The containers
+/// can be optimized as standalone circuits, and inlined back into their
+/// region with hugr's `InlineDFG`.
+pub const CIRCUIT_BOX: &str = "jeff.circuit_box";
+
+/// The device the program is compiled for, as a serialized
+/// [`crate::TargetInfo`] object.
+///
+/// Set on the module root by the translation when
+/// [`crate::JeffToHugrConfig::target`] is set.
+pub const TARGET: &str = "jeff.target";
+
+/// Marks the function declarations standing for a function defined in
+/// another piece of a split module.
+///
+/// Set to `true` by [`crate::jeff_to_hugr_split`] on the declarations of the
+/// functions called by a piece. [`crate::link_hugrs`] replaces them with the
+/// function of the same name.
+pub const LINK_STUB: &str = "jeff.link_stub";
+
+/// The measurement results returned by a function, as an object with the
+/// `convention` of the results, the `original` convention, the origin of
+/// the qubit of each result as a `qubits` array, and the `positions` of the
+/// results in the original convention.
+///
+/// The origins are `{"input": port}` for the qubits taken as function
+/// inputs, `{"alloc": k}` for the `k`-th allocation of the function region,
+/// and `null` for the qubits that could not be followed.
+///
+/// Set on the functions by [`crate::passes::canonicalize_results`], and by
+/// the translation when [`crate::JeffToHugrConfig::result_convention`] is
+/// set.
+pub const RESULTS: &str = "jeff.results";
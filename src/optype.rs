@@ -5,13 +5,32 @@ use jeff::reader::optype as jeff_optype;
 use crate::JeffToHugrError;
 use crate::to_hugr::BuildContext;
 
+mod bounds;
+pub(crate) mod catalog;
 mod control_flow;
 mod float;
+mod float_array;
 mod function;
 mod int;
 mod int_array;
 mod qubit;
 mod qubit_array;
+pub(crate) mod tket_ops;
+
+pub(crate) use control_flow::{
+    DO_WHILE_NODES, FOR_NODES, INT_SWITCH_CASE_NODES, INT_SWITCH_COMPARISON_NODES,
+    INT_SWITCH_NODES, SELECT_FUNCTION_NODES, SWITCH_NODES, WHILE_NODES, build_select_function,
+    forwarded_sources,
+};
+pub(crate) use qubit::{
+    ROTATION_CONVERSION_NODES, WellKnownTranslation, classify_gate, classify_well_known,
+    gate_name, gate_param_count, has_param_array, unpacked_gate_op,
+};
+pub(crate) use float_array::precision_bits;
+pub(crate) use int::{IntTranslation, int_translation};
+
+/// Number of nodes of a loaded constant: the `Const` and its `LoadConstant`.
+pub(crate) const LOADED_CONSTANT_NODES: usize = 2;
 
 /// Internal utility trait to convert jeff optypes.
 pub(crate) trait JeffToHugrOp {
@@ -33,8 +52,17 @@ impl JeffToHugrOp for jeff_optype::OpType<'_> {
         builder: &mut impl hugr::builder::Dataflow,
         ctx: &mut BuildContext,
     ) -> Result<(), JeffToHugrError> {
+        if !ctx.config().hooks.is_empty() {
+            let hooks = ctx.config().hooks.clone();
+            for hook in hooks.iter() {
+                if let Some(result) = hook.try_convert(op, builder, ctx) {
+                    return result;
+                }
+            }
+        }
         match self {
             jeff_optype::OpType::FloatOp(optype) => optype.build_hugr_op(op, builder, ctx),
+            jeff_optype::OpType::FloatArrayOp(optype) => optype.build_hugr_op(op, builder, ctx),
             jeff_optype::OpType::FuncOp(optype) => optype.build_hugr_op(op, builder, ctx),
             jeff_optype::OpType::IntOp(optype) => optype.build_hugr_op(op, builder, ctx),
             jeff_optype::OpType::IntArrayOp(optype) => optype.build_hugr_op(op, builder, ctx),
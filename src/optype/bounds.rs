@@ -0,0 +1,257 @@
+//! Bounds handling for indexed array and register accesses.
+//!
+//! See [`crate::ArrayBounds`].
+
+use hugr::builder::{ConditionalBuilder, Dataflow, DataflowSubContainer, HugrBuilder};
+use hugr::extension::prelude::ConstError;
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::ops::{OpTrait, OpType};
+use hugr::std_extensions::arithmetic::int_ops::IntOpDef;
+use hugr::std_extensions::arithmetic::int_types::ConstInt;
+use hugr::types::TypeRow;
+use hugr::{HugrView, IncomingPort, Node, Wire};
+use itertools::Itertools;
+
+use crate::extension::JeffOp;
+use crate::to_hugr::BuildContext;
+use crate::{ArrayBounds, JeffToHugrError, metadata};
+
+/// Log width of the `int(32)` indices.
+pub(super) const INDEX_LOG_WIDTH: u8 = 5;
+
+/// Emit an indexed access to an array or register, handling out-of-bounds
+/// indices according to [`crate::JeffToHugrConfig::array_bounds`].
+///
+/// - `access` is the operation reading or writing the container. Its first
+///   input is the container, and its `index_port`-th input the index.
+/// - `length` is the operation returning the container length as its last
+///   output. If it has two outputs, the first one is the container itself.
+pub(super) fn build_indexed_access(
+    ctx: &mut BuildContext,
+    access: JeffOp,
+    length: JeffOp,
+    index_port: usize,
+    jeff_op: &jeff::reader::Operation<'_>,
+    builder: &mut impl Dataflow,
+) -> Result<(), JeffToHugrError> {
+    let mode = ctx.config().array_bounds;
+    if mode == ArrayBounds::Unchecked {
+        return ctx.build_single_op(access.try_into_extension_op()?, jeff_op, builder);
+    }
+
+    let jeff_inputs = jeff_op
+        .inputs()
+        .map(|value| Ok(value?.id()))
+        .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+    let [container_id, index_id] = [jeff_inputs[0], jeff_inputs[index_port]];
+
+    // Query the length of the container.
+    let length_op: OpType = length.try_into_extension_op()?.into();
+    let length_outputs = length_op.dataflow_signature().unwrap().output_count();
+    let length_node = builder.add_child_node(length_op);
+    mark_bounds_check(builder, length_node);
+    ctx.register_input(container_id, length_node, IncomingPort::from(0));
+    let length = Wire::new(length_node, length_outputs - 1);
+    // Linear containers are threaded through the length query.
+    let container = (length_outputs > 1).then(|| Wire::new(length_node, 0));
+
+    let access_op: OpType = access.try_into_extension_op()?.into();
+    let access_types: TypeRow = access_op
+        .dataflow_signature()
+        .unwrap()
+        .input_types()
+        .to_vec()
+        .into();
+    let access_node = builder.add_child_node(access_op);
+    ctx.record_origin(access_node);
+
+    match mode {
+        ArrayBounds::Panic => {
+            let in_bounds = builder.add_child_node(IntOpDef::ilt_u.with_log_width(INDEX_LOG_WIDTH));
+            mark_bounds_check(builder, in_bounds);
+            ctx.register_input(index_id, in_bounds, IncomingPort::from(0));
+            builder
+                .hugr_mut()
+                .connect(length.node(), length.source(), in_bounds, 1);
+
+            let message = format!(
+                "Index out of bounds in function '{}'",
+                ctx.current_function_name().unwrap_or_default()
+            );
+            let guard = build_panic_guard(builder, access_types.clone(), message)?;
+            mark_bounds_check(builder, guard);
+            builder.hugr_mut().connect(in_bounds, 0, guard, 0);
+
+            // The guard threads all the access inputs, and is controlled by the comparison.
+            for (port, &value) in jeff_inputs.iter().enumerate() {
+                if let (0, Some(wire)) = (port, container) {
+                    builder
+                        .hugr_mut()
+                        .connect(wire.node(), wire.source(), guard, 1);
+                } else {
+                    ctx.register_input(value, guard, IncomingPort::from(port + 1));
+                }
+                builder.hugr_mut().connect(guard, port, access_node, port);
+            }
+        }
+        ArrayBounds::Clamp => {
+            let one = builder.add_load_value(ConstInt::new_s(INDEX_LOG_WIDTH, 1).unwrap());
+            let zero = builder.add_load_value(ConstInt::new_s(INDEX_LOG_WIDTH, 0).unwrap());
+            let last = builder.add_dataflow_op(
+                IntOpDef::isub.with_log_width(INDEX_LOG_WIDTH),
+                [length, one],
+            )?;
+            let upper = builder.add_child_node(IntOpDef::imin_s.with_log_width(INDEX_LOG_WIDTH));
+            ctx.register_input(index_id, upper, IncomingPort::from(0));
+            builder.hugr_mut().connect(last.node(), 0, upper, 1);
+            let index = builder.add_dataflow_op(
+                IntOpDef::imax_s.with_log_width(INDEX_LOG_WIDTH),
+                [Wire::new(upper, 0), zero],
+            )?;
+            for node in [one.node(), zero.node(), last.node(), upper, index.node()] {
+                mark_bounds_check(builder, node);
+            }
+
+            for (port, &value) in jeff_inputs.iter().enumerate() {
+                if let (0, Some(wire)) = (port, container) {
+                    builder
+                        .hugr_mut()
+                        .connect(wire.node(), wire.source(), access_node, 0);
+                } else if port == index_port {
+                    builder
+                        .hugr_mut()
+                        .connect(index.node(), 0, access_node, port);
+                } else {
+                    ctx.register_input(value, access_node, IncomingPort::from(port));
+                }
+            }
+        }
+        ArrayBounds::Unchecked => unreachable!(),
+    }
+
+    for (port, value) in builder.hugr().node_outputs(access_node).zip(jeff_op.outputs()) {
+        ctx.register_output(value?.id(), access_node, port);
+    }
+    Ok(())
+}
+
+/// Build a conditional that panics with `message` on its first (false)
+/// branch, and passes through the `types` values otherwise.
+///
+/// Shared by the index checks and the integer overflow checks.
+pub(super) fn build_panic_guard(
+    builder: &mut impl Dataflow,
+    types: TypeRow,
+    message: String,
+) -> Result<Node, JeffToHugrError> {
+    let mut cond =
+        ConditionalBuilder::new([TypeRow::new(), TypeRow::new()], types.clone(), types.clone())?;
+
+    let mut failed = cond.case_builder(0)?;
+    let inputs = failed.input_wires().collect_vec();
+    let panic = failed.add_panic(
+        ConstError::new(1, message),
+        types.iter().cloned(),
+        inputs.into_iter().zip(types.iter().cloned()),
+    )?;
+    failed.finish_with_outputs(panic.outputs())?;
+
+    let passed = cond.case_builder(1)?;
+    let inputs = passed.input_wires();
+    passed.finish_with_outputs(inputs)?;
+
+    let res = builder.add_hugr(cond.finish_hugr()?);
+    Ok(res.inserted_entrypoint)
+}
+
+/// Tag a node as part of a bounds or overflow check.
+pub(super) fn mark_bounds_check(builder: &mut impl Dataflow, node: Node) {
+    builder
+        .hugr_mut()
+        .set_metadata(node, metadata::BOUNDS_CHECK, true);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::passes::{StripOptions, strip};
+    use crate::test::{assert_hugr_equivalent, catalyst_simple};
+    use crate::{JeffToHugrConfig, jeff_to_hugr_with_config};
+    use hugr::Hugr;
+    use jeff::Jeff;
+    use rstest::rstest;
+
+    fn translate(jeff: &Jeff<'static>, array_bounds: ArrayBounds) -> Hugr {
+        let config = JeffToHugrConfig {
+            array_bounds,
+            ..Default::default()
+        };
+        let hugr = jeff_to_hugr_with_config(jeff, &config).unwrap().hugr;
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        hugr
+    }
+
+    fn bounds_check_nodes(hugr: &Hugr) -> Vec<Node> {
+        hugr.nodes()
+            .filter(|&n| hugr.get_metadata(n, metadata::BOUNDS_CHECK).is_some())
+            .collect()
+    }
+
+    fn indexed_accesses(hugr: &Hugr) -> usize {
+        hugr.nodes()
+            .filter(|&n| {
+                matches!(
+                    hugr.get_optype(n).cast::<JeffOp>(),
+                    Some(
+                        JeffOp::QuregExtractIndex
+                            | JeffOp::QuregInsertIndex
+                            | JeffOp::IntArrayGet { .. }
+                            | JeffOp::IntArraySet { .. }
+                    )
+                )
+            })
+            .count()
+    }
+
+    #[rstest]
+    fn unchecked_by_default(catalyst_simple: Jeff<'static>) {
+        let hugr = translate(&catalyst_simple, ArrayBounds::Unchecked);
+        assert!(bounds_check_nodes(&hugr).is_empty());
+    }
+
+    #[rstest]
+    fn panic_guards(catalyst_simple: Jeff<'static>) {
+        let hugr = translate(&catalyst_simple, ArrayBounds::Panic);
+        let accesses = indexed_accesses(&hugr);
+        assert!(accesses > 0);
+
+        let guards = bounds_check_nodes(&hugr)
+            .into_iter()
+            .filter(|&n| hugr.get_optype(n).is_conditional())
+            .count();
+        assert_eq!(guards, accesses);
+    }
+
+    #[rstest]
+    fn clamp_indices(catalyst_simple: Jeff<'static>) {
+        let hugr = translate(&catalyst_simple, ArrayBounds::Clamp);
+        let checks = bounds_check_nodes(&hugr);
+        assert!(!checks.is_empty());
+        assert!(checks.iter().all(|&n| !hugr.get_optype(n).is_conditional()));
+    }
+
+    #[rstest]
+    fn strip_panic_guards(catalyst_simple: Jeff<'static>) {
+        let mut hugr = translate(&catalyst_simple, ArrayBounds::Panic);
+        let options = StripOptions {
+            debug_prints: false,
+            ..Default::default()
+        };
+        let removed = strip(&mut hugr, &options);
+        assert!(removed > 0);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        assert!(bounds_check_nodes(&hugr).is_empty());
+        assert_hugr_equivalent(&hugr, &translate(&catalyst_simple, ArrayBounds::Unchecked));
+    }
+}
@@ -0,0 +1,163 @@
+//! Catalog of the _jeff_ well-known gates.
+//!
+//! Everything the translation knows about a [`WellKnownGate`] is recorded
+//! once in [`CATALOG`]: the gate translation rules, the parameter counts and
+//! the tests read it from there. [`GateInfo::of`] matches exhaustively on
+//! the gates, so a gate added to the _jeff_ crate fails to compile until it
+//! is described here.
+
+use jeff::reader::optype::WellKnownGate;
+
+use super::qubit::WellKnownTranslation;
+use super::tket_ops::QuantumOp;
+
+/// What the translation knows about a well-known gate.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct GateInfo {
+    /// The described gate.
+    pub gate: WellKnownGate,
+    /// The number of qubits the gate acts on, without the control qubits.
+    pub arity: usize,
+    /// The number of angle parameters of the gate.
+    pub params: usize,
+    /// Whether the gate is its own inverse.
+    pub is_self_inverse: bool,
+    /// The translation of the gate without modifiers, or `None` if it is
+    /// translated as an opaque gate.
+    pub tket_equivalent: Option<WellKnownTranslation>,
+    /// The `tket.quantum` operation of the adjoint of a gate that is not its
+    /// own inverse.
+    pub adjoint_equivalent: Option<QuantumOp>,
+    /// The `tket.quantum` operation of the gate with a single control qubit.
+    pub controlled_equivalent: Option<QuantumOp>,
+}
+
+/// All the _jeff_ well-known gates.
+///
+/// A gate added to [`GateInfo::of`] must also be added here.
+pub(crate) const CATALOG: [GateInfo; 14] = {
+    use WellKnownGate::*;
+
+    [
+        GateInfo::of(GPhase),
+        GateInfo::of(I),
+        GateInfo::of(X),
+        GateInfo::of(Y),
+        GateInfo::of(Z),
+        GateInfo::of(S),
+        GateInfo::of(T),
+        GateInfo::of(R1),
+        GateInfo::of(Rx),
+        GateInfo::of(Ry),
+        GateInfo::of(Rz),
+        GateInfo::of(H),
+        GateInfo::of(U),
+        GateInfo::of(Swap),
+    ]
+};
+
+impl GateInfo {
+    /// Describe a well-known gate.
+    pub(crate) const fn of(gate: WellKnownGate) -> Self {
+        use QuantumOp as Op;
+        use WellKnownTranslation::{GlobalPhase, Swap, Tket, Transparent};
+
+        let info = Self::opaque(gate);
+        // Keep this match exhaustive, so that new gates are not missed.
+        match gate {
+            WellKnownGate::GPhase => Self {
+                arity: 0,
+                params: 1,
+                tket_equivalent: Some(GlobalPhase),
+                ..info
+            },
+            WellKnownGate::I => Self {
+                is_self_inverse: true,
+                tket_equivalent: Some(Transparent),
+                ..info
+            },
+            WellKnownGate::X => info.pauli(Op::X, Op::CX),
+            WellKnownGate::Y => info.pauli(Op::Y, Op::CY),
+            WellKnownGate::Z => info.pauli(Op::Z, Op::CZ),
+            WellKnownGate::S => info.phase(Op::S, Op::Sdg),
+            WellKnownGate::T => info.phase(Op::T, Op::Tdg),
+            WellKnownGate::R1 => Self { params: 1, ..info },
+            WellKnownGate::Rx => info.rotation(Op::Rx),
+            WellKnownGate::Ry => info.rotation(Op::Ry),
+            WellKnownGate::Rz => info.rotation(Op::Rz),
+            WellKnownGate::H => Self {
+                is_self_inverse: true,
+                tket_equivalent: Some(Tket(Op::H)),
+                ..info
+            },
+            WellKnownGate::U => Self { params: 3, ..info },
+            WellKnownGate::Swap => Self {
+                arity: 2,
+                is_self_inverse: true,
+                tket_equivalent: Some(Swap),
+                ..info
+            },
+        }
+    }
+
+    /// A single-qubit gate without parameters nor `tket` equivalent.
+    const fn opaque(gate: WellKnownGate) -> Self {
+        Self {
+            gate,
+            arity: 1,
+            params: 0,
+            is_self_inverse: false,
+            tket_equivalent: None,
+            adjoint_equivalent: None,
+            controlled_equivalent: None,
+        }
+    }
+
+    /// A Pauli gate, with a controlled `tket` equivalent.
+    const fn pauli(self, tket_op: QuantumOp, controlled: QuantumOp) -> Self {
+        Self {
+            is_self_inverse: true,
+            tket_equivalent: Some(WellKnownTranslation::Tket(tket_op)),
+            controlled_equivalent: Some(controlled),
+            ..self
+        }
+    }
+
+    /// A phase gate, with an adjoint `tket` equivalent.
+    const fn phase(self, tket_op: QuantumOp, adjoint: QuantumOp) -> Self {
+        Self {
+            tket_equivalent: Some(WellKnownTranslation::Tket(tket_op)),
+            adjoint_equivalent: Some(adjoint),
+            ..self
+        }
+    }
+
+    /// A rotation gate, taking its angle in radians.
+    const fn rotation(self, tket_op: QuantumOp) -> Self {
+        Self {
+            params: 1,
+            tket_equivalent: Some(WellKnownTranslation::Rotation(tket_op)),
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::BTreeSet;
+    use rstest::rstest;
+    use std::mem::discriminant;
+
+    /// The catalog lists each gate once, with its own description.
+    #[rstest]
+    fn every_gate_described() {
+        let names = CATALOG.iter().map(|info| info.gate.to_string()).collect::<BTreeSet<_>>();
+        assert_eq!(names.len(), CATALOG.len(), "a gate is listed twice");
+        for info in CATALOG {
+            let described = GateInfo::of(info.gate);
+            assert_eq!(discriminant(&described.gate), discriminant(&info.gate));
+            assert_eq!(format!("{described:?}"), format!("{info:?}"));
+        }
+    }
+}
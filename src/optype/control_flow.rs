@@ -1,17 +1,22 @@
+use std::collections::BTreeMap;
+
 use hugr::builder::{
-    ConditionalBuilder, Container as _, Dataflow, DataflowSubContainer, SubContainer,
-    TailLoopBuilder,
+    ConditionalBuilder, Container as _, Dataflow, DataflowSubContainer, ModuleBuilder,
+    SubContainer, TailLoopBuilder,
 };
 use hugr::extension::prelude::bool_t;
-use hugr::ops::handle::NodeHandle;
+use hugr::ops::Value;
+use hugr::ops::handle::FuncID;
 use hugr::std_extensions::arithmetic::int_ops::IntOpDef;
 use hugr::std_extensions::arithmetic::int_types::int_type;
-use hugr::types::{Signature, SumType, TypeRow};
-use hugr::{HugrView as _, type_row};
+use hugr::std_extensions::logic::LogicOp;
+use hugr::types::{Signature, SumType, Type as HugrType, TypeRow};
+use hugr::{Hugr, HugrView as _, IncomingPort, OutgoingPort, type_row};
 use itertools::Itertools;
 use jeff::reader::Region;
 use jeff::reader::optype::{self as jeff_optype, ControlFlowOp};
 
+use crate::passes::tag_computation;
 use crate::to_hugr::BuildContext;
 use crate::types::{jeff_int_width_to_hugr_arg, jeff_int_width_to_hugr_width};
 use crate::{JeffToHugrError, types};
@@ -19,6 +24,36 @@ use crate::{JeffToHugrError, types};
 use super::JeffToHugrOp;
 use jeff::types::Type as JeffType;
 
+/// Number of nodes of a translated two-way `Switch`, excluding its branches:
+/// the `Conditional` and two cases with their input and output nodes.
+pub(crate) const SWITCH_NODES: usize = 7;
+/// Number of nodes of a `Switch` on a wider integer, excluding its cases:
+/// the `Conditional`, and the tag computation `DFG` with its input and output
+/// nodes and the loaded fallback tag.
+pub(crate) const INT_SWITCH_NODES: usize = 6;
+/// Number of nodes of a case of a `Switch` on a wider integer, excluding its
+/// branch: the case with its input and output nodes.
+pub(crate) const INT_SWITCH_CASE_NODES: usize = 3;
+/// Number of nodes comparing the selector of a `Switch` on a wider integer
+/// with a branch index: the loaded index, the `ieq`, and a two-way
+/// `Conditional` with its cases, their input and output nodes, and the
+/// loaded tag of the branch.
+pub(crate) const INT_SWITCH_COMPARISON_NODES: usize = 12;
+/// Number of nodes of a translated `DoWhile`, excluding its regions: the
+/// `TailLoop`, the body and condition `DFG`s, their input and output nodes,
+/// and the negation of the condition.
+pub(crate) const DO_WHILE_NODES: usize = 10;
+/// Number of nodes of a translated `While`, excluding its regions: the
+/// `TailLoop`, the condition `DFG`, the `Conditional` running the body, their
+/// input and output nodes, and the negation of the condition.
+pub(crate) const WHILE_NODES: usize = 14;
+/// Number of nodes of a translated `For`, excluding its body: the `TailLoop`,
+/// the bound comparison, the `Conditional`, the body `DFG`, their input and
+/// output nodes, the counter increment and the two loop control tags.
+pub(crate) const FOR_NODES: usize = 17;
+/// Number of nodes of a select function, see [`build_select_function`].
+pub(crate) const SELECT_FUNCTION_NODES: usize = 10;
+
 /// Translation for _jeff_ quantum ops
 impl JeffToHugrOp for jeff_optype::ControlFlowOp<'_> {
     fn build_hugr_op(
@@ -44,30 +79,87 @@ impl JeffToHugrOp for jeff_optype::ControlFlowOp<'_> {
 
         match self {
             ControlFlowOp::Switch(switch_op) => {
-                // For now, we only support an i1 switch
-                let Ok(JeffType::Int { bits: 1 }) = op.input_types().next().unwrap() else {
-                    todo!("Lower switches with more branches")
+                let branches = match (switch_op.branch_count(), switch_op.default_branch()) {
+                    (2, _) => Some([switch_op.branch(0), switch_op.branch(1)]),
+                    (1, Some(default_branch)) => Some([switch_op.branch(0), default_branch]),
+                    _ => None,
+                };
+                if let Some(branches) = branches
+                    && let Some(forwarded) = forwarded_sources(&branches, &input_types)?
+                {
+                    return build_select(&forwarded, &input_types[1..], op, builder, ctx);
+                }
+
+                let bits = match op.input_types().next().transpose()? {
+                    Some(JeffType::Int { bits }) => bits,
+                    Some(_) => return Err(JeffToHugrError::unsupported_op(self)),
+                    None => return Err(JeffToHugrError::invalid_op_io("Switch", op)),
                 };
+                // One case per branch, up to the number of selector values,
+                // and a last case for the other values.
+                let selector_values = 1usize.checked_shl(bits.into()).unwrap_or(usize::MAX);
+                let mut cases = (0..switch_op.branch_count().min(selector_values))
+                    .map(|idx| switch_op.branch(idx))
+                    .collect_vec();
+                let exhaustive = cases.len() == selector_values;
+                match switch_op.default_branch() {
+                    Some(default_branch) if !exhaustive => cases.push(default_branch),
+                    None if !exhaustive && bits > 1 => {
+                        return Err(JeffToHugrError::invalid_op_io("Switch", op));
+                    }
+                    _ => {}
+                }
+                // A missing `int(1)` branch forwards its inputs.
+                let forwarding = if bits == 1 { 2 - cases.len() } else { 0 };
+                // The constants only used by the switch are loaded in the
+                // branches. A missing branch forwards its inputs, so it would
+                // need them as inputs.
+                let sunk = ctx.place_boundary_constants(op, 1, forwarding == 0, builder)?;
+                let case_inputs = input_types[1..]
+                    .iter()
+                    .enumerate()
+                    .filter(|(position, _)| !sunk.contains_key(position))
+                    .map(|(_, ty)| ty.clone())
+                    .collect_vec();
+                let case_count = cases.len() + forwarding;
                 let mut cond_builder = ConditionalBuilder::new(
-                    vec![vec![].into(), vec![].into()],
-                    input_types,
+                    vec![type_row![]; case_count],
+                    case_inputs,
                     output_types,
                 )?;
-                let mut case0 = cond_builder.case_builder(0)?;
-                build_nested(&mut case0, &switch_op.branch(0))?;
-                let mut case1 = cond_builder.case_builder(1)?;
-                if switch_op.branch_count() > 1 {
-                    build_nested(&mut case1, &switch_op.branch(1))?;
-                } else if let Some(default_branch) = switch_op.default_branch() {
-                    build_nested(&mut case1, &default_branch)?;
-                } else {
-                    case1.set_outputs(case1.input_wires())?;
+                let mut nested = Vec::new();
+                for (idx, region) in cases.iter().enumerate() {
+                    let mut case = cond_builder.case_builder(idx)?;
+                    nested.push(build_nested(&mut case, region, &sunk, ctx)?);
+                }
+                for idx in cases.len()..case_count {
+                    let mut case = cond_builder.case_builder(idx)?;
+                    case.set_outputs(case.input_wires())?;
                 }
                 // Insert into the current Hugr and update context
-                let cond_node = builder
-                    .add_hugr(cond_builder.hugr().clone())
-                    .inserted_entrypoint;
-                for (port, value) in builder.hugr().node_inputs(cond_node).zip(op.inputs()) {
+                let res = builder.add_hugr(cond_builder.hugr().clone());
+                ctx.absorb_nested(nested, &res.node_map);
+                let cond_node = res.inserted_entrypoint;
+                let mut inputs = op
+                    .inputs()
+                    .enumerate()
+                    .filter(|&(position, _)| position == 0 || !sunk.contains_key(&(position - 1)))
+                    .map(|(_, value)| value);
+                // An `int(1)` selector is the `Conditional` predicate, a wider
+                // one is mapped to the index of its case.
+                let selector = inputs.next().unwrap()?.id();
+                if bits == 1 {
+                    ctx.register_input(selector, cond_node, IncomingPort::from(0));
+                } else {
+                    let log_width = jeff_int_width_to_hugr_width(bits);
+                    let values = (0..case_count as u64 - 1).collect_vec();
+                    let tag = builder
+                        .add_hugr(tag_computation(log_width, &values)?)
+                        .inserted_entrypoint;
+                    builder.hugr_mut().connect(tag, 0, cond_node, 0);
+                    ctx.register_input(selector, tag, IncomingPort::from(0));
+                }
+                for (port, value) in builder.hugr().node_inputs(cond_node).skip(1).zip(inputs) {
                     ctx.register_input(value?.id(), cond_node, port);
                 }
                 for (port, value) in builder.hugr().node_outputs(cond_node).zip(op.outputs()) {
@@ -75,47 +167,24 @@ impl JeffToHugrOp for jeff_optype::ControlFlowOp<'_> {
                 }
             }
             ControlFlowOp::DoWhile { body, condition } => {
-                if !itertools::equal(
-                    op.input_types().map(|ty| ty.unwrap()),
-                    op.output_types().map(|ty| ty.unwrap()),
-                ) {
+                if input_types != output_types {
                     return Err(JeffToHugrError::invalid_op_io("DoWhile", op));
                 }
-                let state_types = op
-                    .input_types()
-                    .map(|ty| types::jeff_to_hugr(ty.unwrap()))
-                    .collect_vec();
+                let state_types = input_types;
+                let condition_kind =
+                    LoopCondition::recognize(&region_target_types(condition)?, &state_types)
+                        .ok_or_else(|| JeffToHugrError::invalid_op_io("DoWhile", op))?;
+                // The loop state is carried across iterations, so its constants stay outside.
+                let sunk = ctx.place_boundary_constants(op, 0, false, builder)?;
 
-                let mut loop_builder = TailLoopBuilder::new(vec![], state_types.clone(), vec![])?;
-
-                let body_dfg = {
-                    let mut body_builder = loop_builder.dfg_builder(
-                        Signature::new_endo(state_types.clone()),
-                        loop_builder.input_wires(),
-                    )?;
-                    build_nested(&mut body_builder, body)?;
-                    body_builder.finish_sub_container()?
-                };
-
-                let condition_dfg = {
-                    let mut condition_builder = loop_builder.dfg_builder(
-                        Signature::new(state_types, vec![bool_t()]),
-                        body_dfg.outputs(),
-                    )?;
-                    build_nested(&mut condition_builder, condition)?;
-                    condition_builder.finish_sub_container()?
-                };
-                let conditional_result = condition_dfg.out_wire(0);
-
-                // TODO: This assumes that the state returned by the body is copyable.
-                //
-                // See <https://github.com/unitaryfoundation/jeff/issues/4>
-                loop_builder.set_outputs(conditional_result, body_dfg.outputs())?;
+                let mut regions = NestedLoopRegions::new(body, condition, &sunk, ctx);
+                let loop_hugr = build_do_while(&state_types, condition_kind, &mut regions)?;
+                let nested = regions.nested;
 
                 // Insert into the current Hugr and update context
-                let loop_node = builder
-                    .add_hugr(loop_builder.hugr().clone())
-                    .inserted_entrypoint;
+                let res = builder.add_hugr(loop_hugr);
+                ctx.absorb_nested(nested, &res.node_map);
+                let loop_node = res.inserted_entrypoint;
                 for (port, value) in builder.hugr().node_inputs(loop_node).zip(op.inputs()) {
                     ctx.register_input(value?.id(), loop_node, port);
                 }
@@ -124,65 +193,23 @@ impl JeffToHugrOp for jeff_optype::ControlFlowOp<'_> {
                 }
             }
             ControlFlowOp::While { body, condition } => {
-                if !itertools::equal(
-                    op.input_types().map(|ty| ty.unwrap()),
-                    op.output_types().map(|ty| ty.unwrap()),
-                ) {
-                    return Err(JeffToHugrError::invalid_op_io("DoWhile", op));
+                if input_types != output_types {
+                    return Err(JeffToHugrError::invalid_op_io("While", op));
                 }
-                let state_types = op
-                    .input_types()
-                    .map(|ty| types::jeff_to_hugr(ty.unwrap()))
-                    .collect_vec();
-
-                let mut loop_builder = TailLoopBuilder::new(vec![], state_types.clone(), vec![])?;
-
-                let condition_dfg = {
-                    let mut condition_builder = loop_builder.dfg_builder(
-                        Signature::new(state_types.clone(), vec![bool_t()]),
-                        loop_builder.input_wires(),
-                    )?;
-                    build_nested(&mut condition_builder, condition)?;
-                    condition_builder.finish_sub_container()?
-                };
-                let conditional_result = condition_dfg.out_wire(0);
-
-                let body_conditional = {
-                    // TODO: This assumes that the state at the loop_builder input is copyable.
-                    //
-                    // See <https://github.com/unitaryfoundation/jeff/issues/4>
-                    let mut conditional_builder = loop_builder.conditional_builder(
-                        ([type_row!(), type_row!()], conditional_result),
-                        state_types
-                            .clone()
-                            .into_iter()
-                            .zip(loop_builder.input_wires()),
-                        state_types.clone().into(),
-                    )?;
-
-                    // False branch
-                    {
-                        let false_case = conditional_builder.case_builder(0)?;
-                        let inputs = false_case.input_wires();
-                        false_case.finish_with_outputs(inputs)?;
-                    }
+                let state_types = input_types;
+                let condition_kind =
+                    LoopCondition::recognize(&region_target_types(condition)?, &state_types)
+                        .ok_or_else(|| JeffToHugrError::invalid_op_io("While", op))?;
+                let sunk = ctx.place_boundary_constants(op, 0, false, builder)?;
 
-                    // True branch
-                    {
-                        let mut body_builder = conditional_builder.case_builder(1)?;
-                        build_nested(&mut body_builder, body)?;
-                        body_builder.finish_sub_container()?;
-                    }
-
-                    conditional_builder.finish_sub_container()?
-                };
-
-                loop_builder.set_outputs(conditional_result, body_conditional.outputs())?;
+                let mut regions = NestedLoopRegions::new(body, condition, &sunk, ctx);
+                let loop_hugr = build_while(&state_types, condition_kind, &mut regions)?;
+                let nested = regions.nested;
 
                 // Insert into the current Hugr and update context
-                let loop_node = builder
-                    .add_hugr(loop_builder.hugr().clone())
-                    .inserted_entrypoint;
+                let res = builder.add_hugr(loop_hugr);
+                ctx.absorb_nested(nested, &res.node_map);
+                let loop_node = res.inserted_entrypoint;
                 for (port, value) in builder.hugr().node_inputs(loop_node).zip(op.inputs()) {
                     ctx.register_input(value?.id(), loop_node, port);
                 }
@@ -197,12 +224,17 @@ impl JeffToHugrOp for jeff_optype::ControlFlowOp<'_> {
                 // - `int(N)`: The (signed) stop value (exclusive).
                 // - `int(N)`: The (signed) step value.
                 // - `... state`: Any number of values that are passed to the loop body.
-                let Ok(JeffType::Int { bits }) = op.input_types().next().unwrap() else {
+                let Some(JeffType::Int { bits }) = op.input_types().next().transpose()? else {
                     return Err(JeffToHugrError::invalid_op_io("For", op));
                 };
+                if op.input_count() < 3 {
+                    return Err(JeffToHugrError::invalid_op_io("For", op));
+                }
                 let log_width = jeff_int_width_to_hugr_width(bits);
                 let int_t = || int_type(jeff_int_width_to_hugr_arg(bits));
                 let state_types = output_types;
+                let sunk = ctx.place_boundary_constants(op, 3, false, builder)?;
+                let mut nested = Vec::new();
 
                 // Construct a loop that takes
                 // - An integer counter
@@ -270,7 +302,7 @@ impl JeffToHugrOp for jeff_optype::ControlFlowOp<'_> {
                                     Signature::new(body_inputs, body_outputs),
                                     std::iter::once(start_value).chain(state_inputs),
                                 )?;
-                                build_nested(&mut body, region)?;
+                                nested.push(build_nested(&mut body, region, &sunk, ctx)?);
                                 body.finish_sub_container()?
                             };
 
@@ -329,6 +361,7 @@ impl JeffToHugrOp for jeff_optype::ControlFlowOp<'_> {
 
                 // Insert into the current hugr and update context
                 let res = builder.add_hugr(loop_hugr);
+                ctx.absorb_nested(nested, &res.node_map);
                 let loop_node = res.inserted_entrypoint;
                 for (port, value) in builder.hugr().node_inputs(loop_node).zip(op.inputs()) {
                     ctx.register_input(value?.id(), loop_node, port);
@@ -342,22 +375,710 @@ impl JeffToHugrOp for jeff_optype::ControlFlowOp<'_> {
     }
 }
 
+/// Recognize a two-way switch whose branches only forward their inputs.
+///
+/// Such switches select between classical values, and are common in
+/// bit-twiddling code. For each output, returns the index of the switch
+/// argument forwarded by each branch.
+///
+/// Returns `None` unless the selector is an `int(1)`, the branches contain no
+/// operations, and all the switch arguments are copyable.
+pub(crate) fn forwarded_sources(
+    branches: &[Region<'_>; 2],
+    input_types: &[HugrType],
+) -> Result<Option<Vec<[usize; 2]>>, JeffToHugrError> {
+    let Some((selector, args)) = input_types.split_first() else {
+        return Ok(None);
+    };
+    if *selector != bool_t() || !args.iter().all(HugrType::copyable) {
+        return Ok(None);
+    }
+
+    let mut forwarded = Vec::with_capacity(2);
+    for branch in branches {
+        if branch.operations().next().is_some() {
+            return Ok(None);
+        }
+        let sources = branch
+            .sources()
+            .map(|value| Ok(value?.id()))
+            .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+        let indices = branch
+            .targets()
+            .map(|value| {
+                let id = value?.id();
+                Ok(sources.iter().position(|&source| source == id))
+            })
+            .collect::<Result<Option<Vec<_>>, JeffToHugrError>>()?;
+        let Some(indices) = indices else {
+            return Ok(None);
+        };
+        forwarded.push(indices);
+    }
+
+    let [first, second] = <[Vec<usize>; 2]>::try_from(forwarded).unwrap();
+    if first.len() != second.len() {
+        return Ok(None);
+    }
+    Ok(Some(
+        first.into_iter().zip(second).map(|(a, b)| [a, b]).collect(),
+    ))
+}
+
+/// Translate a switch whose branches only forward their inputs.
+///
+/// Outputs forwarding the same argument in both branches are merged with it.
+/// The others call a select function shared by all selects of the same
+/// type, defined once the module is built. See [`build_select_function`].
+fn build_select(
+    forwarded: &[[usize; 2]],
+    arg_types: &[HugrType],
+    op: &jeff::reader::Operation<'_>,
+    builder: &mut impl hugr::builder::Dataflow,
+    ctx: &mut BuildContext,
+) -> Result<(), JeffToHugrError> {
+    let inputs = op
+        .inputs()
+        .map(|value| Ok(value?.id()))
+        .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+    let (&selector, args) = inputs.split_first().unwrap();
+
+    for (output, &[first, second]) in op.outputs().zip(forwarded) {
+        let output = output?;
+        if first == second {
+            ctx.merge_with_earlier(output.id(), args[first]);
+            continue;
+        }
+
+        let ty = arg_types[first].clone();
+        let call = hugr::ops::Call::try_new(select_signature(ty.clone()).into(), vec![]).unwrap();
+        let node = builder.add_child_node(call);
+        ctx.record_origin(node);
+        ctx.register_input(selector, node, IncomingPort::from(0));
+        ctx.register_input(args[first], node, IncomingPort::from(1));
+        ctx.register_input(args[second], node, IncomingPort::from(2));
+        ctx.register_output(output.id(), node, OutgoingPort::from(0));
+        ctx.register_select_call(ty, node, IncomingPort::from(3));
+    }
+    Ok(())
+}
+
+/// The signature `(bool, T, T) -> T` of a select function.
+fn select_signature(ty: HugrType) -> Signature {
+    Signature::new(vec![bool_t(), ty.clone(), ty.clone()], vec![ty])
+}
+
+/// Define a select function for values of type `ty` in a module.
+///
+/// The function returns its first value when the flag is false, and its
+/// second value otherwise.
+pub(crate) fn build_select_function(
+    name: &str,
+    ty: HugrType,
+    module: &mut ModuleBuilder<&mut Hugr>,
+) -> Result<FuncID<true>, JeffToHugrError> {
+    let mut func = module.define_function(name, select_signature(ty.clone()))?;
+    let [flag, first, second] = func.input_wires_arr();
+
+    let mut cond = func.conditional_builder(
+        ([type_row![], type_row![]], flag),
+        [(ty.clone(), first), (ty.clone(), second)],
+        vec![ty].into(),
+    )?;
+    for case in 0..2 {
+        let case_builder = cond.case_builder(case)?;
+        let selected = case_builder.input_wires().nth(case).unwrap();
+        case_builder.finish_with_outputs([selected])?;
+    }
+    let cond = cond.finish_sub_container()?;
+
+    Ok(func.finish_with_outputs(cond.outputs())?)
+}
+
+/// The shape of the condition region in a _jeff_ `DoWhile` or `While` loop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LoopCondition {
+    /// The condition reads the loop state and returns a single `int(1)`.
+    ///
+    /// The state is used both by the condition and the loop body, so it must be copyable.
+    Pure,
+    /// The condition returns an `int(1)` followed by the loop state.
+    ///
+    /// This is the case for measurement-conditioned loops (e.g. repeat-until-success),
+    /// where the condition consumes the qubits it measures and returns them afterwards.
+    Threaded,
+}
+
+impl LoopCondition {
+    /// Recognize the shape of a loop condition region from the types of its
+    /// targets.
+    ///
+    /// Returns `None` if the targets are not an `int(1)` flag optionally
+    /// followed by the loop state, or if a condition only returning the flag
+    /// reads a state that is not copyable.
+    fn recognize(target_types: &[HugrType], state_types: &[HugrType]) -> Option<Self> {
+        match target_types.split_first() {
+            Some((flag, [])) if *flag == bool_t() => state_types
+                .iter()
+                .all(HugrType::copyable)
+                .then_some(Self::Pure),
+            Some((flag, state)) if *flag == bool_t() && state == state_types => {
+                Some(Self::Threaded)
+            }
+            _ => None,
+        }
+    }
+
+    /// The signature of the DFG holding the condition region.
+    fn signature(&self, state_types: &[HugrType]) -> Signature {
+        let outputs = match self {
+            Self::Pure => vec![bool_t()],
+            Self::Threaded => std::iter::once(bool_t())
+                .chain(state_types.iter().cloned())
+                .collect_vec(),
+        };
+        Signature::new(state_types.to_vec(), outputs)
+    }
+}
+
+/// The HUGR types of the targets of a _jeff_ region.
+fn region_target_types(region: &Region) -> Result<Vec<HugrType>, JeffToHugrError> {
+    region
+        .targets()
+        .map(|value| Ok(types::jeff_to_hugr(value?.ty())))
+        .collect()
+}
+
+/// A region of a _jeff_ `DoWhile` or `While` loop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LoopRegion {
+    /// The loop body.
+    Body,
+    /// The condition deciding whether to run another iteration.
+    Condition,
+}
+
+/// Builder of the regions of a translated `DoWhile` or `While` loop.
+trait LoopRegionBuilder {
+    /// Build a region of the loop in a dataflow container, connecting its
+    /// inputs and outputs.
+    fn build_region(
+        &mut self,
+        region: LoopRegion,
+        builder: &mut impl Dataflow,
+    ) -> Result<(), JeffToHugrError>;
+}
+
+/// The regions of a _jeff_ loop, built with [`build_nested`].
+struct NestedLoopRegions<'a, 'r> {
+    body: &'a Region<'r>,
+    condition: &'a Region<'r>,
+    sunk: &'a BTreeMap<usize, Value>,
+    ctx: &'a mut BuildContext,
+    /// The contexts of the built regions, to merge back into `ctx`.
+    nested: Vec<BuildContext>,
+}
+
+impl<'a, 'r> NestedLoopRegions<'a, 'r> {
+    fn new(
+        body: &'a Region<'r>,
+        condition: &'a Region<'r>,
+        sunk: &'a BTreeMap<usize, Value>,
+        ctx: &'a mut BuildContext,
+    ) -> Self {
+        Self {
+            body,
+            condition,
+            sunk,
+            ctx,
+            nested: Vec::new(),
+        }
+    }
+}
+
+impl LoopRegionBuilder for NestedLoopRegions<'_, '_> {
+    fn build_region(
+        &mut self,
+        region: LoopRegion,
+        builder: &mut impl Dataflow,
+    ) -> Result<(), JeffToHugrError> {
+        let region = match region {
+            LoopRegion::Body => self.body,
+            LoopRegion::Condition => self.condition,
+        };
+        self.nested
+            .push(build_nested(builder, region, self.sunk, self.ctx)?);
+        Ok(())
+    }
+}
+
+/// Build the `TailLoop` translating a _jeff_ `DoWhile`, which runs the body
+/// and then the condition until the condition is false.
+///
+/// A `TailLoop` continues on the first tag of its control output, so the
+/// condition is negated.
+///
+/// The HUGR is not validated, as the regions may have inputs connected later
+/// by the build context.
+fn build_do_while(
+    state_types: &[HugrType],
+    condition_kind: LoopCondition,
+    regions: &mut impl LoopRegionBuilder,
+) -> Result<Hugr, JeffToHugrError> {
+    let mut loop_builder = TailLoopBuilder::new(vec![], state_types.to_vec(), vec![])?;
+
+    let body_dfg = {
+        let mut body_builder = loop_builder.dfg_builder(
+            Signature::new_endo(state_types.to_vec()),
+            loop_builder.input_wires(),
+        )?;
+        regions.build_region(LoopRegion::Body, &mut body_builder)?;
+        body_builder.finish_sub_container()?
+    };
+
+    let condition_dfg = {
+        let mut condition_builder =
+            loop_builder.dfg_builder(condition_kind.signature(state_types), body_dfg.outputs())?;
+        regions.build_region(LoopRegion::Condition, &mut condition_builder)?;
+        condition_builder.finish_sub_container()?
+    };
+    let mut condition_outputs = condition_dfg.outputs();
+    let conditional_result = condition_outputs.next().unwrap();
+    let continue_flag = loop_builder
+        .add_dataflow_op(LogicOp::Not, [conditional_result])?
+        .out_wire(0);
+
+    match condition_kind {
+        // The condition only read the body outputs, so we can reuse them.
+        LoopCondition::Pure => loop_builder.set_outputs(continue_flag, body_dfg.outputs())?,
+        LoopCondition::Threaded => loop_builder.set_outputs(continue_flag, condition_outputs)?,
+    }
+    Ok(loop_builder.hugr().clone())
+}
+
+/// Build the `TailLoop` translating a _jeff_ `While`, which runs the
+/// condition and then the body while the condition is true.
+///
+/// A `TailLoop` continues on the first tag of its control output, so the
+/// condition is negated.
+///
+/// The HUGR is not validated, as the regions may have inputs connected later
+/// by the build context.
+fn build_while(
+    state_types: &[HugrType],
+    condition_kind: LoopCondition,
+    regions: &mut impl LoopRegionBuilder,
+) -> Result<Hugr, JeffToHugrError> {
+    let mut loop_builder = TailLoopBuilder::new(vec![], state_types.to_vec(), vec![])?;
+
+    let condition_dfg = {
+        let mut condition_builder = loop_builder.dfg_builder(
+            condition_kind.signature(state_types),
+            loop_builder.input_wires(),
+        )?;
+        regions.build_region(LoopRegion::Condition, &mut condition_builder)?;
+        condition_builder.finish_sub_container()?
+    };
+    let mut condition_outputs = condition_dfg.outputs();
+    let conditional_result = condition_outputs.next().unwrap();
+
+    let body_conditional = {
+        // The body runs on the state returned by the condition if it threads
+        // it, otherwise on the (copyable) state at the loop input.
+        let state_wires = match condition_kind {
+            LoopCondition::Pure => loop_builder.input_wires().collect_vec(),
+            LoopCondition::Threaded => condition_outputs.collect_vec(),
+        };
+        let mut conditional_builder = loop_builder.conditional_builder(
+            ([type_row!(), type_row!()], conditional_result),
+            state_types.iter().cloned().zip(state_wires),
+            state_types.to_vec().into(),
+        )?;
+
+        // False branch
+        {
+            let false_case = conditional_builder.case_builder(0)?;
+            let inputs = false_case.input_wires();
+            false_case.finish_with_outputs(inputs)?;
+        }
+
+        // True branch
+        {
+            let mut body_builder = conditional_builder.case_builder(1)?;
+            regions.build_region(LoopRegion::Body, &mut body_builder)?;
+            body_builder.finish_sub_container()?;
+        }
+
+        conditional_builder.finish_sub_container()?
+    };
+
+    let continue_flag = loop_builder
+        .add_dataflow_op(LogicOp::Not, [conditional_result])?
+        .out_wire(0);
+    loop_builder.set_outputs(continue_flag, body_conditional.outputs())?;
+    Ok(loop_builder.hugr().clone())
+}
+
 /// Build a region nested inside a builder.
 ///
-/// Uses the builder's input and output nodes for the new `BuildContext` input and output wires.
+/// The region sources and targets are read from the builder's input and
+/// output nodes, except for the `sunk` constants, loaded inside the region
+/// by source position. See [`BuildContext::place_boundary_constants`].
+///
+/// Returns the nested context, which must be merged back into `ctx` with
+/// [`BuildContext::absorb_nested`] once the nested nodes are inserted in the final HUGR.
 fn build_nested(
     builder: &mut impl hugr::builder::Dataflow,
     region: &Region,
-) -> Result<(), JeffToHugrError> {
-    let inp_node = builder.input().node();
-    let out_node = builder.output().node();
-    let mut ctx = BuildContext::default();
-    for (port, value) in builder.hugr().node_outputs(inp_node).zip(region.sources()) {
-        ctx.register_output(value?.id(), inp_node, port);
+    sunk: &BTreeMap<usize, Value>,
+    ctx: &mut BuildContext,
+) -> Result<BuildContext, JeffToHugrError> {
+    let mut nested = ctx.nested();
+    nested.set_sunk_sources(sunk.clone());
+    nested.build_region(*region, builder)?;
+    ctx.sync_nested(&nested);
+    Ok(nested)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::optype::tket_ops::{self, QuantumOp};
+    use crate::test::{int_switch, int_switch_no_default, switch_constants};
+    use crate::{JeffToHugrConfig, jeff_to_hugr_with_config};
+    use hugr::builder::{DFGBuilder, DataflowHugr};
+    use hugr::extension::prelude::qb_t;
+    use hugr::hugr::hugrmut::HugrMut;
+    use hugr::ops::handle::NodeHandle;
+    use hugr::std_extensions::arithmetic::float_types::ConstF64;
+    use hugr::types::EdgeKind;
+    use hugr::{HugrView, Node};
+    use jeff::Jeff;
+    use rstest::rstest;
+
+    /// The `Const` node holding a float constant in a function.
+    fn float_const(hugr: &Hugr, func: Node, value: f64) -> Node {
+        hugr.descendants(func)
+            .find(|&n| {
+                hugr.get_optype(n)
+                    .as_const()
+                    .and_then(|c| c.value().get_custom_value::<ConstF64>())
+                    .is_some_and(|f| f.value() == value)
+            })
+            .unwrap_or_else(|| panic!("No constant {value} in the function"))
     }
-    for (port, value) in builder.hugr().node_inputs(out_node).zip(region.targets()) {
-        ctx.register_input(value?.id(), out_node, port);
+
+    /// The number of value edges between nodes with different parents.
+    fn nonlocal_edges(hugr: &Hugr) -> usize {
+        hugr.nodes()
+            .flat_map(|node| hugr.node_inputs(node).map(move |port| (node, port)))
+            .filter(|&(node, port)| {
+                matches!(hugr.get_optype(node).port_kind(port), Some(EdgeKind::Value(_)))
+            })
+            .flat_map(|(node, port)| {
+                hugr.linked_outputs(node, port).map(move |(src, _)| (src, node))
+            })
+            .filter(|&(src, tgt)| hugr.get_parent(src) != hugr.get_parent(tgt))
+            .count()
+    }
+
+    /// The `Conditional` translating the switch of a function.
+    fn switch_conditional(hugr: &Hugr, func: Node) -> Node {
+        hugr.children(func)
+            .find(|&n| hugr.get_optype(n).is_conditional())
+            .unwrap()
+    }
+
+    /// Constants only used by a switch are loaded in the branches reading them.
+    #[rstest]
+    fn switch_sinks_constants(switch_constants: Jeff<'static>) {
+        let converted =
+            jeff_to_hugr_with_config(&switch_constants, &JeffToHugrConfig::default()).unwrap();
+        let hugr = &converted.hugr;
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        let func = converted.map.function_by_name("distinct").unwrap();
+        let cond = switch_conditional(hugr, func);
+        let cases = hugr.children(cond).collect_vec();
+        assert_eq!(hugr.get_parent(float_const(hugr, func, 0.3)), Some(cases[0]));
+        assert_eq!(hugr.get_parent(float_const(hugr, func, 0.7)), Some(cases[1]));
+        // Only the flag and the qubit are passed to the `Conditional`.
+        let sig = hugr.get_optype(cond).dataflow_signature().unwrap();
+        assert_eq!(sig.input_count(), 2);
+
+        assert_eq!(nonlocal_edges(hugr), 0);
+        assert_eq!(converted.stats.sunk_constants, 2);
+    }
+
+    /// Constants also used outside of a switch stay in the enclosing region,
+    /// and are passed as inputs of the `Conditional`.
+    #[rstest]
+    fn switch_threads_shared_constants(switch_constants: Jeff<'static>) {
+        let converted =
+            jeff_to_hugr_with_config(&switch_constants, &JeffToHugrConfig::default()).unwrap();
+        let hugr = &converted.hugr;
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        let func = converted.map.function_by_name("shared").unwrap();
+        let cond = switch_conditional(hugr, func);
+        assert_eq!(hugr.get_parent(float_const(hugr, func, 0.3)), Some(func));
+        // The flag, the qubit and the angle are passed to the `Conditional`.
+        let sig = hugr.get_optype(cond).dataflow_signature().unwrap();
+        assert_eq!(sig.input_count(), 3);
+        let (angle, _) = hugr.single_linked_output(cond, 2).unwrap();
+        assert!(hugr.get_optype(angle).is_load_constant());
+
+        assert_eq!(nonlocal_edges(hugr), 0);
+        assert_eq!(converted.stats.threaded_constants, 1);
+    }
+
+    /// A switch on an `int(32)` has one case per branch and a last case for
+    /// the default branch, chosen by comparing the selector with each index.
+    #[rstest]
+    fn int_switch_cases(int_switch: Jeff<'static>) {
+        let converted =
+            jeff_to_hugr_with_config(&int_switch, &JeffToHugrConfig::default()).unwrap();
+        let hugr = &converted.hugr;
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        let func = converted.map.function_by_name("three_way").unwrap();
+        let cond = switch_conditional(hugr, func);
+        assert_eq!(hugr.children(cond).count(), 4);
+        // Only the qubit is passed to the cases.
+        let sig = hugr.get_optype(cond).dataflow_signature().unwrap();
+        assert_eq!(sig.input_count(), 2);
+
+        // The predicate is computed from the selector by a `DFG`.
+        let (tag, _) = hugr.single_linked_output(cond, 0).unwrap();
+        assert!(hugr.get_optype(tag).is_dfg());
+        let comparisons = hugr
+            .descendants(tag)
+            .filter(|&n| {
+                hugr.get_optype(n)
+                    .as_extension_op()
+                    .is_some_and(|op| op.unqualified_id() == "ieq")
+            })
+            .count();
+        assert_eq!(comparisons, 3);
+    }
+
+    /// A switch on an `int(8)` without a default branch does not cover all
+    /// the selector values.
+    #[rstest]
+    fn int_switch_without_default(int_switch_no_default: Jeff<'static>) {
+        let result = jeff_to_hugr_with_config(&int_switch_no_default, &JeffToHugrConfig::default());
+        assert!(matches!(
+            result,
+            Err(JeffToHugrError::InvalidOperationIO { ref op, .. }) if op == "Switch"
+        ));
+    }
+
+    /// Repeated selects share a single function, with a single `Conditional`.
+    #[rstest]
+    fn shared_select_function() {
+        let int_t = int_type(5);
+        let num_selects = 500;
+
+        let mut builder = DFGBuilder::new(Signature::new(
+            vec![bool_t(), int_t.clone(), int_t.clone()],
+            vec![int_t.clone()],
+        ))
+        .unwrap();
+        let [flag, first, mut second] = builder.input_wires_arr();
+        let mut calls = Vec::new();
+        for _ in 0..num_selects {
+            let call = hugr::ops::Call::try_new(select_signature(int_t.clone()).into(), vec![])
+                .unwrap();
+            second = builder
+                .add_dataflow_op(call, [flag, first, second])
+                .unwrap()
+                .out_wire(0);
+            calls.push(second.node());
+        }
+        let mut hugr = builder.finish_hugr_with_outputs([second]).unwrap();
+
+        let module_root = hugr.module_root();
+        hugr.set_entrypoint(module_root);
+        let func = {
+            let mut module = ModuleBuilder::with_hugr(&mut hugr);
+            build_select_function("select", int_t, &mut module).unwrap()
+        };
+        for call in calls {
+            hugr.connect(func.node(), 0, call, 3);
+        }
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        let conditionals = hugr
+            .nodes()
+            .filter(|&n| hugr.get_optype(n).is_conditional())
+            .count();
+        assert_eq!(conditionals, 1);
+        assert!(hugr.num_nodes() < 3 * num_selects);
+    }
+
+    /// The select function returns its first value on `false`.
+    #[rstest]
+    fn select_function_cases() {
+        let mut hugr = Hugr::default();
+        let func = build_select_function(
+            "select",
+            int_type(5),
+            &mut ModuleBuilder::with_hugr(&mut hugr),
+        )
+        .unwrap();
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        let cond = hugr
+            .descendants(func.node())
+            .find(|&n| hugr.get_optype(n).is_conditional())
+            .unwrap();
+        // The case inputs are the two values, as the flag is consumed by the conditional.
+        for (case, expected_port) in hugr.children(cond).zip([0, 1]) {
+            let [case_input, case_output] = hugr.get_io(case).unwrap();
+            let (src, src_port) = hugr.single_linked_output(case_output, 0).unwrap();
+            assert_eq!(src, case_input);
+            assert_eq!(src_port.index(), expected_port);
+        }
+    }
+
+    /// The condition region shapes recognized for a loop state.
+    #[rstest]
+    #[case::pure(vec![bool_t()], vec![int_type(5)], Some(LoopCondition::Pure))]
+    #[case::pure_without_state(vec![bool_t()], vec![], Some(LoopCondition::Pure))]
+    #[case::pure_linear(vec![bool_t()], vec![qb_t()], None)]
+    #[case::threaded(vec![bool_t(), qb_t()], vec![qb_t()], Some(LoopCondition::Threaded))]
+    #[case::threaded_copyable(
+        vec![bool_t(), int_type(5)],
+        vec![int_type(5)],
+        Some(LoopCondition::Threaded)
+    )]
+    #[case::no_targets(vec![], vec![qb_t()], None)]
+    #[case::flag_not_bool(vec![int_type(5)], vec![int_type(5)], None)]
+    #[case::flag_not_first(vec![qb_t(), bool_t()], vec![qb_t()], None)]
+    #[case::state_mismatch(vec![bool_t(), int_type(5)], vec![qb_t()], None)]
+    #[case::state_missing(vec![bool_t(), qb_t()], vec![qb_t(), qb_t()], None)]
+    fn recognize_loop_condition(
+        #[case] target_types: Vec<HugrType>,
+        #[case] state_types: Vec<HugrType>,
+        #[case] expected: Option<LoopCondition>,
+    ) {
+        assert_eq!(
+            LoopCondition::recognize(&target_types, &state_types),
+            expected
+        );
+    }
+
+    /// A repeat-until-success loop on a qubit: the body resets it and the
+    /// condition measures it, returning the outcome and the qubit.
+    struct RepeatUntilSuccess;
+
+    impl LoopRegionBuilder for RepeatUntilSuccess {
+        fn build_region(
+            &mut self,
+            region: LoopRegion,
+            builder: &mut impl Dataflow,
+        ) -> Result<(), JeffToHugrError> {
+            let [qb] = builder.input_wires_arr();
+            match region {
+                LoopRegion::Body => {
+                    let reset = builder.add_dataflow_op(tket_ops::reset(), [qb])?;
+                    builder.set_outputs(reset.outputs())?;
+                }
+                LoopRegion::Condition => {
+                    let [qb, flag] = builder
+                        .add_dataflow_op(tket_ops::measure(), [qb])?
+                        .outputs_arr();
+                    builder.set_outputs([flag, qb])?;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// Insert a loop on a qubit in a DFG, returning the validated HUGR and
+    /// the `TailLoop` node.
+    fn qubit_loop_in_dfg(loop_hugr: Hugr) -> (Hugr, Node) {
+        let mut builder = DFGBuilder::new(Signature::new_endo(vec![qb_t()])).unwrap();
+        let inputs = builder.input_wires();
+        let tail_loop = builder.add_hugr_with_wires(loop_hugr, inputs).unwrap();
+        let hugr = builder
+            .finish_hugr_with_outputs(tail_loop.outputs())
+            .unwrap_or_else(|e| panic!("{e}"));
+        (hugr, tail_loop.node())
+    }
+
+    /// The node feeding the control input of a `TailLoop`, checking it
+    /// negates the condition flag.
+    fn negated_condition(hugr: &Hugr, tail_loop: Node) -> (Node, OutgoingPort) {
+        let [_, loop_output] = hugr.get_io(tail_loop).unwrap();
+        let (not, _) = hugr.single_linked_output(loop_output, 0).unwrap();
+        assert_eq!(hugr.get_optype(not).cast::<LogicOp>(), Some(LogicOp::Not));
+        hugr.single_linked_output(not, 0).unwrap()
+    }
+
+    /// A `DoWhile` continues while its condition is true, so the control
+    /// input of the `TailLoop` is the negated flag of the condition and the
+    /// threaded qubit is the loop state.
+    #[rstest]
+    fn do_while_continues_on_true() {
+        let loop_hugr =
+            build_do_while(&[qb_t()], LoopCondition::Threaded, &mut RepeatUntilSuccess).unwrap();
+        let (hugr, tail_loop) = qubit_loop_in_dfg(loop_hugr);
+        assert!(hugr.get_optype(tail_loop).is_tail_loop());
+
+        let (condition, flag_port) = negated_condition(&hugr, tail_loop);
+        assert!(hugr.get_optype(condition).is_dfg());
+        assert_eq!(flag_port.index(), 0);
+        let [_, condition_output] = hugr.get_io(condition).unwrap();
+        let (measure, measure_port) = hugr.single_linked_output(condition_output, 0).unwrap();
+        assert_eq!(
+            QuantumOp::of(hugr.get_optype(measure)),
+            Some(QuantumOp::Measure)
+        );
+        assert_eq!(measure_port.index(), 1);
+
+        // The body runs before the condition, which returns the loop state.
+        let (body, _) = hugr.single_linked_output(condition, 0).unwrap();
+        assert!(hugr.get_optype(body).is_dfg());
+        let [_, loop_output] = hugr.get_io(tail_loop).unwrap();
+        assert_eq!(
+            hugr.single_linked_output(loop_output, 1),
+            Some((condition, OutgoingPort::from(1)))
+        );
+    }
+
+    /// A `While` runs its body in the true case of a `Conditional` on the
+    /// condition flag, and continues on the negated flag.
+    #[rstest]
+    fn while_continues_on_true() {
+        let loop_hugr =
+            build_while(&[qb_t()], LoopCondition::Threaded, &mut RepeatUntilSuccess).unwrap();
+        let (hugr, tail_loop) = qubit_loop_in_dfg(loop_hugr);
+
+        let (condition, flag_port) = negated_condition(&hugr, tail_loop);
+        assert!(hugr.get_optype(condition).is_dfg());
+        assert_eq!(flag_port.index(), 0);
+
+        let cond = hugr
+            .children(tail_loop)
+            .find(|&n| hugr.get_optype(n).is_conditional())
+            .unwrap();
+        assert_eq!(
+            hugr.single_linked_output(cond, 0),
+            Some((condition, OutgoingPort::from(0)))
+        );
+        assert_eq!(
+            hugr.single_linked_output(cond, 1),
+            Some((condition, OutgoingPort::from(1)))
+        );
+        let cases = hugr.children(cond).collect_vec();
+        assert_eq!(cases.len(), 2);
+        // The false case passes the state through, the true case resets it.
+        let has_reset = |case: Node| {
+            hugr.children(case)
+                .any(|n| QuantumOp::of(hugr.get_optype(n)) == Some(QuantumOp::Reset))
+        };
+        assert!(!has_reset(cases[0]));
+        assert!(has_reset(cases[1]));
     }
-    ctx.build_region(*region, builder)?;
-    Ok(())
 }
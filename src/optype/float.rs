@@ -3,6 +3,7 @@ use hugr::std_extensions::arithmetic::float_types::ConstF64;
 use jeff::reader::optype as jeff_optype;
 
 use crate::JeffToHugrError;
+use crate::extension::JeffOp;
 use crate::to_hugr::BuildContext;
 
 use super::JeffToHugrOp;
@@ -17,7 +18,7 @@ impl JeffToHugrOp for jeff_optype::FloatOp {
     ) -> Result<(), JeffToHugrError> {
         match self {
             jeff_optype::FloatOp::Const32(f) => {
-                ctx.build_constant_value(ConstF64::new(*f as f64), op, builder)?
+                ctx.build_constant_value(ConstF64::new(widen_f32(*f)), op, builder)?
             }
             jeff_optype::FloatOp::Const64(f) => {
                 ctx.build_constant_value(ConstF64::new(*f), op, builder)?
@@ -32,14 +33,12 @@ impl JeffToHugrOp for jeff_optype::FloatOp {
             jeff_optype::FloatOp::Abs => ctx.build_single_op(FloatOps::fabs, op, builder)?,
             jeff_optype::FloatOp::Ceil => ctx.build_single_op(FloatOps::fceil, op, builder)?,
             jeff_optype::FloatOp::Floor => ctx.build_single_op(FloatOps::ffloor, op, builder)?,
-            jeff_optype::FloatOp::Exp => ctx.build_single_op(FloatOps::fpow, op, builder)?,
             jeff_optype::FloatOp::Max => ctx.build_single_op(FloatOps::fmax, op, builder)?,
             jeff_optype::FloatOp::Min => ctx.build_single_op(FloatOps::fmin, op, builder)?,
-            // Unsupported _jeff_ float ops
+            // Functions without `arithmetic.float` equivalent
             jeff_optype::FloatOp::Sqrt
-            | jeff_optype::FloatOp::IsNan
-            | jeff_optype::FloatOp::IsInf
             | jeff_optype::FloatOp::Log
+            | jeff_optype::FloatOp::Exp
             | jeff_optype::FloatOp::Sin
             | jeff_optype::FloatOp::Cos
             | jeff_optype::FloatOp::Tan
@@ -52,9 +51,174 @@ impl JeffToHugrOp for jeff_optype::FloatOp {
             | jeff_optype::FloatOp::Tanh
             | jeff_optype::FloatOp::Asinh
             | jeff_optype::FloatOp::Acosh
-            | jeff_optype::FloatOp::Atanh
-            | _ => return Err(JeffToHugrError::unsupported_op(self)),
+            | jeff_optype::FloatOp::Atanh => {
+                let function = JeffOp::float_function(self).expect("A jeff float function");
+                ctx.build_single_op(function, op, builder)?
+            }
+            // Unsupported _jeff_ float ops
+            jeff_optype::FloatOp::IsNan | jeff_optype::FloatOp::IsInf | _ => {
+                return Err(JeffToHugrError::unsupported_op(self));
+            }
         };
         Ok(())
     }
 }
+
+/// Widen a 32-bit float to a 64-bit float, keeping the sign and the payload
+/// of NaNs.
+///
+/// Float constants are translated bit-exactly: `-0.0` and the infinities are
+/// kept by the conversion, but an `as` cast may replace a NaN with another.
+pub(super) fn widen_f32(value: f32) -> f64 {
+    if !value.is_nan() {
+        return f64::from(value);
+    }
+    let bits = u64::from(value.to_bits());
+    let sign = (bits >> 31) << 63;
+    let payload = (bits & 0x007f_ffff) << 29;
+    f64::from_bits(sign | 0x7ff0_0000_0000_0000 | payload)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::jeff_to_hugr;
+    use crate::test::float_functions;
+    use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr};
+    use hugr::std_extensions::arithmetic::float_types::float64_type;
+    use hugr::types::{Signature, TypeRow};
+    use hugr::{HugrView, OutgoingPort};
+    use jeff::Jeff;
+    use rstest::rstest;
+
+    /// The special doubles, with some NaN payloads.
+    const SPECIAL_F64: [u64; 12] = [
+        0x0000_0000_0000_0000, // 0.0
+        0x8000_0000_0000_0000, // -0.0
+        0x7ff0_0000_0000_0000, // inf
+        0xfff0_0000_0000_0000, // -inf
+        0x7ff8_0000_0000_0000, // quiet NaN
+        0xfff8_0000_0000_0000, // negative quiet NaN
+        0x7ff8_0000_dead_beef, // quiet NaN with a payload
+        0x7ff0_0000_0000_0001, // signaling NaN
+        0x0000_0000_0000_0001, // smallest subnormal
+        0x8010_0000_0000_0000, // -MIN_POSITIVE
+        0x7fef_ffff_ffff_ffff, // MAX
+        0xffef_ffff_ffff_ffff, // MIN
+    ];
+
+    /// The special doubles and some random finite doubles.
+    fn f64_patterns() -> Vec<u64> {
+        // A xorshift generator, for reproducible sequences.
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let random = std::iter::from_fn(move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            Some(state)
+        });
+        let finite = random.filter(|&bits| f64::from_bits(bits).is_finite());
+        SPECIAL_F64.into_iter().chain(finite.take(200)).collect()
+    }
+
+    /// The bits of the float constant loaded by a HUGR.
+    fn loaded_bits(value: ConstF64) -> u64 {
+        let mut builder = DFGBuilder::new(Signature::new_endo(TypeRow::new())).unwrap();
+        builder.add_load_value(value);
+        let hugr = builder.finish_hugr_with_outputs([]).unwrap();
+        let constant = hugr
+            .nodes()
+            .find(|&node| hugr.get_optype(node).is_const())
+            .unwrap();
+        let value = hugr.get_optype(constant).as_const().unwrap().value();
+        value
+            .get_custom_value::<ConstF64>()
+            .unwrap()
+            .value()
+            .to_bits()
+    }
+
+    #[rstest]
+    fn f64_constants_are_bit_exact() {
+        for bits in f64_patterns() {
+            let value = ConstF64::new(f64::from_bits(bits));
+            assert_eq!(loaded_bits(value), bits, "{bits:#018x}");
+        }
+    }
+
+    #[rstest]
+    #[case::zero(0x0000_0000, 0x0000_0000_0000_0000)]
+    #[case::neg_zero(0x8000_0000, 0x8000_0000_0000_0000)]
+    #[case::inf(0x7f80_0000, 0x7ff0_0000_0000_0000)]
+    #[case::neg_inf(0xff80_0000, 0xfff0_0000_0000_0000)]
+    #[case::one(0x3f80_0000, 0x3ff0_0000_0000_0000)]
+    #[case::subnormal(0x0000_0001, 0x36a0_0000_0000_0000)]
+    #[case::quiet_nan(0x7fc0_1234, 0x7ff8_0246_8000_0000)]
+    #[case::signaling_nan(0xff80_0001, 0xfff0_0000_2000_0000)]
+    fn f32_constants_are_bit_exact(#[case] bits: u32, #[case] expected: u64) {
+        let widened = widen_f32(f32::from_bits(bits));
+        assert_eq!(widened.to_bits(), expected, "{bits:#010x}");
+        assert_eq!(loaded_bits(ConstF64::new(widened)), expected);
+    }
+
+    /// A [`JeffOp::FloatUnaryFn`] applying the named function.
+    fn unary(name: &str) -> Option<JeffOp> {
+        let name = name.to_string();
+        Some(JeffOp::FloatUnaryFn { name })
+    }
+
+    #[rstest]
+    #[case::sqrt(jeff_optype::FloatOp::Sqrt, unary("sqrt"))]
+    #[case::log(jeff_optype::FloatOp::Log, unary("log"))]
+    #[case::exp(jeff_optype::FloatOp::Exp, unary("exp"))]
+    #[case::sin(jeff_optype::FloatOp::Sin, unary("sin"))]
+    #[case::acosh(jeff_optype::FloatOp::Acosh, unary("acosh"))]
+    #[case::atan2(jeff_optype::FloatOp::Atan2, Some(JeffOp::FloatAtan2))]
+    #[case::pow(jeff_optype::FloatOp::Pow, None)]
+    #[case::is_nan(jeff_optype::FloatOp::IsNan, None)]
+    fn functions(#[case] float_op: jeff_optype::FloatOp, #[case] expected: Option<JeffOp>) {
+        assert_eq!(JeffOp::float_function(&float_op), expected);
+    }
+
+    /// The translated functions chain on `float64` values, as in
+    /// `sin(sqrt(theta))`.
+    #[rstest]
+    fn chained_functions(float_functions: Jeff<'static>) {
+        let hugr = jeff_to_hugr(&float_functions).unwrap();
+        let function = |name| {
+            hugr.nodes()
+                .find(|&node| hugr.get_optype(node).cast::<JeffOp>() == unary(name))
+                .unwrap()
+        };
+        let (sqrt, sin) = (function("sqrt"), function("sin"));
+
+        let (input, _) = hugr.single_linked_output(sqrt, 0).unwrap();
+        assert!(hugr.get_optype(input).is_input());
+        assert_eq!(
+            hugr.single_linked_output(sin, 0),
+            Some((sqrt, OutgoingPort::from(0)))
+        );
+        assert_eq!(
+            hugr.signature(sin).unwrap().output_types(),
+            [float64_type()]
+        );
+    }
+
+    #[rstest]
+    fn sqrt_and_sin(float_functions: Jeff<'static>) {
+        let hugr = jeff_to_hugr(&float_functions).unwrap();
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        let functions: Vec<JeffOp> = hugr
+            .nodes()
+            .filter_map(|node| hugr.get_optype(node).cast())
+            .collect();
+        assert_eq!(functions.len(), 2);
+        assert!(functions.contains(&unary("sqrt").unwrap()));
+        assert!(functions.contains(&unary("sin").unwrap()));
+        assert!(
+            hugr.nodes()
+                .all(|node| hugr.get_optype(node).cast::<FloatOps>() != Some(FloatOps::fpow))
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use hugr::ops::handle::NodeHandle;
+use hugr::std_extensions::arithmetic::float_types::ConstF64;
+use jeff::reader::optype as jeff_optype;
+use jeff::types::FloatPrecision;
+
+use crate::extension::JeffOp;
+use crate::to_hugr::BuildContext;
+use crate::{JeffToHugrError, ResourceLimit};
+
+use super::JeffToHugrOp;
+use super::float::widen_f32;
+
+/// Translation for _jeff_ float array ops
+///
+/// The elements of arrays built from constants are recorded in the context,
+/// so gates taking their parameters from them can be unpacked at conversion time.
+///
+/// Float array reads are not checked against [`crate::ArrayBounds`], as the
+/// _jeff_ extension has no float array length operation.
+impl JeffToHugrOp for jeff_optype::FloatArrayOp<'_> {
+    fn build_hugr_op(
+        &self,
+        op: &jeff::reader::Operation<'_>,
+        builder: &mut impl hugr::builder::Dataflow,
+        ctx: &mut BuildContext,
+    ) -> Result<(), JeffToHugrError> {
+        match self {
+            jeff_optype::FloatArrayOp::Create => {
+                if op.output_count() != 1 {
+                    return Err(JeffToHugrError::invalid_op_io("FloatArrayCreate", op));
+                }
+                let output = op.output(0).unwrap()?;
+                let jeff::types::Type::FloatArray { precision } = output.ty() else {
+                    return Err(JeffToHugrError::unsupported_op(self));
+                };
+                let inputs = op.input_count();
+                ctx.config()
+                    .limits
+                    .check(ResourceLimit::ConstArrayLength, inputs)?;
+                let bits = precision_bits(precision);
+                ctx.build_single_op(
+                    JeffOp::FloatArrayCreate { bits, inputs }.try_into_extension_op()?,
+                    op,
+                    builder,
+                )?;
+
+                let values = op
+                    .inputs()
+                    .map(|value| Ok(ctx.float_constant(value?.id())))
+                    .collect::<Result<Option<Vec<_>>, JeffToHugrError>>()?;
+                if let Some(values) = values {
+                    ctx.record_float_array_constant(output.id(), values);
+                }
+            }
+            jeff_optype::FloatArrayOp::GetIndex => {
+                let array = op.input(0).transpose()?;
+                let Some(jeff::types::Type::FloatArray { precision }) = array.map(|v| v.ty())
+                else {
+                    return Err(JeffToHugrError::invalid_op_io("FloatArrayGet", op));
+                };
+                let bits = precision_bits(precision);
+                ctx.build_single_op(
+                    JeffOp::FloatArrayGet { bits }.try_into_extension_op()?,
+                    op,
+                    builder,
+                )?
+            }
+            jeff_optype::FloatArrayOp::ConstArray32(array) => {
+                let values = array.values().map(widen_f32).collect();
+                build_constant_float_array(values, 32, op, builder, ctx)?
+            }
+            jeff_optype::FloatArrayOp::ConstArray64(array) => {
+                let values = array.values().collect();
+                build_constant_float_array(values, 64, op, builder, ctx)?
+            }
+            _ => return Err(JeffToHugrError::unsupported_op(self)),
+        };
+        Ok(())
+    }
+}
+
+/// The precision of a _jeff_ float, in bits.
+pub(crate) fn precision_bits(precision: FloatPrecision) -> u8 {
+    match precision {
+        FloatPrecision::Float32 => 32,
+        FloatPrecision::Float64 => 64,
+    }
+}
+
+/// Emit a constant float array as a [`JeffOp::FloatArrayCreate`] of
+/// constant floats, and record its elements.
+fn build_constant_float_array(
+    values: Vec<f64>,
+    bits: u8,
+    op: &jeff::reader::Operation<'_>,
+    builder: &mut impl hugr::builder::Dataflow,
+    ctx: &mut BuildContext,
+) -> Result<(), JeffToHugrError> {
+    // Constant ops in _jeff_ have no inputs and a single output.
+    if op.input_count() != 0 || op.output_count() != 1 {
+        return Err(JeffToHugrError::unsupported_op(op));
+    }
+    ctx.config()
+        .limits
+        .check(ResourceLimit::ConstArrayLength, values.len())?;
+    let output = op.output(0).unwrap()?;
+
+    let elements = values
+        .iter()
+        .map(|&v| builder.add_load_value(ConstF64::new(v)))
+        .collect::<Vec<_>>();
+    let create = JeffOp::FloatArrayCreate {
+        bits,
+        inputs: values.len(),
+    };
+    let node = builder
+        .add_dataflow_op(create.try_into_extension_op()?, elements)?
+        .node();
+    ctx.record_origin(node);
+    ctx.register_output(output.id(), node, 0.into());
+    ctx.record_float_array_constant(output.id(), values);
+    Ok(())
+}
@@ -1,8 +1,11 @@
-use hugr::{HugrView, IncomingPort};
+use hugr::IncomingPort;
+use hugr::ops::OpType;
+use hugr::std_extensions::arithmetic::conversions::ConvertOpDef;
 use jeff::reader::{FunctionId, optype as jeff_optype};
 
-use crate::JeffToHugrError;
-use crate::to_hugr::BuildContext;
+use crate::{ConversionWarning, JeffToHugrError};
+use crate::extension::JeffOp;
+use crate::to_hugr::{BuildContext, check_port_count};
 use crate::types::jeff_signature_to_hugr;
 
 use super::JeffToHugrOp;
@@ -15,28 +18,244 @@ impl JeffToHugrOp for jeff_optype::FuncOp {
         builder: &mut impl hugr::builder::Dataflow,
         ctx: &mut BuildContext,
     ) -> Result<(), JeffToHugrError> {
-        let fn_inputs = op.input_types().collect::<Result<Vec<_>, _>>()?;
-        let fn_outputs = op.output_types().collect::<Result<Vec<_>, _>>()?;
+        // Calls to intrinsics are not deduplicated, since each runtime query
+        // is an effectful read.
+        if let Some(view) = ctx.view_intrinsic(self.func_idx as FunctionId) {
+            return ctx.build_view_intrinsic(view, op, builder);
+        }
+        if let Some(intrinsic) = ctx.intrinsic(self.func_idx as FunctionId).cloned() {
+            match intrinsic {
+                JeffOp::StatePrep { qubits } => check_state_prep_amplitudes(qubits, op, ctx)?,
+                JeffOp::BitcastIntToFloat { .. } | JeffOp::BitcastFloatToInt { .. } => {
+                    let bitcast = bitcast_op(intrinsic, ctx);
+                    return ctx.build_single_op(bitcast, op, builder);
+                }
+                _ => {}
+            }
+            return ctx.build_single_op(intrinsic, op, builder);
+        }
+        if let Some(body) = ctx.inlined_body(self.func_idx as FunctionId) {
+            return ctx.build_inlined_call(&body, op, builder);
+        }
+
+        check_port_count(op.input_count().max(op.output_count()))?;
+        let mut fn_inputs = Vec::with_capacity(op.input_count());
+        for ty in op.input_types() {
+            fn_inputs.push(ty?);
+        }
+        let mut fn_outputs = Vec::with_capacity(op.output_count());
+        for ty in op.output_types() {
+            fn_outputs.push(ty?);
+        }
         let call_signature = jeff_signature_to_hugr(fn_inputs, fn_outputs);
 
         let call = hugr::ops::Call::try_new(call_signature.into(), vec![]).unwrap();
         let node = builder.add_child_node(call);
+        ctx.record_origin(node);
 
-        // Note: the `zip` will stop when the _jeff_ operation inputs are
-        // exhausted, so it won't register the static function parameters of the
-        // call.
-        for (port, value) in builder.hugr().node_inputs(node).zip(op.inputs()) {
-            let value = value?;
-            ctx.register_input(value.id(), node, port);
-        }
-        for (port, value) in builder.hugr().node_outputs(node).zip(op.outputs()) {
-            let value = value?;
-            ctx.register_output(value.id(), node, port);
-        }
-
+        // The static function input comes after the value inputs.
+        ctx.register_ports(node, op)?;
         let static_inp = IncomingPort::from(op.input_count());
         ctx.register_function_call(self.func_idx as FunctionId, node, static_inp);
 
         Ok(())
     }
 }
+
+/// Check the length of the amplitude array of a [`JeffOp::StatePrep`], when
+/// it is built from constants.
+///
+/// # Errors
+///
+/// - [`JeffToHugrError::InvalidStatePrep`] if the array holds neither `2^n`
+///   real amplitudes nor `2^n` interleaved complex amplitudes.
+fn check_state_prep_amplitudes(
+    qubits: usize,
+    op: &jeff::reader::Operation<'_>,
+    ctx: &BuildContext,
+) -> Result<(), JeffToHugrError> {
+    let Some(amplitudes) = op.input(qubits) else {
+        return Err(JeffToHugrError::invalid_op_io("state_prep", op));
+    };
+    let amplitudes = amplitudes?;
+    let Some(floats) = ctx.float_array_constant(amplitudes.id()).map(<[f64]>::len) else {
+        return Ok(());
+    };
+    match valid_amplitude_count(qubits, floats) {
+        true => Ok(()),
+        false => Err(JeffToHugrError::InvalidStatePrep { qubits, floats }),
+    }
+}
+
+/// The HUGR operation translating a bitcast intrinsic.
+///
+/// 64-bit bitcasts are translated to the operations of the HUGR conversions
+/// extension. There is no 32-bit float in HUGR, so 32-bit bitcasts keep the
+/// _jeff_ extension operation, whose float is a widened `float64`, and record
+/// a [`ConversionWarning::WidenedBitcast`].
+fn bitcast_op(intrinsic: JeffOp, ctx: &mut BuildContext) -> OpType {
+    match intrinsic {
+        JeffOp::BitcastIntToFloat { bits: 64 } => {
+            ConvertOpDef::bytecast_int64_to_float64.without_log_width().into()
+        }
+        JeffOp::BitcastFloatToInt { bits: 64 } => {
+            ConvertOpDef::bytecast_float64_to_int64.without_log_width().into()
+        }
+        _ => {
+            let warning = ConversionWarning::WidenedBitcast {
+                function: ctx.current_function_name().map(str::to_string),
+                op_index: ctx.current_op(),
+            };
+            ctx.warn(warning);
+            intrinsic.into()
+        }
+    }
+}
+
+/// Returns `true` if `floats` is the length of a real or interleaved complex
+/// amplitude array over `qubits` qubits.
+fn valid_amplitude_count(qubits: usize, floats: usize) -> bool {
+    let Some(real) = u32::try_from(qubits).ok().and_then(|n| 1usize.checked_shl(n)) else {
+        return false;
+    };
+    floats == real || Some(floats) == real.checked_mul(2)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr};
+    use hugr::std_extensions::arithmetic::conversions::ConvertOpType;
+    use jeff::types::{FloatPrecision, Type as JeffType};
+    use rstest::rstest;
+
+    const AMPLITUDES: JeffType = JeffType::FloatArray {
+        precision: FloatPrecision::Float64,
+    };
+
+    #[rstest]
+    #[case::qubits(
+        "state_prep",
+        vec![JeffType::Qubit, JeffType::Qubit, AMPLITUDES],
+        vec![JeffType::Qubit, JeffType::Qubit],
+        Some(JeffOp::StatePrep { qubits: 2 })
+    )]
+    #[case::register(
+        "state_prep",
+        vec![JeffType::QubitRegister, AMPLITUDES],
+        vec![JeffType::QubitRegister],
+        Some(JeffOp::QuregStatePrep)
+    )]
+    #[case::no_qubits("state_prep", vec![AMPLITUDES], vec![], None)]
+    #[case::single_precision(
+        "state_prep",
+        vec![JeffType::Qubit, JeffType::FloatArray { precision: FloatPrecision::Float32 }],
+        vec![JeffType::Qubit],
+        None
+    )]
+    #[case::dropped_qubit(
+        "state_prep",
+        vec![JeffType::Qubit, JeffType::Qubit, AMPLITUDES],
+        vec![JeffType::Qubit],
+        None
+    )]
+    #[case::other_name(
+        "prepare",
+        vec![JeffType::Qubit, AMPLITUDES],
+        vec![JeffType::Qubit],
+        None
+    )]
+    fn state_prep_intrinsic(
+        #[case] name: &str,
+        #[case] inputs: Vec<JeffType>,
+        #[case] outputs: Vec<JeffType>,
+        #[case] expected: Option<JeffOp>,
+    ) {
+        assert_eq!(JeffOp::state_prep(name, &inputs, &outputs), expected);
+    }
+
+    const INT32: JeffType = JeffType::Int { bits: 32 };
+    const INT64: JeffType = JeffType::Int { bits: 64 };
+    const F32: JeffType = JeffType::Float {
+        precision: FloatPrecision::Float32,
+    };
+    const F64: JeffType = JeffType::Float {
+        precision: FloatPrecision::Float64,
+    };
+
+    #[rstest]
+    #[case::int64_to_float("bitcast", INT64, F64, Some(JeffOp::BitcastIntToFloat { bits: 64 }))]
+    #[case::float64_to_int("bitcast", F64, INT64, Some(JeffOp::BitcastFloatToInt { bits: 64 }))]
+    #[case::int32_to_float("bitcast", INT32, F32, Some(JeffOp::BitcastIntToFloat { bits: 32 }))]
+    #[case::float32_to_int("bitcast", F32, INT32, Some(JeffOp::BitcastFloatToInt { bits: 32 }))]
+    #[case::mixed_widths("bitcast", INT32, F64, None)]
+    #[case::int_to_int("bitcast", INT64, INT64, None)]
+    #[case::other_name("reinterpret", INT64, F64, None)]
+    fn bitcast_intrinsic(
+        #[case] name: &str,
+        #[case] input: JeffType,
+        #[case] output: JeffType,
+        #[case] expected: Option<JeffOp>,
+    ) {
+        assert_eq!(JeffOp::bitcast(name, &[input], &[output]), expected);
+    }
+
+    #[rstest]
+    #[case::borrow("qubit_borrow", vec![], vec![JeffType::Qubit], Some(JeffOp::QubitBorrow))]
+    #[case::give_back("qubit_return", vec![JeffType::Qubit], vec![], Some(JeffOp::QubitReturn))]
+    #[case::borrow_register("qubit_borrow", vec![], vec![JeffType::QubitRegister], None)]
+    #[case::return_value("qubit_return", vec![JeffType::Qubit], vec![JeffType::Qubit], None)]
+    #[case::other_name("qubit_alloc", vec![], vec![JeffType::Qubit], None)]
+    fn qubit_borrow_intrinsic(
+        #[case] name: &str,
+        #[case] inputs: Vec<JeffType>,
+        #[case] outputs: Vec<JeffType>,
+        #[case] expected: Option<JeffOp>,
+    ) {
+        assert_eq!(JeffOp::qubit_borrow(name, &inputs, &outputs), expected);
+    }
+
+    #[rstest]
+    #[case::int_to_float(JeffOp::BitcastIntToFloat { bits: 64 }, INT64, F64)]
+    #[case::float_to_int(JeffOp::BitcastFloatToInt { bits: 64 }, F64, INT64)]
+    fn bitcast_64(#[case] intrinsic: JeffOp, #[case] input: JeffType, #[case] output: JeffType) {
+        let mut ctx = BuildContext::default();
+        let bitcast = bitcast_op(intrinsic, &mut ctx);
+        assert!(bitcast.cast::<ConvertOpType>().is_some());
+
+        let signature = jeff_signature_to_hugr([input], [output]);
+        let mut builder = DFGBuilder::new(signature).unwrap();
+        let inputs = builder.input_wires();
+        let outputs = builder.add_dataflow_op(bitcast, inputs).unwrap().outputs();
+        builder.finish_hugr_with_outputs(outputs).unwrap();
+        assert_eq!(ctx.take_warnings(), []);
+    }
+
+    /// 32-bit floats are widened to 64 bits by default.
+    #[rstest]
+    fn bitcast_32_warning() {
+        let mut ctx = BuildContext::default();
+        let intrinsic = JeffOp::BitcastIntToFloat { bits: 32 };
+        let bitcast = bitcast_op(intrinsic.clone(), &mut ctx);
+        assert_eq!(bitcast.cast::<JeffOp>(), Some(intrinsic));
+        assert_eq!(
+            ctx.take_warnings(),
+            [ConversionWarning::WidenedBitcast {
+                function: None,
+                op_index: None,
+            }]
+        );
+    }
+
+    #[rstest]
+    #[case::real(2, 4, true)]
+    #[case::complex(2, 8, true)]
+    #[case::single_qubit(1, 2, true)]
+    #[case::short(2, 3, false)]
+    #[case::empty(1, 0, false)]
+    #[case::long(2, 16, false)]
+    #[case::huge(usize::BITS as usize, 0, false)]
+    fn amplitude_count(#[case] qubits: usize, #[case] floats: usize, #[case] valid: bool) {
+        assert_eq!(valid_amplitude_count(qubits, floats), valid);
+    }
+}
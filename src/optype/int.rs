@@ -1,11 +1,24 @@
+use hugr::builder::{BuildError, DFGBuilder, Dataflow, DataflowHugr};
+use hugr::hugr::hugrmut::HugrMut;
 use hugr::ops::Value;
-use hugr::std_extensions::arithmetic::int_types::ConstInt;
+use hugr::std_extensions::arithmetic::int_ops::IntOpDef;
+use hugr::std_extensions::arithmetic::int_types::{ConstInt, int_type};
+use hugr::std_extensions::logic::LogicOp;
+use hugr::types::Signature;
+use hugr::{Hugr, HugrView, IncomingPort, Node, OutgoingPort, Wire};
 use jeff::reader::optype as jeff_optype;
+use jeff::types::Type as JeffType;
 
-use crate::JeffToHugrError;
 use crate::to_hugr::BuildContext;
+use crate::types::jeff_int_width_to_hugr_width;
+use crate::{ConversionWarning, IntOverflow, JeffToHugrError};
 
 use super::JeffToHugrOp;
+use super::bounds::{build_panic_guard, mark_bounds_check};
+
+/// Largest log width whose multiplications can be checked, by computing
+/// them at twice the width.
+const MAX_CHECKED_MUL_LOG_WIDTH: u8 = 5;
 
 /// Translation for _jeff_ quantum ops
 impl JeffToHugrOp for jeff_optype::IntOp {
@@ -31,11 +44,489 @@ impl JeffToHugrOp for jeff_optype::IntOp {
             jeff_optype::IntOp::Const64(n) => {
                 ctx.build_constant_value(ConstInt::new_u(6, *n).unwrap(), op, builder)?
             }
-
-            // TODO: Int operations require querying the jeff value type to determine the correct
-            // integer width.
-            _ => return Err(JeffToHugrError::unsupported_op(self)),
+            jeff_optype::IntOp::Add => build_arithmetic(IntOpDef::iadd, op, builder, ctx)?,
+            jeff_optype::IntOp::Sub => build_arithmetic(IntOpDef::isub, op, builder, ctx)?,
+            jeff_optype::IntOp::Mul => build_arithmetic(IntOpDef::imul, op, builder, ctx)?,
+            _ => {
+                let bits = match op.input_types().next().transpose()? {
+                    Some(JeffType::Int { bits }) => bits,
+                    _ => return Err(JeffToHugrError::invalid_op_io(format!("{self:?}"), op)),
+                };
+                match int_translation(self, bits) {
+                    Some(IntTranslation::Logic(logic_op)) => {
+                        ctx.build_single_op(logic_op, op, builder)?
+                    }
+                    Some(IntTranslation::Int(int_op)) => ctx.build_single_op(
+                        int_op.with_log_width(jeff_int_width_to_hugr_width(bits)),
+                        op,
+                        builder,
+                    )?,
+                    Some(IntTranslation::Signed(signed_op)) => {
+                        let log_width = jeff_int_width_to_hugr_width(bits);
+                        build_signed_op(signed_op, log_width, op, builder, ctx)?
+                    }
+                    None => return Err(JeffToHugrError::unsupported_op(self)),
+                }
+            }
         };
         Ok(())
     }
 }
+
+/// The HUGR translation of a _jeff_ integer operation other than the
+/// constants and the arithmetic checked for overflow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum IntTranslation {
+    /// A logic operation on `int(1)` values, which are HUGR booleans.
+    Logic(LogicOp),
+    /// An integer operation at the width of the operands.
+    Int(IntOpDef),
+    /// A signed operation without HUGR equivalent, built by
+    /// [`signed_op_dfg`].
+    Signed(SignedIntOp),
+}
+
+/// The translation of an integer operation on `bits`-wide operands, if it is
+/// supported.
+///
+/// Only the bitwise operations and the equality are supported on `int(1)`
+/// values, as logic operations.
+pub(crate) fn int_translation(int_op: &jeff_optype::IntOp, bits: u8) -> Option<IntTranslation> {
+    use jeff_optype::IntOp;
+
+    if bits == 1 {
+        let logic_op = match int_op {
+            IntOp::And => LogicOp::And,
+            IntOp::Or => LogicOp::Or,
+            IntOp::Xor => LogicOp::Xor,
+            IntOp::Not => LogicOp::Not,
+            IntOp::Eq => LogicOp::Eq,
+            _ => return None,
+        };
+        return Some(IntTranslation::Logic(logic_op));
+    }
+    let int_op = match int_op {
+        IntOp::DivU => IntOpDef::idiv_u,
+        IntOp::RemU => IntOpDef::imod_u,
+        IntOp::And => IntOpDef::iand,
+        IntOp::Or => IntOpDef::ior,
+        IntOp::Xor => IntOpDef::ixor,
+        IntOp::Not => IntOpDef::inot,
+        IntOp::Shl => IntOpDef::ishl,
+        IntOp::ShrU => IntOpDef::ishr,
+        IntOp::Eq => IntOpDef::ieq,
+        IntOp::LtS => IntOpDef::ilt_s,
+        IntOp::LtU => IntOpDef::ilt_u,
+        IntOp::LteS => IntOpDef::ile_s,
+        IntOp::LteU => IntOpDef::ile_u,
+        IntOp::DivS => return Some(IntTranslation::Signed(SignedIntOp::Div)),
+        IntOp::RemS => return Some(IntTranslation::Signed(SignedIntOp::Rem)),
+        IntOp::ShrS => return Some(IntTranslation::Signed(SignedIntOp::Shr)),
+        _ => return None,
+    };
+    Some(IntTranslation::Int(int_op))
+}
+
+/// A signed integer operation computed from the unsigned HUGR operations.
+///
+/// HUGR divides signed integers by unsigned ones, and only has a logical
+/// right shift, while _jeff_ follows the C semantics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SignedIntOp {
+    /// Division rounding towards zero.
+    Div,
+    /// Remainder of the division rounding towards zero, with the sign of the
+    /// dividend.
+    Rem,
+    /// Arithmetic right shift, copying the sign bit.
+    Shr,
+}
+
+impl SignedIntOp {
+    /// The number of nodes of the [`signed_op_dfg`] of the operation,
+    /// including the `DFG`, its input and output nodes.
+    pub(crate) fn node_count(self) -> usize {
+        match self {
+            Self::Div => 19,
+            Self::Rem => 18,
+            Self::Shr => 12,
+        }
+    }
+}
+
+/// Translate a signed integer operation as a `DFG`, see [`signed_op_dfg`].
+fn build_signed_op(
+    signed_op: SignedIntOp,
+    log_width: u8,
+    op: &jeff::reader::Operation<'_>,
+    builder: &mut impl Dataflow,
+    ctx: &mut BuildContext,
+) -> Result<(), JeffToHugrError> {
+    let inputs = op
+        .inputs()
+        .map(|value| Ok(value?.id()))
+        .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+    let outputs = op
+        .outputs()
+        .map(|value| Ok(value?.id()))
+        .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+    let (&[a, b], &[out]) = (inputs.as_slice(), outputs.as_slice()) else {
+        return Err(JeffToHugrError::invalid_op_io(format!("{signed_op:?}"), op));
+    };
+
+    let dfg = builder
+        .add_hugr(signed_op_dfg(signed_op, log_width)?)
+        .inserted_entrypoint;
+    ctx.record_origin(dfg);
+    ctx.register_input(a, dfg, IncomingPort::from(0));
+    ctx.register_input(b, dfg, IncomingPort::from(1));
+    ctx.register_output(out, dfg, OutgoingPort::from(0));
+    Ok(())
+}
+
+/// A dataflow graph computing a signed operation on two integers of width
+/// `2^log_width`.
+///
+/// The operation is computed on the absolute values of the operands, and
+/// the sign of the result is restored with a mask of the sign bits: `-1`
+/// for negative values and `0` otherwise.
+pub(crate) fn signed_op_dfg(signed_op: SignedIntOp, log_width: u8) -> Result<Hugr, BuildError> {
+    let int_t = int_type(log_width);
+    let signature = Signature::new(vec![int_t.clone(), int_t.clone()], vec![int_t]);
+    let mut builder = DFGBuilder::new(signature)?;
+    let [a, b] = builder.input_wires_arr();
+    let sign_shift = (1u64 << log_width) - 1;
+    let sign_shift = builder.add_load_value(ConstInt::new_u(log_width, sign_shift).unwrap());
+    let zero = builder.add_load_value(ConstInt::new_u(log_width, 0).unwrap());
+    let binary = |builder: &mut DFGBuilder<Hugr>, int_op: IntOpDef, lhs: Wire, rhs: Wire| {
+        let op = builder.add_dataflow_op(int_op.with_log_width(log_width), [lhs, rhs])?;
+        Ok::<_, BuildError>(op.out_wire(0))
+    };
+    // The sign mask of a value.
+    let sign_mask = |builder: &mut DFGBuilder<Hugr>, value: Wire| {
+        let sign = binary(builder, IntOpDef::ishr, value, sign_shift)?;
+        binary(builder, IntOpDef::isub, zero, sign)
+    };
+    // Negate a value if the mask is set, as `(value ^ mask) - mask`.
+    let negate_if = |builder: &mut DFGBuilder<Hugr>, value: Wire, mask: Wire| {
+        let flipped = binary(builder, IntOpDef::ixor, value, mask)?;
+        binary(builder, IntOpDef::isub, flipped, mask)
+    };
+
+    let a_mask = sign_mask(&mut builder, a)?;
+    let result = match signed_op {
+        SignedIntOp::Div | SignedIntOp::Rem => {
+            let b_mask = sign_mask(&mut builder, b)?;
+            let a_abs = negate_if(&mut builder, a, a_mask)?;
+            let b_abs = negate_if(&mut builder, b, b_mask)?;
+            if signed_op == SignedIntOp::Div {
+                let quotient = binary(&mut builder, IntOpDef::idiv_u, a_abs, b_abs)?;
+                let mask = binary(&mut builder, IntOpDef::ixor, a_mask, b_mask)?;
+                negate_if(&mut builder, quotient, mask)?
+            } else {
+                let remainder = binary(&mut builder, IntOpDef::imod_u, a_abs, b_abs)?;
+                negate_if(&mut builder, remainder, a_mask)?
+            }
+        }
+        SignedIntOp::Shr => {
+            // Shift the complement of negative values, so that the vacated
+            // bits are set once complemented back.
+            let flipped = binary(&mut builder, IntOpDef::ixor, a, a_mask)?;
+            let shifted = binary(&mut builder, IntOpDef::ishr, flipped, b)?;
+            binary(&mut builder, IntOpDef::ixor, shifted, a_mask)?
+        }
+    };
+    builder.finish_hugr_with_outputs([result])
+}
+
+/// Translate a binary integer addition, subtraction or multiplication,
+/// checking it for overflow according to
+/// [`crate::JeffToHugrConfig::int_overflow`].
+fn build_arithmetic(
+    arith: IntOpDef,
+    op: &jeff::reader::Operation<'_>,
+    builder: &mut impl Dataflow,
+    ctx: &mut BuildContext,
+) -> Result<(), JeffToHugrError> {
+    let name = format!("{:?}", op.op_type());
+    let bits = match op.input_types().next().transpose()? {
+        Some(JeffType::Int { bits }) if bits > 1 => bits,
+        _ => return Err(JeffToHugrError::invalid_op_io(name, op)),
+    };
+    let log_width = jeff_int_width_to_hugr_width(bits);
+    let checkable = arith != IntOpDef::imul || log_width <= MAX_CHECKED_MUL_LOG_WIDTH;
+    match ctx.config().int_overflow {
+        IntOverflow::Trap if checkable => {}
+        IntOverflow::Trap => {
+            let warning = ConversionWarning::UncheckedOverflow {
+                function: ctx.current_function_name().map(str::to_string),
+                op_index: ctx.current_op(),
+            };
+            ctx.warn(warning);
+            return ctx.build_single_op(arith.with_log_width(log_width), op, builder);
+        }
+        IntOverflow::Wrapping => {
+            return ctx.build_single_op(arith.with_log_width(log_width), op, builder);
+        }
+    }
+
+    let inputs = op
+        .inputs()
+        .map(|value| Ok(value?.id()))
+        .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+    let outputs = op
+        .outputs()
+        .map(|value| Ok(value?.id()))
+        .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+    let (&[a, b], &[out]) = (inputs.as_slice(), outputs.as_slice()) else {
+        return Err(JeffToHugrError::invalid_op_io(name, op));
+    };
+
+    let message = format!(
+        "Integer overflow in function '{}' at operation {}",
+        ctx.current_function_name().unwrap_or_default(),
+        ctx.current_op().unwrap_or_default()
+    );
+    let checked = build_checked_op(builder, arith, log_width, message)?;
+    ctx.record_origin(checked.result);
+    for (id, uses) in [a, b].into_iter().zip(checked.operand_uses) {
+        for (node, port) in uses {
+            ctx.register_input(id, node, port);
+        }
+    }
+    ctx.register_output(out, checked.guard, OutgoingPort::from(0));
+    Ok(())
+}
+
+/// An arithmetic operation checked for overflow, built by
+/// [`build_checked_op`].
+#[derive(Debug)]
+struct CheckedOp {
+    /// The wrapping operation computing the result.
+    result: Node,
+    /// The guard forwarding the result, or panicking on overflow.
+    guard: Node,
+    /// The unconnected input ports reading each operand.
+    operand_uses: [Vec<(Node, IncomingPort)>; 2],
+}
+
+/// Build a wrapping `arith` operation on signed integers of width
+/// `2^log_width`, followed by a guard panicking with `message` when the
+/// result overflows.
+///
+/// HUGR has no checked variant of the additions, subtractions and
+/// multiplications, so the overflow is detected from the operand and result
+/// signs, or by computing multiplications at twice the width. All the nodes
+/// but the result are tagged with [`crate::metadata::BOUNDS_CHECK`].
+fn build_checked_op(
+    builder: &mut impl Dataflow,
+    arith: IntOpDef,
+    log_width: u8,
+    message: String,
+) -> Result<CheckedOp, JeffToHugrError> {
+    let result = builder.add_child_node(arith.with_log_width(log_width));
+    let wrapped = Wire::new(result, 0);
+    let mut operand_uses = [0, 1].map(|port: usize| vec![(result, IncomingPort::from(port))]);
+    let mut checks = Vec::new();
+
+    let no_overflow = match arith {
+        IntOpDef::iadd | IntOpDef::isub => {
+            // An addition overflows when the result sign differs from the
+            // sign of both operands. A subtraction overflows when the
+            // operand signs differ, and the result sign differs from the
+            // first operand.
+            let xor = IntOpDef::ixor.with_log_width(log_width);
+            let first = builder.add_child_node(xor.clone());
+            let second = builder.add_child_node(xor);
+            operand_uses[0].push((first, IncomingPort::from(0)));
+            builder
+                .hugr_mut()
+                .connect(wrapped.node(), wrapped.source(), first, 1);
+            if arith == IntOpDef::iadd {
+                operand_uses[1].push((second, IncomingPort::from(0)));
+                builder
+                    .hugr_mut()
+                    .connect(wrapped.node(), wrapped.source(), second, 1);
+            } else {
+                operand_uses[0].push((second, IncomingPort::from(0)));
+                operand_uses[1].push((second, IncomingPort::from(1)));
+            }
+            let both = builder.add_dataflow_op(
+                IntOpDef::iand.with_log_width(log_width),
+                [Wire::new(first, 0), Wire::new(second, 0)],
+            )?;
+            let zero = builder.add_load_value(ConstInt::new_s(log_width, 0).unwrap());
+            let no_overflow = builder.add_dataflow_op(
+                IntOpDef::ige_s.with_log_width(log_width),
+                [both.out_wire(0), zero],
+            )?;
+            checks.extend([first, second, both.node(), zero.node(), no_overflow.node()]);
+            checks.extend(builder.hugr().static_source(zero.node()));
+            no_overflow.out_wire(0)
+        }
+        IntOpDef::imul => {
+            // Compare with the product of the operands at twice the width.
+            let wide_log_width = log_width + 1;
+            let widen = IntOpDef::iwiden_s.with_two_log_widths(log_width, wide_log_width);
+            let wide_operands = [0, 1].map(|i| {
+                let node = builder.add_child_node(widen.clone());
+                operand_uses[i].push((node, IncomingPort::from(0)));
+                node
+            });
+            let product = builder.add_dataflow_op(
+                IntOpDef::imul.with_log_width(wide_log_width),
+                wide_operands.map(|node| Wire::new(node, 0)),
+            )?;
+            let wide_result = builder.add_dataflow_op(widen, [wrapped])?;
+            let no_overflow = builder.add_dataflow_op(
+                IntOpDef::ieq.with_log_width(wide_log_width),
+                [wide_result.out_wire(0), product.out_wire(0)],
+            )?;
+            checks.extend(wide_operands);
+            checks.extend([product.node(), wide_result.node(), no_overflow.node()]);
+            no_overflow.out_wire(0)
+        }
+        _ => return Err(JeffToHugrError::unsupported_op(&arith)),
+    };
+
+    let guard = build_panic_guard(builder, vec![int_type(log_width)].into(), message)?;
+    checks.push(guard);
+    builder
+        .hugr_mut()
+        .connect(no_overflow.node(), no_overflow.source(), guard, 0);
+    builder
+        .hugr_mut()
+        .connect(wrapped.node(), wrapped.source(), guard, 1);
+    for node in checks {
+        mark_bounds_check(builder, node);
+    }
+
+    Ok(CheckedOp {
+        result,
+        guard,
+        operand_uses,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::jeff_to_hugr;
+    use crate::metadata;
+    use crate::passes::{StripOptions, strip};
+    use crate::test::{assert_hugr_equivalent, entangled_calls};
+    use jeff::Jeff;
+    use jeff::reader::optype::IntOp;
+    use rstest::rstest;
+
+    /// Log width of the `int(8)` operands.
+    const LOG_WIDTH: u8 = 3;
+
+    fn int8_dfg() -> DFGBuilder<Hugr> {
+        let int_t = int_type(LOG_WIDTH);
+        let sig = Signature::new(vec![int_t.clone(), int_t.clone()], vec![int_t]);
+        DFGBuilder::new(sig).unwrap()
+    }
+
+    /// The `int(8)` operation emitted with [`IntOverflow::Wrapping`].
+    fn wrapping_dfg(arith: IntOpDef) -> Hugr {
+        let mut builder = int8_dfg();
+        let inputs = builder.input_wires();
+        let op = builder
+            .add_dataflow_op(arith.with_log_width(LOG_WIDTH), inputs)
+            .unwrap();
+        builder.finish_hugr_with_outputs(op.outputs()).unwrap()
+    }
+
+    /// The `int(8)` operation emitted with [`IntOverflow::Trap`].
+    fn trapping_dfg(arith: IntOpDef) -> (Hugr, CheckedOp) {
+        let mut builder = int8_dfg();
+        let inputs = builder.input_wires_arr::<2>();
+        let message = "Integer overflow".to_string();
+        let checked = build_checked_op(&mut builder, arith, LOG_WIDTH, message).unwrap();
+        for (wire, uses) in inputs.into_iter().zip(&checked.operand_uses) {
+            for &(node, port) in uses {
+                builder
+                    .hugr_mut()
+                    .connect(wire.node(), wire.source(), node, port);
+            }
+        }
+        let outputs = [Wire::new(checked.guard, 0)];
+        (builder.finish_hugr_with_outputs(outputs).unwrap(), checked)
+    }
+
+    fn is_check(hugr: &Hugr, node: Node) -> bool {
+        hugr.get_metadata(node, metadata::BOUNDS_CHECK).is_some()
+    }
+
+    #[rstest]
+    #[case::add(IntOpDef::iadd)]
+    #[case::sub(IntOpDef::isub)]
+    #[case::mul(IntOpDef::imul)]
+    fn trap_overflow(#[case] arith: IntOpDef) {
+        let (mut hugr, checked) = trapping_dfg(arith);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        // The wrapping result goes through a guard controlled by the check.
+        assert!(hugr.get_optype(checked.guard).is_conditional());
+        assert_eq!(
+            hugr.single_linked_output(checked.guard, 1),
+            Some((checked.result, OutgoingPort::from(0)))
+        );
+        let (control, _) = hugr.single_linked_output(checked.guard, 0).unwrap();
+        assert!(is_check(&hugr, control));
+        assert!(is_check(&hugr, checked.guard));
+        assert!(!is_check(&hugr, checked.result));
+
+        // Stripping the checks leaves the wrapping operation.
+        let removed = strip(&mut hugr, &StripOptions::default());
+        assert!(removed > 0);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        assert!(hugr.nodes().all(|node| !is_check(&hugr, node)));
+        assert_hugr_equivalent(&hugr, &wrapping_dfg(arith));
+    }
+
+    #[rstest]
+    #[case::div_u(IntOp::DivU, 32, Some(IntTranslation::Int(IntOpDef::idiv_u)))]
+    #[case::shl(IntOp::Shl, 8, Some(IntTranslation::Int(IntOpDef::ishl)))]
+    #[case::lt_s(IntOp::LtS, 64, Some(IntTranslation::Int(IntOpDef::ilt_s)))]
+    #[case::shr_s(IntOp::ShrS, 16, Some(IntTranslation::Signed(SignedIntOp::Shr)))]
+    #[case::bool_xor(IntOp::Xor, 1, Some(IntTranslation::Logic(LogicOp::Xor)))]
+    #[case::bool_eq(IntOp::Eq, 1, Some(IntTranslation::Logic(LogicOp::Eq)))]
+    #[case::bool_lt(IntOp::LtU, 1, None)]
+    #[case::constant(IntOp::Const32(1), 32, None)]
+    fn translations(
+        #[case] int_op: IntOp,
+        #[case] bits: u8,
+        #[case] expected: Option<IntTranslation>,
+    ) {
+        assert_eq!(int_translation(&int_op, bits), expected);
+    }
+
+    #[rstest]
+    #[case::div(SignedIntOp::Div)]
+    #[case::rem(SignedIntOp::Rem)]
+    #[case::shr(SignedIntOp::Shr)]
+    fn signed_ops(#[case] signed_op: SignedIntOp) {
+        for log_width in [LOG_WIDTH, 6] {
+            let hugr = signed_op_dfg(signed_op, log_width).unwrap();
+            hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+            assert_eq!(hugr.num_nodes(), signed_op.node_count());
+        }
+    }
+
+    /// The measurement results are packed with shifts and additions.
+    #[rstest]
+    fn packed_measurements(entangled_calls: Jeff<'static>) {
+        let hugr = jeff_to_hugr(&entangled_calls).unwrap();
+        let shifts = hugr
+            .nodes()
+            .filter(|&node| {
+                hugr.get_optype(node)
+                    .as_extension_op()
+                    .is_some_and(|op| op.def().name() == "ishl")
+            })
+            .count();
+        assert_eq!(shifts, 5);
+    }
+}
@@ -1,12 +1,15 @@
 use jeff::reader::optype as jeff_optype;
 
-use crate::JeffToHugrError;
+use crate::{JeffToHugrError, ResourceLimit};
 use crate::extension::{ConstIntReg, JeffOp};
 use crate::to_hugr::BuildContext;
 
 use super::JeffToHugrOp;
+use super::bounds::build_indexed_access;
 
-/// Translation for _jeff_ quantum ops
+/// Translation for _jeff_ integer array ops
+///
+/// Arrays keep their _jeff_ element order, see [`crate::BitOrder`] for bit arrays.
 impl JeffToHugrOp for jeff_optype::IntArrayOp<'_> {
     fn build_hugr_op(
         &self,
@@ -16,7 +19,10 @@ impl JeffToHugrOp for jeff_optype::IntArrayOp<'_> {
     ) -> Result<(), JeffToHugrError> {
         // Get the number of bits in an integer input.
         let input_bits = |idx| {
-            let value = op.input(idx).unwrap()?;
+            let Some(value) = op.input(idx) else {
+                return Err(JeffToHugrError::invalid_op_io(format!("{self:?}"), op));
+            };
+            let value = value?;
             match value.ty() {
                 jeff::types::Type::Int { bits } => Ok(bits),
                 jeff::types::Type::IntArray { bits } => Ok(bits),
@@ -28,40 +34,59 @@ impl JeffToHugrOp for jeff_optype::IntArrayOp<'_> {
             jeff_optype::IntArrayOp::Create => {
                 let bits = input_bits(0)?;
                 let inputs = op.input_count();
-                ctx.build_single_op(JeffOp::IntArrayCreate { bits, inputs }, op, builder)?
+                ctx.config()
+                    .limits
+                    .check(ResourceLimit::ConstArrayLength, inputs)?;
+                ctx.build_single_op(
+                    JeffOp::IntArrayCreate { bits, inputs }.try_into_extension_op()?,
+                    op,
+                    builder,
+                )?
             }
             jeff_optype::IntArrayOp::GetIndex => {
                 let bits = input_bits(0)?;
-                ctx.build_single_op(JeffOp::IntArrayGet { bits }, op, builder)?
+                let access = JeffOp::IntArrayGet { bits };
+                let length = JeffOp::IntArrayLength { bits };
+                build_indexed_access(ctx, access, length, 1, op, builder)?
             }
             jeff_optype::IntArrayOp::SetIndex => {
                 let bits = input_bits(0)?;
-                ctx.build_single_op(JeffOp::IntArraySet { bits }, op, builder)?
+                let access = JeffOp::IntArraySet { bits };
+                let length = JeffOp::IntArrayLength { bits };
+                build_indexed_access(ctx, access, length, 1, op, builder)?
             }
             jeff_optype::IntArrayOp::Zero { bits } => {
-                ctx.build_single_op(JeffOp::IntArrayZero { bits: *bits }, op, builder)?
+                ctx.build_single_op(
+                    JeffOp::IntArrayZero { bits: *bits }.try_into_extension_op()?,
+                    op,
+                    builder,
+                )?
+            }
+            jeff_optype::IntArrayOp::ConstArray1(array) => {
+                let bits = 1;
+                let const_val = ConstIntReg::new(array.values().map(|v| v as u64), bits);
+                ctx.build_shared_constant(const_val, op, builder)?
             }
             jeff_optype::IntArrayOp::ConstArray8(array) => {
-                let bits = 3;
+                let bits = 8;
                 let const_val = ConstIntReg::new(array.values().map(|v| v as u64), bits);
-                ctx.build_constant_value(const_val, op, builder)?
+                ctx.build_shared_constant(const_val, op, builder)?
             }
             jeff_optype::IntArrayOp::ConstArray16(array) => {
-                let bits = 4;
+                let bits = 16;
                 let const_val = ConstIntReg::new(array.values().map(|v| v as u64), bits);
-                ctx.build_constant_value(const_val, op, builder)?
+                ctx.build_shared_constant(const_val, op, builder)?
             }
             jeff_optype::IntArrayOp::ConstArray32(array) => {
-                let bits = 5;
+                let bits = 32;
                 let const_val = ConstIntReg::new(array.values().map(|v| v as u64), bits);
-                ctx.build_constant_value(const_val, op, builder)?
+                ctx.build_shared_constant(const_val, op, builder)?
             }
             jeff_optype::IntArrayOp::ConstArray64(array) => {
-                let bits = 6;
+                let bits = 64;
                 let const_val = ConstIntReg::new(array.values(), bits);
-                ctx.build_constant_value(const_val, op, builder)?
+                ctx.build_shared_constant(const_val, op, builder)?
             }
-            // TODO: jeff_optype::IntArrayOp::ConstArray1(array)
             _ => return Err(JeffToHugrError::unsupported_op(self)),
         };
         Ok(())
@@ -1,18 +1,25 @@
+use hugr::extension::prelude::ConstError;
 use hugr::hugr::hugrmut::HugrMut;
 use hugr::ops::OpTrait;
-use hugr::ops::handle::NodeHandle;
 use hugr::std_extensions::arithmetic::float_ops::FloatOps;
 use hugr::std_extensions::arithmetic::float_types::ConstF64;
-use hugr::{HugrView, Wire};
-use itertools::Itertools;
+use hugr::std_extensions::arithmetic::int_types::ConstInt;
+use hugr::{IncomingPort, Node, OutgoingPort, Wire};
 use jeff::reader::optype as jeff_optype;
-use tket::extension::rotation::{RotationOp, rotation_type};
+use lazy_static::lazy_static;
+use tket::extension::rotation::{ConstRotation, RotationOp, rotation_type};
 
-use crate::JeffToHugrError;
+use crate::{ConversionWarning, ExactAngleConfig, JeffToHugrError, NanAngles, ResourceLimit};
 use crate::extension::JeffOp;
+use crate::metadata;
 use crate::to_hugr::BuildContext;
+use crate::to_hugr::hooks::{DataflowDyn, SeqIo};
 
 use super::JeffToHugrOp;
+use super::bounds::INDEX_LOG_WIDTH;
+use super::catalog::{CATALOG, GateInfo};
+use super::float_array::precision_bits;
+use super::tket_ops::{self, QuantumOp};
 
 /// Translation for _jeff_ quantum ops
 impl JeffToHugrOp for jeff_optype::QubitOp<'_> {
@@ -23,21 +30,19 @@ impl JeffToHugrOp for jeff_optype::QubitOp<'_> {
         ctx: &mut BuildContext,
     ) -> Result<(), JeffToHugrError> {
         match self {
-            jeff_optype::QubitOp::Alloc => {
-                ctx.build_single_op(tket::TketOp::QAlloc, op, builder)?
-            }
-            jeff_optype::QubitOp::Free => ctx.build_single_op(tket::TketOp::QFree, op, builder)?,
-            // TODO: Define a custom op for freeing qubits that are known to be in the |0> state.
-            jeff_optype::QubitOp::FreeZero => {
-                ctx.build_single_op(tket::TketOp::QFree, op, builder)?
+            jeff_optype::QubitOp::Alloc => ctx.build_single_op(tket_ops::qalloc(), op, builder)?,
+            jeff_optype::QubitOp::Free => ctx.build_single_op(tket_ops::qfree(), op, builder)?,
+            jeff_optype::QubitOp::FreeZero => build_free_zero(op, builder, ctx)?,
+            jeff_optype::QubitOp::Measure if ctx.config().split_measures => {
+                build_split_measure(op, builder, ctx)?
             }
             jeff_optype::QubitOp::Measure => {
-                ctx.build_single_op(tket::TketOp::MeasureFree, op, builder)?
+                ctx.build_single_op(tket_ops::measure_destructive(), op, builder)?
             }
             jeff_optype::QubitOp::MeasureNd => {
-                ctx.build_single_op(tket::TketOp::Measure, op, builder)?
+                ctx.build_single_op(tket_ops::measure(), op, builder)?
             }
-            jeff_optype::QubitOp::Reset => ctx.build_single_op(tket::TketOp::Reset, op, builder)?,
+            jeff_optype::QubitOp::Reset => ctx.build_single_op(tket_ops::reset(), op, builder)?,
             jeff_optype::QubitOp::Gate(gate_op) => gate_op.build_hugr_op(op, builder, ctx)?,
             _ => return Err(JeffToHugrError::unsupported_op(self)),
         };
@@ -45,6 +50,60 @@ impl JeffToHugrOp for jeff_optype::QubitOp<'_> {
     }
 }
 
+/// Emit a destructive measurement as a non-destructive `Measure` followed by
+/// a `QFree` of the measured qubit.
+///
+/// See [`crate::JeffToHugrConfig::split_measures`].
+/// Translate a `FreeZero` into a `QFree` marked with [`metadata::FREE_ZERO`].
+fn build_free_zero(
+    op: &jeff::reader::Operation<'_>,
+    builder: &mut impl hugr::builder::Dataflow,
+    ctx: &mut BuildContext,
+) -> Result<(), JeffToHugrError> {
+    if op.input_count() != 1 || op.output_count() != 0 {
+        return Err(JeffToHugrError::invalid_op_io("FreeZero", op));
+    }
+    ctx.build_op_sequence(
+        |builder| {
+            let free = builder.add_child_node(tket_ops::qfree());
+            builder
+                .hugr_mut()
+                .set_metadata(free, metadata::FREE_ZERO, true);
+            Ok::<_, JeffToHugrError>(SeqIo {
+                nodes: vec![free],
+                inputs: vec![vec![(free, IncomingPort::from(0))]],
+                outputs: vec![],
+            })
+        },
+        op,
+        builder,
+    )
+}
+
+fn build_split_measure(
+    op: &jeff::reader::Operation<'_>,
+    builder: &mut impl hugr::builder::Dataflow,
+    ctx: &mut BuildContext,
+) -> Result<(), JeffToHugrError> {
+    if op.input_count() != 1 || op.output_count() != 1 {
+        return Err(JeffToHugrError::invalid_op_io("Measure", op));
+    }
+    ctx.build_op_sequence(
+        |builder| {
+            let measure = builder.add_child_node(tket_ops::measure());
+            let free = builder.add_child_node(tket_ops::qfree());
+            builder.hugr_mut().connect(measure, 0, free, 0);
+            Ok::<_, JeffToHugrError>(SeqIo {
+                nodes: vec![measure, free],
+                inputs: vec![vec![(measure, IncomingPort::from(0))]],
+                outputs: vec![(measure, OutgoingPort::from(1))],
+            })
+        },
+        op,
+        builder,
+    )
+}
+
 impl JeffToHugrOp for jeff_optype::GateOp<'_> {
     fn build_hugr_op(
         &self,
@@ -52,21 +111,189 @@ impl JeffToHugrOp for jeff_optype::GateOp<'_> {
         builder: &mut impl hugr::builder::Dataflow,
         ctx: &mut BuildContext,
     ) -> Result<(), JeffToHugrError> {
+        ctx.config()
+            .limits
+            .check(ResourceLimit::Qubits, self.num_qubits())?;
         let gate = self.normalize();
+        let name = match gate.gate_type {
+            jeff_optype::GateOpType::WellKnown(well_known) => well_known.to_string(),
+            jeff_optype::GateOpType::PauliProdRotation { pauli_string } => {
+                ctx.sanitize_string(pauli_string.as_bytes())?
+            }
+            jeff_optype::GateOpType::Custom { name, .. } => ctx.sanitize_string(name.as_bytes())?,
+        };
+        if has_param_array(gate, op)? {
+            return build_unpacked_gate(gate, name, op, builder, ctx);
+        }
         match gate.gate_type {
             jeff_optype::GateOpType::WellKnown(well_known) => {
                 build_well_known_gate(well_known, gate, op, builder, ctx)
             }
-            jeff_optype::GateOpType::PauliProdRotation { pauli_string } => {
-                ctx.build_single_op(JeffOp::jeff_gate_op(pauli_string, gate), op, builder)
-            }
-            jeff_optype::GateOpType::Custom { name, .. } => {
-                ctx.build_single_op(JeffOp::jeff_gate_op(name, gate), op, builder)
-            }
+            jeff_optype::GateOpType::PauliProdRotation { .. }
+            | jeff_optype::GateOpType::Custom { .. } => ctx.build_single_op(
+                JeffOp::jeff_gate_op(name, gate).try_into_extension_op()?,
+                op,
+                builder,
+            ),
         }
     }
 }
 
+/// How a well-known gate is translated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum WellKnownTranslation {
+    /// The gate is the identity, its outputs are merged with its inputs.
+    Transparent,
+    /// The gate swaps its qubits, its outputs are merged with the swapped inputs.
+    Swap,
+    /// A single `tket.quantum` operation.
+    Tket(QuantumOp),
+    /// A `tket.quantum` rotation, whose angle is converted from radians.
+    /// See [`build_parametric_tket_op`].
+    Rotation(QuantumOp),
+    /// A [`JeffOp::GlobalPhase`].
+    GlobalPhase,
+    /// An opaque gate of the _jeff_ extension.
+    Opaque,
+}
+
+lazy_static! {
+    /// The translation of the well-known gates, in matching order.
+    ///
+    /// The rules are generated from the [`CATALOG`] of well-known gates.
+    /// Gates not matched by any rule are translated as opaque
+    /// [`JeffOp::QGate`]s. Changes to the catalog must be reflected in the
+    /// `tests/well_known_gates.txt` snapshot.
+    pub(crate) static ref WELL_KNOWN_GATES: Vec<WellKnownRule> =
+        CATALOG.iter().flat_map(well_known_rules).collect();
+}
+
+/// The translation rules of a well-known gate.
+///
+/// Self-inverse gates match any power and may be controlled once, the
+/// other gates match single applications and may be adjoint. Gates without
+/// a `tket` equivalent have no rule.
+fn well_known_rules(info: &GateInfo) -> Vec<WellKnownRule> {
+    use WellKnownAction::{Fixed, SelfInverse};
+
+    let Some(translation) = info.tket_equivalent else {
+        return vec![];
+    };
+    let gate = info.gate;
+    if translation == WellKnownTranslation::Transparent {
+        return vec![WellKnownRule::new(gate, None, None, None, Fixed(translation))];
+    }
+    let tket = WellKnownTranslation::Tket;
+    if info.is_self_inverse {
+        let controlled = info.controlled_equivalent.map(|controlled| {
+            WellKnownRule::new(gate, None, Some(1), None, SelfInverse(tket(controlled)))
+        });
+        let plain = WellKnownRule::new(gate, None, Some(0), None, SelfInverse(translation));
+        [plain].into_iter().chain(controlled).collect()
+    } else {
+        let adjoint = info.adjoint_equivalent.map(|adjoint| {
+            WellKnownRule::new(gate, Some(true), Some(0), Some(1), Fixed(tket(adjoint)))
+        });
+        let plain = WellKnownRule::new(gate, Some(false), Some(0), Some(1), Fixed(translation));
+        [plain].into_iter().chain(adjoint).collect()
+    }
+}
+
+/// A row of [`WELL_KNOWN_GATES`], matching a gate with some modifiers.
+///
+/// The `None` patterns match any value.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct WellKnownRule {
+    /// The matched gate.
+    pub gate: jeff_optype::WellKnownGate,
+    /// Whether the gate is adjoint.
+    pub adjoint: Option<bool>,
+    /// The number of control qubits.
+    pub controls: Option<usize>,
+    /// The power the gate is raised to.
+    pub power: Option<usize>,
+    /// The translation of the matched gates.
+    pub action: WellKnownAction,
+}
+
+/// The translation of the gates matched by a [`WellKnownRule`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum WellKnownAction {
+    /// The same translation for all the matched gates.
+    Fixed(WellKnownTranslation),
+    /// A self-inverse gate, transparent when raised to an even power.
+    SelfInverse(WellKnownTranslation),
+}
+
+impl WellKnownRule {
+    const fn new(
+        gate: jeff_optype::WellKnownGate,
+        adjoint: Option<bool>,
+        controls: Option<usize>,
+        power: Option<usize>,
+        action: WellKnownAction,
+    ) -> Self {
+        Self {
+            gate,
+            adjoint,
+            controls,
+            power,
+            action,
+        }
+    }
+
+    /// Returns the translation of a gate, if the rule matches it.
+    pub(crate) fn translate(
+        &self,
+        gate: jeff_optype::WellKnownGate,
+        adjoint: bool,
+        controls: usize,
+        power: usize,
+    ) -> Option<WellKnownTranslation> {
+        let matches = std::mem::discriminant(&self.gate) == std::mem::discriminant(&gate)
+            && self.adjoint.is_none_or(|a| a == adjoint)
+            && self.controls.is_none_or(|c| c == controls)
+            && self.power.is_none_or(|p| p == power);
+        if !matches {
+            return None;
+        }
+        Some(match self.action {
+            WellKnownAction::SelfInverse(_) if power % 2 == 0 => WellKnownTranslation::Transparent,
+            WellKnownAction::Fixed(translation) | WellKnownAction::SelfInverse(translation) => {
+                translation
+            }
+        })
+    }
+}
+
+/// Classify the translation of a well-known gate, see [`WELL_KNOWN_GATES`].
+///
+/// Gates acting on a different number of qubits than their [`GateInfo::arity`]
+/// are translated as opaque gates.
+pub(crate) fn classify_well_known(
+    wk_gate: jeff_optype::WellKnownGate,
+    gate_op: jeff_optype::GateOp<'_>,
+) -> WellKnownTranslation {
+    let controls = gate_op.control_qubits as usize;
+    if gate_op.num_qubits() != GateInfo::of(wk_gate).arity + controls {
+        return WellKnownTranslation::Opaque;
+    }
+    classify_gate(wk_gate, gate_op.adjoint, controls, gate_op.power as usize)
+}
+
+/// Classify the translation of a well-known gate with some modifiers.
+pub(crate) fn classify_gate(
+    gate: jeff_optype::WellKnownGate,
+    adjoint: bool,
+    controls: usize,
+    power: usize,
+) -> WellKnownTranslation {
+    WELL_KNOWN_GATES
+        .iter()
+        .find_map(|rule| rule.translate(gate, adjoint, controls, power))
+        .unwrap_or(WellKnownTranslation::Opaque)
+}
+
 /// Adds a well-known gate to the HUGR.
 ///
 /// Reads the extra parameters from the gate operation if any.
@@ -77,56 +304,44 @@ fn build_well_known_gate(
     builder: &mut impl hugr::builder::Dataflow,
     ctx: &mut BuildContext,
 ) -> Result<(), JeffToHugrError> {
-    use jeff_optype::WellKnownGate::*;
-
-    let mut build_self_inverse = |tket_op, pwr| match pwr % 2 == 0 {
-        true => ctx.build_transparent_op(op),
-        false => ctx.build_single_op(tket_op, op, builder),
-    };
-
-    match (
-        wk_gate,
-        gate_op.adjoint,
-        gate_op.control_qubits,
-        gate_op.power,
-    ) {
-        // Any operation with power 0 is a no-op.
-        (I, _, _, _) => ctx.build_transparent_op(op),
-        (H, _, 0, pwr) => build_self_inverse(tket::TketOp::H, pwr),
-        (X, _, 0, pwr) => build_self_inverse(tket::TketOp::X, pwr),
-        (X, _, 1, pwr) => build_self_inverse(tket::TketOp::CX, pwr),
-        (Y, _, 0, pwr) => build_self_inverse(tket::TketOp::Y, pwr),
-        (Y, _, 1, pwr) => build_self_inverse(tket::TketOp::CY, pwr),
-        (Z, _, 0, pwr) => build_self_inverse(tket::TketOp::Z, pwr),
-        (Z, _, 1, pwr) => build_self_inverse(tket::TketOp::CZ, pwr),
-        (S, false, 0, 1) => ctx.build_single_op(tket::TketOp::S, op, builder),
-        (S, true, 0, 1) => ctx.build_single_op(tket::TketOp::Sdg, op, builder),
-        (T, false, 0, 1) => ctx.build_single_op(tket::TketOp::T, op, builder),
-        (T, true, 0, 1) => ctx.build_single_op(tket::TketOp::Tdg, op, builder),
-        (Rx, false, 0, 1) => build_parametric_tket_op(ctx, tket::TketOp::Rx, op, builder),
-        (Ry, false, 0, 1) => build_parametric_tket_op(ctx, tket::TketOp::Ry, op, builder),
-        (Rz, false, 0, 1) => build_parametric_tket_op(ctx, tket::TketOp::Rz, op, builder),
-        (Swap, _, 0, pwr) => match pwr % 2 == 0 {
-            true => ctx.build_transparent_op(op),
-            false => {
-                let mut inputs = op.inputs();
-                let mut outputs = op.outputs();
-                let a_in = inputs.next().unwrap().unwrap().id();
-                let b_in = inputs.next().unwrap().unwrap().id();
-                let a_out = outputs.next().unwrap().unwrap().id();
-                let b_out = outputs.next().unwrap().unwrap().id();
-                ctx.merge_with_earlier(a_out, b_in);
-                ctx.merge_with_earlier(b_out, a_in);
-                Ok(())
-            }
-        },
-        _ => ctx.build_single_op(JeffOp::jeff_gate_op(wk_gate, gate_op), op, builder),
+    match classify_well_known(wk_gate, gate_op) {
+        WellKnownTranslation::Transparent => ctx.build_transparent_op(op),
+        WellKnownTranslation::Swap => {
+            let inputs = op
+                .inputs()
+                .map(|value| Ok(value?.id()))
+                .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+            let outputs = op
+                .outputs()
+                .map(|value| Ok(value?.id()))
+                .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+            let (&[a_in, b_in], &[a_out, b_out]) = (&inputs[..], &outputs[..]) else {
+                return Err(JeffToHugrError::invalid_op_io("SWAP", op));
+            };
+            ctx.merge_with_earlier(a_out, b_in);
+            ctx.merge_with_earlier(b_out, a_in);
+            Ok(())
+        }
+        WellKnownTranslation::Tket(tket_op) => ctx.build_single_op(tket_op, op, builder),
+        WellKnownTranslation::Rotation(tket_op) => {
+            build_parametric_tket_op(ctx, tket_op, op, builder)
+        }
+        WellKnownTranslation::GlobalPhase => ctx.build_global_phase(op, builder),
+        WellKnownTranslation::Opaque => {
+            ctx.build_single_op(
+                JeffOp::jeff_gate_op(wk_gate, gate_op).try_into_extension_op()?,
+                op,
+                builder,
+            )
+        }
     }
 }
 
 /// Emit a single HUGR operation that expects rotation-type parameters.
 ///
 /// Jeff operations work on radians, so we need to convert the inputs to half-turn rotations here.
+/// Constant parameters that are exact fractions of π are emitted directly as rotation constants,
+/// see [`crate::JeffToHugrConfig::exact_angles`].
 pub fn build_parametric_tket_op(
     ctx: &mut BuildContext,
     op: impl Into<hugr::ops::OpType>,
@@ -135,31 +350,496 @@ pub fn build_parametric_tket_op(
 ) -> Result<(), JeffToHugrError> {
     let op: hugr::ops::OpType = op.into();
     let sig = op.dataflow_signature().unwrap().into_owned();
-    let node = builder.add_child_node(op);
+    if sig.input_count() != jeff_op.input_count() || sig.output_count() != jeff_op.output_count() {
+        let name = format!("{:?}", jeff_op.op_type());
+        return Err(JeffToHugrError::invalid_op_io(name, jeff_op));
+    }
+    let exact_angles = ctx.config().exact_angles;
+    let constants = jeff_op
+        .inputs()
+        .map(|value| Ok(ctx.float_constant(value?.id())))
+        .collect::<Result<Vec<_>, JeffToHugrError>>()?;
     let rotation_t = rotation_type();
+    let is_rotation =
+        |index: usize| sig.in_port_type(IncomingPort::from(index)).unwrap() == &rotation_t;
+    // The rotations replacing the constant angles that are not finite.
+    let mut replaced = Vec::with_capacity(constants.len());
+    for (index, &radians) in constants.iter().enumerate() {
+        replaced.push(match is_rotation(index) {
+            true => non_finite_rotation(ctx, radians, builder)?,
+            false => None,
+        });
+    }
 
-    // A loaded pi constant, used for converting radians to half-turns.
-    let mut pi: Option<Wire> = None;
+    ctx.build_op_sequence(
+        |builder| {
+            let node = builder.add_child_node(op);
+            let mut io = SeqIo::single_node(node, 0, sig.output_count());
+
+            // A loaded pi constant, used for converting radians to half-turns.
+            let mut pi: Option<Wire> = None;
+
+            for (index, (radians, replaced)) in constants.into_iter().zip(replaced).enumerate() {
+                let port = IncomingPort::from(index);
+                let consumers = if !is_rotation(index) {
+                    vec![(node, port)]
+                } else if let Some(rot) = replaced {
+                    builder.hugr_mut().connect(rot.node(), rot.source(), node, port);
+                    vec![]
+                } else {
+                    connect_rotation(exact_angles, jeff_op, builder, node, port, radians, &mut pi)?
+                        .map(|div| (div, IncomingPort::from(0)))
+                        .into_iter()
+                        .collect()
+                };
+                io.inputs.push(consumers);
+            }
+            Ok::<_, JeffToHugrError>(io)
+        },
+        jeff_op,
+        builder,
+    )
+}
 
-    let input_ports = builder.hugr().node_inputs(node).collect_vec();
-    for (&port, value) in input_ports.iter().zip(jeff_op.inputs()) {
-        if sig.in_port_type(port).unwrap() == &rotation_t {
-            let pi = *pi
-                .get_or_insert_with(|| builder.add_load_value(ConstF64::new(std::f64::consts::PI)));
-            let div = builder.add_child_node(FloatOps::fdiv);
-            let rot = builder
-                .add_dataflow_op(RotationOp::from_halfturns_unchecked, [Wire::new(div, 0)])?;
-
-            builder.hugr_mut().connect(pi.node(), pi.source(), div, 1);
-            builder.hugr_mut().connect(rot.node(), 0, node, port);
-            ctx.register_input(value?.id(), div, 0.into());
-        } else {
-            ctx.register_input(value?.id(), node, port);
+/// Number of nodes converting an angle in radians to half-turns for a
+/// rotation input: an `fdiv` by π and a `from_halfturns_unchecked`.
+///
+/// The loaded π constant is shared by all the rotation inputs of a gate.
+/// See [`connect_rotation`].
+pub(crate) const ROTATION_CONVERSION_NODES: usize = 2;
+
+/// The rotation replacing a constant gate angle that is not finite, see
+/// [`NanAngles`].
+///
+/// Returns `None` for the finite and the runtime angles, which are
+/// converted by [`connect_rotation`].
+///
+/// # Errors
+///
+/// Returns [`JeffToHugrError::NonFiniteAngle`] for infinite angles, and for
+/// NaN angles with [`NanAngles::Error`].
+fn non_finite_rotation(
+    ctx: &mut BuildContext,
+    radians: Option<f64>,
+    builder: &mut impl hugr::builder::Dataflow,
+) -> Result<Option<Wire>, JeffToHugrError> {
+    let Some(radians) = radians.filter(|radians| !radians.is_finite()) else {
+        return Ok(None);
+    };
+    if radians.is_infinite() || ctx.config().nan_angles == NanAngles::Error {
+        return Err(JeffToHugrError::NonFiniteAngle {
+            radians,
+            op_index: ctx.current_op().unwrap_or_default(),
+        });
+    }
+    let function = ctx.current_function_name().map(str::to_string);
+    let message = format!(
+        "NaN gate angle in function '{}'",
+        function.as_deref().unwrap_or_default()
+    );
+    ctx.warn(ConversionWarning::NanAngle {
+        function,
+        op_index: ctx.current_op(),
+    });
+    let panic = builder.add_panic(ConstError::new(1, message), [rotation_type()], [])?;
+    Ok(Some(panic.out_wire(0)))
+}
+
+/// Connect an angle in radians to a rotation input port of `node`.
+///
+/// Constant angles that are exact fractions of π are loaded as rotation
+/// constants. Otherwise, returns the `fdiv` node converting the angle to
+/// half-turns, whose first input expects the angle.
+///
+/// Constant angles must be finite, see [`non_finite_rotation`]. `pi` caches
+/// the loaded π constant used by the conversions.
+fn connect_rotation(
+    exact_angles: Option<ExactAngleConfig>,
+    jeff_op: &jeff::reader::Operation<'_>,
+    builder: &mut dyn DataflowDyn,
+    node: Node,
+    port: IncomingPort,
+    radians: Option<f64>,
+    pi: &mut Option<Wire>,
+) -> Result<Option<Node>, JeffToHugrError> {
+    debug_assert!(radians.is_none_or(f64::is_finite));
+    let exact = exact_angles
+        .zip(radians)
+        .and_then(|(config, radians)| config.exact_half_turns(radians));
+    if let Some((k, n)) = exact {
+        let denominator = 1i64 << n;
+        let half_turns = ConstRotation::new(k as f64 / denominator as f64)
+            .map_err(|_| JeffToHugrError::unsupported_op(jeff_op))?;
+        let rot = builder.add_load_value(half_turns.into());
+        builder.hugr_mut().connect(rot.node(), rot.source(), node, port);
+        builder.hugr_mut().set_metadata(
+            node,
+            metadata::EXACT_ANGLE,
+            format!("{k}/{denominator}"),
+        );
+        return Ok(None);
+    }
+
+    let pi = *pi
+        .get_or_insert_with(|| builder.add_load_value(ConstF64::new(std::f64::consts::PI).into()));
+    let div = builder.add_child_node(FloatOps::fdiv.into());
+    let [rot] = builder
+        .add_dataflow_op(
+            RotationOp::from_halfturns_unchecked.into(),
+            vec![Wire::new(div, 0)],
+        )?
+        .try_into()
+        .unwrap();
+
+    builder.hugr_mut().connect(pi.node(), pi.source(), div, 1);
+    builder.hugr_mut().connect(rot.node(), rot.source(), node, port);
+    Ok(Some(div))
+}
+
+/// Whether the parameters of a gate are passed as a single float array,
+/// following the gate qubits.
+pub(crate) fn has_param_array(
+    gate: jeff_optype::GateOp<'_>,
+    op: &jeff::reader::Operation<'_>,
+) -> Result<bool, JeffToHugrError> {
+    if op.input_count() != gate.num_qubits() + 1 {
+        return Ok(false);
+    }
+    let param = op.input(gate.num_qubits()).unwrap()?;
+    Ok(matches!(param.ty(), jeff::types::Type::FloatArray { .. }))
+}
+
+/// The number of parameters of a gate, from its identity or its declared
+/// parameter count.
+pub(crate) fn gate_param_count(gate: jeff_optype::GateOp<'_>) -> Option<usize> {
+    match gate.gate_type {
+        jeff_optype::GateOpType::WellKnown(well_known) => Some(GateInfo::of(well_known).params),
+        jeff_optype::GateOpType::PauliProdRotation { .. } => Some(1),
+        jeff_optype::GateOpType::Custom { .. } => Some(gate.num_params()),
+    }
+}
+
+/// The name of a gate, as read from the _jeff_ program.
+pub(crate) fn gate_name(gate: jeff_optype::GateOp<'_>) -> String {
+    match gate.gate_type {
+        jeff_optype::GateOpType::WellKnown(well_known) => well_known.to_string(),
+        jeff_optype::GateOpType::PauliProdRotation { pauli_string } => pauli_string.to_string(),
+        jeff_optype::GateOpType::Custom { name, .. } => name.to_string(),
+    }
+}
+
+/// The operation emitted for a gate named `name` whose parameters are passed
+/// as a float array, along with the gate name and its number of parameters.
+///
+/// # Errors
+///
+/// - [`JeffToHugrError::UnknownGateParams`] if the number of parameters of
+///   the gate is unknown.
+pub(crate) fn unpacked_gate_op(
+    gate: jeff_optype::GateOp<'_>,
+    name: String,
+) -> Result<(hugr::ops::OpType, String, usize), JeffToHugrError> {
+    // Gates without parameters cannot take a parameter array, so the count is unknown.
+    let params = gate_param_count(gate)
+        .filter(|&n| n > 0)
+        .ok_or_else(|| JeffToHugrError::UnknownGateParams { gate: name.clone() })?;
+
+    let rotation = match gate.gate_type {
+        jeff_optype::GateOpType::WellKnown(well_known) => {
+            match classify_well_known(well_known, gate) {
+                WellKnownTranslation::Rotation(tket_op) => Some(tket_op),
+                _ => None,
+            }
         }
+        _ => None,
+    };
+    let hugr_op: hugr::ops::OpType = match rotation {
+        Some(tket_op) => tket_op.into(),
+        None => JeffOp::quantum_gate(
+            name.clone(),
+            gate.num_qubits() - gate.control_qubits as usize,
+            params,
+            gate.control_qubits as usize,
+            gate.adjoint,
+            gate.power as usize,
+        )
+        .try_into_extension_op()?
+        .into(),
+    };
+    Ok((hugr_op, name, params))
+}
+
+/// Emit a gate whose parameters are passed as a float array.
+///
+/// The elements of arrays built from constants are loaded as constants,
+/// otherwise they are read with [`JeffOp::FloatArrayGet`] operations. Rotation
+/// gates take their parameters as half-turns, see [`build_parametric_tket_op`].
+///
+/// # Errors
+///
+/// - [`JeffToHugrError::UnknownGateParams`] if the number of parameters of
+///   the gate is unknown.
+/// - [`JeffToHugrError::InvalidOperationIO`] if a constant array does not
+///   have one element per gate parameter.
+fn build_unpacked_gate(
+    gate: jeff_optype::GateOp<'_>,
+    name: String,
+    op: &jeff::reader::Operation<'_>,
+    builder: &mut impl hugr::builder::Dataflow,
+    ctx: &mut BuildContext,
+) -> Result<(), JeffToHugrError> {
+    let num_qubits = gate.num_qubits();
+    let array = op.input(num_qubits).unwrap()?;
+    let jeff::types::Type::FloatArray { precision } = array.ty() else {
+        return Err(JeffToHugrError::unsupported_op(op));
+    };
+    let (hugr_op, name, params) = unpacked_gate_op(gate, name)?;
+    let constants = ctx.float_array_constant(array.id()).map(<[f64]>::to_vec);
+    if constants.as_ref().is_some_and(|values| values.len() != params) {
+        return Err(JeffToHugrError::invalid_op_io(&name, op));
+    }
+
+    let sig = hugr_op.dataflow_signature().unwrap().into_owned();
+    if sig.input_count() != num_qubits + params || sig.output_count() != op.output_count() {
+        return Err(JeffToHugrError::invalid_op_io(format!("{:?}", op.op_type()), op));
     }
-    for (port, value) in builder.hugr().node_outputs(node).zip(jeff_op.outputs()) {
-        ctx.register_output(value?.id(), node, port);
+
+    let node = builder.add_child_node(hugr_op);
+    ctx.record_origin(node);
+    ctx.order_quantum_op(node, builder);
+    for (index, value) in op.inputs().take(num_qubits).enumerate() {
+        ctx.register_input(value?.id(), node, IncomingPort::from(index));
+    }
+    for (index, value) in op.outputs().enumerate() {
+        ctx.register_output(value?.id(), node, OutgoingPort::from(index));
     }
 
+    let exact_angles = ctx.config().exact_angles;
+    let rotation_t = rotation_type();
+    let mut pi: Option<Wire> = None;
+    for param in 0..params {
+        let port = IncomingPort::from(num_qubits + param);
+        let constant = constants.as_ref().map(|values| values[param]);
+        let is_rotation = sig.in_port_type(port).unwrap() == &rotation_t;
+        if is_rotation && let Some(rot) = non_finite_rotation(ctx, constant, builder)? {
+            builder.hugr_mut().connect(rot.node(), rot.source(), node, port);
+            continue;
+        }
+        let target = match is_rotation {
+            true => connect_rotation(exact_angles, op, builder, node, port, constant, &mut pi)?
+                .map(|div| (div, IncomingPort::from(0))),
+            false => Some((node, port)),
+        };
+        let Some((target, target_port)) = target else {
+            continue;
+        };
+
+        let element = match constant {
+            Some(value) => builder.add_load_value(ConstF64::new(value)),
+            None => {
+                let index = ConstInt::new_u(INDEX_LOG_WIDTH, param as u64).unwrap();
+                let index = builder.add_load_value(index);
+                let get = JeffOp::FloatArrayGet {
+                    bits: precision_bits(precision),
+                };
+                let get = builder.add_child_node(get.try_into_extension_op()?);
+                builder.hugr_mut().connect(index.node(), index.source(), get, 1);
+                ctx.register_input(array.id(), get, IncomingPort::from(0));
+                Wire::new(get, 0)
+            }
+        };
+        builder
+            .hugr_mut()
+            .connect(element.node(), element.source(), target, target_port);
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{ConversionWarning, JeffToHugrConfig};
+    use hugr::{Hugr, HugrView};
+    use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr};
+    use hugr::extension::prelude::qb_t;
+    use hugr::types::Signature;
+    use itertools::iproduct;
+    use jeff_optype::WellKnownGate as Gate;
+    use rstest::rstest;
+    use std::fmt::Write;
+
+    const MAX_CONTROLS: usize = 2;
+    const MAX_POWER: usize = 3;
+
+    /// The checked-in translation of the well-known gates.
+    const SNAPSHOT_PATH: &str = "tests/well_known_gates.txt";
+
+    /// All the gate and modifier combinations covered by the snapshot.
+    fn combinations() -> impl Iterator<Item = (Gate, bool, usize, usize)> {
+        let gates = CATALOG.map(|info| info.gate);
+        iproduct!(gates, [false, true], 0..=MAX_CONTROLS, 0..=MAX_POWER)
+    }
+
+    fn describe(translation: WellKnownTranslation) -> String {
+        match translation {
+            WellKnownTranslation::Transparent => "transparent".to_string(),
+            WellKnownTranslation::Swap => "swap".to_string(),
+            WellKnownTranslation::Tket(tket_op) => format!("tket {tket_op:?}"),
+            WellKnownTranslation::Rotation(tket_op) => format!("rotation {tket_op:?}"),
+            WellKnownTranslation::GlobalPhase => "global phase".to_string(),
+            WellKnownTranslation::Opaque => "opaque".to_string(),
+        }
+    }
+
+    /// Compare the translation of every combination with the snapshot. Run
+    /// with `UPDATE_GATE_TABLE=1` to update it after an intentional change.
+    #[rstest]
+    fn well_known_gate_matrix() {
+        let mut matrix = String::new();
+        for (gate, adjoint, controls, power) in combinations() {
+            let translation = classify_gate(gate, adjoint, controls, power);
+            if translation != WellKnownTranslation::Opaque {
+                let modifiers = format!("adjoint={adjoint} controls={controls} power={power}");
+                writeln!(matrix, "{gate:?} {modifiers} -> {}", describe(translation)).unwrap();
+            }
+        }
+
+        if std::env::var_os("UPDATE_GATE_TABLE").is_some() {
+            std::fs::write(SNAPSHOT_PATH, &matrix).unwrap();
+            return;
+        }
+        let snapshot = std::fs::read_to_string(SNAPSHOT_PATH).unwrap();
+        assert_eq!(
+            matrix, snapshot,
+            "Gate translations changed. Run with UPDATE_GATE_TABLE=1 to update the snapshot."
+        );
+    }
+
+    /// Every rule of the table is used by some combination, so none is
+    /// shadowed by an earlier rule or left out of the snapshot.
+    #[rstest]
+    fn rules_reachable() {
+        for (index, rule) in WELL_KNOWN_GATES.iter().enumerate() {
+            assert!(rule.controls.is_none_or(|c| c <= MAX_CONTROLS));
+            assert!(rule.power.is_none_or(|p| p <= MAX_POWER));
+            let used = combinations().any(|(gate, adjoint, controls, power)| {
+                let first = WELL_KNOWN_GATES
+                    .iter()
+                    .position(|r| r.translate(gate, adjoint, controls, power).is_some());
+                first == Some(index)
+            });
+            assert!(used, "rule {index} is never used: {rule:?}");
+        }
+    }
+
+    /// The `tket` equivalents of the catalog agree with the snapshot.
+    #[rstest]
+    fn catalog_matches_snapshot() {
+        let snapshot = std::fs::read_to_string(SNAPSHOT_PATH).unwrap();
+        let translation = |gate: Gate, adjoint: bool, controls: usize| {
+            let line = format!("{gate:?} adjoint={adjoint} controls={controls} power=1 -> ");
+            snapshot.lines().find_map(|l| l.strip_prefix(&line)).map(str::to_string)
+        };
+        for info in CATALOG {
+            let gate = info.gate;
+            let expected = info.tket_equivalent.map(describe);
+            assert_eq!(translation(gate, false, 0), expected, "{gate}");
+            if let Some(adjoint) = info.adjoint_equivalent {
+                let expected = describe(WellKnownTranslation::Tket(adjoint));
+                assert_eq!(translation(gate, true, 0), Some(expected), "{gate}");
+            }
+            if let Some(controlled) = info.controlled_equivalent {
+                let expected = describe(WellKnownTranslation::Tket(controlled));
+                assert_eq!(translation(gate, false, 1), Some(expected), "{gate}");
+            }
+        }
+    }
+
+    /// Unmatched gates fall back to an opaque `QGate`.
+    #[rstest]
+    #[case::phase(Gate::R1, false, 0, 1, 1)]
+    #[case::unitary(Gate::U, true, 1, 1, 3)]
+    #[case::power(Gate::S, false, 0, 2, 0)]
+    #[case::adjoint_rotation(Gate::Rx, true, 0, 1, 1)]
+    #[case::controlled_hadamard(Gate::H, false, 1, 1, 0)]
+    #[case::doubly_controlled(Gate::X, false, 2, 3, 0)]
+    fn opaque_fallback(
+        #[case] gate: Gate,
+        #[case] adjoint: bool,
+        #[case] controls: usize,
+        #[case] power: usize,
+        #[case] params: usize,
+    ) {
+        assert_eq!(
+            classify_gate(gate, adjoint, controls, power),
+            WellKnownTranslation::Opaque
+        );
+        let op = JeffOp::quantum_gate(gate.to_string(), 1, params, controls, adjoint, power);
+        assert!(op.try_into_extension_op().is_ok());
+    }
+
+    /// A single-qubit DFG, and a context translating with `nan_angles`.
+    fn rotation_builder(nan_angles: NanAngles) -> (DFGBuilder<Hugr>, BuildContext) {
+        let config = JeffToHugrConfig::default().with_nan_angles(nan_angles);
+        let builder = DFGBuilder::new(Signature::new_endo(vec![qb_t()])).unwrap();
+        (builder, BuildContext::with_config(&config))
+    }
+
+    /// Constant gate angles that are not finite fail the translation, or
+    /// are replaced by a runtime panic. No rotation is computed from them.
+    #[rstest]
+    #[case::nan_error(f64::NAN, NanAngles::Error, false)]
+    #[case::negative_nan_error(-f64::NAN, NanAngles::Error, false)]
+    #[case::nan_warn(f64::NAN, NanAngles::Warn, true)]
+    #[case::inf(f64::INFINITY, NanAngles::Warn, false)]
+    #[case::neg_inf(f64::NEG_INFINITY, NanAngles::Error, false)]
+    fn non_finite_angles(
+        #[case] radians: f64,
+        #[case] nan_angles: NanAngles,
+        #[case] replaced: bool,
+    ) {
+        let (mut builder, mut ctx) = rotation_builder(nan_angles);
+        let result = non_finite_rotation(&mut ctx, Some(radians), &mut builder);
+        if !replaced {
+            assert!(
+                matches!(result, Err(JeffToHugrError::NonFiniteAngle { .. })),
+                "{result:?}"
+            );
+            assert!(ctx.take_warnings().is_empty());
+            return;
+        }
+
+        let rot = result.unwrap().unwrap();
+        let [qubit] = builder.input_wires_arr();
+        let rz = builder.add_dataflow_op(QuantumOp::Rz, [qubit, rot]).unwrap();
+        let hugr = builder.finish_hugr_with_outputs(rz.outputs()).unwrap();
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        let panic = hugr.get_optype(rot.node()).as_extension_op().unwrap();
+        assert_eq!(panic.unqualified_id(), "panic");
+        assert!(
+            !hugr
+                .nodes()
+                .any(|node| hugr.get_optype(node).cast::<RotationOp>().is_some())
+        );
+        assert_eq!(
+            ctx.take_warnings(),
+            [ConversionWarning::NanAngle {
+                function: None,
+                op_index: None,
+            }]
+        );
+    }
+
+    /// Finite and runtime angles are left to [`connect_rotation`], keeping
+    /// the sign of zero.
+    #[rstest]
+    #[case::neg_zero(Some(-0.0))]
+    #[case::max(Some(f64::MAX))]
+    #[case::runtime(None)]
+    fn finite_angles(#[case] radians: Option<f64>) {
+        let (mut builder, mut ctx) = rotation_builder(NanAngles::Error);
+        let result = non_finite_rotation(&mut ctx, radians, &mut builder);
+        assert_eq!(result.unwrap(), None);
+        assert!(ctx.take_warnings().is_empty());
+    }
+}
@@ -1,10 +1,11 @@
 use jeff::reader::optype as jeff_optype;
 
-use crate::JeffToHugrError;
+use crate::{JeffToHugrError, ResourceLimit};
 use crate::extension::JeffOp;
 use crate::to_hugr::BuildContext;
 
 use super::JeffToHugrOp;
+use super::bounds::build_indexed_access;
 
 /// Translation for _jeff_ quantum ops
 impl JeffToHugrOp for jeff_optype::QubitRegisterOp {
@@ -16,38 +17,63 @@ impl JeffToHugrOp for jeff_optype::QubitRegisterOp {
     ) -> Result<(), JeffToHugrError> {
         match self {
             jeff_optype::QubitRegisterOp::Alloc => {
-                ctx.build_single_op(JeffOp::QuregAlloc, op, builder)?
+                ctx.build_single_op(JeffOp::QuregAlloc.try_into_extension_op()?, op, builder)?
             }
             jeff_optype::QubitRegisterOp::Free => {
-                ctx.build_single_op(JeffOp::QuregFree, op, builder)?
+                ctx.build_single_op(JeffOp::QuregFree.try_into_extension_op()?, op, builder)?
             }
             jeff_optype::QubitRegisterOp::FreeZero => {
-                ctx.build_single_op(JeffOp::QuregFree, op, builder)?
-            }
-            jeff_optype::QubitRegisterOp::ExtractIndex => {
-                ctx.build_single_op(JeffOp::QuregExtractIndex, op, builder)?
-            }
-            jeff_optype::QubitRegisterOp::InsertIndex => {
-                ctx.build_single_op(JeffOp::QuregInsertIndex, op, builder)?
+                ctx.build_single_op(JeffOp::QuregFree.try_into_extension_op()?, op, builder)?
             }
+            jeff_optype::QubitRegisterOp::ExtractIndex => build_indexed_access(
+                ctx,
+                JeffOp::QuregExtractIndex,
+                JeffOp::QuregLength,
+                1,
+                op,
+                builder,
+            )?,
+            jeff_optype::QubitRegisterOp::InsertIndex => build_indexed_access(
+                ctx,
+                JeffOp::QuregInsertIndex,
+                JeffOp::QuregLength,
+                2,
+                op,
+                builder,
+            )?,
             jeff_optype::QubitRegisterOp::ExtractSlice => {
-                ctx.build_single_op(JeffOp::QuregExtractSlice, op, builder)?
+                ctx.build_single_op(
+                    JeffOp::QuregExtractSlice.try_into_extension_op()?,
+                    op,
+                    builder,
+                )?
             }
             jeff_optype::QubitRegisterOp::InsertSlice => {
-                ctx.build_single_op(JeffOp::QuregInsertSlice, op, builder)?
+                ctx.build_single_op(JeffOp::QuregInsertSlice.try_into_extension_op()?, op, builder)?
             }
             jeff_optype::QubitRegisterOp::Length => {
-                ctx.build_single_op(JeffOp::QuregLength, op, builder)?
+                ctx.build_single_op(JeffOp::QuregLength.try_into_extension_op()?, op, builder)?
             }
             jeff_optype::QubitRegisterOp::Split => {
-                ctx.build_single_op(JeffOp::QuregSplit, op, builder)?
+                ctx.build_single_op(JeffOp::QuregSplit.try_into_extension_op()?, op, builder)?
             }
             jeff_optype::QubitRegisterOp::Join => {
-                ctx.build_single_op(JeffOp::QuregJoin, op, builder)?
+                ctx.build_single_op(JeffOp::QuregJoin.try_into_extension_op()?, op, builder)?
+            }
+            jeff_optype::QubitRegisterOp::Measure => {
+                ctx.build_single_op(JeffOp::QuregMeasure.try_into_extension_op()?, op, builder)?
+            }
+            jeff_optype::QubitRegisterOp::MeasureNd => {
+                ctx.build_single_op(JeffOp::QuregMeasureNd.try_into_extension_op()?, op, builder)?
             }
             jeff_optype::QubitRegisterOp::Create => {
                 let qubits = op.input_count();
-                ctx.build_single_op(JeffOp::QuregCreate { qubits }, op, builder)?
+                ctx.config().limits.check(ResourceLimit::Qubits, qubits)?;
+                ctx.build_single_op(
+                    JeffOp::QuregCreate { qubits }.try_into_extension_op()?,
+                    op,
+                    builder,
+                )?
             }
             _ => return Err(JeffToHugrError::unsupported_op(self)),
         };
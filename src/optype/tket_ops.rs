@@ -0,0 +1,230 @@
+//! The `tket.quantum` operations emitted and matched by the translation.
+//!
+//! The translation, the passes and the analyses refer to the quantum
+//! operations through [`QuantumOp`] and the constructors of this module,
+//! and never name the [`TketOp`] variants directly. The naming and signature
+//! conventions of the tket extension are mapped here, so that upgrading the
+//! `tket` dependency only requires changes to this module. The conventions
+//! the crate relies on are checked by the `signatures` test.
+//!
+//! [`crate::analysis::GateSet`] is the exception, as its public API is
+//! expressed in terms of [`TketOp`]s.
+
+use hugr::extension::simple_op::MakeOpDef;
+use hugr::ops::OpType;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+use tket::TketOp;
+
+/// A quantum operation of the `tket.quantum` extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter)]
+pub(crate) enum QuantumOp {
+    /// Allocate a qubit in the zero state.
+    QAlloc,
+    /// Free a qubit.
+    QFree,
+    /// Measure a qubit and free it, returning the outcome.
+    MeasureDestructive,
+    /// Measure a qubit, returning the qubit and the outcome.
+    Measure,
+    /// Reset a qubit to the zero state.
+    Reset,
+    /// Hadamard gate.
+    H,
+    /// Pauli X gate.
+    X,
+    /// Pauli Y gate.
+    Y,
+    /// Pauli Z gate.
+    Z,
+    /// Controlled X gate, with the control first.
+    CX,
+    /// Controlled Y gate, with the control first.
+    CY,
+    /// Controlled Z gate, with the control first.
+    CZ,
+    /// S gate.
+    S,
+    /// Adjoint of the S gate.
+    Sdg,
+    /// T gate.
+    T,
+    /// Adjoint of the T gate.
+    Tdg,
+    /// Rotation around the X axis, by an angle in half-turns.
+    Rx,
+    /// Rotation around the Y axis, by an angle in half-turns.
+    Ry,
+    /// Rotation around the Z axis, by an angle in half-turns.
+    Rz,
+}
+
+impl QuantumOp {
+    /// The tket operation implementing this operation.
+    fn tket_op(self) -> TketOp {
+        match self {
+            Self::QAlloc => TketOp::QAlloc,
+            Self::QFree => TketOp::QFree,
+            Self::MeasureDestructive => TketOp::MeasureFree,
+            Self::Measure => TketOp::Measure,
+            Self::Reset => TketOp::Reset,
+            Self::H => TketOp::H,
+            Self::X => TketOp::X,
+            Self::Y => TketOp::Y,
+            Self::Z => TketOp::Z,
+            Self::CX => TketOp::CX,
+            Self::CY => TketOp::CY,
+            Self::CZ => TketOp::CZ,
+            Self::S => TketOp::S,
+            Self::Sdg => TketOp::Sdg,
+            Self::T => TketOp::T,
+            Self::Tdg => TketOp::Tdg,
+            Self::Rx => TketOp::Rx,
+            Self::Ry => TketOp::Ry,
+            Self::Rz => TketOp::Rz,
+        }
+    }
+
+    /// Returns the quantum operation of a HUGR operation, if any.
+    pub(crate) fn of(op: &OpType) -> Option<Self> {
+        let tket_op = op.cast::<TketOp>()?;
+        Self::iter().find(|quantum_op| quantum_op.tket_op() == tket_op)
+    }
+
+    /// Returns `true` if the operation is a gate that is its own inverse.
+    pub(crate) fn is_self_inverse(self) -> bool {
+        matches!(
+            self,
+            Self::H | Self::X | Self::Y | Self::Z | Self::CX | Self::CY | Self::CZ
+        )
+    }
+}
+
+impl From<QuantumOp> for OpType {
+    fn from(op: QuantumOp) -> Self {
+        op.tket_op().into()
+    }
+}
+
+/// Allocate a qubit in the zero state.
+pub(crate) fn qalloc() -> OpType {
+    QuantumOp::QAlloc.into()
+}
+
+/// Free a qubit.
+pub(crate) fn qfree() -> OpType {
+    QuantumOp::QFree.into()
+}
+
+/// Measure a qubit and free it, returning the outcome.
+pub(crate) fn measure_destructive() -> OpType {
+    QuantumOp::MeasureDestructive.into()
+}
+
+/// Measure a qubit, returning the qubit and the outcome.
+pub(crate) fn measure() -> OpType {
+    QuantumOp::Measure.into()
+}
+
+/// Reset a qubit to the zero state.
+pub(crate) fn reset() -> OpType {
+    QuantumOp::Reset.into()
+}
+
+/// Returns `true` if the operation belongs to the `tket.quantum` extension,
+/// including the operations not covered by [`QuantumOp`].
+pub(crate) fn is_tket_op(op: &OpType) -> bool {
+    op.cast::<TketOp>().is_some()
+}
+
+/// The name of a `tket.quantum` operation, read back by [`tket_op_by_name`].
+pub(crate) fn tket_op_name(op: &OpType) -> Option<String> {
+    op.cast::<TketOp>().map(|tket_op| tket_op.opdef_id().to_string())
+}
+
+/// The `tket.quantum` operation with the given name, see [`tket_op_name`].
+pub(crate) fn tket_op_by_name(name: &str) -> Option<OpType> {
+    name.parse::<TketOp>().ok().map(OpType::from)
+}
+
+/// The names of all the `tket.quantum` operations, see [`tket_op_name`].
+pub(crate) fn tket_op_names() -> impl Iterator<Item = String> {
+    TketOp::iter().map(|tket_op| tket_op.opdef_id().to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::jeff_to_hugr;
+    use crate::test::{catalyst_simple, catalyst_tket_opt, entangled_calls, qubits};
+    use hugr::HugrView;
+    use hugr::extension::prelude::{bool_t, qb_t};
+    use hugr::types::Type;
+    use jeff::Jeff;
+    use rstest::rstest;
+    use tket::extension::rotation::rotation_type;
+
+    /// The input and output types the translation expects of an operation.
+    fn expected_signature(op: QuantumOp) -> (Vec<Type>, Vec<Type>) {
+        use QuantumOp::*;
+        match op {
+            QAlloc => (vec![], vec![qb_t()]),
+            QFree => (vec![qb_t()], vec![]),
+            MeasureDestructive => (vec![qb_t()], vec![bool_t()]),
+            Measure => (vec![qb_t()], vec![qb_t(), bool_t()]),
+            Reset | H | X | Y | Z | S | Sdg | T | Tdg => (vec![qb_t()], vec![qb_t()]),
+            CX | CY | CZ => (vec![qb_t(), qb_t()], vec![qb_t(), qb_t()]),
+            Rx | Ry | Rz => (vec![qb_t(), rotation_type()], vec![qb_t()]),
+        }
+    }
+
+    /// The extension and version of an operation, for the failure messages.
+    fn extension_version(op: &OpType) -> String {
+        let Some(ext_op) = op.as_extension_op() else {
+            return "no extension".to_string();
+        };
+        let id = ext_op.def().extension_id().to_string();
+        match tket::extension::REGISTRY.get(&id) {
+            Some(ext) => format!("{id} {}", ext.version),
+            None => format!("{id} (unregistered)"),
+        }
+    }
+
+    /// Every operation has the signature the translation relies on, and is
+    /// recognized back from the HUGR operation.
+    #[rstest]
+    fn signatures() {
+        for quantum_op in QuantumOp::iter() {
+            let op = OpType::from(quantum_op);
+            let version = extension_version(&op);
+            let sig = op
+                .dataflow_signature()
+                .unwrap_or_else(|| panic!("{quantum_op:?} has no signature in {version}"));
+            let (inputs, outputs) = expected_signature(quantum_op);
+            assert_eq!(
+                (sig.input_types(), sig.output_types()),
+                (&inputs[..], &outputs[..]),
+                "The signature of {quantum_op:?} changed in {version}, update the tket facade"
+            );
+            assert_eq!(QuantumOp::of(&op), Some(quantum_op), "{quantum_op:?} in {version}");
+            let name = tket_op_name(&op).unwrap();
+            assert_eq!(tket_op_by_name(&name), Some(op), "{quantum_op:?} in {version}");
+        }
+    }
+
+    /// Every `tket.quantum` operation emitted on the fixtures goes through the facade.
+    #[rstest]
+    #[case::qubits(qubits())]
+    #[case::catalyst_simple(catalyst_simple())]
+    #[case::catalyst_tket(catalyst_tket_opt())]
+    #[case::entangled_calls(entangled_calls())]
+    fn fixtures_use_facade(#[case] jeff: Jeff<'static>) {
+        let hugr = jeff_to_hugr(&jeff).unwrap();
+        for node in hugr.nodes() {
+            let op = hugr.get_optype(node);
+            if is_tket_op(op) {
+                assert!(QuantumOp::of(op).is_some(), "{op:?} is not in the tket facade");
+            }
+        }
+    }
+}
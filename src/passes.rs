@@ -0,0 +1,61 @@
+//! Rewrite passes over HUGR programs produced by the _jeff_ translation.
+//!
+//! Each pass returns the number of rewrites it made, and appends its name to
+//! the [`Provenance`](crate::provenance::Provenance) of translated HUGRs.
+//!
+//! # Examples
+//!
+//! Annotate the registers of known size, and lower their measurements:
+//!
+//! ```
+//! use hugr_jeff::doctest_support::measured_register;
+//! use hugr_jeff::hugr::HugrView;
+//! use hugr_jeff::passes::{annotate_register_sizes, lower_register_measures};
+//!
+//! let mut hugr = measured_register(3);
+//! // The allocation produces a register of 3 qubits.
+//! assert_eq!(annotate_register_sizes(&mut hugr), 1);
+//! assert_eq!(lower_register_measures(&mut hugr), 1);
+//! hugr.validate().unwrap();
+//! ```
+
+mod ancilla;
+mod borrow;
+pub(crate) mod cancel;
+mod circuit_box;
+mod cond_gate;
+mod counter_loop;
+mod free_zero;
+pub(crate) mod fuse;
+mod measure;
+mod phase;
+mod protect;
+mod qureg;
+mod results;
+mod reverse;
+mod runtime;
+mod strip;
+mod switch_ladder;
+
+pub use ancilla::reuse_ancillas;
+pub use borrow::lower_qubit_borrows;
+pub use cancel::cancel_inverse_gates;
+pub use circuit_box::box_circuit_segments;
+pub use cond_gate::{fuse_conditional_gates, lower_conditional_gates};
+pub use counter_loop::annotate_counter_loops;
+pub use free_zero::{FreeZeroRepair, repair_free_zero};
+pub use fuse::fuse_single_qubit_gates;
+pub use measure::lower_register_measures;
+pub use phase::{GlobalPhaseLowering, lower_global_phases};
+pub use protect::protect_ops;
+pub use qureg::{
+    annotate_register_sizes, cancel_register_roundtrips, fold_register_lengths,
+    propagate_register_sizes, resolve_register_permutations,
+};
+pub use results::{ResultConvention, canonicalize_results};
+pub use reverse::lower_int_array_reverses;
+pub use runtime::{RuntimeValues, bind_runtime_queries};
+pub use strip::{StripOptions, strip};
+pub use switch_ladder::{SwitchLadderOptions, collapse_switch_ladders};
+
+pub(crate) use switch_ladder::tag_computation;
@@ -0,0 +1,126 @@
+//! Reuse of freed ancilla qubits by later allocations.
+
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::{HugrView, Node};
+
+use crate::analysis::{find_ancilla_reuse, reaches};
+use crate::optype::tket_ops::QuantumOp;
+use crate::provenance::record_pass;
+
+/// Replace later qubit allocations by the wires of freed ancillas.
+///
+/// For each ancilla found by [`find_ancilla_reuse`], the first `QAlloc` of
+/// the same region whose qubit is only used after the ancilla is freed is
+/// removed together with the ancilla's `QFree`, and its users are connected
+/// to the ancilla wire instead. Nodes with order edges are left unchanged.
+///
+/// Returns the number of removed allocations.
+pub fn reuse_ancillas(hugr: &mut impl HugrMut<Node = Node>) -> usize {
+    record_pass(hugr, "reuse_ancillas");
+    let mut reused = 0;
+    for ancilla in find_ancilla_reuse(hugr) {
+        let free = ancilla.free;
+        let Some((src, src_port)) = hugr.single_linked_output(free, 0) else {
+            continue;
+        };
+        if has_order_edges(hugr, free) {
+            continue;
+        }
+        let region = hugr.get_parent(free).unwrap();
+        let later = hugr.children(region).find(|&alloc| {
+            alloc != ancilla.alloc
+                && QuantumOp::of(hugr.get_optype(alloc)) == Some(QuantumOp::QAlloc)
+                && !has_order_edges(hugr, alloc)
+                && hugr
+                    .single_linked_input(alloc, 0)
+                    .is_some_and(|(tgt, _)| !reaches(hugr, region, tgt, src))
+        });
+        let Some(alloc) = later else {
+            continue;
+        };
+        let (tgt, tgt_port) = hugr.single_linked_input(alloc, 0).unwrap();
+        hugr.remove_node(free);
+        hugr.remove_node(alloc);
+        hugr.connect(src, src_port, tgt, tgt_port);
+        reused += 1;
+    }
+    reused
+}
+
+fn has_order_edges(hugr: &impl HugrView<Node = Node>, node: Node) -> bool {
+    let op = hugr.get_optype(node);
+    let linked_input = op.other_input_port().is_some_and(|p| hugr.is_linked(node, p));
+    let linked_output = op.other_output_port().is_some_and(|p| hugr.is_linked(node, p));
+    linked_input || linked_output
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::analysis::peak_qubits_bound;
+    use hugr::Hugr;
+    use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr};
+    use hugr::extension::prelude::qb_t;
+    use hugr::types::Signature;
+    use rstest::rstest;
+
+    fn count_op(hugr: &impl HugrView<Node = Node>, op: QuantumOp) -> usize {
+        hugr.nodes()
+            .filter(|&n| QuantumOp::of(hugr.get_optype(n)) == Some(op))
+            .count()
+    }
+
+    /// Two ancillas entangled and disentangled with the input qubit, one
+    /// after the other.
+    fn sequential_ancillas() -> Hugr {
+        let mut builder = DFGBuilder::new(Signature::new_endo(vec![qb_t()])).unwrap();
+        let [mut q] = builder.input_wires_arr();
+        for _ in 0..2 {
+            let [mut a] = builder.add_dataflow_op(QuantumOp::QAlloc, []).unwrap().outputs_arr();
+            for _ in 0..2 {
+                [q, a] = builder.add_dataflow_op(QuantumOp::CX, [q, a]).unwrap().outputs_arr();
+            }
+            builder.add_dataflow_op(QuantumOp::QFree, [a]).unwrap();
+        }
+        builder.finish_hugr_with_outputs([q]).unwrap()
+    }
+
+    /// Two ancillas entangled with each other while both are live.
+    fn overlapping_ancillas() -> Hugr {
+        let mut builder = DFGBuilder::new(Signature::new_endo(vec![qb_t()])).unwrap();
+        let [q] = builder.input_wires_arr();
+        let [a] = builder.add_dataflow_op(QuantumOp::QAlloc, []).unwrap().outputs_arr();
+        let [b] = builder.add_dataflow_op(QuantumOp::QAlloc, []).unwrap().outputs_arr();
+        let [q, a] = builder.add_dataflow_op(QuantumOp::CX, [q, a]).unwrap().outputs_arr();
+        let [a, b] = builder.add_dataflow_op(QuantumOp::CX, [a, b]).unwrap().outputs_arr();
+        let [a, b] = builder.add_dataflow_op(QuantumOp::CX, [a, b]).unwrap().outputs_arr();
+        let [q, a] = builder.add_dataflow_op(QuantumOp::CX, [q, a]).unwrap().outputs_arr();
+        builder.add_dataflow_op(QuantumOp::QFree, [a]).unwrap();
+        builder.add_dataflow_op(QuantumOp::QFree, [b]).unwrap();
+        builder.finish_hugr_with_outputs([q]).unwrap()
+    }
+
+    #[rstest]
+    fn reuse_sequential() {
+        let mut hugr = sequential_ancillas();
+        assert_eq!(find_ancilla_reuse(&hugr).len(), 2);
+        assert_eq!(peak_qubits_bound(&hugr, hugr.entrypoint()), 3);
+
+        assert_eq!(reuse_ancillas(&mut hugr), 1);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(peak_qubits_bound(&hugr, hugr.entrypoint()), 2);
+        assert_eq!(count_op(&hugr, QuantumOp::QAlloc), 1);
+        assert_eq!(count_op(&hugr, QuantumOp::QFree), 1);
+    }
+
+    #[rstest]
+    fn keep_overlapping() {
+        let mut hugr = overlapping_ancillas();
+        assert_eq!(find_ancilla_reuse(&hugr).len(), 2);
+
+        assert_eq!(reuse_ancillas(&mut hugr), 0);
+        assert_eq!(peak_qubits_bound(&hugr, hugr.entrypoint()), 3);
+        assert_eq!(count_op(&hugr, QuantumOp::QAlloc), 2);
+    }
+}
@@ -0,0 +1,127 @@
+//! Lowering of borrowed qubits into allocated ones.
+
+use hugr::builder::{BuildError, DFGBuilder, Dataflow, DataflowHugr};
+use hugr::extension::prelude::qb_t;
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::types::Signature;
+use hugr::{Hugr, HugrView, Node};
+use itertools::Itertools;
+
+use super::measure::replace_with_dfg;
+use crate::extension::JeffOp;
+use crate::optype::tket_ops::QuantumOp;
+use crate::provenance::record_pass;
+
+/// Replace the [`JeffOp::QubitBorrow`] operations by a `QAlloc` followed by
+/// a `Reset`, and the [`JeffOp::QubitReturn`] operations by a `QFree`, for
+/// the backends without a pool of qubits to borrow from.
+///
+/// A borrowed qubit is in an unknown state, so programs cannot rely on the
+/// state of the fresh qubit. The `Reset` keeps the lowered program correct
+/// on backends whose allocations are not guaranteed to be clean.
+///
+/// Each lowered operation is replaced by a nested dataflow graph.
+///
+/// Returns the number of lowered operations.
+pub fn lower_qubit_borrows(hugr: &mut impl HugrMut<Node = Node>) -> usize {
+    record_pass(hugr, "lower_qubit_borrows");
+    let borrows = hugr
+        .nodes()
+        .filter_map(|node| match hugr.get_optype(node).cast::<JeffOp>()? {
+            op @ (JeffOp::QubitBorrow | JeffOp::QubitReturn) => Some((node, op)),
+            _ => None,
+        })
+        .collect_vec();
+
+    for (node, op) in &borrows {
+        let lowered = match op {
+            JeffOp::QubitBorrow => allocate_reset(),
+            _ => free(),
+        };
+        replace_with_dfg(hugr, *node, lowered.expect("Failed to build the lowered borrow"));
+    }
+    borrows.len()
+}
+
+/// Allocate a qubit and reset it.
+fn allocate_reset() -> Result<Hugr, BuildError> {
+    let mut builder = DFGBuilder::new(Signature::new(vec![], vec![qb_t()]))?;
+    let qubit = builder.add_dataflow_op(QuantumOp::QAlloc, [])?.out_wire(0);
+    let qubit = builder.add_dataflow_op(QuantumOp::Reset, [qubit])?.out_wire(0);
+    builder.finish_hugr_with_outputs([qubit])
+}
+
+/// Free a qubit.
+fn free() -> Result<Hugr, BuildError> {
+    let mut builder = DFGBuilder::new(Signature::new(vec![qb_t()], vec![]))?;
+    let [qubit] = builder.input_wires_arr();
+    builder.add_dataflow_op(QuantumOp::QFree, [qubit])?;
+    builder.finish_hugr_with_outputs([])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::jeff_to_hugr;
+    use crate::test::borrowed_qubit;
+    use jeff::Jeff;
+    use rstest::rstest;
+
+    /// Borrow a qubit to entangle it with the input qubit, then return it.
+    fn borrow_use_return() -> Hugr {
+        let mut builder = DFGBuilder::new(Signature::new_endo(vec![qb_t()])).unwrap();
+        let [q] = builder.input_wires_arr();
+        let borrowed = builder
+            .add_dataflow_op(JeffOp::QubitBorrow.into_extension_op(), [])
+            .unwrap()
+            .out_wire(0);
+        let [q, borrowed] = builder
+            .add_dataflow_op(QuantumOp::CX, [q, borrowed])
+            .unwrap()
+            .outputs_arr();
+        builder
+            .add_dataflow_op(JeffOp::QubitReturn.into_extension_op(), [borrowed])
+            .unwrap();
+        builder.finish_hugr_with_outputs([q]).unwrap()
+    }
+
+    #[rstest]
+    fn lower_borrow() {
+        let mut hugr = borrow_use_return();
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        assert_eq!(lower_qubit_borrows(&mut hugr), 2);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        let ops = hugr
+            .nodes()
+            .filter_map(|node| QuantumOp::of(hugr.get_optype(node)))
+            .sorted_by_key(|op| format!("{op:?}"))
+            .collect_vec();
+        assert_eq!(
+            ops,
+            [QuantumOp::CX, QuantumOp::QAlloc, QuantumOp::QFree, QuantumOp::Reset]
+        );
+        assert!(hugr.nodes().all(|node| hugr.get_optype(node).cast::<JeffOp>().is_none()));
+        assert_eq!(lower_qubit_borrows(&mut hugr), 0);
+    }
+
+    #[rstest]
+    fn convert_and_lower(borrowed_qubit: Jeff<'static>) {
+        let mut hugr = jeff_to_hugr(&borrowed_qubit).unwrap();
+        let count = |hugr: &Hugr, expected: JeffOp| {
+            hugr.nodes()
+                .filter(|&node| hugr.get_optype(node).cast::<JeffOp>() == Some(expected.clone()))
+                .count()
+        };
+        assert_eq!(count(&hugr, JeffOp::QubitBorrow), 2);
+        assert_eq!(count(&hugr, JeffOp::QubitReturn), 3);
+
+        assert_eq!(lower_qubit_borrows(&mut hugr), 5);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        let resets = hugr
+            .nodes()
+            .filter(|&node| QuantumOp::of(hugr.get_optype(node)) == Some(QuantumOp::Reset))
+            .count();
+        assert_eq!(resets, 2);
+    }
+}
@@ -0,0 +1,140 @@
+//! Cancellation of adjacent self-inverse gates.
+
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::{HugrView, IncomingPort, Node, OutgoingPort};
+use itertools::Itertools;
+
+use crate::metadata;
+use crate::optype::tket_ops::QuantumOp;
+use crate::provenance::record_pass;
+
+/// Remove pairs of adjacent gates that cancel out.
+///
+/// A pair is a self-inverse gate, such as `H` or `CX`, whose outputs are all
+/// directly consumed, at the same ports, by an identical gate. Gates
+/// separated by a barrier do not form a pair.
+///
+/// Gates with order edges, the operations marked with the
+/// [`metadata::PROTECTED`] metadata and the contents of the protected
+/// containers are left unchanged, see [`crate::passes::protect_ops`].
+///
+/// Returns the number of removed pairs.
+pub fn cancel_inverse_gates(hugr: &mut impl HugrMut<Node = Node>) -> usize {
+    record_pass(hugr, "cancel_inverse_gates");
+    let mut cancelled = 0;
+    loop {
+        let Some((first, second)) = hugr.nodes().find_map(|node| inverse_pair(hugr, node)) else {
+            return cancelled;
+        };
+        cancel_pair(hugr, first, second);
+        cancelled += 1;
+    }
+}
+
+/// Returns the pair of gates starting at `node` if they cancel out.
+fn inverse_pair(hugr: &impl HugrView<Node = Node>, node: Node) -> Option<(Node, Node)> {
+    let gate = QuantumOp::of(hugr.get_optype(node)).filter(|gate| gate.is_self_inverse())?;
+    let (next, _) = hugr.single_linked_input(node, 0)?;
+    if QuantumOp::of(hugr.get_optype(next)) != Some(gate)
+        || is_protected(hugr, node)
+        || is_protected(hugr, next)
+        || has_order_edges(hugr, node)
+        || has_order_edges(hugr, next)
+    {
+        return None;
+    }
+    let qubits = hugr.signature(node)?.output_count();
+    (0..qubits)
+        .all(|port| hugr.single_linked_input(node, port) == Some((next, IncomingPort::from(port))))
+        .then_some((node, next))
+}
+
+/// Returns `true` if the node, or one of its ancestors, is protected.
+pub(super) fn is_protected(hugr: &impl HugrView<Node = Node>, node: Node) -> bool {
+    std::iter::successors(Some(node), |&n| hugr.get_parent(n)).any(|n| {
+        hugr.get_metadata(n, metadata::PROTECTED)
+            .is_some_and(|marker| *marker == true || *marker == "container")
+    })
+}
+
+/// Returns `true` if the node has incoming or outgoing order edges.
+pub(crate) fn has_order_edges(hugr: &impl HugrView<Node = Node>, node: Node) -> bool {
+    let optype = hugr.get_optype(node);
+    optype
+        .other_input_port()
+        .is_some_and(|port| hugr.is_linked(node, port))
+        || optype
+            .other_output_port()
+            .is_some_and(|port| hugr.is_linked(node, port))
+}
+
+/// Remove a pair of gates, connecting the qubits entering the first one to
+/// the consumers of the second one.
+fn cancel_pair(hugr: &mut impl HugrMut<Node = Node>, first: Node, second: Node) {
+    let qubits = hugr.signature(first).unwrap().input_count();
+    let rewires = (0..qubits)
+        .filter_map(|port| {
+            let src = hugr.single_linked_output(first, IncomingPort::from(port))?;
+            let targets = hugr
+                .linked_inputs(second, OutgoingPort::from(port))
+                .collect_vec();
+            Some((src, targets))
+        })
+        .collect_vec();
+
+    hugr.remove_node(first);
+    hugr.remove_node(second);
+
+    for ((src, src_port), targets) in rewires {
+        for (tgt, tgt_port) in targets {
+            hugr.connect(src, src_port, tgt, tgt_port);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr};
+    use hugr::extension::prelude::qb_t;
+    use hugr::types::Signature;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::hadamards(&[QuantumOp::H, QuantumOp::H], 1, 0)]
+    #[case::different(&[QuantumOp::H, QuantumOp::X], 0, 2)]
+    #[case::not_self_inverse(&[QuantumOp::S, QuantumOp::S], 0, 2)]
+    #[case::nested(&[QuantumOp::X, QuantumOp::H, QuantumOp::H, QuantumOp::X], 2, 0)]
+    fn cancel_single_qubit(
+        #[case] gates: &[QuantumOp],
+        #[case] cancelled: usize,
+        #[case] remaining: usize,
+    ) {
+        let mut builder = DFGBuilder::new(Signature::new_endo(vec![qb_t()])).unwrap();
+        let [mut q] = builder.input_wires_arr();
+        for &gate in gates {
+            [q] = builder.add_dataflow_op(gate, [q]).unwrap().outputs_arr();
+        }
+        let mut hugr = builder.finish_hugr_with_outputs([q]).unwrap();
+
+        assert_eq!(cancel_inverse_gates(&mut hugr), cancelled);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        let gate_count = hugr
+            .nodes()
+            .filter(|&n| QuantumOp::of(hugr.get_optype(n)).is_some())
+            .count();
+        assert_eq!(gate_count, remaining);
+    }
+
+    #[rstest]
+    fn cancel_swapped_cx() {
+        let mut builder = DFGBuilder::new(Signature::new_endo(vec![qb_t(), qb_t()])).unwrap();
+        let [a, b] = builder.input_wires_arr();
+        let [a, b] = builder.add_dataflow_op(QuantumOp::CX, [a, b]).unwrap().outputs_arr();
+        // The second CX has its control and target swapped.
+        let [b, a] = builder.add_dataflow_op(QuantumOp::CX, [b, a]).unwrap().outputs_arr();
+        let mut hugr = builder.finish_hugr_with_outputs([a, b]).unwrap();
+
+        assert_eq!(cancel_inverse_gates(&mut hugr), 0);
+    }
+}
@@ -0,0 +1,159 @@
+//! Boxing of straight-line quantum segments into circuit containers.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::mem;
+
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::{HugrView, Node};
+use itertools::Itertools;
+
+use super::protect::{enclose, is_convex};
+use crate::metadata;
+use crate::provenance::record_pass;
+
+/// Move the quantum segments marked by the translation into `DFG`
+/// containers, to be optimized as separate circuits.
+///
+/// The operations marked with the same [`metadata::CIRCUIT_BOX`] segment in
+/// a region are moved into a `DFG` container, which keeps the mark. The
+/// container reads the values entering the segment and produces the values
+/// leaving it, in the order of the operations using them. A segment that
+/// cannot be moved as a whole, as it feeds operations outside of it that
+/// feed it back, is split into several containers in node order.
+///
+/// The operations are marked when [`crate::JeffToHugrConfig::circuit_boxes`]
+/// is set, which runs this pass after the translation. The containers can
+/// be inlined back into their region with hugr's `InlineDFG`.
+///
+/// Returns the number of containers.
+pub fn box_circuit_segments(hugr: &mut impl HugrMut<Node = Node>) -> usize {
+    record_pass(hugr, "box_circuit_segments");
+    let mut segments: BTreeMap<(Node, u64), BTreeSet<Node>> = BTreeMap::new();
+    let marked = hugr
+        .nodes()
+        .filter(|&node| !hugr.get_optype(node).is_dfg())
+        .filter_map(|node| {
+            let segment = hugr.get_metadata(node, metadata::CIRCUIT_BOX)?.as_u64()?;
+            Some((hugr.get_parent(node)?, segment, node))
+        })
+        .collect_vec();
+    for (parent, segment, node) in marked {
+        segments.entry((parent, segment)).or_default().insert(node);
+    }
+
+    let mut boxes = 0;
+    for ((_, segment), nodes) in segments {
+        for piece in convex_pieces(hugr, &nodes) {
+            for &node in &piece {
+                hugr.remove_metadata(node, metadata::CIRCUIT_BOX);
+            }
+            let container = enclose(hugr, &piece);
+            hugr.set_metadata(container, metadata::CIRCUIT_BOX, segment);
+            boxes += 1;
+        }
+    }
+    boxes
+}
+
+/// Split a set of sibling operations into convex pieces, adding the
+/// operations to the current piece in node order.
+fn convex_pieces(
+    hugr: &impl HugrView<Node = Node>,
+    nodes: &BTreeSet<Node>,
+) -> Vec<BTreeSet<Node>> {
+    let mut pieces = Vec::new();
+    let mut piece = BTreeSet::new();
+    for &node in nodes {
+        piece.insert(node);
+        if !is_convex(hugr, &piece) {
+            piece.remove(&node);
+            pieces.push(mem::replace(&mut piece, BTreeSet::from([node])));
+        }
+    }
+    pieces.push(piece);
+    pieces
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::optype::tket_ops::QuantumOp;
+    use crate::test::{assert_hugr_equivalent, catalyst_tket_opt};
+    use crate::{JeffToHugrConfig, jeff_to_hugr, jeff_to_hugr_with_config};
+    use hugr::Hugr;
+    use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr};
+    use hugr::extension::prelude::{Barrier, qb_t};
+    use hugr::hugr::patch::inline_dfg::InlineDFG;
+    use hugr::types::Signature;
+    use jeff::Jeff;
+    use rstest::rstest;
+
+    /// The containers of the circuit segments.
+    fn boxes(hugr: &impl HugrView<Node = Node>) -> Vec<Node> {
+        hugr.nodes()
+            .filter(|&node| hugr.get_metadata(node, metadata::CIRCUIT_BOX).is_some())
+            .collect()
+    }
+
+    #[rstest]
+    fn box_catalyst_segments(catalyst_tket_opt: Jeff<'static>) {
+        let config = JeffToHugrConfig::default().with_circuit_boxes(true);
+        let mut hugr = jeff_to_hugr_with_config(&catalyst_tket_opt, &config).unwrap().hugr;
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        // The gates before the loop, and the gates and measurements after
+        // it. The swap in the loop is translated as a permutation of wires.
+        let containers = boxes(&hugr);
+        assert_eq!(containers.len(), 2);
+        let segments = containers
+            .iter()
+            .map(|&node| hugr.get_metadata(node, metadata::CIRCUIT_BOX).unwrap().clone())
+            .collect_vec();
+        assert_eq!(segments, [serde_json::json!(6), serde_json::json!(14)]);
+        for &container in &containers {
+            assert!(hugr.get_optype(container).is_dfg());
+            let (circuit, _) = hugr.extract_hugr(container);
+            circuit.validate().unwrap_or_else(|e| panic!("{e}"));
+        }
+
+        for container in containers {
+            hugr.apply_patch(InlineDFG(container.into())).unwrap();
+        }
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        assert_hugr_equivalent(&hugr, &jeff_to_hugr(&catalyst_tket_opt).unwrap());
+    }
+
+    /// Two `H` gates of a segment around a barrier, which is part of the
+    /// segment or not.
+    fn fenced_segment(barrier_in_segment: bool) -> Hugr {
+        let mut builder = DFGBuilder::new(Signature::new_endo(vec![qb_t()])).unwrap();
+        let [q] = builder.input_wires_arr();
+        let first = builder.add_dataflow_op(QuantumOp::H, [q]).unwrap();
+        let barrier = builder
+            .add_dataflow_op(Barrier::new(vec![qb_t()]), first.outputs())
+            .unwrap();
+        let second = builder.add_dataflow_op(QuantumOp::H, barrier.outputs()).unwrap();
+        let mut hugr = builder.finish_hugr_with_outputs(second.outputs()).unwrap();
+
+        let mut marked = vec![first.node(), second.node()];
+        if barrier_in_segment {
+            marked.push(barrier.node());
+        }
+        for node in marked {
+            hugr.set_metadata(node, metadata::CIRCUIT_BOX, 0);
+        }
+        hugr
+    }
+
+    #[rstest]
+    #[case::whole(true, 1)]
+    #[case::split(false, 2)]
+    fn split_segments(#[case] barrier_in_segment: bool, #[case] expected: usize) {
+        let mut hugr = fenced_segment(barrier_in_segment);
+        assert_eq!(box_circuit_segments(&mut hugr), expected);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(boxes(&hugr).len(), expected);
+        // The operations are only boxed once.
+        assert_eq!(box_circuit_segments(&mut hugr), 0);
+    }
+}
@@ -0,0 +1,362 @@
+//! Gates conditioned on a measurement outcome, for fast feedforward.
+
+use hugr::builder::{
+    BuildError, DFGBuilder, Dataflow, DataflowHugr, DataflowSubContainer, SubContainer,
+};
+use hugr::extension::prelude::qb_t;
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::ops::OpType;
+use hugr::{Hugr, HugrView, IncomingPort, Node, OutgoingPort, type_row};
+use itertools::Itertools;
+
+use super::measure::replace_with_dfg;
+use crate::extension::JeffOp;
+use crate::extension::signature::cond_gate_signature;
+use crate::optype::tket_ops::{self, QuantumOp};
+use crate::provenance::record_pass;
+
+/// A `Conditional` applying a single gate on a measurement outcome.
+struct CondGateMatch {
+    /// The conditional node.
+    conditional: Node,
+    /// The name of the `tket.quantum` gate applied in one of the cases.
+    gate: String,
+    /// The case applying the gate.
+    outcome: bool,
+    /// The conditional input forwarded to each qubit of the gate.
+    gate_ports: Vec<usize>,
+}
+
+/// Replace the `Conditional`s applying a single gate on a fresh measurement
+/// outcome by a [`JeffOp::CondGate`].
+///
+/// A conditional is replaced when:
+/// - its predicate is the outcome of a `Measure` or `MeasureFree` that is
+///   used nowhere else,
+/// - it has two cases, each returning its inputs in order,
+/// - one case applies a single `tket.quantum` gate acting only on qubits,
+///   and the other case applies nothing.
+///
+/// The inputs the gate does not act on are connected around the new
+/// operation. Other conditionals are left unchanged. The replacement is
+/// undone by [`lower_conditional_gates`].
+///
+/// Returns the number of replaced conditionals.
+pub fn fuse_conditional_gates(hugr: &mut impl HugrMut<Node = Node>) -> usize {
+    record_pass(hugr, "fuse_conditional_gates");
+    let matches = hugr
+        .nodes()
+        .filter_map(|node| match_cond_gate(hugr, node))
+        .collect_vec();
+    for m in &matches {
+        replace_conditional(hugr, m);
+    }
+    matches.len()
+}
+
+/// Replace the [`JeffOp::CondGate`] operations by a `Conditional` applying
+/// the gate in the case selected by the bit.
+///
+/// Each lowered operation is replaced by a nested dataflow graph. Operations
+/// naming an unknown gate are left unchanged.
+///
+/// Returns the number of lowered operations.
+pub fn lower_conditional_gates(hugr: &mut impl HugrMut<Node = Node>) -> usize {
+    record_pass(hugr, "lower_conditional_gates");
+    let cond_gates = hugr
+        .nodes()
+        .filter_map(|node| match hugr.get_optype(node).cast::<JeffOp>()? {
+            JeffOp::CondGate {
+                gate,
+                qubits,
+                outcome,
+            } => Some((node, tket_ops::tket_op_by_name(&gate)?, qubits, outcome)),
+            _ => None,
+        })
+        .collect_vec();
+
+    let lowered = cond_gates.len();
+    for (node, gate, qubits, outcome) in cond_gates {
+        let dfg = conditional_gate(gate, qubits, outcome)
+            .expect("Failed to build the conditional gate");
+        replace_with_dfg(hugr, node, dfg);
+    }
+    lowered
+}
+
+/// Check whether a node is a conditional that can be replaced by a [`JeffOp::CondGate`].
+fn match_cond_gate(hugr: &impl HugrView<Node = Node>, node: Node) -> Option<CondGateMatch> {
+    let OpType::Conditional(conditional) = hugr.get_optype(node) else {
+        return None;
+    };
+    if conditional.sum_rows.len() != 2
+        || conditional.sum_rows.iter().any(|row| !row.is_empty())
+        || conditional.other_inputs != conditional.outputs
+    {
+        return None;
+    }
+
+    // The predicate must be a measurement outcome used only here.
+    let (measure, port) = hugr.single_linked_output(node, 0)?;
+    if !matches!(
+        QuantumOp::of(hugr.get_optype(measure)),
+        Some(QuantumOp::Measure | QuantumOp::MeasureDestructive)
+    ) || hugr.linked_inputs(measure, port).count() != 1
+    {
+        return None;
+    }
+
+    let ports = conditional.outputs.len();
+    let [case0, case1] = hugr.children(node).collect_vec().try_into().ok()?;
+    let (gate, outcome, gate_ports) = match (
+        case_gate(hugr, case0, ports)?,
+        case_gate(hugr, case1, ports)?,
+    ) {
+        (None, Some((gate, ports))) => (gate, true, ports),
+        (Some((gate, ports)), None) => (gate, false, ports),
+        _ => return None,
+    };
+    Some(CondGateMatch {
+        conditional: node,
+        gate,
+        outcome,
+        gate_ports,
+    })
+}
+
+/// Returns the gate applied by a conditional case, with the case input
+/// forwarded to each of its qubits.
+///
+/// Returns `Some(None)` if the case returns its inputs unchanged, and `None`
+/// if the case does anything else.
+fn case_gate(
+    hugr: &impl HugrView<Node = Node>,
+    case: Node,
+    ports: usize,
+) -> Option<Option<(String, Vec<usize>)>> {
+    let [input, output] = hugr.get_io(case)?;
+    let gate = match hugr
+        .children(case)
+        .filter(|&n| n != input && n != output)
+        .collect_vec()
+        .as_slice()
+    {
+        [] => None,
+        &[gate] => Some(gate),
+        _ => return None,
+    };
+
+    let gate_op = match gate {
+        Some(gate) => {
+            let gate_op = tket_ops::tket_op_name(hugr.get_optype(gate))?;
+            let sig = hugr.get_optype(gate).dataflow_signature()?;
+            if sig.input_types() != sig.output_types()
+                || sig.input_types().iter().any(|ty| *ty != qb_t())
+            {
+                return None;
+            }
+            Some((gate, gate_op, sig.input_count()))
+        }
+        None => None,
+    };
+
+    // Each case input goes either straight to the same output, or through the gate.
+    let mut gate_ports = vec![None; gate_op.as_ref().map_or(0, |(_, _, qubits)| *qubits)];
+    for port in 0..ports {
+        let (target, target_port) = hugr.linked_inputs(input, port).exactly_one().ok()?;
+        match &gate_op {
+            _ if target == output && target_port.index() == port => {}
+            &Some((gate, _, _)) if target == gate => {
+                let (out, out_port) = hugr
+                    .linked_inputs(gate, target_port.index())
+                    .exactly_one()
+                    .ok()?;
+                if out != output || out_port.index() != port {
+                    return None;
+                }
+                gate_ports[target_port.index()] = Some(port);
+            }
+            _ => return None,
+        }
+    }
+
+    match gate_op {
+        Some((_, gate_op, _)) => {
+            let gate_ports = gate_ports.into_iter().collect::<Option<Vec<_>>>()?;
+            Some(Some((gate_op, gate_ports)))
+        }
+        None => Some(None),
+    }
+}
+
+/// Replace a matched conditional by a [`JeffOp::CondGate`].
+fn replace_conditional(hugr: &mut impl HugrMut<Node = Node>, m: &CondGateMatch) {
+    let cond = m.conditional;
+    let parent = hugr.get_parent(cond).unwrap();
+    let (measure, bit_port) = hugr.single_linked_output(cond, 0).unwrap();
+    let optype = hugr.get_optype(cond);
+    let ports = optype.dataflow_signature().unwrap().output_count();
+    let inputs = (0..ports)
+        .map(|i| hugr.single_linked_output(cond, i + 1))
+        .collect_vec();
+    let outputs = (0..ports)
+        .map(|i| hugr.linked_inputs(cond, i).collect_vec())
+        .collect_vec();
+    let predecessors = optype
+        .other_input_port()
+        .map(|port| hugr.linked_outputs(cond, port).map(|(n, _)| n).collect_vec())
+        .unwrap_or_default();
+    let successors = optype
+        .other_output_port()
+        .map(|port| hugr.linked_inputs(cond, port).map(|(n, _)| n).collect_vec())
+        .unwrap_or_default();
+
+    let op = JeffOp::CondGate {
+        gate: m.gate.clone(),
+        qubits: m.gate_ports.len(),
+        outcome: m.outcome,
+    };
+    let node = hugr.add_node_with_parent(parent, op.into_extension_op());
+    hugr.remove_subtree(cond);
+
+    hugr.connect(measure, bit_port, node, 0);
+    for (i, (input, targets)) in inputs.into_iter().zip(outputs).enumerate() {
+        let qubit = m.gate_ports.iter().position(|&p| p == i);
+        let (source, source_port): (Node, OutgoingPort) = match qubit {
+            Some(qubit) => {
+                if let Some((src, src_port)) = input {
+                    hugr.connect(src, src_port, node, IncomingPort::from(qubit + 1));
+                }
+                (node, OutgoingPort::from(qubit))
+            }
+            None => match input {
+                Some(input) => input,
+                None => continue,
+            },
+        };
+        for (target, target_port) in targets {
+            hugr.connect(source, source_port, target, target_port);
+        }
+    }
+    for pred in predecessors {
+        hugr.add_other_edge(pred, node);
+    }
+    for succ in successors {
+        hugr.add_other_edge(node, succ);
+    }
+}
+
+/// A `Conditional` applying `gate` to its qubits when the bit equals `outcome`.
+fn conditional_gate(gate: OpType, qubits: usize, outcome: bool) -> Result<Hugr, BuildError> {
+    let qubit_types = vec![qb_t(); qubits];
+    let mut builder = DFGBuilder::new(cond_gate_signature(qubits))?;
+    let mut inputs = builder.input_wires();
+    let bit = inputs.next().unwrap();
+
+    let mut cond = builder.conditional_builder(
+        ([type_row![], type_row![]], bit),
+        qubit_types.iter().cloned().zip(inputs),
+        qubit_types.into(),
+    )?;
+    for case in [false, true] {
+        let mut case_builder = cond.case_builder(case as usize)?;
+        let wires = case_builder.input_wires().collect_vec();
+        let outputs = match case == outcome {
+            true => case_builder.add_dataflow_op(gate.clone(), wires)?.outputs().collect_vec(),
+            false => wires,
+        };
+        case_builder.finish_with_outputs(outputs)?;
+    }
+    let outputs = cond.finish_sub_container()?.outputs();
+    builder.finish_hugr_with_outputs(outputs)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hugr::builder::{ConditionalBuilder, HugrBuilder};
+    use hugr::types::Signature;
+    use rstest::rstest;
+
+    /// Teleportation correction: measure the first qubit, and apply `gates`
+    /// to the second one in the case selected by the outcome.
+    ///
+    /// `gates[i]` are the gates applied in case `i`.
+    fn correction(gates: [&[QuantumOp]; 2]) -> Hugr {
+        let mut builder =
+            DFGBuilder::new(Signature::new(vec![qb_t(), qb_t()], vec![qb_t()])).unwrap();
+        let [control, target] = builder.input_wires_arr();
+        let bit = builder
+            .add_dataflow_op(QuantumOp::MeasureDestructive, [control])
+            .unwrap()
+            .out_wire(0);
+
+        let mut cond = ConditionalBuilder::new(
+            [type_row![], type_row![]],
+            vec![qb_t()],
+            vec![qb_t()],
+        )
+        .unwrap();
+        for (case, ops) in gates.into_iter().enumerate() {
+            let mut case_builder = cond.case_builder(case).unwrap();
+            let [mut qubit] = case_builder.input_wires_arr();
+            for &op in ops {
+                qubit = case_builder.add_dataflow_op(op, [qubit]).unwrap().out_wire(0);
+            }
+            case_builder.finish_with_outputs([qubit]).unwrap();
+        }
+        let cond = cond.finish_hugr().unwrap();
+        let cond = builder
+            .add_hugr_with_wires(cond, [bit, target])
+            .unwrap()
+            .outputs();
+        builder.finish_hugr_with_outputs(cond).unwrap()
+    }
+
+    fn count(hugr: &Hugr, pred: impl Fn(&OpType) -> bool) -> usize {
+        hugr.nodes().filter(|&n| pred(hugr.get_optype(n))).count()
+    }
+
+    fn is_cond_gate(op: &OpType) -> bool {
+        matches!(op.cast::<JeffOp>(), Some(JeffOp::CondGate { .. }))
+    }
+
+    #[rstest]
+    #[case::x_if_one([&[], &[QuantumOp::X]], Some(true))]
+    #[case::z_if_zero([&[QuantumOp::Z], &[]], Some(false))]
+    #[case::two_gates([&[], &[QuantumOp::X, QuantumOp::Z]], None)]
+    #[case::both_cases([&[QuantumOp::Z], &[QuantumOp::X]], None)]
+    fn fuse_and_lower(#[case] gates: [&[QuantumOp]; 2], #[case] outcome: Option<bool>) {
+        let mut hugr = correction(gates);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        let fused = fuse_conditional_gates(&mut hugr);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        let Some(outcome) = outcome else {
+            assert_eq!(fused, 0);
+            assert_eq!(count(&hugr, OpType::is_conditional), 1);
+            return;
+        };
+        assert_eq!(fused, 1);
+        assert_eq!(count(&hugr, OpType::is_conditional), 0);
+        let cond_gates = hugr
+            .nodes()
+            .filter_map(|n| hugr.get_optype(n).cast::<JeffOp>())
+            .collect_vec();
+        let gate = gates[outcome as usize][0];
+        assert_eq!(
+            cond_gates,
+            [JeffOp::CondGate {
+                gate: tket_ops::tket_op_name(&gate.into()).unwrap(),
+                qubits: 1,
+                outcome,
+            }]
+        );
+
+        assert_eq!(lower_conditional_gates(&mut hugr), 1);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(count(&hugr, is_cond_gate), 0);
+        assert_eq!(count(&hugr, OpType::is_conditional), 1);
+        assert_eq!(count(&hugr, |op| QuantumOp::of(op) == Some(gate)), 1);
+    }
+}
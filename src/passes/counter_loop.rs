@@ -0,0 +1,383 @@
+//! Recognition of the counted loops emitted as `DoWhile` loops by older producers.
+
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::ops::OpType;
+use hugr::std_extensions::arithmetic::int_ops::{ConcreteIntOp, IntOpDef};
+use hugr::std_extensions::arithmetic::int_types::ConstInt;
+use hugr::std_extensions::logic::LogicOp;
+use hugr::{HugrView, IncomingPort, Node, OutgoingPort};
+use itertools::Itertools;
+
+use crate::extension::JeffOp;
+use crate::metadata;
+use crate::provenance::record_pass;
+
+/// A `TailLoop` whose state holds a counter incremented by a constant step
+/// and compared with a loop-invariant bound.
+struct CounterLoop {
+    /// The `TailLoop` node.
+    node: Node,
+    /// The position of the counter in the loop state.
+    port: usize,
+    /// The increment of the counter at each iteration.
+    step: i64,
+    /// The number of iterations, if the counter starts and ends at constants.
+    trip_count: Option<u64>,
+}
+
+/// The loop-invariant value a counter is compared with.
+enum Bound {
+    /// A loaded constant.
+    Constant(ConstInt),
+    /// A value of the loop state returned unchanged by the body.
+    Invariant,
+}
+
+/// Annotate the loops translated from a _jeff_ `DoWhile` that count
+/// iterations with a manually incremented counter.
+///
+/// Producers that predate the _jeff_ `For` operation, such as older Catalyst
+/// versions, emit counted loops as a `DoWhile` whose state holds a counter.
+/// A loop is recognized when:
+/// - its body increments the counter with an `iadd` of a loaded constant,
+///   and otherwise only uses it as the index of register or array accesses,
+/// - its condition only compares the counter with `ilt` or `igt` against a
+///   loaded constant, or against a state value returned unchanged by the
+///   body, and returns the comparison,
+/// - the step moves the counter towards the bound,
+/// - the final counter is not used after the loop.
+///
+/// Recognized loops are annotated with [`metadata::INDUCTION_VAR`], and with
+/// [`metadata::TRIP_COUNT`] when the counter starts and ends at constants.
+///
+/// Returns the number of annotated loops.
+pub fn annotate_counter_loops(hugr: &mut impl HugrMut<Node = Node>) -> usize {
+    record_pass(hugr, "annotate_counter_loops");
+    let loops = hugr
+        .nodes()
+        .filter_map(|node| match_counter_loop(hugr, node))
+        .collect_vec();
+    for counter_loop in &loops {
+        let induction_var = serde_json::json!({
+            "port": counter_loop.port,
+            "step": counter_loop.step,
+        });
+        hugr.set_metadata(counter_loop.node, metadata::INDUCTION_VAR, induction_var);
+        if let Some(trip_count) = counter_loop.trip_count {
+            hugr.set_metadata(counter_loop.node, metadata::TRIP_COUNT, trip_count);
+        }
+    }
+    loops.len()
+}
+
+/// Find the counter of a `TailLoop` translated from a `DoWhile`.
+///
+/// The loop runs a body `DFG` on its state, then a condition `DFG` on the
+/// body outputs, and continues while the condition holds.
+fn match_counter_loop(hugr: &impl HugrView<Node = Node>, node: Node) -> Option<CounterLoop> {
+    let OpType::TailLoop(tail_loop) = hugr.get_optype(node) else {
+        return None;
+    };
+    if !tail_loop.just_inputs.is_empty() || !tail_loop.just_outputs.is_empty() {
+        return None;
+    }
+    let [input, output] = hugr.get_io(node)?;
+    let (not, _) = hugr.single_linked_output(output, 0)?;
+    if !matches!(hugr.get_optype(not).cast::<LogicOp>(), Some(LogicOp::Not)) {
+        return None;
+    }
+    let (condition, _) = hugr.single_linked_output(not, 0)?;
+    let (body, _) = hugr.single_linked_output(condition, 0)?;
+    if !hugr.get_optype(condition).is_dfg() || !hugr.get_optype(body).is_dfg() {
+        return None;
+    }
+    let state = tail_loop.rest.len();
+    let chained = (0..state).all(|port| {
+        hugr.single_linked_output(body, port) == Some((input, OutgoingPort::from(port)))
+            && hugr.single_linked_output(condition, port) == Some((body, OutgoingPort::from(port)))
+    });
+    if !chained {
+        return None;
+    }
+
+    (0..state).find_map(|port| {
+        let (step, bound, below, signed) = match_counter(hugr, node, body, condition, port)?;
+        let start = hugr
+            .single_linked_output(node, port)
+            .and_then(|(load, _)| constant_value(hugr, load));
+        let trip_count = match (start, bound) {
+            (Some(start), Bound::Constant(bound)) => {
+                let value = |c: &ConstInt| match signed {
+                    true => c.value_s() as i128,
+                    false => c.value_u() as i128,
+                };
+                let (distance, stride) = match below {
+                    true => (value(&bound) - value(&start), step as i128),
+                    false => (value(&start) - value(&bound), -(step as i128)),
+                };
+                // The body runs once before the condition is checked.
+                let iterations = (distance.max(0) as u128).div_ceil(stride as u128).max(1);
+                u64::try_from(iterations).ok()
+            }
+            _ => None,
+        };
+        Some(CounterLoop {
+            node,
+            port,
+            step,
+            trip_count,
+        })
+    })
+}
+
+/// Match the counter at a position of the state of a loop.
+///
+/// Returns its step, its bound, whether the loop continues while the counter
+/// is below the bound, and whether the comparison is signed.
+fn match_counter(
+    hugr: &impl HugrView<Node = Node>,
+    node: Node,
+    body: Node,
+    condition: Node,
+    port: usize,
+) -> Option<(i64, Bound, bool, bool)> {
+    // The counter must not escape the loop.
+    if hugr.linked_inputs(node, port).next().is_some() {
+        return None;
+    }
+    let [_, output] = hugr.get_io(node)?;
+    let (returned, returned_port) = hugr.single_linked_output(output, port + 1)?;
+    let threaded = returned == condition;
+    if !threaded && (returned, returned_port) != (body, OutgoingPort::from(port)) {
+        return None;
+    }
+
+    // The body increments the counter by a constant.
+    let [body_input, body_output] = hugr.get_io(body)?;
+    let counter = (body_input, OutgoingPort::from(port));
+    let (add, _) = hugr.single_linked_output(body_output, port)?;
+    if int_op(hugr, add)? != IntOpDef::iadd {
+        return None;
+    }
+    let step = match [0, 1].map(|p| hugr.single_linked_output(add, p)) {
+        [Some(lhs), Some((load, _))] if lhs == counter => constant_value(hugr, load)?,
+        [Some((load, _)), Some(rhs)] if rhs == counter => constant_value(hugr, load)?,
+        _ => return None,
+    };
+    let step = step.value_s();
+    let index_only = hugr
+        .linked_inputs(counter.0, counter.1)
+        .all(|(user, user_port)| user == add || is_index_use(hugr, user, user_port));
+    let next_uses = hugr.linked_inputs(body, port).all(|target| {
+        target == (condition, IncomingPort::from(port))
+            || (!threaded && target == (output, IncomingPort::from(port + 1)))
+    });
+    if !index_only || !next_uses {
+        return None;
+    }
+
+    // The condition compares the counter with a loop-invariant bound.
+    let [condition_input, condition_output] = hugr.get_io(condition)?;
+    let counter = (condition_input, OutgoingPort::from(port));
+    let (compare, _) = hugr.single_linked_output(condition_output, 0)?;
+    let (greater, signed) = match int_op(hugr, compare)? {
+        IntOpDef::ilt_s => (false, true),
+        IntOpDef::ilt_u => (false, false),
+        IntOpDef::igt_s => (true, true),
+        IntOpDef::igt_u => (true, false),
+        _ => return None,
+    };
+    let (counter_lhs, other) = match [0, 1].map(|p| hugr.single_linked_output(compare, p)) {
+        [Some(lhs), Some(rhs)] if lhs == counter => (true, rhs),
+        [Some(lhs), Some(rhs)] if rhs == counter => (false, lhs),
+        _ => return None,
+    };
+    let bound = match other {
+        (source, bound_port) if source == condition_input => {
+            let unchanged = hugr.single_linked_output(body_output, bound_port.index())
+                == Some((body_input, bound_port));
+            if bound_port.index() == port || !unchanged {
+                return None;
+            }
+            Bound::Invariant
+        }
+        (load, _) => Bound::Constant(constant_value(hugr, load)?),
+    };
+    let compared_only = hugr.linked_inputs(counter.0, counter.1).all(|target| {
+        target.0 == compare
+            || (threaded && target == (condition_output, IncomingPort::from(port + 1)))
+    });
+    if !compared_only {
+        return None;
+    }
+    if threaded && hugr.single_linked_output(condition_output, port + 1) != Some(counter) {
+        return None;
+    }
+
+    // The step moves the counter towards the bound.
+    let below = counter_lhs != greater;
+    if (below && step <= 0) || (!below && step >= 0) {
+        return None;
+    }
+    Some((step, bound, below, signed))
+}
+
+/// Whether an input port of a node is the index of a register or array access.
+fn is_index_use(hugr: &impl HugrView<Node = Node>, node: Node, port: IncomingPort) -> bool {
+    let index_port = match hugr.get_optype(node).cast::<JeffOp>() {
+        Some(
+            JeffOp::QuregExtractIndex
+            | JeffOp::IntArrayGet { .. }
+            | JeffOp::IntArraySet { .. }
+            | JeffOp::FloatArrayGet { .. },
+        ) => 1,
+        Some(JeffOp::QuregInsertIndex) => 2,
+        _ => return false,
+    };
+    port.index() == index_port
+}
+
+/// The definition of an integer operation.
+fn int_op(hugr: &impl HugrView<Node = Node>, node: Node) -> Option<IntOpDef> {
+    Some(hugr.get_optype(node).cast::<ConcreteIntOp>()?.def)
+}
+
+/// Returns the integer loaded by a node, if it loads a constant.
+fn constant_value(hugr: &impl HugrView<Node = Node>, node: Node) -> Option<ConstInt> {
+    if !hugr.get_optype(node).is_load_constant() {
+        return None;
+    }
+    let const_node = hugr.static_source(node)?;
+    let value = hugr.get_optype(const_node).as_const()?;
+    value.get_custom_value::<ConstInt>().cloned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr, DataflowSubContainer, SubContainer};
+    use hugr::extension::prelude::bool_t;
+    use hugr::std_extensions::arithmetic::int_types::int_type;
+    use hugr::types::Signature;
+    use hugr::{Hugr, type_row};
+    use rstest::rstest;
+
+    use crate::extension::intreg_type;
+
+    const LOG_WIDTH: u8 = 5;
+
+    /// A DFG reading an array at each value of a counter, in a `DoWhile`
+    /// loop translated from a _jeff_ program predating the `For` operation.
+    ///
+    /// The counter starts at `start`, is incremented by `step` and the loop
+    /// continues while it is below `bound`. If `escape`, the final counter is
+    /// returned after the loop.
+    fn counter_loop(start: i64, step: i64, bound: i64, escape: bool) -> Hugr {
+        let int_t = int_type(LOG_WIDTH);
+        let array_t = intreg_type(32);
+        let outputs = match escape {
+            true => vec![array_t.clone(), int_t.clone()],
+            false => vec![array_t.clone()],
+        };
+        let mut builder = DFGBuilder::new(Signature::new(vec![array_t.clone()], outputs)).unwrap();
+        let [array] = builder.input_wires_arr();
+        let start = builder.add_load_value(ConstInt::new_s(LOG_WIDTH, start).unwrap());
+
+        let mut tail_loop = builder
+            .tail_loop_builder(
+                [],
+                [(int_t.clone(), start), (array_t.clone(), array)],
+                type_row![],
+            )
+            .unwrap();
+        let state = vec![int_t.clone(), array_t.clone()];
+        let mut body = tail_loop
+            .dfg_builder(Signature::new_endo(state.clone()), tail_loop.input_wires())
+            .unwrap();
+        let [counter, array] = body.input_wires_arr();
+        body.add_dataflow_op(JeffOp::IntArrayGet { bits: 32 }.into_extension_op(), [array, counter])
+            .unwrap();
+        let step = body.add_load_value(ConstInt::new_s(LOG_WIDTH, step).unwrap());
+        let next = body
+            .add_dataflow_op(IntOpDef::iadd.with_log_width(LOG_WIDTH), [counter, step])
+            .unwrap()
+            .out_wire(0);
+        let body = body.finish_with_outputs([next, array]).unwrap();
+
+        let mut condition = tail_loop
+            .dfg_builder(Signature::new(state, vec![bool_t()]), body.outputs())
+            .unwrap();
+        let [counter, _] = condition.input_wires_arr();
+        let bound = condition.add_load_value(ConstInt::new_s(LOG_WIDTH, bound).unwrap());
+        let below = condition
+            .add_dataflow_op(IntOpDef::ilt_s.with_log_width(LOG_WIDTH), [counter, bound])
+            .unwrap()
+            .out_wire(0);
+        let condition = condition.finish_with_outputs([below]).unwrap();
+        let continue_flag = tail_loop
+            .add_dataflow_op(LogicOp::Not, [condition.out_wire(0)])
+            .unwrap()
+            .out_wire(0);
+        let [counter, array] = tail_loop
+            .finish_with_outputs(continue_flag, body.outputs())
+            .unwrap()
+            .outputs_arr();
+
+        let outputs = match escape {
+            true => vec![array, counter],
+            false => vec![array],
+        };
+        builder.finish_hugr_with_outputs(outputs).unwrap()
+    }
+
+    /// The `TailLoop` node of a HUGR.
+    fn tail_loop(hugr: &Hugr) -> Node {
+        hugr.nodes()
+            .filter(|&n| hugr.get_optype(n).is_tail_loop())
+            .exactly_one()
+            .ok()
+            .unwrap()
+    }
+
+    #[rstest]
+    #[case::ascending(0, 1, 4, 4)]
+    #[case::strided(1, 3, 8, 3)]
+    #[case::runs_once(5, 1, 2, 1)]
+    fn recognize_counter(
+        #[case] start: i64,
+        #[case] step: i64,
+        #[case] bound: i64,
+        #[case] trip_count: u64,
+    ) {
+        let mut hugr = counter_loop(start, step, bound, false);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        assert_eq!(annotate_counter_loops(&mut hugr), 1);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        let node = tail_loop(&hugr);
+        assert_eq!(
+            hugr.get_metadata(node, metadata::INDUCTION_VAR),
+            Some(&serde_json::json!({"port": 0, "step": step}))
+        );
+        assert_eq!(
+            hugr.get_metadata(node, metadata::TRIP_COUNT),
+            Some(&serde_json::json!(trip_count))
+        );
+    }
+
+    #[rstest]
+    #[case::escaping_counter(0, 1, 4, true)]
+    #[case::diverging_step(0, -1, 4, false)]
+    fn ignore_loop(
+        #[case] start: i64,
+        #[case] step: i64,
+        #[case] bound: i64,
+        #[case] escape: bool,
+    ) {
+        let mut hugr = counter_loop(start, step, bound, escape);
+        assert_eq!(annotate_counter_loops(&mut hugr), 0);
+        let node = tail_loop(&hugr);
+        assert_eq!(hugr.get_metadata(node, metadata::INDUCTION_VAR), None);
+        assert_eq!(hugr.get_metadata(node, metadata::TRIP_COUNT), None);
+    }
+}
@@ -0,0 +1,118 @@
+//! Repair of the qubits asserted to be in the |0⟩ state when freed.
+
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::{HugrView, IncomingPort, Node};
+use serde::Serialize;
+
+use crate::analysis::check_free_zero;
+use crate::metadata;
+use crate::optype::tket_ops;
+use crate::provenance::record_pass;
+
+/// How [`repair_free_zero`] handles the _jeff_ `FreeZero` operations whose
+/// qubit is not known to be in the |0⟩ state.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum FreeZeroRepair {
+    /// Reset the qubit before freeing it, which keeps the |0⟩ guarantee.
+    #[default]
+    InsertReset,
+    /// Remove the [`metadata::FREE_ZERO`] mark, turning the operation into a
+    /// plain `QFree`.
+    Downgrade,
+    /// Leave the program unchanged. The translation fails with
+    /// [`crate::JeffToHugrError::UnverifiedFreeZero`] instead, see
+    /// [`crate::JeffToHugrConfig::free_zero_repair`].
+    Error,
+}
+
+/// Repair the _jeff_ `FreeZero` operations reported by
+/// [`crate::analysis::check_free_zero`].
+///
+/// Returns the number of repaired operations.
+pub fn repair_free_zero(hugr: &mut impl HugrMut<Node = Node>, repair: FreeZeroRepair) -> usize {
+    record_pass(hugr, "repair_free_zero");
+    let unverified = check_free_zero(hugr);
+    match repair {
+        FreeZeroRepair::InsertReset => {
+            for free in &unverified {
+                insert_reset(hugr, free.free);
+            }
+        }
+        FreeZeroRepair::Downgrade => {
+            for free in &unverified {
+                hugr.remove_metadata(free.free, metadata::FREE_ZERO);
+            }
+        }
+        FreeZeroRepair::Error => return 0,
+    }
+    unverified.len()
+}
+
+/// Add a `Reset` on the qubit wire entering `free`.
+fn insert_reset(hugr: &mut impl HugrMut<Node = Node>, free: Node) {
+    let parent = hugr.get_parent(free).unwrap();
+    let (src, src_port) = hugr.single_linked_output(free, 0).unwrap();
+    let reset = hugr.add_node_with_parent(parent, tket_ops::reset());
+    hugr.disconnect(free, IncomingPort::from(0));
+    hugr.connect(src, src_port, reset, 0);
+    hugr.connect(reset, 0, free, 0);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::optype::tket_ops::QuantumOp;
+    use crate::test::{free_zero_entangled, free_zero_input, free_zero_verified};
+    use hugr::Hugr;
+    use rstest::rstest;
+
+    /// The `QFree` nodes marked with [`metadata::FREE_ZERO`].
+    fn marked_frees(hugr: &impl HugrView<Node = Node>) -> usize {
+        hugr.nodes()
+            .filter(|&node| hugr.get_metadata(node, metadata::FREE_ZERO).is_some())
+            .count()
+    }
+
+    fn count_op(hugr: &impl HugrView<Node = Node>, op: QuantumOp) -> usize {
+        hugr.nodes()
+            .filter(|&n| QuantumOp::of(hugr.get_optype(n)) == Some(op))
+            .count()
+    }
+
+    #[rstest]
+    #[case::verified(free_zero_verified(), 0)]
+    #[case::entangled(free_zero_entangled(), 1)]
+    #[case::input(free_zero_input(), 1)]
+    fn insert_resets(#[case] mut hugr: Hugr, #[case] repaired: usize) {
+        assert_eq!(repair_free_zero(&mut hugr, FreeZeroRepair::InsertReset), repaired);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(count_op(&hugr, QuantumOp::Reset), repaired);
+        // The repaired frees keep their mark, and are verified.
+        assert_eq!(marked_frees(&hugr), 1);
+        assert!(check_free_zero(&hugr).is_empty());
+        assert_eq!(repair_free_zero(&mut hugr, FreeZeroRepair::InsertReset), 0);
+    }
+
+    #[rstest]
+    #[case::verified(free_zero_verified(), 0)]
+    #[case::entangled(free_zero_entangled(), 1)]
+    #[case::input(free_zero_input(), 1)]
+    fn downgrade(#[case] mut hugr: Hugr, #[case] repaired: usize) {
+        assert_eq!(repair_free_zero(&mut hugr, FreeZeroRepair::Downgrade), repaired);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(count_op(&hugr, QuantumOp::Reset), 0);
+        assert_eq!(count_op(&hugr, QuantumOp::QFree), 1);
+        assert_eq!(marked_frees(&hugr), 1 - repaired);
+        assert!(check_free_zero(&hugr).is_empty());
+    }
+
+    #[rstest]
+    fn error_unchanged(free_zero_input: Hugr) {
+        let mut hugr = free_zero_input;
+        assert_eq!(repair_free_zero(&mut hugr, FreeZeroRepair::Error), 0);
+        assert_eq!(count_op(&hugr, QuantumOp::Reset), 0);
+        assert_eq!(check_free_zero(&hugr).len(), 1);
+    }
+}
@@ -0,0 +1,566 @@
+//! Fusion of runs of single-qubit gates.
+
+use std::f64::consts::PI;
+use std::ops::{Add, Mul, Sub};
+
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::ops::{Const, LoadConstant, Value};
+use hugr::{HugrView, Node};
+use itertools::Itertools;
+use tket::extension::rotation::{ConstRotation, rotation_type};
+
+use super::cancel::{has_order_edges, is_protected};
+use crate::metadata;
+use crate::optype::tket_ops::QuantumOp;
+use crate::provenance::record_pass;
+
+/// Tolerance of the matrix and angle comparisons.
+const EPSILON: f64 = 1e-9;
+
+/// Replace the runs of single-qubit gates on a qubit wire by at most three
+/// rotations, `Rz·Rx·Rz`.
+///
+/// A run is a sequence of `tket.quantum` single-qubit gates, such as `H`,
+/// `T` or `Rz`, each consuming the qubit produced by the previous one.
+/// Rotations are only fused when their angle is a loaded constant. Runs end
+/// at the other operations, such as multi-qubit gates, measurements and
+/// control flow, and at the gates with order edges, such as those ordered
+/// after a global phase. The operations marked with the
+/// [`metadata::PROTECTED`] metadata and the contents of the protected
+/// containers are left unchanged, see [`crate::passes::protect_ops`].
+///
+/// The fused rotations implement the unitary of the run up to a global
+/// phase. When all the angles of the run are exact fractions of half-turns,
+/// see [`metadata::EXACT_ANGLE`], the fused angles that are exact fractions
+/// with the same denominators are recorded as such. A run is only replaced
+/// when the fused sequence is shorter.
+///
+/// The translation runs this pass when
+/// [`crate::JeffToHugrConfig::fuse_single_qubit_gates`] is set.
+///
+/// Returns the number of fused runs.
+pub fn fuse_single_qubit_gates(hugr: &mut impl HugrMut<Node = Node>) -> usize {
+    record_pass(hugr, "fuse_single_qubit_gates");
+    let runs = hugr
+        .nodes()
+        .filter(|&node| fusable_gate(hugr, node).is_some())
+        .filter(|&node| {
+            hugr.single_linked_output(node, 0)
+                .is_none_or(|(prev, _)| fusable_gate(hugr, prev).is_none())
+        })
+        .map(|start| {
+            std::iter::successors(fusable_gate(hugr, start), |gate| {
+                let (next, _) = hugr.single_linked_input(gate.node, 0)?;
+                fusable_gate(hugr, next)
+            })
+            .collect_vec()
+        })
+        .collect_vec();
+
+    let mut fused = 0;
+    for run in runs {
+        let rotations = fuse(&run);
+        if rotations.len() < run.len() {
+            replace_run(hugr, &run, &rotations);
+            fused += 1;
+        }
+    }
+    fused
+}
+
+/// A single-qubit gate that can be fused, with its constant angle if any.
+#[derive(Debug, Clone, Copy)]
+struct FusableGate {
+    node: Node,
+    op: QuantumOp,
+    /// The angle of a rotation in half-turns, and the node loading it.
+    angle: Option<(f64, Node)>,
+    /// The denominator of the angle as an exact fraction of half-turns, or
+    /// `None` if it is not known to be exact.
+    denominator: Option<u64>,
+}
+
+/// A rotation emitted for a fused run.
+#[derive(Debug, Clone, Copy)]
+struct Rotation {
+    op: QuantumOp,
+    half_turns: f64,
+    /// The angle as an exact `(k, d)` fraction of half-turns.
+    exact: Option<(u64, u64)>,
+}
+
+/// Returns the gate at `node` if it can be part of a fused run.
+fn fusable_gate(hugr: &impl HugrView<Node = Node>, node: Node) -> Option<FusableGate> {
+    let op = QuantumOp::of(hugr.get_optype(node))?;
+    let (angle, denominator) = match op {
+        QuantumOp::H | QuantumOp::S | QuantumOp::Sdg => (None, Some(2)),
+        QuantumOp::X | QuantumOp::Y | QuantumOp::Z => (None, Some(1)),
+        QuantumOp::T | QuantumOp::Tdg => (None, Some(4)),
+        QuantumOp::Rx | QuantumOp::Ry | QuantumOp::Rz => {
+            let angle = constant_angle(hugr, node)?;
+            let denominator = hugr
+                .get_metadata(node, metadata::EXACT_ANGLE)
+                .and_then(|exact| exact.as_str()?.split_once('/')?.1.parse().ok());
+            (Some(angle), denominator)
+        }
+        _ => return None,
+    };
+    if is_protected(hugr, node) || has_order_edges(hugr, node) {
+        return None;
+    }
+    Some(FusableGate {
+        node,
+        op,
+        angle,
+        denominator,
+    })
+}
+
+/// Returns the angle of a rotation in half-turns and the node loading it,
+/// if it is a loaded constant.
+pub(crate) fn constant_angle(
+    hugr: &impl HugrView<Node = Node>,
+    node: Node,
+) -> Option<(f64, Node)> {
+    let (load, _) = hugr.single_linked_output(node, 1)?;
+    if !hugr.get_optype(load).is_load_constant() {
+        return None;
+    }
+    let const_node = hugr.static_source(load)?;
+    let value = hugr.get_optype(const_node).as_const()?;
+    Some((value.get_custom_value::<ConstRotation>()?.half_turns(), load))
+}
+
+/// The rotations implementing a run of gates, up to a global phase.
+fn fuse(gates: &[FusableGate]) -> Vec<Rotation> {
+    let unitary = gates.iter().fold(Unitary::IDENTITY, |unitary, gate| {
+        gate_unitary(gate.op, gate.angle.map(|(half_turns, _)| half_turns)).unwrap() * unitary
+    });
+    // The common denominator of the exact angles of the run.
+    let denominator = gates
+        .iter()
+        .map(|gate| gate.denominator)
+        .try_fold(1, |common, d| Some(common.max(d?)));
+    let rotation = |op, half_turns: f64| {
+        let exact = denominator.and_then(|d| {
+            let scaled = half_turns * d as f64;
+            let k = scaled.round();
+            let k_mod = k.rem_euclid(2.0 * d as f64) as u64;
+            ((scaled - k).abs() < EPSILON).then_some((k_mod, d))
+        });
+        let half_turns = exact.map_or(half_turns.rem_euclid(2.0), |(k, d)| k as f64 / d as f64);
+        Rotation {
+            op,
+            half_turns,
+            exact: exact.map(reduce_fraction),
+        }
+    };
+
+    let [first, middle, last] = unitary.zxz_angles();
+    let rotations = if is_zero_angle(middle) {
+        vec![rotation(QuantumOp::Rz, first + last)]
+    } else {
+        vec![
+            rotation(QuantumOp::Rz, first),
+            rotation(QuantumOp::Rx, middle),
+            rotation(QuantumOp::Rz, last),
+        ]
+    };
+    let rotations = rotations
+        .into_iter()
+        .filter(|rotation| !is_zero_angle(rotation.half_turns))
+        .collect_vec();
+    debug_assert!({
+        let fused = rotations.iter().fold(Unitary::IDENTITY, |unitary, rotation| {
+            gate_unitary(rotation.op, Some(rotation.half_turns)).unwrap() * unitary
+        });
+        fused.equivalent(&unitary)
+    });
+    rotations
+}
+
+/// Returns `true` if a rotation by the angle is the identity, up to a
+/// global phase.
+fn is_zero_angle(half_turns: f64) -> bool {
+    let angle = half_turns.rem_euclid(2.0);
+    angle.min(2.0 - angle) < EPSILON
+}
+
+/// Reduce a fraction whose denominator is a power of two.
+fn reduce_fraction((mut k, mut d): (u64, u64)) -> (u64, u64) {
+    while k % 2 == 0 && d % 2 == 0 && d > 1 {
+        k /= 2;
+        d /= 2;
+    }
+    (k, d)
+}
+
+/// Replace a run of gates by a sequence of rotations.
+///
+/// The rotations inherit the [`metadata::OP_INDEX`] and
+/// [`metadata::CIRCUIT_BOX`] of the first gate of the run.
+fn replace_run(
+    hugr: &mut impl HugrMut<Node = Node>,
+    run: &[FusableGate],
+    rotations: &[Rotation],
+) {
+    let (first, last) = (run[0], run[run.len() - 1]);
+    let parent = hugr.get_parent(first.node).unwrap();
+    let (mut src, mut src_port) = hugr.single_linked_output(first.node, 0).unwrap();
+    let targets = hugr.linked_inputs(last.node, 0).collect_vec();
+    let inherited = [metadata::OP_INDEX, metadata::CIRCUIT_BOX]
+        .into_iter()
+        .filter_map(|key| Some((key, hugr.get_metadata(first.node, key)?.clone())))
+        .collect_vec();
+
+    for gate in run {
+        hugr.remove_node(gate.node);
+        if let Some((_, load)) = gate.angle {
+            remove_unused_constant(hugr, load);
+        }
+    }
+
+    for rotation in rotations {
+        let node = hugr.add_node_with_parent(parent, rotation.op);
+        let value = Value::extension(ConstRotation::new(rotation.half_turns).unwrap());
+        let load = hugr.add_node_with_parent(
+            parent,
+            LoadConstant {
+                datatype: rotation_type(),
+            },
+        );
+        let const_node = hugr.add_node_with_parent(parent, Const::new(value));
+        hugr.connect(const_node, 0, load, 0);
+        hugr.connect(load, 0, node, 1);
+        hugr.connect(src, src_port, node, 0);
+        (src, src_port) = (node, 0.into());
+
+        for (key, value) in &inherited {
+            hugr.set_metadata(node, *key, value.clone());
+        }
+        if let Some((k, d)) = rotation.exact {
+            hugr.set_metadata(node, metadata::EXACT_ANGLE, format!("{k}/{d}"));
+        }
+    }
+    for (tgt, tgt_port) in targets {
+        hugr.connect(src, src_port, tgt, tgt_port);
+    }
+}
+
+/// Remove a `LoadConstant` node left without consumers, and its `Const` if
+/// it is not loaded elsewhere.
+pub(crate) fn remove_unused_constant(hugr: &mut impl HugrMut<Node = Node>, load: Node) {
+    if !hugr.get_optype(load).is_load_constant() || hugr.linked_inputs(load, 0).next().is_some() {
+        return;
+    }
+    let const_node = hugr.static_source(load);
+    hugr.remove_node(load);
+    if let Some(const_node) = const_node
+        && hugr.linked_inputs(const_node, 0).next().is_none()
+    {
+        hugr.remove_node(const_node);
+    }
+}
+
+/// The unitary of a single-qubit gate, given the angle of rotations in
+/// half-turns.
+///
+/// Returns `None` for the operations that are not single-qubit gates, and
+/// for rotations without an angle.
+pub(crate) fn gate_unitary(op: QuantumOp, half_turns: Option<f64>) -> Option<Unitary> {
+    let c = Complex::new;
+    let (zero, one, i) = (c(0.0, 0.0), c(1.0, 0.0), c(0.0, 1.0));
+    let phase = |half_turns: f64| Complex::from_phase(PI * half_turns);
+    let h = std::f64::consts::FRAC_1_SQRT_2;
+    Some(Unitary(match op {
+        QuantumOp::H => [[c(h, 0.0), c(h, 0.0)], [c(h, 0.0), c(-h, 0.0)]],
+        QuantumOp::X => [[zero, one], [one, zero]],
+        QuantumOp::Y => [[zero, c(0.0, -1.0)], [i, zero]],
+        QuantumOp::Z => [[one, zero], [zero, c(-1.0, 0.0)]],
+        QuantumOp::S => [[one, zero], [zero, i]],
+        QuantumOp::Sdg => [[one, zero], [zero, c(0.0, -1.0)]],
+        QuantumOp::T => [[one, zero], [zero, phase(0.25)]],
+        QuantumOp::Tdg => [[one, zero], [zero, phase(-0.25)]],
+        QuantumOp::Rx | QuantumOp::Ry | QuantumOp::Rz => {
+            let theta = PI * half_turns? / 2.0;
+            let (sin, cos) = theta.sin_cos();
+            match op {
+                QuantumOp::Rx => [[c(cos, 0.0), c(0.0, -sin)], [c(0.0, -sin), c(cos, 0.0)]],
+                QuantumOp::Ry => [[c(cos, 0.0), c(-sin, 0.0)], [c(sin, 0.0), c(cos, 0.0)]],
+                _ => [[phase(-half_turns? / 2.0), zero], [zero, phase(half_turns? / 2.0)]],
+            }
+        }
+        _ => return None,
+    }))
+}
+
+/// A complex number.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Complex {
+    re: f64,
+    im: f64,
+}
+
+impl Complex {
+    const fn new(re: f64, im: f64) -> Self {
+        Self { re, im }
+    }
+
+    /// The complex number of modulus one and the given argument.
+    fn from_phase(arg: f64) -> Self {
+        let (sin, cos) = arg.sin_cos();
+        Self::new(cos, sin)
+    }
+
+    fn conj(self) -> Self {
+        Self::new(self.re, -self.im)
+    }
+
+    fn norm(self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    fn arg(self) -> f64 {
+        self.im.atan2(self.re)
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Self::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+}
+
+/// The unitary matrix of a single-qubit operation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Unitary([[Complex; 2]; 2]);
+
+impl Unitary {
+    /// The identity matrix.
+    pub(crate) const IDENTITY: Self = Self([
+        [Complex::new(1.0, 0.0), Complex::new(0.0, 0.0)],
+        [Complex::new(0.0, 0.0), Complex::new(1.0, 0.0)],
+    ]);
+
+    /// Returns `true` if the matrices are equal up to a global phase.
+    pub(crate) fn equivalent(&self, other: &Self) -> bool {
+        let entries = || self.0.iter().flatten().zip(other.0.iter().flatten());
+        let Some((&a, &b)) = entries().max_by(|(_, x), (_, y)| x.norm().total_cmp(&y.norm()))
+        else {
+            return true;
+        };
+        if (a.norm() - b.norm()).abs() > EPSILON {
+            return false;
+        }
+        let phase = Complex::from_phase(a.arg() - b.arg());
+        entries().all(|(&a, &b)| (a - phase * b).norm() < EPSILON)
+    }
+
+    /// The angles in half-turns of the rotations `Rz`, `Rx` and `Rz`, in
+    /// circuit order, implementing the matrix up to a global phase.
+    fn zxz_angles(&self) -> [f64; 3] {
+        let [[u00, u01], [u10, u11]] = self.0;
+        // Remove the global phase, so that the determinant is one.
+        let det = u00 * u11 - u01 * u10;
+        let phase = Complex::from_phase(det.arg() / 2.0).conj();
+        let (v00, v10) = (u00 * phase, u10 * phase);
+
+        // `Rz(a)·Rx(b)·Rz(c)` has `cos(b/2)·exp(-i(a+c)/2)` on its diagonal
+        // and `-i·sin(b/2)·exp(i(a-c)/2)` below it, with angles in radians.
+        let half_middle = v10.norm().atan2(v00.norm());
+        let sum = if v00.norm() > EPSILON { -v00.arg() } else { 0.0 };
+        let diff = if v10.norm() > EPSILON { v10.arg() + PI / 2.0 } else { 0.0 };
+        let to_half_turns = |half_angle: f64| 2.0 * half_angle / PI;
+        [
+            to_half_turns((sum - diff) / 2.0),
+            to_half_turns(half_middle),
+            to_half_turns((sum + diff) / 2.0),
+        ]
+    }
+}
+
+impl Mul for Unitary {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        let entry = |row: usize, col: usize| {
+            self.0[row][0] * other.0[0][col] + self.0[row][1] * other.0[1][col]
+        };
+        Self([[entry(0, 0), entry(0, 1)], [entry(1, 0), entry(1, 1)]])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{assert_unitary_equivalent, catalyst_tket_opt};
+    use crate::{JeffToHugrConfig, jeff_to_hugr, jeff_to_hugr_with_config};
+    use hugr::Hugr;
+    use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr};
+    use hugr::extension::prelude::qb_t;
+    use hugr::types::Signature;
+    use jeff::Jeff;
+    use rstest::rstest;
+
+    /// A DFG applying a sequence of single-qubit gates, with the angles of
+    /// the rotations in half-turns.
+    fn circuit(gates: &[(QuantumOp, Option<f64>)]) -> Hugr {
+        let mut builder = DFGBuilder::new(Signature::new_endo(vec![qb_t()])).unwrap();
+        let [mut q] = builder.input_wires_arr();
+        for &(gate, half_turns) in gates {
+            let mut inputs = vec![q];
+            if let Some(half_turns) = half_turns {
+                inputs.push(builder.add_load_value(ConstRotation::new(half_turns).unwrap()));
+            }
+            [q] = builder.add_dataflow_op(gate, inputs).unwrap().outputs_arr();
+        }
+        builder.finish_hugr_with_outputs([q]).unwrap()
+    }
+
+    fn gates(hugr: &Hugr) -> Vec<Node> {
+        hugr.nodes()
+            .filter(|&n| QuantumOp::of(hugr.get_optype(n)).is_some())
+            .collect()
+    }
+
+    #[rstest]
+    #[case::hadamards(&[(QuantumOp::H, None), (QuantumOp::H, None)], 1, 0)]
+    #[case::single(&[(QuantumOp::H, None)], 0, 1)]
+    #[case::phases(&[(QuantumOp::T, None), (QuantumOp::T, None), (QuantumOp::S, None)], 1, 1)]
+    #[case::clifford_t(&[
+        (QuantumOp::H, None),
+        (QuantumOp::T, None),
+        (QuantumOp::H, None),
+        (QuantumOp::S, None),
+    ], 1, 2)]
+    #[case::rotations(&[(QuantumOp::Rx, Some(0.3)), (QuantumOp::Rx, Some(-0.3))], 1, 0)]
+    fn fuse_runs(
+        #[case] sequence: &[(QuantumOp, Option<f64>)],
+        #[case] runs: usize,
+        #[case] remaining: usize,
+    ) {
+        let original = circuit(sequence);
+        let mut hugr = original.clone();
+        assert_eq!(fuse_single_qubit_gates(&mut hugr), runs);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(gates(&hugr).len(), remaining);
+        assert_unitary_equivalent(&original, &hugr);
+    }
+
+    /// Phase gates fuse into a rotation by an exact angle.
+    #[rstest]
+    fn exact_angles() {
+        let mut hugr = circuit(&[(QuantumOp::T, None), (QuantumOp::S, None)]);
+        fuse_single_qubit_gates(&mut hugr);
+        let [rz] = gates(&hugr).try_into().unwrap();
+        assert_eq!(QuantumOp::of(hugr.get_optype(rz)), Some(QuantumOp::Rz));
+        assert_eq!(constant_angle(&hugr, rz).map(|(angle, _)| angle), Some(0.75));
+        assert_eq!(
+            hugr.get_metadata(rz, metadata::EXACT_ANGLE),
+            Some(&serde_json::json!("3/4"))
+        );
+    }
+
+    /// Randomized sequences implement the same unitary once fused.
+    #[rstest]
+    fn fuse_random_runs() {
+        const OPS: [QuantumOp; 11] = [
+            QuantumOp::H,
+            QuantumOp::X,
+            QuantumOp::Y,
+            QuantumOp::Z,
+            QuantumOp::S,
+            QuantumOp::Sdg,
+            QuantumOp::T,
+            QuantumOp::Tdg,
+            QuantumOp::Rx,
+            QuantumOp::Ry,
+            QuantumOp::Rz,
+        ];
+        // A xorshift generator, for reproducible sequences.
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for _ in 0..50 {
+            let length = 2 + next() as usize % 8;
+            let sequence = (0..length)
+                .map(|_| {
+                    let op = OPS[next() as usize % OPS.len()];
+                    let angle = (next() % 4000) as f64 / 1000.0 - 2.0;
+                    let is_rotation = matches!(op, QuantumOp::Rx | QuantumOp::Ry | QuantumOp::Rz);
+                    (op, is_rotation.then_some(angle))
+                })
+                .collect_vec();
+            let original = circuit(&sequence);
+            let mut hugr = original.clone();
+            fuse_single_qubit_gates(&mut hugr);
+            hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+            assert!(gates(&hugr).len() <= 3.min(length), "{sequence:?}");
+            assert_unitary_equivalent(&original, &hugr);
+        }
+    }
+
+    /// Two-qubit gates and protected gates end the runs.
+    #[rstest]
+    #[case::unprotected(false, 2, 1)]
+    #[case::protected(true, 1, 3)]
+    fn run_boundaries(#[case] protect: bool, #[case] runs: usize, #[case] remaining: usize) {
+        let signature = Signature::new_endo(vec![qb_t(), qb_t()]);
+        let mut builder = DFGBuilder::new(signature).unwrap();
+        let [a, b] = builder.input_wires_arr();
+        let [a] = builder.add_dataflow_op(QuantumOp::H, [a]).unwrap().outputs_arr();
+        let first = builder.add_dataflow_op(QuantumOp::H, [a]).unwrap();
+        let [a, b] = builder
+            .add_dataflow_op(QuantumOp::CX, [first.out_wire(0), b])
+            .unwrap()
+            .outputs_arr();
+        let [a] = builder.add_dataflow_op(QuantumOp::S, [a]).unwrap().outputs_arr();
+        let [a] = builder.add_dataflow_op(QuantumOp::S, [a]).unwrap().outputs_arr();
+        let mut hugr = builder.finish_hugr_with_outputs([a, b]).unwrap();
+        if protect {
+            hugr.set_metadata(first.node(), metadata::PROTECTED, true);
+        }
+
+        assert_eq!(fuse_single_qubit_gates(&mut hugr), runs);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        // The CX is left unchanged.
+        assert_eq!(gates(&hugr).len(), remaining + 1);
+    }
+
+    /// The single-qubit gates of the program are separated by two-qubit
+    /// gates and measurements, so there is nothing to fuse.
+    #[rstest]
+    fn catalyst_runs(catalyst_tket_opt: Jeff<'static>) {
+        let config = JeffToHugrConfig::default().with_fuse_single_qubit_gates(true);
+        let fused = jeff_to_hugr_with_config(&catalyst_tket_opt, &config).unwrap().hugr;
+        fused.validate().unwrap_or_else(|e| panic!("{e}"));
+        let mut hugr = jeff_to_hugr(&catalyst_tket_opt).unwrap();
+        assert_eq!(gates(&fused).len(), gates(&hugr).len());
+        assert_eq!(fuse_single_qubit_gates(&mut hugr), 0);
+    }
+}
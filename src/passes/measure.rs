@@ -0,0 +1,323 @@
+//! Lowering of register-level measurements into per-qubit measurements.
+
+use hugr::builder::{
+    BuildError, DFGBuilder, Dataflow, DataflowHugr, DataflowSubContainer, SubContainer,
+};
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::ops::OpTrait;
+use hugr::std_extensions::arithmetic::int_ops::IntOpDef;
+use hugr::std_extensions::arithmetic::int_types::{ConstInt, int_type};
+use hugr::types::Signature;
+use hugr::{Hugr, HugrView, Node, Wire, type_row};
+use itertools::Itertools;
+
+use crate::extension::register_semantics::static_length;
+use crate::extension::{JeffOp, intreg_type, qureg_type};
+use crate::optype::tket_ops;
+use crate::provenance::record_pass;
+
+/// Log width of the `int(32)` indices.
+pub(super) const INDEX_LOG_WIDTH: u8 = 5;
+
+/// Replace the [`JeffOp::QuregMeasure`] and [`JeffOp::QuregMeasureNd`]
+/// operations by measurements of the individual qubits.
+///
+/// When the size of the measured register is statically known, see
+/// [`register_sizes`](crate::extension::register_semantics::register_sizes),
+/// the measurements are emitted as a flat sequence feeding a
+/// [`JeffOp::IntArrayCreate`]. Otherwise, a loop over the register
+/// length writes each outcome with a [`JeffOp::IntArraySet`].
+///
+/// Each lowered operation is replaced by a nested dataflow graph.
+///
+/// Returns the number of lowered operations.
+///
+/// # Examples
+///
+/// ```
+/// use hugr_jeff::doctest_support::measured_register;
+/// use hugr_jeff::hugr::HugrView;
+/// use hugr_jeff::passes::lower_register_measures;
+/// use hugr_jeff::tket::TketOp;
+///
+/// let mut hugr = measured_register(4);
+/// assert_eq!(lower_register_measures(&mut hugr), 1);
+/// hugr.validate().unwrap();
+///
+/// // The register is measured with one `tket.quantum` measurement per qubit.
+/// let measures = hugr
+///     .nodes()
+///     .filter(|&node| hugr.get_optype(node).cast::<TketOp>() == Some(TketOp::Measure))
+///     .count();
+/// assert_eq!(measures, 4);
+/// ```
+pub fn lower_register_measures(hugr: &mut impl HugrMut<Node = Node>) -> usize {
+    record_pass(hugr, "lower_register_measures");
+    let measures = hugr
+        .nodes()
+        .filter_map(|node| {
+            let destructive = match hugr.get_optype(node).cast::<JeffOp>()? {
+                JeffOp::QuregMeasure => true,
+                JeffOp::QuregMeasureNd => false,
+                _ => return None,
+            };
+            Some((node, destructive, static_size(hugr, node)))
+        })
+        .collect_vec();
+
+    for &(node, destructive, size) in &measures {
+        let lowered = match size {
+            Some(size) => flat_measure(size, destructive),
+            None => loop_measure(destructive),
+        }
+        .expect("Failed to build the register measurement");
+        replace_with_dfg(hugr, node, lowered);
+    }
+    measures.len()
+}
+
+/// Returns the size of the register measured by a node, if it is statically
+/// known.
+fn static_size(hugr: &impl HugrView<Node = Node>, node: Node) -> Option<usize> {
+    let (src, src_port) = hugr.single_linked_output(node, 0)?;
+    static_length(hugr, src, src_port)?.try_into().ok()
+}
+
+/// The signature of a register measurement.
+fn measure_signature(destructive: bool) -> Signature {
+    let op = match destructive {
+        true => JeffOp::QuregMeasure,
+        false => JeffOp::QuregMeasureNd,
+    };
+    let op: hugr::ops::OpType = op.into_extension_op().into();
+    op.dataflow_signature().unwrap().into_owned()
+}
+
+/// Measure the `size` qubits of a register one by one.
+fn flat_measure(size: usize, destructive: bool) -> Result<Hugr, BuildError> {
+    let mut builder = DFGBuilder::new(measure_signature(destructive))?;
+    let [mut reg] = builder.input_wires_arr();
+
+    let mut bits = Vec::with_capacity(size);
+    for i in 0..size {
+        let index = builder.add_load_value(ConstInt::new_u(INDEX_LOG_WIDTH, i as u64).unwrap());
+        let [new_reg, qubit] = builder
+            .add_dataflow_op(JeffOp::QuregExtractIndex.into_extension_op(), [reg, index])?
+            .outputs_arr();
+        let [qubit, bit] = builder
+            .add_dataflow_op(tket_ops::measure(), [qubit])?
+            .outputs_arr();
+        reg = builder
+            .add_dataflow_op(
+                JeffOp::QuregInsertIndex.into_extension_op(),
+                [new_reg, qubit, index],
+            )?
+            .out_wire(0);
+        bits.push(bit);
+    }
+    let create = JeffOp::IntArrayCreate {
+        bits: 1,
+        inputs: size,
+    };
+    let bits = builder
+        .add_dataflow_op(create.into_extension_op(), bits)?
+        .out_wire(0);
+
+    finish_measure(builder, reg, bits, destructive)
+}
+
+/// Measure the qubits of a register in a loop over its length.
+fn loop_measure(destructive: bool) -> Result<Hugr, BuildError> {
+    let index_t = int_type(INDEX_LOG_WIDTH);
+    let bits_t = intreg_type(1);
+
+    let mut builder = DFGBuilder::new(measure_signature(destructive))?;
+    let [reg] = builder.input_wires_arr();
+    let [reg, len] = builder
+        .add_dataflow_op(JeffOp::QuregLength.into_extension_op(), [reg])?
+        .outputs_arr();
+    let bits = builder
+        .add_dataflow_op(JeffOp::IntArrayZero { bits: 1 }.into_extension_op(), [len])?
+        .out_wire(0);
+    let zero = builder.add_load_value(ConstInt::new_u(INDEX_LOG_WIDTH, 0).unwrap());
+
+    let mut tail_loop = builder.tail_loop_builder(
+        [],
+        [
+            (qureg_type(), reg),
+            (bits_t.clone(), bits),
+            (index_t.clone(), zero),
+            (index_t.clone(), len),
+        ],
+        type_row![],
+    )?;
+    let [reg, bits, index, len] = tail_loop.input_wires_arr();
+
+    // Guard the first iteration against empty registers.
+    let in_bounds = tail_loop
+        .add_dataflow_op(IntOpDef::ilt_u.with_log_width(INDEX_LOG_WIDTH), [index, len])?
+        .out_wire(0);
+    let mut cond = tail_loop.conditional_builder(
+        ([type_row![], type_row![]], in_bounds),
+        [
+            (qureg_type(), reg),
+            (bits_t.clone(), bits),
+            (index_t.clone(), index),
+        ],
+        vec![qureg_type(), bits_t, index_t].into(),
+    )?;
+    let out_of_bounds = cond.case_builder(0)?;
+    let inputs = out_of_bounds.input_wires();
+    out_of_bounds.finish_with_outputs(inputs)?;
+
+    let mut measure = cond.case_builder(1)?;
+    let [reg, bits, index] = measure.input_wires_arr();
+    let [reg, qubit] = measure
+        .add_dataflow_op(JeffOp::QuregExtractIndex.into_extension_op(), [reg, index])?
+        .outputs_arr();
+    let [qubit, bit] = measure
+        .add_dataflow_op(tket_ops::measure(), [qubit])?
+        .outputs_arr();
+    let reg = measure
+        .add_dataflow_op(
+            JeffOp::QuregInsertIndex.into_extension_op(),
+            [reg, qubit, index],
+        )?
+        .out_wire(0);
+    let bits = measure
+        .add_dataflow_op(
+            JeffOp::IntArraySet { bits: 1 }.into_extension_op(),
+            [bits, index, bit],
+        )?
+        .out_wire(0);
+    let one = measure.add_load_value(ConstInt::new_u(INDEX_LOG_WIDTH, 1).unwrap());
+    let next = measure
+        .add_dataflow_op(IntOpDef::iadd.with_log_width(INDEX_LOG_WIDTH), [index, one])?
+        .out_wire(0);
+    measure.finish_with_outputs([reg, bits, next])?;
+    let [reg, bits, index] = cond.finish_sub_container()?.outputs_arr();
+
+    // Break once all the qubits have been measured.
+    let done = tail_loop
+        .add_dataflow_op(IntOpDef::ige_u.with_log_width(INDEX_LOG_WIDTH), [index, len])?
+        .out_wire(0);
+    let [reg, bits, _, _] = tail_loop
+        .finish_with_outputs(done, [reg, bits, index, len])?
+        .outputs_arr();
+
+    finish_measure(builder, reg, bits, destructive)
+}
+
+/// Output the measured bits, freeing the register for destructive measurements.
+fn finish_measure(
+    mut builder: DFGBuilder<Hugr>,
+    reg: Wire,
+    bits: Wire,
+    destructive: bool,
+) -> Result<Hugr, BuildError> {
+    let outputs = match destructive {
+        true => {
+            builder.add_dataflow_op(JeffOp::QuregFree.into_extension_op(), [reg])?;
+            vec![bits]
+        }
+        false => vec![reg, bits],
+    };
+    builder.finish_hugr_with_outputs(outputs)
+}
+
+/// Replace a node by a nested dataflow graph with the same signature.
+pub(super) fn replace_with_dfg(hugr: &mut impl HugrMut<Node = Node>, node: Node, dfg: Hugr) {
+    let parent = hugr.get_parent(node).unwrap();
+    let inputs = hugr
+        .node_inputs(node)
+        .filter_map(|port| Some((port, hugr.single_linked_output(node, port)?)))
+        .collect_vec();
+    let outputs = hugr
+        .node_outputs(node)
+        .map(|port| (port, hugr.linked_inputs(node, port).collect_vec()))
+        .collect_vec();
+
+    let dfg = hugr.insert_hugr(parent, dfg).inserted_entrypoint;
+    hugr.remove_node(node);
+    for (port, (src, src_port)) in inputs {
+        hugr.connect(src, src_port, dfg, port);
+    }
+    for (port, targets) in outputs {
+        for (tgt, tgt_port) in targets {
+            hugr.connect(dfg, port, tgt, tgt_port);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::optype::tket_ops::QuantumOp;
+    use hugr::extension::prelude::qb_t;
+    use rstest::rstest;
+
+    /// A DFG measuring a register, either created from `qubits` input qubits
+    /// or given as an input.
+    fn register_measure(qubits: Option<usize>, destructive: bool) -> Hugr {
+        let inputs = match qubits {
+            Some(n) => vec![qb_t(); n],
+            None => vec![qureg_type()],
+        };
+        let outputs = measure_signature(destructive).output().clone();
+        let mut builder = DFGBuilder::new(Signature::new(inputs, outputs)).unwrap();
+        let reg = match qubits {
+            Some(n) => builder
+                .add_dataflow_op(
+                    JeffOp::QuregCreate { qubits: n }.into_extension_op(),
+                    builder.input_wires().collect_vec(),
+                )
+                .unwrap()
+                .out_wire(0),
+            None => builder.input_wires().next().unwrap(),
+        };
+        let op = match destructive {
+            true => JeffOp::QuregMeasure,
+            false => JeffOp::QuregMeasureNd,
+        };
+        let outputs = builder
+            .add_dataflow_op(op.into_extension_op(), [reg])
+            .unwrap()
+            .outputs();
+        builder.finish_hugr_with_outputs(outputs).unwrap()
+    }
+
+    fn count_ops(hugr: &Hugr, pred: impl Fn(&hugr::ops::OpType) -> bool) -> usize {
+        hugr.nodes().filter(|&n| pred(hugr.get_optype(n))).count()
+    }
+
+    #[rstest]
+    #[case::flat(Some(3), false)]
+    #[case::flat_destructive(Some(3), true)]
+    #[case::flat_empty(Some(0), false)]
+    #[case::looped(None, false)]
+    #[case::looped_destructive(None, true)]
+    fn lower_measures(#[case] qubits: Option<usize>, #[case] destructive: bool) {
+        let mut hugr = register_measure(qubits, destructive);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        assert_eq!(lower_register_measures(&mut hugr), 1);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        let register_measures = count_ops(&hugr, |op| {
+            matches!(
+                op.cast::<JeffOp>(),
+                Some(JeffOp::QuregMeasure | JeffOp::QuregMeasureNd)
+            )
+        });
+        assert_eq!(register_measures, 0);
+
+        let measures = count_ops(&hugr, |op| QuantumOp::of(op) == Some(QuantumOp::Measure));
+        let loops = count_ops(&hugr, |op| op.is_tail_loop());
+        match qubits {
+            Some(n) => assert_eq!((measures, loops), (n, 0)),
+            None => assert_eq!((measures, loops), (1, 1)),
+        }
+        let frees = count_ops(&hugr, |op| op.cast::<JeffOp>() == Some(JeffOp::QuregFree));
+        assert_eq!(frees, destructive as usize);
+    }
+}
@@ -0,0 +1,150 @@
+//! Simplification of global phases.
+
+use std::collections::BTreeMap;
+
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::ops::{Const, LoadConstant, Value};
+use hugr::std_extensions::arithmetic::float_types::ConstF64;
+use hugr::{HugrView, Node};
+use itertools::Itertools;
+
+use crate::extension::JeffOp;
+use crate::provenance::record_pass;
+
+/// How [`lower_global_phases`] handles the [`JeffOp::GlobalPhase`] operations.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum GlobalPhaseLowering {
+    /// Remove all the global phases, for programs where they are irrelevant.
+    Drop,
+    /// Replace the global phases with a constant angle in each region by a
+    /// single phase, with the sum of their angles.
+    #[default]
+    Fold,
+}
+
+/// Remove or fold the [`JeffOp::GlobalPhase`] operations of a HUGR.
+///
+/// Returns the number of removed phase nodes.
+pub fn lower_global_phases(
+    hugr: &mut impl HugrMut<Node = Node>,
+    mode: GlobalPhaseLowering,
+) -> usize {
+    record_pass(hugr, "lower_global_phases");
+    let phases = hugr
+        .nodes()
+        .filter(|&node| hugr.get_optype(node).cast::<JeffOp>() == Some(JeffOp::GlobalPhase))
+        .collect_vec();
+
+    match mode {
+        GlobalPhaseLowering::Drop => {
+            for &node in &phases {
+                hugr.remove_node(node);
+            }
+            phases.len()
+        }
+        GlobalPhaseLowering::Fold => {
+            let mut by_region: BTreeMap<Node, Vec<(Node, f64)>> = BTreeMap::new();
+            for node in phases {
+                if let Some(angle) = constant_angle(hugr, node) {
+                    let parent = hugr.get_parent(node).unwrap();
+                    by_region.entry(parent).or_default().push((node, angle));
+                }
+            }
+            let mut removed = 0;
+            for (parent, phases) in by_region {
+                if phases.len() < 2 {
+                    continue;
+                }
+                let total = phases.iter().map(|(_, angle)| angle).sum();
+                let (kept, _) = phases[0];
+                for &(node, _) in &phases[1..] {
+                    hugr.remove_node(node);
+                }
+                removed += phases.len() - 1;
+
+                let value = Value::extension(ConstF64::new(total));
+                let load = hugr.add_node_with_parent(
+                    parent,
+                    LoadConstant {
+                        datatype: value.get_type(),
+                    },
+                );
+                let const_node = hugr.add_node_with_parent(parent, Const::new(value));
+                hugr.connect(const_node, 0, load, 0);
+                hugr.disconnect(kept, 0);
+                hugr.connect(load, 0, kept, 0);
+            }
+            removed
+        }
+    }
+}
+
+/// Returns the angle of a global phase, if it is a loaded constant.
+fn constant_angle(hugr: &impl HugrView<Node = Node>, node: Node) -> Option<f64> {
+    let (load, _) = hugr.single_linked_output(node, 0)?;
+    if !hugr.get_optype(load).is_load_constant() {
+        return None;
+    }
+    let const_node = hugr.static_source(load)?;
+    let value = hugr.get_optype(const_node).as_const()?;
+    Some(value.get_custom_value::<ConstF64>()?.value())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr};
+    use hugr::std_extensions::arithmetic::float_types::float64_type;
+    use hugr::types::Signature;
+    use hugr::Hugr;
+    use rstest::rstest;
+
+    /// A DFG applying a global phase for each constant angle, and one with
+    /// an angle given as input.
+    fn phase_dfg(angles: &[f64]) -> Hugr {
+        let mut builder = DFGBuilder::new(Signature::new(vec![float64_type()], vec![])).unwrap();
+        let [dynamic] = builder.input_wires_arr();
+        for &angle in angles {
+            let angle = builder.add_load_value(ConstF64::new(angle));
+            builder
+                .add_dataflow_op(JeffOp::GlobalPhase.into_extension_op(), [angle])
+                .unwrap();
+        }
+        builder
+            .add_dataflow_op(JeffOp::GlobalPhase.into_extension_op(), [dynamic])
+            .unwrap();
+        builder.finish_hugr_with_outputs([]).unwrap()
+    }
+
+    fn phase_angles(hugr: &Hugr) -> Vec<Option<f64>> {
+        hugr.nodes()
+            .filter(|&n| hugr.get_optype(n).cast::<JeffOp>() == Some(JeffOp::GlobalPhase))
+            .map(|n| constant_angle(hugr, n))
+            .sorted_by(|a, b| a.partial_cmp(b).unwrap())
+            .collect()
+    }
+
+    #[rstest]
+    fn fold_constant_phases() {
+        let mut hugr = phase_dfg(&[0.25, 0.5]);
+        assert_eq!(lower_global_phases(&mut hugr, GlobalPhaseLowering::Fold), 1);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(phase_angles(&hugr), [None, Some(0.75)]);
+    }
+
+    #[rstest]
+    fn fold_single_phase() {
+        let mut hugr = phase_dfg(&[0.25]);
+        assert_eq!(lower_global_phases(&mut hugr, GlobalPhaseLowering::Fold), 0);
+        assert_eq!(phase_angles(&hugr), [None, Some(0.25)]);
+    }
+
+    #[rstest]
+    fn drop_phases() {
+        let mut hugr = phase_dfg(&[0.25, 0.5]);
+        assert_eq!(lower_global_phases(&mut hugr, GlobalPhaseLowering::Drop), 3);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        assert!(phase_angles(&hugr).is_empty());
+    }
+}
@@ -0,0 +1,353 @@
+//! Protection of operations from optimization.
+
+use std::collections::BTreeSet;
+
+use hugr::extension::prelude::{Barrier, qb_t};
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::ops::{DFG, Input, Output};
+use hugr::types::Signature;
+use hugr::{HugrView, IncomingPort, Node, OutgoingPort};
+use itertools::Itertools;
+
+use crate::Protection;
+use crate::metadata;
+use crate::provenance::record_pass;
+
+/// A value edge, from an output port to an input port.
+type Wire = ((Node, OutgoingPort), (Node, IncomingPort));
+
+/// Protect operations from optimization.
+///
+/// The operations are annotated with the [`metadata::PROTECTED`] metadata,
+/// and grouped into spans of operations of the same region linked by direct
+/// edges. Each span is then encoded following `encoding`:
+///
+/// - [`Protection::Barriers`] inserts a prelude `Barrier` marked `"begin"`
+///   on the qubit inputs of each operation, and one marked `"end"` on the
+///   qubits leaving the span.
+/// - [`Protection::Container`] moves the span into a `DFG` marked
+///   `"container"`. A span that cannot be moved as a whole, as it feeds
+///   unprotected operations that feed it back, gets one container per
+///   operation.
+///
+/// Nodes that are not dataflow operations, such as constants, are left in
+/// place. The protected operations are recovered from either encoding by
+/// [`crate::protected_ops`].
+///
+/// Returns the number of protected spans.
+pub fn protect_ops(
+    hugr: &mut impl HugrMut<Node = Node>,
+    ops: &[Node],
+    encoding: Protection,
+) -> usize {
+    record_pass(hugr, "protect_ops");
+    let ops: BTreeSet<Node> = ops
+        .iter()
+        .copied()
+        .filter(|&node| hugr.get_optype(node).dataflow_signature().is_some())
+        .collect();
+    for &node in &ops {
+        hugr.set_metadata(node, metadata::PROTECTED, true);
+    }
+
+    let spans = spans(hugr, &ops);
+    for span in &spans {
+        let containers = match encoding {
+            Protection::Barriers => {
+                fence(hugr, span);
+                continue;
+            }
+            Protection::Container if is_convex(hugr, span) => vec![enclose(hugr, span)],
+            Protection::Container => span
+                .iter()
+                .map(|&node| enclose(hugr, &BTreeSet::from([node])))
+                .collect(),
+        };
+        for container in containers {
+            hugr.set_metadata(container, metadata::PROTECTED, "container");
+        }
+    }
+    spans.len()
+}
+
+/// Group the operations into sets of siblings linked by direct edges.
+fn spans(hugr: &impl HugrView<Node = Node>, ops: &BTreeSet<Node>) -> Vec<BTreeSet<Node>> {
+    let mut visited = BTreeSet::new();
+    let mut spans = Vec::new();
+    for &start in ops {
+        if !visited.insert(start) {
+            continue;
+        }
+        let parent = hugr.get_parent(start);
+        let mut span = BTreeSet::from([start]);
+        let mut stack = vec![start];
+        while let Some(node) = stack.pop() {
+            for next in hugr.input_neighbours(node).chain(hugr.output_neighbours(node)) {
+                if ops.contains(&next) && hugr.get_parent(next) == parent && visited.insert(next) {
+                    span.insert(next);
+                    stack.push(next);
+                }
+            }
+        }
+        spans.push(span);
+    }
+    spans
+}
+
+/// Returns `true` if no path leaving the span comes back into it.
+pub(super) fn is_convex(hugr: &impl HugrView<Node = Node>, span: &BTreeSet<Node>) -> bool {
+    let mut visited = BTreeSet::new();
+    let mut stack = span
+        .iter()
+        .flat_map(|&node| hugr.output_neighbours(node))
+        .filter(|next| !span.contains(next))
+        .collect_vec();
+    while let Some(node) = stack.pop() {
+        if span.contains(&node) {
+            return false;
+        }
+        if visited.insert(node) {
+            stack.extend(hugr.output_neighbours(node));
+        }
+    }
+    true
+}
+
+/// Insert barriers before each operation of the span, and after the span.
+fn fence(hugr: &mut impl HugrMut<Node = Node>, span: &BTreeSet<Node>) {
+    let leaving = span
+        .iter()
+        .flat_map(|&node| qubit_outputs(hugr, node))
+        .filter(|(_, (tgt, _))| !span.contains(tgt))
+        .collect_vec();
+    for &node in span {
+        let entering = qubit_inputs(hugr, node);
+        insert_barrier(hugr, entering, "begin");
+    }
+    insert_barrier(hugr, leaving, "end");
+}
+
+/// Insert a barrier on qubit wires of the same region, marked with `marker`.
+fn insert_barrier(hugr: &mut impl HugrMut<Node = Node>, wires: Vec<Wire>, marker: &str) {
+    let Some(&(_, (node, _))) = wires.first() else {
+        return;
+    };
+    let parent = hugr.get_parent(node).unwrap();
+    let barrier = hugr.add_node_with_parent(parent, Barrier::new(vec![qb_t(); wires.len()]));
+    hugr.set_metadata(barrier, metadata::PROTECTED, marker);
+    for (port, ((src, src_port), (tgt, tgt_port))) in wires.into_iter().enumerate() {
+        hugr.disconnect(tgt, tgt_port);
+        hugr.connect(src, src_port, barrier, port);
+        hugr.connect(barrier, port, tgt, tgt_port);
+    }
+}
+
+/// The qubit wires entering a node.
+fn qubit_inputs(hugr: &impl HugrView<Node = Node>, node: Node) -> Vec<Wire> {
+    let Some(signature) = hugr.signature(node) else {
+        return Vec::new();
+    };
+    (0..signature.input_count())
+        .map(IncomingPort::from)
+        .filter(|&port| signature.in_port_type(port) == Some(&qb_t()))
+        .filter_map(|port| Some((hugr.single_linked_output(node, port)?, (node, port))))
+        .collect()
+}
+
+/// The qubit wires leaving a node.
+fn qubit_outputs(hugr: &impl HugrView<Node = Node>, node: Node) -> Vec<Wire> {
+    let Some(signature) = hugr.signature(node) else {
+        return Vec::new();
+    };
+    (0..signature.output_count())
+        .map(OutgoingPort::from)
+        .filter(|&port| signature.out_port_type(port) == Some(&qb_t()))
+        .flat_map(|port| hugr.linked_inputs(node, port).map(move |tgt| ((node, port), tgt)))
+        .collect()
+}
+
+/// Move a convex span of sibling operations into a `DFG` container.
+///
+/// The container reads the values entering the span, and produces the
+/// values leaving it, in the order of the operations using them.
+///
+/// Returns the container node.
+pub(super) fn enclose(hugr: &mut impl HugrMut<Node = Node>, span: &BTreeSet<Node>) -> Node {
+    let parent = hugr.get_parent(*span.first().unwrap()).unwrap();
+
+    // The values entering the span, read once each by the container.
+    let mut sources: Vec<(Node, OutgoingPort)> = Vec::new();
+    let mut input_types = Vec::new();
+    let mut inputs = Vec::new();
+    // The values leaving the span, with their consumers outside of it.
+    let mut outputs = Vec::new();
+    let mut output_types = Vec::new();
+    for &node in span {
+        let signature = hugr.signature(node).unwrap();
+        for port in (0..signature.input_count()).map(IncomingPort::from) {
+            let Some(src) = hugr.single_linked_output(node, port) else {
+                continue;
+            };
+            if span.contains(&src.0) {
+                continue;
+            }
+            let index = sources.iter().position(|&s| s == src).unwrap_or_else(|| {
+                sources.push(src);
+                input_types.push(signature.in_port_type(port).unwrap().clone());
+                sources.len() - 1
+            });
+            inputs.push((node, port, index));
+        }
+        for port in (0..signature.output_count()).map(OutgoingPort::from) {
+            let targets = hugr
+                .linked_inputs(node, port)
+                .filter(|(tgt, _)| !span.contains(tgt))
+                .collect_vec();
+            if !targets.is_empty() {
+                output_types.push(signature.out_port_type(port).unwrap().clone());
+                outputs.push((node, port, targets));
+            }
+        }
+    }
+
+    let signature = Signature::new(input_types, output_types);
+    let container = hugr.add_node_with_parent(
+        parent,
+        DFG {
+            signature: signature.clone(),
+        },
+    );
+    let input = hugr.add_node_with_parent(container, Input::new(signature.input));
+    let output = hugr.add_node_with_parent(container, Output::new(signature.output));
+    redirect_order_edges(hugr, span, container);
+    for &node in span {
+        hugr.set_parent(node, container);
+    }
+
+    for (node, port, index) in inputs {
+        hugr.disconnect(node, port);
+        hugr.connect(input, index, node, port);
+    }
+    for (index, (src, src_port)) in sources.into_iter().enumerate() {
+        hugr.connect(src, src_port, container, index);
+    }
+    for (index, (node, port, targets)) in outputs.into_iter().enumerate() {
+        for (tgt, tgt_port) in targets {
+            hugr.disconnect(tgt, tgt_port);
+            hugr.connect(container, index, tgt, tgt_port);
+        }
+        hugr.connect(node, port, output, index);
+    }
+    container
+}
+
+/// Move the order edges between a span and its siblings to the container
+/// the span is moved into.
+fn redirect_order_edges(
+    hugr: &mut impl HugrMut<Node = Node>,
+    span: &BTreeSet<Node>,
+    container: Node,
+) {
+    let mut predecessors = BTreeSet::new();
+    let mut successors = BTreeSet::new();
+    for &node in span {
+        let optype = hugr.get_optype(node);
+        let (in_port, out_port) = (optype.other_input_port(), optype.other_output_port());
+        if let Some(port) = in_port {
+            let (inside, outside): (Vec<_>, Vec<_>) = hugr
+                .linked_outputs(node, port)
+                .map(|(pred, _)| pred)
+                .partition(|pred| span.contains(pred));
+            if !outside.is_empty() {
+                hugr.disconnect(node, port);
+                for pred in inside {
+                    hugr.add_other_edge(pred, node);
+                }
+                predecessors.extend(outside);
+            }
+        }
+        if let Some(port) = out_port {
+            let (inside, outside): (Vec<_>, Vec<_>) = hugr
+                .linked_inputs(node, port)
+                .map(|(succ, _)| succ)
+                .partition(|succ| span.contains(succ));
+            if !outside.is_empty() {
+                hugr.disconnect(node, port);
+                for succ in inside {
+                    hugr.add_other_edge(node, succ);
+                }
+                successors.extend(outside);
+            }
+        }
+    }
+    for pred in predecessors {
+        hugr.add_other_edge(pred, container);
+    }
+    for succ in successors {
+        hugr.add_other_edge(container, succ);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::optype::tket_ops::QuantumOp;
+    use crate::passes::cancel_inverse_gates;
+    use crate::protected_ops;
+    use hugr::Hugr;
+    use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr};
+    use rstest::rstest;
+
+    /// Two qubits with a pair of `H` on each, returning the graph and the
+    /// gates on the first qubit.
+    fn hadamard_pairs() -> (Hugr, [Node; 2]) {
+        let mut builder = DFGBuilder::new(Signature::new_endo(vec![qb_t(), qb_t()])).unwrap();
+        let [q0, q1] = builder.input_wires_arr();
+        let first = builder.add_dataflow_op(QuantumOp::H, [q0]).unwrap();
+        let second = builder.add_dataflow_op(QuantumOp::H, first.outputs()).unwrap();
+        let [q1] = builder.add_dataflow_op(QuantumOp::H, [q1]).unwrap().outputs_arr();
+        let [q1] = builder.add_dataflow_op(QuantumOp::H, [q1]).unwrap().outputs_arr();
+        let [q0] = second.outputs_arr();
+        let hugr = builder.finish_hugr_with_outputs([q0, q1]).unwrap();
+        (hugr, [first.node(), second.node()])
+    }
+
+    #[rstest]
+    #[case::barriers(Protection::Barriers)]
+    #[case::container(Protection::Container)]
+    fn protected_pair_survives(#[case] encoding: Protection) {
+        let (mut hugr, protected) = hadamard_pairs();
+        assert_eq!(protect_ops(&mut hugr, &protected, encoding), 1);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        // Only the pair on the second qubit is cancelled.
+        assert_eq!(cancel_inverse_gates(&mut hugr), 1);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        let gates = hugr
+            .nodes()
+            .filter(|&n| QuantumOp::of(hugr.get_optype(n)) == Some(QuantumOp::H))
+            .collect::<BTreeSet<_>>();
+        assert_eq!(gates, BTreeSet::from(protected));
+        assert_eq!(protected_ops(&hugr), BTreeSet::from(protected));
+    }
+
+    #[rstest]
+    fn barriers_fence_each_gate() {
+        let (mut hugr, protected) = hadamard_pairs();
+        protect_ops(&mut hugr, &protected, Protection::Barriers);
+
+        let markers = hugr
+            .nodes()
+            .filter(|&n| hugr.get_optype(n).cast::<Barrier>().is_some())
+            .map(|n| hugr.get_metadata(n, metadata::PROTECTED).unwrap().clone())
+            .sorted_by_key(|marker| marker.to_string())
+            .collect_vec();
+        assert_eq!(markers, ["begin", "begin", "end"]);
+
+        // The barriers alone are enough to recover the protected gates.
+        for node in protected {
+            hugr.remove_metadata(node, metadata::PROTECTED);
+        }
+        assert_eq!(protected_ops(&hugr), BTreeSet::from(protected));
+    }
+}
@@ -0,0 +1,816 @@
+//! Peephole simplifications of qubit register accesses.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::ops::{Const, LoadConstant, OpType, Value};
+use hugr::std_extensions::arithmetic::int_types::ConstInt;
+use hugr::{HugrView, IncomingPort, Node, OutgoingPort};
+use itertools::Itertools;
+
+use super::measure::INDEX_LOG_WIDTH;
+use crate::extension::{JeffOp, qureg_type};
+use crate::extension::register_semantics::{register_sizes, static_length};
+use crate::metadata;
+use crate::provenance::record_pass;
+
+/// Cancel register accesses that undo each other.
+///
+/// The following patterns are removed, when both accesses use the same
+/// constant index:
+///
+/// - A [`JeffOp::QuregExtractIndex`] whose register and qubit outputs are
+///   directly inserted back with a [`JeffOp::QuregInsertIndex`].
+/// - A [`JeffOp::QuregInsertIndex`] whose register output is directly
+///   extracted from with a [`JeffOp::QuregExtractIndex`]. The inserted
+///   qubit is connected to the consumers of the extracted one.
+///
+/// Accesses with dynamic indices, or with other operations between them
+/// on the register wire, are left untouched. An extraction does not change
+/// the length of the register, see
+/// [`crate::extension::register_semantics`].
+///
+/// Returns the number of cancelled pairs.
+pub fn cancel_register_roundtrips(hugr: &mut impl HugrMut<Node = Node>) -> usize {
+    record_pass(hugr, "cancel_register_roundtrips");
+    let mut cancelled = 0;
+    loop {
+        let Some((first, second)) = hugr.nodes().find_map(|node| roundtrip_pair(hugr, node))
+        else {
+            return cancelled;
+        };
+        cancel_pair(hugr, first, second);
+        cancelled += 1;
+    }
+}
+
+/// Returns the pair of accesses starting at `node` if they cancel out.
+fn roundtrip_pair(hugr: &impl HugrView<Node = Node>, node: Node) -> Option<(Node, Node)> {
+    let first = hugr.get_optype(node).cast::<JeffOp>()?;
+    let (next, port) = hugr.single_linked_input(node, 0)?;
+    if port.index() != 0 {
+        return None;
+    }
+    let second = hugr.get_optype(next).cast::<JeffOp>()?;
+
+    let index_ports = match (first, second) {
+        (JeffOp::QuregExtractIndex, JeffOp::QuregInsertIndex) => {
+            // The extracted qubit must be inserted back untouched.
+            let (src, src_port) = hugr.single_linked_output(next, 1)?;
+            if src != node || src_port.index() != 1 {
+                return None;
+            }
+            (1, 2)
+        }
+        (JeffOp::QuregInsertIndex, JeffOp::QuregExtractIndex) => (2, 1),
+        _ => return None,
+    };
+
+    let first_index = constant_index(hugr, node, index_ports.0)?;
+    let second_index = constant_index(hugr, next, index_ports.1)?;
+    (first_index == second_index).then_some((node, next))
+}
+
+/// Returns the value of a constant integer input.
+fn constant_index(hugr: &impl HugrView<Node = Node>, node: Node, port: usize) -> Option<u64> {
+    let (load, _) = hugr.single_linked_output(node, port)?;
+    if !hugr.get_optype(load).is_load_constant() {
+        return None;
+    }
+    let const_node = hugr.static_source(load)?;
+    let value = hugr.get_optype(const_node).as_const()?;
+    Some(value.get_custom_value::<ConstInt>()?.value_u())
+}
+
+/// Remove a pair of accesses, reconnecting the values threaded through them.
+fn cancel_pair(hugr: &mut impl HugrMut<Node = Node>, first: Node, second: Node) {
+    // Pairs of (source in `first`'s inputs, consumers of `second`'s outputs).
+    let threaded: &[(usize, usize)] = match hugr.get_optype(first).cast::<JeffOp>() {
+        // The register is threaded through.
+        Some(JeffOp::QuregExtractIndex) => &[(0, 0)],
+        // The register and the inserted qubit are threaded through.
+        _ => &[(0, 0), (1, 1)],
+    };
+    let rewires = threaded
+        .iter()
+        .filter_map(|&(input, output)| {
+            let (src, src_port) = hugr.single_linked_output(first, IncomingPort::from(input))?;
+            let targets = hugr
+                .linked_inputs(second, OutgoingPort::from(output))
+                .collect_vec();
+            Some((src, src_port, targets))
+        })
+        .collect_vec();
+
+    hugr.remove_node(first);
+    hugr.remove_node(second);
+
+    for (src, src_port, targets) in rewires {
+        for (tgt, tgt_port) in targets {
+            hugr.connect(src, src_port, tgt, tgt_port);
+        }
+    }
+}
+
+/// Replace the [`JeffOp::QuregLength`] of registers of statically known
+/// length by integer constants.
+///
+/// The lengths are computed with
+/// [`register_semantics::static_length`](crate::extension::register_semantics::static_length),
+/// so a register keeps its length while qubits extracted from it are alive.
+/// The register threaded through a folded operation is passed on to its
+/// consumers.
+///
+/// Returns the number of folded operations.
+pub fn fold_register_lengths(hugr: &mut impl HugrMut<Node = Node>) -> usize {
+    record_pass(hugr, "fold_register_lengths");
+    let folded = hugr
+        .nodes()
+        .filter(|&node| hugr.get_optype(node).cast::<JeffOp>() == Some(JeffOp::QuregLength))
+        .filter_map(|node| {
+            let (src, src_port) = hugr.single_linked_output(node, 0)?;
+            Some((node, static_length(hugr, src, src_port)?))
+        })
+        .collect_vec();
+
+    for &(node, length) in &folded {
+        let parent = hugr.get_parent(node).unwrap();
+        let register = hugr.single_linked_output(node, 0).unwrap();
+        let register_targets = hugr.linked_inputs(node, 0).collect_vec();
+        let length_targets = hugr.linked_inputs(node, 1).collect_vec();
+
+        let value = Value::extension(ConstInt::new_u(INDEX_LOG_WIDTH, length).unwrap());
+        let load = hugr.add_node_with_parent(
+            parent,
+            LoadConstant {
+                datatype: value.get_type(),
+            },
+        );
+        let const_node = hugr.add_node_with_parent(parent, Const::new(value));
+        hugr.connect(const_node, 0, load, 0);
+        hugr.remove_node(node);
+
+        for (tgt, tgt_port) in register_targets {
+            hugr.connect(register.0, register.1, tgt, tgt_port);
+        }
+        for (tgt, tgt_port) in length_targets {
+            hugr.connect(load, 0, tgt, tgt_port);
+        }
+    }
+    folded.len()
+}
+
+/// Annotate the nodes producing registers of statically known length with
+/// their [`metadata::STATIC_SIZE`].
+///
+/// The lengths are computed with
+/// [`register_sizes`](crate::extension::register_semantics::register_sizes).
+///
+/// Returns the number of annotated nodes.
+pub fn annotate_register_sizes(hugr: &mut impl HugrMut<Node = Node>) -> usize {
+    record_pass(hugr, "annotate_register_sizes");
+    let sizes = register_sizes(hugr);
+    let mut annotated = 0;
+    for (node, ports) in &sizes.iter().chunk_by(|&(&(node, _), _)| node) {
+        let mut entries = vec![serde_json::Value::Null; hugr.num_outputs(node)];
+        for (&(_, port), &size) in ports {
+            entries[port.index()] = serde_json::json!(size);
+        }
+        hugr.set_metadata(node, metadata::STATIC_SIZE, serde_json::json!(entries));
+        annotated += 1;
+    }
+    annotated
+}
+
+/// Annotate the function definitions with the lengths of the registers
+/// passed to them, in their [`metadata::PARAM_SIZES`].
+///
+/// A register parameter gets a length when all the calls to the function
+/// pass registers of the same statically known length, as computed by
+/// [`register_sizes`](crate::extension::register_semantics::register_sizes).
+/// The length is then known inside the function body, e.g. to
+/// [`fold_register_lengths`], and flows on to the functions it calls.
+///
+/// Functions that are never called, whose address is loaded, or that are
+/// part of a recursion get no lengths, and their previous annotations are
+/// removed.
+///
+/// Returns the number of annotated functions.
+pub fn propagate_register_sizes(hugr: &mut impl HugrMut<Node = Node>) -> usize {
+    record_pass(hugr, "propagate_register_sizes");
+    let mut calls: BTreeMap<Node, Vec<Node>> = BTreeMap::new();
+    let mut loaded = BTreeSet::new();
+    for node in hugr.nodes() {
+        match hugr.get_optype(node) {
+            OpType::Call(_) => {
+                if let Some(func) = hugr.static_source(node) {
+                    calls.entry(func).or_default().push(node);
+                }
+            }
+            OpType::LoadFunction(_) => loaded.extend(hugr.static_source(node)),
+            _ => {}
+        }
+    }
+    let functions = hugr
+        .children(hugr.module_root())
+        .filter(|&node| hugr.get_optype(node).is_func_defn())
+        .collect_vec();
+    for &func in &functions {
+        hugr.remove_metadata(func, metadata::PARAM_SIZES);
+    }
+    let callees = call_graph(hugr, &calls);
+    let candidates = functions
+        .into_iter()
+        .filter(|func| calls.contains_key(func) && !loaded.contains(func))
+        .filter(|&func| !is_recursive(&callees, func))
+        .collect_vec();
+
+    // The lengths passed by a function depend on its own parameters, so
+    // they are recomputed until they settle. This takes at most one round
+    // per function, as the calls between candidates form no cycle.
+    let mut annotated: BTreeMap<Node, Vec<Option<u64>>> = BTreeMap::new();
+    for _ in 0..=candidates.len() {
+        let mut changed = false;
+        for &func in &candidates {
+            let sizes = param_sizes(hugr, func, &calls[&func]);
+            if annotated.get(&func) == Some(&sizes) {
+                continue;
+            }
+            if sizes.iter().any(Option::is_some) {
+                hugr.set_metadata(func, metadata::PARAM_SIZES, serde_json::json!(sizes));
+            } else {
+                hugr.remove_metadata(func, metadata::PARAM_SIZES);
+            }
+            annotated.insert(func, sizes);
+            changed = true;
+        }
+        if !changed {
+            break;
+        }
+    }
+    annotated
+        .values()
+        .filter(|sizes| sizes.iter().any(Option::is_some))
+        .count()
+}
+
+/// The lengths of the register parameters of a function agreed on by all
+/// its calls, `None` for the other parameters.
+fn param_sizes(hugr: &impl HugrView<Node = Node>, func: Node, calls: &[Node]) -> Vec<Option<u64>> {
+    let OpType::FuncDefn(defn) = hugr.get_optype(func) else {
+        return Vec::new();
+    };
+    let qureg = qureg_type();
+    let inputs = defn.signature().body().input_types();
+    (0..inputs.len())
+        .map(|input| {
+            if inputs[input] != qureg {
+                return None;
+            }
+            let sizes = calls
+                .iter()
+                .map(|&call| {
+                    let (src, src_port) = hugr.single_linked_output(call, input)?;
+                    static_length(hugr, src, src_port)
+                })
+                .collect::<Option<Vec<_>>>()?;
+            sizes.into_iter().all_equal_value().ok()
+        })
+        .collect()
+}
+
+/// The functions called by each function, from the calls to each function.
+fn call_graph(
+    hugr: &impl HugrView<Node = Node>,
+    calls: &BTreeMap<Node, Vec<Node>>,
+) -> BTreeMap<Node, BTreeSet<Node>> {
+    let mut callees: BTreeMap<Node, BTreeSet<Node>> = BTreeMap::new();
+    for (&callee, calls) in calls {
+        for &call in calls {
+            let mut caller = hugr.get_parent(call);
+            while let Some(node) = caller.filter(|&node| !hugr.get_optype(node).is_func_defn()) {
+                caller = hugr.get_parent(node);
+            }
+            if let Some(caller) = caller {
+                callees.entry(caller).or_default().insert(callee);
+            }
+        }
+    }
+    callees
+}
+
+/// Whether a function calls itself, directly or through other functions.
+fn is_recursive(callees: &BTreeMap<Node, BTreeSet<Node>>, func: Node) -> bool {
+    let mut visited = BTreeSet::new();
+    let mut stack = callees.get(&func).into_iter().flatten().copied().collect_vec();
+    while let Some(node) = stack.pop() {
+        if node == func {
+            return true;
+        }
+        if visited.insert(node) {
+            stack.extend(callees.get(&node).into_iter().flatten());
+        }
+    }
+    false
+}
+
+/// Resolve register permutations into a reordering of the register's qubits.
+///
+/// A [`JeffOp::QuregSwapElems`] with constant indices, or a
+/// [`JeffOp::QuregReverse`], applied directly to the register of a
+/// [`JeffOp::QuregCreate`] is removed, and the qubits given to the
+/// [`JeffOp::QuregCreate`] are reordered instead. No gates are emitted.
+///
+/// Permutations of registers whose qubits are not known, or swaps with
+/// dynamic or out-of-bounds indices, are left untouched.
+///
+/// Returns the number of resolved permutations.
+pub fn resolve_register_permutations(hugr: &mut impl HugrMut<Node = Node>) -> usize {
+    record_pass(hugr, "resolve_register_permutations");
+    let mut resolved = 0;
+    loop {
+        let Some((create, node, perm)) =
+            hugr.nodes().find_map(|node| register_permutation(hugr, node))
+        else {
+            return resolved;
+        };
+        permute_create(hugr, create, node, &perm);
+        resolved += 1;
+    }
+}
+
+/// Returns the [`JeffOp::QuregCreate`] permuted by `node`, and the position
+/// in the created register of each qubit in the permuted one.
+fn register_permutation(
+    hugr: &impl HugrView<Node = Node>,
+    node: Node,
+) -> Option<(Node, Node, Vec<usize>)> {
+    let op = hugr.get_optype(node).cast::<JeffOp>()?;
+    if !matches!(op, JeffOp::QuregSwapElems | JeffOp::QuregReverse) {
+        return None;
+    }
+    let (create, port) = hugr.single_linked_output(node, 0)?;
+    let Some(JeffOp::QuregCreate { qubits }) = hugr.get_optype(create).cast::<JeffOp>() else {
+        return None;
+    };
+    if port.index() != 0 {
+        return None;
+    }
+
+    let mut perm = (0..qubits).collect_vec();
+    match op {
+        JeffOp::QuregSwapElems => {
+            let i = usize::try_from(constant_index(hugr, node, 1)?).ok()?;
+            let j = usize::try_from(constant_index(hugr, node, 2)?).ok()?;
+            if i >= qubits || j >= qubits {
+                return None;
+            }
+            perm.swap(i, j);
+        }
+        _ => perm.reverse(),
+    }
+    Some((create, node, perm))
+}
+
+/// Reorder the inputs of a [`JeffOp::QuregCreate`] and remove the
+/// permutation applied to its register.
+fn permute_create(hugr: &mut impl HugrMut<Node = Node>, create: Node, node: Node, perm: &[usize]) {
+    let sources = (0..perm.len())
+        .map(|i| hugr.single_linked_output(create, IncomingPort::from(i)))
+        .collect_vec();
+    let targets = hugr.linked_inputs(node, OutgoingPort::from(0)).collect_vec();
+
+    hugr.remove_node(node);
+    for i in 0..perm.len() {
+        hugr.disconnect(create, IncomingPort::from(i));
+    }
+    for (i, &from) in perm.iter().enumerate() {
+        if let Some((src, src_port)) = sources[from] {
+            hugr.connect(src, src_port, create, IncomingPort::from(i));
+        }
+    }
+    for (tgt, tgt_port) in targets {
+        hugr.connect(create, OutgoingPort::from(0), tgt, tgt_port);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::extension::{intreg_type, qureg_type};
+    use crate::jeff_to_hugr;
+    use crate::passes::lower_register_measures;
+    use crate::test::catalyst_simple;
+    use hugr::builder::{
+        DFGBuilder, Dataflow, DataflowHugr, DataflowSubContainer, HugrBuilder, ModuleBuilder,
+    };
+    use hugr::extension::prelude::qb_t;
+    use hugr::ops::handle::NodeHandle;
+    use hugr::std_extensions::arithmetic::int_types::int_type;
+    use hugr::types::Signature;
+    use hugr::{Hugr, Wire};
+    use jeff::Jeff;
+    use rstest::rstest;
+    use serde_json::json;
+
+    /// How the index of the second access is given.
+    #[derive(Debug, Clone, Copy)]
+    enum SecondIndex {
+        /// The same constant as the first access.
+        Same,
+        /// A different constant.
+        Other,
+        /// A function input.
+        Dynamic,
+    }
+
+    fn access(builder: &mut DFGBuilder<Hugr>, op: JeffOp, inputs: Vec<Wire>) -> Vec<Wire> {
+        builder
+            .add_dataflow_op(op.into_extension_op(), inputs)
+            .unwrap()
+            .outputs()
+            .collect()
+    }
+
+    fn second_index(builder: &mut DFGBuilder<Hugr>, second: SecondIndex, dynamic: Wire) -> Wire {
+        match second {
+            SecondIndex::Same => builder.add_load_value(ConstInt::new_u(5, 1).unwrap()),
+            SecondIndex::Other => builder.add_load_value(ConstInt::new_u(5, 2).unwrap()),
+            SecondIndex::Dynamic => dynamic,
+        }
+    }
+
+    /// Extract a qubit from a register and insert it back.
+    fn extract_insert(second: SecondIndex) -> Hugr {
+        let mut builder = DFGBuilder::new(Signature::new(
+            vec![qureg_type(), int_type(5)],
+            vec![qureg_type()],
+        ))
+        .unwrap();
+        let [reg, dynamic] = builder.input_wires_arr();
+        let idx = builder.add_load_value(ConstInt::new_u(5, 1).unwrap());
+        let extracted = access(&mut builder, JeffOp::QuregExtractIndex, vec![reg, idx]);
+        let idx = second_index(&mut builder, second, dynamic);
+        let inserted = access(
+            &mut builder,
+            JeffOp::QuregInsertIndex,
+            vec![extracted[0], extracted[1], idx],
+        );
+        builder.finish_hugr_with_outputs(inserted).unwrap()
+    }
+
+    /// Insert a qubit into a register and extract it again.
+    fn insert_extract(second: SecondIndex) -> Hugr {
+        let mut builder = DFGBuilder::new(Signature::new(
+            vec![qureg_type(), qb_t(), int_type(5)],
+            vec![qureg_type(), qb_t()],
+        ))
+        .unwrap();
+        let [reg, qb, dynamic] = builder.input_wires_arr();
+        let idx = builder.add_load_value(ConstInt::new_u(5, 1).unwrap());
+        let inserted = access(&mut builder, JeffOp::QuregInsertIndex, vec![reg, qb, idx]);
+        let idx = second_index(&mut builder, second, dynamic);
+        let extracted = access(
+            &mut builder,
+            JeffOp::QuregExtractIndex,
+            vec![inserted[0], idx],
+        );
+        builder.finish_hugr_with_outputs(extracted).unwrap()
+    }
+
+    fn num_accesses(hugr: &Hugr) -> usize {
+        hugr.nodes()
+            .filter(|&n| hugr.get_optype(n).cast::<JeffOp>().is_some())
+            .count()
+    }
+
+    #[rstest]
+    #[case::extract_insert(extract_insert(SecondIndex::Same), 1)]
+    #[case::insert_extract(insert_extract(SecondIndex::Same), 1)]
+    #[case::extract_insert_other(extract_insert(SecondIndex::Other), 0)]
+    #[case::insert_extract_other(insert_extract(SecondIndex::Other), 0)]
+    #[case::extract_insert_dynamic(extract_insert(SecondIndex::Dynamic), 0)]
+    #[case::insert_extract_dynamic(insert_extract(SecondIndex::Dynamic), 0)]
+    fn cancel_roundtrips(#[case] mut hugr: Hugr, #[case] expected: usize) {
+        let before = num_accesses(&hugr);
+        assert_eq!(cancel_register_roundtrips(&mut hugr), expected);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(num_accesses(&hugr), before - 2 * expected);
+    }
+
+    /// A permutation applied to a created register.
+    #[derive(Debug, Clone, Copy)]
+    enum Permutation {
+        /// Swap two constant indices.
+        Swap(u64, u64),
+        /// Swap a constant index with a function input.
+        SwapDynamic,
+        /// Reverse the register.
+        Reverse,
+    }
+
+    /// Permute a register of three qubits.
+    ///
+    /// The register is created from the function inputs, or allocated if
+    /// `alloc` is set.
+    fn permuted_register(permutation: Permutation, alloc: bool) -> Hugr {
+        let qubits = if alloc { vec![] } else { vec![qb_t(); 3] };
+        let mut builder = DFGBuilder::new(Signature::new(
+            [qubits, vec![int_type(5)]].concat(),
+            vec![qureg_type()],
+        ))
+        .unwrap();
+        let mut inputs = builder.input_wires().collect_vec();
+        let dynamic = inputs.pop().unwrap();
+        let reg = if alloc {
+            let size = builder.add_load_value(ConstInt::new_u(5, 3).unwrap());
+            access(&mut builder, JeffOp::QuregAlloc, vec![size])[0]
+        } else {
+            access(&mut builder, JeffOp::QuregCreate { qubits: 3 }, inputs)[0]
+        };
+        let permuted = match permutation {
+            Permutation::Swap(i, j) => {
+                let i = builder.add_load_value(ConstInt::new_u(5, i).unwrap());
+                let j = builder.add_load_value(ConstInt::new_u(5, j).unwrap());
+                access(&mut builder, JeffOp::QuregSwapElems, vec![reg, i, j])
+            }
+            Permutation::SwapDynamic => {
+                let i = builder.add_load_value(ConstInt::new_u(5, 0).unwrap());
+                access(&mut builder, JeffOp::QuregSwapElems, vec![reg, i, dynamic])
+            }
+            Permutation::Reverse => access(&mut builder, JeffOp::QuregReverse, vec![reg]),
+        };
+        builder.finish_hugr_with_outputs(permuted).unwrap()
+    }
+
+    /// The function input given to each qubit of the created register.
+    fn created_order(hugr: &Hugr) -> Vec<usize> {
+        let create = hugr
+            .nodes()
+            .find(|&n| {
+                matches!(
+                    hugr.get_optype(n).cast::<JeffOp>(),
+                    Some(JeffOp::QuregCreate { .. })
+                )
+            })
+            .unwrap();
+        (0..3)
+            .map(|i| {
+                let (src, port) = hugr.single_linked_output(create, i).unwrap();
+                assert!(hugr.get_optype(src).is_input());
+                port.index()
+            })
+            .collect()
+    }
+
+    fn num_permutations(hugr: &Hugr) -> usize {
+        hugr.nodes()
+            .filter(|&n| {
+                matches!(
+                    hugr.get_optype(n).cast::<JeffOp>(),
+                    Some(JeffOp::QuregSwapElems | JeffOp::QuregReverse)
+                )
+            })
+            .count()
+    }
+
+    #[rstest]
+    #[case::swap(Permutation::Swap(0, 2), false, Some(vec![2, 1, 0]))]
+    #[case::swap_same(Permutation::Swap(1, 1), false, Some(vec![0, 1, 2]))]
+    #[case::reverse(Permutation::Reverse, false, Some(vec![2, 1, 0]))]
+    #[case::swap_out_of_bounds(Permutation::Swap(0, 3), false, None)]
+    #[case::swap_dynamic(Permutation::SwapDynamic, false, None)]
+    #[case::swap_alloc(Permutation::Swap(0, 1), true, None)]
+    #[case::reverse_alloc(Permutation::Reverse, true, None)]
+    fn resolve_permutations(
+        #[case] permutation: Permutation,
+        #[case] alloc: bool,
+        #[case] expected: Option<Vec<usize>>,
+    ) {
+        let mut hugr = permuted_register(permutation, alloc);
+        let resolved = resolve_register_permutations(&mut hugr);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(resolved, expected.is_some() as usize);
+        assert_eq!(num_permutations(&hugr), 1 - resolved);
+        if let Some(expected) = expected {
+            assert_eq!(created_order(&hugr), expected);
+        }
+    }
+
+    /// Extracting the first qubit of a swapped register reads the second
+    /// created one.
+    #[test]
+    fn swap_then_extract() {
+        let mut builder = DFGBuilder::new(Signature::new(
+            vec![qb_t(), qb_t()],
+            vec![qureg_type(), qb_t()],
+        ))
+        .unwrap();
+        let [q0, q1] = builder.input_wires_arr();
+        let reg = access(&mut builder, JeffOp::QuregCreate { qubits: 2 }, vec![q0, q1])[0];
+        let i = builder.add_load_value(ConstInt::new_u(5, 0).unwrap());
+        let j = builder.add_load_value(ConstInt::new_u(5, 1).unwrap());
+        let reg = access(&mut builder, JeffOp::QuregSwapElems, vec![reg, i, j])[0];
+        let idx = builder.add_load_value(ConstInt::new_u(5, 0).unwrap());
+        let extracted = access(&mut builder, JeffOp::QuregExtractIndex, vec![reg, idx]);
+        let mut hugr = builder.finish_hugr_with_outputs(extracted).unwrap();
+
+        assert_eq!(resolve_register_permutations(&mut hugr), 1);
+        cancel_register_roundtrips(&mut hugr);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(num_permutations(&hugr), 0);
+
+        let extract = hugr
+            .nodes()
+            .find(|&n| hugr.get_optype(n).cast::<JeffOp>() == Some(JeffOp::QuregExtractIndex))
+            .unwrap();
+        assert_eq!(constant_index(&hugr, extract, 1), Some(0));
+        let (create, _) = hugr.single_linked_output(extract, 0).unwrap();
+        let (src, port) = hugr.single_linked_output(create, 0).unwrap();
+        assert!(hugr.get_optype(src).is_input());
+        assert_eq!(port.index(), 1);
+    }
+
+    /// Extract a qubit from an allocated register of three qubits, read the
+    /// length of the register, and insert the qubit back.
+    fn mixed_access() -> Hugr {
+        let mut builder = DFGBuilder::new(Signature::new(
+            vec![],
+            vec![qureg_type(), int_type(INDEX_LOG_WIDTH)],
+        ))
+        .unwrap();
+        let size = builder.add_load_value(ConstInt::new_u(INDEX_LOG_WIDTH, 3).unwrap());
+        let reg = access(&mut builder, JeffOp::QuregAlloc, vec![size])[0];
+        let idx = builder.add_load_value(ConstInt::new_u(INDEX_LOG_WIDTH, 1).unwrap());
+        let [reg, qb] = access(&mut builder, JeffOp::QuregExtractIndex, vec![reg, idx])
+            .try_into()
+            .unwrap();
+        let [reg, length] = access(&mut builder, JeffOp::QuregLength, vec![reg])
+            .try_into()
+            .unwrap();
+        let idx = builder.add_load_value(ConstInt::new_u(INDEX_LOG_WIDTH, 1).unwrap());
+        let reg = access(&mut builder, JeffOp::QuregInsertIndex, vec![reg, qb, idx])[0];
+        builder.finish_hugr_with_outputs([reg, length]).unwrap()
+    }
+
+    #[test]
+    fn mixed_access_lengths() {
+        let mut hugr = mixed_access();
+        // The length read between the accesses blocks the cancellation.
+        assert_eq!(cancel_register_roundtrips(&mut hugr), 0);
+
+        // The register keeps its length while the qubit is extracted.
+        assert_eq!(fold_register_lengths(&mut hugr), 1);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        let output = hugr.get_io(hugr.entrypoint()).unwrap()[1];
+        let (load, _) = hugr.single_linked_output(output, 1).unwrap();
+        let (const_node, _) = hugr.single_linked_output(load, 0).unwrap();
+        let length = hugr.get_optype(const_node).as_const().unwrap();
+        assert_eq!(length.get_custom_value::<ConstInt>().unwrap().value_u(), 3);
+
+        // Once folded, the accesses cancel out.
+        assert_eq!(cancel_register_roundtrips(&mut hugr), 1);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        let (alloc, _) = hugr.single_linked_output(output, 0).unwrap();
+        assert_eq!(hugr.get_optype(alloc).cast::<JeffOp>(), Some(JeffOp::QuregAlloc));
+    }
+
+    #[test]
+    fn dynamic_length_unfolded() {
+        let mut builder = DFGBuilder::new(Signature::new(
+            vec![qureg_type()],
+            vec![qureg_type(), int_type(INDEX_LOG_WIDTH)],
+        ))
+        .unwrap();
+        let [reg] = builder.input_wires_arr();
+        let outputs = access(&mut builder, JeffOp::QuregLength, vec![reg]);
+        let mut hugr = builder.finish_hugr_with_outputs(outputs).unwrap();
+        assert_eq!(fold_register_lengths(&mut hugr), 0);
+    }
+
+    /// Allocate 8 qubits, split them 3/5, join them back, and measure the
+    /// slice 2..6 before inserting it back and measuring the register.
+    ///
+    /// Returns the HUGR with the register operations in order.
+    fn sliced_register() -> (Hugr, Vec<Node>) {
+        let bits_t = intreg_type(1);
+        let mut builder =
+            DFGBuilder::new(Signature::new(vec![], vec![bits_t.clone(), bits_t])).unwrap();
+        let mut ops = Vec::new();
+        let mut add = |builder: &mut DFGBuilder<Hugr>, op: JeffOp, inputs: Vec<Wire>| {
+            let node = builder
+                .add_dataflow_op(op.into_extension_op(), inputs)
+                .unwrap();
+            ops.push(node.node());
+            node.outputs().collect_vec()
+        };
+        let mut constant =
+            |value| builder.add_load_value(ConstInt::new_u(INDEX_LOG_WIDTH, value).unwrap());
+        let [eight, three, two, six, two_again] = [8, 3, 2, 6, 2].map(&mut constant);
+
+        let reg = add(&mut builder, JeffOp::QuregAlloc, vec![eight])[0];
+        let [first, second] = add(&mut builder, JeffOp::QuregSplit, vec![reg, three])
+            .try_into()
+            .unwrap();
+        let reg = add(&mut builder, JeffOp::QuregJoin, vec![first, second])[0];
+        let [rest, slice] = add(&mut builder, JeffOp::QuregExtractSlice, vec![reg, two, six])
+            .try_into()
+            .unwrap();
+        let [slice, slice_bits] = add(&mut builder, JeffOp::QuregMeasureNd, vec![slice])
+            .try_into()
+            .unwrap();
+        let reg = add(
+            &mut builder,
+            JeffOp::QuregInsertSlice,
+            vec![rest, slice, two_again],
+        )[0];
+        let bits = add(&mut builder, JeffOp::QuregMeasure, vec![reg])[0];
+        let hugr = builder
+            .finish_hugr_with_outputs([slice_bits, bits])
+            .unwrap();
+        (hugr, ops)
+    }
+
+    #[test]
+    fn sliced_register_sizes() {
+        let (mut hugr, ops) = sliced_register();
+        assert_eq!(annotate_register_sizes(&mut hugr), 6);
+        let sizes = ops
+            .iter()
+            .map(|&node| hugr.get_metadata(node, metadata::STATIC_SIZE).cloned())
+            .collect_vec();
+        let expected = [
+            Some(json!([8])),
+            Some(json!([3, 5])),
+            Some(json!([8])),
+            Some(json!([4, 4])),
+            Some(json!([4, null])),
+            Some(json!([8])),
+            None,
+        ];
+        assert_eq!(sizes, expected);
+
+        // All the measurements are lowered without a loop.
+        assert_eq!(lower_register_measures(&mut hugr), 2);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        assert!(hugr.nodes().all(|node| !hugr.get_optype(node).is_tail_loop()));
+    }
+
+    /// A module with a `helper` reading the length of its register, and a
+    /// function per size allocating a register of that size and passing it
+    /// to `helper`.
+    ///
+    /// Returns the module and the `helper` node.
+    fn register_callers(sizes: &[u64]) -> (Hugr, Node) {
+        let mut builder = ModuleBuilder::new();
+        let helper_sig =
+            Signature::new(vec![qureg_type()], vec![qureg_type(), int_type(INDEX_LOG_WIDTH)]);
+        let mut helper = builder.define_function("helper", helper_sig).unwrap();
+        let [reg] = helper.input_wires_arr();
+        let length = helper
+            .add_dataflow_op(JeffOp::QuregLength.into_extension_op(), [reg])
+            .unwrap();
+        let helper = helper.finish_with_outputs(length.outputs()).unwrap();
+
+        for (i, &size) in sizes.iter().enumerate() {
+            let caller_sig = Signature::new(vec![], vec![qureg_type()]);
+            let mut caller = builder.define_function(format!("caller_{i}"), caller_sig).unwrap();
+            let size = caller.add_load_value(ConstInt::new_u(INDEX_LOG_WIDTH, size).unwrap());
+            let alloc = caller
+                .add_dataflow_op(JeffOp::QuregAlloc.into_extension_op(), [size])
+                .unwrap();
+            let call = caller.call(helper.handle(), &[], alloc.outputs()).unwrap();
+            caller.finish_with_outputs([call.out_wire(0)]).unwrap();
+        }
+        (builder.finish_hugr().unwrap(), helper.node())
+    }
+
+    #[rstest]
+    #[case::single_caller(&[4], Some(4))]
+    #[case::agreeing_callers(&[4, 4], Some(4))]
+    #[case::disagreeing_callers(&[4, 5], None)]
+    fn propagate_sizes(#[case] sizes: &[u64], #[case] expected: Option<u64>) {
+        let (mut hugr, helper) = register_callers(sizes);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        // The length is unknown inside the helper without the annotation.
+        assert_eq!(fold_register_lengths(&mut hugr.clone()), 0);
+
+        let annotated = propagate_register_sizes(&mut hugr);
+        assert_eq!(annotated, expected.is_some() as usize);
+        assert_eq!(
+            hugr.get_metadata(helper, metadata::PARAM_SIZES),
+            expected.map(|size| json!([size])).as_ref()
+        );
+        assert_eq!(fold_register_lengths(&mut hugr), expected.is_some() as usize);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+    }
+
+    #[rstest]
+    fn cancel_in_translation(catalyst_simple: Jeff<'static>) {
+        let mut hugr = jeff_to_hugr(&catalyst_simple).unwrap();
+        let before = num_accesses(&hugr);
+        let cancelled = cancel_register_roundtrips(&mut hugr);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(num_accesses(&hugr), before - 2 * cancelled);
+    }
+}
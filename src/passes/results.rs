@@ -0,0 +1,832 @@
+//! Canonicalization of the measurement results returned by the functions.
+
+use std::collections::BTreeMap;
+
+use hugr::extension::prelude::{bool_t, qb_t};
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::ops::{Const, LoadConstant, OpType, Value};
+use hugr::std_extensions::arithmetic::conversions::{ConvertOpDef, ConvertOpType};
+use hugr::std_extensions::arithmetic::int_ops::{ConcreteIntOp, IntOpDef};
+use hugr::std_extensions::arithmetic::int_types::{ConstInt, int_type};
+use hugr::types::{Signature, Type};
+use hugr::{Direction, HugrView, IncomingPort, Node, OutgoingPort};
+use itertools::Itertools;
+use serde::Serialize;
+
+use super::fuse::remove_unused_constant;
+use crate::extension::register_semantics::constant_input;
+use crate::extension::{JeffOp, intreg_type};
+use crate::metadata;
+use crate::optype::tket_ops::QuantumOp;
+use crate::provenance::record_pass;
+use crate::types::jeff_int_width_to_hugr_width;
+
+/// How a function returns the outcomes of its final measurements.
+///
+/// See [`canonicalize_results`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum ResultConvention {
+    /// One `bool` output per measured qubit.
+    Bits,
+    /// A single integer output, with the outcome of the `i`-th measured
+    /// qubit in bit `i`.
+    PackedInt {
+        /// The width of the integer in bits, rounded up to a power of two.
+        /// Widths above 64 are not supported.
+        width: u8,
+    },
+    /// A single _jeff_ `int(1)` array output, with the outcome of the `i`-th
+    /// measured qubit in element `i`.
+    BitArray,
+}
+
+/// The origin of a measured qubit in the function measuring it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum QubitOrigin {
+    /// The function input at this port.
+    Input(usize),
+    /// The `QAlloc` at this position among the allocations of the function
+    /// region.
+    Alloc(usize),
+}
+
+/// A measurement outcome returned by a function.
+#[derive(Debug, Clone, Copy)]
+struct ResultBit {
+    /// The port of the measurement producing the outcome.
+    outcome: (Node, OutgoingPort),
+    /// The position of the outcome in the original convention: the index of
+    /// the output among the results, the element of the array or the bit of
+    /// the integer.
+    position: usize,
+    /// The origin of the measured qubit, if it could be followed.
+    origin: Option<QubitOrigin>,
+}
+
+/// The measurement outcomes returned by a function.
+#[derive(Debug, Clone)]
+struct FunctionResults {
+    /// The convention of the function.
+    original: ResultConvention,
+    /// The output ports carrying the results.
+    ports: Vec<usize>,
+    /// The outcomes, in canonical order.
+    bits: Vec<ResultBit>,
+    /// The operations packing the outcomes into the outputs.
+    packing: Vec<Node>,
+}
+
+/// Rewrite the functions ending by measuring qubits and returning the
+/// outcomes into a canonical result convention.
+///
+/// The results of a function are the outputs computed only from
+/// measurement outcomes, which are not used elsewhere:
+///
+/// - `bool` outputs fed directly by a measurement, for [`ResultConvention::Bits`],
+/// - a single [`JeffOp::IntArrayCreate`] of `int(1)` outcomes, for
+///   [`ResultConvention::BitArray`],
+/// - a single integer built from the outcomes with `ifrombool`, `iwiden_u`,
+///   `ishl` by a constant, and `ior` or `iadd` of disjoint bits, for
+///   [`ResultConvention::PackedInt`].
+///
+/// The results are returned with the requested `convention`, after the
+/// other outputs of the function, ordered by the provenance of the measured
+/// qubits: the function inputs by port, then the qubits allocated in the
+/// function region, by allocation order. The outcomes of qubits that cannot
+/// be followed to their origin come last, in their original order. The
+/// convention and the qubit of each result are recorded in the
+/// [`metadata::RESULTS`] of the function.
+///
+/// Only the functions that are not called or loaded are rewritten, as the
+/// rewrite changes their signature. Functions already following the
+/// convention, and those whose results do not fit in the packed integer,
+/// are left unchanged.
+///
+/// Returns the number of rewritten functions.
+///
+/// # Examples
+///
+/// ```
+/// use hugr_jeff::doctest_support::measured_register;
+/// use hugr_jeff::hugr::HugrView;
+/// use hugr_jeff::passes::{ResultConvention, canonicalize_results, lower_register_measures};
+///
+/// // A DFG measuring a register, without functions to rewrite.
+/// let mut hugr = measured_register(3);
+/// lower_register_measures(&mut hugr);
+/// assert_eq!(canonicalize_results(&mut hugr, ResultConvention::Bits), 0);
+/// hugr.validate().unwrap();
+/// ```
+pub fn canonicalize_results(
+    hugr: &mut impl HugrMut<Node = Node>,
+    convention: ResultConvention,
+) -> usize {
+    record_pass(hugr, "canonicalize_results");
+    let capacity = match convention {
+        ResultConvention::PackedInt { width } if width <= 64 => width as usize,
+        ResultConvention::PackedInt { .. } => 0,
+        _ => usize::MAX,
+    };
+    let functions = hugr
+        .children(hugr.module_root())
+        .filter(|&func| hugr.get_optype(func).is_func_defn())
+        .filter(|&func| {
+            hugr.static_targets(func)
+                .is_none_or(|mut targets| targets.next().is_none())
+        })
+        .collect_vec();
+
+    let mut rewritten = 0;
+    for func in functions {
+        let Some(results) = function_results(hugr, func) else {
+            continue;
+        };
+        if results.bits.len() > capacity {
+            continue;
+        }
+        if !results.is_canonical(hugr, func, convention) {
+            rewrite_results(hugr, func, &results, convention);
+            rewritten += 1;
+        }
+        hugr.set_metadata(
+            func,
+            metadata::RESULTS,
+            serde_json::json!({
+                "convention": convention,
+                "original": results.original,
+                "qubits": results.bits.iter().map(|bit| bit.origin).collect_vec(),
+                "positions": results.bits.iter().map(|bit| bit.position).collect_vec(),
+            }),
+        );
+    }
+    rewritten
+}
+
+impl FunctionResults {
+    /// Whether the results already follow a convention, in canonical order
+    /// after the other outputs.
+    fn is_canonical(
+        &self,
+        hugr: &impl HugrView<Node = Node>,
+        func: Node,
+        convention: ResultConvention,
+    ) -> bool {
+        let [_, output] = hugr.get_io(func).unwrap();
+        let outputs = hugr.signature(output).unwrap().input_count();
+        let ordered = self
+            .bits
+            .iter()
+            .enumerate()
+            .all(|(i, bit)| bit.position == i);
+        let trailing = (outputs - self.ports.len()..outputs).eq(self.ports.iter().copied());
+        self.original == convention && ordered && trailing
+    }
+}
+
+/// Find the measurement results returned by a function, see
+/// [`canonicalize_results`].
+fn function_results(hugr: &impl HugrView<Node = Node>, func: Node) -> Option<FunctionResults> {
+    let [input, output] = hugr.get_io(func)?;
+    let outputs = hugr.signature(output)?.input_count();
+    let mut ports = Vec::new();
+    let mut outcomes = Vec::new();
+    let mut packing = Vec::new();
+    let mut packed = None;
+    for port in 0..outputs {
+        let Some((src, src_port)) = hugr.single_linked_output(output, port) else {
+            continue;
+        };
+        if is_outcome(hugr, src, src_port) {
+            ports.push(port);
+            outcomes.push(((src, src_port), outcomes.len()));
+        } else if let Some((convention, bits)) = packed_results(hugr, src, src_port, &mut packing) {
+            // A single output can pack the results.
+            if packed.replace(convention).is_some() {
+                return None;
+            }
+            ports.push(port);
+            outcomes.extend(bits);
+        }
+    }
+    let original = match packed {
+        Some(convention) if ports.len() == 1 => convention,
+        Some(_) => return None,
+        None if !ports.is_empty() => ResultConvention::Bits,
+        None => return None,
+    };
+
+    let allocs: BTreeMap<Node, usize> = hugr
+        .children(func)
+        .filter(|&node| QuantumOp::of(hugr.get_optype(node)) == Some(QuantumOp::QAlloc))
+        .enumerate()
+        .map(|(k, node)| (node, k))
+        .collect();
+    let mut bits = outcomes
+        .into_iter()
+        .map(|(outcome, position)| ResultBit {
+            outcome,
+            position,
+            origin: qubit_origin(hugr, outcome.0, input, &allocs),
+        })
+        .collect_vec();
+    bits.sort_by_key(|bit| (bit.origin.is_none(), bit.origin, bit.position));
+    Some(FunctionResults {
+        original,
+        ports,
+        bits,
+        packing,
+    })
+}
+
+/// Whether a port is the outcome of a measurement, used only once.
+fn is_outcome(hugr: &impl HugrView<Node = Node>, node: Node, port: OutgoingPort) -> bool {
+    let outcome_port = match QuantumOp::of(hugr.get_optype(node)) {
+        Some(QuantumOp::Measure) => 1,
+        Some(QuantumOp::MeasureDestructive) => 0,
+        _ => return false,
+    };
+    port.index() == outcome_port && hugr.linked_inputs(node, port).count() == 1
+}
+
+/// The measurement outcomes packed into an array or an integer output, with
+/// their position.
+///
+/// The packing operations are added to `packing`.
+fn packed_results(
+    hugr: &impl HugrView<Node = Node>,
+    node: Node,
+    port: OutgoingPort,
+    packing: &mut Vec<Node>,
+) -> Option<(ResultConvention, Vec<((Node, OutgoingPort), usize)>)> {
+    if hugr.linked_inputs(node, port).count() != 1 {
+        return None;
+    }
+    if let Some(JeffOp::IntArrayCreate { bits: 1, inputs }) = hugr.get_optype(node).cast() {
+        let elements = (0..inputs)
+            .map(|i| {
+                let (src, src_port) = hugr.single_linked_output(node, i)?;
+                is_outcome(hugr, src, src_port).then_some(((src, src_port), i))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        packing.push(node);
+        return Some((ResultConvention::BitArray, elements));
+    }
+
+    let width = int_width(hugr, node, port)?;
+    let mut nodes = Vec::new();
+    let bits = packed_bits(hugr, node, &mut nodes)?;
+    if bits.is_empty() || bits.keys().any(|&position| position >= width) {
+        return None;
+    }
+    packing.extend(nodes);
+    let bits = bits
+        .into_iter()
+        .map(|(position, outcome)| (outcome, position as usize))
+        .collect();
+    Some((ResultConvention::PackedInt { width: width as u8 }, bits))
+}
+
+/// The width in bits of an integer output of a node.
+fn int_width(hugr: &impl HugrView<Node = Node>, node: Node, port: OutgoingPort) -> Option<u64> {
+    let ty = hugr.signature(node)?.out_port_type(port)?.clone();
+    let log_width = (0..=6).find(|&log_width| ty == int_type(log_width))?;
+    Some(1 << log_width)
+}
+
+/// The measurement outcomes packed into an integer, by bit position.
+///
+/// Zero constants pack no outcomes. The packing operations, other than the
+/// constants, are added to `nodes`. Outcomes shifted out of the width of a
+/// packing operation are not packed.
+fn packed_bits(
+    hugr: &impl HugrView<Node = Node>,
+    node: Node,
+    nodes: &mut Vec<Node>,
+) -> Option<BTreeMap<u64, (Node, OutgoingPort)>> {
+    let optype = hugr.get_optype(node);
+    if optype.is_load_constant() {
+        let const_node = hugr.static_source(node)?;
+        let value = hugr.get_optype(const_node).as_const()?;
+        let value = value.get_custom_value::<ConstInt>()?.value_u();
+        return (value == 0).then(BTreeMap::new);
+    }
+    if optype.cast::<ConvertOpType>() == Some(ConvertOpDef::ifrombool.without_log_width()) {
+        let (src, src_port) = hugr.single_linked_output(node, 0)?;
+        if !is_outcome(hugr, src, src_port) {
+            return None;
+        }
+        nodes.push(node);
+        return Some(BTreeMap::from([(0, (src, src_port))]));
+    }
+
+    let bits = match optype.cast::<ConcreteIntOp>()?.def {
+        IntOpDef::iwiden_u => packed_input(hugr, node, 0, nodes)?,
+        IntOpDef::ishl => {
+            let shift = constant_input(hugr, node, 1)?;
+            packed_input(hugr, node, 0, nodes)?
+                .into_iter()
+                .map(|(position, outcome)| Some((position.checked_add(shift)?, outcome)))
+                .collect::<Option<_>>()?
+        }
+        IntOpDef::ior | IntOpDef::iadd => {
+            let mut bits = packed_input(hugr, node, 0, nodes)?;
+            for (position, outcome) in packed_input(hugr, node, 1, nodes)? {
+                // Overlapping bits would not be packed.
+                if bits.insert(position, outcome).is_some() {
+                    return None;
+                }
+            }
+            bits
+        }
+        _ => return None,
+    };
+    let width = int_width(hugr, node, OutgoingPort::from(0))?;
+    if bits.keys().any(|&position| position >= width) {
+        return None;
+    }
+    nodes.push(node);
+    Some(bits)
+}
+
+/// The measurement outcomes packed into an input of a packing operation,
+/// see [`packed_bits`].
+fn packed_input(
+    hugr: &impl HugrView<Node = Node>,
+    node: Node,
+    port: usize,
+    nodes: &mut Vec<Node>,
+) -> Option<BTreeMap<u64, (Node, OutgoingPort)>> {
+    let (src, src_port) = hugr.single_linked_output(node, port)?;
+    let constant = hugr.get_optype(src).is_load_constant();
+    if !constant && hugr.linked_inputs(src, src_port).count() != 1 {
+        return None;
+    }
+    packed_bits(hugr, src, nodes)
+}
+
+/// The origin of the qubit measured by a node, following the qubit wire
+/// back through the operations of the function region.
+fn qubit_origin(
+    hugr: &impl HugrView<Node = Node>,
+    measure: Node,
+    input: Node,
+    allocs: &BTreeMap<Node, usize>,
+) -> Option<QubitOrigin> {
+    let (mut node, mut port) = hugr.single_linked_output(measure, 0)?;
+    loop {
+        if node == input {
+            return Some(QubitOrigin::Input(port.index()));
+        }
+        if let Some(&k) = allocs.get(&node) {
+            return Some(QubitOrigin::Alloc(k));
+        }
+        // Quantum operations return their qubits at the ports they read
+        // them from.
+        let signature = hugr.signature(node)?;
+        let in_port = IncomingPort::from(port.index());
+        if signature.out_port_type(port) != Some(&qb_t())
+            || signature.in_port_type(in_port) != Some(&qb_t())
+        {
+            return None;
+        }
+        (node, port) = hugr.single_linked_output(node, in_port)?;
+    }
+}
+
+/// Replace the results of a function by the results of a convention.
+fn rewrite_results(
+    hugr: &mut impl HugrMut<Node = Node>,
+    func: Node,
+    results: &FunctionResults,
+    convention: ResultConvention,
+) {
+    let [_, output] = hugr.get_io(func).unwrap();
+    let OpType::Output(output_op) = hugr.get_optype(output) else {
+        panic!("the second child of a function is its output");
+    };
+    // The other outputs keep their order, before the results.
+    let kept = output_op
+        .types
+        .iter()
+        .enumerate()
+        .filter(|(port, _)| !results.ports.contains(port))
+        .map(|(port, ty)| (ty.clone(), hugr.single_linked_output(output, port)))
+        .collect_vec();
+    let order_port = hugr.get_optype(output).other_input_port();
+    let predecessors = order_port
+        .map(|port| hugr.linked_outputs(output, port).collect_vec())
+        .unwrap_or_default();
+    for port in 0..hugr.num_inputs(output) {
+        hugr.disconnect(output, IncomingPort::from(port));
+    }
+    remove_packing(hugr, &results.packing);
+
+    let outcomes = results.bits.iter().map(|bit| bit.outcome).collect_vec();
+    let packed = pack_outcomes(hugr, func, &outcomes, convention);
+    let (types, sources): (Vec<Type>, Vec<_>) = kept
+        .into_iter()
+        .chain(packed.into_iter().map(|(ty, src)| (ty, Some(src))))
+        .unzip();
+
+    if let OpType::FuncDefn(defn) = hugr.optype_mut(func) {
+        let inputs = defn.signature().body().input().clone();
+        *defn.signature_mut() = Signature::new(inputs, types.clone()).into();
+    }
+    if let OpType::Output(output_op) = hugr.optype_mut(output) {
+        output_op.types = types.into();
+    }
+    let optype = hugr.get_optype(output);
+    let (incoming, outgoing) = (
+        optype.port_count(Direction::Incoming),
+        optype.port_count(Direction::Outgoing),
+    );
+    hugr.set_num_ports(output, incoming, outgoing);
+
+    for (port, source) in sources.into_iter().enumerate() {
+        if let Some((src, src_port)) = source {
+            hugr.connect(src, src_port, output, port);
+        }
+    }
+    if let Some(port) = hugr.get_optype(output).other_input_port() {
+        for (pred, pred_port) in predecessors {
+            hugr.connect(pred, pred_port, output, port);
+        }
+    }
+}
+
+/// Remove the packing operations of the original results, along with the
+/// constants only they used.
+fn remove_packing(hugr: &mut impl HugrMut<Node = Node>, packing: &[Node]) {
+    let constants = packing
+        .iter()
+        .flat_map(|&node| {
+            hugr.node_inputs(node)
+                .filter_map(move |port| hugr.single_linked_output(node, port))
+        })
+        .map(|(src, _)| src)
+        .filter(|&src| hugr.get_optype(src).is_load_constant())
+        .unique()
+        .collect_vec();
+    for &node in packing {
+        hugr.remove_node(node);
+    }
+    for load in constants {
+        remove_unused_constant(hugr, load);
+    }
+}
+
+/// Add the operations returning measurement outcomes with a convention.
+///
+/// Returns the type and the source of each result.
+fn pack_outcomes(
+    hugr: &mut impl HugrMut<Node = Node>,
+    parent: Node,
+    outcomes: &[(Node, OutgoingPort)],
+    convention: ResultConvention,
+) -> Vec<(Type, (Node, OutgoingPort))> {
+    match convention {
+        ResultConvention::Bits => outcomes
+            .iter()
+            .map(|&outcome| (bool_t(), outcome))
+            .collect(),
+        ResultConvention::BitArray => {
+            let create = JeffOp::IntArrayCreate {
+                bits: 1,
+                inputs: outcomes.len(),
+            };
+            let create = hugr.add_node_with_parent(parent, create.into_extension_op());
+            for (i, &(src, src_port)) in outcomes.iter().enumerate() {
+                hugr.connect(src, src_port, create, i);
+            }
+            vec![(intreg_type(1), (create, OutgoingPort::from(0)))]
+        }
+        ResultConvention::PackedInt { width } => {
+            let log_width = jeff_int_width_to_hugr_width(width);
+            let mut packed: Option<(Node, OutgoingPort)> = None;
+            for (i, &(src, src_port)) in outcomes.iter().enumerate() {
+                let bit =
+                    hugr.add_node_with_parent(parent, ConvertOpDef::ifrombool.without_log_width());
+                hugr.connect(src, src_port, bit, 0);
+                let mut bit = (bit, OutgoingPort::from(0));
+                if log_width > 0 {
+                    let widen = IntOpDef::iwiden_u.with_two_log_widths(0, log_width);
+                    bit = apply(hugr, parent, widen, &[bit]);
+                }
+                if i > 0 {
+                    let value = Value::extension(ConstInt::new_u(log_width, i as u64).unwrap());
+                    let load = hugr.add_node_with_parent(
+                        parent,
+                        LoadConstant {
+                            datatype: value.get_type(),
+                        },
+                    );
+                    let const_node = hugr.add_node_with_parent(parent, Const::new(value));
+                    hugr.connect(const_node, 0, load, 0);
+                    let shift = IntOpDef::ishl.with_log_width(log_width);
+                    bit = apply(hugr, parent, shift, &[bit, (load, OutgoingPort::from(0))]);
+                }
+                packed = Some(match packed {
+                    Some(packed) => {
+                        let or = IntOpDef::ior.with_log_width(log_width);
+                        apply(hugr, parent, or, &[packed, bit])
+                    }
+                    None => bit,
+                });
+            }
+            let packed = packed.expect("functions return at least one result");
+            vec![(int_type(log_width), packed)]
+        }
+    }
+}
+
+/// Add an operation reading `inputs`, returning its first output.
+fn apply(
+    hugr: &mut impl HugrMut<Node = Node>,
+    parent: Node,
+    op: impl Into<OpType>,
+    inputs: &[(Node, OutgoingPort)],
+) -> (Node, OutgoingPort) {
+    let node = hugr.add_node_with_parent(parent, op);
+    for (port, &(src, src_port)) in inputs.iter().enumerate() {
+        hugr.connect(src, src_port, node, port);
+    }
+    (node, OutgoingPort::from(0))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::optype::tket_ops;
+    use crate::test::assert_hugr_equivalent;
+    use hugr::builder::handle::BuildHandle;
+    use hugr::builder::{Dataflow, DataflowSubContainer, HugrBuilder, ModuleBuilder};
+    use hugr::ops::handle::FuncID;
+    use hugr::{Hugr, type_row};
+    use rstest::rstest;
+    use serde_json::json;
+
+    /// Define a kernel entangling two qubits and measuring them, the second
+    /// one first, and returning the outcomes with a convention.
+    ///
+    /// The packed integer holds the outcome of the first qubit in bit 1.
+    fn define_kernel(
+        module: &mut ModuleBuilder<Hugr>,
+        original: ResultConvention,
+    ) -> BuildHandle<FuncID<true>> {
+        let outputs = match original {
+            ResultConvention::Bits => vec![bool_t(); 2],
+            ResultConvention::BitArray => vec![intreg_type(1)],
+            ResultConvention::PackedInt { width } => {
+                vec![int_type(jeff_int_width_to_hugr_width(width))]
+            }
+        };
+        let signature = Signature::new(type_row![], outputs);
+        let mut func = module.define_function("kernel", signature).unwrap();
+        let q0 = func
+            .add_dataflow_op(tket_ops::qalloc(), [])
+            .unwrap()
+            .out_wire(0);
+        let q1 = func
+            .add_dataflow_op(tket_ops::qalloc(), [])
+            .unwrap()
+            .out_wire(0);
+        let [q0] = func
+            .add_dataflow_op(QuantumOp::H, [q0])
+            .unwrap()
+            .outputs_arr();
+        let [q0, q1] = func
+            .add_dataflow_op(QuantumOp::CX, [q0, q1])
+            .unwrap()
+            .outputs_arr();
+        let b1 = func
+            .add_dataflow_op(tket_ops::measure_destructive(), [q1])
+            .unwrap()
+            .out_wire(0);
+        let b0 = func
+            .add_dataflow_op(tket_ops::measure_destructive(), [q0])
+            .unwrap()
+            .out_wire(0);
+
+        let results = match original {
+            ResultConvention::Bits => vec![b0, b1],
+            ResultConvention::BitArray => {
+                let create = JeffOp::IntArrayCreate { bits: 1, inputs: 2 };
+                let create = func
+                    .add_dataflow_op(create.into_extension_op(), [b0, b1])
+                    .unwrap();
+                vec![create.out_wire(0)]
+            }
+            ResultConvention::PackedInt { width } => {
+                // `(b0 << 1) + b1`
+                let log_width = jeff_int_width_to_hugr_width(width);
+                let [b0, b1] = [b0, b1].map(|bit| {
+                    let bit = func
+                        .add_dataflow_op(ConvertOpDef::ifrombool.without_log_width(), [bit])
+                        .unwrap()
+                        .out_wire(0);
+                    let widen = IntOpDef::iwiden_u.with_two_log_widths(0, log_width);
+                    func.add_dataflow_op(widen, [bit]).unwrap().out_wire(0)
+                });
+                let one = func.add_load_value(ConstInt::new_u(log_width, 1).unwrap());
+                let high = func
+                    .add_dataflow_op(IntOpDef::ishl.with_log_width(log_width), [b0, one])
+                    .unwrap()
+                    .out_wire(0);
+                let sum = func
+                    .add_dataflow_op(IntOpDef::iadd.with_log_width(log_width), [high, b1])
+                    .unwrap();
+                vec![sum.out_wire(0)]
+            }
+        };
+        func.finish_with_outputs(results).unwrap()
+    }
+
+    fn kernel(original: ResultConvention) -> Hugr {
+        let mut module = ModuleBuilder::new();
+        define_kernel(&mut module, original);
+        module.finish_hugr().unwrap()
+    }
+
+    const PACKED: ResultConvention = ResultConvention::PackedInt { width: 8 };
+
+    #[rstest]
+    #[case::bits(ResultConvention::Bits)]
+    #[case::bit_array(ResultConvention::BitArray)]
+    #[case::packed_int(PACKED)]
+    fn canonical_results(#[case] convention: ResultConvention) {
+        let originals = [ResultConvention::Bits, ResultConvention::BitArray, PACKED];
+        let hugrs = originals.map(|original| {
+            let mut hugr = kernel(original);
+            hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+            // The packed outcomes are not in canonical order.
+            let expected = original != convention || original == PACKED;
+            assert_eq!(
+                canonicalize_results(&mut hugr, convention),
+                expected as usize
+            );
+            hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+            hugr
+        });
+        for hugr in &hugrs[1..] {
+            assert_hugr_equivalent(&hugrs[0], hugr);
+        }
+
+        for (hugr, original) in hugrs.iter().zip(originals) {
+            let func = hugr.children(hugr.module_root()).next().unwrap();
+            let positions = match original {
+                PACKED => [1, 0],
+                _ => [0, 1],
+            };
+            assert_eq!(
+                hugr.get_metadata(func, metadata::RESULTS),
+                Some(&json!({
+                    "convention": convention,
+                    "original": original,
+                    "qubits": [{"alloc": 0}, {"alloc": 1}],
+                    "positions": positions,
+                }))
+            );
+        }
+    }
+
+    #[rstest]
+    fn reordered_outputs() {
+        let mut module = ModuleBuilder::new();
+        let signature = Signature::new(vec![qb_t(), qb_t()], vec![bool_t(), qb_t(), bool_t()]);
+        let mut func = module.define_function("kernel", signature).unwrap();
+        let [q0, q1] = func.input_wires_arr();
+        let [q0, b0] = func
+            .add_dataflow_op(tket_ops::measure(), [q0])
+            .unwrap()
+            .outputs_arr();
+        let b1 = func
+            .add_dataflow_op(tket_ops::measure_destructive(), [q1])
+            .unwrap()
+            .out_wire(0);
+        func.finish_with_outputs([b1, q0, b0]).unwrap();
+        let mut hugr = module.finish_hugr().unwrap();
+
+        assert_eq!(canonicalize_results(&mut hugr, ResultConvention::Bits), 1);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        let func = hugr.children(hugr.module_root()).next().unwrap();
+        let OpType::FuncDefn(defn) = hugr.get_optype(func) else {
+            panic!("not a function");
+        };
+        // The qubit is returned first, followed by the outcomes by input port.
+        assert_eq!(
+            defn.signature().body().output(),
+            &vec![qb_t(), bool_t(), bool_t()].into()
+        );
+        let [_, output] = hugr.get_io(func).unwrap();
+        let (measure, _) = hugr.single_linked_output(output, 1).unwrap();
+        assert_eq!(
+            QuantumOp::of(hugr.get_optype(measure)),
+            Some(QuantumOp::Measure)
+        );
+        assert_eq!(
+            hugr.get_metadata(func, metadata::RESULTS).unwrap()["positions"],
+            json!([1, 0])
+        );
+        // Running the pass again leaves the function unchanged.
+        assert_eq!(canonicalize_results(&mut hugr, ResultConvention::Bits), 0);
+    }
+
+    #[rstest]
+    fn unchanged_functions() {
+        // The outcome is also used by a second output.
+        let mut module = ModuleBuilder::new();
+        let signature = Signature::new(vec![qb_t()], vec![bool_t(), bool_t()]);
+        let mut func = module.define_function("reused", signature).unwrap();
+        let [qubit] = func.input_wires_arr();
+        let bit = func
+            .add_dataflow_op(tket_ops::measure_destructive(), [qubit])
+            .unwrap()
+            .out_wire(0);
+        func.finish_with_outputs([bit, bit]).unwrap();
+
+        // The kernel is called.
+        let kernel = define_kernel(&mut module, ResultConvention::Bits);
+        let signature = Signature::new(type_row![], vec![bool_t(); 2]);
+        let mut main = module.define_function("main", signature).unwrap();
+        let call = main.call(kernel.handle(), &[], []).unwrap();
+        main.finish_with_outputs(call.outputs()).unwrap();
+        let mut hugr = module.finish_hugr().unwrap();
+
+        assert_eq!(
+            canonicalize_results(&mut hugr, ResultConvention::BitArray),
+            0
+        );
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        assert!(
+            hugr.children(hugr.module_root())
+                .all(|func| hugr.get_metadata(func, metadata::RESULTS).is_none())
+        );
+    }
+
+    #[rstest]
+    fn results_exceeding_width() {
+        let mut hugr = kernel(ResultConvention::Bits);
+        let convention = ResultConvention::PackedInt { width: 1 };
+        assert_eq!(canonicalize_results(&mut hugr, convention), 0);
+    }
+
+    /// A kernel returning a measurement outcome widened to
+    /// `int(2^log_width)`, shifted left by each of `shifts`, and widened to
+    /// `int(2^out_log_width)`.
+    fn shifted_outcome(log_width: u8, shifts: &[u64], out_log_width: u8) -> Hugr {
+        let mut module = ModuleBuilder::new();
+        let signature = Signature::new(type_row![], vec![int_type(out_log_width)]);
+        let mut func = module.define_function("kernel", signature).unwrap();
+        let qubit = func
+            .add_dataflow_op(tket_ops::qalloc(), [])
+            .unwrap()
+            .out_wire(0);
+        let bit = func
+            .add_dataflow_op(tket_ops::measure_destructive(), [qubit])
+            .unwrap()
+            .out_wire(0);
+        let bit = func
+            .add_dataflow_op(ConvertOpDef::ifrombool.without_log_width(), [bit])
+            .unwrap()
+            .out_wire(0);
+        let widen = IntOpDef::iwiden_u.with_two_log_widths(0, log_width);
+        let mut packed = func.add_dataflow_op(widen, [bit]).unwrap().out_wire(0);
+        for &shift in shifts {
+            let shift = func.add_load_value(ConstInt::new_u(log_width, shift).unwrap());
+            packed = func
+                .add_dataflow_op(IntOpDef::ishl.with_log_width(log_width), [packed, shift])
+                .unwrap()
+                .out_wire(0);
+        }
+        if out_log_width != log_width {
+            let widen = IntOpDef::iwiden_u.with_two_log_widths(log_width, out_log_width);
+            packed = func.add_dataflow_op(widen, [packed]).unwrap().out_wire(0);
+        }
+        func.finish_with_outputs([packed]).unwrap();
+        module.finish_hugr().unwrap()
+    }
+
+    /// Outcomes are only recognized at positions within the width of every
+    /// packing operation.
+    #[rstest]
+    #[case::within_width(3, &[7], 5, 1)]
+    #[case::shifted_out_before_widening(3, &[10], 5, 0)]
+    #[case::overflowing_position(6, &[1, u64::MAX], 6, 0)]
+    fn shifted_outcome_positions(
+        #[case] log_width: u8,
+        #[case] shifts: &[u64],
+        #[case] out_log_width: u8,
+        #[case] expected: usize,
+    ) {
+        let mut hugr = shifted_outcome(log_width, shifts, out_log_width);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(
+            canonicalize_results(&mut hugr, ResultConvention::Bits),
+            expected
+        );
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+    }
+}
@@ -0,0 +1,157 @@
+//! Lowering of integer array reversals into element-wise copies.
+
+use hugr::builder::{
+    BuildError, DFGBuilder, Dataflow, DataflowHugr, DataflowSubContainer, SubContainer,
+};
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::std_extensions::arithmetic::int_ops::IntOpDef;
+use hugr::std_extensions::arithmetic::int_types::{ConstInt, int_type};
+use hugr::types::Signature;
+use hugr::{Hugr, HugrView, Node, type_row};
+use itertools::Itertools;
+
+use super::measure::{INDEX_LOG_WIDTH, replace_with_dfg};
+use crate::extension::{JeffOp, intreg_type};
+use crate::provenance::record_pass;
+
+/// Replace the [`JeffOp::IntArrayReverse`] operations by a loop copying each
+/// element of the array to its mirrored index in a new array.
+///
+/// Each lowered operation is replaced by a nested dataflow graph.
+///
+/// Returns the number of lowered operations.
+pub fn lower_int_array_reverses(hugr: &mut impl HugrMut<Node = Node>) -> usize {
+    record_pass(hugr, "lower_int_array_reverses");
+    let reverses = hugr
+        .nodes()
+        .filter_map(|node| match hugr.get_optype(node).cast::<JeffOp>()? {
+            JeffOp::IntArrayReverse { bits } => Some((node, bits)),
+            _ => None,
+        })
+        .collect_vec();
+
+    for &(node, bits) in &reverses {
+        let lowered = loop_reverse(bits).expect("Failed to build the array reversal");
+        replace_with_dfg(hugr, node, lowered);
+    }
+    reverses.len()
+}
+
+/// Copy element `i` of an array to index `len - 1 - i` of a zeroed array.
+fn loop_reverse(bits: u8) -> Result<Hugr, BuildError> {
+    let index_t = int_type(INDEX_LOG_WIDTH);
+    let array_t = intreg_type(bits);
+
+    let mut builder = DFGBuilder::new(Signature::new_endo(vec![array_t.clone()]))?;
+    let [array] = builder.input_wires_arr();
+    let len = builder
+        .add_dataflow_op(
+            JeffOp::IntArrayLength { bits }.into_extension_op(),
+            [array],
+        )?
+        .out_wire(0);
+    let reversed = builder
+        .add_dataflow_op(JeffOp::IntArrayZero { bits }.into_extension_op(), [len])?
+        .out_wire(0);
+    let zero = builder.add_load_value(ConstInt::new_u(INDEX_LOG_WIDTH, 0).unwrap());
+
+    let mut tail_loop = builder.tail_loop_builder(
+        [],
+        [
+            (array_t.clone(), array),
+            (array_t.clone(), reversed),
+            (index_t.clone(), zero),
+            (index_t.clone(), len),
+        ],
+        type_row![],
+    )?;
+    let [array, reversed, index, len] = tail_loop.input_wires_arr();
+
+    // Guard the first iteration against empty arrays.
+    let in_bounds = tail_loop
+        .add_dataflow_op(IntOpDef::ilt_u.with_log_width(INDEX_LOG_WIDTH), [index, len])?
+        .out_wire(0);
+    let mut cond = tail_loop.conditional_builder(
+        ([type_row![], type_row![]], in_bounds),
+        [
+            (array_t.clone(), array),
+            (array_t.clone(), reversed),
+            (index_t.clone(), index),
+            (index_t.clone(), len),
+        ],
+        vec![array_t.clone(), array_t, index_t.clone(), index_t].into(),
+    )?;
+    let out_of_bounds = cond.case_builder(0)?;
+    let inputs = out_of_bounds.input_wires();
+    out_of_bounds.finish_with_outputs(inputs)?;
+
+    let mut copy = cond.case_builder(1)?;
+    let [array, reversed, index, len] = copy.input_wires_arr();
+    let value = copy
+        .add_dataflow_op(
+            JeffOp::IntArrayGet { bits }.into_extension_op(),
+            [array, index],
+        )?
+        .out_wire(0);
+    let one = copy.add_load_value(ConstInt::new_u(INDEX_LOG_WIDTH, 1).unwrap());
+    let last = copy
+        .add_dataflow_op(IntOpDef::isub.with_log_width(INDEX_LOG_WIDTH), [len, one])?
+        .out_wire(0);
+    let mirrored = copy
+        .add_dataflow_op(IntOpDef::isub.with_log_width(INDEX_LOG_WIDTH), [last, index])?
+        .out_wire(0);
+    let reversed = copy
+        .add_dataflow_op(
+            JeffOp::IntArraySet { bits }.into_extension_op(),
+            [reversed, mirrored, value],
+        )?
+        .out_wire(0);
+    let next = copy
+        .add_dataflow_op(IntOpDef::iadd.with_log_width(INDEX_LOG_WIDTH), [index, one])?
+        .out_wire(0);
+    copy.finish_with_outputs([array, reversed, next, len])?;
+    let [array, reversed, index, len] = cond.finish_sub_container()?.outputs_arr();
+
+    // Break once all the elements have been copied.
+    let done = tail_loop
+        .add_dataflow_op(IntOpDef::ige_u.with_log_width(INDEX_LOG_WIDTH), [index, len])?
+        .out_wire(0);
+    let [_, reversed, _, _] = tail_loop
+        .finish_with_outputs(done, [array, reversed, index, len])?
+        .outputs_arr();
+
+    builder.finish_hugr_with_outputs([reversed])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::bits(1)]
+    #[case::int32(32)]
+    fn lower_reverses(#[case] bits: u8) {
+        let array_t = intreg_type(bits);
+        let mut builder = DFGBuilder::new(Signature::new_endo(vec![array_t])).unwrap();
+        let [array] = builder.input_wires_arr();
+        let reversed = builder
+            .add_dataflow_op(
+                JeffOp::IntArrayReverse { bits }.into_extension_op(),
+                [array],
+            )
+            .unwrap()
+            .out_wire(0);
+        let mut hugr = builder.finish_hugr_with_outputs([reversed]).unwrap();
+
+        assert_eq!(lower_int_array_reverses(&mut hugr), 1);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        let ops = hugr.nodes().map(|n| hugr.get_optype(n)).collect_vec();
+        assert!(
+            !ops.iter()
+                .any(|op| matches!(op.cast::<JeffOp>(), Some(JeffOp::IntArrayReverse { .. })))
+        );
+        assert_eq!(ops.iter().filter(|op| op.is_tail_loop()).count(), 1);
+    }
+}
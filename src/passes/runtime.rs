@@ -0,0 +1,180 @@
+//! Binding of runtime queries to values known ahead of time.
+
+use std::collections::BTreeMap;
+
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::ops::{Const, LoadConstant, Value};
+use hugr::std_extensions::arithmetic::int_types::ConstInt;
+use hugr::{HugrView, Node};
+use itertools::Itertools;
+
+use crate::extension::JeffOp;
+use crate::provenance::record_pass;
+
+/// Log width of the `int(64)` query results.
+const QUERY_LOG_WIDTH: u8 = 6;
+
+/// Values of the runtime properties known before execution.
+///
+/// Queries for properties left unset are kept in the program.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RuntimeValues {
+    /// The number of device qubits, bound to [`JeffOp::NumQubits`].
+    pub num_qubits: Option<i64>,
+    /// The number of shots, bound to [`JeffOp::ShotCount`].
+    pub shot_count: Option<i64>,
+    /// Named properties, bound to [`JeffOp::RuntimeQuery`].
+    pub queries: BTreeMap<String, i64>,
+}
+
+impl RuntimeValues {
+    /// Set [`RuntimeValues::num_qubits`].
+    pub fn with_num_qubits(mut self, num_qubits: impl Into<Option<i64>>) -> Self {
+        self.num_qubits = num_qubits.into();
+        self
+    }
+
+    /// Set [`RuntimeValues::shot_count`].
+    pub fn with_shot_count(mut self, shot_count: impl Into<Option<i64>>) -> Self {
+        self.shot_count = shot_count.into();
+        self
+    }
+
+    /// Set [`RuntimeValues::queries`].
+    pub fn with_queries(mut self, queries: BTreeMap<String, i64>) -> Self {
+        self.queries = queries;
+        self
+    }
+
+    /// Bind the result of a named [`JeffOp::RuntimeQuery`].
+    pub fn with_query(mut self, name: impl ToString, value: i64) -> Self {
+        self.queries.insert(name.to_string(), value);
+        self
+    }
+
+    /// Returns the known result of a runtime query, if any.
+    pub fn value(&self, query: &JeffOp) -> Option<i64> {
+        match query {
+            JeffOp::NumQubits => self.num_qubits,
+            JeffOp::ShotCount => self.shot_count,
+            JeffOp::RuntimeQuery { name } => self.queries.get(name).copied(),
+            _ => None,
+        }
+    }
+}
+
+/// Replace the runtime queries with a known result by integer constants.
+///
+/// This enables constant folding of device-dependent computations, such as
+/// loops bounded by the number of qubits.
+///
+/// Returns the number of replaced queries.
+pub fn bind_runtime_queries(
+    hugr: &mut impl HugrMut<Node = Node>,
+    values: &RuntimeValues,
+) -> usize {
+    record_pass(hugr, "bind_runtime_queries");
+    let bound = hugr
+        .nodes()
+        .filter_map(|node| {
+            let query = hugr.get_optype(node).cast::<JeffOp>()?;
+            Some((node, values.value(&query)?))
+        })
+        .collect_vec();
+
+    for &(node, value) in &bound {
+        let parent = hugr.get_parent(node).unwrap();
+        let targets = hugr.linked_inputs(node, 0).collect_vec();
+
+        let value = Value::extension(ConstInt::new_s(QUERY_LOG_WIDTH, value).unwrap());
+        let load = hugr.add_node_with_parent(
+            parent,
+            LoadConstant {
+                datatype: value.get_type(),
+            },
+        );
+        let const_node = hugr.add_node_with_parent(parent, Const::new(value));
+        hugr.connect(const_node, 0, load, 0);
+        hugr.remove_node(node);
+
+        for (tgt, tgt_port) in targets {
+            hugr.connect(load, 0, tgt, tgt_port);
+        }
+    }
+    bound.len()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr};
+    use hugr::std_extensions::arithmetic::int_types::int_type;
+    use hugr::types::Signature;
+    use hugr::Hugr;
+    use rstest::rstest;
+
+    /// A DFG returning the results of the given queries.
+    fn query_dfg(queries: Vec<JeffOp>) -> Hugr {
+        let outputs = vec![int_type(QUERY_LOG_WIDTH); queries.len()];
+        let mut builder = DFGBuilder::new(Signature::new(vec![], outputs)).unwrap();
+        let wires = queries
+            .into_iter()
+            .map(|query| {
+                builder
+                    .add_dataflow_op(query.into_extension_op(), [])
+                    .unwrap()
+                    .out_wire(0)
+            })
+            .collect_vec();
+        builder.finish_hugr_with_outputs(wires).unwrap()
+    }
+
+    fn bound_constants(hugr: &Hugr) -> Vec<i64> {
+        hugr.nodes()
+            .filter_map(|n| hugr.get_optype(n).as_const())
+            .filter_map(|c| c.get_custom_value::<ConstInt>())
+            .map(|c| c.value_s())
+            .sorted()
+            .collect()
+    }
+
+    #[rstest]
+    #[case::num_qubits("num_qubits", Some(JeffOp::NumQubits))]
+    #[case::shot_count("shot_count", Some(JeffOp::ShotCount))]
+    #[case::named("runtime.device_id", Some(JeffOp::RuntimeQuery { name: "device_id".to_string() }))]
+    #[case::empty_name("runtime.", None)]
+    #[case::unknown("rand", None)]
+    fn intrinsic_names(#[case] name: &str, #[case] expected: Option<JeffOp>) {
+        assert_eq!(JeffOp::runtime_query(name), expected);
+    }
+
+    #[rstest]
+    fn bind_queries() {
+        let named = JeffOp::RuntimeQuery {
+            name: "device_id".to_string(),
+        };
+        let mut hugr = query_dfg(vec![
+            JeffOp::NumQubits,
+            JeffOp::NumQubits,
+            JeffOp::ShotCount,
+            named.clone(),
+        ]);
+        let values = RuntimeValues {
+            num_qubits: Some(5),
+            queries: BTreeMap::from([("device_id".to_string(), 42)]),
+            ..Default::default()
+        };
+
+        assert_eq!(bind_runtime_queries(&mut hugr, &values), 3);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(bound_constants(&hugr), [5, 5, 42]);
+
+        // The shot count is unknown, and stays as a query.
+        let remaining = hugr
+            .nodes()
+            .filter_map(|n| hugr.get_optype(n).cast::<JeffOp>())
+            .collect_vec();
+        assert_eq!(remaining, [JeffOp::ShotCount]);
+    }
+}
@@ -0,0 +1,228 @@
+//! Removal of diagnostic-only operations for release pipelines.
+
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::{HugrView, IncomingPort, Node, OutgoingPort};
+use itertools::Itertools;
+
+use crate::extension::JeffOp;
+use crate::metadata;
+use crate::provenance::record_pass;
+
+/// Options for the [`strip`] pass, selecting which kind of operations to remove.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct StripOptions {
+    /// Remove [`JeffOp::DebugPrint`] operations, reconnecting the values threaded through them.
+    pub debug_prints: bool,
+    /// Remove the index checks emitted by [`crate::ArrayBounds::Panic`] and
+    /// the overflow checks emitted by [`crate::IntOverflow::Trap`].
+    ///
+    /// Indices clamped by [`crate::ArrayBounds::Clamp`] are left untouched.
+    pub bounds_checks: bool,
+}
+
+impl Default for StripOptions {
+    fn default() -> Self {
+        Self {
+            debug_prints: true,
+            bounds_checks: true,
+        }
+    }
+}
+
+impl StripOptions {
+    /// Set [`StripOptions::debug_prints`].
+    pub fn with_debug_prints(mut self, debug_prints: bool) -> Self {
+        self.debug_prints = debug_prints;
+        self
+    }
+
+    /// Set [`StripOptions::bounds_checks`].
+    pub fn with_bounds_checks(mut self, bounds_checks: bool) -> Self {
+        self.bounds_checks = bounds_checks;
+        self
+    }
+}
+
+/// Remove diagnostic operations from a HUGR.
+///
+/// Values threaded through the removed operations are reconnected directly
+/// from their producers to their consumers.
+///
+/// Returns the number of removed nodes.
+pub fn strip(hugr: &mut impl HugrMut<Node = Node>, options: &StripOptions) -> usize {
+    record_pass(hugr, "strip");
+    let to_remove = hugr
+        .nodes()
+        .filter(|&node| is_stripped(hugr, node, options))
+        .collect_vec();
+
+    for &node in &to_remove {
+        remove_passthrough_node(hugr, node, 0);
+    }
+
+    let mut removed = to_remove.len();
+    if options.bounds_checks {
+        removed += strip_bounds_checks(hugr);
+    }
+    removed
+}
+
+/// Remove the index checks emitted by [`crate::ArrayBounds::Panic`] and the
+/// overflow checks emitted by [`crate::IntOverflow::Trap`].
+///
+/// The guard conditionals are bypassed first. The length queries and
+/// comparisons feeding them are then removed once their results are unused.
+fn strip_bounds_checks(hugr: &mut impl HugrMut<Node = Node>) -> usize {
+    // The guards thread the access inputs after their control input.
+    let guards = hugr
+        .nodes()
+        .filter(|&node| is_bounds_check(hugr, node) && hugr.get_optype(node).is_conditional())
+        .collect_vec();
+    for &node in &guards {
+        remove_passthrough_node(hugr, node, 1);
+    }
+    let mut removed = guards.len();
+
+    loop {
+        let unused = hugr
+            .nodes()
+            .filter(|&node| is_bounds_check(hugr, node) && !check_result_used(hugr, node))
+            .collect_vec();
+        if unused.is_empty() {
+            break;
+        }
+        for &node in &unused {
+            remove_passthrough_node(hugr, node, 0);
+        }
+        removed += unused.len();
+    }
+    removed
+}
+
+/// Returns `true` if the node was emitted to check an array index or an
+/// integer overflow.
+fn is_bounds_check(hugr: &impl HugrView<Node = Node>, node: Node) -> bool {
+    hugr.get_metadata(node, metadata::BOUNDS_CHECK).is_some()
+}
+
+/// Returns `true` if any output of a bounds check node is used, apart from
+/// the register threaded through a [`JeffOp::QuregLength`].
+fn check_result_used(hugr: &impl HugrView<Node = Node>, node: Node) -> bool {
+    let threaded = match hugr.get_optype(node).cast::<JeffOp>() {
+        Some(JeffOp::QuregLength) => 1,
+        _ => 0,
+    };
+    (threaded..hugr.num_outputs(node))
+        .any(|port| hugr.linked_inputs(node, OutgoingPort::from(port)).next().is_some())
+}
+
+/// Returns `true` if the node should be removed by the [`strip`] pass.
+fn is_stripped(hugr: &impl HugrView<Node = Node>, node: Node, options: &StripOptions) -> bool {
+    match hugr.get_optype(node).cast::<JeffOp>() {
+        Some(JeffOp::DebugPrint { .. }) => options.debug_prints,
+        _ => false,
+    }
+}
+
+/// Remove a node whose `i`-th output carries the same value as its
+/// `offset + i`-th input, connecting each input's source to the consumers of
+/// the corresponding output.
+pub(crate) fn remove_passthrough_node(
+    hugr: &mut impl HugrMut<Node = Node>,
+    node: Node,
+    offset: usize,
+) {
+    let num_values = hugr
+        .num_outputs(node)
+        .min(hugr.num_inputs(node).saturating_sub(offset));
+    let rewires = (0..num_values)
+        .filter_map(|i| {
+            let (src, src_port) =
+                hugr.single_linked_output(node, IncomingPort::from(offset + i))?;
+            let targets = hugr
+                .linked_inputs(node, OutgoingPort::from(i))
+                .collect_vec();
+            Some((src, src_port, targets))
+        })
+        .collect_vec();
+
+    hugr.remove_subtree(node);
+
+    for (src, src_port, targets) in rewires {
+        for (tgt, tgt_port) in targets {
+            hugr.connect(src, src_port, tgt, tgt_port);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::assert_hugr_equivalent;
+    use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr};
+    use hugr::std_extensions::arithmetic::int_types::int_type;
+    use hugr::types::Signature;
+    use hugr::Hugr;
+    use rstest::rstest;
+
+    /// Build a DFG with an integer input threaded through `num_prints` debug prints.
+    fn printing_dfg(num_prints: usize) -> Hugr {
+        let int_t = int_type(5);
+        let mut builder = DFGBuilder::new(Signature::new_endo(vec![int_t.clone()])).unwrap();
+        let [mut wire] = builder.input_wires_arr();
+        for i in 0..num_prints {
+            let print = JeffOp::DebugPrint {
+                format: format!("print {i}: {{}}"),
+                types: vec![int_t.clone()],
+            };
+            wire = builder
+                .add_dataflow_op(print.into_extension_op(), [wire])
+                .unwrap()
+                .out_wire(0);
+        }
+        builder.finish_hugr_with_outputs([wire]).unwrap()
+    }
+
+    #[rstest]
+    fn debug_print_threading() {
+        let hugr = printing_dfg(2);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        let prints = hugr
+            .entry_descendants()
+            .filter(|&n| matches!(hugr.get_optype(n).cast::<JeffOp>(), Some(JeffOp::DebugPrint { .. })))
+            .collect_vec();
+        assert_eq!(prints.len(), 2);
+
+        // The second print must consume the value threaded through the first.
+        let (src, _) = hugr.single_linked_output(prints[1], 0).unwrap();
+        assert_eq!(src, prints[0]);
+        let out = hugr.get_io(hugr.entrypoint()).unwrap()[1];
+        assert_eq!(
+            hugr.single_linked_output(out, 0).map(|(n, _)| n),
+            Some(prints[1])
+        );
+    }
+
+    #[rstest]
+    fn strip_debug_prints() {
+        let mut hugr = printing_dfg(2);
+        let removed = strip(&mut hugr, &StripOptions::default());
+        assert_eq!(removed, 2);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        assert_hugr_equivalent(&hugr, &printing_dfg(0));
+    }
+
+    #[rstest]
+    fn strip_disabled() {
+        let mut hugr = printing_dfg(2);
+        let options = StripOptions {
+            debug_prints: false,
+            ..Default::default()
+        };
+        let removed = strip(&mut hugr, &options);
+        assert_eq!(removed, 0);
+    }
+}
@@ -0,0 +1,515 @@
+//! Collapsing of integer comparison ladders into multi-way conditionals.
+
+use std::collections::BTreeSet;
+
+use hugr::builder::{
+    BuildError, DFGBuilder, Dataflow, DataflowHugr, DataflowSubContainer, SubContainer,
+};
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::ops::{OpType, Value};
+use hugr::std_extensions::arithmetic::int_ops::{ConcreteIntOp, IntOpDef};
+use hugr::std_extensions::arithmetic::int_types::{ConstInt, int_type};
+use hugr::types::{Signature, SumType, Type as HugrType};
+use hugr::{Hugr, HugrView, Node, OutgoingPort, type_row};
+use itertools::Itertools;
+
+use crate::provenance::record_pass;
+
+/// Options for the [`collapse_switch_ladders`] pass.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SwitchLadderOptions {
+    /// Minimum number of comparisons in a collapsed ladder.
+    ///
+    /// Ladders of a single comparison are always left unchanged.
+    pub min_depth: usize,
+    /// Maximum number of cases of a collapsed conditional, including the
+    /// default case.
+    ///
+    /// Longer ladders are collapsed in several conditionals, each one
+    /// running the next in its default case.
+    pub max_cases: usize,
+}
+
+impl Default for SwitchLadderOptions {
+    fn default() -> Self {
+        Self {
+            min_depth: 2,
+            max_cases: 64,
+        }
+    }
+}
+
+impl SwitchLadderOptions {
+    /// Set [`SwitchLadderOptions::min_depth`].
+    pub fn with_min_depth(mut self, min_depth: usize) -> Self {
+        self.min_depth = min_depth;
+        self
+    }
+
+    /// Set [`SwitchLadderOptions::max_cases`].
+    pub fn with_max_cases(mut self, max_cases: usize) -> Self {
+        self.max_cases = max_cases;
+        self
+    }
+}
+
+/// A two-way `Conditional` on the equality of an integer with a constant.
+struct Comparison {
+    /// The `ieq` node computing the predicate.
+    ieq: Node,
+    /// The compared integer.
+    selector: (Node, OutgoingPort),
+    /// The constant the selector is compared with.
+    value: u64,
+    /// The log width of the compared integers.
+    log_width: u8,
+}
+
+/// A chain of nested two-way conditionals comparing the same integer with
+/// distinct constants.
+struct Ladder {
+    /// The comparison of the outermost conditional.
+    comparison: Comparison,
+    /// The conditionals of the ladder, outermost first, with their constant.
+    levels: Vec<(Node, u64)>,
+    /// The case run when the selector matches no constant.
+    default: Node,
+}
+
+/// Replace the ladders of integer comparisons by a single multi-way
+/// `Conditional`.
+///
+/// Producers lower `match` statements into a chain of two-way conditionals,
+/// each comparing the same integer with a constant using `ieq` and running
+/// the next comparison when it differs. A ladder is collapsed when:
+/// - each conditional has two cases with no sum values, and takes its
+///   predicate from an `ieq` of the selector with a loaded constant,
+/// - the constants are distinct,
+/// - the `false` case of each conditional only contains the comparison and
+///   the next conditional, which takes the case inputs in order and returns
+///   its outputs unchanged.
+///
+/// The selector is mapped to the case tag by a dataflow graph comparing it
+/// with each constant, followed by a `Conditional` with one case per
+/// constant and a last case for the other values.
+///
+/// Returns the number of collapsed ladders.
+pub fn collapse_switch_ladders(
+    hugr: &mut impl HugrMut<Node = Node>,
+    options: &SwitchLadderOptions,
+) -> usize {
+    record_pass(hugr, "collapse_switch_ladders");
+    let mut collapsed = 0;
+    // Truncated ladders continue in the default case of the new conditional,
+    // where they are collapsed in the next round.
+    loop {
+        let ladders = hugr
+            .nodes()
+            .filter(|&node| !is_nested_level(hugr, node))
+            .filter_map(|node| match_ladder(hugr, node, options))
+            .collect_vec();
+        if ladders.is_empty() {
+            return collapsed;
+        }
+        for ladder in &ladders {
+            collapse(hugr, ladder).expect("Failed to build the tag computation");
+        }
+        collapsed += ladders.len();
+    }
+}
+
+/// Returns the comparison of a two-way conditional, if it is a ladder level.
+fn comparison(hugr: &impl HugrView<Node = Node>, node: Node) -> Option<Comparison> {
+    let OpType::Conditional(conditional) = hugr.get_optype(node) else {
+        return None;
+    };
+    if conditional.sum_rows.len() != 2 || conditional.sum_rows.iter().any(|row| !row.is_empty()) {
+        return None;
+    }
+
+    let (ieq, port) = hugr.single_linked_output(node, 0)?;
+    if hugr.get_optype(ieq).cast::<ConcreteIntOp>()?.def != IntOpDef::ieq
+        || hugr.linked_inputs(ieq, port).count() != 1
+    {
+        return None;
+    }
+    let operands = [0, 1].map(|port| hugr.single_linked_output(ieq, port));
+    let [Some(lhs), Some(rhs)] = operands else {
+        return None;
+    };
+    let (selector, constant) = match (constant_value(hugr, lhs.0), constant_value(hugr, rhs.0)) {
+        (None, Some(constant)) => (lhs, constant),
+        (Some(constant), None) => (rhs, constant),
+        _ => return None,
+    };
+    Some(Comparison {
+        ieq,
+        selector,
+        value: constant.value_u(),
+        log_width: constant.log_width(),
+    })
+}
+
+/// Returns the integer loaded by a node, if it loads a constant.
+fn constant_value(hugr: &impl HugrView<Node = Node>, node: Node) -> Option<ConstInt> {
+    if !hugr.get_optype(node).is_load_constant() {
+        return None;
+    }
+    let const_node = hugr.static_source(node)?;
+    let value = hugr.get_optype(const_node).as_const()?;
+    value.get_custom_value::<ConstInt>().cloned()
+}
+
+/// Returns the conditional of the next level of a ladder, in the `false`
+/// case of a level.
+fn next_level(hugr: &impl HugrView<Node = Node>, node: Node) -> Option<Node> {
+    let selector = comparison(hugr, node)?.selector;
+    let OpType::Conditional(conditional) = hugr.get_optype(node) else {
+        return None;
+    };
+    // The selector is threaded into the case as one of its inputs.
+    let selector_port = (0..conditional.other_inputs.len())
+        .find(|&port| hugr.single_linked_output(node, port + 1) == Some(selector))?;
+
+    let case = hugr.children(node).next()?;
+    let [input, output] = hugr.get_io(case)?;
+    let next = hugr
+        .children(case)
+        .filter(|&child| hugr.get_optype(child).is_conditional())
+        .exactly_one()
+        .ok()?;
+    let next_comparison = comparison(hugr, next)?;
+    if next_comparison.selector != (input, OutgoingPort::from(selector_port)) {
+        return None;
+    }
+    let OpType::Conditional(next_conditional) = hugr.get_optype(next) else {
+        return None;
+    };
+    if next_conditional.other_inputs != conditional.other_inputs
+        || next_conditional.outputs != conditional.outputs
+    {
+        return None;
+    }
+
+    // The case only runs the next level, with its inputs in order.
+    let ports = conditional.other_inputs.len();
+    let wired_in = (0..ports).all(|port| {
+        hugr.single_linked_output(next, port + 1) == Some((input, OutgoingPort::from(port)))
+    });
+    let wired_out = (0..conditional.outputs.len()).all(|port| {
+        hugr.single_linked_output(output, port) == Some((next, OutgoingPort::from(port)))
+    });
+    let load = [0, 1]
+        .into_iter()
+        .filter_map(|port| hugr.single_linked_output(next_comparison.ieq, port))
+        .map(|(node, _)| node)
+        .find(|&node| node != input)?;
+    let mut allowed = BTreeSet::from([input, output, next, next_comparison.ieq, load]);
+    allowed.extend(hugr.static_source(load));
+    let only_level = hugr.children(case).all(|child| allowed.contains(&child));
+    (wired_in && wired_out && only_level).then_some(next)
+}
+
+/// Whether a conditional is a nested level of a ladder.
+fn is_nested_level(hugr: &impl HugrView<Node = Node>, node: Node) -> bool {
+    let Some(parent) = hugr.get_parent(node).and_then(|case| hugr.get_parent(case)) else {
+        return false;
+    };
+    next_level(hugr, parent) == Some(node)
+}
+
+/// Find the ladder starting at a conditional.
+fn match_ladder(
+    hugr: &impl HugrView<Node = Node>,
+    node: Node,
+    options: &SwitchLadderOptions,
+) -> Option<Ladder> {
+    let first = comparison(hugr, node)?;
+    let mut levels = vec![(node, first.value)];
+    let mut values = BTreeSet::from([first.value]);
+    let max_levels = options.max_cases.saturating_sub(1);
+    let mut current = node;
+    while levels.len() < max_levels {
+        let Some(next) = next_level(hugr, current) else {
+            break;
+        };
+        let Some(next_comparison) = comparison(hugr, next) else {
+            break;
+        };
+        if next_comparison.log_width != first.log_width || !values.insert(next_comparison.value) {
+            break;
+        }
+        levels.push((next, next_comparison.value));
+        current = next;
+    }
+    if levels.len() < options.min_depth.max(2) {
+        return None;
+    }
+    let default = hugr.children(current).next()?;
+    Some(Ladder {
+        comparison: first,
+        levels,
+        default,
+    })
+}
+
+/// Replace a matched ladder by a tag computation and a multi-way conditional.
+fn collapse(hugr: &mut impl HugrMut<Node = Node>, ladder: &Ladder) -> Result<(), BuildError> {
+    let root = ladder.levels[0].0;
+    let parent = hugr.get_parent(root).unwrap();
+    let OpType::Conditional(mut conditional) = hugr.get_optype(root).clone() else {
+        unreachable!("Ladder levels are conditionals")
+    };
+    let inputs = (0..conditional.other_inputs.len())
+        .map(|port| hugr.single_linked_output(root, port + 1))
+        .collect_vec();
+    let outputs = (0..conditional.outputs.len())
+        .map(|port| hugr.linked_inputs(root, port).collect_vec())
+        .collect_vec();
+    let optype = hugr.get_optype(root);
+    let predecessors = optype
+        .other_input_port()
+        .map(|port| hugr.linked_outputs(root, port).map(|(n, _)| n).collect_vec())
+        .unwrap_or_default();
+    let successors = optype
+        .other_output_port()
+        .map(|port| hugr.linked_inputs(root, port).map(|(n, _)| n).collect_vec())
+        .unwrap_or_default();
+
+    let values = ladder.levels.iter().map(|&(_, value)| value).collect_vec();
+    let tags = tag_computation(ladder.comparison.log_width, &values)?;
+    let tags = hugr.insert_hugr(parent, tags).inserted_entrypoint;
+    conditional.sum_rows = vec![type_row![]; values.len() + 1];
+    let node = hugr.add_node_with_parent(parent, conditional);
+
+    // The `true` case of each level, then the default case.
+    let cases = ladder
+        .levels
+        .iter()
+        .map(|&(level, _)| hugr.children(level).nth(1).unwrap())
+        .chain([ladder.default])
+        .collect_vec();
+    for case in cases {
+        hugr.set_parent(case, node);
+    }
+    hugr.remove_subtree(root);
+    remove_comparison(hugr, ladder.comparison.ieq);
+
+    let (selector, selector_port) = ladder.comparison.selector;
+    hugr.connect(selector, selector_port, tags, 0);
+    hugr.connect(tags, 0, node, 0);
+    for (port, input) in inputs.into_iter().enumerate() {
+        if let Some((src, src_port)) = input {
+            hugr.connect(src, src_port, node, port + 1);
+        }
+    }
+    for (port, targets) in outputs.into_iter().enumerate() {
+        for (tgt, tgt_port) in targets {
+            hugr.connect(node, port, tgt, tgt_port);
+        }
+    }
+    for pred in predecessors {
+        hugr.add_other_edge(pred, node);
+    }
+    for succ in successors {
+        hugr.add_other_edge(node, succ);
+    }
+    Ok(())
+}
+
+/// Remove the `ieq` of a collapsed ladder, along with its constant operand
+/// if it is not used elsewhere.
+fn remove_comparison(hugr: &mut impl HugrMut<Node = Node>, ieq: Node) {
+    let loads = [0, 1]
+        .into_iter()
+        .filter_map(|port| hugr.single_linked_output(ieq, port))
+        .filter(|&(node, _)| hugr.get_optype(node).is_load_constant())
+        .map(|(node, _)| node)
+        .collect_vec();
+    hugr.remove_node(ieq);
+    for load in loads {
+        if hugr.linked_inputs(load, 0).next().is_some() {
+            continue;
+        }
+        let const_node = hugr.static_source(load);
+        hugr.remove_node(load);
+        if let Some(const_node) = const_node
+            && hugr.linked_inputs(const_node, 0).next().is_none()
+        {
+            hugr.remove_node(const_node);
+        }
+    }
+}
+
+/// A dataflow graph mapping an integer to the index of the first constant
+/// it equals, or to the number of constants if there is none.
+///
+/// The index is returned as a tag of a sum with empty variants.
+pub(crate) fn tag_computation(log_width: u8, values: &[u64]) -> Result<Hugr, BuildError> {
+    let tag_sum = SumType::new(vec![type_row![]; values.len() + 1]);
+    let tag_t: HugrType = tag_sum.clone().into();
+    let unit_tag = |tag: usize| Value::sum(tag, [], tag_sum.clone()).unwrap();
+
+    let signature = Signature::new(vec![int_type(log_width)], vec![tag_t.clone()]);
+    let mut builder = DFGBuilder::new(signature)?;
+    let [selector] = builder.input_wires_arr();
+    let mut tag = builder.add_load_value(unit_tag(values.len()));
+    for (index, &value) in values.iter().enumerate().rev() {
+        let constant = builder.add_load_value(ConstInt::new_u(log_width, value).unwrap());
+        let equal = builder
+            .add_dataflow_op(IntOpDef::ieq.with_log_width(log_width), [selector, constant])?
+            .out_wire(0);
+        let mut cond = builder.conditional_builder(
+            ([type_row![], type_row![]], equal),
+            [(tag_t.clone(), tag)],
+            vec![tag_t.clone()].into(),
+        )?;
+        let different = cond.case_builder(0)?;
+        let [previous] = different.input_wires_arr();
+        different.finish_with_outputs([previous])?;
+        let mut equal = cond.case_builder(1)?;
+        let matched = equal.add_load_value(unit_tag(index));
+        equal.finish_with_outputs([matched])?;
+        tag = cond.finish_sub_container()?.out_wire(0);
+    }
+    builder.finish_hugr_with_outputs([tag])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hugr::Wire;
+    use rstest::rstest;
+
+    const LOG_WIDTH: u8 = 6;
+
+    /// A DFG returning `100 + i` if its input equals `keys[i]`, and its
+    /// input otherwise, as a ladder of nested two-way conditionals.
+    fn ladder(keys: &[u64]) -> Hugr {
+        let mut builder = DFGBuilder::new(Signature::new_endo(vec![int_type(LOG_WIDTH)])).unwrap();
+        let [selector] = builder.input_wires_arr();
+        let result = build_ladder(&mut builder, selector, keys, 0);
+        builder.finish_hugr_with_outputs([result]).unwrap()
+    }
+
+    fn build_ladder(builder: &mut impl Dataflow, selector: Wire, keys: &[u64], case: u64) -> Wire {
+        let Some((&key, rest)) = keys.split_first() else {
+            return selector;
+        };
+        let key = builder.add_load_value(ConstInt::new_u(LOG_WIDTH, key).unwrap());
+        let equal = builder
+            .add_dataflow_op(IntOpDef::ieq.with_log_width(LOG_WIDTH), [selector, key])
+            .unwrap()
+            .out_wire(0);
+        let mut cond = builder
+            .conditional_builder(
+                ([type_row![], type_row![]], equal),
+                [(int_type(LOG_WIDTH), selector)],
+                vec![int_type(LOG_WIDTH)].into(),
+            )
+            .unwrap();
+        let mut different = cond.case_builder(0).unwrap();
+        let [selector] = different.input_wires_arr();
+        let result = build_ladder(&mut different, selector, rest, case + 1);
+        different.finish_with_outputs([result]).unwrap();
+        let mut matched = cond.case_builder(1).unwrap();
+        let result = matched.add_load_value(ConstInt::new_u(LOG_WIDTH, 100 + case).unwrap());
+        matched.finish_with_outputs([result]).unwrap();
+        cond.finish_sub_container().unwrap().out_wire(0)
+    }
+
+    /// The maximum number of nested conditionals.
+    fn conditional_depth(hugr: &Hugr) -> usize {
+        hugr.nodes()
+            .map(|node| {
+                std::iter::successors(Some(node), |&n| hugr.get_parent(n))
+                    .filter(|&n| hugr.get_optype(n).is_conditional())
+                    .count()
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The conditionals with more than two cases.
+    fn multiway_conditionals(hugr: &Hugr) -> Vec<Node> {
+        hugr.nodes()
+            .filter(|&n| hugr.get_optype(n).is_conditional() && hugr.children(n).count() > 2)
+            .collect()
+    }
+
+    /// The integers loaded in a region, including its nested regions.
+    fn loaded_values(hugr: &Hugr, region: Node) -> Vec<u64> {
+        hugr.descendants(region)
+            .filter_map(|n| constant_value(hugr, n))
+            .map(|c| c.value_u())
+            .collect()
+    }
+
+    #[rstest]
+    fn collapse_five_cases() {
+        let keys = [3, 7, 1, 12, 5];
+        let mut hugr = ladder(&keys);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(conditional_depth(&hugr), keys.len());
+
+        assert_eq!(collapse_switch_ladders(&mut hugr, &SwitchLadderOptions::default()), 1);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(conditional_depth(&hugr), 1);
+
+        let [cond] = multiway_conditionals(&hugr).try_into().unwrap();
+        let cases = hugr.children(cond).collect_vec();
+        assert_eq!(cases.len(), keys.len() + 1);
+        for (case, &node) in cases[..keys.len()].iter().enumerate() {
+            assert_eq!(loaded_values(&hugr, node), [100 + case as u64]);
+        }
+        assert!(loaded_values(&hugr, cases[keys.len()]).is_empty());
+
+        // The tag computation selects the case of each key.
+        let (tags, _) = hugr.single_linked_output(cond, 0).unwrap();
+        let tag_conditionals = hugr
+            .children(tags)
+            .filter(|&n| hugr.get_optype(n).is_conditional())
+            .collect_vec();
+        assert_eq!(tag_conditionals.len(), keys.len());
+        for node in tag_conditionals {
+            let key = comparison(&hugr, node).unwrap().value;
+            let matched = hugr.children(node).nth(1).unwrap();
+            let tag = hugr
+                .children(matched)
+                .filter_map(|n| hugr.get_optype(n).as_const())
+                .exactly_one()
+                .unwrap();
+            let Value::Sum(tag) = tag.value() else {
+                panic!("expected a sum constant, got {tag:?}");
+            };
+            assert_eq!(keys[tag.tag], key);
+        }
+    }
+
+    #[rstest]
+    #[case::too_shallow(&[3, 7], SwitchLadderOptions::default().with_min_depth(3), 0, &[])]
+    #[case::single(&[3], SwitchLadderOptions::default().with_min_depth(1), 0, &[])]
+    #[case::repeated_key(&[3, 7, 3, 5], SwitchLadderOptions::default(), 2, &[3, 3])]
+    #[case::truncated(
+        &[3, 7, 1, 12, 5],
+        SwitchLadderOptions::default().with_max_cases(3),
+        2,
+        &[3, 3]
+    )]
+    fn collapse_limits(
+        #[case] keys: &[u64],
+        #[case] options: SwitchLadderOptions,
+        #[case] collapsed: usize,
+        #[case] cases: &[usize],
+    ) {
+        let mut hugr = ladder(keys);
+        assert_eq!(collapse_switch_ladders(&mut hugr, &options), collapsed);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        let case_counts = multiway_conditionals(&hugr)
+            .into_iter()
+            .map(|n| hugr.children(n).count())
+            .collect_vec();
+        assert_eq!(case_counts, cases);
+    }
+}
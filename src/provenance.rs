@@ -0,0 +1,239 @@
+//! Record of how a HUGR was produced by the _jeff_ translation.
+//!
+//! The translation stores a [`Provenance`] in the [`metadata::PROVENANCE`]
+//! entry of the module root. The passes of [`crate::passes`] append their
+//! name to it with [`record_pass`] when they run.
+//!
+//! # Examples
+//!
+//! ```
+//! use hugr_jeff::doctest_support::qubits;
+//! use hugr_jeff::passes::lower_register_measures;
+//! use hugr_jeff::provenance::Provenance;
+//! use hugr_jeff::{JeffToHugrConfig, jeff_to_hugr_with_config};
+//!
+//! let config = JeffToHugrConfig::default().with_record_provenance(true);
+//! let mut hugr = jeff_to_hugr_with_config(&qubits(), &config).unwrap().hugr;
+//! lower_register_measures(&mut hugr);
+//!
+//! let provenance = Provenance::from_hugr(&hugr).unwrap();
+//! assert!(!provenance.crate_version.is_empty());
+//! assert_eq!(provenance.input_sha256, Provenance::UNAVAILABLE);
+//! assert_eq!(provenance.passes, ["lower_register_measures"]);
+//! ```
+
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::{HugrView, Node};
+use serde::{Deserialize, Serialize};
+
+use crate::JeffToHugrConfig;
+use crate::metadata;
+
+/// How a HUGR was produced: the translation that created it and the passes
+/// applied to it since.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Provenance {
+    /// The version of the `hugr-jeff` crate that translated the program.
+    pub crate_version: String,
+    /// The _jeff_ format version of the input.
+    ///
+    /// The _jeff_ reader does not expose the version of the programs it
+    /// reads yet, so this is `None` for now.
+    pub jeff_version: Option<String>,
+    /// The translation configuration, serialized after the presets were
+    /// applied.
+    pub config: serde_json::Value,
+    /// The hex-encoded SHA-256 of the input bytes, or
+    /// [`Provenance::UNAVAILABLE`] when the program was not translated from
+    /// bytes.
+    pub input_sha256: String,
+    /// The names of the passes applied to the HUGR, in order.
+    pub passes: Vec<String>,
+}
+
+impl Provenance {
+    /// The [`Provenance::input_sha256`] of programs not translated from bytes.
+    pub const UNAVAILABLE: &str = "unavailable";
+
+    /// The provenance of a translation with the given configuration, with no
+    /// input hash and no passes.
+    pub(crate) fn new(config: &JeffToHugrConfig) -> Self {
+        Self {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            jeff_version: None,
+            config: serde_json::to_value(config).expect("the config is serializable"),
+            input_sha256: Self::UNAVAILABLE.to_string(),
+            passes: Vec::new(),
+        }
+    }
+
+    /// Set the input hash to the SHA-256 of the translated bytes.
+    pub(crate) fn with_input(mut self, bytes: &[u8]) -> Self {
+        self.input_sha256 = sha256(bytes).iter().map(|b| format!("{b:02x}")).collect();
+        self
+    }
+
+    /// Read the provenance stored in the module root of a HUGR.
+    ///
+    /// Returns `None` if the HUGR has no provenance entry, or if it cannot be
+    /// parsed.
+    pub fn from_hugr(hugr: &impl HugrView<Node = Node>) -> Option<Self> {
+        let value = hugr.get_metadata(hugr.module_root(), metadata::PROVENANCE)?;
+        serde_json::from_value(value.clone()).ok()
+    }
+
+    /// Store the provenance in the module root of a HUGR, replacing any
+    /// previous entry.
+    pub(crate) fn store(&self, hugr: &mut impl HugrMut<Node = Node>) {
+        let value = serde_json::to_value(self).expect("the provenance is serializable");
+        hugr.set_metadata(hugr.module_root(), metadata::PROVENANCE, value);
+    }
+}
+
+/// Append a pass to the provenance of a HUGR.
+///
+/// Does nothing if the HUGR has no [`Provenance`], i.e. it was not produced
+/// by the translation.
+pub fn record_pass(hugr: &mut impl HugrMut<Node = Node>, name: &str) {
+    if let Some(mut provenance) = Provenance::from_hugr(hugr) {
+        provenance.passes.push(name.to_string());
+        provenance.store(hugr);
+    }
+}
+
+/// The round constants of SHA-256.
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// The SHA-256 digest of a byte string.
+///
+/// The inputs are small enough for a plain implementation, which spares a
+/// dependency for the provenance hashes.
+fn sha256(bytes: &[u8]) -> [u8; 32] {
+    let mut state: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+    let mut message = bytes.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(bytes.len() as u64 * 8).to_be_bytes());
+
+    for block in message.chunks_exact(64) {
+        let mut w = Vec::with_capacity(64);
+        w.extend(
+            block
+                .chunks_exact(4)
+                .map(|word| u32::from_be_bytes(word.try_into().unwrap())),
+        );
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w.push(
+                w[i - 16]
+                    .wrapping_add(s0)
+                    .wrapping_add(w[i - 7])
+                    .wrapping_add(s1),
+            );
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for (&k, &w) in K.iter().zip(&w) {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(k)
+                .wrapping_add(w);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (s, v) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+
+    let mut digest = [0u8; 32];
+    for (chunk, s) in digest.chunks_exact_mut(4).zip(state) {
+        chunk.copy_from_slice(&s.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hugr_importer::{ImportOptions, import};
+    use crate::passes::{cancel_register_roundtrips, lower_int_array_reverses};
+    use crate::{BitOrder, jeff_to_hugr};
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::empty(b"", "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855")]
+    #[case::abc(b"abc", "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")]
+    #[case::two_blocks(
+        b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+        "248d6a61d20638b8e5c0262693a93ce459a7a1c06d23ef3d1e0d6a6b2b9e1b05"
+    )]
+    fn sha256_vectors(#[case] input: &[u8], #[case] expected: &str) {
+        let provenance = Provenance::new(&JeffToHugrConfig::default()).with_input(input);
+        assert_eq!(provenance.input_sha256, expected);
+    }
+
+    #[rstest]
+    fn provenance_roundtrip() {
+        let bytes = std::fs::read("test_files/qubits/qubits.jeff").unwrap();
+        let config = JeffToHugrConfig::default().with_bit_order(BitOrder::MsbFirst);
+        let opts = ImportOptions::default().with_translation(config.clone());
+        let mut hugr = import(&mut bytes.as_slice(), &opts).unwrap();
+        lower_int_array_reverses(&mut hugr);
+        cancel_register_roundtrips(&mut hugr);
+
+        let provenance = Provenance::from_hugr(&hugr).unwrap();
+        let expected = Provenance::new(&config).with_input(&bytes);
+        assert_eq!(provenance.crate_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(provenance.input_sha256, expected.input_sha256);
+        assert_eq!(provenance.input_sha256.len(), 64);
+        assert_eq!(provenance.config, expected.config);
+        assert_eq!(provenance.config["bit_order"], "msb_first");
+        assert_eq!(
+            provenance.passes,
+            ["lower_int_array_reverses", "cancel_register_roundtrips"]
+        );
+    }
+
+    #[rstest]
+    fn unavailable_input() {
+        let file = std::fs::File::open("test_files/qubits/qubits.jeff").unwrap();
+        let jeff = jeff::Jeff::read(std::io::BufReader::new(file)).unwrap();
+        let mut hugr = jeff_to_hugr(&jeff).unwrap();
+        let provenance = Provenance::from_hugr(&hugr).unwrap();
+        assert_eq!(provenance.input_sha256, Provenance::UNAVAILABLE);
+
+        // Passes are not recorded on HUGRs without provenance.
+        hugr.remove_metadata(hugr.module_root(), metadata::PROVENANCE);
+        record_pass(&mut hugr, "strip");
+        assert_eq!(Provenance::from_hugr(&hugr), None);
+    }
+}
@@ -0,0 +1,121 @@
+//! Renderings of translated HUGRs meant to be read and compared by people.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use hugr::ops::OpType;
+use hugr::types::EdgeKind;
+use hugr::{HugrView, Node};
+
+use crate::WireNames;
+
+/// Render a HUGR as a mermaid flowchart, labelling the edges with the names
+/// of their wires.
+///
+/// Unlike [`HugrView::mermaid_string`], the nodes are identified by their
+/// position in the hierarchy rather than by their index, and only value
+/// edges are drawn. The edges are labelled with the names stored in the
+/// [`crate::metadata::WIRE_NAMES`] metadata, see
+/// [`crate::JeffToHugrConfig::name_wires`]. The renderings of two
+/// conversions can thus be compared line by line.
+///
+/// # Examples
+///
+/// ```
+/// use hugr_jeff::doctest_support::qubits;
+/// use hugr_jeff::render::mermaid_pretty;
+/// use hugr_jeff::{JeffToHugrConfig, jeff_to_hugr_with_config};
+///
+/// let config = JeffToHugrConfig::default().with_name_wires(true);
+/// let converted = jeff_to_hugr_with_config(&qubits(), &config).unwrap();
+/// let mermaid = mermaid_pretty(&converted.hugr);
+/// // The allocated qubit is the first one of the function.
+/// assert!(mermaid.contains("-->|q0|"));
+/// ```
+pub fn mermaid_pretty(hugr: &impl HugrView<Node = Node>) -> String {
+    let names = WireNames::from_metadata(hugr);
+    let mut ids = BTreeMap::new();
+    let mut mermaid = String::from("graph LR\n");
+    write_node(hugr, hugr.entrypoint(), 1, &mut ids, &mut mermaid);
+
+    for (&node, &id) in &ids {
+        let optype = hugr.get_optype(node);
+        for port in hugr.node_outputs(node) {
+            if !matches!(optype.port_kind(port), Some(EdgeKind::Value(_))) {
+                continue;
+            }
+            let targets = hugr
+                .linked_inputs(node, port)
+                .filter_map(|(target, _)| ids.get(&target));
+            for target in targets {
+                match names.get(node, port) {
+                    Some(name) => writeln!(mermaid, "    n{id} -->|{name}| n{target}"),
+                    None => writeln!(mermaid, "    n{id} --> n{target}"),
+                }
+                .unwrap();
+            }
+        }
+    }
+    mermaid
+}
+
+/// Write a node and its descendants, numbering them in the order they are
+/// written.
+fn write_node(
+    hugr: &impl HugrView<Node = Node>,
+    node: Node,
+    depth: usize,
+    ids: &mut BTreeMap<Node, usize>,
+    mermaid: &mut String,
+) {
+    let id = ids.len();
+    ids.insert(node, id);
+    let indent = "    ".repeat(depth);
+    let label = label(hugr.get_optype(node)).replace('"', "#quot;");
+    if hugr.first_child(node).is_none() {
+        writeln!(mermaid, "{indent}n{id}[\"{label}\"]").unwrap();
+        return;
+    }
+    writeln!(mermaid, "{indent}subgraph n{id} [\"{label}\"]").unwrap();
+    for child in hugr.children(node) {
+        write_node(hugr, child, depth + 1, ids, mermaid);
+    }
+    writeln!(mermaid, "{indent}end").unwrap();
+}
+
+/// A short label for an operation.
+fn label(optype: &OpType) -> String {
+    match optype {
+        OpType::FuncDefn(defn) => format!("FuncDefn: {}", defn.func_name()),
+        OpType::FuncDecl(decl) => format!("FuncDecl: {}", decl.func_name()),
+        OpType::ExtensionOp(op) => op.unqualified_id().to_string(),
+        _ => {
+            let debug = format!("{optype:?}");
+            let end = debug.find(['(', ' ', '{']).unwrap_or(debug.len());
+            debug[..end].to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::entangled_qs;
+    use crate::{JeffToHugrConfig, jeff_to_hugr_with_config};
+    use jeff::Jeff;
+    use rstest::rstest;
+
+    #[rstest]
+    fn labels_named_wires(entangled_qs: Jeff<'static>) {
+        let config = JeffToHugrConfig::default().with_name_wires(true);
+        let first = jeff_to_hugr_with_config(&entangled_qs, &config).unwrap();
+        let second = jeff_to_hugr_with_config(&entangled_qs, &config).unwrap();
+
+        let mermaid = mermaid_pretty(&first.hugr);
+        assert!(mermaid.starts_with("graph LR\n"));
+        for name in ["q0", "q4", "m0", "m4"] {
+            assert!(mermaid.contains(&format!("-->|{name}|")), "{name} missing");
+        }
+        assert_eq!(mermaid, mermaid_pretty(&second.hugr));
+    }
+}
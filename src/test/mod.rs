@@ -3,8 +3,19 @@
 use core::panic;
 use std::path::PathBuf;
 
+use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr};
+use hugr::extension::prelude::qb_t;
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::ops::OpType;
+use hugr::types::Signature;
+use hugr::{Hugr, HugrView, Node};
+use itertools::Itertools;
 use jeff::Jeff;
 
+use crate::metadata;
+use crate::optype::tket_ops::QuantumOp;
+use crate::passes::fuse::{Unitary, constant_angle, gate_unitary};
+
 const TEST_PROGRAMS_DIR: &str = "test_files/";
 
 /// Simple catalyst program with qubit arrays
@@ -43,6 +54,131 @@ pub fn catalyst_tket_opt() -> Jeff<'static> {
     load_example_program("catalyst_tket_opt")
 }
 
+/// Two functions choosing a rotation with a switch, with constant angles
+/// defined before it.
+///
+/// The `distinct` function passes a different angle to each branch, while
+/// `shared` also uses its angle after the switch.
+#[rstest::fixture]
+pub fn switch_constants() -> Jeff<'static> {
+    load_example_program("switch_constants")
+}
+
+/// Five classical functions, three of which have identical bodies.
+///
+/// `main` calls `add_b`, `add_c` and `shl`, but not `add_a`.
+#[rstest::fixture]
+pub fn duplicate_bodies() -> Jeff<'static> {
+    load_example_program("duplicate_bodies")
+}
+
+/// Two functions borrowing a qubit from the runtime pool.
+///
+/// `scoped` returns the qubit in the region it was borrowed in, while
+/// `escaping` returns it in both branches of a switch.
+#[rstest::fixture]
+pub fn borrowed_qubit() -> Jeff<'static> {
+    load_example_program("borrowed_qubit")
+}
+
+/// A function choosing one of four gates with a three-branch switch on an
+/// `int(32)`, with a default branch.
+#[rstest::fixture]
+pub fn int_switch() -> Jeff<'static> {
+    load_example_program("int_switch")
+}
+
+/// A function choosing one of two gates with a two-branch switch on an
+/// `int(8)`, without a default branch.
+#[rstest::fixture]
+pub fn int_switch_no_default() -> Jeff<'static> {
+    load_example_program("int_switch_no_default")
+}
+
+/// Two functions declared and defined in the same module, in both orders.
+///
+/// `main` calls `double` and `quadruple` through both their declarations and
+/// their definitions.
+#[rstest::fixture]
+pub fn redeclared_functions() -> Jeff<'static> {
+    load_example_program("redeclared_functions")
+}
+
+/// A function declared and defined with different signatures.
+#[rstest::fixture]
+pub fn conflicting_declaration() -> Jeff<'static> {
+    load_example_program("conflicting_declaration")
+}
+
+/// A program with a gate applied to an integer, an integer of the wrong
+/// width written into an array, and a call with an extra argument.
+#[rstest::fixture]
+pub fn type_errors() -> Jeff<'static> {
+    load_example_program("type_errors")
+}
+
+/// A function rotating a qubit by the sine of the square root of its angle
+/// parameter.
+#[rstest::fixture]
+pub fn float_functions() -> Jeff<'static> {
+    load_example_program("float_functions")
+}
+
+/// A malformed program applying two gates to the same allocated qubit.
+#[rstest::fixture]
+pub fn reused_qubit() -> Jeff<'static> {
+    load_example_program("reused_qubit")
+}
+
+/// A `FreeZero` of an ancilla returned to the |0⟩ state by an uncomputation.
+#[rstest::fixture]
+pub fn free_zero_verified() -> Hugr {
+    use QuantumOp::{CX, H};
+    free_zero_dfg(&[(H, &[1]), (CX, &[1, 0]), (CX, &[1, 0]), (H, &[1])], true)
+}
+
+/// A `FreeZero` of an ancilla left entangled with the input qubit, which
+/// a `Reset` returns to the |0⟩ state.
+#[rstest::fixture]
+pub fn free_zero_entangled() -> Hugr {
+    use QuantumOp::{CX, H};
+    free_zero_dfg(&[(H, &[1]), (CX, &[1, 0])], true)
+}
+
+/// A `FreeZero` of a qubit received as an input, whose state is not known.
+#[rstest::fixture]
+pub fn free_zero_input() -> Hugr {
+    free_zero_dfg(&[(QuantumOp::H, &[1])], false)
+}
+
+/// A DFG applying gates to its qubit input `0` and a qubit `1`, and freeing
+/// the qubit `1` with a `QFree` marked with [`metadata::FREE_ZERO`].
+///
+/// The freed qubit is allocated, or is a second input of the DFG.
+pub fn free_zero_dfg(gates: &[(QuantumOp, &[usize])], allocated: bool) -> Hugr {
+    let inputs = if allocated { 1 } else { 2 };
+    let signature = Signature::new(vec![qb_t(); inputs], vec![qb_t()]);
+    let mut builder = DFGBuilder::new(signature).unwrap();
+    let mut qubits = builder.input_wires().collect_vec();
+    if allocated {
+        let alloc = builder.add_dataflow_op(QuantumOp::QAlloc, []).unwrap();
+        qubits.push(alloc.out_wire(0));
+    }
+    for &(gate, wires) in gates {
+        let outputs = builder
+            .add_dataflow_op(gate, wires.iter().map(|&i| qubits[i]))
+            .unwrap()
+            .outputs();
+        for (&i, wire) in wires.iter().zip(outputs) {
+            qubits[i] = wire;
+        }
+    }
+    let free = builder.add_dataflow_op(QuantumOp::QFree, [qubits[1]]).unwrap().node();
+    let mut hugr = builder.finish_hugr_with_outputs([qubits[0]]).unwrap();
+    hugr.set_metadata(free, metadata::FREE_ZERO, true);
+    hugr
+}
+
 /// Load the example program by copying the file to an internal buffer.
 fn load_example_program(name: &str) -> Jeff<'static> {
     let filename = format!("{name}.jeff");
@@ -52,3 +188,82 @@ fn load_example_program(name: &str) -> Jeff<'static> {
     let buffer = std::io::BufReader::new(file);
     Jeff::read(buffer).unwrap_or_else(|e| panic!("Failed to read example program: {}", e))
 }
+
+/// Assert that two HUGRs are structurally equivalent, ignoring node indices.
+///
+/// Compares the multiset of operations in the entrypoint region, together with
+/// their parent operations and the operations connected to each of their inputs.
+pub fn assert_hugr_equivalent(a: &impl HugrView<Node = Node>, b: &impl HugrView<Node = Node>) {
+    let a_summary = structural_summary(a);
+    let b_summary = structural_summary(b);
+    assert_eq!(
+        a_summary, b_summary,
+        "HUGRs are not structurally equivalent"
+    );
+}
+
+/// Assert that two HUGRs applying single-qubit gates to their only qubit
+/// implement the same unitary, up to a global phase.
+///
+/// The unitaries are computed with small dense matrices, following the qubit
+/// from the entrypoint input to its output.
+pub fn assert_unitary_equivalent(a: &impl HugrView<Node = Node>, b: &impl HugrView<Node = Node>) {
+    let (a_unitary, b_unitary) = (single_qubit_unitary(a), single_qubit_unitary(b));
+    assert!(
+        a_unitary.equivalent(&b_unitary),
+        "HUGRs are not unitarily equivalent: {a_unitary:?} and {b_unitary:?}"
+    );
+}
+
+/// The unitary of the single-qubit gates applied to the first input of the
+/// HUGR entrypoint.
+fn single_qubit_unitary(hugr: &impl HugrView<Node = Node>) -> Unitary {
+    let [input, output] = hugr.get_io(hugr.entrypoint()).unwrap();
+    let mut unitary = Unitary::IDENTITY;
+    let (mut node, _) = hugr.single_linked_input(input, 0).unwrap();
+    while node != output {
+        let op = QuantumOp::of(hugr.get_optype(node)).unwrap();
+        let half_turns = constant_angle(hugr, node).map(|(half_turns, _)| half_turns);
+        unitary = gate_unitary(op, half_turns).unwrap() * unitary;
+        (node, _) = hugr.single_linked_input(node, 0).unwrap();
+    }
+    unitary
+}
+
+/// Returns a sorted, index-independent description of each node in the HUGR entrypoint region.
+fn structural_summary(hugr: &impl HugrView<Node = Node>) -> Vec<String> {
+    let mut summary = hugr
+        .entry_descendants()
+        .map(|node| {
+            let parent = hugr
+                .get_parent(node)
+                .map(|p| op_label(hugr.get_optype(p)))
+                .unwrap_or_default();
+            let inputs = hugr
+                .node_inputs(node)
+                .map(|port| {
+                    hugr.linked_outputs(node, port)
+                        .map(|(src, src_port)| {
+                            format!("{}:{}", op_label(hugr.get_optype(src)), src_port.index())
+                        })
+                        .sorted()
+                        .join("|")
+                })
+                .join(", ");
+            format!(
+                "{parent} / {} <- [{inputs}]",
+                op_label(hugr.get_optype(node))
+            )
+        })
+        .collect_vec();
+    summary.sort();
+    summary
+}
+
+/// A short, index-independent label for an operation.
+fn op_label(op: &OpType) -> String {
+    match op.as_extension_op() {
+        Some(ext_op) => format!("{}{:?}", ext_op.qualified_id(), ext_op.args()),
+        None => format!("{op:?}"),
+    }
+}
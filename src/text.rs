@@ -0,0 +1,327 @@
+//! A textual rendering of _jeff_ programs, meant to be read by people.
+//!
+//! [`dump_jeff_text`] writes each function of the first module of a program,
+//! one operation per line:
+//!
+//! ```text
+//! func @main(%0: qubit) -> (qubit) {
+//!     %1: qubit = gate "H"(%0)
+//!     return %1
+//! }
+//! declare @callee(qubit) -> (qubit)
+//! ```
+//!
+//! Values are named by their id and annotated with their type where they
+//! are defined. Gates, calls and control flow have a dedicated syntax, the
+//! other operations are written with the `Debug` form of their _jeff_
+//! operation type. Control flow operations are followed by their nested
+//! regions, each ending with a `yield` of its targets.
+//!
+//! The rendering is not lossless: the reader of the `jeff` crate does not
+//! expose the attributes and metadata of the program, so they are not
+//! written. There is no parser for this form either, as the `jeff` crate
+//! only provides a reader and building a binary program from the text needs
+//! a writer.
+
+use std::fmt::Write;
+
+use itertools::Itertools;
+use jeff::Jeff;
+use jeff::reader::optype::{self as jeff_optype, ControlFlowOp, OpType as JeffOpType};
+use jeff::reader::{Function, Operation, Region};
+
+use crate::JeffToHugrError;
+
+/// Render the first module of a _jeff_ program as text.
+///
+/// See the [module documentation](self) for the syntax. If part of the
+/// program cannot be read, the rendering stops with an `error:` line
+/// describing the problem.
+///
+/// # Examples
+///
+/// ```
+/// use hugr_jeff::doctest_support::qubits;
+/// use hugr_jeff::text::dump_jeff_text;
+///
+/// let text = dump_jeff_text(&qubits());
+/// assert!(text.starts_with("func @"));
+/// assert!(text.contains("= gate \"CX\"("));
+/// ```
+pub fn dump_jeff_text(jeff: &Jeff) -> String {
+    let mut text = String::new();
+    if let Err(err) = write_module(jeff, &mut text) {
+        writeln!(text, "error: {err}").unwrap();
+    }
+    text
+}
+
+/// Write the functions of the first module of a program.
+fn write_module(jeff: &Jeff, text: &mut String) -> Result<(), JeffToHugrError> {
+    let functions = jeff.module().functions().collect_vec();
+    for func in &functions {
+        let outputs = func
+            .output_types()
+            .map(|port| Ok(port?.ty().to_string()))
+            .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+        match func {
+            Function::Definition(def) => {
+                let body = def.body();
+                let inputs = typed_values(&body)?;
+                writeln!(
+                    text,
+                    "func @{}({inputs}) -> ({}) {{",
+                    func.name(),
+                    outputs.join(", ")
+                )
+                .unwrap();
+                write_region_body(&body, &functions, 1, text)?;
+                writeln!(text, "    return {}", targets(&body)?).unwrap();
+                writeln!(text, "}}").unwrap();
+            }
+            Function::Declaration(_) => {
+                let inputs = func
+                    .input_types()
+                    .map(|port| Ok(port?.ty().to_string()))
+                    .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+                writeln!(
+                    text,
+                    "declare @{}({}) -> ({})",
+                    func.name(),
+                    inputs.join(", "),
+                    outputs.join(", ")
+                )
+                .unwrap();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Write the operations of a region, indented by `depth` levels.
+fn write_region_body(
+    region: &Region<'_>,
+    functions: &[Function<'_>],
+    depth: usize,
+    text: &mut String,
+) -> Result<(), JeffToHugrError> {
+    let indent = "    ".repeat(depth);
+    for op in region.operations() {
+        let outputs = op
+            .outputs()
+            .map(|value| {
+                let value = value?;
+                Ok(format!("%{:?}: {}", value.id(), value.ty()))
+            })
+            .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+        let inputs = op
+            .inputs()
+            .map(|value| Ok(format!("%{:?}", value?.id())))
+            .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+        write!(text, "{indent}").unwrap();
+        if !outputs.is_empty() {
+            write!(text, "{} = ", outputs.join(", ")).unwrap();
+        }
+        write!(text, "{}({})", op_name(&op, functions), inputs.join(", ")).unwrap();
+
+        let JeffOpType::ControlFlowOp(cf_op) = op.op_type() else {
+            writeln!(text).unwrap();
+            continue;
+        };
+        writeln!(text, " {{").unwrap();
+        match cf_op {
+            ControlFlowOp::Switch(switch_op) => {
+                for idx in 0..switch_op.branch_count() {
+                    let label = format!("branch {idx}");
+                    write_region(&label, &switch_op.branch(idx), functions, depth + 1, text)?;
+                }
+                if let Some(default_branch) = switch_op.default_branch() {
+                    write_region("default", &default_branch, functions, depth + 1, text)?;
+                }
+            }
+            ControlFlowOp::For { region } => {
+                write_region("body", &region, functions, depth + 1, text)?;
+            }
+            ControlFlowOp::While { body, condition } => {
+                write_region("condition", &condition, functions, depth + 1, text)?;
+                write_region("body", &body, functions, depth + 1, text)?;
+            }
+            ControlFlowOp::DoWhile { body, condition } => {
+                write_region("body", &body, functions, depth + 1, text)?;
+                write_region("condition", &condition, functions, depth + 1, text)?;
+            }
+        }
+        writeln!(text, "{indent}}}").unwrap();
+    }
+    Ok(())
+}
+
+/// Write a nested region with its sources, operations and targets.
+fn write_region(
+    label: &str,
+    region: &Region<'_>,
+    functions: &[Function<'_>],
+    depth: usize,
+    text: &mut String,
+) -> Result<(), JeffToHugrError> {
+    let indent = "    ".repeat(depth);
+    writeln!(text, "{indent}{label}({}) {{", typed_values(region)?).unwrap();
+    write_region_body(region, functions, depth + 1, text)?;
+    writeln!(text, "{indent}    yield {}", targets(region)?).unwrap();
+    writeln!(text, "{indent}}}").unwrap();
+    Ok(())
+}
+
+/// The sources of a region, with their types.
+fn typed_values(region: &Region<'_>) -> Result<String, JeffToHugrError> {
+    let sources = region
+        .sources()
+        .map(|value| {
+            let value = value?;
+            Ok(format!("%{:?}: {}", value.id(), value.ty()))
+        })
+        .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+    Ok(sources.join(", "))
+}
+
+/// The targets of a region.
+fn targets(region: &Region<'_>) -> Result<String, JeffToHugrError> {
+    let targets = region
+        .targets()
+        .map(|value| Ok(format!("%{:?}", value?.id())))
+        .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+    Ok(targets.join(", "))
+}
+
+/// The name of an operation, with its payload.
+fn op_name(op: &Operation<'_>, functions: &[Function<'_>]) -> String {
+    match op.op_type() {
+        JeffOpType::QubitOp(jeff_optype::QubitOp::Gate(gate)) => {
+            let mut name = match gate.gate_type {
+                jeff_optype::GateOpType::WellKnown(well_known) => format!("gate {well_known}"),
+                jeff_optype::GateOpType::PauliProdRotation { pauli_string } => {
+                    format!("gate ppr \"{pauli_string}\"")
+                }
+                jeff_optype::GateOpType::Custom { name, .. } => format!("gate \"{name}\""),
+            };
+            if gate.adjoint {
+                name.push_str(" adj");
+            }
+            if gate.control_qubits > 0 {
+                write!(name, " ctrl({})", gate.control_qubits).unwrap();
+            }
+            if gate.power > 1 {
+                write!(name, " pow({})", gate.power).unwrap();
+            }
+            name
+        }
+        JeffOpType::FuncOp(call) => match functions.get(call.func_idx as usize) {
+            Some(callee) => format!("call @{}", callee.name()),
+            None => format!("call #{}", call.func_idx),
+        },
+        JeffOpType::ControlFlowOp(cf_op) => match cf_op {
+            ControlFlowOp::Switch(_) => "switch",
+            ControlFlowOp::For { .. } => "for",
+            ControlFlowOp::While { .. } => "while",
+            ControlFlowOp::DoWhile { .. } => "do_while",
+        }
+        .to_string(),
+        JeffOpType::IntArrayOp(int_array_op) => {
+            let variant = variant_name(&int_array_op);
+            let values = match int_array_op {
+                jeff_optype::IntArrayOp::ConstArray1(array) => array.values().join(", "),
+                jeff_optype::IntArrayOp::ConstArray8(array) => array.values().join(", "),
+                jeff_optype::IntArrayOp::ConstArray16(array) => array.values().join(", "),
+                jeff_optype::IntArrayOp::ConstArray32(array) => array.values().join(", "),
+                jeff_optype::IntArrayOp::ConstArray64(array) => array.values().join(", "),
+                int_array_op => return format!("IntArrayOp({int_array_op:?})"),
+            };
+            format!("IntArrayOp({variant}[{values}])")
+        }
+        JeffOpType::FloatArrayOp(float_array_op) => {
+            let variant = variant_name(&float_array_op);
+            let values = match float_array_op {
+                jeff_optype::FloatArrayOp::ConstArray32(array) => array.values().join(", "),
+                jeff_optype::FloatArrayOp::ConstArray64(array) => array.values().join(", "),
+                float_array_op => return format!("FloatArrayOp({float_array_op:?})"),
+            };
+            format!("FloatArrayOp({variant}[{values}])")
+        }
+        op_type => format!("{op_type:?}"),
+    }
+}
+
+/// The name of an enum variant, without its payload.
+fn variant_name(value: &impl std::fmt::Debug) -> String {
+    let debug = format!("{value:?}");
+    let end = debug.find(['(', ' ', '{']).unwrap_or(debug.len());
+    debug[..end].to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{borrowed_qubit, catalyst_simple, entangled_calls, int_switch, qubits};
+    use rstest::rstest;
+
+    #[rstest]
+    fn function_header_and_return(qubits: Jeff<'static>) {
+        let text = dump_jeff_text(&qubits);
+        let module = qubits.module();
+        let func = module.functions().next().unwrap();
+        let header = text.lines().next().unwrap();
+        assert!(header.starts_with(&format!("func @{}(", func.name())));
+        assert!(header.ends_with(" {"));
+        assert!(text.contains("= gate \"CX\"("));
+        assert!(text.lines().any(|line| line.starts_with("    return %")));
+        assert_eq!(text.lines().last(), Some("}"));
+    }
+
+    #[rstest]
+    fn switch_branches(int_switch: Jeff<'static>) {
+        let text = dump_jeff_text(&int_switch);
+        assert!(text.starts_with("func @three_way("));
+        assert!(text.contains(" = switch(%"));
+        for label in ["branch 0(", "branch 1(", "branch 2(", "default("] {
+            assert!(
+                text.contains(&format!("        {label}%")),
+                "{label} missing"
+            );
+        }
+        for gate in ["H", "X", "Z", "Y"] {
+            assert!(
+                text.contains(&format!("= gate \"{gate}\"(")),
+                "{gate} missing"
+            );
+        }
+        assert_eq!(text.matches("yield %").count(), 4);
+    }
+
+    #[rstest]
+    fn declarations_and_calls(borrowed_qubit: Jeff<'static>) {
+        let text = dump_jeff_text(&borrowed_qubit);
+        assert!(text.contains("declare @qubit_borrow() -> ("));
+        assert!(text.contains("declare @qubit_return("));
+        assert!(text.contains(" = call @qubit_borrow()"));
+        assert!(
+            text.lines()
+                .any(|line| line.starts_with("    call @qubit_return(%"))
+        );
+    }
+
+    #[rstest]
+    #[case::qubits(qubits())]
+    #[case::catalyst_simple(catalyst_simple())]
+    #[case::entangled_calls(entangled_calls())]
+    #[case::int_switch(int_switch())]
+    fn one_header_per_function(#[case] jeff: Jeff<'static>) {
+        let text = dump_jeff_text(&jeff);
+        assert!(!text.contains("error: "), "{text}");
+        let headers = text
+            .lines()
+            .filter(|line| line.starts_with("func @") || line.starts_with("declare @"))
+            .count();
+        assert_eq!(headers, jeff.module().functions().count());
+        assert_eq!(text.matches('{').count(), text.matches('}').count());
+    }
+}
@@ -1,23 +1,414 @@
 //! _jeff_ to HUGR Translation
 
-use std::collections::BTreeMap;
+mod angle_params;
+mod bit_arrays;
+mod bit_order;
+mod config;
+mod dedup;
+mod diagnostics;
+mod effects;
+mod handle_abi;
+pub(crate) mod hooks;
+mod incremental;
+mod inline;
+mod liveness;
+mod map;
+mod modules;
+mod names;
+mod progress;
+mod qureg_arrays;
+mod snapshot;
+mod split;
+mod strings;
+mod target;
+mod trace;
+pub(crate) mod type_codec;
+mod views;
+mod wire_names;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
 use std::mem;
+use std::path::PathBuf;
+use std::sync::Arc;
 
 use derive_more::{Display, Error, From};
-use hugr::builder::{Container, HugrBuilder, ModuleBuilder, SubContainer};
+use hugr::builder::{Container, ModuleBuilder, SubContainer};
+use hugr::extension::ExtensionId;
+use hugr::extension::prelude::qb_t;
 use hugr::hugr::hugrmut::HugrMut;
+use hugr::ops::constant::CustomConst;
 use hugr::ops::handle::{self, NodeHandle};
+use hugr::ops::{LoadConstant, OpType, Value};
+use hugr::std_extensions::arithmetic::float_types::ConstF64;
+use hugr::types::Type as HugrType;
 use hugr::{Hugr, HugrView, IncomingPort, Node, OutgoingPort};
 use itertools::Itertools;
 use jeff::Jeff;
 use jeff::reader::ReadJeff;
 
-use crate::optype::JeffToHugrOp;
+use crate::analysis::{
+    GateSetViolation, TypeCheckError, UnverifiedFreeZero, check_free_zero, check_gate_set,
+    module_depths, module_type_errors, peak_qubits_bound,
+};
+use crate::capabilities::disallowed_extension;
+use crate::extension::{ConstIntReg, JeffOp, OpBuildError};
+use crate::metadata;
+use crate::optype::{JeffToHugrOp, build_select_function};
+use crate::passes::FreeZeroRepair;
+use crate::provenance::Provenance;
 use crate::types::jeff_signature_to_hugr;
+use angle_params::AngleParams;
+use effects::Effect;
+use names::{is_valid_name, sanitize_names};
+use trace::TraceRecorder;
+use type_codec::BoundaryTypes;
+use views::{RegisterViews, ViewIntrinsic};
+
+pub use config::{
+    ArrayBounds, BitArrays, BitOrder, ExactAngleConfig, FunctionDedup, HandleAbi, IntOverflow,
+    JeffToHugrConfig, NanAngles, Protection, QuregLowering, ResourceLimit, ResourceLimits,
+};
+pub use incremental::{
+    ConversionCache, jeff_to_hugr_incremental, jeff_to_hugr_incremental_with_config,
+};
+pub use map::JeffHugrMap;
+pub use modules::{ModuleInfo, ModuleSelector, jeff_modules, jeff_to_hugr_module};
+pub use progress::{Progress, ProgressHook};
+pub use split::{LinkError, jeff_to_hugr_split, jeff_to_hugr_split_with_config, link_hugrs};
+pub use target::TargetInfo;
+pub use trace::{ConversionTrace, TraceEvent};
+pub use wire_names::WireNames;
+
+pub(crate) use liveness::dead_trailing_ops;
 
 /// Translate a _jeff_ program into a HUGR program.
+///
+/// Programs with several modules are translated from their first module, see
+/// [`jeff_to_hugr_module`] to select another one.
+///
+/// # Examples
+///
+/// ```
+/// use hugr_jeff::doctest_support::{QUBITS_FUNCTION, qubits};
+/// use hugr_jeff::hugr::HugrView;
+/// use hugr_jeff::jeff_to_hugr;
+///
+/// let hugr = jeff_to_hugr(&qubits()).unwrap();
+/// hugr.validate().unwrap();
+///
+/// // Each jeff function becomes a function definition of the module.
+/// let names = hugr
+///     .children(hugr.module_root())
+///     .filter_map(|node| hugr.get_optype(node).as_func_defn())
+///     .map(|defn| defn.func_name().as_str())
+///     .collect::<Vec<_>>();
+/// assert_eq!(names, [QUBITS_FUNCTION]);
+/// ```
 pub fn jeff_to_hugr(jeff: &Jeff) -> Result<Hugr, JeffToHugrError> {
-    BuildContext::build_module(jeff.module())
+    jeff_to_hugr_with_config(jeff, &JeffToHugrConfig::default()).map(|res| res.hugr)
+}
+
+/// Translate a _jeff_ program into a HUGR program, using a custom configuration.
+///
+/// Returns the HUGR together with a map relating it to the _jeff_ program.
+///
+/// # Errors
+///
+/// - [`JeffToHugrError::AmbiguousModule`] if
+///   [`JeffToHugrConfig::strict_modules`] is set and the program has more
+///   than one module.
+/// - [`JeffToHugrError::InvalidFunctionName`] if
+///   [`JeffToHugrConfig::strict_names`] is set and a function name is not a
+///   valid HUGR name.
+/// - [`JeffToHugrError::NulInString`] if a gate or function name contains a
+///   NUL byte.
+/// - [`JeffToHugrError::ConflictingFunction`] if a function is declared and
+///   defined with different signatures.
+/// - [`JeffToHugrError::TypeErrors`] if [`JeffToHugrConfig::typecheck`] is
+///   set and the values of the program do not have the expected types.
+/// - [`JeffToHugrError::InvalidHugrProgram`] if [`JeffToHugrConfig::validate`]
+///   is set and the result is not a valid HUGR.
+/// - [`JeffToHugrError::DisallowedExtension`] if
+///   [`JeffToHugrConfig::check_extensions`] is set and the result references
+///   an extension outside of the conversion allow-list.
+/// - [`JeffToHugrError::GateSetViolations`] if
+///   [`JeffToHugrConfig::gate_set`] is set and the result has operations
+///   outside of it.
+/// - [`JeffToHugrError::UnverifiedFreeZero`] if
+///   [`JeffToHugrConfig::free_zero_repair`] is
+///   [`crate::passes::FreeZeroRepair::Error`] and a `FreeZero` cannot be
+///   verified.
+///
+/// # Examples
+///
+/// Annotate the translated functions with their circuit depth, and find
+/// them from the _jeff_ function ids:
+///
+/// ```
+/// use hugr_jeff::doctest_support::qubits;
+/// use hugr_jeff::hugr::HugrView;
+/// use hugr_jeff::{JeffToHugrConfig, jeff_to_hugr_with_config, metadata};
+///
+/// let config = JeffToHugrConfig::default().with_annotate_depths(true);
+/// let converted = jeff_to_hugr_with_config(&qubits(), &config).unwrap();
+/// assert!(converted.warnings.is_empty());
+///
+/// let func = converted.map.function_node(0).unwrap();
+/// let depth = converted.hugr.get_metadata(func, metadata::DEPTH);
+/// assert_eq!(depth, Some(&serde_json::json!(3)));
+/// ```
+pub fn jeff_to_hugr_with_config(
+    jeff: &Jeff,
+    config: &JeffToHugrConfig,
+) -> Result<ConvertedHugr, JeffToHugrError> {
+    modules::check_default_module(&jeff_modules(jeff), config)?;
+    BuildContext::build_module(jeff.module(), config)
+}
+
+/// Translate the functions of a _jeff_ program into an existing HUGR module.
+///
+/// The translated function definitions and declarations are appended as
+/// children of the module root of `module`. If `prefix` is given, it is
+/// prepended to the name of every translated function.
+///
+/// Calls are only resolved among the newly added functions. Returns the
+/// added function nodes, indexed by their _jeff_ function id.
+///
+/// The [`metadata::BIT_ORDER`] of the module is set to the default
+/// [`BitOrder::LsbFirst`].
+///
+/// If the translation fails or panics, the functions added so far are
+/// removed and `module` is left unchanged.
+///
+/// # Errors
+///
+/// Returns [`JeffToHugrError::FunctionNameCollision`] if a translated
+/// function has the same name as a function already in the module. Without
+/// a `prefix`, this includes the names that only clash once normalized into
+/// valid HUGR names. With a `prefix`, the normalized names get a `_<n>`
+/// suffix instead, avoiding the functions of the module.
+pub fn jeff_into_hugr_module(
+    jeff: &Jeff,
+    module: &mut Hugr,
+    prefix: Option<&str>,
+) -> Result<Vec<Node>, JeffToHugrError> {
+    let mut ctx = BuildContext::with_config(&JeffToHugrConfig::default());
+    let map = ctx.build_into_module(jeff.module(), module, prefix, &BTreeMap::new())?;
+    Ok(map.function_nodes().to_vec())
+}
+
+/// The result of a configured _jeff_ to HUGR translation.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ConvertedHugr {
+    /// The translated HUGR program.
+    pub hugr: Hugr,
+    /// Mapping between the _jeff_ program and the HUGR nodes.
+    pub map: JeffHugrMap,
+    /// Approximations made by the translation.
+    pub warnings: Vec<ConversionWarning>,
+    /// Placement choices made by the translation.
+    pub stats: ConversionStats,
+    /// The recorded translation steps, when
+    /// [`JeffToHugrConfig::record_trace`] is set.
+    pub trace: Option<ConversionTrace>,
+}
+
+/// Placement choices made while translating a _jeff_ program.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ConversionStats {
+    /// Constants only used by a `Switch`, loaded inside the branches reading
+    /// them instead of being passed as inputs of the `Conditional`.
+    pub sunk_constants: usize,
+    /// Constants passed to a control-flow operation that stay in the
+    /// enclosing region, and reach the nested regions through the inputs of
+    /// the HUGR container.
+    ///
+    /// These are the constants also used outside of a `Switch`, and all the
+    /// constants carried by loops.
+    pub threaded_constants: usize,
+}
+
+/// An approximation made while translating a _jeff_ program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConversionWarning {
+    /// A 32-bit bitcast whose float is widened to a `float64`, as HUGR has no
+    /// 32-bit float type.
+    WidenedBitcast {
+        /// The HUGR name of the function containing the bitcast.
+        function: Option<String>,
+        /// The position of the bitcast, counting from the start of its
+        /// function.
+        op_index: Option<usize>,
+    },
+    /// A 64-bit multiplication that is not checked for overflow with
+    /// [`IntOverflow::Trap`], as HUGR has no wider integer type to compute
+    /// it.
+    UncheckedOverflow {
+        /// The HUGR name of the function containing the multiplication.
+        function: Option<String>,
+        /// The position of the multiplication, counting from the start of
+        /// its function.
+        op_index: Option<usize>,
+    },
+    /// A function may use more qubits than the target device has, as
+    /// bounded by [`crate::analysis::peak_qubits_bound`].
+    TargetQubitsExceeded {
+        /// The HUGR name of the function.
+        function: String,
+        /// The bound on the number of qubits live at once in the function.
+        qubits: usize,
+        /// The name of the target device.
+        target: String,
+        /// The number of qubits of the target device.
+        available: usize,
+    },
+    /// A `FreeZero` whose qubit is not known to be in the |0⟩ state, which
+    /// is translated as a plain `QFree` with
+    /// [`crate::passes::FreeZeroRepair::Downgrade`].
+    FreeZeroDowngraded {
+        /// The HUGR name of the function containing the operation.
+        function: Option<String>,
+        /// The position of the operation, counting from the start of its
+        /// function.
+        op_index: Option<usize>,
+    },
+    /// A constant gate angle that is NaN, whose rotation is replaced by a
+    /// runtime panic with [`NanAngles::Warn`].
+    NanAngle {
+        /// The HUGR name of the function containing the gate.
+        function: Option<String>,
+        /// The position of the gate, counting from the start of its
+        /// function.
+        op_index: Option<usize>,
+    },
+    /// A gate or function name that is not valid UTF-8, whose invalid
+    /// sequences are replaced by `U+FFFD`.
+    InvalidUtf8String {
+        /// The HUGR name of the function containing the operation, if the
+        /// string is read by an operation.
+        function: Option<String>,
+        /// The position of the operation, counting from the start of its
+        /// function.
+        op_index: Option<usize>,
+        /// The sanitized string.
+        sanitized: String,
+    },
+    /// A gate or function name longer than
+    /// [`ResourceLimits::max_string_len`], which is truncated.
+    TruncatedString {
+        /// The HUGR name of the function containing the operation, if the
+        /// string is read by an operation.
+        function: Option<String>,
+        /// The position of the operation, counting from the start of its
+        /// function.
+        op_index: Option<usize>,
+        /// The length in bytes of the original string.
+        length: usize,
+    },
+    /// A function whose float array parameters are not symbolized with
+    /// [`JeffToHugrConfig::symbolize_angle_params`], as it is called within
+    /// the module.
+    AngleParamsNotSymbolized {
+        /// The HUGR name of the function.
+        function: String,
+    },
+}
+
+impl fmt::Display for ConversionWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConversionWarning::WidenedBitcast { function, op_index } => {
+                write!(f, "32-bit bitcast")?;
+                if let Some(op_index) = op_index {
+                    write!(f, " at operation {op_index}")?;
+                }
+                if let Some(function) = function {
+                    write!(f, " in function '{function}'")?;
+                }
+                write!(f, " is translated with a 64-bit float")
+            }
+            ConversionWarning::UncheckedOverflow { function, op_index } => {
+                write!(f, "64-bit multiplication")?;
+                if let Some(op_index) = op_index {
+                    write!(f, " at operation {op_index}")?;
+                }
+                if let Some(function) = function {
+                    write!(f, " in function '{function}'")?;
+                }
+                write!(f, " is not checked for overflow")
+            }
+            ConversionWarning::TargetQubitsExceeded {
+                function,
+                qubits,
+                target,
+                available,
+            } => write!(
+                f,
+                "Function '{function}' may use {qubits} qubits, but target '{target}' has \
+                 {available}"
+            ),
+            ConversionWarning::FreeZeroDowngraded { function, op_index } => {
+                write!(f, "FreeZero")?;
+                if let Some(op_index) = op_index {
+                    write!(f, " at operation {op_index}")?;
+                }
+                if let Some(function) = function {
+                    write!(f, " in function '{function}'")?;
+                }
+                write!(
+                    f,
+                    " is not known to free a |0⟩ qubit, and is translated as a Free"
+                )
+            }
+            ConversionWarning::NanAngle { function, op_index } => {
+                write!(f, "NaN gate angle")?;
+                if let Some(op_index) = op_index {
+                    write!(f, " at operation {op_index}")?;
+                }
+                if let Some(function) = function {
+                    write!(f, " in function '{function}'")?;
+                }
+                write!(f, " is translated as a runtime panic")
+            }
+            ConversionWarning::InvalidUtf8String {
+                function,
+                op_index,
+                sanitized,
+            } => {
+                write!(f, "Invalid UTF-8 string '{sanitized}'")?;
+                if let Some(op_index) = op_index {
+                    write!(f, " at operation {op_index}")?;
+                }
+                if let Some(function) = function {
+                    write!(f, " in function '{function}'")?;
+                }
+                write!(f, " has its invalid sequences replaced")
+            }
+            ConversionWarning::TruncatedString {
+                function,
+                op_index,
+                length,
+            } => {
+                write!(f, "String of {length} bytes")?;
+                if let Some(op_index) = op_index {
+                    write!(f, " at operation {op_index}")?;
+                }
+                if let Some(function) = function {
+                    write!(f, " in function '{function}'")?;
+                }
+                write!(f, " is truncated")
+            }
+            ConversionWarning::AngleParamsNotSymbolized { function } => write!(
+                f,
+                "The angle parameters of function '{function}' are not symbolized, as it is \
+                 called within the module"
+            ),
+        }
+    }
 }
 
 /// Error type for the _jeff_ to HUGR translation.
@@ -41,23 +432,429 @@ pub enum JeffToHugrError {
     },
     /// The _jeff_ file is invalid.
     MalformedJeffFile(jeff::reader::ReadError),
-    /// We tried to generate an invalid HUGR program.
+    /// We tried to generate an invalid HUGR program, with
+    /// [`JeffToHugrConfig::validate`].
     InvalidHugrProgram(Box<hugr::hugr::ValidationError<Node>>),
     /// Internal error while building the HUGR program.
     BuildError(Box<hugr::builder::BuildError>),
+    /// The bit arrays could not be converted to the configured [`BitArrays`] representation.
+    BitArrayConversion(Box<hugr::algorithms::replace_types::ReplaceTypesError>),
+    /// A function name is not a valid HUGR name, and
+    /// [`JeffToHugrConfig::strict_names`] forbids normalizing it.
+    #[display("The function name '{name}' is not a valid HUGR name")]
+    #[from(ignore)]
+    InvalidFunctionName {
+        /// The _jeff_ function name.
+        name: String,
+    },
+    /// A gate or function name contains a NUL byte.
+    #[display("The string {string:?} contains a NUL byte")]
+    #[from(ignore)]
+    NulInString {
+        /// The string, with its invalid UTF-8 sequences replaced.
+        string: String,
+        /// The position of the operation reading the string, counting from
+        /// the start of its function.
+        op_index: Option<usize>,
+    },
+    /// A translated function has the same name as a function already in the target module.
+    #[display("A function named '{name}' already exists in the module")]
+    #[from(ignore)]
+    FunctionNameCollision {
+        /// The colliding function name.
+        name: String,
+    },
+    /// A function is both declared and defined in the module, with different signatures.
+    #[display("The function '{name}' is declared and defined with different signatures")]
+    #[from(ignore)]
+    ConflictingFunction {
+        /// The _jeff_ function name.
+        name: String,
+    },
+    /// The _jeff_ program requests more resources than allowed by the [`ResourceLimits`].
+    #[display("Resource limit '{limit}' exceeded: requested {requested}")]
+    #[from(ignore)]
+    ResourceLimitExceeded {
+        /// The exceeded limit.
+        limit: ResourceLimit,
+        /// The requested amount.
+        requested: usize,
+    },
+    /// A trailing operation consumes a linear value that is already used by
+    /// the region outputs or by another operation.
+    #[display(
+        "Linear value {value_id:?} is consumed by trailing operation {op_index} after being claimed by the region outputs or another operation"
+    )]
+    #[from(ignore)]
+    UseAfterMove {
+        /// The linear value used more than once.
+        value_id: jeff::reader::value::ValueId,
+        /// The position of the conflicting operation in its region.
+        op_index: usize,
+    },
+    /// A function or operation has more inputs or outputs than a HUGR node can hold.
+    #[display("{count} inputs or outputs exceed the limit of {} for a HUGR node", MAX_VALUE_PORTS)]
+    #[from(ignore)]
+    TooManyPorts {
+        /// The number of inputs or outputs requested.
+        count: usize,
+    },
+    /// A [`ConversionCache`] entry cannot be inserted in the new module.
+    #[display("Invalid conversion cache: {reason}")]
+    #[from(ignore)]
+    InvalidConversionCache {
+        /// Why the cached function cannot be reused.
+        reason: String,
+    },
+    /// The parameters of a gate are passed as a float array, but the number
+    /// of parameters to unpack cannot be determined from the gate.
+    #[display(
+        "Cannot unpack the float array parameters of gate {gate}, as its number of parameters is unknown"
+    )]
+    #[from(ignore)]
+    UnknownGateParams {
+        /// The gate name.
+        gate: String,
+    },
+    /// The HUGR operation translating a _jeff_ operation could not be built.
+    InvalidHugrOp(OpBuildError),
+    /// A state preparation is given an amplitude array of the wrong length.
+    ///
+    /// See [`JeffOpDef::StatePrep`](crate::extension::JeffOpDef::StatePrep)
+    /// for the amplitude layout.
+    #[display(
+        "A state preparation of {qubits} qubits takes 2^{qubits} real or 2^{qubits} complex amplitudes, got an array of {floats} floats"
+    )]
+    #[from(ignore)]
+    InvalidStatePrep {
+        /// The number of prepared qubits.
+        qubits: usize,
+        /// The length of the amplitude array.
+        floats: usize,
+    },
+    /// The translation was cancelled by the [`JeffToHugrConfig::progress`] hook.
+    #[display("Translation cancelled while {progress}")]
+    #[from(ignore)]
+    Cancelled {
+        /// The progress report the hook cancelled the translation at.
+        progress: Progress,
+    },
+    /// A function cannot be inlined, as it calls itself directly or through
+    /// other functions.
+    #[display("Cannot inline the recursive function '{function}'")]
+    #[from(ignore)]
+    RecursiveInline {
+        /// The name of a function in the recursive call chain.
+        function: String,
+    },
+    /// A call to a declared function cannot be inlined, see
+    /// [`JeffToHugrConfig::strict_inline`].
+    #[display("Cannot inline the call to the declared function '{function}'")]
+    #[from(ignore)]
+    UninlinableCall {
+        /// The name of the declared function.
+        function: String,
+    },
+    /// The program has several modules, and
+    /// [`JeffToHugrConfig::strict_modules`] requires selecting one with
+    /// [`jeff_to_hugr_module`].
+    #[display("The jeff program has {modules} modules, select one to translate")]
+    #[from(ignore)]
+    AmbiguousModule {
+        /// The number of modules in the program.
+        modules: usize,
+    },
+    /// No module of the program matches the selector passed to
+    /// [`jeff_to_hugr_module`].
+    #[display("The jeff program has no module {module}")]
+    #[from(ignore)]
+    UnknownModule {
+        /// The module selector.
+        module: ModuleSelector,
+    },
+    /// No function of the program has the name passed to
+    /// [`crate::analysis::circuit_depth`].
+    #[display("The jeff program has no function '{function}'")]
+    #[from(ignore)]
+    UnknownFunction {
+        /// The function name.
+        function: String,
+    },
+    /// The values of the program do not have the types expected by their
+    /// operations, with [`JeffToHugrConfig::typecheck`].
+    #[display(
+        "{} values do not have the expected type, the first one is: {}",
+        errors.len(),
+        errors[0]
+    )]
+    #[from(ignore)]
+    TypeErrors {
+        /// The values with an unexpected type.
+        errors: Vec<TypeCheckError>,
+    },
+    /// The translated HUGR references an extension outside of
+    /// [`crate::capabilities::conversion_extension_allowlist`], with
+    /// [`JeffToHugrConfig::check_extensions`].
+    #[display("The extension '{ext}' used at {node} is not in the conversion allow-list")]
+    #[from(ignore)]
+    DisallowedExtension {
+        /// The extension outside of the allow-list.
+        ext: ExtensionId,
+        /// The first node referencing the extension.
+        node: Node,
+    },
+    /// The translated HUGR has operations outside of the
+    /// [`JeffToHugrConfig::gate_set`].
+    #[display(
+        "{} operations are not in the target gate set, the first one is: {}",
+        violations.len(),
+        violations[0]
+    )]
+    #[from(ignore)]
+    GateSetViolations {
+        /// The operations outside of the gate set.
+        violations: Vec<GateSetViolation>,
+    },
+    /// The translated HUGR has `FreeZero` operations whose qubit is not
+    /// known to be in the |0⟩ state, with
+    /// [`crate::passes::FreeZeroRepair::Error`].
+    #[display(
+        "{} FreeZero operations are not verified, the first one is: {}",
+        frees.len(),
+        frees[0]
+    )]
+    #[from(ignore)]
+    UnverifiedFreeZero {
+        /// The unverified operations.
+        frees: Vec<UnverifiedFreeZero>,
+    },
+    /// A qubit register is used while a view of it is live.
+    ///
+    /// Register views are translated with move semantics, which do not allow
+    /// using the parent register until the view is released.
+    #[display("Qubit register used by operation {op_index} while a view of it is live")]
+    #[from(ignore)]
+    AliasedRegisterUse {
+        /// The position of the operation using the register, counting from
+        /// the start of its function.
+        op_index: usize,
+    },
+    /// A register view is not released in the region it is created in, or a
+    /// release does not match a live view.
+    #[display("Register view created or released by operation {op_index} is unmatched")]
+    #[from(ignore)]
+    UnmatchedRegisterView {
+        /// The position of the unmatched operation, counting from the start
+        /// of its function.
+        op_index: usize,
+    },
+    /// A linear value is listed several times among the sources or among
+    /// the targets of a region.
+    #[display("Linear value {value_id:?} is listed several times at a region boundary")]
+    #[from(ignore)]
+    DuplicateLinearBoundaryValue {
+        /// The repeated value.
+        value_id: jeff::reader::value::ValueId,
+    },
+    /// An explicit copy operation duplicates a linear value.
+    #[display("Linear value {value_id:?} is copied by operation {op_index}")]
+    #[from(ignore)]
+    LinearValueCopied {
+        /// The copied value.
+        value_id: jeff::reader::value::ValueId,
+        /// The position of the copy operation, counting from the start of
+        /// its function.
+        op_index: usize,
+    },
+    /// A constant gate angle is infinite, or is NaN with
+    /// [`NanAngles::Error`].
+    #[display("Gate angle {radians} of operation {op_index} is not finite")]
+    #[from(ignore)]
+    NonFiniteAngle {
+        /// The angle, in radians.
+        radians: f64,
+        /// The position of the gate, counting from the start of its
+        /// function.
+        op_index: usize,
+    },
     /// The _jeff_ operation is not supported.
-    #[display("Unsupported operation: {}", op_name)]
+    #[display("Unsupported operation: {op_name}{hint}", hint = diagnostics::hint(did_you_mean))]
     UnsupportedOperation {
         /// The operation name.
         op_name: String,
+        /// Known operations with a similar name, as `'name' (kind)` strings.
+        did_you_mean: Vec<String>,
+    },
+    /// The translation failed, and its state could not be written to the
+    /// [`JeffToHugrConfig::debug_snapshots`] directory.
+    #[display(
+        "{error} (the debug snapshot could not be written to {dir}: {source})",
+        dir = dir.display(),
+    )]
+    #[from(ignore)]
+    SnapshotFailed {
+        /// The translation error.
+        error: Box<JeffToHugrError>,
+        /// The snapshot directory.
+        dir: PathBuf,
+        /// The error writing the snapshot.
+        source: std::io::Error,
     },
 }
 
+/// Restores a HUGR module when a translation into it fails or panics.
+///
+/// The nodes added to the module root are removed, and its entrypoint and
+/// [`metadata::BIT_ORDER`] restored, unless the translation is committed.
+/// A failed [`jeff_into_hugr_module`] thus leaves the module unchanged.
+struct ModuleRollback<'a> {
+    hugr: &'a mut Hugr,
+    /// The children of the module root before the translation.
+    children: BTreeSet<Node>,
+    entrypoint: Node,
+    bit_order: Option<serde_json::Value>,
+    /// Whether the translation succeeded.
+    committed: bool,
+}
+
+impl<'a> ModuleRollback<'a> {
+    fn new(hugr: &'a mut Hugr) -> Self {
+        let module_root = hugr.module_root();
+        Self {
+            children: hugr.children(module_root).collect(),
+            entrypoint: hugr.entrypoint(),
+            bit_order: hugr.get_metadata(module_root, metadata::BIT_ORDER).cloned(),
+            committed: false,
+            hugr,
+        }
+    }
+}
+
+impl Drop for ModuleRollback<'_> {
+    fn drop(&mut self) {
+        if self.committed {
+            return;
+        }
+        let module_root = self.hugr.module_root();
+        let added = self
+            .hugr
+            .children(module_root)
+            .filter(|node| !self.children.contains(node))
+            .collect_vec();
+        for node in added {
+            self.hugr.remove_subtree(node);
+        }
+        self.hugr.set_entrypoint(self.entrypoint);
+        match self.bit_order.take() {
+            Some(bit_order) => self.hugr.set_metadata(module_root, metadata::BIT_ORDER, bit_order),
+            None => {
+                self.hugr.remove_metadata(module_root, metadata::BIT_ORDER);
+            }
+        }
+    }
+}
+
+/// Maximum number of value ports in each direction of a HUGR node.
+///
+/// Port offsets are stored as `u16`, with room left for a static and an
+/// order port.
+pub(crate) const MAX_VALUE_PORTS: usize = u16::MAX as usize - 2;
+
+/// Check that `count` inputs or outputs fit in the value ports of a HUGR node.
+///
+/// # Errors
+///
+/// - [`JeffToHugrError::TooManyPorts`] if `count` exceeds [`MAX_VALUE_PORTS`].
+pub(crate) fn check_port_count(count: usize) -> Result<(), JeffToHugrError> {
+    match count > MAX_VALUE_PORTS {
+        true => Err(JeffToHugrError::TooManyPorts { count }),
+        false => Ok(()),
+    }
+}
+
+/// The HUGR signature of a _jeff_ function.
+fn function_signature(
+    func: &jeff::reader::Function<'_>,
+) -> Result<hugr::types::Signature, JeffToHugrError> {
+    let inputs = func
+        .input_types()
+        .map(|port| Ok(port?.ty()))
+        .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+    let outputs = func
+        .output_types()
+        .map(|port| Ok(port?.ty()))
+        .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+    check_port_count(inputs.len().max(outputs.len()))?;
+    Ok(jeff_signature_to_hugr(inputs, outputs))
+}
+
+/// The definitions of the functions also declared in a module, indexed by
+/// the id of the redundant declaration.
+///
+/// A declaration and a definition are the same function when they have the
+/// same _jeff_ name. Declarations without a definition are kept.
+///
+/// # Errors
+///
+/// - [`JeffToHugrError::ConflictingFunction`] if a declaration and the
+///   definition with its name have different signatures.
+fn redeclared_functions(
+    module: &jeff::reader::Module<'_>,
+) -> Result<BTreeMap<jeff::reader::FunctionId, jeff::reader::FunctionId>, JeffToHugrError> {
+    let mut definitions = BTreeMap::new();
+    for (func_id, func) in module.functions().enumerate() {
+        if let jeff::reader::Function::Definition(_) = func {
+            definitions.entry(func.name().to_string()).or_insert((
+                func_id as jeff::reader::FunctionId,
+                function_signature(&func)?,
+            ));
+        }
+    }
+    let mut redeclared = BTreeMap::new();
+    for (func_id, func) in module.functions().enumerate() {
+        let jeff::reader::Function::Declaration(_) = func else {
+            continue;
+        };
+        let Some((definition, signature)) = definitions.get(func.name()) else {
+            continue;
+        };
+        if function_signature(&func)? != *signature {
+            return Err(JeffToHugrError::ConflictingFunction {
+                name: func.name().to_string(),
+            });
+        }
+        redeclared.insert(func_id as jeff::reader::FunctionId, *definition);
+    }
+    Ok(redeclared)
+}
+
+/// Count the uses of each value in a _jeff_ region, by its operations and
+/// its targets.
+///
+/// The values used by the operations of nested regions are not counted, as
+/// they are passed to them as explicit inputs.
+fn region_value_uses(
+    region: &jeff::reader::Region<'_>,
+) -> Result<BTreeMap<jeff::reader::value::ValueId, usize>, JeffToHugrError> {
+    let mut uses = BTreeMap::new();
+    for op in region.operations() {
+        for value in op.inputs() {
+            *uses.entry(value?.id()).or_default() += 1;
+        }
+    }
+    for value in region.targets() {
+        *uses.entry(value?.id()).or_default() += 1;
+    }
+    Ok(uses)
+}
+
 impl JeffToHugrError {
     /// New [`JeffToHugrError::UnsupportedOperation`] error.
     pub fn unsupported_op(op: &impl std::fmt::Debug) -> Self {
+        let op_name = format!("{op:?}");
         Self::UnsupportedOperation {
-            op_name: format!("{op:?}"),
+            did_you_mean: diagnostics::did_you_mean(&op_name),
+            op_name,
         }
     }
 
@@ -103,9 +900,18 @@ impl From<hugr::builder::BuildError> for JeffToHugrError {
     }
 }
 
-/// Internal context used while building a HUGR program.
+impl From<hugr::algorithms::replace_types::ReplaceTypesError> for JeffToHugrError {
+    fn from(err: hugr::algorithms::replace_types::ReplaceTypesError) -> Self {
+        Self::BitArrayConversion(Box::new(err))
+    }
+}
+
+/// Context used while building a HUGR program.
+///
+/// Passed to the [`ConversionHook`](hooks::ConversionHook)s to register the values of the
+/// translated operations.
 #[derive(Debug, Default, Clone)]
-pub(crate) struct BuildContext {
+pub struct BuildContext {
     /// Map from _jeff_ (hyperedge) values to incoming node ports.
     ///
     /// This is used to defer the HUGR node connection until all nodes are created.
@@ -118,6 +924,15 @@ pub(crate) struct BuildContext {
     ///
     /// This is used to elide swap operations or other no-op ops.
     merged_values: BTreeMap<jeff::reader::value::ValueId, jeff::reader::value::ValueId>,
+    /// Views of qubit registers created and not released yet in the region.
+    ///
+    /// This is used to translate the register aliasing intrinsics with move semantics.
+    register_views: RegisterViews<jeff::reader::value::ValueId>,
+    /// Incoming node ports left without a source after connecting the
+    /// hyperedges of a region, by _jeff_ value.
+    ///
+    /// Only used to report translation failures, see [`JeffToHugrConfig::debug_snapshots`].
+    unconnected_inputs: BTreeMap<jeff::reader::value::ValueId, Vec<(Node, IncomingPort)>>,
     /// Map from function IDs to HUGR call node inputs ports.
     ///
     /// This is used to defer the HUGR node connection until all functions have been defined.
@@ -126,6 +941,91 @@ pub(crate) struct BuildContext {
     ///
     /// This is used to re-use the same function node on multiple calls.
     utility_functions: BTreeMap<String, handle::FuncID<true>>,
+    /// The translation configuration.
+    config: Arc<JeffToHugrConfig>,
+    /// The _jeff_ id of the function being translated.
+    current_function: Option<jeff::reader::FunctionId>,
+    /// The HUGR name of the function being translated.
+    current_function_name: Option<String>,
+    /// Index of the _jeff_ operation being translated, counting from the start of the function.
+    current_op: Option<usize>,
+    /// Number of _jeff_ operations visited so far in the current function.
+    op_count: usize,
+    /// Number of functions in the _jeff_ module, for the progress reports.
+    function_count: usize,
+    /// Originating _jeff_ operation of the translated nodes.
+    ///
+    /// Only populated when [`JeffToHugrConfig::record_provenance`] is set.
+    node_origins: BTreeMap<Node, (jeff::reader::FunctionId, usize)>,
+    /// Map from constant arrays to the `LoadConstant` node ports that load them.
+    ///
+    /// This is used to emit a single module-level constant for identical arrays.
+    shared_constants: BTreeMap<ConstIntReg, Vec<(Node, IncomingPort)>>,
+    /// Values of the _jeff_ float constants defined in the current region.
+    ///
+    /// This is used to recognize exact gate angles. Value ids are local to
+    /// each function, so the map is cleared when a region is built.
+    float_constants: BTreeMap<jeff::reader::value::ValueId, f64>,
+    /// Elements of the _jeff_ float arrays built from constants in the
+    /// current region.
+    ///
+    /// This is used to unpack constant gate parameter arrays. The map is
+    /// cleared when a region is built, like [`Self::float_constants`].
+    float_array_constants: BTreeMap<jeff::reader::value::ValueId, Vec<f64>>,
+    /// Declared _jeff_ functions that are translated as extension operations,
+    /// such as runtime queries.
+    ///
+    /// Calls to these functions are replaced by the corresponding [`JeffOp`].
+    intrinsics: Arc<BTreeMap<jeff::reader::FunctionId, JeffOp>>,
+    /// Declared _jeff_ functions creating and releasing register views.
+    view_intrinsics: Arc<BTreeMap<jeff::reader::FunctionId, ViewIntrinsic>>,
+    /// Map from the selected value types to the static input ports of the select calls.
+    ///
+    /// This is used to define a single select function per type once the module is built.
+    select_calls: BTreeMap<String, (HugrType, Vec<(Node, IncomingPort)>)>,
+    /// Replacement of the float array parameters of the function about to be built.
+    ///
+    /// Taken by the next call to [`BuildContext::build_region`].
+    angle_params: Option<AngleParams>,
+    /// Translated bodies of the functions inlined at their calls.
+    ///
+    /// Only populated when [`JeffToHugrConfig::inline_calls`] is set.
+    inline_bodies: Arc<BTreeMap<jeff::reader::FunctionId, Arc<inline::InlinedBody>>>,
+    /// The last quantum operation node in the current region.
+    ///
+    /// Global phases are ordered after it.
+    last_quantum_op: Option<Node>,
+    /// Global phase and classical side effect nodes emitted since the last
+    /// quantum operation in the current region.
+    ///
+    /// They are ordered before the next quantum operation.
+    pending_phases: Vec<Node>,
+    /// Index of the first operation of the straight-line quantum segment
+    /// being translated in the current region.
+    ///
+    /// Only tracked when [`JeffToHugrConfig::circuit_boxes`] is set.
+    segment_start: Option<usize>,
+    /// Approximations made by the translation so far.
+    warnings: Vec<ConversionWarning>,
+    /// Constants loaded in the current region, with their `LoadConstant` node.
+    ///
+    /// This is used to sink the constants passed to control-flow operations
+    /// into their regions, see [`BuildContext::place_boundary_constants`].
+    constants: BTreeMap<jeff::reader::value::ValueId, (Value, Node)>,
+    /// Number of uses of each _jeff_ value in the current region, by its
+    /// operations and targets.
+    value_uses: BTreeMap<jeff::reader::value::ValueId, usize>,
+    /// Constants loaded inside the region about to be built instead of being
+    /// read from its input node, by source position.
+    ///
+    /// Taken by the next call to [`BuildContext::build_region`].
+    sunk_sources: BTreeMap<usize, Value>,
+    /// Placement choices made by the translation so far.
+    stats: ConversionStats,
+    /// Recorder of the translation steps.
+    ///
+    /// Only set when [`JeffToHugrConfig::record_trace`] is set.
+    trace: Option<TraceRecorder>,
 }
 
 impl BuildContext {
@@ -137,6 +1037,16 @@ impl BuildContext {
         port: IncomingPort,
     ) {
         let value_id = self.earliest_id(value_id);
+        if let Some(op_index) = self.current_op {
+            self.register_views.record_use(value_id, op_index);
+        }
+        if let Some(trace) = &mut self.trace {
+            trace.record(TraceEvent::ValueRegistered {
+                value: value_id,
+                node,
+                port: port.into(),
+            });
+        }
         self.input_edges
             .entry(value_id)
             .or_default()
@@ -151,14 +1061,37 @@ impl BuildContext {
         port: OutgoingPort,
     ) {
         let value_id = self.earliest_id(value_id);
+        if let Some(trace) = &mut self.trace {
+            trace.record(TraceEvent::ValueRegistered {
+                value: value_id,
+                node,
+                port: port.into(),
+            });
+        }
         self.output_edges
             .entry(value_id)
             .or_default()
             .push((node, port));
     }
 
+    /// Register the inputs and outputs of a _jeff_ operation to the value
+    /// ports of a node, in order.
+    pub(crate) fn register_ports(
+        &mut self,
+        node: Node,
+        jeff_op: &jeff::reader::Operation<'_>,
+    ) -> Result<(), JeffToHugrError> {
+        for (index, value) in jeff_op.inputs().enumerate() {
+            self.register_input(value?.id(), node, IncomingPort::from(index));
+        }
+        for (index, value) in jeff_op.outputs().enumerate() {
+            self.register_output(value?.id(), node, OutgoingPort::from(index));
+        }
+        Ok(())
+    }
+
     /// Register an input port to a function call id.
-    pub fn register_function_call(
+    pub(crate) fn register_function_call(
         &mut self,
         function_id: jeff::reader::FunctionId,
         node: Node,
@@ -170,6 +1103,15 @@ impl BuildContext {
             .push((node, port));
     }
 
+    /// Register the static input port of a call to the select function for `ty`.
+    pub(crate) fn register_select_call(&mut self, ty: HugrType, node: Node, port: IncomingPort) {
+        self.select_calls
+            .entry(ty.to_string())
+            .or_insert_with(|| (ty, Vec::new()))
+            .1
+            .push((node, port));
+    }
+
     /// Signal that a value should be merged into another appearing earlier in the region.
     ///
     /// This is used to elide no-op operations.
@@ -179,6 +1121,12 @@ impl BuildContext {
         earlier_id: jeff::reader::value::ValueId,
     ) {
         self.merged_values.insert(value_id, earlier_id);
+        if let Some(trace) = &mut self.trace {
+            trace.record(TraceEvent::ValuesMerged {
+                value: value_id,
+                earlier: earlier_id,
+            });
+        }
         if let Some(edges) = self.input_edges.remove(&value_id) {
             self.input_edges
                 .entry(earlier_id)
@@ -204,105 +1152,960 @@ impl BuildContext {
         value_id
     }
 
-    /// Build the HUGR program by traversing the _jeff_.
-    fn build_module(module: jeff::reader::Module<'_>) -> Result<Hugr, JeffToHugrError> {
-        let mut builder = ModuleBuilder::new();
-        let mut ctx = BuildContext::default();
+    /// Returns the translation configuration.
+    pub fn config(&self) -> &JeffToHugrConfig {
+        &self.config
+    }
 
-        // A map between _jeff_ (sequential) function IDs and HUGR function nodes.
-        let mut function_nodes: Vec<Node> = vec![];
+    /// Returns the operation replacing calls to a function, if it is a
+    /// recognized intrinsic.
+    pub(crate) fn intrinsic(&self, function_id: jeff::reader::FunctionId) -> Option<&JeffOp> {
+        self.intrinsics.get(&function_id)
+    }
 
-        for func in module.functions() {
-            let name = func.name();
-            let fn_inputs = func
-                .input_types()
-                .map(|port| Ok(port?.ty()))
-                .collect::<Result<Vec<_>, JeffToHugrError>>()?;
-            let fn_outputs = func
-                .output_types()
-                .map(|port| Ok(port?.ty()))
-                .collect::<Result<Vec<_>, JeffToHugrError>>()?;
-            let signature = jeff_signature_to_hugr(fn_inputs, fn_outputs);
+    /// Returns the register view intrinsic implemented by a function, if any.
+    pub(crate) fn view_intrinsic(
+        &self,
+        function_id: jeff::reader::FunctionId,
+    ) -> Option<ViewIntrinsic> {
+        self.view_intrinsics.get(&function_id).copied()
+    }
 
-            match func {
-                jeff::reader::Function::Definition(def) => {
-                    let body = def.body();
-                    let mut fn_builder = builder.define_function(name, signature)?;
+    /// Returns the HUGR name of the function being translated, if any.
+    pub fn current_function_name(&self) -> Option<&str> {
+        self.current_function_name.as_deref()
+    }
 
-                    ctx.build_region(body, &mut fn_builder)?;
+    /// Returns the index of the _jeff_ operation being translated, counting
+    /// from the start of its function.
+    pub(crate) fn current_op(&self) -> Option<usize> {
+        self.current_op
+    }
 
-                    let fn_node = fn_builder.finish_sub_container()?.node();
-                    function_nodes.push(fn_node);
-                }
-                jeff::reader::Function::Declaration(_) => {
-                    let fn_decl = builder.declare(name, signature.into())?;
-                    function_nodes.push(fn_decl.node());
-                }
-            }
+    /// Record an approximation made by the translation, reported in
+    /// [`ConvertedHugr::warnings`].
+    pub(crate) fn warn(&mut self, warning: ConversionWarning) {
+        self.warnings.push(warning);
+    }
+
+    /// Sanitize a gate or function name read from the _jeff_ program, see
+    /// [`strings::sanitize_string`].
+    ///
+    /// Reports a [`ConversionWarning::InvalidUtf8String`] or a
+    /// [`ConversionWarning::TruncatedString`] for the modified strings.
+    ///
+    /// # Errors
+    ///
+    /// - [`JeffToHugrError::NulInString`] if the string contains a NUL byte.
+    pub(crate) fn sanitize_string(&mut self, bytes: &[u8]) -> Result<String, JeffToHugrError> {
+        let max_len = self.config.limits.max_string_len;
+        let Some(sanitized) = strings::sanitize_string(bytes, max_len) else {
+            return Err(JeffToHugrError::NulInString {
+                string: String::from_utf8_lossy(bytes).into_owned(),
+                op_index: self.current_op,
+            });
+        };
+        if sanitized.invalid_utf8 {
+            self.warn(ConversionWarning::InvalidUtf8String {
+                function: self.current_function_name.clone(),
+                op_index: self.current_op,
+                sanitized: sanitized.value.clone(),
+            });
+        }
+        if let Some(length) = sanitized.truncated_from {
+            self.warn(ConversionWarning::TruncatedString {
+                function: self.current_function_name.clone(),
+                op_index: self.current_op,
+                length,
+            });
         }
+        Ok(sanitized.value)
+    }
 
-        // Connect the function calls.
-        for (func_id, inputs) in ctx.function_calls {
-            let fn_node = function_nodes[func_id as usize];
-            for (node, port) in inputs {
-                builder
-                    .hugr_mut()
-                    .connect(fn_node, OutgoingPort::from(0), node, port);
-            }
+    /// Take the approximations recorded so far.
+    pub(crate) fn take_warnings(&mut self) -> Vec<ConversionWarning> {
+        mem::take(&mut self.warnings)
+    }
+
+    /// Create a fresh context for translating a nested region.
+    ///
+    /// The new context shares the configuration and the function-level
+    /// state with `self`, but keeps its own map of value edges.
+    ///
+    /// Call [`BuildContext::sync_nested`] after building the region, and
+    /// [`BuildContext::absorb_nested`] once the nested nodes have been
+    /// inserted into the final HUGR.
+    pub(crate) fn nested(&self) -> BuildContext {
+        BuildContext {
+            config: self.config.clone(),
+            current_function: self.current_function,
+            current_function_name: self.current_function_name.clone(),
+            op_count: self.op_count,
+            function_count: self.function_count,
+            intrinsics: self.intrinsics.clone(),
+            view_intrinsics: self.view_intrinsics.clone(),
+            inline_bodies: self.inline_bodies.clone(),
+            trace: self.trace.as_ref().map(TraceRecorder::nested),
+            ..Default::default()
         }
+    }
 
-        let hugr = builder.hugr().clone();
-        if let Err(e) = builder.finish_hugr() {
-            eprintln!("Failed to build HUGR program: {e}");
-        };
-        Ok(hugr)
+    /// Load constants inside the next region built with this context, instead
+    /// of reading them from its input node, by source position.
+    ///
+    /// See [`BuildContext::place_boundary_constants`].
+    pub(crate) fn set_sunk_sources(&mut self, sunk: BTreeMap<usize, Value>) {
+        self.sunk_sources = sunk;
     }
 
-    /// Build a HUGR dataflow graph from a _jeff_ region.
-    pub fn build_region(
+    /// Update the operation counter after a nested region has been built.
+    pub(crate) fn sync_nested(&mut self, nested: &BuildContext) {
+        self.op_count = nested.op_count;
+    }
+
+    /// Merge the function-level state of nested contexts into `self`.
+    ///
+    /// The `node_map` translates the nodes of the HUGR the nested regions
+    /// were built in to the nodes in the final HUGR.
+    pub(crate) fn absorb_nested(
         &mut self,
-        region: jeff::reader::Region<'_>,
-        builder: &mut impl hugr::builder::Dataflow,
-    ) -> Result<(), JeffToHugrError> {
-        // Each function keeps a separate list of values, while sharing the function table from the module.
-        self.input_edges.clear();
-        self.output_edges.clear();
+        nested: impl IntoIterator<Item = BuildContext>,
+        node_map: &HashMap<Node, Node>,
+    ) {
+        for ctx in nested {
+            for (func_id, calls) in ctx.function_calls {
+                self.function_calls
+                    .entry(func_id)
+                    .or_default()
+                    .extend(calls.into_iter().map(|(n, p)| (node_map[&n], p)));
+            }
+            self.node_origins.extend(
+                ctx.node_origins
+                    .into_iter()
+                    .map(|(n, origin)| (node_map[&n], origin)),
+            );
+            for (name, (ty, calls)) in ctx.select_calls {
+                self.select_calls
+                    .entry(name)
+                    .or_insert_with(|| (ty, Vec::new()))
+                    .1
+                    .extend(calls.into_iter().map(|(n, p)| (node_map[&n], p)));
+            }
+            for (value, loads) in ctx.shared_constants {
+                self.shared_constants
+                    .entry(value)
+                    .or_default()
+                    .extend(loads.into_iter().map(|(n, p)| (node_map[&n], p)));
+            }
+            self.warnings.extend(ctx.warnings);
+            self.stats.sunk_constants += ctx.stats.sunk_constants;
+            self.stats.threaded_constants += ctx.stats.threaded_constants;
+            for (value_id, inputs) in ctx.unconnected_inputs {
+                self.unconnected_inputs
+                    .entry(value_id)
+                    .or_default()
+                    .extend(inputs.into_iter().map(|(n, p)| (node_map[&n], p)));
+            }
+            if let (Some(trace), Some(nested)) = (&mut self.trace, ctx.trace) {
+                trace.absorb(nested, node_map);
+            }
+        }
+    }
 
-        // Start by adding the input and output connections to the maps.
-        let [in_node, out_node] = builder.io();
-        for (output_port, value) in region.sources().enumerate() {
-            let value = value?;
-            let hugr_port = OutgoingPort::from(output_port);
-            self.register_output(value.id(), in_node, hugr_port);
+    /// Record the current _jeff_ operation as the origin of a HUGR node.
+    ///
+    /// Does nothing unless [`JeffToHugrConfig::record_provenance`] is set.
+    pub fn record_origin(&mut self, node: Node) {
+        if !self.config.record_provenance {
+            return;
         }
-        for (input_port, value) in region.targets().enumerate() {
-            let value = value?;
-            let hugr_port = IncomingPort::from(input_port);
-            self.register_input(value.id(), out_node, hugr_port);
+        if let (Some(func), Some(op)) = (self.current_function, self.current_op) {
+            self.node_origins.insert(node, (func, op));
         }
+    }
 
-        // Add all the nodes to the dataflow region,
-        // and register the ports that will need to be connected later.
-        for op in region.operations() {
-            op.op_type().build_hugr_op(&op, builder, self)?;
+    /// Build the HUGR program by traversing the _jeff_.
+    fn build_module(
+        module: jeff::reader::Module<'_>,
+        config: &JeffToHugrConfig,
+    ) -> Result<ConvertedHugr, JeffToHugrError> {
+        let mut hugr = Hugr::default();
+        let mut ctx = BuildContext::with_config(config);
+        let map = ctx.build_into_module(module, &mut hugr, None, &BTreeMap::new())?;
+        ctx.check_module(&hugr)?;
+        if let Some(target) = &config.target {
+            target.store(&mut hugr);
+            ctx.check_target_qubits(&hugr, map.function_nodes(), target);
         }
-
-        // Add all the missing edges.
-        self.connect_hyperedges(builder)?;
-
-        Ok(())
+        if config.check_extensions
+            && let Some((ext, node)) = disallowed_extension(&hugr)
+        {
+            return Err(JeffToHugrError::DisallowedExtension { ext, node });
+        }
+        if let Some(gate_set) = &config.gate_set {
+            let violations = check_gate_set(&hugr, gate_set);
+            if !violations.is_empty() {
+                return Err(JeffToHugrError::GateSetViolations { violations });
+            }
+        }
+        Provenance::new(config).store(&mut hugr);
+        let trace = ctx.trace.take().map(|trace| trace.finish(&hugr));
+        Ok(ConvertedHugr {
+            hugr,
+            map,
+            warnings: ctx.take_warnings(),
+            stats: ctx.stats,
+            trace,
+        })
     }
 
-    /// Connect all the hyperedges between inputs and outputs with the same value id.
-    ///
-    /// See [`BuildContext::register_input`] and [`BuildContext::register_output`] for more details.
-    fn connect_hyperedges(
+    /// Verify the `FreeZero` operations of a HUGR, and repair the unverified
+    /// ones, see [`JeffToHugrConfig::free_zero_repair`].
+    fn repair_free_zero(
         &mut self,
-        builder: &mut impl hugr::builder::Dataflow,
+        hugr: &mut Hugr,
+        map: &JeffHugrMap,
+        repair: FreeZeroRepair,
     ) -> Result<(), JeffToHugrError> {
-        let output_edges = mem::take(&mut self.output_edges);
-        for (value_id, outputs) in output_edges {
-            let Some(inputs) = self.input_edges.get(&value_id) else {
+        let frees = check_free_zero(hugr);
+        match repair {
+            FreeZeroRepair::Error if !frees.is_empty() => {
+                return Err(JeffToHugrError::UnverifiedFreeZero { frees });
+            }
+            FreeZeroRepair::Downgrade => {
+                for free in frees {
+                    self.warn(ConversionWarning::FreeZeroDowngraded {
+                        function: free.function,
+                        op_index: map.node_origin(free.free).map(|(_, op_index)| op_index),
+                    });
+                }
+            }
+            _ => {}
+        }
+        crate::passes::repair_free_zero(hugr, repair);
+        Ok(())
+    }
+
+    /// Warn about the function definitions that may use more qubits than the
+    /// target device has.
+    fn check_target_qubits(&mut self, hugr: &Hugr, functions: &[Node], target: &TargetInfo) {
+        for &func in functions {
+            let OpType::FuncDefn(defn) = hugr.get_optype(func) else {
+                continue;
+            };
+            let qubits = peak_qubits_bound(hugr, func);
+            if qubits > target.num_qubits {
+                self.warn(ConversionWarning::TargetQubitsExceeded {
+                    function: defn.func_name().to_string(),
+                    qubits,
+                    target: target.name.clone(),
+                    available: target.num_qubits,
+                });
+            }
+        }
+    }
+
+    /// Create an empty context for a translation with the given configuration.
+    pub(crate) fn with_config(config: &JeffToHugrConfig) -> Self {
+        BuildContext {
+            config: Arc::new(config.clone()),
+            trace: config.record_trace.then(TraceRecorder::default),
+            ..Default::default()
+        }
+    }
+
+    /// Validate a translated HUGR module, unless [`JeffToHugrConfig::validate`]
+    /// is unset.
+    ///
+    /// A snapshot of an invalid module is written if
+    /// [`JeffToHugrConfig::debug_snapshots`] is set.
+    ///
+    /// # Errors
+    ///
+    /// - [`JeffToHugrError::InvalidHugrProgram`] if the module is invalid.
+    /// - [`JeffToHugrError::Cancelled`] if the progress hook cancels the
+    ///   translation.
+    fn check_module(&self, hugr: &Hugr) -> Result<(), JeffToHugrError> {
+        if !self.config.validate {
+            return Ok(());
+        }
+        self.report(|| Progress::Validating {
+            nodes: hugr.num_nodes(),
+        })?;
+        hugr.validate().map_err(|e| self.snapshot(hugr, e.into()))
+    }
+
+    /// Report the progress of the translation to the [`JeffToHugrConfig::progress`] hook.
+    ///
+    /// # Errors
+    ///
+    /// - [`JeffToHugrError::Cancelled`] if the hook cancels the translation.
+    fn report(&self, progress: impl FnOnce() -> Progress) -> Result<(), JeffToHugrError> {
+        match &self.config.progress {
+            Some(hook) => hook.report(progress()),
+            None => Ok(()),
+        }
+    }
+
+    /// Write the translation state to the [`JeffToHugrConfig::debug_snapshots`]
+    /// directory, if set.
+    ///
+    /// All the translation failures are reported through this method. Only the
+    /// edges of the region being translated are recorded, errors in a nested
+    /// region capture the state of its enclosing function.
+    ///
+    /// Returns the translation error, wrapped in a
+    /// [`JeffToHugrError::SnapshotFailed`] if the snapshot cannot be written.
+    fn snapshot(&self, hugr: &Hugr, error: JeffToHugrError) -> JeffToHugrError {
+        let Some(dir) = &self.config.debug_snapshots else {
+            return error;
+        };
+        match snapshot::write_snapshot(dir, hugr, self, &error) {
+            Ok(()) => error,
+            Err(source) => JeffToHugrError::SnapshotFailed {
+                error: Box::new(error),
+                dir: dir.clone(),
+                source,
+            },
+        }
+    }
+
+    /// Translate the functions of a _jeff_ module, appending them to the
+    /// module root of an existing HUGR.
+    ///
+    /// The function definitions in `reuse` are inserted from their cached
+    /// translation instead of being translated again.
+    ///
+    /// See [`jeff_into_hugr_module`].
+    fn build_into_module(
+        &mut self,
+        module: jeff::reader::Module<'_>,
+        hugr: &mut Hugr,
+        prefix: Option<&str>,
+        reuse: &BTreeMap<jeff::reader::FunctionId, Hugr>,
+    ) -> Result<JeffHugrMap, JeffToHugrError> {
+        let mut rollback = ModuleRollback::new(hugr);
+        let map = self
+            .translate_module(module, &mut *rollback.hugr, prefix, reuse)
+            .map_err(|e| self.snapshot(&*rollback.hugr, e))?;
+        rollback.committed = true;
+        Ok(map)
+    }
+
+    /// Translate the functions of a _jeff_ module.
+    ///
+    /// See [`BuildContext::build_into_module`].
+    fn translate_module(
+        &mut self,
+        module: jeff::reader::Module<'_>,
+        hugr: &mut Hugr,
+        prefix: Option<&str>,
+        reuse: &BTreeMap<jeff::reader::FunctionId, Hugr>,
+    ) -> Result<JeffHugrMap, JeffToHugrError> {
+        let config = Arc::clone(&self.config);
+        self.function_count = module.functions().count();
+        config
+            .limits
+            .check(ResourceLimit::Functions, self.function_count)?;
+        if config.typecheck {
+            let errors = module_type_errors(&module)?;
+            if !errors.is_empty() {
+                return Err(JeffToHugrError::TypeErrors { errors });
+            }
+        }
+        self.report(|| Progress::Scanning {
+            functions: self.function_count,
+        })?;
+
+        let module_root = hugr.module_root();
+        let existing_names: BTreeSet<String> = hugr
+            .children(module_root)
+            .filter_map(|n| match hugr.get_optype(n) {
+                OpType::FuncDefn(defn) => Some(defn.func_name().to_string()),
+                OpType::FuncDecl(decl) => Some(decl.func_name().to_string()),
+                _ => None,
+            })
+            .collect();
+
+        let old_entrypoint = hugr.entrypoint();
+        hugr.set_entrypoint(module_root);
+        let mut builder = ModuleBuilder::with_hugr(&mut *hugr);
+        self.intrinsics = Arc::new(Self::intrinsics(&module)?);
+        self.view_intrinsics = Arc::new(Self::view_intrinsics(&module)?);
+        let mut map = JeffHugrMap::default();
+        let redeclared = redeclared_functions(&module)?;
+        let mut called = match config.symbolize_angle_params {
+            true => angle_params::called_functions(&module)?,
+            false => BTreeSet::new(),
+        };
+        // Calls to a redundant declaration call its definition.
+        called.extend(
+            redeclared
+                .iter()
+                .filter(|(decl, _)| called.contains(decl))
+                .map(|(_, &defn)| defn)
+                .collect_vec(),
+        );
+        // The names are read outside of any function.
+        self.current_function_name = None;
+        self.current_op = None;
+        let original_names: Vec<String> = module
+            .functions()
+            .map(|func| self.sanitize_string(func.name().as_bytes()))
+            .collect::<Result<_, _>>()?;
+        let jeff_names = original_names
+            .iter()
+            .map(|name| match prefix {
+                Some(prefix) => format!("{prefix}{name}"),
+                None => name.clone(),
+            })
+            .collect_vec();
+        let names = match config.strict_names {
+            true => {
+                if let Some(name) = jeff_names.iter().find(|name| !is_valid_name(name)) {
+                    return Err(JeffToHugrError::InvalidFunctionName { name: name.clone() });
+                }
+                jeff_names.clone()
+            }
+            // Without a prefix, a clash with a function of the module is an
+            // error rather than a reason to rename.
+            false if prefix.is_none() => sanitize_names(&jeff_names, &BTreeSet::new()),
+            false => sanitize_names(&jeff_names, &existing_names),
+        };
+        let function_ids: BTreeMap<String, jeff::reader::FunctionId> = match reuse.is_empty() {
+            true => BTreeMap::new(),
+            false => names
+                .iter()
+                .enumerate()
+                .map(|(func_id, name)| (name.clone(), func_id as jeff::reader::FunctionId))
+                .collect(),
+        };
+        if config.inline_calls {
+            self.build_inline_bodies(&module, &names)?;
+        }
+        let duplicates = match config.dedup_functions {
+            FunctionDedup::Disabled => BTreeMap::new(),
+            _ if config.inline_calls => BTreeMap::new(),
+            _ => dedup::duplicate_functions(&module, &called)?,
+        };
+        let mut translated = Vec::new();
+        let mut codec_boundaries = Vec::new();
+
+        for (func_id, func) in module.functions().enumerate() {
+            let name = names[func_id].clone();
+            if existing_names.contains(&name) {
+                return Err(JeffToHugrError::FunctionNameCollision { name });
+            }
+            self.report(|| Progress::Converting {
+                function: func_id,
+                functions: self.function_count,
+                name: name.clone(),
+                operations: 0,
+            })?;
+            if redeclared.contains_key(&(func_id as jeff::reader::FunctionId)) {
+                // Replaced by the node of the definition once it is translated.
+                map.push_function(module_root);
+                continue;
+            }
+            if let Some(cached) = reuse.get(&(func_id as jeff::reader::FunctionId)) {
+                let fn_node =
+                    self.splice_function(builder.hugr_mut(), cached.clone(), &function_ids)?;
+                map.push_function(fn_node);
+                continue;
+            }
+            let mut signature = function_signature(&func)?;
+            let boundary = match config.type_codecs.is_empty() {
+                true => None,
+                false => Some(BoundaryTypes::new(&func)?),
+            };
+
+            match func {
+                jeff::reader::Function::Definition(_)
+                    if duplicates.contains_key(&(func_id as jeff::reader::FunctionId)) =>
+                {
+                    let canonical = duplicates[&(func_id as jeff::reader::FunctionId)];
+                    let canonical_node = map.function_nodes()[canonical as usize];
+                    map.set_canonical(func_id as jeff::reader::FunctionId, canonical);
+                    if config.dedup_functions == FunctionDedup::Retarget {
+                        // Calls to the function are connected to the canonical one.
+                        map.push_function(canonical_node);
+                        continue;
+                    }
+                    let fn_node = dedup::build_wrapper(&mut builder, &name, canonical_node)?;
+                    translated.push(fn_node);
+                    map.push_function(fn_node);
+                    codec_boundaries.extend(boundary.map(|boundary| (fn_node, boundary)));
+                }
+                jeff::reader::Function::Definition(def) => {
+                    let body = def.body();
+                    let angle_params = match config.symbolize_angle_params {
+                        true => AngleParams::analyse(&body)?,
+                        false => None,
+                    };
+                    let angle_params = match angle_params {
+                        // Calls would need to unpack their array argument, which is
+                        // not possible until float arrays can be constructed.
+                        Some(_) if called.contains(&(func_id as jeff::reader::FunctionId)) => {
+                            self.warn(ConversionWarning::AngleParamsNotSymbolized {
+                                function: name.clone(),
+                            });
+                            None
+                        }
+                        angle_params => angle_params,
+                    };
+                    if let Some(params) = &angle_params {
+                        signature = hugr::types::Signature::new(
+                            params.input_types.clone(),
+                            signature.output().clone(),
+                        );
+                    }
+                    let entries = angle_params.as_ref().map(|params| params.entries.clone());
+                    // The symbolized parameters replace the _jeff_ inputs.
+                    let boundary = boundary.map(|mut boundary| {
+                        if angle_params.is_some() {
+                            boundary.inputs.clear();
+                        }
+                        boundary
+                    });
+                    let mut fn_builder = builder.define_function(&name, signature)?;
+
+                    self.current_function = Some(func_id as jeff::reader::FunctionId);
+                    self.current_function_name = Some(name);
+                    self.op_count = 0;
+                    self.angle_params = angle_params;
+                    self.build_region(body, &mut fn_builder)?;
+
+                    let fn_node = fn_builder.finish_sub_container()?.node();
+                    if let Some(entries) = entries {
+                        builder.hugr_mut().set_metadata(
+                            fn_node,
+                            metadata::ANGLE_PARAMS,
+                            serde_json::json!(entries),
+                        );
+                    }
+                    translated.push(fn_node);
+                    map.push_function(fn_node);
+                    codec_boundaries.extend(boundary.map(|boundary| (fn_node, boundary)));
+                }
+                jeff::reader::Function::Declaration(_) => {
+                    let fn_decl = builder.declare(name, signature.into())?;
+                    map.push_function(fn_decl.node());
+                    codec_boundaries.extend(boundary.map(|boundary| (fn_decl.node(), boundary)));
+                }
+            }
+        }
+
+        // The redundant declarations share the node of their definition.
+        for (&decl, &defn) in &redeclared {
+            let fn_node = map.function_nodes()[defn as usize];
+            map.set_function(decl, fn_node);
+        }
+
+        // Record both names of the functions, keeping the _jeff_ name of the
+        // renamed ones.
+        for (func_id, original_name) in original_names.iter().enumerate() {
+            let name = &names[func_id];
+            map.insert_name(name, func_id as jeff::reader::FunctionId);
+            map.insert_name(original_name, func_id as jeff::reader::FunctionId);
+            // Retargeted functions and redundant declarations have no node of
+            // their own.
+            let retargeted = config.dedup_functions == FunctionDedup::Retarget
+                && duplicates.contains_key(&(func_id as jeff::reader::FunctionId));
+            let redundant = redeclared.contains_key(&(func_id as jeff::reader::FunctionId));
+            if *name != jeff_names[func_id] && !retargeted && !redundant {
+                let fn_node = map.function_nodes()[func_id];
+                builder.hugr_mut().set_metadata(
+                    fn_node,
+                    metadata::ORIGINAL_NAME,
+                    original_name.as_str(),
+                );
+            }
+        }
+
+        // Record the merged functions by name.
+        if !duplicates.is_empty() {
+            let hugr = builder.hugr_mut();
+            let mut merged = hugr
+                .get_metadata(module_root, metadata::DEDUPLICATED)
+                .and_then(serde_json::Value::as_object)
+                .cloned()
+                .unwrap_or_default();
+            for (&func_id, &canonical) in &duplicates {
+                merged.insert(
+                    names[func_id as usize].clone(),
+                    serde_json::json!(names[canonical as usize]),
+                );
+            }
+            hugr.set_metadata(module_root, metadata::DEDUPLICATED, merged);
+        }
+
+        let calls = self.function_calls.values().map(Vec::len).sum();
+        self.report(|| Progress::WiringCalls { calls })?;
+
+        // Connect the function calls.
+        for (func_id, inputs) in mem::take(&mut self.function_calls) {
+            let fn_node = map.function_nodes()[func_id as usize];
+            for (node, port) in inputs {
+                builder
+                    .hugr_mut()
+                    .connect(fn_node, OutgoingPort::from(0), node, port);
+            }
+        }
+
+        // Define a single select function for each selected type.
+        for (type_name, (ty, calls)) in mem::take(&mut self.select_calls) {
+            let name = format!("{}__jeff_select_{type_name}", prefix.unwrap_or_default());
+            let func = build_select_function(&name, ty, &mut builder)?;
+            for (node, port) in calls {
+                builder
+                    .hugr_mut()
+                    .connect(func.node(), OutgoingPort::from(0), node, port);
+            }
+        }
+
+        // Emit a single module-level constant for each distinct constant array.
+        for (value, loads) in mem::take(&mut self.shared_constants) {
+            let const_node = builder.add_constant(Value::extension(value)).node();
+            for (node, port) in loads {
+                builder
+                    .hugr_mut()
+                    .connect(const_node, OutgoingPort::from(0), node, port);
+            }
+        }
+
+        // Annotate the nodes with their originating operations.
+        for (node, (func_id, op_index)) in mem::take(&mut self.node_origins) {
+            builder.hugr_mut().set_metadata(
+                node,
+                metadata::OP_INDEX,
+                serde_json::json!([func_id, op_index]),
+            );
+            map.set_origin(node, (func_id, op_index));
+        }
+
+        // Encode the operations the hooks marked as not to be optimized.
+        let protected = translated
+            .iter()
+            .flat_map(|&fn_node| hugr.descendants(fn_node))
+            .filter(|&node| {
+                hugr.get_metadata(node, metadata::PROTECTED)
+                    .is_some_and(|marker| *marker == true)
+            })
+            .collect_vec();
+        if !protected.is_empty() {
+            crate::passes::protect_ops(hugr, &protected, config.protect);
+        }
+        bit_order::reverse_boundary_bits(hugr, config.bit_order, map.function_nodes(), &translated);
+        hugr.set_metadata(
+            module_root,
+            metadata::BIT_ORDER,
+            serde_json::json!(config.bit_order.to_string()),
+        );
+        if config.annotate_depths {
+            let depths = module_depths(&module)?;
+            for (&fn_node, report) in map.function_nodes().iter().zip(depths) {
+                let Some(report) = report else {
+                    continue;
+                };
+                hugr.set_metadata(fn_node, metadata::DEPTH, serde_json::json!(report.depth));
+                hugr.set_metadata(fn_node, metadata::DEPTH_2Q, serde_json::json!(report.depth_2q));
+            }
+        }
+        let max_qubits = config.limits.max(ResourceLimit::Qubits);
+        qureg_arrays::lower_static_registers(hugr, config.qureg_lowering, max_qubits);
+        bit_arrays::convert_bit_arrays(hugr, config.bit_arrays_as)?;
+        type_codec::apply_type_codecs(hugr, &config.type_codecs, &codec_boundaries)?;
+        handle_abi::wrap_handle_functions(hugr, &config.handle_abi, &translated)?;
+        if config.propagate_register_sizes {
+            crate::passes::propagate_register_sizes(hugr);
+        }
+        if let Some(repair) = config.free_zero_repair {
+            self.repair_free_zero(hugr, &map, repair)?;
+        }
+        if config.fuse_single_qubit_gates {
+            crate::passes::fuse_single_qubit_gates(hugr);
+        }
+        if let Some(convention) = config.result_convention {
+            crate::passes::canonicalize_results(hugr, convention);
+        }
+        if config.circuit_boxes {
+            crate::passes::box_circuit_segments(hugr);
+        }
+        if config.name_wires {
+            let wire_names = WireNames::assign(hugr, translated.iter().copied());
+            wire_names.store(hugr);
+            map.set_wire_names(wire_names);
+        }
+
+        hugr.set_entrypoint(old_entrypoint);
+        Ok(map)
+    }
+
+    /// Find the declared intrinsics translated as extension operations.
+    ///
+    /// These are the runtime queries, only recognized if they take no inputs
+    /// and return a single `int(64)` matching
+    /// [`crate::extension::signature::runtime_query_signature`], the state
+    /// preparations recognized by [`JeffOp::state_prep`], the bitcasts
+    /// recognized by [`JeffOp::bitcast`], and the qubit borrows recognized by
+    /// [`JeffOp::qubit_borrow`].
+    pub(crate) fn intrinsics(
+        module: &jeff::reader::Module<'_>,
+    ) -> Result<BTreeMap<jeff::reader::FunctionId, JeffOp>, JeffToHugrError> {
+        let mut intrinsics = BTreeMap::new();
+        for (func_id, func) in module.functions().enumerate() {
+            let jeff::reader::Function::Declaration(_) = func else {
+                continue;
+            };
+            let inputs = func
+                .input_types()
+                .map(|port| Ok(port?.ty()))
+                .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+            let outputs = func
+                .output_types()
+                .map(|port| Ok(port?.ty()))
+                .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+            let intrinsic = match JeffOp::runtime_query(func.name()) {
+                Some(query)
+                    if inputs.is_empty() && outputs == [jeff::types::Type::Int { bits: 64 }] =>
+                {
+                    Some(query)
+                }
+                Some(_) => None,
+                None => JeffOp::state_prep(func.name(), &inputs, &outputs)
+                    .or_else(|| JeffOp::bitcast(func.name(), &inputs, &outputs))
+                    .or_else(|| JeffOp::qubit_borrow(func.name(), &inputs, &outputs)),
+            };
+            if let Some(intrinsic) = intrinsic {
+                intrinsics.insert(func_id as jeff::reader::FunctionId, intrinsic);
+            }
+        }
+        Ok(intrinsics)
+    }
+
+    /// Find the declared functions recognized by [`ViewIntrinsic::from_declaration`].
+    fn view_intrinsics(
+        module: &jeff::reader::Module<'_>,
+    ) -> Result<BTreeMap<jeff::reader::FunctionId, ViewIntrinsic>, JeffToHugrError> {
+        let mut intrinsics = BTreeMap::new();
+        for (func_id, func) in module.functions().enumerate() {
+            let jeff::reader::Function::Declaration(_) = func else {
+                continue;
+            };
+            let inputs = func
+                .input_types()
+                .map(|port| Ok(port?.ty()))
+                .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+            let outputs = func
+                .output_types()
+                .map(|port| Ok(port?.ty()))
+                .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+            if let Some(intrinsic) = ViewIntrinsic::from_declaration(func.name(), &inputs, &outputs)
+            {
+                intrinsics.insert(func_id as jeff::reader::FunctionId, intrinsic);
+            }
+        }
+        Ok(intrinsics)
+    }
+
+    /// Build a HUGR dataflow graph from a _jeff_ region.
+    pub(crate) fn build_region(
+        &mut self,
+        region: jeff::reader::Region<'_>,
+        builder: &mut impl hugr::builder::Dataflow,
+    ) -> Result<(), JeffToHugrError> {
+        // Each function keeps a separate list of values, while sharing the function table from the module.
+        self.input_edges.clear();
+        self.output_edges.clear();
+        self.register_views = RegisterViews::default();
+        self.last_quantum_op = None;
+        self.pending_phases.clear();
+        self.segment_start = None;
+        self.constants.clear();
+        self.float_constants.clear();
+        self.float_array_constants.clear();
+        self.value_uses = region_value_uses(&region)?;
+        if let Some(trace) = &mut self.trace {
+            trace.checkpoint(
+                builder.hugr(),
+                TraceEvent::RegionEntered {
+                    function: self.current_function_name.clone(),
+                    container: builder.container_node(),
+                },
+            );
+        }
+
+        // Start by adding the input and output connections to the maps.
+        let linear = |ty| !crate::types::jeff_to_hugr(ty).copyable();
+        let sources = region
+            .sources()
+            .map(|value| {
+                let value = value?;
+                Ok((value.id(), linear(value.ty())))
+            })
+            .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+        let targets = region
+            .targets()
+            .map(|value| {
+                let value = value?;
+                Ok((value.id(), linear(value.ty())))
+            })
+            .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+        let angle_params = self.angle_params.take();
+        let sunk_sources = mem::take(&mut self.sunk_sources);
+        let (sunk, sources): (Vec<_>, Vec<_>) = sources
+            .into_iter()
+            .enumerate()
+            .partition(|(position, _)| sunk_sources.contains_key(position));
+        let sources = sources.into_iter().map(|(_, source)| source).collect_vec();
+        self.register_boundary(&sources, &targets, builder.io(), angle_params.as_ref())?;
+
+        // Load the sunk constants read by the region, once each.
+        for (position, (value_id, _)) in sunk {
+            if self.value_uses.contains_key(&value_id) && !self.output_edges.contains_key(&value_id)
+            {
+                self.load_constant(sunk_sources[&position].clone(), value_id, builder);
+            }
+        }
+
+        // Unused classical operations after the region outputs are produced.
+        let dead_ops = liveness::dead_trailing_ops(&region)?;
+
+        // Add all the nodes to the dataflow region,
+        // and register the ports that will need to be connected later.
+        for (position, op) in region.operations().enumerate() {
+            self.current_op = Some(self.op_count);
+            self.op_count += 1;
+            let interval = self.config.progress.as_ref().map(ProgressHook::interval);
+            if interval.is_some_and(|interval| self.op_count % interval == 0) {
+                self.report(|| Progress::Converting {
+                    function: self.current_function.unwrap_or_default() as usize,
+                    functions: self.function_count,
+                    name: self.current_function_name.clone().unwrap_or_default(),
+                    operations: self.op_count,
+                })?;
+            }
+            if dead_ops.contains(&position) {
+                continue;
+            }
+            // Array reads replaced by symbolic angle parameters.
+            if angle_params
+                .as_ref()
+                .is_some_and(|params| params.elided_ops.contains(&position))
+            {
+                continue;
+            }
+            // Nodes added from here on translate the operation.
+            let first = builder.hugr().children(builder.container_node()).count();
+            op.op_type().build_hugr_op(&op, builder, self)?;
+            let container = builder.container_node();
+            let nodes = builder.hugr().children(container).skip(first).collect_vec();
+            if self.config.circuit_boxes {
+                self.mark_segment(&op, &nodes, builder.hugr_mut());
+            }
+            if let (Some(trace), Some(op_index)) = (&mut self.trace, self.current_op) {
+                trace.checkpoint(
+                    builder.hugr(),
+                    TraceEvent::OpConverted {
+                        function: self.current_function_name.clone(),
+                        op_index,
+                        nodes,
+                    },
+                );
+            }
+            self.config
+                .limits
+                .check(ResourceLimit::Nodes, builder.hugr().num_nodes())?;
+        }
+        self.current_op = None;
+        self.register_views.finish()?;
+
+        // Add all the missing edges.
+        self.connect_hyperedges(builder)?;
+        if let Some(trace) = &mut self.trace {
+            trace.checkpoint(
+                builder.hugr(),
+                TraceEvent::RegionExited {
+                    container: builder.container_node(),
+                },
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Register the sources and targets of a region, given with whether
+    /// their values are linear, to the ports of its input and output nodes.
+    ///
+    /// A copyable value listed several times among the sources is read from
+    /// its first port, and one listed several times among the targets is
+    /// wired to each of its ports.
+    ///
+    /// # Errors
+    ///
+    /// - [`JeffToHugrError::DuplicateLinearBoundaryValue`] if a linear value
+    ///   is listed several times among the sources or among the targets.
+    fn register_boundary(
+        &mut self,
+        sources: &[(jeff::reader::value::ValueId, bool)],
+        targets: &[(jeff::reader::value::ValueId, bool)],
+        [in_node, out_node]: [Node; 2],
+        angle_params: Option<&AngleParams>,
+    ) -> Result<(), JeffToHugrError> {
+        for values in [sources, targets] {
+            let mut linear = BTreeSet::new();
+            let repeated = values
+                .iter()
+                .filter(|&&(_, is_linear)| is_linear)
+                .find(|&&(value_id, _)| !linear.insert(value_id));
+            if let Some(&(value_id, _)) = repeated {
+                return Err(JeffToHugrError::DuplicateLinearBoundaryValue { value_id });
+            }
+        }
+
+        let mut registered = BTreeSet::new();
+        for (output_port, &(value_id, _)) in sources.iter().enumerate() {
+            if !registered.insert(value_id) {
+                continue;
+            }
+            let output_port = match angle_params {
+                Some(params) => match params.source_ports[output_port] {
+                    Some(port) => port,
+                    None => continue,
+                },
+                None => output_port,
+            };
+            self.register_output(value_id, in_node, OutgoingPort::from(output_port));
+        }
+        if let Some(params) = angle_params {
+            for (&value, &port) in &params.access_outputs {
+                self.register_output(value, in_node, OutgoingPort::from(port));
+            }
+        }
+        for (input_port, &(value_id, _)) in targets.iter().enumerate() {
+            self.register_input(value_id, out_node, IncomingPort::from(input_port));
+        }
+        Ok(())
+    }
+
+    /// Connect all the hyperedges between inputs and outputs with the same value id.
+    ///
+    /// See [`BuildContext::register_input`] and [`BuildContext::register_output`] for more details.
+    fn connect_hyperedges(
+        &mut self,
+        builder: &mut impl hugr::builder::Dataflow,
+    ) -> Result<(), JeffToHugrError> {
+        let output_edges = mem::take(&mut self.output_edges);
+        for (value_id, inputs) in &self.input_edges {
+            if !output_edges.contains_key(value_id) {
+                self.unconnected_inputs
+                    .entry(*value_id)
+                    .or_default()
+                    .extend(inputs);
+            }
+        }
+        for (value_id, outputs) in output_edges {
+            let Some(inputs) = self.input_edges.get(&value_id) else {
                 continue;
             };
             for (out_node, out_port) in outputs {
@@ -310,6 +2113,13 @@ impl BuildContext {
                     builder
                         .hugr_mut()
                         .connect(out_node, out_port, *in_node, *in_port);
+                    if let Some(trace) = &mut self.trace {
+                        trace.record(TraceEvent::HyperedgeConnected {
+                            value: value_id,
+                            source: (out_node, out_port),
+                            target: (*in_node, *in_port),
+                        });
+                    }
 
                     // Insert an order edge if it was a non-local edge
                     let in_parent = builder.hugr().get_parent(*in_node);
@@ -360,41 +2170,167 @@ impl BuildContext {
             }
         };
         let node = builder.call(&func_node, &[], [])?.node();
-
-        // Note: the `zip` will stop when the _jeff_ operation inputs are
-        // exhausted, so it won't register the static function parameters of the
-        // call.
-        for (port, value) in builder.hugr().node_inputs(node).zip(op.inputs()) {
-            let value = value?;
-            self.register_input(value.id(), node, port);
-        }
-        for (port, value) in builder.hugr().node_outputs(node).zip(op.outputs()) {
-            let value = value?;
-            self.register_output(value.id(), node, port);
-        }
-
-        Ok(())
+        self.register_ports(node, op)
     }
 
     /// Emit a single HUGR operation in the node, and register its inputs and outputs.
+    ///
+    /// # Errors
+    ///
+    /// - [`JeffToHugrError::InvalidOperationIO`] if the HUGR operation does not
+    ///   have one value port per _jeff_ input and output.
+    /// - [`JeffToHugrError::TooManyPorts`] if the _jeff_ operation has more
+    ///   inputs or outputs than a HUGR node can hold.
     pub fn build_single_op(
         &mut self,
         op: impl Into<hugr::ops::OpType>,
         jeff_op: &jeff::reader::Operation<'_>,
-        builder: &mut impl hugr::builder::Dataflow,
+        builder: &mut dyn hooks::DataflowDyn,
     ) -> Result<(), JeffToHugrError> {
-        let node = builder.add_child_node(op.into());
+        check_port_count(jeff_op.input_count().max(jeff_op.output_count()))?;
+        let op = op.into();
+        if let Some(sig) = op.dataflow_signature()
+            && (sig.input_count() != jeff_op.input_count()
+                || sig.output_count() != jeff_op.output_count())
+        {
+            let name = format!("{:?}", jeff_op.op_type());
+            return Err(JeffToHugrError::invalid_op_io(name, jeff_op));
+        }
+        let (inputs, outputs) = (jeff_op.input_count(), jeff_op.output_count());
+        self.build_op_sequence(
+            |builder| {
+                let node = builder.add_child_node(op);
+                Ok::<_, JeffToHugrError>(hooks::SeqIo::single_node(node, inputs, outputs))
+            },
+            jeff_op,
+            builder,
+        )
+    }
 
-        for (port, value) in builder.hugr().node_inputs(node).zip(jeff_op.inputs()) {
-            self.register_input(value?.id(), node, port);
+    /// Emit a sequence of HUGR operations translating a single _jeff_
+    /// operation, and register its inputs and outputs.
+    ///
+    /// `ops` adds the operations to the region and wires them together,
+    /// returning the ports corresponding to the _jeff_ inputs and outputs.
+    ///
+    /// # Errors
+    ///
+    /// - [`JeffToHugrError::InvalidOperationIO`] if the returned [`hooks::SeqIo`]
+    ///   does not have one entry per _jeff_ input and output.
+    /// - Any error returned by `ops`.
+    pub fn build_op_sequence<E>(
+        &mut self,
+        ops: impl FnOnce(&mut dyn hooks::DataflowDyn) -> Result<hooks::SeqIo, E>,
+        jeff_op: &jeff::reader::Operation<'_>,
+        builder: &mut dyn hooks::DataflowDyn,
+    ) -> Result<(), JeffToHugrError>
+    where
+        JeffToHugrError: From<E>,
+    {
+        let io = ops(builder)?;
+        if io.inputs.len() != jeff_op.input_count() || io.outputs.len() != jeff_op.output_count() {
+            let name = format!("{:?}", jeff_op.op_type());
+            return Err(JeffToHugrError::invalid_op_io(name, jeff_op));
+        }
+        for &node in &io.nodes {
+            self.record_origin(node);
+        }
+        if let (Some(&first), Some(&last)) = (io.nodes.first(), io.nodes.last()) {
+            let op_type = jeff_op.op_type();
+            match effects::op_effect(&op_type) {
+                Effect::SideEffect if effects::is_quantum(&op_type) => {
+                    self.order_quantum_op(first, builder);
+                    self.last_quantum_op = Some(last);
+                }
+                Effect::SideEffect => self.order_side_effect(first, last, builder),
+                Effect::Pure | Effect::Nested => {}
+            }
+        }
+        for (ports, value) in io.inputs.iter().zip(jeff_op.inputs()) {
+            let value_id = value?.id();
+            for &(node, port) in ports {
+                self.register_input(value_id, node, port);
+            }
         }
-        for (port, value) in builder.hugr().node_outputs(node).zip(jeff_op.outputs()) {
+        for (&(node, port), value) in io.outputs.iter().zip(jeff_op.outputs()) {
             self.register_output(value?.id(), node, port);
         }
+        Ok(())
+    }
+
+    /// Mark the nodes translating a qubit operation with the straight-line
+    /// quantum segment they belong to, in the [`metadata::CIRCUIT_BOX`]
+    /// metadata.
+    ///
+    /// Control-flow operations and calls end the current segment. Only the
+    /// nodes with a qubit input or output are marked, see
+    /// [`crate::passes::box_circuit_segments`].
+    fn mark_segment(&mut self, op: &jeff::reader::Operation<'_>, nodes: &[Node], hugr: &mut Hugr) {
+        match op.op_type() {
+            jeff::reader::optype::OpType::QubitOp(_) => {
+                let segment = *self
+                    .segment_start
+                    .get_or_insert(self.current_op.unwrap_or_default());
+                for &node in nodes {
+                    let acts_on_qubits = hugr.signature(node).is_some_and(|sig| {
+                        sig.input_types().contains(&qb_t()) || sig.output_types().contains(&qb_t())
+                    });
+                    if acts_on_qubits {
+                        hugr.set_metadata(node, metadata::CIRCUIT_BOX, segment);
+                    }
+                }
+            }
+            jeff::reader::optype::OpType::ControlFlowOp(_)
+            | jeff::reader::optype::OpType::FuncOp(_) => self.segment_start = None,
+            _ => {}
+        }
+    }
+
+    /// Emit a [`JeffOp::GlobalPhase`], ordered after the previous quantum
+    /// operation in the region and before the next one.
+    pub(crate) fn build_global_phase(
+        &mut self,
+        jeff_op: &jeff::reader::Operation<'_>,
+        builder: &mut impl hugr::builder::Dataflow,
+    ) -> Result<(), JeffToHugrError> {
+        if jeff_op.input_count() != 1 || jeff_op.output_count() != 0 {
+            return Err(JeffToHugrError::invalid_op_io("GlobalPhase", jeff_op));
+        }
+        let node = builder.add_child_node(JeffOp::GlobalPhase.try_into_extension_op()?);
+        self.record_origin(node);
+        self.register_input(jeff_op.input(0).unwrap()?.id(), node, IncomingPort::from(0));
 
+        if let Some(prev) = self.last_quantum_op {
+            builder.hugr_mut().add_other_edge(prev, node);
+        }
+        self.pending_phases.push(node);
         Ok(())
     }
 
+    /// Register the nodes translating a classical operation with side
+    /// effects, ordering them after the previous quantum operation in the
+    /// region and before the next one.
+    pub(crate) fn order_side_effect(
+        &mut self,
+        first: Node,
+        last: Node,
+        builder: &mut dyn hooks::DataflowDyn,
+    ) {
+        if let Some(prev) = self.last_quantum_op {
+            builder.hugr_mut().add_other_edge(prev, first);
+        }
+        self.pending_phases.push(last);
+    }
+
+    /// Register a node translating a quantum operation, ordering it after
+    /// the global phases emitted since the previous quantum operation.
+    pub(crate) fn order_quantum_op(&mut self, node: Node, builder: &mut dyn hooks::DataflowDyn) {
+        for phase in self.pending_phases.drain(..) {
+            builder.hugr_mut().add_other_edge(phase, node);
+        }
+        self.last_quantum_op = Some(node);
+    }
+
     /// Mark a jeff operation that does not produce any HUGR output values.
     ///
     /// Merges the input values with its outputs in the context.
@@ -420,31 +2356,218 @@ impl BuildContext {
         Ok(())
     }
 
+    /// Translate an explicit copy of a value, with a single input and
+    /// outputs of the same type.
+    ///
+    /// The copy adds no node to the HUGR: its outputs are merged with the
+    /// input, which is then wired to all their uses.
+    ///
+    /// # Errors
+    ///
+    /// - [`JeffToHugrError::LinearValueCopied`] if a linear value is copied
+    ///   to more than one output.
+    /// - [`JeffToHugrError::InvalidOperationIO`] if the operation has several
+    ///   inputs, or outputs of a different type.
+    pub fn build_copy_op(
+        &mut self,
+        jeff_op: &jeff::reader::Operation<'_>,
+    ) -> Result<(), JeffToHugrError> {
+        if jeff_op.input_count() != 1 {
+            return Err(JeffToHugrError::invalid_op_io("Copy", jeff_op));
+        }
+        let input = jeff_op.input(0).unwrap()?;
+        let mut copies = Vec::with_capacity(jeff_op.output_count());
+        for output in jeff_op.outputs() {
+            let output = output?;
+            if output.ty() != input.ty() {
+                return Err(JeffToHugrError::invalid_op_io("Copy", jeff_op));
+            }
+            copies.push(output.id());
+        }
+        let linear = !crate::types::jeff_to_hugr(input.ty()).copyable();
+        self.copy_value(input.id(), linear, &copies)
+    }
+
+    /// Merge the copies of a value with it.
+    fn copy_value(
+        &mut self,
+        value_id: jeff::reader::value::ValueId,
+        linear: bool,
+        copies: &[jeff::reader::value::ValueId],
+    ) -> Result<(), JeffToHugrError> {
+        if linear && copies.len() > 1 {
+            return Err(JeffToHugrError::LinearValueCopied {
+                value_id,
+                op_index: self.current_op.unwrap_or_default(),
+            });
+        }
+        for &copy in copies {
+            self.merge_with_earlier(copy, value_id);
+        }
+        Ok(())
+    }
+
     /// Helper function to convert _jeff_ constant values into HUGR constant / loadConstant pairs.
-    pub fn build_constant_value(
+    pub(crate) fn build_constant_value(
         &mut self,
         value: impl Into<hugr::ops::Value>,
         jeff_op: &jeff::reader::Operation<'_>,
         builder: &mut impl hugr::builder::Dataflow,
     ) -> Result<(), JeffToHugrError> {
-        let wire = builder.add_load_value(value.into());
+        // Constant ops in _jeff_ have no inputs and a single output.
+        if jeff_op.input_count() != 0 || jeff_op.output_count() != 1 {
+            return Err(JeffToHugrError::unsupported_op(jeff_op));
+        }
+        let output = jeff_op.output(0).unwrap()?;
+
+        self.load_constant(value.into(), output.id(), builder);
+        Ok(())
+    }
+
+    /// Load a constant in the region, and register it as the _jeff_ value.
+    fn load_constant(
+        &mut self,
+        value: Value,
+        value_id: jeff::reader::value::ValueId,
+        builder: &mut impl hugr::builder::Dataflow,
+    ) {
+        if let Some(f) = value.get_custom_value::<ConstF64>() {
+            self.float_constants.insert(value_id, f.value());
+        }
+        let wire = builder.add_load_value(value.clone());
+        self.constants.insert(value_id, (value, wire.node()));
+        self.register_output(value_id, wire.node(), wire.source());
+    }
+
+    /// Decide where the constants passed to a control-flow operation are
+    /// loaded, and record the choice in the [`ConversionStats`].
+    ///
+    /// The arguments of the operation are its inputs from `first_arg` on,
+    /// which become the sources of its regions. If `sink` is set, the
+    /// constants only used as arguments of the operation are loaded inside
+    /// the regions reading them instead, and their load in the current
+    /// region is removed. The other constants are passed through the inputs
+    /// of the HUGR container, so the nested regions never read them through
+    /// non-local edges.
+    ///
+    /// Returns the sunk constants, by argument position.
+    pub(crate) fn place_boundary_constants(
+        &mut self,
+        jeff_op: &jeff::reader::Operation<'_>,
+        first_arg: usize,
+        sink: bool,
+        builder: &mut impl hugr::builder::Dataflow,
+    ) -> Result<BTreeMap<usize, Value>, JeffToHugrError> {
+        let inputs = jeff_op
+            .inputs()
+            .map(|value| Ok(value?.id()))
+            .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+        let mut sunk = BTreeMap::new();
+        for value_id in inputs.iter().skip(first_arg).unique() {
+            let Some((value, load)) = self.constants.get(value_id).cloned() else {
+                continue;
+            };
+            let op_uses = inputs.iter().filter(|&id| id == value_id).count();
+            let only_args = inputs[..first_arg].iter().all(|id| id != value_id);
+            let aliased = self.merged_values.values().any(|id| id == value_id);
+            if !sink || !only_args || aliased || self.value_uses.get(value_id) != Some(&op_uses)
+            {
+                self.stats.threaded_constants += 1;
+                continue;
+            }
+            for (position, _) in inputs.iter().enumerate().filter(|&(_, id)| id == value_id) {
+                sunk.insert(position - first_arg, value.clone());
+            }
+            let const_node = builder.hugr().static_source(load);
+            builder.hugr_mut().remove_node(load);
+            if let Some(const_node) = const_node {
+                builder.hugr_mut().remove_node(const_node);
+            }
+            self.constants.remove(value_id);
+            self.output_edges.remove(value_id);
+            self.stats.sunk_constants += 1;
+        }
+        Ok(sunk)
+    }
+
+    /// Returns the value of a _jeff_ value if it is defined by a float constant in the current region.
+    pub(crate) fn float_constant(&self, value_id: jeff::reader::value::ValueId) -> Option<f64> {
+        self.float_constants
+            .get(&self.earliest_id(value_id))
+            .copied()
+    }
+
+    /// Record the elements of a _jeff_ float array built from constants.
+    pub(crate) fn record_float_array_constant(
+        &mut self,
+        value_id: jeff::reader::value::ValueId,
+        values: Vec<f64>,
+    ) {
+        self.float_array_constants.insert(value_id, values);
+    }
+
+    /// Returns the elements of a _jeff_ float array if it is built from constants.
+    pub(crate) fn float_array_constant(
+        &self,
+        value_id: jeff::reader::value::ValueId,
+    ) -> Option<&[f64]> {
+        self.float_array_constants
+            .get(&self.earliest_id(value_id))
+            .map(Vec::as_slice)
+    }
 
+    /// Load a constant array defined at the module level.
+    ///
+    /// Identical arrays across the whole module share a single `Const` node,
+    /// which is emitted when the module is finished.
+    pub(crate) fn build_shared_constant(
+        &mut self,
+        value: ConstIntReg,
+        jeff_op: &jeff::reader::Operation<'_>,
+        builder: &mut impl hugr::builder::Dataflow,
+    ) -> Result<(), JeffToHugrError> {
         // Constant ops in _jeff_ have no inputs and a single output.
         if jeff_op.input_count() != 0 || jeff_op.output_count() != 1 {
             return Err(JeffToHugrError::unsupported_op(jeff_op));
         }
-        let value = jeff_op.output(0).unwrap()?;
+        let output = jeff_op.output(0).unwrap()?;
+        self.config
+            .limits
+            .check(ResourceLimit::ConstArrayLength, value.len())?;
 
-        self.register_output(value.id(), wire.node(), wire.source());
+        let load = builder.add_child_node(LoadConstant {
+            datatype: value.get_type(),
+        });
+        self.shared_constants
+            .entry(value)
+            .or_default()
+            .push((load, IncomingPort::from(0)));
+        self.register_output(output.id(), load, OutgoingPort::from(0));
         Ok(())
     }
 }
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::test::{catalyst_simple, catalyst_tket_opt, qubits};
+    use crate::extension::JeffOp;
+    use crate::optype::tket_ops;
+    use crate::test::{
+        catalyst_simple, catalyst_tket_opt, conflicting_declaration, entangled_calls, entangled_qs,
+        qubits, redeclared_functions, reused_qubit,
+    };
     use hugr::HugrView;
+    use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr, DataflowSubContainer, HugrBuilder};
+    use hugr::extension::prelude::{qb_t, usize_t};
+    use hugr::ops::OpType;
+    use hugr::std_extensions::arithmetic::float_types::float64_type;
+    use hugr::std_extensions::arithmetic::int_ops::IntOpDef;
+    use hugr::std_extensions::arithmetic::int_types::int_type;
+    use hugr::std_extensions::logic::LogicOp;
+    use hugr::types::Signature;
     use rstest::rstest;
+    use std::ops::ControlFlow;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
     #[rstest]
     #[case::qubits(qubits())]
@@ -455,4 +2578,898 @@ mod test {
 
         hugr.validate().unwrap_or_else(|e| panic!("{e}"));
     }
+
+    /// Returns `true` if the operation is a quantum operation emitted by the translation.
+    fn is_quantum_op(op: &OpType) -> bool {
+        crate::optype::tket_ops::is_tket_op(op)
+            || matches!(op.cast::<JeffOp>(), Some(JeffOp::QGate { .. }))
+    }
+
+    #[rstest]
+    fn provenance_qubits(qubits: Jeff<'static>) {
+        let config = JeffToHugrConfig {
+            record_provenance: true,
+            ..Default::default()
+        };
+        let ConvertedHugr { hugr, map, .. } = jeff_to_hugr_with_config(&qubits, &config).unwrap();
+
+        let quantum_nodes = hugr
+            .nodes()
+            .filter(|&n| is_quantum_op(hugr.get_optype(n)))
+            .collect_vec();
+        assert!(!quantum_nodes.is_empty());
+
+        for &node in &quantum_nodes {
+            let (func, op_index) = map
+                .node_origin(node)
+                .unwrap_or_else(|| panic!("{node} has no origin"));
+            assert_eq!(func, 0);
+            assert_eq!(
+                hugr.get_metadata(node, metadata::OP_INDEX),
+                Some(&serde_json::json!([func, op_index]))
+            );
+
+            // Indices strictly increase along the qubit wires.
+            for port in hugr.node_outputs(node) {
+                for (next, _) in hugr.linked_inputs(node, port) {
+                    if let Some((_, next_index)) = map.node_origin(next) {
+                        assert!(next_index > op_index);
+                    }
+                }
+            }
+        }
+    }
+
+    #[rstest]
+    fn provenance_skips_synthesized_nodes(catalyst_tket_opt: Jeff<'static>) {
+        let config = JeffToHugrConfig {
+            record_provenance: true,
+            ..Default::default()
+        };
+        let ConvertedHugr { hugr, map, .. } =
+            jeff_to_hugr_with_config(&catalyst_tket_opt, &config).unwrap();
+
+        for node in hugr.nodes() {
+            let op = hugr.get_optype(node);
+            if is_quantum_op(op) {
+                assert!(map.node_origin(node).is_some(), "{node} has no origin");
+            }
+            // Loop machinery and constants are synthesized by the translation.
+            if matches!(
+                op,
+                OpType::TailLoop(_)
+                    | OpType::Conditional(_)
+                    | OpType::Case(_)
+                    | OpType::Tag(_)
+                    | OpType::Const(_)
+                    | OpType::LoadConstant(_)
+            ) {
+                assert!(map.node_origin(node).is_none());
+            }
+        }
+    }
+
+    #[rstest]
+    fn no_provenance_by_default(qubits: Jeff<'static>) {
+        let ConvertedHugr { hugr, map, .. } =
+            jeff_to_hugr_with_config(&qubits, &JeffToHugrConfig::default()).unwrap();
+        assert_eq!(map.origins().count(), 0);
+        assert!(
+            hugr.nodes()
+                .all(|n| hugr.get_metadata(n, metadata::OP_INDEX).is_none())
+        );
+        assert_eq!(map.function_nodes().len(), 1);
+    }
+
+    /// Angles that are not exact fractions of π keep the radians conversion.
+    #[rstest]
+    fn inexact_angles_unchanged(catalyst_simple: Jeff<'static>) {
+        let exact = jeff_to_hugr(&catalyst_simple).unwrap();
+        let config = JeffToHugrConfig {
+            exact_angles: None,
+            ..Default::default()
+        };
+        let plain = jeff_to_hugr_with_config(&catalyst_simple, &config)
+            .unwrap()
+            .hugr;
+
+        assert!(
+            exact
+                .nodes()
+                .all(|n| exact.get_metadata(n, metadata::EXACT_ANGLE).is_none())
+        );
+        crate::test::assert_hugr_equivalent(&exact, &plain);
+    }
+
+    /// Functions without float array parameters are not affected by the angle symbolization.
+    #[rstest]
+    #[case::qubits(qubits())]
+    #[case::catalyst_simple(catalyst_simple())]
+    #[case::catalyst_tket_opt(catalyst_tket_opt())]
+    fn symbolize_without_angle_arrays(#[case] jeff: Jeff<'static>) {
+        let plain = jeff_to_hugr(&jeff).unwrap();
+        let config = JeffToHugrConfig {
+            symbolize_angle_params: true,
+            ..Default::default()
+        };
+        let symbolized = jeff_to_hugr_with_config(&jeff, &config).unwrap().hugr;
+
+        assert!(
+            symbolized
+                .nodes()
+                .all(|n| symbolized.get_metadata(n, metadata::ANGLE_PARAMS).is_none())
+        );
+        crate::test::assert_hugr_equivalent(&plain, &symbolized);
+    }
+
+    fn limited(limits: ResourceLimits) -> JeffToHugrConfig {
+        JeffToHugrConfig {
+            limits,
+            ..Default::default()
+        }
+    }
+
+    #[rstest]
+    fn limit_functions(qubits: Jeff<'static>) {
+        let config = limited(ResourceLimits {
+            max_functions: 0,
+            ..Default::default()
+        });
+        let err = jeff_to_hugr_with_config(&qubits, &config).unwrap_err();
+        assert!(matches!(
+            err,
+            JeffToHugrError::ResourceLimitExceeded {
+                limit: ResourceLimit::Functions,
+                ..
+            }
+        ));
+
+        // The target module is left untouched.
+        let mut module = Hugr::default();
+        let err = BuildContext::with_config(&config).build_into_module(
+            qubits.module(),
+            &mut module,
+            None,
+            &BTreeMap::new(),
+        );
+        assert!(err.is_err());
+        assert_eq!(module.num_nodes(), 1);
+    }
+
+    /// The `qubits` fixture applies two-qubit gates.
+    #[rstest]
+    fn limit_qubits(qubits: Jeff<'static>) {
+        let config = limited(ResourceLimits {
+            max_qubits: 1,
+            ..Default::default()
+        });
+        let err = jeff_to_hugr_with_config(&qubits, &config).unwrap_err();
+        assert!(matches!(
+            err,
+            JeffToHugrError::ResourceLimitExceeded {
+                limit: ResourceLimit::Qubits,
+                requested: 2,
+            }
+        ));
+    }
+
+    #[rstest]
+    fn limit_nodes(catalyst_tket_opt: Jeff<'static>) {
+        let max_nodes = 20;
+        let config = limited(ResourceLimits {
+            max_nodes,
+            ..Default::default()
+        });
+        let err = jeff_to_hugr_with_config(&catalyst_tket_opt, &config).unwrap_err();
+        let JeffToHugrError::ResourceLimitExceeded {
+            limit: ResourceLimit::Nodes,
+            requested,
+        } = err
+        else {
+            panic!("Unexpected error: {err}");
+        };
+        assert!(requested > max_nodes);
+    }
+
+    /// The extension operations in a function, as sorted labels.
+    fn extension_ops(hugr: &Hugr, func: Node) -> Vec<String> {
+        hugr.descendants(func)
+            .filter_map(|n| hugr.get_optype(n).as_extension_op())
+            .map(|op| format!("{}{:?}", op.qualified_id(), op.args()))
+            .sorted()
+            .collect()
+    }
+
+    /// The second `entangled_calls` function only calls the first one.
+    #[rstest]
+    fn inline_calls(entangled_calls: Jeff<'static>) {
+        let config = JeffToHugrConfig {
+            inline_calls: true,
+            ..Default::default()
+        };
+        let inlined = jeff_to_hugr_with_config(&entangled_calls, &config).unwrap();
+        let hugr = &inlined.hugr;
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        assert!(!hugr.nodes().any(|n| hugr.get_optype(n).is_call()));
+
+        let plain = jeff_to_hugr_with_config(&entangled_calls, &Default::default()).unwrap();
+        assert_eq!(
+            extension_ops(hugr, inlined.map.function_nodes()[1]),
+            extension_ops(&plain.hugr, plain.map.function_nodes()[0])
+        );
+    }
+
+    #[rstest]
+    fn limit_inline_depth(entangled_calls: Jeff<'static>) {
+        let config = JeffToHugrConfig {
+            inline_calls: true,
+            ..limited(ResourceLimits {
+                max_inline_depth: 0,
+                ..Default::default()
+            })
+        };
+        let err = jeff_to_hugr_with_config(&entangled_calls, &config).unwrap_err();
+        assert!(matches!(
+            err,
+            JeffToHugrError::ResourceLimitExceeded {
+                limit: ResourceLimit::InlineDepth,
+                requested: 1,
+            }
+        ));
+    }
+
+    #[rstest]
+    fn progress_phases(entangled_calls: Jeff<'static>) {
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let hook = ProgressHook::new({
+            let reports = Arc::clone(&reports);
+            move |progress| {
+                reports.lock().unwrap().push(progress);
+                ControlFlow::Continue(())
+            }
+        });
+        let config = JeffToHugrConfig {
+            progress: Some(hook.with_interval(10)),
+            ..Default::default()
+        };
+        jeff_to_hugr_with_config(&entangled_calls, &config).unwrap();
+
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports[0], Progress::Scanning { functions: 2 });
+        let phases = reports
+            .iter()
+            .map(|progress| match progress {
+                Progress::Scanning { .. } => "scanning".to_string(),
+                Progress::Converting { function, .. } => format!("function {function}"),
+                Progress::WiringCalls { .. } => "wiring".to_string(),
+                Progress::Validating { .. } => "validating".to_string(),
+            })
+            .dedup()
+            .collect_vec();
+        assert_eq!(
+            phases,
+            ["scanning", "function 0", "function 1", "wiring", "validating"]
+        );
+
+        // The first function has 50 operations.
+        let operations = reports
+            .iter()
+            .filter_map(|progress| match progress {
+                Progress::Converting {
+                    function: 0,
+                    operations,
+                    ..
+                } => Some(*operations),
+                _ => None,
+            })
+            .collect_vec();
+        assert_eq!(operations, [0, 10, 20, 30, 40, 50]);
+    }
+
+    #[rstest]
+    fn progress_cancel(entangled_calls: Jeff<'static>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let hook = ProgressHook::new({
+            let calls = Arc::clone(&calls);
+            move |progress| {
+                calls.fetch_add(1, Ordering::Relaxed);
+                match progress {
+                    Progress::Converting { function: 1, .. } => ControlFlow::Break(()),
+                    _ => ControlFlow::Continue(()),
+                }
+            }
+        });
+        let config = JeffToHugrConfig {
+            progress: Some(hook),
+            ..Default::default()
+        };
+        let err = jeff_to_hugr_with_config(&entangled_calls, &config).unwrap_err();
+        let JeffToHugrError::Cancelled {
+            progress: Progress::Converting { name, .. },
+        } = err
+        else {
+            panic!("Unexpected error: {err}");
+        };
+        let second = entangled_calls.module().functions().nth(1).unwrap();
+        assert_eq!(name, second.name());
+        // The scan, the first function, and the cancelled second function.
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+    }
+
+    #[rstest]
+    #[case::qubits(qubits())]
+    #[case::catalyst_simple(catalyst_simple())]
+    #[case::catalyst_tket_opt(catalyst_tket_opt())]
+    fn unlimited(#[case] jeff: Jeff<'static>) {
+        let config = limited(ResourceLimits::unlimited());
+        let hugr = jeff_to_hugr_with_config(&jeff, &config).unwrap().hugr;
+        crate::test::assert_hugr_equivalent(&hugr, &jeff_to_hugr(&jeff).unwrap());
+    }
+
+    #[rstest]
+    #[case::small(1000, true)]
+    #[case::max(MAX_VALUE_PORTS, true)]
+    #[case::too_large(MAX_VALUE_PORTS + 1, false)]
+    fn port_count(#[case] count: usize, #[case] ok: bool) {
+        match check_port_count(count) {
+            Ok(()) => assert!(ok),
+            Err(JeffToHugrError::TooManyPorts { count: c }) => {
+                assert!(!ok);
+                assert_eq!(c, count);
+            }
+            Err(e) => panic!("unexpected error: {e}"),
+        }
+    }
+
+    /// A function with many parameters and outputs, and a call to it, can be
+    /// built and validated with the port layout used by the translation.
+    #[rstest]
+    fn large_signature() {
+        let n = 1000;
+        let sig = Signature::new(vec![float64_type(); n], vec![float64_type(); n]);
+        let mut builder = ModuleBuilder::new();
+        let func = builder.define_function("calibrated", sig.clone()).unwrap();
+        let inputs = func.input_wires();
+        let func = func.finish_with_outputs(inputs).unwrap();
+
+        let mut main = builder.define_function("main", sig).unwrap();
+        let inputs = main.input_wires();
+        let call = main.call(func.handle(), &[], inputs).unwrap();
+        assert_eq!(
+            main.hugr().get_optype(call.node()).static_input_port(),
+            Some(IncomingPort::from(n))
+        );
+        main.finish_with_outputs(call.outputs()).unwrap();
+
+        let hugr = builder.finish_hugr().unwrap();
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        assert!(hugr.num_nodes() < 20);
+    }
+
+    #[rstest]
+    #[case::lsb_first(BitOrder::LsbFirst)]
+    #[case::msb_first(BitOrder::MsbFirst)]
+    fn bit_order_metadata(qubits: Jeff<'static>, #[case] bit_order: BitOrder) {
+        let config = JeffToHugrConfig {
+            bit_order,
+            ..Default::default()
+        };
+        let hugr = jeff_to_hugr_with_config(&qubits, &config).unwrap().hugr;
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        let recorded = hugr
+            .get_metadata(hugr.module_root(), metadata::BIT_ORDER)
+            .and_then(BitOrder::from_metadata);
+        assert_eq!(recorded, Some(bit_order));
+    }
+
+    /// The Catalyst example has no registers in its signatures, so the handle
+    /// ABI leaves its functions unchanged and the signatures use no
+    /// extension type.
+    #[rstest]
+    fn handle_abi_catalyst_simple(catalyst_simple: Jeff<'static>) {
+        let config = JeffToHugrConfig::default().with_handle_abi(HandleAbi::Entrypoints);
+        let hugr = jeff_to_hugr_with_config(&catalyst_simple, &config).unwrap().hugr;
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        let mut names = Vec::new();
+        for node in hugr.children(hugr.module_root()) {
+            let OpType::FuncDefn(defn) = hugr.get_optype(node) else {
+                continue;
+            };
+            names.push(defn.func_name().clone());
+            let sig = defn.signature().body();
+            for ty in sig.input_types().iter().chain(sig.output_types()) {
+                let hugr::types::TypeEnum::Extension(custom) = ty.as_type_enum() else {
+                    continue;
+                };
+                assert_ne!(custom.extension(), &crate::extension::JEFF_EXTENSION_ID);
+            }
+        }
+        assert_eq!(names, ["hello", "world"]);
+    }
+
+    /// Build a DFG with the given boundary, and return the ports of the
+    /// input node linked to each of its outputs.
+    fn wire_boundary(
+        signature: Signature,
+        sources: &[(u32, bool)],
+        targets: &[(u32, bool)],
+    ) -> Result<Vec<Vec<OutgoingPort>>, JeffToHugrError> {
+        let mut builder = DFGBuilder::new(signature).unwrap();
+        let mut ctx = BuildContext::default();
+        ctx.register_boundary(sources, targets, builder.io(), None)?;
+        ctx.connect_hyperedges(&mut builder)?;
+        let hugr = builder.hugr();
+        let [in_node, out_node] = builder.io();
+        let links = (0..targets.len())
+            .map(|port| {
+                hugr.linked_outputs(out_node, port)
+                    .map(|(node, port)| {
+                        assert_eq!(node, in_node);
+                        port
+                    })
+                    .collect_vec()
+            })
+            .collect_vec();
+        Ok(links)
+    }
+
+    #[rstest]
+    fn duplicate_copyable_sources() {
+        let signature = Signature::new(vec![usize_t(), usize_t()], vec![usize_t()]);
+        let links = wire_boundary(signature, &[(0, false), (0, false)], &[(0, false)]).unwrap();
+        // The first port is read, and the second one is left unused.
+        assert_eq!(links, [vec![OutgoingPort::from(0)]]);
+    }
+
+    #[rstest]
+    fn duplicate_copyable_targets() {
+        let signature = Signature::new(vec![usize_t()], vec![usize_t(), usize_t()]);
+        let links = wire_boundary(signature, &[(0, false)], &[(0, false), (0, false)]).unwrap();
+        let first = vec![OutgoingPort::from(0)];
+        assert_eq!(links, [first.clone(), first]);
+    }
+
+    #[rstest]
+    #[case::sources(&[(0, true), (0, true)], &[(0, true)])]
+    #[case::targets(&[(0, true)], &[(0, true), (0, true)])]
+    fn duplicate_linear_boundary_values(
+        #[case] sources: &[(u32, bool)],
+        #[case] targets: &[(u32, bool)],
+    ) {
+        let signature = Signature::new(vec![qb_t(); sources.len()], vec![qb_t(); targets.len()]);
+        let err = wire_boundary(signature, sources, targets).unwrap_err();
+        assert!(matches!(
+            err,
+            JeffToHugrError::DuplicateLinearBoundaryValue { value_id: 0 }
+        ));
+    }
+
+    /// An integer copied into both operands of two adders, and the ports
+    /// of the input node linked to each adder.
+    #[rstest]
+    fn copied_int() {
+        let int = int_type(6);
+        let signature = Signature::new(vec![int.clone(), int.clone()], vec![int.clone(), int]);
+        let mut builder = DFGBuilder::new(signature).unwrap();
+        let [in_node, _] = builder.io();
+        let mut ctx = BuildContext::default();
+        ctx.register_boundary(
+            &[(0, false), (1, false)],
+            &[(4, false), (5, false)],
+            builder.io(),
+            None,
+        )
+        .unwrap();
+        ctx.copy_value(0, false, &[2, 3]).unwrap();
+        for (copy, sum) in [(2, 4), (3, 5)] {
+            let adder = builder.add_child_node(IntOpDef::iadd.with_log_width(6));
+            ctx.register_input(copy, adder, IncomingPort::from(0));
+            ctx.register_input(1, adder, IncomingPort::from(1));
+            ctx.register_output(sum, adder, OutgoingPort::from(0));
+        }
+        ctx.connect_hyperedges(&mut builder).unwrap();
+
+        let hugr = builder.hugr();
+        let uses = hugr.linked_inputs(in_node, 0).collect_vec();
+        assert_eq!(uses.len(), 2);
+        assert_ne!(uses[0].0, uses[1].0);
+        for (adder, port) in uses {
+            assert_eq!(port, IncomingPort::from(0));
+            assert_eq!(hugr.single_linked_output(adder, 0), Some((in_node, OutgoingPort::from(0))));
+        }
+    }
+
+    #[rstest]
+    fn copied_qubit() {
+        let mut ctx = BuildContext::default();
+        ctx.current_op = Some(3);
+        let err = ctx.copy_value(0, true, &[1, 2]).unwrap_err();
+        assert!(matches!(
+            err,
+            JeffToHugrError::LinearValueCopied {
+                value_id: 0,
+                op_index: 3
+            }
+        ));
+        assert_eq!(err.to_string(), "Linear value 0 is copied by operation 3");
+    }
+
+    /// The body of the first function of a program.
+    fn first_body<'a>(jeff: &'a Jeff<'static>) -> jeff::reader::Region<'a> {
+        let jeff::reader::Function::Definition(def) = jeff.module().functions().next().unwrap()
+        else {
+            panic!("Expected a function definition");
+        };
+        def.body()
+    }
+
+    /// A float constant of a region is not read by the next region, whose
+    /// values reuse the same ids.
+    #[rstest]
+    fn float_constants_are_per_region(qubits: Jeff<'static>) {
+        let mut ctx = BuildContext::default();
+        let mut previous = DFGBuilder::new(Signature::new(vec![], vec![float64_type()])).unwrap();
+        // Value 0 is the first qubit input of the next function.
+        ctx.load_constant(ConstF64::new(0.25).into(), 0, &mut previous);
+        assert_eq!(ctx.float_constant(0), Some(0.25));
+
+        let signature = Signature::new(vec![qb_t(); 2], vec![qb_t(); 3]);
+        let mut builder = DFGBuilder::new(signature).unwrap();
+        ctx.build_region(first_body(&qubits), &mut builder).unwrap();
+        assert_eq!(ctx.float_constant(0), None);
+        // The angle of the `Rx` gate is a constant of the function.
+        assert_eq!(ctx.float_constant(10), Some(0.5));
+    }
+
+    /// A constant float array of a region is not read by the next region,
+    /// whose values reuse the same ids.
+    #[rstest]
+    fn float_array_constants_are_per_region(qubits: Jeff<'static>) {
+        let mut ctx = BuildContext::default();
+        ctx.record_float_array_constant(1, vec![0.25, 0.5]);
+        assert_eq!(ctx.float_array_constant(1), Some([0.25, 0.5].as_slice()));
+
+        let signature = Signature::new(vec![qb_t(); 2], vec![qb_t(); 3]);
+        let mut builder = DFGBuilder::new(signature).unwrap();
+        ctx.build_region(first_body(&qubits), &mut builder).unwrap();
+        assert_eq!(ctx.float_array_constant(1), None);
+    }
+
+    /// A classical operation with side effects, ordered between the
+    /// neighbouring measurements.
+    #[rstest]
+    fn order_side_effect() {
+        let mut builder = DFGBuilder::new(Signature::new_endo(vec![qb_t(), qb_t()])).unwrap();
+        let [a, b] = builder.input_wires_arr();
+        let mut ctx = BuildContext::default();
+        let first = builder.add_dataflow_op(tket_ops::measure(), [a]).unwrap();
+        ctx.order_quantum_op(first.node(), &mut builder);
+        let not = builder
+            .add_dataflow_op(LogicOp::Not, [first.out_wire(1)])
+            .unwrap();
+        ctx.order_side_effect(not.node(), not.node(), &mut builder);
+        let second = builder.add_dataflow_op(tket_ops::measure(), [b]).unwrap();
+        ctx.order_quantum_op(second.node(), &mut builder);
+        let hugr = builder
+            .finish_hugr_with_outputs([first.out_wire(0), second.out_wire(0)])
+            .unwrap();
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        let ordered = |from: Node, to: Node| {
+            let port = hugr.get_optype(from).other_output_port().unwrap();
+            hugr.linked_inputs(from, port).any(|(node, _)| node == to)
+        };
+        assert!(ordered(first.node(), not.node()));
+        assert!(ordered(not.node(), second.node()));
+        assert!(!ordered(first.node(), second.node()));
+    }
+
+    #[rstest]
+    fn annotate_depths(entangled_qs: Jeff<'static>) {
+        let config = JeffToHugrConfig::default().with_annotate_depths(true);
+        let hugr = jeff_to_hugr_with_config(&entangled_qs, &config).unwrap().hugr;
+        let func = hugr
+            .children(hugr.module_root())
+            .find(|&node| match hugr.get_optype(node) {
+                OpType::FuncDefn(defn) => defn.func_name() == "qarray",
+                _ => false,
+            })
+            .unwrap();
+        assert_eq!(hugr.get_metadata(func, metadata::DEPTH), Some(&serde_json::json!(6)));
+        assert_eq!(hugr.get_metadata(func, metadata::DEPTH_2Q), Some(&serde_json::json!(4)));
+
+        // The depths are only annotated on request.
+        let hugr = jeff_to_hugr(&entangled_qs).unwrap();
+        assert!(hugr.nodes().all(|node| hugr.get_metadata(node, metadata::DEPTH).is_none()));
+    }
+
+    #[rstest]
+    #[case::catalyst_simple(catalyst_simple())]
+    #[case::catalyst_tket_opt(catalyst_tket_opt())]
+    fn bool_collection_bit_arrays(#[case] jeff: Jeff<'static>) {
+        let config = JeffToHugrConfig {
+            bit_arrays_as: BitArrays::BoolCollection,
+            ..Default::default()
+        };
+        let hugr = jeff_to_hugr_with_config(&jeff, &config).unwrap().hugr;
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        let bits_t = crate::extension::intreg_type(1);
+        for node in hugr.nodes() {
+            let Some(sig) = hugr.get_optype(node).dataflow_signature() else {
+                continue;
+            };
+            assert!(!sig.input_types().contains(&bits_t));
+            assert!(!sig.output_types().contains(&bits_t));
+        }
+    }
+
+    /// The translation only references the extensions of the allow-list, in
+    /// every mode changing the emitted operations.
+    #[rstest]
+    #[case::default(JeffToHugrConfig::default())]
+    #[case::bool_collection(
+        JeffToHugrConfig::default().with_bit_arrays_as(BitArrays::BoolCollection)
+    )]
+    #[case::split_measures(JeffToHugrConfig::default().with_split_measures(true))]
+    #[case::float_angles(JeffToHugrConfig::default().with_exact_angles(None))]
+    #[case::trap_overflow(JeffToHugrConfig::default().with_int_overflow(IntOverflow::Trap))]
+    #[case::fused(JeffToHugrConfig::default().with_fuse_single_qubit_gates(true))]
+    #[case::static_arrays(
+        JeffToHugrConfig::default().with_qureg_lowering(QuregLowering::StaticArray)
+    )]
+    fn allowed_extensions(#[case] config: JeffToHugrConfig) {
+        let config = config.with_check_extensions(true);
+        let programs = [
+            qubits(),
+            entangled_qs(),
+            entangled_calls(),
+            catalyst_simple(),
+            catalyst_tket_opt(),
+        ];
+        for jeff in programs {
+            jeff_to_hugr_with_config(&jeff, &config).unwrap_or_else(|e| panic!("{e}"));
+        }
+    }
+
+    /// A module with a single unrelated function, returning it and the function node.
+    fn module_with_function(name: &str) -> (Hugr, Node) {
+        let mut builder = ModuleBuilder::new();
+        let func = builder
+            .define_function(name, Signature::new_endo(vec![qb_t()]))
+            .unwrap();
+        let inputs = func.input_wires();
+        let func = func.finish_with_outputs(inputs).unwrap().node();
+        (builder.finish_hugr().unwrap(), func)
+    }
+
+    #[rstest]
+    fn import_into_module(qubits: Jeff<'static>) {
+        let (mut module, existing) = module_with_function("existing");
+        let original = module.clone();
+
+        let added = jeff_into_hugr_module(&qubits, &mut module, None).unwrap();
+        assert_eq!(added.len(), qubits.module().functions().count());
+        for &node in &added {
+            assert_eq!(module.get_parent(node), Some(module.module_root()));
+        }
+        module.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        // The pre-existing function is untouched.
+        let mut before = original;
+        before.set_entrypoint(existing);
+        let mut after = module.clone();
+        after.set_entrypoint(existing);
+        crate::test::assert_hugr_equivalent(&before, &after);
+
+        // Calls in the imported functions resolve to imported functions.
+        for node in module.nodes().filter(|&n| module.get_optype(n).is_call()) {
+            let target = module.static_source(node).unwrap();
+            assert!(added.contains(&target));
+        }
+    }
+
+    #[rstest]
+    fn import_into_module_name_collision(qubits: Jeff<'static>) {
+        let mut module = jeff_to_hugr(&qubits).unwrap();
+
+        let err = jeff_into_hugr_module(&qubits, &mut module, None).unwrap_err();
+        assert!(matches!(err, JeffToHugrError::FunctionNameCollision { .. }));
+
+        let added = jeff_into_hugr_module(&qubits, &mut module, Some("copy_")).unwrap();
+        assert!(!added.is_empty());
+        module.validate().unwrap_or_else(|e| panic!("{e}"));
+    }
+
+    /// Without a prefix, a function whose normalized name clashes with the
+    /// module is not renamed.
+    #[rstest]
+    fn import_into_module_normalized_collision(entangled_calls: Jeff<'static>) {
+        let name = "__nvqpp__mlirgen__function_sample__Z6samplev";
+        let (mut module, _) = module_with_function(name);
+
+        let err = jeff_into_hugr_module(&entangled_calls, &mut module, None).unwrap_err();
+        assert!(
+            matches!(&err, JeffToHugrError::FunctionNameCollision { name: clash } if clash == name)
+        );
+
+        let added = jeff_into_hugr_module(&entangled_calls, &mut module, Some("copy_")).unwrap();
+        assert_eq!(added.len(), entangled_calls.module().functions().count());
+        module.validate().unwrap_or_else(|e| panic!("{e}"));
+    }
+
+    /// A function declared and defined in the same module is translated to a
+    /// single `FuncDefn`, whichever comes first.
+    #[rstest]
+    fn merge_redeclared_functions(redeclared_functions: Jeff<'static>) {
+        let converted =
+            jeff_to_hugr_with_config(&redeclared_functions, &JeffToHugrConfig::default()).unwrap();
+        let hugr = &converted.hugr;
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        let functions = hugr
+            .children(hugr.module_root())
+            .filter(|&n| hugr.get_optype(n).is_func_defn() || hugr.get_optype(n).is_func_decl())
+            .collect_vec();
+        assert_eq!(functions.len(), 3);
+        assert!(functions.iter().all(|&n| hugr.get_optype(n).is_func_defn()));
+
+        // Declaration before the definition, and after it.
+        let map = &converted.map;
+        let double = map.function_node(2).unwrap();
+        let quadruple = map.function_node(3).unwrap();
+        assert_eq!(map.function_node(0), Some(double));
+        assert_eq!(map.function_node(4), Some(quadruple));
+        assert_eq!(map.function_by_name("double"), Some(double));
+        assert_eq!(map.function_by_name("quadruple"), Some(quadruple));
+
+        let main = map.function_by_name("main").unwrap();
+        let targets = hugr
+            .children(main)
+            .filter(|&n| hugr.get_optype(n).is_call())
+            .map(|call| hugr.static_source(call).unwrap())
+            .sorted()
+            .collect_vec();
+        let mut expected = vec![double, double, quadruple, quadruple];
+        expected.sort();
+        assert_eq!(targets, expected);
+    }
+
+    #[rstest]
+    fn conflicting_declaration_error(conflicting_declaration: Jeff<'static>) {
+        let err = jeff_to_hugr(&conflicting_declaration).unwrap_err();
+        assert!(matches!(
+            err,
+            JeffToHugrError::ConflictingFunction { ref name } if name == "double"
+        ));
+    }
+
+    #[rstest]
+    fn typecheck_before_translation(qubits: Jeff<'static>, entangled_qs: Jeff<'static>) {
+        let config = JeffToHugrConfig::default().with_typecheck(true);
+        let hugr = jeff_to_hugr_with_config(&qubits, &config).unwrap().hugr;
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        let err = jeff_to_hugr_with_config(&entangled_qs, &config).unwrap_err();
+        assert!(matches!(err, JeffToHugrError::TypeErrors { ref errors } if errors.len() == 10));
+    }
+
+    /// The linear qubit is linked to both gates, which HUGR rejects.
+    #[rstest]
+    fn invalid_result(reused_qubit: Jeff<'static>) {
+        let err = jeff_to_hugr(&reused_qubit).unwrap_err();
+        assert!(matches!(err, JeffToHugrError::InvalidHugrProgram(_)));
+
+        let config = JeffToHugrConfig::default().with_validate(false);
+        let converted = jeff_to_hugr_with_config(&reused_qubit, &config).unwrap();
+        assert!(converted.hugr.validate().is_err());
+    }
+
+    #[rstest]
+    fn normalized_names(qubits: Jeff<'static>) {
+        let prefix = Some("my kernel::");
+        let mut hugr = Hugr::default();
+        let mut ctx = BuildContext::default();
+        let map = ctx
+            .build_into_module(qubits.module(), &mut hugr, prefix, &BTreeMap::new())
+            .unwrap();
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        // The functions are renamed, and can be found by either name.
+        for (func_id, func) in qubits.module().functions().enumerate() {
+            let node = map.function_nodes()[func_id];
+            let name = match hugr.get_optype(node) {
+                OpType::FuncDefn(defn) => defn.func_name(),
+                OpType::FuncDecl(decl) => decl.func_name(),
+                _ => panic!("not a function"),
+            };
+            assert_eq!(name, &format!("my_kernel__{}", func.name()));
+            assert_eq!(
+                hugr.get_metadata(node, metadata::ORIGINAL_NAME),
+                Some(&serde_json::json!(func.name()))
+            );
+            assert_eq!(map.function_by_name(name), Some(node));
+            assert_eq!(map.function_by_name(func.name()), Some(node));
+        }
+
+        // The strict mode rejects the first invalid name.
+        let config = JeffToHugrConfig::default().with_strict_names(true);
+        let mut ctx = BuildContext::with_config(&config);
+        let err = ctx
+            .build_into_module(qubits.module(), &mut Hugr::default(), prefix, &BTreeMap::new())
+            .unwrap_err();
+        let first = qubits.module().functions().next().unwrap();
+        let expected = format!("my kernel::{}", first.name());
+        assert!(matches!(
+            err,
+            JeffToHugrError::InvalidFunctionName { name } if name == expected
+        ));
+
+        // Valid names are kept.
+        let converted = jeff_to_hugr_with_config(&qubits, &config).unwrap();
+        let node = converted.map.function_by_name("Circuit").unwrap();
+        assert!(converted.hugr.get_metadata(node, metadata::ORIGINAL_NAME).is_none());
+    }
+
+    fn gate_names(hugr: &Hugr) -> BTreeSet<String> {
+        hugr.nodes()
+            .filter_map(|n| match hugr.get_optype(n).cast::<JeffOp>()? {
+                JeffOp::QGate { name, .. } => Some(name),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[rstest]
+    fn truncated_names(qubits: Jeff<'static>) {
+        let limits = ResourceLimits::default().with_max_string_len(1);
+        let config = JeffToHugrConfig::default().with_limits(limits);
+        let converted = jeff_to_hugr_with_config(&qubits, &config).unwrap();
+        converted.hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        // The function name and the three uses of two-letter gate names are
+        // truncated, without merging the distinct gates.
+        let lengths = converted
+            .warnings
+            .iter()
+            .filter_map(|warning| match warning {
+                ConversionWarning::TruncatedString {
+                    function, length, ..
+                } => Some((function.is_some(), *length)),
+                _ => None,
+            })
+            .collect_vec();
+        assert_eq!(lengths, [(false, 7), (true, 2), (true, 2), (true, 2)]);
+        let names = gate_names(&converted.hugr);
+        assert_eq!(names.len(), 4);
+        assert!(names.contains("H") && names.contains("X"));
+        let max_len = strings::HASH_SUFFIX_LEN;
+        assert!(names.iter().all(|name| name.len() <= max_len));
+
+        // The truncation is deterministic.
+        let again = jeff_to_hugr_with_config(&qubits, &config).unwrap();
+        assert_eq!(gate_names(&again.hugr), names);
+        assert_eq!(again.warnings, converted.warnings);
+    }
+
+    /// The `CX` gate name is patched into an invalid UTF-8 sequence.
+    #[rstest]
+    #[ignore = "the jeff reader does not expose the raw bytes of invalid UTF-8 strings yet"]
+    fn invalid_utf8_gate_name() {
+        let mut bytes = std::fs::read("test_files/qubits/qubits.jeff").unwrap();
+        let position = bytes.windows(3).position(|w| w == b"CX\0").unwrap();
+        bytes[position..position + 2].copy_from_slice(b"\xC3\x28");
+        let jeff = Jeff::read(&bytes[..]).unwrap();
+
+        let converted = jeff_to_hugr(&jeff).unwrap();
+        converted.hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        assert!(gate_names(&converted.hugr).contains("\u{FFFD}("));
+        assert!(converted.warnings.iter().any(|warning| matches!(
+            warning,
+            ConversionWarning::InvalidUtf8String { sanitized, op_index: Some(_), .. }
+                if sanitized == "\u{FFFD}("
+        )));
+    }
 }
@@ -0,0 +1,194 @@
+//! Symbolic angle parameters for variational circuits.
+//!
+//! See [`crate::JeffToHugrConfig::symbolize_angle_params`].
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use hugr::types::Type as HugrType;
+use jeff::reader::optype as jeff_optype;
+use jeff::reader::value::ValueId;
+use jeff::reader::{FunctionId, Region};
+use jeff::types::Type as JeffType;
+
+use crate::JeffToHugrError;
+use crate::types;
+
+/// The rewrite of a function whose float array parameters are only read at
+/// constant indices.
+///
+/// Each such array parameter is replaced by one float parameter per
+/// accessed element, in increasing element order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct AngleParams {
+    /// `(array_param_idx, elem_idx, new_param_idx)` for each accessed element.
+    pub entries: Vec<(usize, usize, usize)>,
+    /// The input types of the rewritten function.
+    pub input_types: Vec<HugrType>,
+    /// The new input port of each original parameter, or `None` for the
+    /// replaced arrays.
+    pub source_ports: Vec<Option<usize>>,
+    /// The new input port read by each replaced array access output.
+    pub access_outputs: BTreeMap<ValueId, usize>,
+    /// Positions in the function body of the replaced array accesses.
+    pub elided_ops: BTreeSet<usize>,
+}
+
+impl AngleParams {
+    /// Analyse the body of a function.
+    ///
+    /// Returns `None` if no float array parameter is only read at constant indices.
+    pub fn analyse(body: &Region<'_>) -> Result<Option<Self>, JeffToHugrError> {
+        let sources = body
+            .sources()
+            .map(|value| {
+                let value = value?;
+                Ok((value.id(), value.ty()))
+            })
+            .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+        let mut candidates: BTreeMap<ValueId, usize> = sources
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, ty))| matches!(ty, JeffType::FloatArray { .. }))
+            .map(|(idx, (id, _))| (*id, idx))
+            .collect();
+        if candidates.is_empty() {
+            return Ok(None);
+        }
+
+        // Arrays returned by the function cannot be replaced.
+        for value in body.targets() {
+            candidates.remove(&value?.id());
+        }
+
+        let mut int_constants = BTreeMap::new();
+        // Accessed `(array_param_idx, elem_idx)` pairs, with the access output and position.
+        let mut accesses = Vec::new();
+        for (position, op) in body.operations().enumerate() {
+            let op_type = op.op_type();
+            if let Some(value) = int_constant(&op_type) {
+                int_constants.insert(op.output(0).unwrap()?.id(), value);
+                continue;
+            }
+
+            let inputs = op
+                .inputs()
+                .map(|value| Ok(value?.id()))
+                .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+            let is_get = matches!(
+                op_type,
+                jeff_optype::OpType::FloatArrayOp(jeff_optype::FloatArrayOp::GetIndex)
+            );
+            for (port, id) in inputs.iter().enumerate() {
+                let Some(&param) = candidates.get(id) else {
+                    continue;
+                };
+                let index = inputs.get(1).and_then(|idx| int_constants.get(idx));
+                match (is_get, port, index) {
+                    (true, 0, Some(&elem)) => {
+                        let output = op.output(0).unwrap()?.id();
+                        accesses.push((param, elem as usize, output, position));
+                    }
+                    _ => {
+                        candidates.remove(id);
+                    }
+                }
+            }
+        }
+
+        let symbolized: BTreeSet<usize> = candidates.values().copied().collect();
+        accesses.retain(|(param, ..)| symbolized.contains(param));
+        if accesses.is_empty() {
+            return Ok(None);
+        }
+
+        let mut params = Self::default();
+        let mut new_ports = BTreeMap::new();
+        for (idx, (_, ty)) in sources.into_iter().enumerate() {
+            if !symbolized.contains(&idx) {
+                params.source_ports.push(Some(params.input_types.len()));
+                params.input_types.push(types::jeff_to_hugr(ty));
+                continue;
+            }
+            params.source_ports.push(None);
+            let JeffType::FloatArray { precision } = ty else {
+                unreachable!("Only float arrays are symbolized")
+            };
+            let elems: BTreeSet<usize> = accesses
+                .iter()
+                .filter(|(param, ..)| *param == idx)
+                .map(|(_, elem, ..)| *elem)
+                .collect();
+            for elem in elems {
+                let port = params.input_types.len();
+                params.entries.push((idx, elem, port));
+                new_ports.insert((idx, elem), port);
+                params
+                    .input_types
+                    .push(types::jeff_to_hugr(JeffType::Float { precision }));
+            }
+        }
+        for (param, elem, output, position) in accesses {
+            params
+                .access_outputs
+                .insert(output, new_ports[&(param, elem)]);
+            params.elided_ops.insert(position);
+        }
+        Ok(Some(params))
+    }
+}
+
+/// Returns the value of an integer constant operation.
+fn int_constant(op_type: &jeff_optype::OpType<'_>) -> Option<u64> {
+    match op_type {
+        jeff_optype::OpType::IntOp(jeff_optype::IntOp::Const8(n)) => Some(*n as u64),
+        jeff_optype::OpType::IntOp(jeff_optype::IntOp::Const16(n)) => Some(*n as u64),
+        jeff_optype::OpType::IntOp(jeff_optype::IntOp::Const32(n)) => Some(*n as u64),
+        jeff_optype::OpType::IntOp(jeff_optype::IntOp::Const64(n)) => Some(*n),
+        _ => None,
+    }
+}
+
+/// Collect the ids of the functions called anywhere in a module.
+pub(crate) fn called_functions(
+    module: &jeff::reader::Module<'_>,
+) -> Result<BTreeSet<FunctionId>, JeffToHugrError> {
+    let mut called = BTreeSet::new();
+    for func in module.functions() {
+        if let jeff::reader::Function::Definition(def) = func {
+            collect_calls(&def.body(), &mut called)?;
+        }
+    }
+    Ok(called)
+}
+
+/// Collect the ids of the functions called in a region and its nested regions.
+pub(crate) fn collect_calls(
+    region: &Region<'_>,
+    called: &mut BTreeSet<FunctionId>,
+) -> Result<(), JeffToHugrError> {
+    for op in region.operations() {
+        match op.op_type() {
+            jeff_optype::OpType::FuncOp(call) => {
+                called.insert(call.func_idx as FunctionId);
+            }
+            jeff_optype::OpType::ControlFlowOp(cf) => match cf {
+                jeff_optype::ControlFlowOp::Switch(switch_op) => {
+                    for idx in 0..switch_op.branch_count() {
+                        collect_calls(&switch_op.branch(idx), called)?;
+                    }
+                    if let Some(default_branch) = switch_op.default_branch() {
+                        collect_calls(&default_branch, called)?;
+                    }
+                }
+                jeff_optype::ControlFlowOp::DoWhile { body, condition }
+                | jeff_optype::ControlFlowOp::While { body, condition } => {
+                    collect_calls(&body, called)?;
+                    collect_calls(&condition, called)?;
+                }
+                jeff_optype::ControlFlowOp::For { region } => collect_calls(&region, called)?,
+            },
+            _ => {}
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,328 @@
+//! Conversion of the translated `int(1)` arrays to a [`BitArrays`] representation.
+
+use std::sync::Arc;
+
+use hugr::algorithms::ComposablePass;
+use hugr::algorithms::replace_types::{NodeTemplate, ReplaceTypes, ReplaceTypesError};
+use hugr::builder::{
+    BuildError, DFGBuilder, Dataflow, DataflowHugr, DataflowSubContainer, SubContainer,
+};
+use hugr::extension::prelude::{UnwrapBuilder, bool_t, option_type, sum_with_error};
+use hugr::extension::simple_op::{HasConcrete, MakeOpDef};
+use hugr::ops::Value;
+use hugr::std_extensions::arithmetic::conversions::ConvertOpDef;
+use hugr::std_extensions::arithmetic::int_ops::IntOpDef;
+use hugr::std_extensions::arithmetic::int_types::{ConstInt, int_type};
+use hugr::std_extensions::collections::list::{ListOp, ListValue, list_type};
+use hugr::types::{Signature, Type};
+use hugr::{Hugr, Wire, type_row};
+
+use super::BitArrays;
+use crate::extension::{ConstIntReg, JEFF_EXTENSION, JeffOp, JeffOpDef, intreg_custom_type};
+use crate::passes::{lower_int_array_reverses, lower_register_measures};
+
+/// Log width of the `int(32)` indices and lengths.
+const INDEX_LOG_WIDTH: u8 = 5;
+/// Log width of the `int(64)` values converted to and from `usize`.
+const USIZE_LOG_WIDTH: u8 = 6;
+
+/// Convert the `int(1)` arrays of a HUGR to the `repr` representation.
+///
+/// The whole region under the entrypoint is converted.
+pub(super) fn convert_bit_arrays(
+    hugr: &mut Hugr,
+    repr: BitArrays,
+) -> Result<(), ReplaceTypesError> {
+    if repr == BitArrays::Opaque {
+        return Ok(());
+    }
+    // These operations produce bit arrays without taking their size as a
+    // type argument, so they cannot be replaced directly.
+    lower_register_measures(hugr);
+    lower_int_array_reverses(hugr);
+    bool_list_replacer().run(hugr)?;
+    Ok(())
+}
+
+/// The HUGR type of the bit arrays in [`BitArrays::BoolCollection`] form.
+fn bool_list_type() -> Type {
+    list_type(bool_t())
+}
+
+/// A pass replacing the `int(1)` arrays and their operations by lists of booleans.
+fn bool_list_replacer() -> ReplaceTypes {
+    let bits_t = intreg_custom_type(&Arc::downgrade(&JEFF_EXTENSION), 1);
+    let mut replacer = ReplaceTypes::default();
+    replacer.replace_type(bits_t.clone(), bool_list_type());
+    replacer.replace_consts(bits_t, |value, _| {
+        let array = value
+            .value()
+            .downcast_ref::<ConstIntReg>()
+            .expect("Bit array constants are ConstIntRegs");
+        let bits = array.values().map(|v| Value::from_bool(v != 0));
+        Ok(ListValue::new(bool_t(), bits).into())
+    });
+
+    for def in [
+        JeffOpDef::IntArrayCreate,
+        JeffOpDef::IntArrayLength,
+        JeffOpDef::IntArrayGet,
+        JeffOpDef::IntArraySet,
+        JeffOpDef::IntArrayZero,
+    ] {
+        let op_def = JEFF_EXTENSION.get_op(&def.opdef_id()).unwrap();
+        replacer.replace_parametrized_op(op_def, move |args| {
+            let dfg = bool_list_op(&def.instantiate(args).ok()?)?
+                .expect("Failed to build the list operation");
+            Some(NodeTemplate::CompoundOp(Box::new(dfg)))
+        });
+    }
+    replacer
+}
+
+/// The list operations replacing a bit array operation, or `None` for
+/// operations on other integer arrays.
+fn bool_list_op(op: &JeffOp) -> Option<Result<Hugr, BuildError>> {
+    let dfg = match *op {
+        JeffOp::IntArrayCreate { bits: 1, inputs } => list_create(inputs),
+        JeffOp::IntArrayLength { bits: 1 } => list_length(),
+        JeffOp::IntArrayGet { bits: 1 } => list_get(),
+        JeffOp::IntArraySet { bits: 1 } => list_set(),
+        JeffOp::IntArrayZero { bits: 1 } => list_zero(),
+        _ => return None,
+    };
+    Some(dfg)
+}
+
+/// Push each input to an empty list.
+fn list_create(inputs: usize) -> Result<Hugr, BuildError> {
+    let mut builder = DFGBuilder::new(Signature::new(
+        vec![bool_t(); inputs],
+        vec![bool_list_type()],
+    ))?;
+    let bits = builder.input_wires().collect::<Vec<_>>();
+    let mut list = builder.add_load_value(ListValue::new_empty(bool_t()));
+    for bit in bits {
+        list = builder
+            .add_dataflow_op(ListOp::push.with_type(bool_t()), [list, bit])?
+            .out_wire(0);
+    }
+    builder.finish_hugr_with_outputs([list])
+}
+
+/// The list length, as an `int(32)`.
+fn list_length() -> Result<Hugr, BuildError> {
+    let index_t = int_type(INDEX_LOG_WIDTH);
+    let mut builder = DFGBuilder::new(Signature::new(vec![bool_list_type()], vec![index_t]))?;
+    let [list] = builder.input_wires_arr();
+    let len = builder
+        .add_dataflow_op(ListOp::length.with_type(bool_t()), [list])?
+        .out_wire(1);
+    let len = usize_to_index(&mut builder, len)?;
+    builder.finish_hugr_with_outputs([len])
+}
+
+/// Read a list element, panicking on out-of-bounds indices.
+///
+/// Bounds are already checked by the translation, according to the
+/// [`ArrayBounds`](super::ArrayBounds) configuration.
+fn list_get() -> Result<Hugr, BuildError> {
+    let index_t = int_type(INDEX_LOG_WIDTH);
+    let mut builder =
+        DFGBuilder::new(Signature::new(vec![bool_list_type(), index_t], vec![bool_t()]))?;
+    let [list, index] = builder.input_wires_arr();
+    let index = index_to_usize(&mut builder, index)?;
+    let element = builder
+        .add_dataflow_op(ListOp::get.with_type(bool_t()), [list, index])?
+        .out_wire(0);
+    let [bit] = builder.build_unwrap_sum(1, option_type(bool_t()), element)?;
+    builder.finish_hugr_with_outputs([bit])
+}
+
+/// Write a list element, discarding the previous value.
+fn list_set() -> Result<Hugr, BuildError> {
+    let index_t = int_type(INDEX_LOG_WIDTH);
+    let mut builder = DFGBuilder::new(Signature::new(
+        vec![bool_list_type(), index_t, bool_t()],
+        vec![bool_list_type()],
+    ))?;
+    let [list, index, bit] = builder.input_wires_arr();
+    let index = index_to_usize(&mut builder, index)?;
+    let list = builder
+        .add_dataflow_op(ListOp::set.with_type(bool_t()), [list, index, bit])?
+        .out_wire(0);
+    builder.finish_hugr_with_outputs([list])
+}
+
+/// Push `false` to an empty list as many times as the input length.
+fn list_zero() -> Result<Hugr, BuildError> {
+    let index_t = int_type(INDEX_LOG_WIDTH);
+    let list_t = bool_list_type();
+    let mut builder =
+        DFGBuilder::new(Signature::new(vec![index_t.clone()], vec![list_t.clone()]))?;
+    let [len] = builder.input_wires_arr();
+    let list = builder.add_load_value(ListValue::new_empty(bool_t()));
+    let zero = builder.add_load_value(ConstInt::new_u(INDEX_LOG_WIDTH, 0).unwrap());
+
+    let mut tail_loop = builder.tail_loop_builder(
+        [],
+        [
+            (list_t.clone(), list),
+            (index_t.clone(), zero),
+            (index_t.clone(), len),
+        ],
+        type_row![],
+    )?;
+    let [list, index, len] = tail_loop.input_wires_arr();
+
+    // Guard the first iteration against empty lists.
+    let in_bounds = tail_loop
+        .add_dataflow_op(IntOpDef::ilt_u.with_log_width(INDEX_LOG_WIDTH), [index, len])?
+        .out_wire(0);
+    let mut cond = tail_loop.conditional_builder(
+        ([type_row![], type_row![]], in_bounds),
+        [
+            (list_t.clone(), list),
+            (index_t.clone(), index),
+            (index_t.clone(), len),
+        ],
+        vec![list_t, index_t.clone(), index_t].into(),
+    )?;
+    let done = cond.case_builder(0)?;
+    let inputs = done.input_wires();
+    done.finish_with_outputs(inputs)?;
+
+    let mut push = cond.case_builder(1)?;
+    let [list, index, len] = push.input_wires_arr();
+    let bit = push.add_load_value(Value::false_val());
+    let list = push
+        .add_dataflow_op(ListOp::push.with_type(bool_t()), [list, bit])?
+        .out_wire(0);
+    let one = push.add_load_value(ConstInt::new_u(INDEX_LOG_WIDTH, 1).unwrap());
+    let next = push
+        .add_dataflow_op(IntOpDef::iadd.with_log_width(INDEX_LOG_WIDTH), [index, one])?
+        .out_wire(0);
+    push.finish_with_outputs([list, next, len])?;
+    let [list, index, len] = cond.finish_sub_container()?.outputs_arr();
+
+    let done = tail_loop
+        .add_dataflow_op(IntOpDef::ige_u.with_log_width(INDEX_LOG_WIDTH), [index, len])?
+        .out_wire(0);
+    let [list, _, _] = tail_loop
+        .finish_with_outputs(done, [list, index, len])?
+        .outputs_arr();
+    builder.finish_hugr_with_outputs([list])
+}
+
+/// Convert an `int(32)` index to a `usize`.
+fn index_to_usize(builder: &mut impl Dataflow, index: Wire) -> Result<Wire, BuildError> {
+    let widened = builder
+        .add_dataflow_op(
+            IntOpDef::iwiden_u.with_two_log_widths(INDEX_LOG_WIDTH, USIZE_LOG_WIDTH),
+            [index],
+        )?
+        .out_wire(0);
+    Ok(builder
+        .add_dataflow_op(ConvertOpDef::itousize.without_log_width(), [widened])?
+        .out_wire(0))
+}
+
+/// Convert a `usize` to an `int(32)`, panicking if it does not fit.
+fn usize_to_index(builder: &mut impl Dataflow, value: Wire) -> Result<Wire, BuildError> {
+    let wide = builder
+        .add_dataflow_op(ConvertOpDef::ifromusize.without_log_width(), [value])?
+        .out_wire(0);
+    let narrowed = builder
+        .add_dataflow_op(
+            IntOpDef::inarrow_u.with_two_log_widths(USIZE_LOG_WIDTH, INDEX_LOG_WIDTH),
+            [wide],
+        )?
+        .out_wire(0);
+    let [index] =
+        builder.build_unwrap_sum(1, sum_with_error(int_type(INDEX_LOG_WIDTH)), narrowed)?;
+    Ok(index)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::extension::{intreg_type, qureg_type};
+    use hugr::HugrView;
+    use hugr::builder::{HugrBuilder, ModuleBuilder};
+    use rstest::rstest;
+
+    /// A function measuring a register and combining the outcomes with the
+    /// other bit array operations.
+    fn bits_module() -> Hugr {
+        let bits_t = intreg_type(1);
+        let index_t = int_type(INDEX_LOG_WIDTH);
+        let sig = Signature::new(vec![qureg_type(), index_t], vec![bits_t.clone(), bool_t()]);
+        let mut builder = ModuleBuilder::new();
+        let mut func = builder.define_function("main", sig).unwrap();
+        let [qureg, index] = func.input_wires_arr();
+
+        let op = |op: JeffOp| op.into_extension_op();
+        let bits = func
+            .add_dataflow_op(op(JeffOp::QuregMeasure), [qureg])
+            .unwrap()
+            .out_wire(0);
+        let bits = func
+            .add_dataflow_op(op(JeffOp::IntArrayReverse { bits: 1 }), [bits])
+            .unwrap()
+            .out_wire(0);
+        let bit = func
+            .add_dataflow_op(op(JeffOp::IntArrayGet { bits: 1 }), [bits, index])
+            .unwrap()
+            .out_wire(0);
+        let constant = func.add_load_value(ConstIntReg::new([1, 0, 1], 1));
+        let len = func
+            .add_dataflow_op(op(JeffOp::IntArrayLength { bits: 1 }), [constant])
+            .unwrap()
+            .out_wire(0);
+        let zeros = func
+            .add_dataflow_op(op(JeffOp::IntArrayZero { bits: 1 }), [len])
+            .unwrap()
+            .out_wire(0);
+        let zeros = func
+            .add_dataflow_op(op(JeffOp::IntArraySet { bits: 1 }), [zeros, index, bit])
+            .unwrap()
+            .out_wire(0);
+        let pair = func
+            .add_dataflow_op(op(JeffOp::IntArrayCreate { bits: 1, inputs: 2 }), [bit, bit])
+            .unwrap()
+            .out_wire(0);
+        let first = func
+            .add_dataflow_op(op(JeffOp::IntArrayGet { bits: 1 }), [pair, index])
+            .unwrap()
+            .out_wire(0);
+        func.finish_with_outputs([zeros, first]).unwrap();
+        builder.finish_hugr().unwrap()
+    }
+
+    /// Whether any node of the HUGR uses an `int(1)` array.
+    fn has_bit_arrays(hugr: &Hugr) -> bool {
+        let bits_t = intreg_type(1);
+        hugr.nodes().any(|node| {
+            hugr.get_optype(node)
+                .dataflow_signature()
+                .is_some_and(|sig| {
+                    sig.input_types()
+                        .iter()
+                        .chain(sig.output_types())
+                        .any(|ty| *ty == bits_t)
+                })
+        })
+    }
+
+    #[rstest]
+    #[case::opaque(BitArrays::Opaque)]
+    #[case::bool_collection(BitArrays::BoolCollection)]
+    fn convert(#[case] repr: BitArrays) {
+        let mut hugr = bits_module();
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        convert_bit_arrays(&mut hugr, repr).unwrap();
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(has_bit_arrays(&hugr), repr == BitArrays::Opaque);
+    }
+}
@@ -0,0 +1,167 @@
+//! Conversion of the bit arrays crossing function boundaries to a [`BitOrder`].
+
+use std::collections::BTreeSet;
+
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::ops::OpType;
+use hugr::{Hugr, HugrView, IncomingPort, Node, OutgoingPort};
+use itertools::Itertools;
+
+use super::BitOrder;
+use crate::extension::{JeffOp, intreg_type};
+
+/// Insert [`JeffOp::IntArrayReverse`] operations so that the bit arrays in
+/// the signatures of `functions` follow `order`.
+///
+/// The boundaries of the function definitions in `translated` are reversed,
+/// as well as the calls they make to any of `functions`. Function bodies keep
+/// the _jeff_ least significant bit first order.
+pub(super) fn reverse_boundary_bits(
+    hugr: &mut Hugr,
+    order: BitOrder,
+    functions: &[Node],
+    translated: &[Node],
+) {
+    if order == BitOrder::LsbFirst {
+        return;
+    }
+    let functions: BTreeSet<Node> = functions.iter().copied().collect();
+
+    for &func in translated {
+        let Some([input, output]) = hugr.get_io(func) else {
+            continue;
+        };
+        for port in bit_ports(hugr.get_optype(input), |sig| sig.output_types()) {
+            reverse_outgoing(hugr, input, port.into());
+        }
+        for port in bit_ports(hugr.get_optype(output), |sig| sig.input_types()) {
+            reverse_incoming(hugr, output, port.into());
+        }
+
+        let calls = hugr
+            .descendants(func)
+            .filter(|&node| hugr.get_optype(node).is_call())
+            .filter(|&call| {
+                hugr.static_source(call)
+                    .is_some_and(|target| functions.contains(&target))
+            })
+            .collect_vec();
+        for call in calls {
+            let optype = hugr.get_optype(call).clone();
+            for port in bit_ports(&optype, |sig| sig.input_types()) {
+                reverse_incoming(hugr, call, port.into());
+            }
+            for port in bit_ports(&optype, |sig| sig.output_types()) {
+                reverse_outgoing(hugr, call, port.into());
+            }
+        }
+    }
+}
+
+/// Returns the indices of the `int(1)` arrays among the inputs or outputs of
+/// an operation.
+fn bit_ports(
+    optype: &OpType,
+    types: impl Fn(&hugr::types::Signature) -> &[hugr::types::Type],
+) -> Vec<usize> {
+    let Some(sig) = optype.dataflow_signature() else {
+        return vec![];
+    };
+    let bits_t = intreg_type(1);
+    types(sig.as_ref())
+        .iter()
+        .positions(|ty| *ty == bits_t)
+        .collect()
+}
+
+/// Insert a reversal between an output port and all its targets.
+fn reverse_outgoing(hugr: &mut Hugr, node: Node, port: OutgoingPort) {
+    let targets = hugr.linked_inputs(node, port).collect_vec();
+    if targets.is_empty() {
+        return;
+    }
+    hugr.disconnect(node, port);
+    let reverse = add_reverse(hugr, targets[0].0);
+    hugr.connect(node, port, reverse, 0);
+    for (target, target_port) in targets {
+        hugr.connect(reverse, 0, target, target_port);
+    }
+}
+
+/// Insert a reversal between an input port and its source.
+fn reverse_incoming(hugr: &mut Hugr, node: Node, port: IncomingPort) {
+    let Some((source, source_port)) = hugr.single_linked_output(node, port) else {
+        return;
+    };
+    hugr.disconnect(node, port);
+    let reverse = add_reverse(hugr, node);
+    hugr.connect(source, source_port, reverse, 0);
+    hugr.connect(reverse, 0, node, port);
+}
+
+/// Add a bit array reversal next to `sibling`.
+fn add_reverse(hugr: &mut Hugr, sibling: Node) -> Node {
+    let parent = hugr.get_parent(sibling).unwrap();
+    let op = JeffOp::IntArrayReverse { bits: 1 }.into_extension_op();
+    hugr.add_node_with_parent(parent, op)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hugr::builder::{Dataflow, DataflowSubContainer, HugrBuilder, ModuleBuilder};
+    use hugr::ops::handle::NodeHandle;
+    use hugr::types::Signature;
+    use rstest::rstest;
+
+    /// A module with an identity function on bit arrays, and a caller.
+    ///
+    /// Returns the module and the function nodes, callee first.
+    fn bits_module() -> (Hugr, [Node; 2]) {
+        let sig = Signature::new_endo(vec![intreg_type(1)]);
+        let mut builder = ModuleBuilder::new();
+        let id = builder.define_function("id", sig.clone()).unwrap();
+        let inputs = id.input_wires();
+        let id = id.finish_with_outputs(inputs).unwrap();
+
+        let mut main = builder.define_function("main", sig).unwrap();
+        let [bits] = main.input_wires_arr();
+        let call = main.call(id.handle(), &[], [bits]).unwrap();
+        let main = main.finish_with_outputs(call.outputs()).unwrap();
+
+        let functions = [id.node(), main.node()];
+        (builder.finish_hugr().unwrap(), functions)
+    }
+
+    fn count_reverses(hugr: &Hugr) -> usize {
+        hugr.nodes()
+            .filter(|&n| {
+                matches!(
+                    hugr.get_optype(n).cast::<JeffOp>(),
+                    Some(JeffOp::IntArrayReverse { .. })
+                )
+            })
+            .count()
+    }
+
+    #[rstest]
+    #[case::lsb_first(BitOrder::LsbFirst, 0)]
+    #[case::msb_first(BitOrder::MsbFirst, 6)]
+    fn reverse_boundaries(#[case] order: BitOrder, #[case] expected: usize) {
+        let (mut hugr, functions) = bits_module();
+
+        reverse_boundary_bits(&mut hugr, order, &functions, &functions);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(count_reverses(&hugr), expected);
+    }
+
+    #[rstest]
+    fn reverse_only_translated() {
+        let (mut hugr, [id, main]) = bits_module();
+
+        // Only the caller is translated: its boundary and its call are reversed.
+        reverse_boundary_bits(&mut hugr, BitOrder::MsbFirst, &[id, main], &[main]);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(count_reverses(&hugr), 4);
+    }
+}
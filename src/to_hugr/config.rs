@@ -0,0 +1,1026 @@
+//! Configuration options for the _jeff_ to HUGR translation.
+
+use std::collections::BTreeSet;
+use std::f64::consts::PI;
+use std::path::PathBuf;
+
+use derive_more::Display;
+use serde::Serialize;
+
+use crate::JeffToHugrError;
+use crate::analysis::GateSet;
+use crate::passes::{FreeZeroRepair, ResultConvention};
+use crate::to_hugr::hooks::ConversionHooks;
+use crate::to_hugr::progress::ProgressHook;
+use crate::to_hugr::target::TargetInfo;
+use crate::to_hugr::type_codec::TypeCodecs;
+
+/// Configuration for the _jeff_ to HUGR translation.
+///
+/// The default configuration matches the behaviour of [`crate::jeff_to_hugr`].
+///
+/// The configuration is serialized into the [`crate::provenance::Provenance`]
+/// of the translated HUGRs, without the hooks, the type codecs and the
+/// progress callback.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct JeffToHugrConfig {
+    /// Record the originating _jeff_ operation of each translated node.
+    ///
+    /// When enabled, nodes that directly translate a _jeff_ operation get a
+    /// [`crate::metadata::OP_INDEX`] metadata entry, and the returned
+    /// [`crate::JeffHugrMap`] can be queried with
+    /// [`crate::JeffHugrMap::node_origin`].
+    pub record_provenance: bool,
+    /// Recognize constant gate angles that are exact fractions of π.
+    ///
+    /// When set, rotation parameters defined by such constants are emitted
+    /// as exact rotation constants instead of a runtime radians to
+    /// half-turns conversion. Set to `None` to disable the recognition.
+    pub exact_angles: Option<ExactAngleConfig>,
+    /// Translation of the constant gate angles that are NaN.
+    ///
+    /// Constant gate angles that are infinite always fail the translation,
+    /// with [`JeffToHugrError::NonFiniteAngle`].
+    pub nan_angles: NanAngles,
+    /// Behaviour of indexed array and register accesses on out-of-bounds indices.
+    pub array_bounds: ArrayBounds,
+    /// Behaviour of the integer additions, subtractions and multiplications
+    /// on signed overflow.
+    pub int_overflow: IntOverflow,
+    /// Import destructive _jeff_ measurements as a non-destructive `Measure`
+    /// followed by an explicit `QFree`, instead of a single `MeasureFree`.
+    ///
+    /// The pair is fused back into a destructive measurement by
+    /// [`crate::fuse_destructive_measures`].
+    pub split_measures: bool,
+    /// Replace float array parameters that are only read at constant indices
+    /// by one float parameter per read element.
+    ///
+    /// This suits variational circuits, whose angles are best optimized as
+    /// distinct parameters. The rewritten functions are annotated with
+    /// [`crate::metadata::ANGLE_PARAMS`]. Functions called within the module
+    /// are left unchanged, with a
+    /// [`crate::ConversionWarning::AngleParamsNotSymbolized`].
+    pub symbolize_angle_params: bool,
+    /// Translate the calls to defined functions by inlining the callee body
+    /// in the caller, so the function bodies contain no `Call` to them.
+    ///
+    /// Recursive functions cannot be inlined and are rejected with
+    /// [`JeffToHugrError::RecursiveInline`]. The depth of the nested inlined
+    /// calls is bounded by [`ResourceLimits::max_inline_depth`]. Calls to
+    /// declared functions are still emitted as `Call`s, unless
+    /// [`JeffToHugrConfig::strict_inline`] is set.
+    pub inline_calls: bool,
+    /// With [`JeffToHugrConfig::inline_calls`], reject the calls to declared
+    /// functions with [`JeffToHugrError::UninlinableCall`] instead of
+    /// emitting a `Call`.
+    ///
+    /// Calls to intrinsics, such as the runtime queries, are still
+    /// translated as extension operations.
+    pub strict_inline: bool,
+    /// Merge the function definitions whose body is identical to that of an
+    /// earlier definition, translating the body only once.
+    ///
+    /// The merged functions are listed in the
+    /// [`crate::metadata::DEDUPLICATED`] metadata of the module root, and by
+    /// [`crate::JeffHugrMap::canonical_function`]. Functions are not merged
+    /// when [`JeffToHugrConfig::inline_calls`] is set.
+    pub dedup_functions: FunctionDedup,
+    /// Reject programs with several modules with
+    /// [`JeffToHugrError::AmbiguousModule`], instead of translating their
+    /// first module.
+    ///
+    /// Only applies to [`crate::jeff_to_hugr_with_config`]. A module can be
+    /// selected explicitly with [`crate::jeff_to_hugr_module`].
+    pub strict_modules: bool,
+    /// Reject the function names that are not valid HUGR names with
+    /// [`JeffToHugrError::InvalidFunctionName`], instead of normalizing them.
+    ///
+    /// Valid names are made of ASCII letters, digits and `_`, do not start
+    /// with a digit, and have at most 128 characters. Calls are connected by
+    /// function index, so renaming a function does not change the program.
+    pub strict_names: bool,
+    /// Check the types of the values of the program with
+    /// [`crate::analysis::check_types`] before translating it, and reject
+    /// inconsistent programs with [`JeffToHugrError::TypeErrors`].
+    ///
+    /// Without the check, an inconsistent program fails with the error of
+    /// the first operation that cannot be translated, or produces an invalid
+    /// HUGR.
+    pub typecheck: bool,
+    /// Limits on the work done by the translation, for untrusted inputs.
+    pub limits: ResourceLimits,
+    /// User-defined translations, tried before the built-in translation of
+    /// each operation.
+    #[serde(skip)]
+    pub hooks: ConversionHooks,
+    /// User-defined HUGR types for the inputs and outputs of the translated
+    /// functions, tried before the built-in type translation.
+    ///
+    /// The claimed values are converted at the function boundaries, so the
+    /// function bodies are unchanged.
+    #[serde(skip)]
+    pub type_codecs: TypeCodecs,
+    /// Callback receiving the progress of the translation, which can cancel it.
+    ///
+    /// It is called when each function starts, every
+    /// [`ProgressHook::interval`] operations within a function, and before
+    /// the calls are connected and the result validated. The progress
+    /// reports do not change the translated HUGR.
+    #[serde(skip)]
+    pub progress: Option<ProgressHook>,
+    /// Order of the bits in the `int(1)` arrays of the translated function
+    /// signatures.
+    ///
+    /// The chosen order is recorded in the [`crate::metadata::BIT_ORDER`]
+    /// metadata of the module root.
+    pub bit_order: BitOrder,
+    /// Representation of the _jeff_ `int(1)` arrays in the translated HUGR.
+    ///
+    /// The conversion replaces the bit array operations after the
+    /// translation, so the replaced nodes have no recorded provenance.
+    pub bit_arrays_as: BitArrays,
+    /// Representation of the _jeff_ qubit registers in the translated HUGR.
+    ///
+    /// The conversion replaces the register operations after the
+    /// translation. The new nodes keep the [`crate::metadata::OP_INDEX`] of
+    /// the operations they replace.
+    pub qureg_lowering: QuregLowering,
+    /// Functions exported with opaque handles in place of the _jeff_
+    /// registers in their signature.
+    pub handle_abi: HandleAbi,
+    /// Annotate the translated function definitions with their circuit
+    /// depth, in the [`crate::metadata::DEPTH`] and
+    /// [`crate::metadata::DEPTH_2Q`] metadata entries.
+    ///
+    /// The depths are computed on the _jeff_ program with
+    /// [`crate::analysis::circuit_depth`].
+    pub annotate_depths: bool,
+    /// Encoding of the operations marked with the
+    /// [`crate::metadata::PROTECTED`] metadata during the translation, by
+    /// the conversion [`JeffToHugrConfig::hooks`].
+    ///
+    /// The _jeff_ reader does not expose operation attributes, so the
+    /// protected operations can only be marked by a hook. See
+    /// [`crate::passes::protect_ops`].
+    pub protect: Protection,
+    /// Name the qubit, register and measurement wires of the translated
+    /// functions, in the [`crate::metadata::WIRE_NAMES`] metadata of the
+    /// nodes producing them.
+    ///
+    /// The names are also available from
+    /// [`crate::JeffHugrMap::wire_names`], see [`crate::WireNames`].
+    pub name_wires: bool,
+    /// Wrap each straight-line quantum segment of the _jeff_ regions into a
+    /// `DFG` container marked with [`crate::metadata::CIRCUIT_BOX`], so that
+    /// the segments can be optimized as separate circuits.
+    ///
+    /// A segment is a maximal run of qubit operations of a region, ended by
+    /// the control-flow operations and the function calls. Only the nodes
+    /// acting on qubits are moved into the containers, the classical
+    /// operations between them stay in the region. A segment is split where
+    /// such an operation reads a value of the segment and feeds it back.
+    /// See [`crate::passes::box_circuit_segments`].
+    pub circuit_boxes: bool,
+    /// Fuse the runs of single-qubit gates on a qubit wire into at most three
+    /// rotations, `Rz·Rx·Rz`, implementing the same unitary up to a global
+    /// phase.
+    ///
+    /// Runs end at multi-qubit gates, measurements, control flow and region
+    /// boundaries. Controlled gates, opaque gates and protected operations
+    /// are not fused. See [`crate::passes::fuse_single_qubit_gates`].
+    pub fuse_single_qubit_gates: bool,
+    /// Return the measurement outcomes of the functions with a canonical
+    /// result convention, ordered by the provenance of the measured qubits.
+    ///
+    /// Only the functions that are not called are rewritten. See
+    /// [`crate::passes::canonicalize_results`].
+    pub result_convention: Option<ResultConvention>,
+    /// Annotate the functions taking registers with the lengths of the
+    /// registers passed by all their calls, so that the lengths are known
+    /// inside the function bodies.
+    ///
+    /// See [`crate::passes::propagate_register_sizes`].
+    pub propagate_register_sizes: bool,
+    /// Verify that the qubits released by _jeff_ `FreeZero` operations are
+    /// in the |0⟩ state, and repair the unverified ones.
+    ///
+    /// The verification is structural, see
+    /// [`crate::analysis::check_free_zero`]. Downgraded operations are
+    /// reported with [`crate::ConversionWarning::FreeZeroDowngraded`], and
+    /// with [`FreeZeroRepair::Error`] the translation fails with
+    /// [`JeffToHugrError::UnverifiedFreeZero`]. See
+    /// [`crate::passes::repair_free_zero`].
+    pub free_zero_repair: Option<FreeZeroRepair>,
+    /// Check that the translated HUGR only references the extensions of
+    /// [`crate::capabilities::conversion_extension_allowlist`].
+    ///
+    /// When set, the translation fails with
+    /// [`JeffToHugrError::DisallowedExtension`] on the first node outside of
+    /// the allow-list. The output of the conversion hooks and of the type
+    /// codecs is checked too.
+    pub check_extensions: bool,
+    /// Target gate set the translated HUGR must conform to.
+    ///
+    /// When set, the translation fails with
+    /// [`JeffToHugrError::GateSetViolations`] if the result contains
+    /// operations outside of the gate set, see
+    /// [`crate::analysis::check_gate_set`].
+    pub gate_set: Option<GateSet>,
+    /// The device the program is compiled for.
+    ///
+    /// _jeff_ modules do not describe their target, so it is only known
+    /// from this configuration.
+    ///
+    /// The target is stored in the [`crate::metadata::TARGET`] entry of the
+    /// module root. The translation emits a
+    /// [`crate::ConversionWarning::TargetQubitsExceeded`] for the functions
+    /// that may use more qubits than the device has.
+    pub target: Option<TargetInfo>,
+    /// Validate the translated HUGR.
+    ///
+    /// When set, the translation fails with
+    /// [`JeffToHugrError::InvalidHugrProgram`] if the result is not a valid
+    /// HUGR. Unset it to inspect the possibly invalid result while debugging
+    /// the translation.
+    pub validate: bool,
+    /// Directory to write the intermediate translation state to when the
+    /// translation fails.
+    ///
+    /// On failure, the partially built HUGR, the value edges left to connect
+    /// and the error are written to fixed file names in the directory,
+    /// overwriting any previous snapshot. If the snapshot cannot be written,
+    /// the error is wrapped in a [`JeffToHugrError::SnapshotFailed`].
+    pub debug_snapshots: Option<PathBuf>,
+    /// Record the steps of the translation in [`crate::ConvertedHugr::trace`].
+    ///
+    /// The trace lists the operations translated, the ports registered to
+    /// each _jeff_ value and the hyperedges connected, along with the
+    /// changes to the HUGR, which can be replayed with
+    /// [`crate::ConversionTrace::hugr_at`]. Recording is slow on large
+    /// programs, and is meant for debugging the translation.
+    pub record_trace: bool,
+}
+
+impl Default for JeffToHugrConfig {
+    fn default() -> Self {
+        Self {
+            record_provenance: false,
+            exact_angles: Some(ExactAngleConfig::default()),
+            nan_angles: NanAngles::default(),
+            array_bounds: ArrayBounds::default(),
+            int_overflow: IntOverflow::default(),
+            split_measures: false,
+            symbolize_angle_params: false,
+            inline_calls: false,
+            strict_inline: false,
+            dedup_functions: FunctionDedup::default(),
+            strict_modules: false,
+            strict_names: false,
+            typecheck: false,
+            limits: ResourceLimits::default(),
+            hooks: ConversionHooks::default(),
+            type_codecs: TypeCodecs::default(),
+            progress: None,
+            bit_order: BitOrder::default(),
+            bit_arrays_as: BitArrays::default(),
+            qureg_lowering: QuregLowering::default(),
+            handle_abi: HandleAbi::default(),
+            annotate_depths: false,
+            protect: Protection::default(),
+            name_wires: false,
+            circuit_boxes: false,
+            fuse_single_qubit_gates: false,
+            result_convention: None,
+            propagate_register_sizes: false,
+            free_zero_repair: None,
+            check_extensions: false,
+            gate_set: None,
+            target: None,
+            validate: true,
+            debug_snapshots: None,
+            record_trace: false,
+        }
+    }
+}
+
+impl JeffToHugrConfig {
+    /// Settings for the programs produced by Catalyst.
+    ///
+    /// Starts from the default configuration and sets:
+    /// - [`JeffToHugrConfig::bit_arrays_as`] to [`BitArrays::BoolCollection`],
+    ///   so the measurement results can be consumed without the _jeff_
+    ///   extension.
+    pub fn catalyst() -> Self {
+        Self::default().with_bit_arrays_as(BitArrays::BoolCollection)
+    }
+
+    /// Settings for lossless round-trips between _jeff_ and HUGR, where the
+    /// translated operations match the _jeff_ ones.
+    ///
+    /// Starts from the default configuration and sets:
+    /// - [`JeffToHugrConfig::exact_angles`] to `None`, keeping the angles as
+    ///   floats,
+    /// - [`JeffToHugrConfig::strict_modules`] to `true`,
+    /// - [`JeffToHugrConfig::record_provenance`] to `true`.
+    ///
+    /// The other options keep their default, operation preserving, values:
+    /// no inlining, no angle parameter symbolization, destructive
+    /// measurements, unchecked array accesses, and opaque least significant
+    /// bit first bit arrays.
+    pub fn strict_roundtrip() -> Self {
+        Self::default()
+            .with_exact_angles(None)
+            .with_strict_modules(true)
+            .with_record_provenance(true)
+    }
+
+    /// Settings for programs sent to a hardware backend supporting a gate set.
+    ///
+    /// Starts from the default configuration and sets:
+    /// - [`JeffToHugrConfig::gate_set`] to `gate_set`,
+    /// - [`JeffToHugrConfig::array_bounds`] to [`ArrayBounds::Panic`],
+    /// - [`JeffToHugrConfig::bit_arrays_as`] to [`BitArrays::BoolCollection`],
+    ///   lowering the register measurements and the array reversals.
+    pub fn hardware(gate_set: GateSet) -> Self {
+        Self::default()
+            .with_gate_set(gate_set)
+            .with_array_bounds(ArrayBounds::Panic)
+            .with_bit_arrays_as(BitArrays::BoolCollection)
+    }
+
+    /// Set [`JeffToHugrConfig::record_provenance`].
+    pub fn with_record_provenance(mut self, record_provenance: bool) -> Self {
+        self.record_provenance = record_provenance;
+        self
+    }
+
+    /// Set [`JeffToHugrConfig::exact_angles`].
+    pub fn with_exact_angles(mut self, exact_angles: impl Into<Option<ExactAngleConfig>>) -> Self {
+        self.exact_angles = exact_angles.into();
+        self
+    }
+
+    /// Set [`JeffToHugrConfig::nan_angles`].
+    pub fn with_nan_angles(mut self, nan_angles: NanAngles) -> Self {
+        self.nan_angles = nan_angles;
+        self
+    }
+
+    /// Set [`JeffToHugrConfig::array_bounds`].
+    pub fn with_array_bounds(mut self, array_bounds: ArrayBounds) -> Self {
+        self.array_bounds = array_bounds;
+        self
+    }
+
+    /// Set [`JeffToHugrConfig::int_overflow`].
+    pub fn with_int_overflow(mut self, int_overflow: IntOverflow) -> Self {
+        self.int_overflow = int_overflow;
+        self
+    }
+
+    /// Set [`JeffToHugrConfig::split_measures`].
+    pub fn with_split_measures(mut self, split_measures: bool) -> Self {
+        self.split_measures = split_measures;
+        self
+    }
+
+    /// Set [`JeffToHugrConfig::symbolize_angle_params`].
+    pub fn with_symbolize_angle_params(mut self, symbolize_angle_params: bool) -> Self {
+        self.symbolize_angle_params = symbolize_angle_params;
+        self
+    }
+
+    /// Set [`JeffToHugrConfig::inline_calls`].
+    pub fn with_inline_calls(mut self, inline_calls: bool) -> Self {
+        self.inline_calls = inline_calls;
+        self
+    }
+
+    /// Set [`JeffToHugrConfig::strict_inline`].
+    pub fn with_strict_inline(mut self, strict_inline: bool) -> Self {
+        self.strict_inline = strict_inline;
+        self
+    }
+
+    /// Set [`JeffToHugrConfig::dedup_functions`].
+    pub fn with_dedup_functions(mut self, dedup_functions: FunctionDedup) -> Self {
+        self.dedup_functions = dedup_functions;
+        self
+    }
+
+    /// Set [`JeffToHugrConfig::strict_modules`].
+    pub fn with_strict_modules(mut self, strict_modules: bool) -> Self {
+        self.strict_modules = strict_modules;
+        self
+    }
+
+    /// Set [`JeffToHugrConfig::strict_names`].
+    pub fn with_strict_names(mut self, strict_names: bool) -> Self {
+        self.strict_names = strict_names;
+        self
+    }
+
+    /// Set [`JeffToHugrConfig::typecheck`].
+    pub fn with_typecheck(mut self, typecheck: bool) -> Self {
+        self.typecheck = typecheck;
+        self
+    }
+
+    /// Set [`JeffToHugrConfig::limits`].
+    pub fn with_limits(mut self, limits: ResourceLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Set [`JeffToHugrConfig::hooks`].
+    pub fn with_hooks(mut self, hooks: ConversionHooks) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Set [`JeffToHugrConfig::type_codecs`].
+    pub fn with_type_codecs(mut self, type_codecs: TypeCodecs) -> Self {
+        self.type_codecs = type_codecs;
+        self
+    }
+
+    /// Set [`JeffToHugrConfig::progress`].
+    pub fn with_progress(mut self, progress: impl Into<Option<ProgressHook>>) -> Self {
+        self.progress = progress.into();
+        self
+    }
+
+    /// Set [`JeffToHugrConfig::bit_order`].
+    pub fn with_bit_order(mut self, bit_order: BitOrder) -> Self {
+        self.bit_order = bit_order;
+        self
+    }
+
+    /// Set [`JeffToHugrConfig::bit_arrays_as`].
+    pub fn with_bit_arrays_as(mut self, bit_arrays_as: BitArrays) -> Self {
+        self.bit_arrays_as = bit_arrays_as;
+        self
+    }
+
+    /// Set [`JeffToHugrConfig::qureg_lowering`].
+    pub fn with_qureg_lowering(mut self, qureg_lowering: QuregLowering) -> Self {
+        self.qureg_lowering = qureg_lowering;
+        self
+    }
+
+    /// Set [`JeffToHugrConfig::handle_abi`].
+    pub fn with_handle_abi(mut self, handle_abi: HandleAbi) -> Self {
+        self.handle_abi = handle_abi;
+        self
+    }
+
+    /// Set [`JeffToHugrConfig::annotate_depths`].
+    pub fn with_annotate_depths(mut self, annotate_depths: bool) -> Self {
+        self.annotate_depths = annotate_depths;
+        self
+    }
+
+    /// Set [`JeffToHugrConfig::protect`].
+    pub fn with_protect(mut self, protect: Protection) -> Self {
+        self.protect = protect;
+        self
+    }
+
+    /// Set [`JeffToHugrConfig::name_wires`].
+    pub fn with_name_wires(mut self, name_wires: bool) -> Self {
+        self.name_wires = name_wires;
+        self
+    }
+
+    /// Set [`JeffToHugrConfig::circuit_boxes`].
+    pub fn with_circuit_boxes(mut self, circuit_boxes: bool) -> Self {
+        self.circuit_boxes = circuit_boxes;
+        self
+    }
+
+    /// Set [`JeffToHugrConfig::fuse_single_qubit_gates`].
+    pub fn with_fuse_single_qubit_gates(mut self, fuse_single_qubit_gates: bool) -> Self {
+        self.fuse_single_qubit_gates = fuse_single_qubit_gates;
+        self
+    }
+
+    /// Set [`JeffToHugrConfig::result_convention`].
+    pub fn with_result_convention(
+        mut self,
+        convention: impl Into<Option<ResultConvention>>,
+    ) -> Self {
+        self.result_convention = convention.into();
+        self
+    }
+
+    /// Set [`JeffToHugrConfig::propagate_register_sizes`].
+    pub fn with_propagate_register_sizes(mut self, propagate_register_sizes: bool) -> Self {
+        self.propagate_register_sizes = propagate_register_sizes;
+        self
+    }
+
+    /// Set [`JeffToHugrConfig::free_zero_repair`].
+    pub fn with_free_zero_repair(mut self, repair: impl Into<Option<FreeZeroRepair>>) -> Self {
+        self.free_zero_repair = repair.into();
+        self
+    }
+
+    /// Set [`JeffToHugrConfig::check_extensions`].
+    pub fn with_check_extensions(mut self, check_extensions: bool) -> Self {
+        self.check_extensions = check_extensions;
+        self
+    }
+
+    /// Set [`JeffToHugrConfig::gate_set`].
+    pub fn with_gate_set(mut self, gate_set: impl Into<Option<GateSet>>) -> Self {
+        self.gate_set = gate_set.into();
+        self
+    }
+
+    /// Set [`JeffToHugrConfig::target`].
+    pub fn with_target(mut self, target: impl Into<Option<TargetInfo>>) -> Self {
+        self.target = target.into();
+        self
+    }
+
+    /// Set [`JeffToHugrConfig::validate`].
+    pub fn with_validate(mut self, validate: bool) -> Self {
+        self.validate = validate;
+        self
+    }
+
+    /// Set [`JeffToHugrConfig::debug_snapshots`].
+    pub fn with_debug_snapshots(mut self, dir: impl Into<Option<PathBuf>>) -> Self {
+        self.debug_snapshots = dir.into();
+        self
+    }
+
+    /// Set [`JeffToHugrConfig::record_trace`].
+    pub fn with_record_trace(mut self, record_trace: bool) -> Self {
+        self.record_trace = record_trace;
+        self
+    }
+}
+
+/// Limits on the resources requested by a _jeff_ program.
+///
+/// The translation fails with [`JeffToHugrError::ResourceLimitExceeded`]
+/// before doing the work for an input exceeding a limit. The default limits
+/// are generous but finite, use [`ResourceLimits::unlimited`] to disable them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[non_exhaustive]
+pub struct ResourceLimits {
+    /// Maximum number of nodes in the translated HUGR.
+    ///
+    /// Checked after translating each _jeff_ operation.
+    pub max_nodes: usize,
+    /// Maximum number of qubits in a `QuregCreate` or a gate.
+    pub max_qubits: usize,
+    /// Maximum length of a constant or created integer array.
+    pub max_const_array_len: usize,
+    /// Maximum number of functions in the _jeff_ module.
+    pub max_functions: usize,
+    /// Maximum length of a chain of nested calls inlined by
+    /// [`JeffToHugrConfig::inline_calls`].
+    pub max_inline_depth: usize,
+    /// Maximum length in bytes of a gate or function name.
+    ///
+    /// Unlike the other limits, longer names do not fail the translation.
+    /// They are truncated, ending with a hash of the full name, and reported
+    /// with a [`crate::ConversionWarning::TruncatedString`].
+    pub max_string_len: usize,
+}
+
+impl Default for ResourceLimits {
+    fn default() -> Self {
+        Self {
+            max_nodes: 10_000_000,
+            max_qubits: 100_000,
+            max_const_array_len: 1_000_000,
+            max_functions: 100_000,
+            max_inline_depth: 64,
+            max_string_len: 4096,
+        }
+    }
+}
+
+/// A limit in [`ResourceLimits`].
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ResourceLimit {
+    /// [`ResourceLimits::max_nodes`].
+    #[display("max_nodes")]
+    Nodes,
+    /// [`ResourceLimits::max_qubits`].
+    #[display("max_qubits")]
+    Qubits,
+    /// [`ResourceLimits::max_const_array_len`].
+    #[display("max_const_array_len")]
+    ConstArrayLength,
+    /// [`ResourceLimits::max_functions`].
+    #[display("max_functions")]
+    Functions,
+    /// [`ResourceLimits::max_inline_depth`].
+    #[display("max_inline_depth")]
+    InlineDepth,
+}
+
+impl ResourceLimits {
+    /// No limits, translating any input regardless of its size.
+    pub fn unlimited() -> Self {
+        Self {
+            max_nodes: usize::MAX,
+            max_qubits: usize::MAX,
+            max_const_array_len: usize::MAX,
+            max_functions: usize::MAX,
+            max_inline_depth: usize::MAX,
+            max_string_len: usize::MAX,
+        }
+    }
+
+    /// Set [`ResourceLimits::max_nodes`].
+    pub fn with_max_nodes(mut self, max_nodes: usize) -> Self {
+        self.max_nodes = max_nodes;
+        self
+    }
+
+    /// Set [`ResourceLimits::max_qubits`].
+    pub fn with_max_qubits(mut self, max_qubits: usize) -> Self {
+        self.max_qubits = max_qubits;
+        self
+    }
+
+    /// Set [`ResourceLimits::max_const_array_len`].
+    pub fn with_max_const_array_len(mut self, max_const_array_len: usize) -> Self {
+        self.max_const_array_len = max_const_array_len;
+        self
+    }
+
+    /// Set [`ResourceLimits::max_functions`].
+    pub fn with_max_functions(mut self, max_functions: usize) -> Self {
+        self.max_functions = max_functions;
+        self
+    }
+
+    /// Set [`ResourceLimits::max_inline_depth`].
+    pub fn with_max_inline_depth(mut self, max_inline_depth: usize) -> Self {
+        self.max_inline_depth = max_inline_depth;
+        self
+    }
+
+    /// Set [`ResourceLimits::max_string_len`].
+    pub fn with_max_string_len(mut self, max_string_len: usize) -> Self {
+        self.max_string_len = max_string_len;
+        self
+    }
+
+    /// Returns the maximum value allowed for a limit.
+    pub fn max(&self, limit: ResourceLimit) -> usize {
+        match limit {
+            ResourceLimit::Nodes => self.max_nodes,
+            ResourceLimit::Qubits => self.max_qubits,
+            ResourceLimit::ConstArrayLength => self.max_const_array_len,
+            ResourceLimit::Functions => self.max_functions,
+            ResourceLimit::InlineDepth => self.max_inline_depth,
+        }
+    }
+
+    /// Check a requested amount against a limit.
+    ///
+    /// # Errors
+    ///
+    /// - [`JeffToHugrError::ResourceLimitExceeded`] if `requested` is above the limit.
+    pub fn check(&self, limit: ResourceLimit, requested: usize) -> Result<(), JeffToHugrError> {
+        match requested > self.max(limit) {
+            true => Err(JeffToHugrError::ResourceLimitExceeded { limit, requested }),
+            false => Ok(()),
+        }
+    }
+}
+
+/// Handling of the index in integer array and qubit register accesses.
+///
+/// Applies to `IntArrayGet`, `IntArraySet`, `QuregExtractIndex` and
+/// `QuregInsertIndex`. The nodes added by the checked modes are tagged with
+/// [`crate::metadata::BOUNDS_CHECK`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum ArrayBounds {
+    /// Emit the accesses as-is. Out-of-bounds indices are undefined behaviour.
+    #[default]
+    Unchecked,
+    /// Compare the index with the length before each access, and panic if it
+    /// is out of bounds.
+    ///
+    /// The checks can be removed with [`crate::passes::strip`].
+    Panic,
+    /// Clamp the index to the valid range before each access.
+    ///
+    /// Accesses to empty arrays are still out of bounds.
+    Clamp,
+}
+
+/// Behaviour of the _jeff_ integer arithmetic on overflow.
+///
+/// _jeff_ integers wrap around on overflow. The trapping mode helps locating
+/// the first overflow of a miscompiled program when simulating it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum IntOverflow {
+    /// Emit the wrapping HUGR integer operations, as specified by _jeff_.
+    #[default]
+    Wrapping,
+    /// Check each addition, subtraction and multiplication for signed
+    /// overflow, and panic with the function name and operation index when
+    /// it overflows. Integers are checked at the width of their HUGR type,
+    /// and 64-bit multiplications are not checked.
+    ///
+    /// The checks are tagged with [`crate::metadata::BOUNDS_CHECK`], and can
+    /// be removed with [`crate::passes::strip`].
+    Trap,
+}
+
+/// Translation of the constant gate angles that are NaN.
+///
+/// A rotation has no NaN angle: converting a NaN to half-turns at runtime
+/// gives an undefined rotation, so NaN constants are never converted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum NanAngles {
+    /// Fail the translation with [`JeffToHugrError::NonFiniteAngle`].
+    #[default]
+    Error,
+    /// Replace the rotation with a runtime panic, and report a
+    /// [`crate::ConversionWarning::NanAngle`].
+    ///
+    /// The program only fails if the region holding the gate runs.
+    Warn,
+}
+
+/// Order of the bits in an `int(1)` array, when read as a packed integer.
+///
+/// _jeff_ arrays are least significant bit first: element `i` of a bit array
+/// is the bit of weight `2^i`. This is also the order of the elements of a
+/// [`crate::extension::ConstIntReg`], and of the arrays inside a translated
+/// function body.
+///
+/// With [`BitOrder::MsbFirst`], a [`crate::extension::JeffOp::IntArrayReverse`]
+/// is inserted on each bit array crossing a function boundary: after the
+/// function inputs, before its outputs, and around the calls between
+/// translated functions. The reversals can be lowered with
+/// [`crate::passes::lower_int_array_reverses`].
+#[derive(Debug, Display, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum BitOrder {
+    /// Element `0` is the least significant bit, as in _jeff_.
+    #[default]
+    #[display("lsb_first")]
+    LsbFirst,
+    /// Element `0` is the most significant bit.
+    #[display("msb_first")]
+    MsbFirst,
+}
+
+impl BitOrder {
+    /// Parse the value of a [`crate::metadata::BIT_ORDER`] metadata entry.
+    pub fn from_metadata(value: &serde_json::Value) -> Option<Self> {
+        match value.as_str()? {
+            "lsb_first" => Some(Self::LsbFirst),
+            "msb_first" => Some(Self::MsbFirst),
+            _ => None,
+        }
+    }
+}
+
+/// Representation of the _jeff_ `int(1)` arrays in the translated HUGR.
+///
+/// Bit arrays are first translated as opaque `intArray<1>` registers of the
+/// _jeff_ extension. With [`BitArrays::BoolCollection`], they are then
+/// replaced by `list<bool>` values of the standard collections extension, so
+/// the result can be consumed by tools that do not know the _jeff_ extension.
+#[derive(Debug, Display, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum BitArrays {
+    /// Opaque `intArray<1>` registers of the _jeff_ extension.
+    #[default]
+    #[display("opaque")]
+    Opaque,
+    /// Lists of booleans.
+    ///
+    /// The register measurements and the array reversals are lowered first,
+    /// see [`crate::passes::lower_register_measures`] and
+    /// [`crate::passes::lower_int_array_reverses`], and the remaining bit
+    /// array operations are replaced by list operations.
+    #[display("bool_collection")]
+    BoolCollection,
+}
+
+/// Representation of the _jeff_ qubit registers in the translated HUGR.
+///
+/// Registers are first translated as opaque `qureg` values of the _jeff_
+/// extension. With [`QuregLowering::StaticArray`], the registers of static
+/// length are then replaced by arrays of the standard collections
+/// extensions, whose operations are understood by the HUGR passes that do
+/// not know the _jeff_ extension.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum QuregLowering {
+    /// Opaque `qureg` registers of the _jeff_ extension.
+    #[default]
+    Opaque,
+    /// `borrow_array<n, qubit>` arrays of the `collections.borrow_arr`
+    /// extension, for the registers of static length.
+    ///
+    /// A register is lowered when it is created with a static length, by a
+    /// `QuregCreate` or a `QuregAlloc` of a constant length, and is only
+    /// accessed at constant indices in its region. Index accesses become
+    /// array borrows and returns, lengths become constants, and the qubits
+    /// of split and joined registers are moved to new arrays. Registers
+    /// crossing a region boundary, passed to a function or used by other
+    /// register operations stay opaque.
+    StaticArray,
+}
+
+/// Translation of the function definitions sharing an identical body.
+///
+/// Bodies are compared by a structural hash of their operations, types and
+/// constants, and hash collisions are confirmed by a full comparison. The
+/// first definition with a given body is the canonical one, translated as
+/// usual.
+#[derive(Debug, Display, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum FunctionDedup {
+    /// Translate every definition.
+    #[default]
+    #[display("disabled")]
+    Disabled,
+    /// Translate each other definition as a wrapper calling the canonical
+    /// one, keeping its name and signature.
+    #[display("wrappers")]
+    Wrappers,
+    /// Drop the other definitions, and call the canonical one instead.
+    ///
+    /// The dropped functions are no longer available by their HUGR name, and
+    /// [`crate::JeffHugrMap::function_node`] returns the canonical function
+    /// for them.
+    #[display("retarget")]
+    Retarget,
+}
+
+/// Functions whose signature passes the _jeff_ registers as opaque handles.
+///
+/// This suits consumers that cannot handle extension types in function
+/// signatures. Each selected function with a qubit, integer or float
+/// register in its signature is exported through a wrapper of the same name,
+/// taking and returning `usize` handles in place of the registers. The
+/// wrapper converts them with calls to declared runtime functions, such as
+/// `jeff_rt_qureg_from_handle` and `jeff_rt_handle_from_qureg`, around a
+/// call to the translated function.
+///
+/// The translated function keeps its body and signature, and is renamed with
+/// a `__jeff_impl` suffix. The calls within the module still target it, so
+/// they pass the registers directly. Bit arrays translated with
+/// [`BitArrays::BoolCollection`] are not replaced.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum HandleAbi {
+    /// Keep the registers in all the signatures.
+    #[default]
+    Disabled,
+    /// The functions that are not called within the module.
+    Entrypoints,
+    /// The functions with the given HUGR names.
+    Functions(BTreeSet<String>),
+}
+
+/// Encoding of the operations protected from optimization in the
+/// translated HUGR.
+///
+/// Both encodings keep the optimization passes from rewriting the protected
+/// operations, and are recognized by [`crate::protected_ops`].
+#[derive(Debug, Display, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Protection {
+    /// A prelude `Barrier` on the qubit inputs of each protected operation,
+    /// and one on the qubits leaving each span of protected operations.
+    ///
+    /// Operations cannot be commuted or cancelled across a barrier.
+    #[default]
+    #[display("barriers")]
+    Barriers,
+    /// A `DFG` container around each span of protected operations, marked
+    /// with the [`crate::metadata::PROTECTED`] metadata.
+    ///
+    /// Passes that respect the metadata leave the container untouched.
+    #[display("container")]
+    Container,
+}
+
+/// Parameters for recognizing exact fractions of π in gate angles.
+///
+/// An angle is considered exact if it is within `tolerance` radians of
+/// `k·π/2^n` for some integer `k` and `n <= max_denominator_log`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct ExactAngleConfig {
+    /// Maximum absolute difference in radians from the exact value.
+    pub tolerance: f64,
+    /// Maximum power of two in the denominator.
+    pub max_denominator_log: u32,
+}
+
+impl Default for ExactAngleConfig {
+    fn default() -> Self {
+        Self {
+            tolerance: 1e-12,
+            max_denominator_log: 8,
+        }
+    }
+}
+
+impl ExactAngleConfig {
+    /// Set [`ExactAngleConfig::tolerance`].
+    pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Set [`ExactAngleConfig::max_denominator_log`].
+    pub fn with_max_denominator_log(mut self, max_denominator_log: u32) -> Self {
+        self.max_denominator_log = max_denominator_log;
+        self
+    }
+
+    /// Returns the angle as `k/2^n` half-turns, encoded as `(k, n)`, if it
+    /// is an exact fraction of π.
+    ///
+    /// The fraction is returned with the smallest possible `n`.
+    pub fn exact_half_turns(&self, radians: f64) -> Option<(i64, u32)> {
+        if !radians.is_finite() {
+            return None;
+        }
+        (0..=self.max_denominator_log).find_map(|n| {
+            let denominator = (1u64 << n) as f64;
+            let k = (radians / PI * denominator).round();
+            let exact = k * PI / denominator;
+            ((exact - radians).abs() <= self.tolerance).then_some((k as i64, n))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::zero(0.0, Some((0, 0)))]
+    #[case::pi(PI, Some((1, 0)))]
+    #[case::half_pi(PI / 2.0, Some((1, 1)))]
+    #[case::quarter_pi(PI / 4.0, Some((1, 2)))]
+    #[case::neg_three_quarters(-3.0 * PI / 4.0, Some((-3, 2)))]
+    #[case::reduced(2.0 * PI / 4.0, Some((1, 1)))]
+    #[case::too_fine(PI / 1024.0, None)]
+    #[case::inexact(0.1, None)]
+    #[case::nan(f64::NAN, None)]
+    fn exact_half_turns(#[case] radians: f64, #[case] expected: Option<(i64, u32)>) {
+        assert_eq!(
+            ExactAngleConfig::default().exact_half_turns(radians),
+            expected
+        );
+    }
+
+    #[rstest]
+    fn resource_limits() {
+        let limits = ResourceLimits {
+            max_const_array_len: 4,
+            ..Default::default()
+        };
+        limits.check(ResourceLimit::ConstArrayLength, 4).unwrap();
+        let err = limits
+            .check(ResourceLimit::ConstArrayLength, 5_000_000)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            JeffToHugrError::ResourceLimitExceeded {
+                limit: ResourceLimit::ConstArrayLength,
+                requested: 5_000_000,
+            }
+        ));
+
+        ResourceLimits::unlimited()
+            .check(ResourceLimit::ConstArrayLength, usize::MAX)
+            .unwrap();
+    }
+}
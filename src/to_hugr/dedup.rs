@@ -0,0 +1,284 @@
+//! Deduplication of identical function bodies.
+//!
+//! See [`crate::FunctionDedup`].
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use hugr::builder::{Container, Dataflow, DataflowSubContainer, ModuleBuilder};
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::ops::OpType;
+use hugr::ops::handle::{FuncID, NodeHandle};
+use hugr::{Hugr, HugrView, Node};
+use itertools::Itertools;
+use jeff::reader::optype::{self as jeff_optype, OpType as JeffOpType};
+use jeff::reader::{FunctionId, Region};
+
+use super::incremental::{call_signatures, hash_region, type_signature};
+use crate::JeffToHugrError;
+use crate::metadata;
+
+/// Find the function definitions whose body is identical to that of an
+/// earlier definition.
+///
+/// Functions in `called` are only merged with each other, as they keep
+/// their array parameters when the angle parameters are symbolized.
+///
+/// Returns the map from each duplicate to the first definition with the
+/// same body.
+pub(super) fn duplicate_functions(
+    module: &jeff::reader::Module<'_>,
+    called: &BTreeSet<FunctionId>,
+) -> Result<BTreeMap<FunctionId, FunctionId>, JeffToHugrError> {
+    let functions = module.functions().collect_vec();
+    let signatures = call_signatures(module)?;
+
+    let mut buckets: BTreeMap<u64, Vec<FunctionId>> = BTreeMap::new();
+    let mut duplicates = BTreeMap::new();
+    for (func_id, func) in functions.iter().enumerate() {
+        let jeff::reader::Function::Definition(def) = func else {
+            continue;
+        };
+        let func_id = func_id as FunctionId;
+        let mut hasher = DefaultHasher::new();
+        type_signature(func)?.hash(&mut hasher);
+        called.contains(&func_id).hash(&mut hasher);
+        hash_region(&def.body(), &signatures, &mut hasher)?;
+
+        // Confirm the hash collisions before merging.
+        let bucket = buckets.entry(hasher.finish()).or_default();
+        let mut canonical = None;
+        for &other in bucket.iter() {
+            if called.contains(&other) == called.contains(&func_id)
+                && same_body(&functions[other as usize], func, &signatures)?
+            {
+                canonical = Some(other);
+                break;
+            }
+        }
+        match canonical {
+            Some(canonical) => {
+                duplicates.insert(func_id, canonical);
+            }
+            None => bucket.push(func_id),
+        }
+    }
+    Ok(duplicates)
+}
+
+/// Returns `true` if two function definitions have the same types and
+/// body.
+fn same_body(
+    a: &jeff::reader::Function<'_>,
+    b: &jeff::reader::Function<'_>,
+    signatures: &[String],
+) -> Result<bool, JeffToHugrError> {
+    let (jeff::reader::Function::Definition(a_def), jeff::reader::Function::Definition(b_def)) =
+        (a, b)
+    else {
+        return Ok(false);
+    };
+    Ok(type_signature(a)? == type_signature(b)?
+        && regions_equal(&a_def.body(), &b_def.body(), signatures)?)
+}
+
+/// Compare the values and operations of two regions, including nested
+/// regions, as hashed by [`hash_region`].
+fn regions_equal(
+    a: &Region<'_>,
+    b: &Region<'_>,
+    signatures: &[String],
+) -> Result<bool, JeffToHugrError> {
+    let boundary = |region: &Region<'_>| {
+        let sources = region
+            .sources()
+            .map(|value| value.map(|value| (value.id(), value.ty().to_string())))
+            .collect::<Result<Vec<_>, _>>()?;
+        let targets = region
+            .targets()
+            .map(|value| value.map(|value| (value.id(), value.ty().to_string())))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok::<_, JeffToHugrError>((sources, targets))
+    };
+    if boundary(a)? != boundary(b)? || a.operations().count() != b.operations().count() {
+        return Ok(false);
+    }
+
+    for (a_op, b_op) in a.operations().zip(b.operations()) {
+        let values = |op: &jeff::reader::Operation<'_>| {
+            op.inputs()
+                .chain(op.outputs())
+                .map(|value| value.map(|value| (value.id(), value.ty().to_string())))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        if values(&a_op)? != values(&b_op)? {
+            return Ok(false);
+        }
+        let equal = match (a_op.op_type(), b_op.op_type()) {
+            (JeffOpType::FuncOp(a_call), JeffOpType::FuncOp(b_call)) => {
+                signatures[a_call.func_idx as usize] == signatures[b_call.func_idx as usize]
+            }
+            (JeffOpType::ControlFlowOp(a_cf), JeffOpType::ControlFlowOp(b_cf)) => {
+                if format!("{a_cf:?}") != format!("{b_cf:?}") {
+                    return Ok(false);
+                }
+                let a_regions = nested_regions(a_cf);
+                let b_regions = nested_regions(b_cf);
+                if a_regions.len() != b_regions.len() {
+                    return Ok(false);
+                }
+                for (a_region, b_region) in a_regions.iter().zip(&b_regions) {
+                    if !regions_equal(a_region, b_region, signatures)? {
+                        return Ok(false);
+                    }
+                }
+                true
+            }
+            (a_type, b_type) => format!("{a_type:?}") == format!("{b_type:?}"),
+        };
+        if !equal {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// The regions nested in a control-flow operation, in the order they are
+/// hashed by [`hash_region`].
+fn nested_regions(cf: jeff_optype::ControlFlowOp<'_>) -> Vec<Region<'_>> {
+    match cf {
+        jeff_optype::ControlFlowOp::Switch(switch_op) => (0..switch_op.branch_count())
+            .map(|idx| switch_op.branch(idx))
+            .chain(switch_op.default_branch())
+            .collect(),
+        jeff_optype::ControlFlowOp::DoWhile { body, condition }
+        | jeff_optype::ControlFlowOp::While { body, condition } => vec![body, condition],
+        jeff_optype::ControlFlowOp::For { region } => vec![region],
+    }
+}
+
+/// Define a function calling a canonical function definition with the same
+/// signature.
+///
+/// The wrapper keeps the [`metadata::ANGLE_PARAMS`] of the canonical
+/// function, as it takes the same parameters.
+pub(super) fn build_wrapper(
+    builder: &mut ModuleBuilder<&mut Hugr>,
+    name: &str,
+    canonical: Node,
+) -> Result<Node, JeffToHugrError> {
+    let OpType::FuncDefn(defn) = builder.hugr().get_optype(canonical) else {
+        unreachable!("functions are merged into function definitions");
+    };
+    let signature = defn.signature().body().clone();
+    let angle_params = builder
+        .hugr()
+        .get_metadata(canonical, metadata::ANGLE_PARAMS)
+        .cloned();
+
+    let mut wrapper = builder.define_function(name, signature)?;
+    let inputs = wrapper.input_wires().collect_vec();
+    let call = wrapper.call(&FuncID::<true>::from(canonical), &[], inputs)?;
+    let outputs = call.outputs().collect_vec();
+    let wrapper = wrapper.finish_with_outputs(outputs)?.node();
+    if let Some(entries) = angle_params {
+        builder
+            .hugr_mut()
+            .set_metadata(wrapper, metadata::ANGLE_PARAMS, entries);
+    }
+    Ok(wrapper)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{duplicate_bodies, entangled_calls};
+    use crate::{FunctionDedup, JeffToHugrConfig, jeff_to_hugr_with_config};
+    use jeff::Jeff;
+    use rstest::rstest;
+
+    /// The names of the function definitions of a module.
+    fn definitions(hugr: &Hugr) -> Vec<String> {
+        hugr.children(hugr.module_root())
+            .filter_map(|node| match hugr.get_optype(node) {
+                OpType::FuncDefn(defn) => Some(defn.func_name().to_string()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The functions called from a function, by name.
+    fn callees(hugr: &Hugr, func: Node) -> Vec<String> {
+        hugr.descendants(func)
+            .filter(|&node| hugr.get_optype(node).is_call())
+            .filter_map(|call| match hugr.get_optype(hugr.static_source(call)?) {
+                OpType::FuncDefn(defn) => Some(defn.func_name().to_string()),
+                _ => None,
+            })
+            .sorted()
+            .collect()
+    }
+
+    #[rstest]
+    fn no_duplicates(entangled_calls: Jeff<'static>) {
+        let module = entangled_calls.module();
+        assert!(duplicate_functions(&module, &BTreeSet::new()).unwrap().is_empty());
+
+        let config = JeffToHugrConfig::default().with_dedup_functions(FunctionDedup::Wrappers);
+        let converted = jeff_to_hugr_with_config(&entangled_calls, &config).unwrap();
+        let hugr = &converted.hugr;
+        assert_eq!(hugr.get_metadata(hugr.module_root(), metadata::DEDUPLICATED), None);
+        assert_eq!(converted.map.canonical_function(1), None);
+    }
+
+    #[rstest]
+    fn merge_into_wrappers(duplicate_bodies: Jeff<'static>) {
+        let module = duplicate_bodies.module();
+        let duplicates = duplicate_functions(&module, &BTreeSet::new()).unwrap();
+        assert_eq!(duplicates, BTreeMap::from([(1, 0), (2, 0)]));
+
+        let naive =
+            jeff_to_hugr_with_config(&duplicate_bodies, &JeffToHugrConfig::default()).unwrap();
+        let config = JeffToHugrConfig::default().with_dedup_functions(FunctionDedup::Wrappers);
+        let converted = jeff_to_hugr_with_config(&duplicate_bodies, &config).unwrap();
+        let hugr = &converted.hugr;
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        // The names and the calls of `main` are kept.
+        assert_eq!(definitions(hugr), definitions(&naive.hugr));
+        let main = converted.map.function_by_name("main").unwrap();
+        let naive_main = naive.map.function_by_name("main").unwrap();
+        assert_eq!(callees(hugr, main), callees(&naive.hugr, naive_main));
+        // Only `add_a` has a body, the others call it.
+        for name in ["add_b", "add_c"] {
+            let wrapper = converted.map.function_by_name(name).unwrap();
+            assert_eq!(callees(hugr, wrapper), ["add_a"]);
+            assert_eq!(hugr.children(wrapper).count(), 3);
+        }
+        assert_eq!(converted.map.canonical_function(2), Some(0));
+        assert_eq!(converted.map.canonical_function(3), None);
+        assert_eq!(
+            hugr.get_metadata(hugr.module_root(), metadata::DEDUPLICATED),
+            Some(&serde_json::json!({"add_b": "add_a", "add_c": "add_a"}))
+        );
+    }
+
+    #[rstest]
+    fn retarget_calls(duplicate_bodies: Jeff<'static>) {
+        let config = JeffToHugrConfig::default().with_dedup_functions(FunctionDedup::Retarget);
+        let converted = jeff_to_hugr_with_config(&duplicate_bodies, &config).unwrap();
+        let hugr = &converted.hugr;
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        assert_eq!(definitions(hugr), ["add_a", "shl", "main"]);
+        let main = converted.map.function_by_name("main").unwrap();
+        assert_eq!(callees(hugr, main), ["add_a", "add_a", "shl"]);
+        let add_a = converted.map.function_node(0);
+        assert_eq!(converted.map.function_node(1), add_a);
+        assert_eq!(converted.map.function_by_name("add_c"), add_a);
+        assert_eq!(
+            hugr.get_metadata(hugr.module_root(), metadata::DEDUPLICATED),
+            Some(&serde_json::json!({"add_b": "add_a", "add_c": "add_a"}))
+        );
+    }
+}
@@ -0,0 +1,198 @@
+//! Suggestions attached to the translation errors.
+//!
+//! See [`JeffToHugrError::UnsupportedOperation`](super::JeffToHugrError::UnsupportedOperation).
+
+use itertools::Itertools;
+
+use crate::optype::catalog::CATALOG;
+use crate::optype::tket_ops;
+
+/// Maximum number of names suggested for an operation.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Common spellings of the gates known to the translation, normalized with
+/// [`normalize`], and the name of the gate they refer to.
+const GATE_ALIASES: &[(&str, &str)] = &[
+    ("cnot", "CX"),
+    ("ccx", "Toffoli"),
+    ("ccnot", "Toffoli"),
+    ("sqrtx", "V"),
+    ("sx", "V"),
+    ("sxdg", "Vdg"),
+    ("sdag", "Sdg"),
+    ("tdag", "Tdg"),
+    ("hadamard", "H"),
+    ("not", "X"),
+    ("id", "I"),
+    ("phase", "R1"),
+    ("u1", "R1"),
+    ("u3", "U"),
+];
+
+/// The known operations whose name is close to the name of an unsupported
+/// operation, as `'name' (kind)` strings.
+///
+/// `op_name` is the name recorded in the error, the debug representation
+/// of the operation. The names of custom gates are quoted in it, other
+/// operations are identified by the words of the representation.
+///
+/// Names are compared ignoring case, whitespace, `_` and `-`. A known name
+/// is suggested if it matches an operation name, if one of the
+/// [`GATE_ALIASES`] of an operation name refers to it, or if it is within a
+/// small edit distance of an operation name. At most [`MAX_SUGGESTIONS`]
+/// names are returned, closest first.
+pub(crate) fn did_you_mean(op_name: &str) -> Vec<String> {
+    let known = known_names();
+    operation_names(op_name)
+        .into_iter()
+        .flat_map(|name| close_names(name, &known))
+        .sorted_by_key(|(distance, _)| *distance)
+        .map(|(_, suggestion)| suggestion)
+        .unique()
+        .take(MAX_SUGGESTIONS)
+        .collect()
+}
+
+/// The known names close to an operation name, with their distance to it.
+fn close_names(name: &str, known: &[(String, &str)]) -> Vec<(usize, String)> {
+    let key = normalize(name);
+    if key.is_empty() {
+        return Vec::new();
+    }
+    let aliased = GATE_ALIASES
+        .iter()
+        .filter(|(alias, _)| *alias == key)
+        .map(|(_, target)| normalize(target))
+        .collect_vec();
+    // Short names are only suggested on exact matches.
+    let max_distance = key.chars().count() / 4;
+    known
+        .iter()
+        .filter(|(candidate, _)| candidate != name)
+        .filter_map(|(candidate, kind)| {
+            let normalized = normalize(candidate);
+            let distance = match aliased.contains(&normalized) {
+                true => 0,
+                false => edit_distance(&key, &normalized),
+            };
+            let suggestion = format!("'{candidate}' ({kind})");
+            (distance <= max_distance).then_some((distance, suggestion))
+        })
+        .collect()
+}
+
+/// The hint appended to an error message for some suggested names.
+pub(crate) fn hint(did_you_mean: &[String]) -> String {
+    match did_you_mean.is_empty() {
+        true => String::new(),
+        false => format!(
+            ". Did you mean {}? Consider renaming the operation or registering a conversion hook",
+            did_you_mean.iter().join(" or ")
+        ),
+    }
+}
+
+/// The operation names in the debug representation of an operation.
+///
+/// These are the quoted strings if there are any, or the words of the
+/// representation otherwise.
+fn operation_names(op_name: &str) -> Vec<&str> {
+    let quoted = op_name.split('"').skip(1).step_by(2).collect_vec();
+    match quoted.is_empty() {
+        false => quoted,
+        true => op_name
+            .split(|c: char| !c.is_alphanumeric() && c != '_')
+            .filter(|word| !word.is_empty())
+            .collect(),
+    }
+}
+
+/// The names of the well-known gates and of the `tket.quantum` operations,
+/// with their kind.
+fn known_names() -> Vec<(String, &'static str)> {
+    let gates = CATALOG
+        .iter()
+        .map(|info| (info.gate.to_string(), "well-known gate"));
+    let tket_ops = tket_ops::tket_op_names().map(|name| (name, "tket op"));
+    gates.chain(tket_ops).collect()
+}
+
+/// A name in lowercase, without whitespace, `_` nor `-`.
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| !c.is_whitespace() && !matches!(c, '_' | '-'))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// The Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect_vec();
+    let mut row = (0..=b.len()).collect_vec();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &b_char) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a_char != b_char);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::JeffToHugrError;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::cnot("CNOT", &["'CX' (tket op)"])]
+    #[case::trailing_space(
+        "rz ",
+        &["'Rz' (well-known gate)", "'Rz' (tket op)"]
+    )]
+    #[case::sqrt_x("sqrtX", &["'V' (tket op)"])]
+    #[case::typo("Toffolli", &["'Toffoli' (tket op)"])]
+    #[case::unknown("Frobnicate", &[])]
+    #[case::float_op("Sin", &[])]
+    fn suggestions(#[case] op_name: &str, #[case] expected: &[&str]) {
+        let op_name = format!("{op_name:?}");
+        assert_eq!(did_you_mean(&op_name), expected);
+    }
+
+    #[rstest]
+    fn aliases_are_known() {
+        let known = known_names()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect_vec();
+        for (alias, name) in GATE_ALIASES {
+            assert_eq!(*alias, normalize(alias));
+            assert!(
+                known.iter().any(|known| known == name),
+                "Unknown gate {name}"
+            );
+        }
+    }
+
+    #[rstest]
+    #[case::custom_gate(r#"Custom { name: "cnot", num_qubits: 2 }"#, &["cnot"])]
+    #[case::variant("FloatOp(Sin)", &["FloatOp", "Sin"])]
+    fn names_of_operations(#[case] op_name: &str, #[case] expected: &[&str]) {
+        assert_eq!(operation_names(op_name), expected);
+    }
+
+    #[rstest]
+    fn error_message() {
+        let err = JeffToHugrError::unsupported_op(&"CNOT");
+        assert_eq!(
+            err.to_string(),
+            "Unsupported operation: \"CNOT\". Did you mean 'CX' (tket op)? \
+             Consider renaming the operation or registering a conversion hook"
+        );
+        let err = JeffToHugrError::unsupported_op(&"Frobnicate");
+        assert_eq!(err.to_string(), "Unsupported operation: \"Frobnicate\"");
+    }
+}
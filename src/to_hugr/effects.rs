@@ -0,0 +1,75 @@
+//! Classification of the side effects of _jeff_ operations.
+//!
+//! The ordering of the translated operations, the removal of dead trailing
+//! operations and the deduplication of function bodies all read the effects
+//! of an operation from [`op_effect`].
+//!
+//! The effects only depend on the kind of the operation. Producers can mark
+//! operations with `pure` or `sideeffect` attributes, but the _jeff_ reader
+//! does not expose operation attributes yet, so they are not consulted.
+
+use jeff::reader::optype::OpType as JeffOpType;
+
+/// The side effects of a _jeff_ operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Effect {
+    /// No side effects. The operation can be removed when its results are
+    /// unused, and it is not ordered with the other operations.
+    Pure,
+    /// The side effects of the nested regions or of the called function.
+    /// The operation is kept, but not ordered itself.
+    Nested,
+    /// Side effects of the operation itself. The operation is kept, and
+    /// ordered with the other operations with side effects of its region.
+    SideEffect,
+}
+
+/// The side effects of an operation.
+///
+/// Quantum operations have side effects, classical operations are pure, and
+/// the effects of control flow and calls are those of their bodies.
+/// Operations of unknown kinds are assumed to have side effects.
+pub(crate) fn op_effect(op_type: &JeffOpType<'_>) -> Effect {
+    match op_type {
+        JeffOpType::QubitOp(_) | JeffOpType::QubitRegisterOp(_) => Effect::SideEffect,
+        JeffOpType::IntOp(_)
+        | JeffOpType::IntArrayOp(_)
+        | JeffOpType::FloatOp(_)
+        | JeffOpType::FloatArrayOp(_) => Effect::Pure,
+        JeffOpType::ControlFlowOp(_) | JeffOpType::FuncOp(_) => Effect::Nested,
+        _ => Effect::SideEffect,
+    }
+}
+
+/// Whether an operation acts on qubits.
+///
+/// Quantum operations with side effects are ordered by their qubit wires,
+/// the other operations with side effects are ordered around them.
+pub(crate) fn is_quantum(op_type: &JeffOpType<'_>) -> bool {
+    matches!(
+        op_type,
+        JeffOpType::QubitOp(_) | JeffOpType::QubitRegisterOp(_)
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use jeff::reader::optype::{FloatOp, IntArrayOp, IntOp, QubitOp, QubitRegisterOp};
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::measure(JeffOpType::QubitOp(QubitOp::Measure), Effect::SideEffect)]
+    #[case::alloc(JeffOpType::QubitOp(QubitOp::Alloc), Effect::SideEffect)]
+    #[case::reset(JeffOpType::QubitOp(QubitOp::Reset), Effect::SideEffect)]
+    #[case::qureg_length(
+        JeffOpType::QubitRegisterOp(QubitRegisterOp::Length),
+        Effect::SideEffect
+    )]
+    #[case::int_add(JeffOpType::IntOp(IntOp::Add), Effect::Pure)]
+    #[case::int_array_get(JeffOpType::IntArrayOp(IntArrayOp::GetIndex), Effect::Pure)]
+    #[case::float_sin(JeffOpType::FloatOp(FloatOp::Sin), Effect::Pure)]
+    fn defaults(#[case] op_type: JeffOpType<'static>, #[case] expected: Effect) {
+        assert_eq!(op_effect(&op_type), expected);
+    }
+}
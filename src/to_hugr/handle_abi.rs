@@ -0,0 +1,297 @@
+//! Function signatures passing the _jeff_ registers as opaque handles.
+//!
+//! Some consumers, such as LLVM lowering pipelines, cannot handle extension
+//! types in function signatures. With [`HandleAbi`], the selected functions
+//! are exported through a wrapper whose signature replaces the _jeff_
+//! register types by `usize` handles. The wrapper converts between handles
+//! and registers by calling declared runtime functions:
+//!
+//! - `jeff_rt_<kind>_from_handle(handle: usize) -> <kind>`
+//! - `jeff_rt_handle_from_<kind>(value: <kind>) -> usize`
+//!
+//! where `<kind>` is `qureg`, `int<bits>_array` or `float<bits>_array`.
+//!
+//! The wrapper takes the name of the function, whose definition is renamed
+//! with an [`IMPL_SUFFIX`] and keeps its body and signature unchanged. Calls
+//! within the module still target the definition, so they pass the registers
+//! directly.
+
+use std::collections::BTreeMap;
+
+use hugr::builder::{Dataflow, DataflowSubContainer, ModuleBuilder};
+use hugr::extension::prelude::usize_t;
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::ops::OpType;
+use hugr::ops::handle::FuncID;
+use hugr::types::{Signature, Type as HugrType};
+use hugr::{Hugr, HugrView, Node};
+use itertools::Itertools;
+use jeff::types::{FloatPrecision, Type as JeffType};
+
+use super::HandleAbi;
+use crate::JeffToHugrError;
+use crate::extension::intreg_type;
+use crate::types::hugr_to_jeff;
+
+/// The suffix appended to the name of the functions wrapped with a handle
+/// signature.
+pub(crate) const IMPL_SUFFIX: &str = "__jeff_impl";
+
+/// Export the functions of `translated` selected by `abi` through wrappers
+/// with a handle signature.
+///
+/// Functions without _jeff_ registers in their signature are left unchanged.
+/// The runtime conversion functions are declared once per module.
+pub(super) fn wrap_handle_functions(
+    hugr: &mut Hugr,
+    abi: &HandleAbi,
+    translated: &[Node],
+) -> Result<(), JeffToHugrError> {
+    let selected = translated
+        .iter()
+        .copied()
+        .filter(|&func| is_selected(hugr, abi, func))
+        .collect_vec();
+    if selected.is_empty() {
+        return Ok(());
+    }
+
+    let mut runtime_fns: BTreeMap<String, FuncID<false>> = hugr
+        .children(hugr.module_root())
+        .filter_map(|node| match hugr.get_optype(node) {
+            OpType::FuncDecl(decl) => Some((decl.func_name().to_string(), node.into())),
+            _ => None,
+        })
+        .collect();
+
+    for func in selected {
+        let OpType::FuncDefn(defn) = hugr.get_optype(func) else {
+            continue;
+        };
+        let name = defn.func_name().to_string();
+        let signature = defn.signature().body().clone();
+        let inputs = signature.input_types().iter().map(handle_kind).collect_vec();
+        let outputs = signature.output_types().iter().map(handle_kind).collect_vec();
+        if inputs.iter().chain(&outputs).all(Option::is_none) {
+            continue;
+        }
+
+        if let OpType::FuncDefn(defn) = hugr.optype_mut(func) {
+            *defn.func_name_mut() = format!("{name}{IMPL_SUFFIX}");
+        }
+        let mut module = ModuleBuilder::with_hugr(&mut *hugr);
+
+        // Declare the conversions before building the wrapper, which
+        // borrows the module builder.
+        let from_handle = signature
+            .input_types()
+            .iter()
+            .zip(&inputs)
+            .map(|(ty, kind)| {
+                let Some(kind) = kind else {
+                    return Ok(None);
+                };
+                let name = format!("jeff_rt_{kind}_from_handle");
+                let sig = Signature::new(vec![usize_t()], vec![ty.clone()]);
+                declare(&mut module, &mut runtime_fns, name, sig).map(Some)
+            })
+            .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+        let to_handle = signature
+            .output_types()
+            .iter()
+            .zip(&outputs)
+            .map(|(ty, kind)| {
+                let Some(kind) = kind else {
+                    return Ok(None);
+                };
+                let name = format!("jeff_rt_handle_from_{kind}");
+                let sig = Signature::new(vec![ty.clone()], vec![usize_t()]);
+                declare(&mut module, &mut runtime_fns, name, sig).map(Some)
+            })
+            .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+
+        let handle_types = |types: &[HugrType], kinds: &[Option<String>]| {
+            types
+                .iter()
+                .zip(kinds)
+                .map(|(ty, kind)| match kind {
+                    Some(_) => usize_t(),
+                    None => ty.clone(),
+                })
+                .collect_vec()
+        };
+        let wrapper_sig = Signature::new(
+            handle_types(signature.input_types(), &inputs),
+            handle_types(signature.output_types(), &outputs),
+        );
+        let mut wrapper = module.define_function(&name, wrapper_sig)?;
+        let handles = wrapper.input_wires().collect_vec();
+        let args = handles
+            .into_iter()
+            .zip(&from_handle)
+            .map(|(wire, conversion)| match conversion {
+                Some(conversion) => Ok(wrapper.call(conversion, &[], [wire])?.out_wire(0)),
+                None => Ok(wire),
+            })
+            .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+        let call = wrapper.call(&FuncID::<true>::from(func), &[], args)?;
+        let results = call
+            .outputs()
+            .zip(&to_handle)
+            .map(|(wire, conversion)| match conversion {
+                Some(conversion) => Ok(wrapper.call(conversion, &[], [wire])?.out_wire(0)),
+                None => Ok(wire),
+            })
+            .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+        wrapper.finish_with_outputs(results)?;
+    }
+    Ok(())
+}
+
+/// Whether a function definition is selected by a [`HandleAbi`].
+fn is_selected(hugr: &Hugr, abi: &HandleAbi, func: Node) -> bool {
+    let OpType::FuncDefn(defn) = hugr.get_optype(func) else {
+        return false;
+    };
+    match abi {
+        HandleAbi::Disabled => false,
+        HandleAbi::Entrypoints => hugr
+            .static_targets(func)
+            .is_none_or(|mut targets| targets.next().is_none()),
+        HandleAbi::Functions(names) => names.contains(defn.func_name()),
+    }
+}
+
+/// Returns the name of a _jeff_ register type in the runtime functions, or
+/// `None` for the other types.
+fn handle_kind(ty: &HugrType) -> Option<String> {
+    let precision_bits = |precision: FloatPrecision| match precision {
+        FloatPrecision::Float32 => 32,
+        FloatPrecision::Float64 => 64,
+    };
+    match hugr_to_jeff(ty).ok()? {
+        JeffType::QubitRegister => Some("qureg".to_string()),
+        // Bit arrays translated as boolean lists are not extension types.
+        JeffType::IntArray { bits } if *ty == intreg_type(bits) => Some(format!("int{bits}_array")),
+        JeffType::FloatArray { precision } => {
+            Some(format!("float{}_array", precision_bits(precision)))
+        }
+        _ => None,
+    }
+}
+
+/// Declare a runtime function, unless it is already declared.
+fn declare(
+    module: &mut ModuleBuilder<&mut Hugr>,
+    runtime_fns: &mut BTreeMap<String, FuncID<false>>,
+    name: String,
+    signature: Signature,
+) -> Result<FuncID<false>, JeffToHugrError> {
+    if let Some(&decl) = runtime_fns.get(&name) {
+        return Ok(decl);
+    }
+    let decl = module.declare(&name, signature.into())?;
+    runtime_fns.insert(name, decl);
+    Ok(decl)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::extension::qureg_type;
+    use hugr::builder::HugrBuilder;
+    use hugr::extension::prelude::qb_t;
+    use hugr::ops::handle::NodeHandle;
+    use rstest::rstest;
+
+    /// A module with a helper on a register, and an entry function calling
+    /// it and returning the register with a qubit.
+    ///
+    /// Returns the module and the function nodes, helper first.
+    fn register_module() -> (Hugr, [Node; 2]) {
+        let mut builder = ModuleBuilder::new();
+        let helper_sig = Signature::new_endo(vec![qureg_type()]);
+        let helper = builder.define_function("helper", helper_sig).unwrap();
+        let inputs = helper.input_wires();
+        let helper = helper.finish_with_outputs(inputs).unwrap();
+
+        let main_sig = Signature::new_endo(vec![qureg_type(), qb_t()]);
+        let mut main = builder.define_function("main", main_sig).unwrap();
+        let [reg, q] = main.input_wires_arr();
+        let call = main.call(helper.handle(), &[], [reg]).unwrap();
+        let main = main.finish_with_outputs([call.out_wire(0), q]).unwrap();
+
+        let functions = [helper.node(), main.node()];
+        (builder.finish_hugr().unwrap(), functions)
+    }
+
+    fn function(hugr: &Hugr, name: &str) -> Option<Node> {
+        hugr.children(hugr.module_root())
+            .find(|&node| match hugr.get_optype(node) {
+                OpType::FuncDefn(defn) => defn.func_name() == name,
+                OpType::FuncDecl(decl) => decl.func_name() == name,
+                _ => false,
+            })
+    }
+
+    #[rstest]
+    fn wrap_entrypoints() {
+        let (mut hugr, [helper, main]) = register_module();
+        wrap_handle_functions(&mut hugr, &HandleAbi::Entrypoints, &[helper, main]).unwrap();
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        // The entry function has a handle signature.
+        let wrapper = function(&hugr, "main").unwrap();
+        let OpType::FuncDefn(defn) = hugr.get_optype(wrapper) else {
+            panic!("main is not a definition");
+        };
+        let handle_sig = Signature::new_endo(vec![usize_t(), qb_t()]);
+        assert_eq!(defn.signature().body(), &handle_sig);
+
+        // The body and the helper keep their register signatures, and the
+        // helper is still called directly.
+        assert_eq!(function(&hugr, "main__jeff_impl"), Some(main));
+        let OpType::FuncDefn(defn) = hugr.get_optype(helper) else {
+            panic!("helper is not a definition");
+        };
+        assert_eq!(defn.func_name(), "helper");
+        assert_eq!(defn.signature().body().input_types(), &[qureg_type()]);
+
+        // The wrapper converts the register on both sides of the call.
+        let calls = hugr
+            .children(wrapper)
+            .filter_map(|node| hugr.static_source(node))
+            .collect_vec();
+        let from_handle = function(&hugr, "jeff_rt_qureg_from_handle").unwrap();
+        let to_handle = function(&hugr, "jeff_rt_handle_from_qureg").unwrap();
+        assert_eq!(calls.len(), 3);
+        for target in [from_handle, main, to_handle] {
+            assert!(calls.contains(&target));
+        }
+    }
+
+    #[rstest]
+    fn wrap_selected_functions() {
+        let (mut hugr, functions) = register_module();
+        let abi = HandleAbi::Functions(["helper".to_string(), "main".to_string()].into());
+        wrap_handle_functions(&mut hugr, &abi, &functions).unwrap();
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        assert!(function(&hugr, "helper__jeff_impl").is_some());
+        assert!(function(&hugr, "main__jeff_impl").is_some());
+        // The conversions are declared once.
+        let decls = hugr
+            .children(hugr.module_root())
+            .filter(|&node| hugr.get_optype(node).is_func_decl())
+            .count();
+        assert_eq!(decls, 2);
+    }
+
+    #[rstest]
+    fn disabled() {
+        let (mut hugr, functions) = register_module();
+        let nodes = hugr.num_nodes();
+        wrap_handle_functions(&mut hugr, &HandleAbi::Disabled, &functions).unwrap();
+        assert_eq!(hugr.num_nodes(), nodes);
+    }
+}
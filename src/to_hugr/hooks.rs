@@ -0,0 +1,523 @@
+//! Extension points for overriding the translation of _jeff_ operations.
+//!
+//! See [`ConversionHook`].
+
+use std::fmt;
+use std::panic::RefUnwindSafe;
+use std::sync::Arc;
+
+use hugr::builder::{BuildError, Dataflow};
+use hugr::ops::{OpType, Value};
+use hugr::{Hugr, IncomingPort, Node, OutgoingPort, Wire};
+
+use super::BuildContext;
+use crate::JeffToHugrError;
+
+/// A user-defined translation of _jeff_ operations, consulted before the
+/// built-in translation.
+///
+/// Hooks are registered in [`crate::JeffToHugrConfig::hooks`], and tried in
+/// order for every operation. The first hook returning `Some` handles the
+/// operation.
+///
+/// Hooks must be [`RefUnwindSafe`], so a configuration can be shared with a
+/// translation run under [`std::panic::catch_unwind`].
+///
+/// # Example
+///
+/// A hook translating the custom `cphase` gates into an operation from
+/// another extension.
+///
+/// ```
+/// use hugr_jeff::hugr::ops::OpType;
+/// use hugr_jeff::jeff::reader::optype::{GateOpType, OpType as JeffOpType, QubitOp};
+/// use hugr_jeff::{BuildContext, ConversionHook, DataflowDyn, JeffToHugrError};
+///
+/// struct CPhaseHook {
+///     op: OpType,
+/// }
+///
+/// impl ConversionHook for CPhaseHook {
+///     fn try_convert(
+///         &self,
+///         op: &hugr_jeff::jeff::reader::Operation<'_>,
+///         builder: &mut dyn DataflowDyn,
+///         ctx: &mut BuildContext,
+///     ) -> Option<Result<(), JeffToHugrError>> {
+///         let JeffOpType::QubitOp(QubitOp::Gate(gate)) = op.op_type() else {
+///             return None;
+///         };
+///         match gate.gate_type {
+///             GateOpType::Custom { name, .. } if name == "cphase" => {
+///                 Some(ctx.build_single_op(self.op.clone(), op, builder))
+///             }
+///             _ => None,
+///         }
+///     }
+/// }
+/// ```
+pub trait ConversionHook: Send + Sync + RefUnwindSafe {
+    /// Translate a _jeff_ operation, or return `None` to fall back to the
+    /// next hook and then to the built-in translation.
+    ///
+    /// The hook must register the operation inputs and outputs with
+    /// [`BuildContext::register_input`] and [`BuildContext::register_output`],
+    /// or use [`BuildContext::build_single_op`] or
+    /// [`BuildContext::build_op_sequence`] to do so. Explicit copies of
+    /// values, which the _jeff_ reader does not expose as a dedicated
+    /// operation, can be translated with [`BuildContext::build_copy_op`].
+    fn try_convert(
+        &self,
+        op: &jeff::reader::Operation<'_>,
+        builder: &mut dyn DataflowDyn,
+        ctx: &mut BuildContext,
+    ) -> Option<Result<(), JeffToHugrError>>;
+
+    /// A name identifying the hook in debug output.
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+}
+
+/// The list of [`ConversionHook`]s in a [`crate::JeffToHugrConfig`].
+///
+/// Two lists are equal if they contain the same hook instances.
+#[derive(Clone, Default)]
+pub struct ConversionHooks(Vec<Arc<dyn ConversionHook>>);
+
+impl ConversionHooks {
+    /// Append a hook, tried after the already registered ones.
+    pub fn push(&mut self, hook: impl ConversionHook + 'static) {
+        self.0.push(Arc::new(hook));
+    }
+
+    /// Returns `true` if no hook is registered.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the registered hooks, in the order they are tried.
+    pub fn iter(&self) -> impl Iterator<Item = &dyn ConversionHook> + '_ {
+        self.0.iter().map(|hook| hook.as_ref())
+    }
+}
+
+impl fmt::Debug for ConversionHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.iter().map(|hook| hook.name()))
+            .finish()
+    }
+}
+
+impl PartialEq for ConversionHooks {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.len() == other.0.len()
+            && self.0.iter().zip(&other.0).all(|(a, b)| Arc::ptr_eq(a, b))
+    }
+}
+
+/// A dyn-compatible subset of the [`Dataflow`] builder operations, used by
+/// the [`ConversionHook`]s.
+pub trait DataflowDyn {
+    /// Returns the HUGR being built.
+    fn hugr(&self) -> &Hugr;
+
+    /// Returns the HUGR being built, mutably.
+    fn hugr_mut(&mut self) -> &mut Hugr;
+
+    /// Add an operation to the dataflow region, without connecting it.
+    fn add_child_node(&mut self, op: OpType) -> Node;
+
+    /// Add a constant value to the region and load it, returning the loaded wire.
+    fn add_load_value(&mut self, value: Value) -> Wire;
+
+    /// Add an operation to the dataflow region connected to the given inputs,
+    /// returning its output wires.
+    fn add_dataflow_op(&mut self, op: OpType, inputs: Vec<Wire>) -> Result<Vec<Wire>, BuildError>;
+}
+
+impl<T: Dataflow> DataflowDyn for T {
+    fn hugr(&self) -> &Hugr {
+        hugr::builder::Container::hugr(self)
+    }
+
+    fn hugr_mut(&mut self) -> &mut Hugr {
+        hugr::builder::Container::hugr_mut(self)
+    }
+
+    fn add_child_node(&mut self, op: OpType) -> Node {
+        hugr::builder::Container::add_child_node(self, op)
+    }
+
+    fn add_load_value(&mut self, value: Value) -> Wire {
+        Dataflow::add_load_value(self, value)
+    }
+
+    fn add_dataflow_op(&mut self, op: OpType, inputs: Vec<Wire>) -> Result<Vec<Wire>, BuildError> {
+        Ok(Dataflow::add_dataflow_op(self, op, inputs)?.outputs().collect())
+    }
+}
+
+/// The wiring of a sequence of HUGR operations translating a single _jeff_
+/// operation, see [`BuildContext::build_op_sequence`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SeqIo {
+    /// The nodes of the sequence, in execution order.
+    ///
+    /// Their provenance is recorded, and quantum sequences are ordered after
+    /// the previous quantum operation through their first and last nodes.
+    pub nodes: Vec<Node>,
+    /// The ports consuming each _jeff_ input, in order.
+    ///
+    /// An input may be consumed by several ports, or by none.
+    pub inputs: Vec<Vec<(Node, IncomingPort)>>,
+    /// The port producing each _jeff_ output, in order.
+    pub outputs: Vec<(Node, OutgoingPort)>,
+}
+
+impl SeqIo {
+    /// The wiring of a single node, with one value port per _jeff_ input
+    /// and output.
+    pub fn single_node(node: Node, inputs: usize, outputs: usize) -> Self {
+        Self {
+            nodes: vec![node],
+            inputs: (0..inputs)
+                .map(|port| vec![(node, IncomingPort::from(port))])
+                .collect(),
+            outputs: (0..outputs)
+                .map(|port| (node, OutgoingPort::from(port)))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+
+    use super::*;
+    use crate::extension::{JeffOp, OpBuildError};
+    use crate::optype::tket_ops::{self, QuantumOp};
+    use crate::optype::{WellKnownTranslation, classify_well_known};
+    use crate::test::{assert_hugr_equivalent, catalyst_simple, qubits};
+    use crate::{JeffToHugrConfig, Protection, jeff_to_hugr_with_config, metadata, protected_ops};
+    use hugr::HugrView;
+    use hugr::extension::prelude::qb_t;
+    use hugr::hugr::hugrmut::HugrMut;
+    use hugr::std_extensions::arithmetic::float_ops::FloatOps;
+    use hugr::std_extensions::arithmetic::float_types::ConstF64;
+    use jeff::Jeff;
+    use jeff::reader::optype::{GateOpType, OpType as JeffOpType, QubitOp};
+    use rstest::rstest;
+    use tket::extension::rotation::RotationOp;
+
+    /// Translates the custom gates into [`JeffOp::QGate`]s named `hooked`.
+    struct RenameCustomGates;
+
+    impl ConversionHook for RenameCustomGates {
+        fn try_convert(
+            &self,
+            op: &jeff::reader::Operation<'_>,
+            builder: &mut dyn DataflowDyn,
+            ctx: &mut BuildContext,
+        ) -> Option<Result<(), JeffToHugrError>> {
+            let JeffOpType::QubitOp(QubitOp::Gate(gate)) = op.op_type() else {
+                return None;
+            };
+            let GateOpType::Custom { .. } = gate.gate_type else {
+                return None;
+            };
+            let renamed = JeffOp::jeff_gate_op("hooked", gate);
+            Some(ctx.build_single_op(renamed.into_extension_op(), op, builder))
+        }
+    }
+
+    /// Translates the custom gates into [`JeffOp::QGate`]s marked as
+    /// protected from optimization.
+    struct ProtectCustomGates;
+
+    impl ConversionHook for ProtectCustomGates {
+        fn try_convert(
+            &self,
+            op: &jeff::reader::Operation<'_>,
+            builder: &mut dyn DataflowDyn,
+            ctx: &mut BuildContext,
+        ) -> Option<Result<(), JeffToHugrError>> {
+            let JeffOpType::QubitOp(QubitOp::Gate(gate)) = op.op_type() else {
+                return None;
+            };
+            let GateOpType::Custom { .. } = gate.gate_type else {
+                return None;
+            };
+            let gate_op = JeffOp::jeff_gate_op("protected", gate).into_extension_op();
+            let (inputs, outputs) = (op.input_count(), op.output_count());
+            Some(ctx.build_op_sequence(
+                |builder| {
+                    let node = builder.add_child_node(gate_op.into());
+                    builder
+                        .hugr_mut()
+                        .set_metadata(node, metadata::PROTECTED, true);
+                    Ok::<_, JeffToHugrError>(SeqIo::single_node(node, inputs, outputs))
+                },
+                op,
+                builder,
+            ))
+        }
+    }
+
+    /// Translates the custom gates into an invalid [`JeffOp::DebugPrint`] of
+    /// their qubits.
+    struct PrintQubits;
+
+    impl ConversionHook for PrintQubits {
+        fn try_convert(
+            &self,
+            op: &jeff::reader::Operation<'_>,
+            builder: &mut dyn DataflowDyn,
+            ctx: &mut BuildContext,
+        ) -> Option<Result<(), JeffToHugrError>> {
+            let JeffOpType::QubitOp(QubitOp::Gate(gate)) = op.op_type() else {
+                return None;
+            };
+            let GateOpType::Custom { .. } = gate.gate_type else {
+                return None;
+            };
+            let print = JeffOp::DebugPrint {
+                format: String::new(),
+                types: vec![qb_t(); gate.num_qubits()],
+            };
+            Some(match print.try_into_extension_op() {
+                Ok(print) => ctx.build_single_op(print, op, builder),
+                Err(e) => Err(e.into()),
+            })
+        }
+    }
+
+    fn gate_names(config: &JeffToHugrConfig, jeff: &Jeff<'static>) -> Vec<String> {
+        let hugr = jeff_to_hugr_with_config(jeff, config).unwrap().hugr;
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        hugr.nodes()
+            .filter_map(|n| match hugr.get_optype(n).cast::<JeffOp>()? {
+                JeffOp::QGate { name, .. } => Some(name),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[rstest]
+    fn hook_precedence(qubits: Jeff<'static>) {
+        let default_names = gate_names(&JeffToHugrConfig::default(), &qubits);
+        assert!(!default_names.is_empty());
+        assert!(default_names.iter().all(|name| name != "hooked"));
+
+        let mut config = JeffToHugrConfig::default();
+        config.hooks.push(RenameCustomGates);
+        let hooked_names = gate_names(&config, &qubits);
+        assert_eq!(hooked_names.len(), default_names.len());
+        assert!(hooked_names.iter().any(|name| name == "hooked"));
+    }
+
+    #[rstest]
+    fn invalid_hook_op(qubits: Jeff<'static>) {
+        let mut config = JeffToHugrConfig::default();
+        config.hooks.push(PrintQubits);
+        let err = jeff_to_hugr_with_config(&qubits, &config).unwrap_err();
+        assert!(matches!(
+            err,
+            JeffToHugrError::InvalidHugrOp(OpBuildError::InvalidOp { ref op, .. })
+                if op.starts_with("DebugPrint")
+        ));
+    }
+
+    /// The operations the hooks mark as protected are encoded after the
+    /// translation.
+    #[rstest]
+    #[case::barriers(Protection::Barriers)]
+    #[case::container(Protection::Container)]
+    fn hook_protected_ops(qubits: Jeff<'static>, #[case] encoding: Protection) {
+        let mut config = JeffToHugrConfig::default().with_protect(encoding);
+        config.hooks.push(ProtectCustomGates);
+        let hugr = jeff_to_hugr_with_config(&qubits, &config).unwrap().hugr;
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        let gates = hugr
+            .nodes()
+            .filter(|&n| {
+                matches!(
+                    hugr.get_optype(n).cast::<JeffOp>(),
+                    Some(JeffOp::QGate { name, .. }) if name == "protected"
+                )
+            })
+            .collect::<BTreeSet<_>>();
+        assert!(!gates.is_empty());
+        assert_eq!(protected_ops(&hugr), gates);
+
+        let markers = hugr
+            .nodes()
+            .filter_map(|n| hugr.get_metadata(n, metadata::PROTECTED))
+            .collect::<Vec<_>>();
+        match encoding {
+            Protection::Barriers => assert!(markers.iter().any(|&m| *m == "begin")),
+            _ => assert!(markers.iter().any(|&m| *m == "container")),
+        }
+    }
+
+    #[rstest]
+    fn hooks_equality() {
+        let mut hooks = ConversionHooks::default();
+        hooks.push(RenameCustomGates);
+        assert_eq!(hooks, hooks.clone());
+        let mut other = ConversionHooks::default();
+        other.push(RenameCustomGates);
+        assert_ne!(hooks, other);
+        assert_eq!(format!("{hooks:?}").matches("RenameCustomGates").count(), 1);
+    }
+
+    /// Translates the non-destructive measurements as a `Measure` followed
+    /// by a `Reset` of the measured qubit.
+    struct MeasureAndReset;
+
+    impl ConversionHook for MeasureAndReset {
+        fn try_convert(
+            &self,
+            op: &jeff::reader::Operation<'_>,
+            builder: &mut dyn DataflowDyn,
+            ctx: &mut BuildContext,
+        ) -> Option<Result<(), JeffToHugrError>> {
+            let JeffOpType::QubitOp(QubitOp::MeasureNd) = op.op_type() else {
+                return None;
+            };
+            let sequence = |builder: &mut dyn DataflowDyn| {
+                let measure = builder.add_child_node(tket_ops::measure());
+                let reset = builder.add_child_node(tket_ops::reset());
+                builder.hugr_mut().connect(measure, 0, reset, 0);
+                Ok::<_, JeffToHugrError>(SeqIo {
+                    nodes: vec![measure, reset],
+                    inputs: vec![vec![(measure, IncomingPort::from(0))]],
+                    outputs: vec![
+                        (reset, OutgoingPort::from(0)),
+                        (measure, OutgoingPort::from(1)),
+                    ],
+                })
+            };
+            Some(ctx.build_op_sequence(sequence, op, builder))
+        }
+    }
+
+    /// Translates the rotation gates by wiring the angle conversions
+    /// directly, as done before [`BuildContext::build_op_sequence`].
+    struct DirectRotations;
+
+    impl ConversionHook for DirectRotations {
+        fn try_convert(
+            &self,
+            op: &jeff::reader::Operation<'_>,
+            builder: &mut dyn DataflowDyn,
+            ctx: &mut BuildContext,
+        ) -> Option<Result<(), JeffToHugrError>> {
+            let JeffOpType::QubitOp(QubitOp::Gate(gate)) = op.op_type() else {
+                return None;
+            };
+            let gate = gate.normalize();
+            let GateOpType::WellKnown(well_known) = gate.gate_type else {
+                return None;
+            };
+            let WellKnownTranslation::Rotation(tket_op) = classify_well_known(well_known, gate)
+            else {
+                return None;
+            };
+            Some(direct_rotation(tket_op, op, builder, ctx))
+        }
+    }
+
+    fn direct_rotation(
+        tket_op: QuantumOp,
+        op: &jeff::reader::Operation<'_>,
+        builder: &mut dyn DataflowDyn,
+        ctx: &mut BuildContext,
+    ) -> Result<(), JeffToHugrError> {
+        let node = builder.add_child_node(tket_op.into());
+        ctx.record_origin(node);
+        ctx.order_quantum_op(node, builder);
+
+        let pi = builder.add_load_value(ConstF64::new(std::f64::consts::PI).into());
+        let div = builder.add_child_node(FloatOps::fdiv.into());
+        let rot = builder.add_dataflow_op(
+            RotationOp::from_halfturns_unchecked.into(),
+            vec![Wire::new(div, 0)],
+        )?[0];
+        builder.hugr_mut().connect(pi.node(), pi.source(), div, 1);
+        builder.hugr_mut().connect(rot.node(), rot.source(), node, 1);
+
+        ctx.register_input(op.input(0).unwrap()?.id(), node, IncomingPort::from(0));
+        ctx.register_input(op.input(1).unwrap()?.id(), div, IncomingPort::from(0));
+        ctx.register_output(op.output(0).unwrap()?.id(), node, OutgoingPort::from(0));
+        Ok(())
+    }
+
+    fn count_op(hugr: &impl HugrView<Node = Node>, op: QuantumOp) -> usize {
+        hugr.nodes()
+            .filter(|&n| QuantumOp::of(hugr.get_optype(n)) == Some(op))
+            .count()
+    }
+
+    #[rstest]
+    fn measure_and_reset_sequence(catalyst_simple: Jeff<'static>) {
+        let plain = jeff_to_hugr_with_config(&catalyst_simple, &JeffToHugrConfig::default())
+            .unwrap()
+            .hugr;
+        let mut config = JeffToHugrConfig::default();
+        config.hooks.push(MeasureAndReset);
+        let hugr = jeff_to_hugr_with_config(&catalyst_simple, &config)
+            .unwrap()
+            .hugr;
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        let measures = count_op(&hugr, QuantumOp::Measure);
+        assert!(measures > 0);
+        assert_eq!(measures, count_op(&plain, QuantumOp::Measure));
+        assert_eq!(
+            count_op(&hugr, QuantumOp::Reset),
+            count_op(&plain, QuantumOp::Reset) + measures
+        );
+
+        for measure in hugr
+            .nodes()
+            .filter(|&n| QuantumOp::of(hugr.get_optype(n)) == Some(QuantumOp::Measure))
+        {
+            // The measured qubit is only consumed by the reset.
+            let qubit_targets = hugr.linked_inputs(measure, 0).collect::<Vec<_>>();
+            let [(reset, port)] = qubit_targets.as_slice() else {
+                panic!("Measured qubit has {} targets", qubit_targets.len());
+            };
+            assert_eq!(QuantumOp::of(hugr.get_optype(*reset)), Some(QuantumOp::Reset));
+            assert_eq!(port.index(), 0);
+            assert_eq!(hugr.linked_outputs(measure, 0).count(), 1);
+            assert_eq!(hugr.linked_inputs(*reset, 0).count(), 1);
+        }
+    }
+
+    /// The rotations built as an op sequence match the direct wiring.
+    #[rstest]
+    fn rotation_sequence_unchanged(catalyst_simple: Jeff<'static>) {
+        let config = JeffToHugrConfig {
+            exact_angles: None,
+            ..Default::default()
+        };
+        let sequence = jeff_to_hugr_with_config(&catalyst_simple, &config)
+            .unwrap()
+            .hugr;
+
+        let mut direct_config = config.clone();
+        direct_config.hooks.push(DirectRotations);
+        let direct = jeff_to_hugr_with_config(&catalyst_simple, &direct_config)
+            .unwrap()
+            .hugr;
+        direct.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        let rotations = [QuantumOp::Rx, QuantumOp::Ry, QuantumOp::Rz];
+        assert!(rotations.iter().any(|&op| count_op(&sequence, op) > 0));
+        assert_hugr_equivalent(&sequence, &direct);
+    }
+}
@@ -0,0 +1,473 @@
+//! Incremental re-translation of _jeff_ modules.
+//!
+//! See [`jeff_to_hugr_incremental`].
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use hugr::envelope::EnvelopeConfig;
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::hugr::views::ExtractionResult;
+use hugr::ops::OpType;
+use hugr::types::Type as HugrType;
+use hugr::{Hugr, HugrView, IncomingPort, Node};
+use jeff::Jeff;
+use jeff::reader::optype as jeff_optype;
+use jeff::reader::{FunctionId, ReadJeff, Region};
+use serde::{Deserialize, Serialize};
+
+use super::{BuildContext, angle_params};
+use crate::extension::ConstIntReg;
+use crate::hugr_importer::extension_registry;
+use crate::provenance::Provenance;
+use crate::{FunctionDedup, HandleAbi, JeffToHugrConfig, JeffToHugrError};
+
+/// Metadata key used to record the static inputs of a cached function that
+/// are connected to module-level nodes.
+const STATIC_LINK: &str = "jeff.static_link";
+
+/// Translated functions from a previous conversion, reused by
+/// [`jeff_to_hugr_incremental`] when their _jeff_ definition is unchanged.
+///
+/// The cache can be stored with `serde` for reuse across processes. Function
+/// hashes are only stable for a given build of this crate.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConversionCache {
+    /// Hash of the translation configuration the functions were converted with.
+    config_hash: u64,
+    /// The translated function definitions, by _jeff_ function name.
+    functions: BTreeMap<String, CachedFunction>,
+    /// Names of the functions translated from scratch when building this cache.
+    #[serde(skip)]
+    converted: BTreeSet<String>,
+}
+
+impl ConversionCache {
+    /// Returns the names of the functions that were translated from scratch,
+    /// rather than reused from the previous cache, when this cache was built.
+    pub fn converted_functions(&self) -> impl Iterator<Item = &str> + '_ {
+        self.converted.iter().map(String::as_str)
+    }
+
+    /// Returns the number of cached function definitions.
+    pub fn len(&self) -> usize {
+        self.functions.len()
+    }
+
+    /// Returns `true` if the cache contains no function.
+    pub fn is_empty(&self) -> bool {
+        self.functions.is_empty()
+    }
+}
+
+/// A translated function definition.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedFunction {
+    /// Structural hash of the _jeff_ function definition.
+    hash: u64,
+    /// A HUGR whose entrypoint is the translated function definition.
+    ///
+    /// Static inputs connected to module-level nodes are left disconnected,
+    /// and described by a [`STATIC_LINK`] metadata entry.
+    #[serde(with = "envelope")]
+    hugr: Hugr,
+}
+
+/// A module-level node connected to a static input of a cached function.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum StaticLink {
+    /// A translated _jeff_ function, by HUGR name.
+    Function { port: usize, name: String },
+    /// A select function for a value type.
+    Select { port: usize, ty: HugrType },
+    /// A shared constant array.
+    Constant { port: usize, value: ConstIntReg },
+}
+
+/// Translate a _jeff_ program into a HUGR, reusing the functions of a
+/// previous translation whose definitions did not change.
+///
+/// Calls between functions are re-connected in the new HUGR. Functions are
+/// matched by name, so renamed functions are translated from scratch.
+///
+/// Returns the new HUGR, together with an updated cache for the next call.
+pub fn jeff_to_hugr_incremental(
+    new: &Jeff,
+    previous: &ConversionCache,
+) -> Result<(Hugr, ConversionCache), JeffToHugrError> {
+    jeff_to_hugr_incremental_with_config(new, previous, &JeffToHugrConfig::default())
+}
+
+/// Translate a _jeff_ program into a HUGR using the given configuration,
+/// reusing the functions of a previous translation whose definitions did not
+/// change.
+///
+/// Cached functions translated with a different configuration are not reused.
+/// No function is reused when [`JeffToHugrConfig::inline_calls`] is set, as
+/// the translated callers depend on the bodies of the functions they call,
+/// nor when [`JeffToHugrConfig::handle_abi`] wraps some functions or
+/// [`JeffToHugrConfig::dedup_functions`] merges them.
+///
+/// See [`jeff_to_hugr_incremental`].
+pub fn jeff_to_hugr_incremental_with_config(
+    new: &Jeff,
+    previous: &ConversionCache,
+    config: &JeffToHugrConfig,
+) -> Result<(Hugr, ConversionCache), JeffToHugrError> {
+    let module = new.module();
+    let hashes = function_hashes(&module, config)?;
+    let config_hash = config_hash(config);
+
+    let mut reuse = BTreeMap::new();
+    let reusable = !config.inline_calls
+        && config.handle_abi == HandleAbi::Disabled
+        && config.dedup_functions == FunctionDedup::Disabled;
+    if previous.config_hash == config_hash && reusable {
+        for (func_id, func) in module.functions().enumerate() {
+            let (Some(hash), Some(cached)) = (hashes[func_id], previous.functions.get(func.name()))
+            else {
+                continue;
+            };
+            if cached.hash == hash {
+                reuse.insert(func_id as FunctionId, cached.hugr.clone());
+            }
+        }
+    }
+
+    let mut hugr = Hugr::default();
+    let mut ctx = BuildContext::with_config(config);
+    let map = ctx.build_into_module(module, &mut hugr, None, &reuse)?;
+    ctx.check_module(&hugr)?;
+
+    let mut cache = ConversionCache {
+        config_hash,
+        ..Default::default()
+    };
+    for (func_id, func) in new.module().functions().enumerate() {
+        let Some(hash) = hashes[func_id] else {
+            continue;
+        };
+        let name = func.name().to_string();
+        let cached = match reuse.remove(&(func_id as FunctionId)) {
+            Some(hugr) => CachedFunction { hash, hugr },
+            None => {
+                cache.converted.insert(name.clone());
+                let fn_node = map.function_nodes()[func_id];
+                CachedFunction {
+                    hash,
+                    hugr: extract_function(&hugr, fn_node),
+                }
+            }
+        };
+        cache.functions.insert(name, cached);
+    }
+    Provenance::new(config).store(&mut hugr);
+    Ok((hugr, cache))
+}
+
+impl BuildContext {
+    /// Insert a cached function definition into the module root, registering
+    /// its static inputs to be connected once the module is built.
+    ///
+    /// `function_ids` maps HUGR function names to their _jeff_ id.
+    pub(super) fn splice_function(
+        &mut self,
+        hugr: &mut Hugr,
+        cached: Hugr,
+        function_ids: &BTreeMap<String, FunctionId>,
+    ) -> Result<Node, JeffToHugrError> {
+        let module_root = hugr.module_root();
+        let inserted = hugr.insert_hugr(module_root, cached);
+        for &node in inserted.node_map.values() {
+            let Some(link) = hugr.get_metadata(node, STATIC_LINK).cloned() else {
+                continue;
+            };
+            hugr.remove_metadata(node, STATIC_LINK);
+            let link: StaticLink = serde_json::from_value(link).map_err(|e| {
+                JeffToHugrError::InvalidConversionCache {
+                    reason: format!("invalid static link: {e}"),
+                }
+            })?;
+            match link {
+                StaticLink::Function { port, name } => {
+                    let Some(&func_id) = function_ids.get(&name) else {
+                        return Err(JeffToHugrError::InvalidConversionCache {
+                            reason: format!("call to missing function '{name}'"),
+                        });
+                    };
+                    self.function_calls
+                        .entry(func_id)
+                        .or_default()
+                        .push((node, IncomingPort::from(port)));
+                }
+                StaticLink::Select { port, ty } => {
+                    self.register_select_call(ty, node, IncomingPort::from(port));
+                }
+                StaticLink::Constant { port, value } => {
+                    self.shared_constants
+                        .entry(value)
+                        .or_default()
+                        .push((node, IncomingPort::from(port)));
+                }
+            }
+        }
+        Ok(inserted.inserted_entrypoint)
+    }
+}
+
+/// Copy a function definition into a standalone HUGR, recording its static
+/// inputs connected to other module-level nodes as [`STATIC_LINK`] metadata.
+fn extract_function(hugr: &Hugr, fn_node: Node) -> Hugr {
+    let module_root = hugr.module_root();
+    let (mut extracted, node_map) = hugr.extract_hugr(fn_node);
+    extracted.set_entrypoint(node_map.extracted_node(fn_node));
+
+    for node in hugr.descendants(fn_node) {
+        let Some(port) = hugr.get_optype(node).static_input_port() else {
+            continue;
+        };
+        let Some((src, _)) = hugr.single_linked_output(node, port) else {
+            continue;
+        };
+        if hugr.get_parent(src) != Some(module_root) {
+            continue;
+        }
+        let port = port.index();
+        let link = match hugr.get_optype(src) {
+            OpType::FuncDefn(defn) if defn.func_name().contains("__jeff_select_") => {
+                let ty = defn.signature().body().output()[0].clone();
+                StaticLink::Select { port, ty }
+            }
+            OpType::FuncDefn(defn) => StaticLink::Function {
+                port,
+                name: defn.func_name().to_string(),
+            },
+            OpType::FuncDecl(decl) => StaticLink::Function {
+                port,
+                name: decl.func_name().to_string(),
+            },
+            OpType::Const(c) => match c.value().get_custom_value::<ConstIntReg>() {
+                Some(value) => StaticLink::Constant {
+                    port,
+                    value: value.clone(),
+                },
+                None => continue,
+            },
+            _ => continue,
+        };
+        extracted.set_metadata(
+            node_map.extracted_node(node),
+            STATIC_LINK,
+            serde_json::to_value(link).unwrap(),
+        );
+    }
+    extracted
+}
+
+/// Hash of the settings that affect the translation of every function.
+fn config_hash(config: &JeffToHugrConfig) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    env!("CARGO_PKG_VERSION").hash(&mut hasher);
+    // The progress reports do not change the translation.
+    let config = JeffToHugrConfig {
+        progress: None,
+        ..config.clone()
+    };
+    format!("{config:?}").hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Compute a structural hash for each function definition in a module.
+///
+/// Calls are hashed by the name and signature of the callee, so that
+/// inserting or removing other functions does not invalidate the caller.
+/// Declarations have no hash.
+fn function_hashes(
+    module: &jeff::reader::Module<'_>,
+    config: &JeffToHugrConfig,
+) -> Result<Vec<Option<u64>>, JeffToHugrError> {
+    let signatures = call_signatures(module)?;
+    let called = match config.symbolize_angle_params {
+        true => angle_params::called_functions(module)?,
+        false => BTreeSet::new(),
+    };
+
+    module
+        .functions()
+        .enumerate()
+        .map(|(func_id, func)| {
+            let jeff::reader::Function::Definition(def) = func else {
+                return Ok(None);
+            };
+            let mut hasher = DefaultHasher::new();
+            signatures[func_id].hash(&mut hasher);
+            called.contains(&(func_id as FunctionId)).hash(&mut hasher);
+            // Provenance metadata records the function id.
+            if config.record_provenance {
+                func_id.hash(&mut hasher);
+            }
+            hash_region(&def.body(), &signatures, &mut hasher)?;
+            Ok(Some(hasher.finish()))
+        })
+        .collect()
+}
+
+/// The name and types of each function in a module, identifying the callee
+/// of the calls hashed by [`hash_region`].
+pub(super) fn call_signatures(
+    module: &jeff::reader::Module<'_>,
+) -> Result<Vec<String>, JeffToHugrError> {
+    module
+        .functions()
+        .map(|func| Ok(format!("{}{}", func.name(), type_signature(&func)?)))
+        .collect()
+}
+
+/// The input and output types of a function, as `(inputs) -> (outputs)`.
+pub(super) fn type_signature(func: &jeff::reader::Function<'_>) -> Result<String, JeffToHugrError> {
+    let inputs = func
+        .input_types()
+        .map(|port| Ok(port?.ty().to_string()))
+        .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+    let outputs = func
+        .output_types()
+        .map(|port| Ok(port?.ty().to_string()))
+        .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+    Ok(format!("({}) -> ({})", inputs.join(", "), outputs.join(", ")))
+}
+
+/// Hash the values and operations of a region, including nested regions.
+///
+/// Calls are hashed by the entry of `signatures` for the callee.
+pub(super) fn hash_region(
+    region: &Region<'_>,
+    signatures: &[String],
+    hasher: &mut DefaultHasher,
+) -> Result<(), JeffToHugrError> {
+    for value in region.sources().chain(region.targets()) {
+        let value = value?;
+        (value.id(), value.ty().to_string()).hash(hasher);
+    }
+    for op in region.operations() {
+        for value in op.inputs().chain(op.outputs()) {
+            let value = value?;
+            (value.id(), value.ty().to_string()).hash(hasher);
+        }
+        match op.op_type() {
+            jeff_optype::OpType::FuncOp(call) => {
+                signatures[call.func_idx as usize].hash(hasher);
+            }
+            jeff_optype::OpType::ControlFlowOp(cf) => {
+                format!("{cf:?}").hash(hasher);
+                match cf {
+                    jeff_optype::ControlFlowOp::Switch(switch_op) => {
+                        for idx in 0..switch_op.branch_count() {
+                            hash_region(&switch_op.branch(idx), signatures, hasher)?;
+                        }
+                        if let Some(default_branch) = switch_op.default_branch() {
+                            hash_region(&default_branch, signatures, hasher)?;
+                        }
+                    }
+                    jeff_optype::ControlFlowOp::DoWhile { body, condition }
+                    | jeff_optype::ControlFlowOp::While { body, condition } => {
+                        hash_region(&body, signatures, hasher)?;
+                        hash_region(&condition, signatures, hasher)?;
+                    }
+                    jeff_optype::ControlFlowOp::For { region } => {
+                        hash_region(&region, signatures, hasher)?
+                    }
+                }
+            }
+            op_type => format!("{op_type:?}").hash(hasher),
+        }
+    }
+    Ok(())
+}
+
+/// Store cached HUGRs as text envelopes.
+mod envelope {
+    use super::*;
+
+    pub fn serialize<S: serde::Serializer>(hugr: &Hugr, serializer: S) -> Result<S::Ok, S::Error> {
+        let envelope = hugr
+            .store_str(EnvelopeConfig::text())
+            .map_err(serde::ser::Error::custom)?;
+        serializer.serialize_str(&envelope)
+    }
+
+    pub fn deserialize<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Hugr, D::Error> {
+        let envelope = String::deserialize(deserializer)?;
+        Hugr::load_str(envelope, Some(&extension_registry())).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{assert_hugr_equivalent, catalyst_tket_opt, qubits};
+    use crate::jeff_to_hugr;
+    use rstest::rstest;
+
+    fn num_definitions(jeff: &Jeff<'static>) -> usize {
+        jeff.module()
+            .functions()
+            .filter(|func| matches!(func, jeff::reader::Function::Definition(_)))
+            .count()
+    }
+
+    #[rstest]
+    #[case::qubits(qubits())]
+    #[case::catalyst_tket_opt(catalyst_tket_opt())]
+    fn reuse_unchanged(#[case] jeff: Jeff<'static>) {
+        let (hugr, cache) = jeff_to_hugr_incremental(&jeff, &ConversionCache::default()).unwrap();
+        assert_eq!(cache.converted_functions().count(), num_definitions(&jeff));
+        assert_eq!(cache.len(), num_definitions(&jeff));
+
+        let (rebuilt, cache) = jeff_to_hugr_incremental(&jeff, &cache).unwrap();
+        assert_eq!(cache.converted_functions().count(), 0);
+        rebuilt.validate().unwrap_or_else(|e| panic!("{e}"));
+
+        let scratch = jeff_to_hugr(&jeff).unwrap();
+        assert_hugr_equivalent(&hugr, &scratch);
+        assert_hugr_equivalent(&rebuilt, &scratch);
+    }
+
+    #[rstest]
+    fn reconvert_changed(qubits: Jeff<'static>) {
+        let (_, mut cache) = jeff_to_hugr_incremental(&qubits, &ConversionCache::default()).unwrap();
+
+        // Simulate an edit of a single function.
+        let (name, cached) = cache.functions.iter_mut().next().unwrap();
+        let name = name.clone();
+        cached.hash = cached.hash.wrapping_add(1);
+
+        let (hugr, cache) = jeff_to_hugr_incremental(&qubits, &cache).unwrap();
+        assert_eq!(cache.converted_functions().collect::<Vec<_>>(), [name.as_str()]);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        assert_hugr_equivalent(&hugr, &jeff_to_hugr(&qubits).unwrap());
+    }
+
+    #[rstest]
+    fn config_change(qubits: Jeff<'static>) {
+        let (_, cache) = jeff_to_hugr_incremental(&qubits, &ConversionCache::default()).unwrap();
+        let config = JeffToHugrConfig {
+            record_provenance: true,
+            ..Default::default()
+        };
+        let (_, cache) = jeff_to_hugr_incremental_with_config(&qubits, &cache, &config).unwrap();
+        assert_eq!(cache.converted_functions().count(), num_definitions(&qubits));
+    }
+
+    #[rstest]
+    fn serialized_cache(catalyst_tket_opt: Jeff<'static>) {
+        let (_, cache) =
+            jeff_to_hugr_incremental(&catalyst_tket_opt, &ConversionCache::default()).unwrap();
+        let json = serde_json::to_string(&cache).unwrap();
+        let cache: ConversionCache = serde_json::from_str(&json).unwrap();
+
+        let (hugr, cache) = jeff_to_hugr_incremental(&catalyst_tket_opt, &cache).unwrap();
+        assert_eq!(cache.converted_functions().count(), 0);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        assert_hugr_equivalent(&hugr, &jeff_to_hugr(&catalyst_tket_opt).unwrap());
+    }
+}
@@ -0,0 +1,241 @@
+//! Inlining of the calls between _jeff_ functions.
+//!
+//! See [`JeffToHugrConfig::inline_calls`](crate::JeffToHugrConfig::inline_calls).
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use hugr::builder::{Container, DFGBuilder, Dataflow};
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::{Hugr, HugrView};
+use itertools::Itertools;
+use jeff::reader::{Function, FunctionId};
+
+use super::{BuildContext, ResourceLimit, angle_params, function_signature};
+use crate::JeffToHugrError;
+
+/// The translated body of a function definition, inserted at each call.
+#[derive(Debug, Clone)]
+pub(crate) struct InlinedBody {
+    /// A HUGR whose entrypoint is a `DFG` with the function body.
+    hugr: Hugr,
+    /// The context the body was built with.
+    ///
+    /// Holds the static edges of the body, connected once the module is built.
+    ctx: BuildContext,
+}
+
+impl BuildContext {
+    /// Translate the bodies of the function definitions, to be inlined at
+    /// their calls.
+    ///
+    /// `names` are the HUGR names of the functions of the module.
+    ///
+    /// # Errors
+    ///
+    /// - [`JeffToHugrError::RecursiveInline`] if a function calls itself,
+    ///   directly or through other functions.
+    /// - [`JeffToHugrError::UninlinableCall`] if
+    ///   [`JeffToHugrConfig::strict_inline`](crate::JeffToHugrConfig::strict_inline)
+    ///   is set and a function calls a declared function.
+    /// - [`JeffToHugrError::ResourceLimitExceeded`] if a chain of nested calls
+    ///   is longer than
+    ///   [`ResourceLimits::max_inline_depth`](crate::ResourceLimits::max_inline_depth).
+    pub(super) fn build_inline_bodies(
+        &mut self,
+        module: &jeff::reader::Module<'_>,
+        names: &[String],
+    ) -> Result<(), JeffToHugrError> {
+        let functions = module.functions().collect_vec();
+        let calls = functions
+            .iter()
+            .map(|func| match func {
+                Function::Definition(def) => {
+                    let mut called = BTreeSet::new();
+                    angle_params::collect_calls(&def.body(), &mut called)?;
+                    Ok(Some(called))
+                }
+                Function::Declaration(_) => Ok(None),
+            })
+            .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+
+        if self.config.strict_inline {
+            let declared = calls.iter().flatten().flatten().find(|&callee| {
+                calls.get(*callee as usize).is_some_and(Option::is_none)
+                    && !self.intrinsics.contains_key(callee)
+                    && !self.view_intrinsics.contains_key(callee)
+            });
+            if let Some(&callee) = declared {
+                return Err(JeffToHugrError::UninlinableCall {
+                    function: names[callee as usize].clone(),
+                });
+            }
+        }
+
+        let order = inline_order(&calls).map_err(|func_id| JeffToHugrError::RecursiveInline {
+            function: names[func_id as usize].clone(),
+        })?;
+        let mut bodies = BTreeMap::new();
+        for (func_id, depth) in order {
+            self.config.limits.check(ResourceLimit::InlineDepth, depth)?;
+            let func = &functions[func_id as usize];
+            let Function::Definition(def) = func else {
+                continue;
+            };
+            let mut builder = DFGBuilder::new(function_signature(func)?)?;
+
+            // The body only inlines the functions it calls, built before it.
+            self.inline_bodies = Arc::new(bodies.clone());
+            let mut ctx = self.nested();
+            ctx.current_function = Some(func_id);
+            ctx.current_function_name = Some(names[func_id as usize].clone());
+            ctx.op_count = 0;
+            ctx.build_region(def.body(), &mut builder)?;
+
+            let hugr = builder.hugr().clone();
+            bodies.insert(func_id, Arc::new(InlinedBody { hugr, ctx }));
+        }
+        self.inline_bodies = Arc::new(bodies);
+        Ok(())
+    }
+
+    /// Returns the translated body of a function to inline at its calls, if any.
+    pub(crate) fn inlined_body(&self, function_id: FunctionId) -> Option<Arc<InlinedBody>> {
+        self.inline_bodies.get(&function_id).cloned()
+    }
+
+    /// Insert a function body in place of a call.
+    ///
+    /// The nodes of the body are added to the current region, and connected
+    /// to the values of the call.
+    pub(crate) fn build_inlined_call(
+        &mut self,
+        body: &InlinedBody,
+        op: &jeff::reader::Operation<'_>,
+        builder: &mut impl Dataflow,
+    ) -> Result<(), JeffToHugrError> {
+        let inputs = op
+            .inputs()
+            .map(|value| Ok(value?.id()))
+            .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+        let outputs = op
+            .outputs()
+            .map(|value| Ok(value?.id()))
+            .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+
+        let parent = builder.container_node();
+        let inserted = builder.add_hugr(body.hugr.clone());
+        self.absorb_nested([body.ctx.clone()], &inserted.node_map);
+        let dfg = inserted.inserted_entrypoint;
+        let hugr = builder.hugr_mut();
+        let [input, output] = hugr.get_io(dfg).unwrap();
+
+        for (port, &value_id) in inputs.iter().enumerate() {
+            for (node, node_port) in hugr.linked_inputs(input, port).collect_vec() {
+                hugr.disconnect(node, node_port);
+                match node == output {
+                    // The value is passed through the body unchanged.
+                    true => self.merge_with_earlier(outputs[node_port.index()], value_id),
+                    false => self.register_input(value_id, node, node_port),
+                }
+            }
+        }
+        for (port, &value_id) in outputs.iter().enumerate() {
+            if let Some((node, node_port)) = hugr.single_linked_output(output, port) {
+                self.register_output(value_id, node, node_port);
+            }
+        }
+
+        for child in hugr.children(dfg).collect_vec() {
+            if child != input && child != output {
+                hugr.set_parent(child, parent);
+            }
+        }
+        for node in [input, output, dfg] {
+            hugr.remove_node(node);
+        }
+        Ok(())
+    }
+}
+
+/// Order the function definitions so that each one follows the definitions
+/// it calls.
+///
+/// `calls` holds the functions called by each definition, or `None` for the
+/// declarations. Returns the definitions with the length of their longest
+/// chain of nested calls to definitions, or the id of a recursive function.
+fn inline_order(
+    calls: &[Option<BTreeSet<FunctionId>>],
+) -> Result<Vec<(FunctionId, usize)>, FunctionId> {
+    let callees = |func: usize| calls[func].iter().flatten().map(|&callee| callee as usize);
+    let is_definition = |func: usize| calls.get(func).is_some_and(Option::is_some);
+
+    let mut depths: Vec<Option<usize>> = vec![None; calls.len()];
+    let mut visiting = vec![false; calls.len()];
+    let mut order = Vec::new();
+    for root in 0..calls.len() {
+        if !is_definition(root) || depths[root].is_some() {
+            continue;
+        }
+        // The definitions being visited, with their callees left to visit.
+        let mut stack = vec![(root, callees(root))];
+        visiting[root] = true;
+        while let Some((func, pending)) = stack.last_mut() {
+            let func = *func;
+            match pending.next() {
+                Some(callee) if !is_definition(callee) || depths[callee].is_some() => {}
+                Some(callee) if visiting[callee] => return Err(callee as FunctionId),
+                Some(callee) => {
+                    visiting[callee] = true;
+                    stack.push((callee, callees(callee)));
+                }
+                None => {
+                    let depth = callees(func)
+                        .filter_map(|callee| Some(depths.get(callee).copied().flatten()? + 1))
+                        .max()
+                        .unwrap_or(0);
+                    depths[func] = Some(depth);
+                    visiting[func] = false;
+                    order.push((func as FunctionId, depth));
+                    stack.pop();
+                }
+            }
+        }
+    }
+    Ok(order)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    /// The callees of each function, with `None` for the declarations.
+    fn calls(graph: Vec<Option<Vec<FunctionId>>>) -> Vec<Option<BTreeSet<FunctionId>>> {
+        graph
+            .into_iter()
+            .map(|callees| callees.map(BTreeSet::from_iter))
+            .collect()
+    }
+
+    #[rstest]
+    #[case::independent(vec![Some(vec![]), Some(vec![])], Ok(vec![(0, 0), (1, 0)]))]
+    #[case::callee_first(vec![Some(vec![1]), Some(vec![])], Ok(vec![(1, 0), (0, 1)]))]
+    #[case::chain(
+        vec![Some(vec![1]), Some(vec![2]), Some(vec![])],
+        Ok(vec![(2, 0), (1, 1), (0, 2)])
+    )]
+    #[case::diamond(
+        vec![Some(vec![1, 2]), Some(vec![2]), Some(vec![])],
+        Ok(vec![(2, 0), (1, 1), (0, 2)])
+    )]
+    #[case::declaration(vec![Some(vec![1]), None], Ok(vec![(0, 0)]))]
+    #[case::self_call(vec![Some(vec![0])], Err(0))]
+    #[case::cycle(vec![Some(vec![1]), Some(vec![2]), Some(vec![1])], Err(1))]
+    fn order(
+        #[case] graph: Vec<Option<Vec<FunctionId>>>,
+        #[case] expected: Result<Vec<(FunctionId, usize)>, FunctionId>,
+    ) {
+        assert_eq!(inline_order(&calls(graph)), expected);
+    }
+}
@@ -0,0 +1,202 @@
+//! Liveness of the operations trailing a region's outputs.
+//!
+//! Some producers emit operations after all the region target values have
+//! been produced. These are skipped when their results are unused and they
+//! only handle classical values, and rejected when they consume a linear
+//! value already claimed by a live use.
+
+use std::collections::BTreeSet;
+
+use jeff::reader::Region;
+use jeff::reader::value::ValueId;
+
+use super::effects::{self, Effect};
+use crate::JeffToHugrError;
+use crate::types;
+
+/// The values read and written by an operation.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct OpValues<V> {
+    /// Input values, and whether they are linear.
+    inputs: Vec<(V, bool)>,
+    /// Output values, and whether they are linear.
+    outputs: Vec<(V, bool)>,
+    /// Whether the operation may have effects hidden from its values, see
+    /// [`effects::op_effect`].
+    has_effects: bool,
+}
+
+/// Find the trailing operations of a region that can be skipped.
+///
+/// Returns the positions of the skipped operations in the region, or a
+/// [`JeffToHugrError::UseAfterMove`] error if a trailing operation consumes a
+/// linear value that is already used elsewhere.
+pub(crate) fn dead_trailing_ops(region: &Region<'_>) -> Result<BTreeSet<usize>, JeffToHugrError> {
+    let linear = |ty| !types::jeff_to_hugr(ty).copyable();
+    let ops = region
+        .operations()
+        .map(|op| {
+            let inputs = op
+                .inputs()
+                .map(|value| {
+                    let value = value?;
+                    Ok((value.id(), linear(value.ty())))
+                })
+                .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+            let outputs = op
+                .outputs()
+                .map(|value| {
+                    let value = value?;
+                    Ok((value.id(), linear(value.ty())))
+                })
+                .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+            let has_effects = effects::op_effect(&op.op_type()) != Effect::Pure;
+            Ok(OpValues {
+                inputs,
+                outputs,
+                has_effects,
+            })
+        })
+        .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+    let targets = region
+        .targets()
+        .map(|value| Ok(value?.id()))
+        .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+
+    analyse(&ops, &targets).map_err(|(value_id, op_index)| JeffToHugrError::UseAfterMove {
+        value_id,
+        op_index,
+    })
+}
+
+/// Liveness analysis over the operations following the last producer of a
+/// region target.
+///
+/// Returns the positions of the dead classical operations, or the
+/// `(value, position)` of a trailing operation consuming a linear value that
+/// is claimed by a live use.
+fn analyse<V: Ord + Copy>(
+    ops: &[OpValues<V>],
+    targets: &[V],
+) -> Result<BTreeSet<usize>, (V, usize)> {
+    let targets: BTreeSet<V> = targets.iter().copied().collect();
+    let first_trailing = ops
+        .iter()
+        .rposition(|op| op.outputs.iter().any(|(v, _)| targets.contains(v)))
+        .map_or(0, |pos| pos + 1);
+
+    // Walk the trailing operations backwards, marking as dead the ones whose
+    // outputs are not read by a live operation.
+    let mut live_reads: BTreeSet<V> = BTreeSet::new();
+    let mut dead = BTreeSet::new();
+    for (pos, op) in ops.iter().enumerate().skip(first_trailing).rev() {
+        if op.outputs.iter().all(|(v, _)| !live_reads.contains(v)) {
+            dead.insert(pos);
+        } else {
+            live_reads.extend(op.inputs.iter().map(|(v, _)| *v));
+        }
+    }
+
+    // Linear values claimed by the region targets and the live operations.
+    let mut claimed: BTreeSet<V> = targets;
+    for (pos, op) in ops.iter().enumerate() {
+        if !dead.contains(&pos) {
+            claimed.extend(op.inputs.iter().filter(|(_, lin)| *lin).map(|(v, _)| *v));
+        }
+    }
+
+    let mut skipped = BTreeSet::new();
+    for pos in dead {
+        let op = &ops[pos];
+        if let Some((value, _)) = op
+            .inputs
+            .iter()
+            .find(|(v, lin)| *lin && claimed.contains(v))
+        {
+            return Err((*value, pos));
+        }
+        let classical = op.inputs.iter().chain(&op.outputs).all(|(_, lin)| !lin);
+        // Operations without outputs are kept for their side effects.
+        if classical && !op.outputs.is_empty() && !op.has_effects {
+            skipped.insert(pos);
+        }
+    }
+    Ok(skipped)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    fn op(inputs: &[(u32, bool)], outputs: &[(u32, bool)]) -> OpValues<u32> {
+        OpValues {
+            inputs: inputs.to_vec(),
+            outputs: outputs.to_vec(),
+            has_effects: false,
+        }
+    }
+
+    const Q: bool = true;
+    const C: bool = false;
+
+    #[rstest]
+    fn live_region() {
+        // q1 = h(q0); out q1
+        let ops = [op(&[(0, Q)], &[(1, Q)])];
+        assert_eq!(analyse(&ops, &[1]), Ok(BTreeSet::new()));
+    }
+
+    #[rstest]
+    fn trailing_classical_op() {
+        // c1 = const; q1 = h(q0); c2 = add(c1, c1); out q1
+        let ops = [
+            op(&[], &[(1, C)]),
+            op(&[(0, Q)], &[(2, Q)]),
+            op(&[(1, C), (1, C)], &[(3, C)]),
+        ];
+        assert_eq!(analyse(&ops, &[2]), Ok(BTreeSet::from([2])));
+    }
+
+    #[rstest]
+    fn trailing_chain() {
+        // q1 = h(q0); c1 = const; c2 = neg(c1); out q1
+        let ops = [
+            op(&[(0, Q)], &[(1, Q)]),
+            op(&[], &[(2, C)]),
+            op(&[(2, C)], &[(3, C)]),
+        ];
+        assert_eq!(analyse(&ops, &[1]), Ok(BTreeSet::from([1, 2])));
+    }
+
+    #[rstest]
+    fn trailing_region_op() {
+        let mut cf = op(&[], &[(2, C)]);
+        cf.has_effects = true;
+        let ops = [op(&[(0, Q)], &[(1, Q)]), cf];
+        assert_eq!(analyse(&ops, &[1]), Ok(BTreeSet::new()));
+    }
+
+    #[rstest]
+    fn trailing_qubit_double_use() {
+        // q1 = h(q0); c = measure(q1); out q1
+        let ops = [op(&[(0, Q)], &[(1, Q)]), op(&[(1, Q)], &[(2, C)])];
+        assert_eq!(analyse(&ops, &[1]), Err((1, 1)));
+    }
+
+    #[rstest]
+    fn trailing_qubit_free() {
+        // q1 = h(q0); free(q1); out q1
+        let ops = [op(&[(0, Q)], &[(1, Q)]), op(&[(1, Q)], &[])];
+        assert_eq!(analyse(&ops, &[1]), Err((1, 1)));
+    }
+
+    #[rstest]
+    fn trailing_linear_consumer() {
+        // q1 = h(q0); c = measure(q2); out q1
+        //
+        // The trailing measurement is the only consumer of `q2`, so it is kept.
+        let ops = [op(&[(0, Q)], &[(1, Q)]), op(&[(2, Q)], &[(3, C)])];
+        assert_eq!(analyse(&ops, &[1]), Ok(BTreeSet::new()));
+    }
+}
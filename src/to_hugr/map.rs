@@ -0,0 +1,113 @@
+//! Mapping between _jeff_ entities and the HUGR nodes they were translated to.
+
+use std::collections::BTreeMap;
+
+use hugr::Node;
+use jeff::reader::FunctionId;
+
+use super::WireNames;
+
+/// Mapping between a _jeff_ module and the translated HUGR.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JeffHugrMap {
+    /// HUGR function nodes, indexed by their _jeff_ function id.
+    functions: Vec<Node>,
+    /// _jeff_ function ids, by HUGR name and by original _jeff_ name.
+    names: BTreeMap<String, FunctionId>,
+    /// Originating _jeff_ operation of each translated node.
+    ///
+    /// Only populated when [`crate::JeffToHugrConfig::record_provenance`] is set.
+    origins: BTreeMap<Node, (FunctionId, usize)>,
+    /// The function each merged function shares its body with.
+    ///
+    /// Only populated when [`crate::JeffToHugrConfig::dedup_functions`] is set.
+    canonical: BTreeMap<FunctionId, FunctionId>,
+    /// Names of the quantum wires.
+    ///
+    /// Only populated when [`crate::JeffToHugrConfig::name_wires`] is set.
+    wire_names: WireNames,
+}
+
+impl JeffHugrMap {
+    /// Returns the HUGR node for a _jeff_ function id.
+    pub fn function_node(&self, function: FunctionId) -> Option<Node> {
+        self.functions.get(function as usize).copied()
+    }
+
+    /// Returns the HUGR node for a function, given either its HUGR name or
+    /// its original _jeff_ name.
+    ///
+    /// The names differ when the translation normalized the _jeff_ name, see
+    /// [`crate::JeffToHugrConfig::strict_names`]. If several functions have
+    /// the same name, the first one is returned.
+    pub fn function_by_name(&self, name: &str) -> Option<Node> {
+        let &function = self.names.get(name)?;
+        self.function_node(function)
+    }
+
+    /// Returns the HUGR function nodes, in _jeff_ function id order.
+    pub fn function_nodes(&self) -> &[Node] {
+        &self.functions
+    }
+
+    /// Returns the function whose body a merged function shares, see
+    /// [`crate::JeffToHugrConfig::dedup_functions`].
+    ///
+    /// Returns `None` for the functions translated on their own.
+    pub fn canonical_function(&self, function: FunctionId) -> Option<FunctionId> {
+        self.canonical.get(&function).copied()
+    }
+
+    /// Returns the names of the qubit, register and measurement wires, see
+    /// [`crate::JeffToHugrConfig::name_wires`].
+    pub fn wire_names(&self) -> &WireNames {
+        &self.wire_names
+    }
+
+    /// Returns the _jeff_ function id and operation index a HUGR node was translated from.
+    ///
+    /// The operation index counts the operations of the function in the order
+    /// they are read, including those in nested regions.
+    ///
+    /// Nodes synthesized by the translation (control flow machinery, type
+    /// conversions, constants) have no origin.
+    pub fn node_origin(&self, node: Node) -> Option<(FunctionId, usize)> {
+        self.origins.get(&node).copied()
+    }
+
+    /// Returns an iterator over all nodes with a recorded origin.
+    pub fn origins(&self) -> impl Iterator<Item = (Node, (FunctionId, usize))> + '_ {
+        self.origins.iter().map(|(&node, &origin)| (node, origin))
+    }
+
+    /// Register the HUGR node for the next _jeff_ function id.
+    pub(crate) fn push_function(&mut self, node: Node) {
+        self.functions.push(node);
+    }
+
+    /// Replace the HUGR node of a _jeff_ function.
+    pub(crate) fn set_function(&mut self, function: FunctionId, node: Node) {
+        self.functions[function as usize] = node;
+    }
+
+    /// Register a name of a _jeff_ function. The first function registered
+    /// with a name is kept.
+    pub(crate) fn insert_name(&mut self, name: &str, function: FunctionId) {
+        self.names.entry(name.to_string()).or_insert(function);
+    }
+
+    /// Register a function merged into the `canonical` one.
+    pub(crate) fn set_canonical(&mut self, function: FunctionId, canonical: FunctionId) {
+        self.canonical.insert(function, canonical);
+    }
+
+    /// Register the names of the quantum wires.
+    pub(crate) fn set_wire_names(&mut self, wire_names: WireNames) {
+        self.wire_names = wire_names;
+    }
+
+    /// Register the origin of a HUGR node.
+    pub(crate) fn set_origin(&mut self, node: Node, origin: (FunctionId, usize)) {
+        self.origins.insert(node, origin);
+    }
+}
@@ -0,0 +1,174 @@
+//! Selection of a module in _jeff_ programs.
+//!
+//! See [`jeff_modules`] and [`jeff_to_hugr_module`].
+
+use derive_more::Display;
+use hugr::Hugr;
+use jeff::Jeff;
+
+use super::BuildContext;
+use crate::{JeffToHugrConfig, JeffToHugrError};
+
+/// A module of a _jeff_ program, listed by [`jeff_modules`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ModuleInfo {
+    /// The position of the module in the program.
+    pub index: usize,
+    /// The name of the module, if it has one.
+    pub name: Option<String>,
+    /// The number of function definitions and declarations in the module.
+    pub functions: usize,
+}
+
+/// A module of a _jeff_ program, selected by its index or its name.
+#[derive(Debug, Display, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ModuleSelector {
+    /// The module at a position in the program.
+    #[display("#{_0}")]
+    Index(usize),
+    /// The module with a name.
+    #[display("'{_0}'")]
+    Name(String),
+}
+
+impl From<usize> for ModuleSelector {
+    fn from(index: usize) -> Self {
+        Self::Index(index)
+    }
+}
+
+impl From<String> for ModuleSelector {
+    fn from(name: String) -> Self {
+        Self::Name(name)
+    }
+}
+
+impl From<&str> for ModuleSelector {
+    fn from(name: &str) -> Self {
+        Self::Name(name.to_string())
+    }
+}
+
+impl ModuleSelector {
+    /// Whether the selector designates a module.
+    pub fn matches(&self, module: &ModuleInfo) -> bool {
+        match self {
+            Self::Index(index) => module.index == *index,
+            Self::Name(name) => module.name.as_ref() == Some(name),
+        }
+    }
+}
+
+/// List the modules of a _jeff_ program.
+///
+/// The _jeff_ reader currently exposes a single unnamed module per program,
+/// so the list holds one entry.
+pub fn jeff_modules(jeff: &Jeff) -> Vec<ModuleInfo> {
+    vec![ModuleInfo {
+        index: 0,
+        name: None,
+        functions: jeff.module().functions().count(),
+    }]
+}
+
+/// Translate a module of a _jeff_ program into a HUGR program.
+///
+/// # Errors
+///
+/// - [`JeffToHugrError::UnknownModule`] if no module matches `module`.
+pub fn jeff_to_hugr_module(
+    jeff: &Jeff,
+    module: impl Into<ModuleSelector>,
+) -> Result<Hugr, JeffToHugrError> {
+    let module = module.into();
+    if !jeff_modules(jeff).iter().any(|info| module.matches(info)) {
+        return Err(JeffToHugrError::UnknownModule { module });
+    }
+    let config = JeffToHugrConfig::default();
+    BuildContext::build_module(jeff.module(), &config).map(|res| res.hugr)
+}
+
+/// Check that the first module of a program can be translated by default.
+///
+/// # Errors
+///
+/// - [`JeffToHugrError::AmbiguousModule`] if
+///   [`JeffToHugrConfig::strict_modules`] is set and the program has more
+///   than one module.
+pub(super) fn check_default_module(
+    modules: &[ModuleInfo],
+    config: &JeffToHugrConfig,
+) -> Result<(), JeffToHugrError> {
+    match config.strict_modules && modules.len() > 1 {
+        true => Err(JeffToHugrError::AmbiguousModule {
+            modules: modules.len(),
+        }),
+        false => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{assert_hugr_equivalent, qubits};
+    use rstest::rstest;
+
+    fn module(index: usize, name: Option<&str>) -> ModuleInfo {
+        ModuleInfo {
+            index,
+            name: name.map(str::to_string),
+            functions: 1,
+        }
+    }
+
+    #[rstest]
+    fn single_module(qubits: Jeff<'static>) {
+        let modules = jeff_modules(&qubits);
+        assert_eq!(modules.len(), 1);
+        assert_eq!(modules[0].functions, qubits.module().functions().count());
+
+        let hugr = jeff_to_hugr_module(&qubits, 0usize).unwrap();
+        assert_hugr_equivalent(&hugr, &crate::jeff_to_hugr(&qubits).unwrap());
+
+        let config = JeffToHugrConfig::default().with_strict_modules(true);
+        assert!(crate::jeff_to_hugr_with_config(&qubits, &config).is_ok());
+    }
+
+    #[rstest]
+    #[case::index(ModuleSelector::Index(1))]
+    #[case::name(ModuleSelector::from("main"))]
+    fn unknown_module(qubits: Jeff<'static>, #[case] selector: ModuleSelector) {
+        assert!(matches!(
+            jeff_to_hugr_module(&qubits, selector.clone()),
+            Err(JeffToHugrError::UnknownModule { module }) if module == selector
+        ));
+    }
+
+    #[rstest]
+    #[case::index(ModuleSelector::Index(1), true)]
+    #[case::name("kernel".into(), true)]
+    #[case::other_index(ModuleSelector::Index(0), false)]
+    #[case::other_name("main".into(), false)]
+    fn selector_matches(#[case] selector: ModuleSelector, #[case] expected: bool) {
+        assert_eq!(selector.matches(&module(1, Some("kernel"))), expected);
+    }
+
+    #[rstest]
+    #[case::single(1, true, true)]
+    #[case::several(2, false, true)]
+    #[case::ambiguous(2, true, false)]
+    fn default_module(#[case] count: usize, #[case] strict: bool, #[case] ok: bool) {
+        let modules = (0..count).map(|index| module(index, None)).collect::<Vec<_>>();
+        let config = JeffToHugrConfig::default().with_strict_modules(strict);
+        match check_default_module(&modules, &config) {
+            Ok(()) => assert!(ok),
+            Err(JeffToHugrError::AmbiguousModule { modules }) => {
+                assert!(!ok);
+                assert_eq!(modules, count);
+            }
+            Err(e) => panic!("unexpected error: {e}"),
+        }
+    }
+}
@@ -0,0 +1,116 @@
+//! Normalization of the _jeff_ function names into HUGR function names.
+//!
+//! _jeff_ function names may contain spaces, unicode or mangled C++ names,
+//! which break the mermaid rendering, the metadata-based lookups and some
+//! serializers of HUGR programs. Unless
+//! [`crate::JeffToHugrConfig::strict_names`] is set, invalid names are
+//! normalized with the following rules:
+//!
+//! - characters other than ASCII letters, digits and `_` are replaced by `_`,
+//! - names that are empty or start with a digit are prefixed with `_`,
+//! - names are truncated to [`MAX_NAME_LEN`] characters.
+//!
+//! Valid names are kept unchanged. A normalized name colliding with another
+//! function name gets the smallest free `_<n>` suffix, in function order.
+//! The names of the functions already in the HUGR are only avoided when
+//! importing with a prefix, see [`crate::jeff_into_hugr_module`].
+//! The _jeff_ name of a renamed function is kept in its
+//! [`crate::metadata::ORIGINAL_NAME`] metadata.
+
+use std::collections::BTreeSet;
+
+/// Maximum length of a HUGR function name.
+pub(crate) const MAX_NAME_LEN: usize = 128;
+
+/// Returns `true` if the name is kept unchanged by the normalization.
+pub(crate) fn is_valid_name(name: &str) -> bool {
+    name.len() <= MAX_NAME_LEN
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && name.chars().next().is_some_and(|c| !c.is_ascii_digit())
+}
+
+/// Normalize a function name, without avoiding collisions.
+fn sanitize_name(name: &str) -> String {
+    let mut sanitized: String = name
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c,
+            false => '_',
+        })
+        .collect();
+    if sanitized.chars().next().is_none_or(|c| c.is_ascii_digit()) {
+        sanitized.insert(0, '_');
+    }
+    sanitized.truncate(MAX_NAME_LEN);
+    sanitized
+}
+
+/// Normalize the names of the functions of a module.
+///
+/// The normalized names do not collide with the valid names of the module,
+/// nor with the `reserved` names of the functions already in the HUGR.
+pub(super) fn sanitize_names(names: &[String], reserved: &BTreeSet<String>) -> Vec<String> {
+    let mut taken: BTreeSet<String> = names
+        .iter()
+        .filter(|name| is_valid_name(name))
+        .chain(reserved)
+        .cloned()
+        .collect();
+    names
+        .iter()
+        .map(|name| {
+            if is_valid_name(name) {
+                return name.clone();
+            }
+            let base = sanitize_name(name);
+            let mut sanitized = base.clone();
+            for n in 1.. {
+                if !taken.contains(&sanitized) {
+                    break;
+                }
+                let suffix = format!("_{n}");
+                let len = base.len().min(MAX_NAME_LEN - suffix.len());
+                sanitized = format!("{}{suffix}", &base[..len]);
+            }
+            taken.insert(sanitized.clone());
+            sanitized
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::valid("circuit", "circuit")]
+    #[case::space("my kernel", "my_kernel")]
+    #[case::leading_digit("2fast", "_2fast")]
+    #[case::mangled("std::foo<int>", "std__foo_int_")]
+    #[case::unicode("θ_rot", "__rot")]
+    #[case::empty("", "_")]
+    fn normalize(#[case] name: &str, #[case] expected: &str) {
+        let sanitized = sanitize_names(&[name.to_string()], &BTreeSet::new());
+        assert_eq!(sanitized, [expected]);
+        assert!(is_valid_name(expected));
+    }
+
+    #[rstest]
+    fn truncate() {
+        let long = "a b".repeat(MAX_NAME_LEN);
+        let sanitized = sanitize_names(&[long.clone(), long], &BTreeSet::new());
+        assert_eq!(sanitized[0].len(), MAX_NAME_LEN);
+        assert_eq!(sanitized[1].len(), MAX_NAME_LEN);
+        assert!(sanitized[1].ends_with("_1"));
+    }
+
+    #[rstest]
+    fn collisions() {
+        let names = ["a b", "a_b", "a-b", "a_b_1", "c d"].map(str::to_string);
+        let reserved = BTreeSet::from(["c_d".to_string()]);
+        let sanitized = sanitize_names(&names, &reserved);
+        // Valid names are kept, and the others take the first free suffix.
+        assert_eq!(sanitized, ["a_b_2", "a_b", "a_b_3", "a_b_1", "c_d_1"]);
+    }
+}
@@ -0,0 +1,120 @@
+//! Progress reports of long translations.
+//!
+//! See [`JeffToHugrConfig::progress`](crate::JeffToHugrConfig::progress).
+
+use std::fmt;
+use std::ops::ControlFlow;
+use std::panic::RefUnwindSafe;
+use std::sync::Arc;
+
+use derive_more::Display;
+
+use crate::JeffToHugrError;
+
+/// A stage of the translation, reported to a [`ProgressHook`].
+#[derive(Debug, Display, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Progress {
+    /// Scanning the _jeff_ module before translating its functions.
+    #[display("scanning {functions} functions")]
+    Scanning {
+        /// The number of functions in the module.
+        functions: usize,
+    },
+    /// Translating a function.
+    ///
+    /// Reported when the translation of the function starts, and then every
+    /// [`ProgressHook::interval`] operations.
+    #[display(
+        "converting function {} of {functions} '{name}' ({operations} operations)",
+        function + 1
+    )]
+    Converting {
+        /// The _jeff_ id of the function, counting from zero.
+        function: usize,
+        /// The number of functions in the module.
+        functions: usize,
+        /// The HUGR name of the function.
+        name: String,
+        /// The number of operations of the function translated so far.
+        operations: usize,
+    },
+    /// Connecting the calls between the translated functions.
+    #[display("wiring {calls} calls")]
+    WiringCalls {
+        /// The number of call nodes to connect.
+        calls: usize,
+    },
+    /// Validating the translated HUGR.
+    ///
+    /// Not reported when [`crate::JeffToHugrConfig::validate`] is unset.
+    #[display("validating {nodes} nodes")]
+    Validating {
+        /// The number of nodes in the HUGR.
+        nodes: usize,
+    },
+}
+
+/// A callback receiving the [`Progress`] of a translation.
+///
+/// Returning [`ControlFlow::Break`] cancels the translation with
+/// [`JeffToHugrError::Cancelled`]. The partially built HUGR is discarded.
+///
+/// Two hooks are equal if they share the same callback and interval.
+#[derive(Clone)]
+pub struct ProgressHook {
+    /// The user callback.
+    callback: Arc<dyn Fn(Progress) -> ControlFlow<()> + Send + Sync + RefUnwindSafe>,
+    /// The number of operations between two reports within a function.
+    interval: usize,
+}
+
+impl ProgressHook {
+    /// The default number of operations between two reports within a function.
+    pub const DEFAULT_INTERVAL: usize = 1000;
+
+    /// Create a hook calling `callback` at each stage of the translation.
+    pub fn new(
+        callback: impl Fn(Progress) -> ControlFlow<()> + Send + Sync + RefUnwindSafe + 'static,
+    ) -> Self {
+        Self {
+            callback: Arc::new(callback),
+            interval: Self::DEFAULT_INTERVAL,
+        }
+    }
+
+    /// Report the translation of a function every `interval` operations.
+    ///
+    /// An interval of zero is treated as one.
+    pub fn with_interval(mut self, interval: usize) -> Self {
+        self.interval = interval.max(1);
+        self
+    }
+
+    /// The number of operations between two reports within a function.
+    pub fn interval(&self) -> usize {
+        self.interval
+    }
+
+    /// Call the callback, returning an error if it cancels the translation.
+    pub(crate) fn report(&self, progress: Progress) -> Result<(), JeffToHugrError> {
+        match (self.callback)(progress.clone()) {
+            ControlFlow::Continue(()) => Ok(()),
+            ControlFlow::Break(()) => Err(JeffToHugrError::Cancelled { progress }),
+        }
+    }
+}
+
+impl fmt::Debug for ProgressHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProgressHook")
+            .field("interval", &self.interval)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for ProgressHook {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.callback, &other.callback) && self.interval == other.interval
+    }
+}
@@ -0,0 +1,626 @@
+//! Lowering of the translated qubit registers to a [`QuregLowering`]
+//! representation.
+//!
+//! A _jeff_ register keeps its slots while qubits extracted from it are
+//! alive, see [`crate::extension::register_semantics`]. The registers of
+//! static length are lowered to `collections.borrow_arr` arrays of qubits,
+//! whose `borrow` and `return` operations leave and fill the same empty
+//! slots. The plain `collections.array` cannot hold a missing qubit, and
+//! only reads copyable elements.
+//!
+//! The borrow arrays have no operations splitting or concatenating them, so
+//! the qubits of the registers split or joined are moved one by one to new
+//! arrays.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use hugr::extension::prelude::{ConstUsize, qb_t};
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::ops::{Const, LoadConstant, OpType, Value};
+use hugr::std_extensions::arithmetic::int_types::ConstInt;
+use hugr::std_extensions::collections::borrow_array::{BArrayOpDef, BArrayUnsafeOpDef};
+use hugr::{Hugr, HugrView, IncomingPort, Node, OutgoingPort};
+use itertools::Itertools;
+
+use super::QuregLowering;
+use crate::extension::register_semantics::constant_input;
+use crate::extension::{JeffOp, qureg_type};
+use crate::metadata;
+use crate::optype::tket_ops;
+use crate::passes::cancel::has_order_edges;
+use crate::passes::fuse::remove_unused_constant;
+
+/// Log width of the `int(32)` lengths.
+const INDEX_LOG_WIDTH: u8 = 5;
+
+/// A wire of the HUGR, by the port producing it.
+type Source = (Node, OutgoingPort);
+
+/// Lower the registers of a HUGR to the `lowering` representation.
+///
+/// With [`QuregLowering::StaticArray`], a register is lowered together with
+/// the registers it is split from or joined with, when all of them:
+///
+/// - are created by a [`JeffOp::QuregCreate`], or a [`JeffOp::QuregAlloc`]
+///   of a constant length of at most `max_len` qubits,
+/// - stay in the region they are created in, with no order edges,
+/// - are only used by [`JeffOp::QuregExtractIndex`] and
+///   [`JeffOp::QuregInsertIndex`] at constant indices,
+///   [`JeffOp::QuregLength`], [`JeffOp::QuregSplit`] at a constant index,
+///   [`JeffOp::QuregJoin`], [`JeffOp::QuregMeasure`],
+///   [`JeffOp::QuregMeasureNd`] and [`JeffOp::QuregFree`],
+/// - hold all their qubits when measured or freed, and are never extracted
+///   from an empty slot or inserted into a full one.
+///
+/// The other registers are left as opaque registers.
+///
+/// Returns the number of lowered register creations.
+pub(super) fn lower_static_registers(
+    hugr: &mut Hugr,
+    lowering: QuregLowering,
+    max_len: usize,
+) -> usize {
+    if lowering == QuregLowering::Opaque {
+        return 0;
+    }
+    let plans = register_groups(hugr)
+        .into_iter()
+        .filter_map(|group| plan_group(hugr, &group, max_len))
+        .collect_vec();
+    let mut lowered = 0;
+    for plan in plans {
+        lowered += plan
+            .nodes
+            .iter()
+            .filter(|(_, op)| matches!(op, JeffOp::QuregAlloc | JeffOp::QuregCreate { .. }))
+            .count();
+        ArrayLowering::new(hugr, plan).run();
+    }
+    lowered
+}
+
+/// The nodes linked by register wires to the register creations of a
+/// HUGR, grouped by connected component.
+fn register_groups(hugr: &Hugr) -> Vec<Vec<Node>> {
+    let mut visited = BTreeSet::new();
+    let mut groups = Vec::new();
+    for root in hugr.nodes() {
+        let creates = matches!(
+            hugr.get_optype(root).cast::<JeffOp>(),
+            Some(JeffOp::QuregAlloc | JeffOp::QuregCreate { .. })
+        );
+        if !creates || !visited.insert(root) {
+            continue;
+        }
+        let mut group = Vec::new();
+        let mut stack = vec![root];
+        while let Some(node) = stack.pop() {
+            group.push(node);
+            stack.extend(register_links(hugr, node).filter(|&next| visited.insert(next)));
+        }
+        groups.push(group);
+    }
+    groups
+}
+
+/// The nodes connected to a node by a register wire.
+fn register_links(hugr: &Hugr, node: Node) -> impl Iterator<Item = Node> + '_ {
+    let qureg = qureg_type();
+    let ports = hugr.signature(node).map_or_else(Vec::new, |signature| {
+        hugr.all_node_ports(node)
+            .filter(|&port| signature.port_type(port) == Some(&qureg))
+            .collect_vec()
+    });
+    ports
+        .into_iter()
+        .flat_map(move |port| hugr.linked_ports(node, port).map(|(next, _)| next))
+}
+
+/// A group of registers to lower.
+#[derive(Debug, Clone)]
+struct GroupPlan {
+    /// The region of the registers.
+    parent: Node,
+    /// The register operations, in dataflow order.
+    nodes: Vec<(Node, JeffOp)>,
+    /// Whether each slot of the registers holds a qubit, by the port
+    /// producing the register.
+    slots: BTreeMap<Source, Vec<bool>>,
+}
+
+/// Check that a group of registers can be lowered, following the qubits in
+/// their slots.
+fn plan_group(hugr: &Hugr, group: &[Node], max_len: usize) -> Option<GroupPlan> {
+    let parent = hugr.get_parent(group[0])?;
+    let ops = group
+        .iter()
+        .map(|&node| {
+            let op = hugr.get_optype(node).cast::<JeffOp>()?;
+            let supported = matches!(
+                op,
+                JeffOp::QuregAlloc
+                    | JeffOp::QuregCreate { .. }
+                    | JeffOp::QuregExtractIndex
+                    | JeffOp::QuregInsertIndex
+                    | JeffOp::QuregLength
+                    | JeffOp::QuregSplit
+                    | JeffOp::QuregJoin
+                    | JeffOp::QuregMeasure
+                    | JeffOp::QuregMeasureNd
+                    | JeffOp::QuregFree
+            );
+            let local = hugr.get_parent(node) == Some(parent) && !has_order_edges(hugr, node);
+            (supported && local).then_some((node, op))
+        })
+        .collect::<Option<BTreeMap<_, _>>>()?;
+
+    // Follow the registers in dataflow order. All the register operations
+    // linked to the group are in it.
+    let mut pending: BTreeMap<Node, usize> = ops
+        .keys()
+        .map(|&node| (node, register_inputs(hugr, node).len()))
+        .collect();
+    let mut ready: VecDeque<Node> = pending
+        .iter()
+        .filter(|&(_, &inputs)| inputs == 0)
+        .map(|(&node, _)| node)
+        .collect();
+    let mut slots = BTreeMap::new();
+    let mut nodes = Vec::new();
+    while let Some(node) = ready.pop_front() {
+        let op = ops[&node];
+        let inputs = register_inputs(hugr, node)
+            .iter()
+            .map(|src| slots.get(src))
+            .collect::<Option<Vec<&Vec<bool>>>>()?;
+        let outputs = output_slots(hugr, node, op, &inputs, max_len)?;
+        for (port, register) in outputs.into_iter().enumerate() {
+            let port = OutgoingPort::from(port);
+            slots.insert((node, port), register);
+            for (next, _) in hugr.linked_inputs(node, port) {
+                let count = pending.get_mut(&next)?;
+                *count -= 1;
+                if *count == 0 {
+                    ready.push_back(next);
+                }
+            }
+        }
+        nodes.push((node, op));
+    }
+    (nodes.len() == ops.len()).then_some(GroupPlan {
+        parent,
+        nodes,
+        slots,
+    })
+}
+
+/// The ports producing the registers read by a node, in input order.
+fn register_inputs(hugr: &Hugr, node: Node) -> Vec<Source> {
+    let qureg = qureg_type();
+    let Some(signature) = hugr.signature(node) else {
+        return Vec::new();
+    };
+    hugr.node_inputs(node)
+        .filter(|&port| signature.in_port_type(port) == Some(&qureg))
+        .filter_map(|port| hugr.single_linked_output(node, port))
+        .collect()
+}
+
+/// The slots of the registers produced by an operation, from the slots of
+/// its register inputs, or `None` if the operation cannot be lowered.
+///
+/// The registers are the first outputs of the lowered operations.
+fn output_slots(
+    hugr: &Hugr,
+    node: Node,
+    op: JeffOp,
+    inputs: &[&Vec<bool>],
+    max_len: usize,
+) -> Option<Vec<Vec<bool>>> {
+    let constant = |input| usize::try_from(constant_input(hugr, node, input)?).ok();
+    let full = |slots: &[bool]| slots.iter().all(|&slot| slot);
+    let outputs = match op {
+        JeffOp::QuregAlloc => vec![vec![true; constant(0).filter(|&len| len <= max_len)?]],
+        JeffOp::QuregCreate { qubits } => vec![vec![true; qubits]],
+        JeffOp::QuregExtractIndex | JeffOp::QuregInsertIndex => {
+            let extract = op == JeffOp::QuregExtractIndex;
+            let mut slots = inputs[0].clone();
+            let slot = slots.get_mut(constant(if extract { 1 } else { 2 })?)?;
+            // The slot must hold a qubit to extract, and be empty to insert.
+            if *slot != extract {
+                return None;
+            }
+            *slot = !extract;
+            vec![slots]
+        }
+        JeffOp::QuregSplit => {
+            let (first, rest) = inputs[0].split_at_checked(constant(1)?)?;
+            vec![first.to_vec(), rest.to_vec()]
+        }
+        JeffOp::QuregJoin => vec![[inputs[0].as_slice(), inputs[1]].concat()],
+        JeffOp::QuregLength => vec![inputs[0].clone()],
+        JeffOp::QuregMeasureNd if full(inputs[0]) => vec![inputs[0].clone()],
+        JeffOp::QuregMeasure | JeffOp::QuregFree if full(inputs[0]) => vec![],
+        _ => return None,
+    };
+    Some(outputs)
+}
+
+/// Replacement of a group of registers by borrow arrays.
+struct ArrayLowering<'h> {
+    hugr: &'h mut Hugr,
+    plan: GroupPlan,
+    /// The arrays replacing the registers, by the port producing the
+    /// register.
+    arrays: BTreeMap<Source, Source>,
+    /// The nodes added for the operation being lowered.
+    added: Vec<Node>,
+    /// The loaded constants read by the lowered operations.
+    constants: BTreeSet<Node>,
+}
+
+impl<'h> ArrayLowering<'h> {
+    fn new(hugr: &'h mut Hugr, plan: GroupPlan) -> Self {
+        Self {
+            hugr,
+            plan,
+            arrays: BTreeMap::new(),
+            added: Vec::new(),
+            constants: BTreeSet::new(),
+        }
+    }
+
+    /// Replace the register operations, in dataflow order.
+    fn run(mut self) {
+        for (node, op) in self.plan.nodes.clone() {
+            self.lower_op(node, op);
+        }
+        for load in self.constants {
+            remove_unused_constant(self.hugr, load);
+        }
+    }
+
+    /// Replace a register operation. The new nodes keep the
+    /// [`metadata::OP_INDEX`] of the operation.
+    fn lower_op(&mut self, node: Node, op: JeffOp) {
+        let hugr = &*self.hugr;
+        let inputs = hugr
+            .node_inputs(node)
+            .map(|port| hugr.single_linked_output(node, port))
+            .collect_vec();
+        let targets = hugr
+            .node_outputs(node)
+            .map(|port| hugr.linked_inputs(node, port).collect_vec())
+            .collect_vec();
+        let origin = hugr.get_metadata(node, metadata::OP_INDEX).cloned();
+        let index = match op {
+            JeffOp::QuregExtractIndex => constant_input(hugr, node, 1),
+            JeffOp::QuregInsertIndex => constant_input(hugr, node, 2),
+            _ => None,
+        }
+        .map(|index| index as usize);
+        self.constants.extend(
+            inputs
+                .iter()
+                .flatten()
+                .map(|&(src, _)| src)
+                .filter(|&src| hugr.get_optype(src).is_load_constant()),
+        );
+        let register = inputs
+            .first()
+            .copied()
+            .flatten()
+            .and_then(|src| Some((src, self.arrays.get(&src).copied()?)));
+        self.hugr.remove_node(node);
+
+        let register_out = (node, OutgoingPort::from(0));
+        match op {
+            JeffOp::QuregAlloc => {
+                let len = self.plan.slots[&register_out].len();
+                let qubits = (0..len)
+                    .map(|_| (self.add(tket_ops::qalloc(), &[]), OutgoingPort::from(0)))
+                    .collect_vec();
+                let array = self.new_array(&qubits);
+                self.arrays.insert(register_out, array);
+            }
+            JeffOp::QuregCreate { .. } => {
+                let qubits = inputs.iter().flatten().copied().collect_vec();
+                let array = self.new_array(&qubits);
+                self.arrays.insert(register_out, array);
+            }
+            JeffOp::QuregExtractIndex => {
+                let (src, array) = register.unwrap();
+                let (qubit, array) = self.borrow(array, self.len(src), index.unwrap());
+                self.arrays.insert(register_out, array);
+                self.connect_all(qubit, &targets[1]);
+            }
+            JeffOp::QuregInsertIndex => {
+                let (src, array) = register.unwrap();
+                let qubit = inputs[1].unwrap();
+                let array = self.put_back(array, self.len(src), index.unwrap(), qubit);
+                self.arrays.insert(register_out, array);
+            }
+            JeffOp::QuregLength => {
+                let (src, array) = register.unwrap();
+                let value = ConstInt::new_u(INDEX_LOG_WIDTH, self.len(src) as u64).unwrap();
+                let length = self.load_constant(Value::extension(value));
+                self.arrays.insert(register_out, array);
+                self.connect_all(length, &targets[1]);
+            }
+            JeffOp::QuregSplit | JeffOp::QuregJoin => {
+                let (registers_in, registers_out) = match op {
+                    JeffOp::QuregSplit => (1, 2),
+                    _ => (2, 1),
+                };
+                let qubits = inputs[..registers_in]
+                    .iter()
+                    .flatten()
+                    .flat_map(|&src| {
+                        let array = self.arrays[&src];
+                        self.take_qubits(src, array)
+                    })
+                    .collect_vec();
+                let mut qubits = qubits.as_slice();
+                for port in 0..registers_out {
+                    let out = (node, OutgoingPort::from(port));
+                    let (slots, rest) = qubits.split_at(self.plan.slots[&out].len());
+                    let array = self.refill(slots);
+                    self.arrays.insert(out, array);
+                    qubits = rest;
+                }
+            }
+            JeffOp::QuregMeasure => {
+                let (src, array) = register.unwrap();
+                let bits = self
+                    .take_qubits(src, array)
+                    .into_iter()
+                    .map(|qubit| {
+                        let qubit = qubit.expect("measured registers hold all their qubits");
+                        let measure = self.add(tket_ops::measure_destructive(), &[qubit]);
+                        (measure, OutgoingPort::from(0))
+                    })
+                    .collect_vec();
+                let bits = self.bit_array(&bits);
+                self.connect_all(bits, &targets[0]);
+            }
+            JeffOp::QuregMeasureNd => {
+                let (src, mut array) = register.unwrap();
+                let len = self.len(src);
+                let mut bits = Vec::with_capacity(len);
+                for index in 0..len {
+                    let (qubit, borrowed) = self.borrow(array, len, index);
+                    let measure = self.add(tket_ops::measure(), &[qubit]);
+                    bits.push((measure, OutgoingPort::from(1)));
+                    array = self.put_back(borrowed, len, index, (measure, OutgoingPort::from(0)));
+                }
+                let bits = self.bit_array(&bits);
+                self.arrays.insert(register_out, array);
+                self.connect_all(bits, &targets[1]);
+            }
+            JeffOp::QuregFree => {
+                let (src, array) = register.unwrap();
+                for qubit in self.take_qubits(src, array).into_iter().flatten() {
+                    self.add(tket_ops::qfree(), &[qubit]);
+                }
+            }
+            _ => unreachable!("{op:?} is not lowered"),
+        }
+
+        let added = std::mem::take(&mut self.added);
+        if let Some(origin) = origin {
+            for added in added {
+                self.hugr
+                    .set_metadata(added, metadata::OP_INDEX, origin.clone());
+            }
+        }
+    }
+
+    /// The length of a register.
+    fn len(&self, register: Source) -> usize {
+        self.plan.slots[&register].len()
+    }
+
+    /// Add an operation to the region, reading `inputs`.
+    fn add(&mut self, op: impl Into<OpType>, inputs: &[Source]) -> Node {
+        let node = self.hugr.add_node_with_parent(self.plan.parent, op);
+        for (port, &(src, src_port)) in inputs.iter().enumerate() {
+            self.hugr.connect(src, src_port, node, port);
+        }
+        self.added.push(node);
+        node
+    }
+
+    /// Connect a value to its consumers.
+    fn connect_all(&mut self, (src, src_port): Source, targets: &[(Node, IncomingPort)]) {
+        for &(tgt, tgt_port) in targets {
+            self.hugr.connect(src, src_port, tgt, tgt_port);
+        }
+    }
+
+    /// Load a constant value.
+    fn load_constant(&mut self, value: Value) -> Source {
+        let load = self.add(
+            LoadConstant {
+                datatype: value.get_type(),
+            },
+            &[],
+        );
+        let const_node = self.add(Const::new(value), &[]);
+        self.hugr.connect(const_node, 0, load, 0);
+        (load, OutgoingPort::from(0))
+    }
+
+    /// An array holding the qubits.
+    fn new_array(&mut self, qubits: &[Source]) -> Source {
+        let op = BArrayOpDef::new_array.to_concrete(qb_t(), qubits.len() as u64);
+        (self.add(op, qubits), OutgoingPort::from(0))
+    }
+
+    /// An array of `int(1)` outcomes.
+    fn bit_array(&mut self, bits: &[Source]) -> Source {
+        let op = JeffOp::IntArrayCreate {
+            bits: 1,
+            inputs: bits.len(),
+        }
+        .into_extension_op();
+        (self.add(op, bits), OutgoingPort::from(0))
+    }
+
+    /// Borrow the qubit of an array at a constant index, returning the qubit
+    /// and the array.
+    fn borrow(&mut self, array: Source, len: usize, index: usize) -> (Source, Source) {
+        let index = self.load_constant(Value::extension(ConstUsize::new(index as u64)));
+        let op = BArrayUnsafeOpDef::borrow.to_concrete(qb_t(), len as u64);
+        let node = self.add(op, &[array, index]);
+        ((node, OutgoingPort::from(0)), (node, OutgoingPort::from(1)))
+    }
+
+    /// Return a qubit to an array at a constant index.
+    fn put_back(&mut self, array: Source, len: usize, index: usize, qubit: Source) -> Source {
+        let index = self.load_constant(Value::extension(ConstUsize::new(index as u64)));
+        let op = BArrayUnsafeOpDef::r#return.to_concrete(qb_t(), len as u64);
+        (self.add(op, &[array, index, qubit]), OutgoingPort::from(0))
+    }
+
+    /// Borrow all the qubits of the array replacing a register, and discard
+    /// the array. Returns the qubit of each slot, `None` for the empty ones.
+    fn take_qubits(&mut self, register: Source, mut array: Source) -> Vec<Option<Source>> {
+        let slots = self.plan.slots[&register].clone();
+        let qubits = slots
+            .iter()
+            .enumerate()
+            .map(|(index, &full)| {
+                full.then(|| {
+                    let (qubit, borrowed) = self.borrow(array, slots.len(), index);
+                    array = borrowed;
+                    qubit
+                })
+            })
+            .collect();
+        let op = BArrayUnsafeOpDef::discard_all_borrowed.to_concrete(qb_t(), slots.len() as u64);
+        self.add(op, &[array]);
+        qubits
+    }
+
+    /// A new array with the qubits in its slots, leaving the other slots
+    /// empty.
+    fn refill(&mut self, qubits: &[Option<Source>]) -> Source {
+        let len = qubits.len();
+        let op = BArrayUnsafeOpDef::new_all_borrowed.to_concrete(qb_t(), len as u64);
+        let mut array = (self.add(op, &[]), OutgoingPort::from(0));
+        for (index, &qubit) in qubits.iter().enumerate() {
+            if let Some(qubit) = qubit {
+                array = self.put_back(array, len, index, qubit);
+            }
+        }
+        array
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::extension::{JEFF_EXTENSION_ID, intreg_type};
+    use crate::test::{assert_hugr_equivalent, catalyst_simple, qubits};
+    use crate::{JeffToHugrConfig, jeff_to_hugr_with_config};
+    use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr};
+    use hugr::std_extensions::collections::borrow_array;
+    use hugr::type_row;
+    use hugr::types::Signature;
+    use jeff::Jeff;
+    use rstest::rstest;
+
+    /// The number of _jeff_ register operations in a HUGR.
+    fn register_ops(hugr: &Hugr) -> usize {
+        hugr.nodes()
+            .filter_map(|node| hugr.get_optype(node).as_extension_op())
+            .filter(|op| {
+                op.def().extension_id() == &JEFF_EXTENSION_ID
+                    && op.def().name().starts_with("Qureg")
+            })
+            .count()
+    }
+
+    fn translate(jeff: &Jeff<'static>, lowering: QuregLowering) -> Hugr {
+        let config = JeffToHugrConfig::default().with_qureg_lowering(lowering);
+        let hugr = jeff_to_hugr_with_config(jeff, &config).unwrap().hugr;
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        hugr
+    }
+
+    #[rstest]
+    fn catalyst_simple_arrays(catalyst_simple: Jeff<'static>) {
+        assert!(register_ops(&translate(&catalyst_simple, QuregLowering::Opaque)) > 0);
+
+        let hugr = translate(&catalyst_simple, QuregLowering::StaticArray);
+        assert_eq!(register_ops(&hugr), 0);
+        let borrows = hugr
+            .nodes()
+            .filter_map(|node| hugr.get_optype(node).as_extension_op())
+            .filter(|op| op.def().extension_id() == &borrow_array::EXTENSION_ID)
+            .count();
+        assert!(borrows > 0);
+    }
+
+    #[rstest]
+    fn without_registers(qubits: Jeff<'static>) {
+        let opaque = translate(&qubits, QuregLowering::Opaque);
+        let lowered = translate(&qubits, QuregLowering::StaticArray);
+        assert_hugr_equivalent(&opaque, &lowered);
+    }
+
+    /// A graph creating a register of two qubits, splitting it after the
+    /// first one and joining the halves back, measuring the result.
+    fn split_join(split_at: u64) -> Hugr {
+        let signature = Signature::new(type_row![], vec![intreg_type(1)]);
+        let mut builder = DFGBuilder::new(signature).unwrap();
+        let qubits = (0..2)
+            .map(|_| {
+                builder
+                    .add_dataflow_op(tket_ops::qalloc(), [])
+                    .unwrap()
+                    .out_wire(0)
+            })
+            .collect_vec();
+        let create = JeffOp::QuregCreate { qubits: 2 }.into_extension_op();
+        let [reg] = builder
+            .add_dataflow_op(create, qubits)
+            .unwrap()
+            .outputs_arr();
+        let index = builder.add_load_value(ConstInt::new_u(INDEX_LOG_WIDTH, split_at).unwrap());
+        let [first, rest] = builder
+            .add_dataflow_op(JeffOp::QuregSplit.into_extension_op(), [reg, index])
+            .unwrap()
+            .outputs_arr();
+        let [reg] = builder
+            .add_dataflow_op(JeffOp::QuregJoin.into_extension_op(), [rest, first])
+            .unwrap()
+            .outputs_arr();
+        let [bits] = builder
+            .add_dataflow_op(JeffOp::QuregMeasure.into_extension_op(), [reg])
+            .unwrap()
+            .outputs_arr();
+        builder.finish_hugr_with_outputs([bits]).unwrap()
+    }
+
+    #[rstest]
+    #[case::split(1, 1)]
+    #[case::out_of_bounds(3, 0)]
+    fn split_and_join(#[case] split_at: u64, #[case] expected: usize) {
+        let mut hugr = split_join(split_at);
+        let lowered = lower_static_registers(&mut hugr, QuregLowering::StaticArray, 100);
+        assert_eq!(lowered, expected);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(register_ops(&hugr) == 0, expected > 0);
+    }
+
+    #[rstest]
+    fn opaque_registers() {
+        let mut hugr = split_join(1);
+        assert_eq!(
+            lower_static_registers(&mut hugr, QuregLowering::Opaque, 100),
+            0
+        );
+        assert_eq!(register_ops(&hugr), 4);
+    }
+}
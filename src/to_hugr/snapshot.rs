@@ -0,0 +1,199 @@
+//! Snapshots of the translation state, written when a translation fails.
+//!
+//! See [`JeffToHugrConfig::debug_snapshots`](super::JeffToHugrConfig::debug_snapshots).
+
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::fs;
+use std::path::Path;
+
+use hugr::envelope::EnvelopeConfig;
+use hugr::{Hugr, HugrView, Node, NodeIndex};
+use serde_json::{Value as JsonValue, json};
+
+use super::BuildContext;
+
+/// File name of the partially built HUGR, stored as a text envelope.
+const HUGR_FILE: &str = "partial_hugr.json";
+/// File name of the partially built HUGR as a mermaid diagram, written when
+/// it cannot be stored as an envelope.
+const MERMAID_FILE: &str = "partial_hugr.mmd";
+/// File name of the [`BuildContext`] value edges.
+const CONTEXT_FILE: &str = "context.json";
+/// File name of the translation error.
+const ERROR_FILE: &str = "error.txt";
+
+/// Write the partially built HUGR, the value edges of the context and the
+/// error to `dir`.
+///
+/// The I/O error is returned so the caller can report it along with the
+/// translation error, see
+/// [`JeffToHugrError::SnapshotFailed`](super::JeffToHugrError::SnapshotFailed).
+pub(super) fn write_snapshot(
+    dir: &Path,
+    hugr: &Hugr,
+    ctx: &BuildContext,
+    error: &(impl std::error::Error + ?Sized),
+) -> std::io::Result<()> {
+    fs::create_dir_all(dir)?;
+
+    // Storing does not validate the HUGR, but fall back to a diagram in case
+    // the graph is too broken to be serialized.
+    match hugr.store_str(EnvelopeConfig::text()) {
+        Ok(envelope) => fs::write(dir.join(HUGR_FILE), envelope)?,
+        Err(_) => fs::write(dir.join(MERMAID_FILE), hugr.mermaid_string())?,
+    }
+
+    let context = serde_json::to_string_pretty(&context_json(ctx))?;
+    fs::write(dir.join(CONTEXT_FILE), context)?;
+
+    let mut report = format!("{error}\n\n{error:#?}\n");
+    let mut source = error.source();
+    while let Some(cause) = source {
+        report.push_str(&format!("\nCaused by: {cause}"));
+        source = cause.source();
+    }
+    fs::write(dir.join(ERROR_FILE), report)
+}
+
+/// Encode the value edges of a context as JSON.
+///
+/// Values are keyed by the debug representation of their _jeff_ id, and
+/// ports are encoded as `[node index, port offset]` pairs.
+fn context_json(ctx: &BuildContext) -> JsonValue {
+    let merged_values: BTreeMap<String, Vec<String>> = ctx
+        .merged_values
+        .keys()
+        .map(|&value_id| {
+            let mut chain = vec![value_id];
+            while let Some(&earlier) = ctx.merged_values.get(chain.last().unwrap()) {
+                chain.push(earlier);
+            }
+            (value_key(value_id), chain.into_iter().map(value_key).collect())
+        })
+        .collect();
+
+    json!({
+        "function": ctx.current_function_name,
+        "op_index": ctx.current_op,
+        "input_edges": edges_json(&ctx.input_edges),
+        "output_edges": edges_json(&ctx.output_edges),
+        "unconnected_inputs": edges_json(&ctx.unconnected_inputs),
+        "merged_values": merged_values,
+    })
+}
+
+/// Encode a map from values to node ports.
+fn edges_json<V: Debug, P: Copy + Into<hugr::Port>>(
+    edges: &BTreeMap<V, Vec<(Node, P)>>,
+) -> BTreeMap<String, Vec<[usize; 2]>> {
+    edges
+        .iter()
+        .map(|(value_id, ports)| {
+            let ports = ports
+                .iter()
+                .map(|&(node, port)| [node.index(), port.into().index()])
+                .collect();
+            (value_key(value_id), ports)
+        })
+        .collect()
+}
+
+/// The JSON key of a _jeff_ value.
+fn value_key(value_id: impl Debug) -> String {
+    format!("{value_id:?}")
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::test::qubits;
+    use crate::to_hugr::hooks::{ConversionHook, DataflowDyn};
+    use crate::{JeffToHugrConfig, JeffToHugrError, jeff_to_hugr_with_config};
+    use jeff::Jeff;
+    use jeff::reader::optype::{OpType as JeffOpType, QubitOp};
+    use rstest::rstest;
+
+    /// Drops the gates without registering their values, leaving the inputs
+    /// that read their outputs unconnected.
+    struct DropGates(Arc<Mutex<Vec<String>>>);
+
+    impl ConversionHook for DropGates {
+        fn try_convert(
+            &self,
+            op: &jeff::reader::Operation<'_>,
+            _builder: &mut dyn DataflowDyn,
+            _ctx: &mut BuildContext,
+        ) -> Option<Result<(), JeffToHugrError>> {
+            let JeffOpType::QubitOp(QubitOp::Gate(_)) = op.op_type() else {
+                return None;
+            };
+            let mut dropped = self.0.lock().unwrap();
+            for value in op.outputs() {
+                match value {
+                    Ok(value) => dropped.push(value_key(value.id())),
+                    Err(e) => return Some(Err(e.into())),
+                }
+            }
+            Some(Ok(()))
+        }
+    }
+
+    #[rstest]
+    fn snapshot_on_failure(qubits: Jeff<'static>) {
+        let dir = std::env::temp_dir().join(format!("hugr-jeff-snapshot-{}", std::process::id()));
+        let dropped = Arc::new(Mutex::new(Vec::new()));
+        let mut config = JeffToHugrConfig::default();
+        config.hooks.push(DropGates(dropped.clone()));
+        config.debug_snapshots = Some(dir.clone());
+
+        let err = jeff_to_hugr_with_config(&qubits, &config).unwrap_err();
+        assert!(matches!(err, JeffToHugrError::InvalidHugrProgram(_)));
+
+        assert!(dir.join(HUGR_FILE).exists());
+        assert!(dir.join(ERROR_FILE).exists());
+        let context = fs::read_to_string(dir.join(CONTEXT_FILE)).unwrap();
+        let context: JsonValue = serde_json::from_str(&context).unwrap();
+        let unconnected = context["unconnected_inputs"].as_object().unwrap();
+        let dropped = dropped.lock().unwrap();
+        assert!(!dropped.is_empty());
+        assert!(dropped.iter().any(|value| unconnected.contains_key(value)));
+        fs::remove_dir_all(&dir).unwrap();
+
+        // Without validation, the invalid HUGR is returned for inspection.
+        let config = config.with_validate(false);
+        let hugr = jeff_to_hugr_with_config(&qubits, &config).unwrap().hugr;
+        assert!(hugr.validate().is_err());
+        assert!(!dir.exists());
+    }
+
+    /// A snapshot that cannot be written is reported along with the
+    /// translation error.
+    #[rstest]
+    fn snapshot_write_failure(qubits: Jeff<'static>) {
+        // The snapshot directory cannot be created below a file.
+        let file =
+            std::env::temp_dir().join(format!("hugr-jeff-snapshot-file-{}", std::process::id()));
+        fs::write(&file, "").unwrap();
+        let dir = file.join("snapshot");
+        let mut config = JeffToHugrConfig::default();
+        config.hooks.push(DropGates(Arc::default()));
+        config.debug_snapshots = Some(dir.clone());
+
+        let err = jeff_to_hugr_with_config(&qubits, &config).unwrap_err();
+        fs::remove_file(&file).unwrap();
+        let JeffToHugrError::SnapshotFailed {
+            error,
+            dir: snapshot_dir,
+            ..
+        } = &err
+        else {
+            panic!("Unexpected error: {err}");
+        };
+        assert!(matches!(**error, JeffToHugrError::InvalidHugrProgram(_)));
+        assert_eq!(snapshot_dir, &dir);
+        assert!(std::error::Error::source(&err).is_some());
+    }
+}
@@ -0,0 +1,383 @@
+//! Translation of _jeff_ programs into one HUGR per function.
+//!
+//! See [`jeff_to_hugr_split`] and [`link_hugrs`].
+
+use std::collections::{BTreeMap, HashMap};
+
+use derive_more::{Display, Error};
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::hugr::views::ExtractionResult;
+use hugr::ops::{FuncDecl, OpType};
+use hugr::types::PolyFuncType;
+use hugr::{Hugr, HugrView, Node};
+use jeff::Jeff;
+
+use crate::metadata;
+use crate::{JeffToHugrConfig, JeffToHugrError, jeff_to_hugr_with_config};
+
+/// Error raised by [`link_hugrs`].
+#[derive(Debug, Display, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum LinkError {
+    /// A function is declared with another signature than the one it is
+    /// defined with.
+    #[display("Function '{name}' is declared as {declared} but defined as {defined}")]
+    SignatureMismatch {
+        /// The name of the function.
+        name: String,
+        /// The signature of the declaration.
+        declared: String,
+        /// The signature of the definition.
+        defined: String,
+    },
+    /// A declared function is not defined by any piece.
+    #[display("Function '{name}' is declared but not defined")]
+    MissingDefinition {
+        /// The name of the function.
+        name: String,
+    },
+    /// Several pieces define a function with the same name.
+    #[display("Function '{name}' is defined more than once")]
+    DuplicateDefinition {
+        /// The name of the function.
+        name: String,
+    },
+}
+
+/// Translate a _jeff_ program into one HUGR per function, for build systems
+/// caching their artifacts per function.
+///
+/// See [`jeff_to_hugr_split_with_config`].
+pub fn jeff_to_hugr_split(jeff: &Jeff) -> Result<Vec<(String, Hugr)>, JeffToHugrError> {
+    jeff_to_hugr_split_with_config(jeff, &JeffToHugrConfig::default())
+}
+
+/// Translate a _jeff_ program into one HUGR per function, using a custom
+/// configuration.
+///
+/// The program is translated as by [`jeff_to_hugr_with_config`], then each
+/// function of the module is moved to its own HUGR module, returned with
+/// the HUGR name of the function. The helper functions added by the
+/// translation get their own piece too.
+///
+/// A piece holds the function, declarations of the functions it calls
+/// marked with [`metadata::LINK_STUB`], and copies of the constants it
+/// loads, so each piece validates on its own. The entrypoint of a piece is
+/// its function definition, or the module root for a declaration.
+/// [`link_hugrs`] reassembles the pieces.
+///
+/// # Examples
+///
+/// ```
+/// use hugr_jeff::doctest_support::qubits;
+/// use hugr_jeff::hugr::HugrView;
+/// use hugr_jeff::{jeff_to_hugr_split, link_hugrs};
+///
+/// let pieces = jeff_to_hugr_split(&qubits()).unwrap();
+/// for (_, piece) in &pieces {
+///     piece.validate().unwrap();
+/// }
+///
+/// let pieces = pieces.into_iter().map(|(_, piece)| piece).collect::<Vec<_>>();
+/// let hugr = link_hugrs(&pieces).unwrap();
+/// hugr.validate().unwrap();
+/// ```
+pub fn jeff_to_hugr_split_with_config(
+    jeff: &Jeff,
+    config: &JeffToHugrConfig,
+) -> Result<Vec<(String, Hugr)>, JeffToHugrError> {
+    let hugr = jeff_to_hugr_with_config(jeff, config)?.hugr;
+    Ok(hugr
+        .children(hugr.module_root())
+        .filter_map(|node| {
+            let (name, _) = function_signature(hugr.get_optype(node))?;
+            Some((name.to_string(), extract_piece(&hugr, node)))
+        })
+        .collect())
+}
+
+/// Link HUGR modules into a single module, resolving the function
+/// declarations marked with [`metadata::LINK_STUB`].
+///
+/// The marked declarations are replaced with the function of the same name
+/// from another piece. The other functions are kept, and must have distinct
+/// names. Identical constants are merged, and the metadata of the module
+/// roots is combined, the first piece taking precedence.
+///
+/// Linking the pieces of [`jeff_to_hugr_split`] gives back the translation
+/// of the whole program.
+///
+/// # Errors
+///
+/// - [`LinkError::MissingDefinition`] if no piece has a function for a
+///   marked declaration.
+/// - [`LinkError::SignatureMismatch`] if a marked declaration does not have
+///   the signature of its function.
+/// - [`LinkError::DuplicateDefinition`] if two pieces have a function with
+///   the same name.
+pub fn link_hugrs(pieces: &[Hugr]) -> Result<Hugr, LinkError> {
+    // The functions of the pieces, by name.
+    let mut functions: BTreeMap<&str, (usize, Node)> = BTreeMap::new();
+    for (idx, piece) in pieces.iter().enumerate() {
+        for node in piece.children(piece.module_root()) {
+            if is_stub(piece, node) {
+                continue;
+            }
+            let Some((name, _)) = function_signature(piece.get_optype(node)) else {
+                continue;
+            };
+            if functions.insert(name, (idx, node)).is_some() {
+                let name = name.to_string();
+                return Err(LinkError::DuplicateDefinition { name });
+            }
+        }
+    }
+
+    let mut linked = Hugr::default();
+    let linked_root = linked.module_root();
+    // The nodes of the linked HUGR, by piece and node of the piece.
+    let mut nodes: HashMap<(usize, Node), Node> = HashMap::new();
+    let mut constants: Vec<(&OpType, Node)> = Vec::new();
+    for (idx, piece) in pieces.iter().enumerate() {
+        let root = piece.module_root();
+        for (key, value) in piece.node_metadata_map(root) {
+            if linked.get_metadata(linked_root, key).is_none() {
+                linked.set_metadata(linked_root, key, value.clone());
+            }
+        }
+        for child in piece.children(root) {
+            let op = piece.get_optype(child);
+            if is_stub(piece, child) {
+                continue;
+            } else if op.is_func_defn() {
+                let (mut extracted, node_map) = piece.extract_hugr(child);
+                extracted.set_entrypoint(node_map.extracted_node(child));
+                let inserted = linked.insert_hugr(linked_root, extracted);
+                for node in piece.descendants(child) {
+                    let new_node = inserted.node_map[&node_map.extracted_node(node)];
+                    nodes.insert((idx, node), new_node);
+                }
+            } else {
+                let shared = constants.iter().find(|(constant, _)| *constant == op);
+                let new_node = match shared {
+                    Some(&(_, new_node)) => new_node,
+                    None => {
+                        let new_node = linked.add_node_with_parent(linked_root, op.clone());
+                        let metadata = piece.node_metadata_map(child).clone();
+                        linked.overwrite_node_metadata(new_node, Some(metadata));
+                        if op.is_const() {
+                            constants.push((op, new_node));
+                        }
+                        new_node
+                    }
+                };
+                nodes.insert((idx, child), new_node);
+            }
+        }
+    }
+
+    // Connect the static inputs linked to module-level nodes of the pieces.
+    for (idx, piece) in pieces.iter().enumerate() {
+        let root = piece.module_root();
+        for node in piece.nodes() {
+            let Some(port) = piece.get_optype(node).static_input_port() else {
+                continue;
+            };
+            let Some((src, src_port)) = piece.single_linked_output(node, port) else {
+                continue;
+            };
+            let target = nodes[&(idx, node)];
+            if piece.get_parent(src) != Some(root)
+                || linked.single_linked_output(target, port).is_some()
+            {
+                continue;
+            }
+            let source = match is_stub(piece, src) {
+                true => resolve_stub(pieces, piece.get_optype(src), &functions, &nodes)?,
+                false => nodes[&(idx, src)],
+            };
+            linked.connect(source, src_port, target, port);
+        }
+    }
+    Ok(linked)
+}
+
+/// Copy a function into a standalone HUGR module, with declarations of the
+/// functions it calls and copies of the constants it loads.
+fn extract_piece(hugr: &Hugr, func: Node) -> Hugr {
+    let module_root = hugr.module_root();
+    let (mut piece, func_copy, node_map) = match hugr.get_optype(func).is_func_defn() {
+        true => {
+            let (mut piece, node_map) = hugr.extract_hugr(func);
+            let func_copy = node_map.extracted_node(func);
+            piece.set_entrypoint(func_copy);
+            let node_map = hugr
+                .descendants(func)
+                .map(|node| (node, node_map.extracted_node(node)))
+                .collect::<HashMap<_, _>>();
+            (piece, func_copy, node_map)
+        }
+        false => {
+            let mut piece = Hugr::default();
+            let root = piece.module_root();
+            let func_copy = piece.add_node_with_parent(root, hugr.get_optype(func).clone());
+            (piece, func_copy, HashMap::from([(func, func_copy)]))
+        }
+    };
+    let piece_root = piece.module_root();
+    for (key, value) in hugr.node_metadata_map(module_root) {
+        piece.set_metadata(piece_root, key, value.clone());
+    }
+    let metadata = hugr.node_metadata_map(func).clone();
+    piece.overwrite_node_metadata(func_copy, Some(metadata));
+
+    // The copies of the module-level nodes used by the function.
+    let mut copies = HashMap::from([(func, func_copy)]);
+    for node in hugr.descendants(func) {
+        let Some(port) = hugr.get_optype(node).static_input_port() else {
+            continue;
+        };
+        let Some((src, src_port)) = hugr.single_linked_output(node, port) else {
+            continue;
+        };
+        if src == func || hugr.get_parent(src) != Some(module_root) {
+            continue;
+        }
+        let copy = *copies.entry(src).or_insert_with(|| {
+            let op = hugr.get_optype(src);
+            match function_signature(op) {
+                Some((name, signature)) => {
+                    let stub = FuncDecl::new(name, signature.clone());
+                    let stub = piece.add_node_with_parent(piece_root, stub);
+                    piece.set_metadata(stub, metadata::LINK_STUB, true);
+                    stub
+                }
+                None => {
+                    let copy = piece.add_node_with_parent(piece_root, op.clone());
+                    let metadata = hugr.node_metadata_map(src).clone();
+                    piece.overwrite_node_metadata(copy, Some(metadata));
+                    copy
+                }
+            }
+        });
+        piece.connect(copy, src_port, node_map[&node], port);
+    }
+    piece
+}
+
+/// The node of the linked HUGR replacing a function declaration marked with
+/// [`metadata::LINK_STUB`].
+fn resolve_stub(
+    pieces: &[Hugr],
+    stub: &OpType,
+    functions: &BTreeMap<&str, (usize, Node)>,
+    nodes: &HashMap<(usize, Node), Node>,
+) -> Result<Node, LinkError> {
+    let (name, declared) = function_signature(stub).expect("stubs are declarations");
+    let Some(&(idx, func)) = functions.get(name) else {
+        let name = name.to_string();
+        return Err(LinkError::MissingDefinition { name });
+    };
+    let (_, defined) = function_signature(pieces[idx].get_optype(func)).unwrap();
+    if declared != defined {
+        return Err(LinkError::SignatureMismatch {
+            name: name.to_string(),
+            declared: declared.to_string(),
+            defined: defined.to_string(),
+        });
+    }
+    Ok(nodes[&(idx, func)])
+}
+
+/// The name and signature of a function definition or declaration.
+fn function_signature(op: &OpType) -> Option<(&str, &PolyFuncType)> {
+    match op {
+        OpType::FuncDefn(defn) => Some((defn.func_name().as_str(), defn.signature())),
+        OpType::FuncDecl(decl) => Some((decl.func_name().as_str(), decl.signature())),
+        _ => None,
+    }
+}
+
+/// Whether a node is a declaration marked with [`metadata::LINK_STUB`].
+fn is_stub(hugr: &Hugr, node: Node) -> bool {
+    hugr.get_metadata(node, metadata::LINK_STUB)
+        .and_then(|mark| mark.as_bool())
+        == Some(true)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::jeff_to_hugr;
+    use crate::test::{assert_hugr_equivalent, catalyst_tket_opt, entangled_calls, qubits};
+    use hugr::extension::prelude::qb_t;
+    use hugr::types::Signature;
+    use itertools::Itertools;
+    use rstest::rstest;
+
+    /// The pieces of a program, and the piece declaring a stub with its name.
+    fn pieces_with_stub(jeff: &Jeff<'static>) -> (Vec<Hugr>, usize, Node, String) {
+        let pieces = jeff_to_hugr_split(jeff)
+            .unwrap()
+            .into_iter()
+            .map(|(_, piece)| piece)
+            .collect_vec();
+        let (idx, stub) = pieces
+            .iter()
+            .enumerate()
+            .find_map(|(idx, piece)| {
+                let stub = piece
+                    .children(piece.module_root())
+                    .find(|&node| is_stub(piece, node))?;
+                Some((idx, stub))
+            })
+            .unwrap();
+        let (name, _) = function_signature(pieces[idx].get_optype(stub)).unwrap();
+        let name = name.to_string();
+        (pieces, idx, stub, name)
+    }
+
+    #[rstest]
+    #[case::qubits(qubits())]
+    #[case::entangled_calls(entangled_calls())]
+    #[case::catalyst_tket_opt(catalyst_tket_opt())]
+    fn split_and_link(#[case] jeff: Jeff<'static>) {
+        let pieces = jeff_to_hugr_split(&jeff).unwrap();
+        for (name, piece) in &pieces {
+            piece
+                .validate()
+                .unwrap_or_else(|e| panic!("Piece '{name}': {e}"));
+        }
+
+        let pieces = pieces.into_iter().map(|(_, piece)| piece).collect_vec();
+        let linked = link_hugrs(&pieces).unwrap();
+        linked.validate().unwrap_or_else(|e| panic!("{e}"));
+        assert_hugr_equivalent(&linked, &jeff_to_hugr(&jeff).unwrap());
+    }
+
+    #[rstest]
+    fn signature_mismatch(entangled_calls: Jeff<'static>) {
+        let (mut pieces, idx, stub, name) = pieces_with_stub(&entangled_calls);
+        let wrong_signature = Signature::new_endo(vec![qb_t()]);
+        pieces[idx].replace_op(stub, FuncDecl::new(name.clone(), wrong_signature));
+
+        let err = link_hugrs(&pieces).unwrap_err();
+        let LinkError::SignatureMismatch { name: function, .. } = &err else {
+            panic!("Unexpected error: {err}");
+        };
+        assert_eq!(*function, name);
+        assert!(err.to_string().contains(&name));
+    }
+
+    #[rstest]
+    fn missing_definition(entangled_calls: Jeff<'static>) {
+        let (mut pieces, _, _, name) = pieces_with_stub(&entangled_calls);
+        pieces.retain(|piece| {
+            let entrypoint = piece.get_optype(piece.entrypoint());
+            function_signature(entrypoint).is_none_or(|(function, _)| function != name)
+        });
+
+        let err = link_hugrs(&pieces).unwrap_err();
+        assert_eq!(err, LinkError::MissingDefinition { name });
+    }
+}
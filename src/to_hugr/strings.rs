@@ -0,0 +1,124 @@
+//! Sanitization of the strings read from _jeff_ programs.
+//!
+//! The gate and function names of a program enter the HUGR through
+//! [`sanitize_string`], which:
+//!
+//! - rejects strings with an embedded NUL byte, which the HUGR serializers
+//!   and the C runtimes cannot represent,
+//! - replaces the invalid UTF-8 sequences with `U+FFFD`,
+//! - truncates the strings longer than
+//!   [`crate::ResourceLimits::max_string_len`] bytes, replacing their end with
+//!   a hash of the full string so that distinct long strings stay distinct.
+
+use std::borrow::Cow;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// Length in bytes of the suffix of a truncated string, a `#` followed by the
+/// 16 hexadecimal digits of its hash.
+pub(crate) const HASH_SUFFIX_LEN: usize = 17;
+
+/// A string read from a _jeff_ program, ready to enter the HUGR.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SanitizedString {
+    /// The sanitized string.
+    pub value: String,
+    /// Whether invalid UTF-8 sequences were replaced.
+    pub invalid_utf8: bool,
+    /// The length in bytes of the original string, if it was truncated.
+    pub truncated_from: Option<usize>,
+}
+
+/// Sanitize a string read from a _jeff_ program.
+///
+/// Truncated strings are at most `max_len` bytes long, or
+/// [`HASH_SUFFIX_LEN`] bytes if `max_len` is shorter than the hash suffix.
+/// The truncation only depends on the original bytes, and is identical
+/// across runs.
+///
+/// Returns `None` if the string contains a NUL byte.
+pub(crate) fn sanitize_string(bytes: &[u8], max_len: usize) -> Option<SanitizedString> {
+    if bytes.contains(&0) {
+        return None;
+    }
+    let decoded = String::from_utf8_lossy(bytes);
+    let invalid_utf8 = matches!(decoded, Cow::Owned(_));
+    let mut value = decoded.into_owned();
+    let truncated_from = (value.len() > max_len).then(|| {
+        truncate_with_hash(&mut value, bytes, max_len);
+        bytes.len()
+    });
+    Some(SanitizedString {
+        value,
+        invalid_utf8,
+        truncated_from,
+    })
+}
+
+/// Truncate a string to `max_len` bytes, ending with the hash of its original
+/// bytes.
+fn truncate_with_hash(value: &mut String, bytes: &[u8], max_len: usize) {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    let suffix = format!("#{:016x}", hasher.finish());
+    let mut len = max_len.saturating_sub(suffix.len());
+    while !value.is_char_boundary(len) {
+        len -= 1;
+    }
+    value.truncate(len);
+    value.push_str(&suffix);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::valid(b"CX", "CX", false)]
+    #[case::unicode("θ_rot".as_bytes(), "θ_rot", false)]
+    #[case::invalid(b"C\xC3\x28X", "C\u{FFFD}(X", true)]
+    #[case::truncated_sequence(b"CX\xE2\x82", "CX\u{FFFD}", true)]
+    fn decode(#[case] bytes: &[u8], #[case] expected: &str, #[case] invalid_utf8: bool) {
+        let sanitized = sanitize_string(bytes, 4096).unwrap();
+        assert_eq!(sanitized.value, expected);
+        assert_eq!(sanitized.invalid_utf8, invalid_utf8);
+        assert_eq!(sanitized.truncated_from, None);
+    }
+
+    #[rstest]
+    fn nul() {
+        assert_eq!(sanitize_string(b"C\0X", 4096), None);
+        assert_eq!(sanitize_string(b"\0", 4096), None);
+    }
+
+    #[rstest]
+    fn truncate() {
+        let long = "g".repeat(1 << 20);
+        let sanitized = sanitize_string(long.as_bytes(), 4096).unwrap();
+        assert_eq!(sanitized.value.len(), 4096);
+        assert_eq!(sanitized.truncated_from, Some(1 << 20));
+        assert!(long.starts_with(&sanitized.value[..4096 - HASH_SUFFIX_LEN]));
+        assert!(sanitized.value[4096 - HASH_SUFFIX_LEN..].starts_with('#'));
+        // The truncation is deterministic.
+        assert_eq!(sanitize_string(long.as_bytes(), 4096), Some(sanitized));
+    }
+
+    #[rstest]
+    fn truncate_without_collisions() {
+        let first = format!("{}a", "g".repeat(1 << 20));
+        let second = format!("{}b", "g".repeat(1 << 20));
+        let first = sanitize_string(first.as_bytes(), 4096).unwrap().value;
+        let second = sanitize_string(second.as_bytes(), 4096).unwrap().value;
+        assert_eq!(first.len(), second.len());
+        assert_ne!(first, second);
+    }
+
+    #[rstest]
+    #[case::char_boundary(&"θ".repeat(100), 22)]
+    #[case::shorter_than_suffix("long gate name", 4)]
+    fn truncate_short(#[case] string: &str, #[case] max_len: usize) {
+        let sanitized = sanitize_string(string.as_bytes(), max_len).unwrap();
+        assert!(sanitized.value.len() <= max_len.max(HASH_SUFFIX_LEN));
+        assert!(string.starts_with(&sanitized.value[..sanitized.value.len() - HASH_SUFFIX_LEN]));
+    }
+}
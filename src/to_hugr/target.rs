@@ -0,0 +1,167 @@
+//! Description of the quantum device a program is compiled for.
+//!
+//! See [`TargetInfo`].
+
+use std::collections::BTreeSet;
+
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::{HugrView, Node};
+use serde::{Deserialize, Serialize};
+
+use crate::metadata;
+
+/// The quantum device a program is compiled for, as needed for placement.
+///
+/// The target of a translation is set with
+/// [`crate::JeffToHugrConfig::target`], and stored in the
+/// [`metadata::TARGET`] entry of the module root. It is read back with
+/// [`TargetInfo::from_metadata`].
+///
+/// Targets can be loaded from JSON, e.g.
+///
+/// ```json
+/// {
+///   "name": "line-3",
+///   "num_qubits": 3,
+///   "coupling": [[0, 1], [1, 2]],
+///   "native_gates": ["Rz", "Rx", "CZ"]
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct TargetInfo {
+    /// Name of the backend.
+    pub name: String,
+    /// Number of physical qubits of the device.
+    pub num_qubits: usize,
+    /// Pairs of physical qubits two-qubit gates can act on, in either order,
+    /// with the lowest qubit first. An empty coupling map connects all the
+    /// qubits.
+    pub coupling: BTreeSet<(usize, usize)>,
+    /// Names of the native gates of the device, see
+    /// [`crate::analysis::GateSet::from_target`].
+    pub native_gates: BTreeSet<String>,
+}
+
+impl TargetInfo {
+    /// A device with all-to-all connectivity and no native gates.
+    pub fn new(name: impl ToString, num_qubits: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            num_qubits,
+            ..Self::default()
+        }
+    }
+
+    /// Add some coupled pairs of qubits, see [`TargetInfo::coupling`].
+    pub fn with_coupling(mut self, edges: impl IntoIterator<Item = (usize, usize)>) -> Self {
+        self.coupling
+            .extend(edges.into_iter().map(|(a, b)| (a.min(b), a.max(b))));
+        self
+    }
+
+    /// Add some native gates, see [`TargetInfo::native_gates`].
+    pub fn with_native_gates(mut self, gates: impl IntoIterator<Item = impl ToString>) -> Self {
+        self.native_gates
+            .extend(gates.into_iter().map(|gate| gate.to_string()));
+        self
+    }
+
+    /// Whether a two-qubit gate can act on a pair of physical qubits.
+    pub fn is_coupled(&self, a: usize, b: usize) -> bool {
+        a != b
+            && a.max(b) < self.num_qubits
+            && (self.coupling.is_empty() || self.coupling.contains(&(a.min(b), a.max(b))))
+    }
+
+    /// Read the target stored in the [`metadata::TARGET`] entry of the
+    /// module root, if any.
+    pub fn from_metadata(hugr: &impl HugrView<Node = Node>) -> Option<Self> {
+        let entry = hugr.get_metadata(hugr.module_root(), metadata::TARGET)?;
+        serde_json::from_value(entry.clone()).ok()
+    }
+
+    /// Store the target in the [`metadata::TARGET`] entry of the module root.
+    pub fn store(&self, hugr: &mut impl HugrMut<Node = Node>) {
+        let entry = serde_json::to_value(self).expect("Targets serialize to JSON");
+        hugr.set_metadata(hugr.module_root(), metadata::TARGET, entry);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::catalyst_tket_opt;
+    use crate::{
+        ConversionWarning, ConvertedHugr, JeffToHugrConfig, jeff_to_hugr, jeff_to_hugr_with_config,
+    };
+    use jeff::Jeff;
+    use rstest::rstest;
+
+    fn line_target() -> TargetInfo {
+        TargetInfo::new("line-3", 3)
+            .with_coupling([(1, 0), (1, 2)])
+            .with_native_gates(["Rz", "Rx", "CZ"])
+    }
+
+    #[rstest]
+    fn coupling() {
+        let target = line_target();
+        assert!(target.is_coupled(0, 1));
+        assert!(target.is_coupled(2, 1));
+        assert!(!target.is_coupled(0, 2));
+        assert!(!target.is_coupled(1, 1));
+        // All the qubits of a device without coupling map are connected.
+        let all_to_all = TargetInfo::new("all-to-all", 3);
+        assert!(all_to_all.is_coupled(0, 2));
+        assert!(!all_to_all.is_coupled(0, 3));
+    }
+
+    #[rstest]
+    fn metadata_roundtrip(catalyst_tket_opt: Jeff<'static>) {
+        let target = line_target();
+        let config = JeffToHugrConfig::default().with_target(target.clone());
+        let converted = jeff_to_hugr_with_config(&catalyst_tket_opt, &config).unwrap();
+        assert_eq!(TargetInfo::from_metadata(&converted.hugr), Some(target.clone()));
+
+        let json = serde_json::json!({
+            "name": "line-3",
+            "num_qubits": 3,
+            "coupling": [[0, 1], [1, 2]],
+            "native_gates": ["CZ", "Rx", "Rz"],
+        });
+        assert_eq!(serde_json::to_value(&target).unwrap(), json);
+        assert_eq!(serde_json::from_value::<TargetInfo>(json).unwrap(), target);
+
+        let hugr = jeff_to_hugr(&catalyst_tket_opt).unwrap();
+        assert_eq!(TargetInfo::from_metadata(&hugr), None);
+    }
+
+    /// The warnings about functions using more qubits than the target has.
+    fn exceeded(converted: &ConvertedHugr) -> Vec<(&str, usize)> {
+        converted
+            .warnings
+            .iter()
+            .filter_map(|warning| match warning {
+                ConversionWarning::TargetQubitsExceeded {
+                    target, available, ..
+                } => Some((target.as_str(), *available)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[rstest]
+    fn too_few_qubits(catalyst_tket_opt: Jeff<'static>) {
+        let config = JeffToHugrConfig::default().with_target(TargetInfo::new("large", 64));
+        let converted = jeff_to_hugr_with_config(&catalyst_tket_opt, &config).unwrap();
+        assert!(exceeded(&converted).is_empty());
+
+        let config = config.with_target(TargetInfo::new("empty", 0));
+        let converted = jeff_to_hugr_with_config(&catalyst_tket_opt, &config).unwrap();
+        let warnings = exceeded(&converted);
+        assert!(!warnings.is_empty());
+        assert!(warnings.iter().all(|&warning| warning == ("empty", 0)));
+    }
+}
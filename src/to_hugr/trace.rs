@@ -0,0 +1,642 @@
+//! Traces of the translation, replayable into the partially built HUGRs.
+//!
+//! See [`JeffToHugrConfig::record_trace`](super::JeffToHugrConfig::record_trace).
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::{self, Write};
+use std::iter;
+
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::ops::OpType;
+use hugr::{Direction, Hugr, HugrView, IncomingPort, Node, NodeIndex, OutgoingPort, Port};
+use itertools::Itertools;
+use jeff::reader::value::ValueId;
+use serde_json::{Value as JsonValue, json};
+
+/// A link between two ports, as `(source, source offset, target, target offset)`.
+type Link = (Node, usize, Node, usize);
+
+/// An event recorded while translating a _jeff_ program.
+///
+/// The structural events, from [`TraceEvent::NodeAdded`] to
+/// [`TraceEvent::EdgeRemoved`], describe the changes to the HUGR and are
+/// replayed by [`ConversionTrace::hugr_at`]. The other events describe the
+/// translation steps causing them.
+///
+/// Nodes are identified by their index in the translated HUGR.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum TraceEvent {
+    /// A node was added to the HUGR.
+    NodeAdded {
+        /// The new node.
+        node: Node,
+        /// The parent of the node, `None` for the module root.
+        parent: Option<Node>,
+        /// The operation of the node.
+        op: OpType,
+    },
+    /// A node was moved under a new parent.
+    NodeMoved {
+        /// The moved node.
+        node: Node,
+        /// The new parent of the node.
+        parent: Node,
+    },
+    /// The operation of a node was replaced.
+    OpReplaced {
+        /// The updated node.
+        node: Node,
+        /// The new operation of the node.
+        op: OpType,
+    },
+    /// A node was removed from the HUGR.
+    NodeRemoved {
+        /// The removed node.
+        node: Node,
+    },
+    /// An edge was added between two ports, given by their offsets.
+    ///
+    /// Value, static and order edges are all recorded.
+    EdgeAdded {
+        /// The source node.
+        source: Node,
+        /// The offset of the outgoing port.
+        source_port: usize,
+        /// The target node.
+        target: Node,
+        /// The offset of the incoming port.
+        target_port: usize,
+    },
+    /// An edge between two ports, given by their offsets, was removed.
+    EdgeRemoved {
+        /// The source node.
+        source: Node,
+        /// The offset of the outgoing port.
+        source_port: usize,
+        /// The target node.
+        target: Node,
+        /// The offset of the incoming port.
+        target_port: usize,
+    },
+    /// The translation of a _jeff_ region started.
+    RegionEntered {
+        /// The HUGR name of the function containing the region.
+        function: Option<String>,
+        /// The HUGR node holding the translated region.
+        container: Node,
+    },
+    /// The translation of a _jeff_ region finished, after connecting its
+    /// hyperedges.
+    RegionExited {
+        /// The HUGR node holding the translated region.
+        container: Node,
+    },
+    /// A _jeff_ operation was translated.
+    OpConverted {
+        /// The HUGR name of the function containing the operation.
+        function: Option<String>,
+        /// The position of the operation, counting from the start of its
+        /// function.
+        op_index: usize,
+        /// The nodes added to the region to translate the operation.
+        nodes: Vec<Node>,
+    },
+    /// A port was registered to a _jeff_ value, to be connected with the
+    /// other ports of the value at the end of the region.
+    ValueRegistered {
+        /// The value, after following the merged values.
+        value: ValueId,
+        /// The node of the port.
+        node: Node,
+        /// The registered port.
+        port: Port,
+    },
+    /// A _jeff_ value was merged into a value appearing earlier in its
+    /// region.
+    ValuesMerged {
+        /// The merged value.
+        value: ValueId,
+        /// The value it was merged into.
+        earlier: ValueId,
+    },
+    /// An outgoing and an incoming port registered to the same _jeff_ value
+    /// were connected.
+    HyperedgeConnected {
+        /// The connected value.
+        value: ValueId,
+        /// The source port.
+        source: (Node, OutgoingPort),
+        /// The target port.
+        target: (Node, IncomingPort),
+    },
+}
+
+impl TraceEvent {
+    /// Whether the event mentions a _jeff_ value.
+    pub fn mentions_value(&self, value_id: ValueId) -> bool {
+        match self {
+            TraceEvent::ValueRegistered { value, .. }
+            | TraceEvent::HyperedgeConnected { value, .. } => *value == value_id,
+            TraceEvent::ValuesMerged { value, earlier } => {
+                *value == value_id || *earlier == value_id
+            }
+            _ => false,
+        }
+    }
+
+    /// Encode the event as a JSON object, with its kind in the `"event"`
+    /// field.
+    ///
+    /// Nodes are encoded by their index, ports by their offset, and values
+    /// by the debug representation of their _jeff_ id.
+    pub fn to_json(&self) -> JsonValue {
+        match self {
+            TraceEvent::NodeAdded { node, parent, op } => json!({
+                "event": "node_added",
+                "node": node.index(),
+                "parent": parent.map(|parent| parent.index()),
+                "op": op,
+            }),
+            TraceEvent::NodeMoved { node, parent } => json!({
+                "event": "node_moved",
+                "node": node.index(),
+                "parent": parent.index(),
+            }),
+            TraceEvent::OpReplaced { node, op } => json!({
+                "event": "op_replaced",
+                "node": node.index(),
+                "op": op,
+            }),
+            TraceEvent::NodeRemoved { node } => json!({
+                "event": "node_removed",
+                "node": node.index(),
+            }),
+            TraceEvent::EdgeAdded {
+                source,
+                source_port,
+                target,
+                target_port,
+            } => json!({
+                "event": "edge_added",
+                "source": [source.index(), source_port],
+                "target": [target.index(), target_port],
+            }),
+            TraceEvent::EdgeRemoved {
+                source,
+                source_port,
+                target,
+                target_port,
+            } => json!({
+                "event": "edge_removed",
+                "source": [source.index(), source_port],
+                "target": [target.index(), target_port],
+            }),
+            TraceEvent::RegionEntered {
+                function,
+                container,
+            } => json!({
+                "event": "region_entered",
+                "function": function,
+                "container": container.index(),
+            }),
+            TraceEvent::RegionExited { container } => json!({
+                "event": "region_exited",
+                "container": container.index(),
+            }),
+            TraceEvent::OpConverted {
+                function,
+                op_index,
+                nodes,
+            } => json!({
+                "event": "op_converted",
+                "function": function,
+                "op_index": op_index,
+                "nodes": nodes.iter().map(|node| node.index()).collect_vec(),
+            }),
+            TraceEvent::ValueRegistered { value, node, port } => json!({
+                "event": "value_registered",
+                "value": format!("{value:?}"),
+                "node": node.index(),
+                "port": port.index(),
+                "direction": match port.direction() {
+                    Direction::Incoming => "incoming",
+                    Direction::Outgoing => "outgoing",
+                },
+            }),
+            TraceEvent::ValuesMerged { value, earlier } => json!({
+                "event": "values_merged",
+                "value": format!("{value:?}"),
+                "earlier": format!("{earlier:?}"),
+            }),
+            TraceEvent::HyperedgeConnected {
+                value,
+                source,
+                target,
+            } => json!({
+                "event": "hyperedge_connected",
+                "value": format!("{value:?}"),
+                "source": [source.0.index(), source.1.index()],
+                "target": [target.0.index(), target.1.index()],
+            }),
+        }
+    }
+
+    /// The event with its nodes translated by a node map, or `None` if one
+    /// of them is missing from the map.
+    ///
+    /// Only used for the non-structural events of nested regions.
+    fn map_nodes(self, node_map: &HashMap<Node, Node>) -> Option<Self> {
+        let map = |node: Node| node_map.get(&node).copied();
+        Some(match self {
+            TraceEvent::RegionEntered {
+                function,
+                container,
+            } => TraceEvent::RegionEntered {
+                function,
+                container: map(container)?,
+            },
+            TraceEvent::RegionExited { container } => TraceEvent::RegionExited {
+                container: map(container)?,
+            },
+            TraceEvent::OpConverted {
+                function,
+                op_index,
+                nodes,
+            } => TraceEvent::OpConverted {
+                function,
+                op_index,
+                nodes: nodes.into_iter().filter_map(map).collect(),
+            },
+            TraceEvent::ValueRegistered { value, node, port } => TraceEvent::ValueRegistered {
+                value,
+                node: map(node)?,
+                port,
+            },
+            TraceEvent::HyperedgeConnected {
+                value,
+                source: (source, source_port),
+                target: (target, target_port),
+            } => TraceEvent::HyperedgeConnected {
+                value,
+                source: (map(source)?, source_port),
+                target: (map(target)?, target_port),
+            },
+            event @ TraceEvent::ValuesMerged { .. } => event,
+            _ => return None,
+        })
+    }
+}
+
+/// The events recorded while translating a _jeff_ program, in order.
+///
+/// Returned in [`crate::ConvertedHugr::trace`] when
+/// [`crate::JeffToHugrConfig::record_trace`] is set.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConversionTrace {
+    events: Vec<TraceEvent>,
+}
+
+impl ConversionTrace {
+    /// The recorded events, in order.
+    pub fn events(&self) -> &[TraceEvent] {
+        &self.events
+    }
+
+    /// The number of recorded events.
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Whether no event was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Rebuild the HUGR as it was after the event at position `step`.
+    ///
+    /// The structural events up to `step` are replayed into a fresh HUGR.
+    /// The metadata of the nodes is not recorded, and the node indices may
+    /// differ from the ones in the events. A `step` past the last event
+    /// rebuilds the translated HUGR.
+    pub fn hugr_at(&self, step: usize) -> Hugr {
+        let mut hugr = Hugr::default();
+        let mut nodes = BTreeMap::new();
+        let mut links = BTreeSet::new();
+        for event in self.events.iter().take(step.saturating_add(1)) {
+            match event {
+                TraceEvent::NodeAdded {
+                    node, parent: None, ..
+                } => {
+                    nodes.insert(*node, hugr.module_root());
+                }
+                TraceEvent::NodeAdded {
+                    node,
+                    parent: Some(parent),
+                    op,
+                } => {
+                    let added = hugr.add_node_with_parent(nodes[parent], op.clone());
+                    nodes.insert(*node, added);
+                }
+                TraceEvent::NodeMoved { node, parent } => {
+                    hugr.set_parent(nodes[node], nodes[parent]);
+                }
+                TraceEvent::OpReplaced { node, op } => {
+                    hugr.replace_op(nodes[node], op.clone());
+                }
+                TraceEvent::NodeRemoved { node } => {
+                    hugr.remove_node(nodes.remove(node).unwrap());
+                }
+                &TraceEvent::EdgeAdded {
+                    source,
+                    source_port,
+                    target,
+                    target_port,
+                } => {
+                    hugr.connect(nodes[&source], source_port, nodes[&target], target_port);
+                    links.insert((source, source_port, target, target_port));
+                }
+                &TraceEvent::EdgeRemoved {
+                    source,
+                    source_port,
+                    target,
+                    target_port,
+                } => {
+                    // Ports can only be disconnected as a whole, the other
+                    // links of the incoming port are connected again.
+                    links.remove(&(source, source_port, target, target_port));
+                    hugr.disconnect(nodes[&target], IncomingPort::from(target_port));
+                    for &(other, other_port, _, _) in links
+                        .iter()
+                        .filter(|&&(_, _, t, p)| (t, p) == (target, target_port))
+                    {
+                        hugr.connect(nodes[&other], other_port, nodes[&target], target_port);
+                    }
+                }
+                _ => {}
+            }
+        }
+        hugr
+    }
+
+    /// The events mentioning a _jeff_ value, with their position.
+    ///
+    /// Ports are registered to the value a merged value was merged into, see
+    /// [`TraceEvent::ValuesMerged`].
+    pub fn find_events_for_value(&self, value: ValueId) -> Vec<(usize, &TraceEvent)> {
+        self.events
+            .iter()
+            .enumerate()
+            .filter(|(_, event)| event.mentions_value(value))
+            .collect()
+    }
+
+    /// Write the events as JSON lines, see [`TraceEvent::to_json`].
+    ///
+    /// # Errors
+    ///
+    /// If the writer fails.
+    pub fn write_json_lines(&self, mut writer: impl Write) -> io::Result<()> {
+        for event in &self.events {
+            serde_json::to_writer(&mut writer, &event.to_json())?;
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+}
+
+/// Records the events of a translation in a [`ConversionTrace`].
+///
+/// The structural events are computed by comparing the HUGR to the state it
+/// had at the previous checkpoint, so that the changes made by the hooks and
+/// the passes are recorded too. The comparison goes through the whole HUGR,
+/// which makes the recording quadratic in its size.
+#[derive(Debug, Clone, Default)]
+pub(super) struct TraceRecorder {
+    trace: ConversionTrace,
+    /// Whether the regions are built in a separate HUGR, inserted in the
+    /// translated one later.
+    ///
+    /// Nested recorders only record the translation steps, which are
+    /// merged with [`TraceRecorder::absorb`].
+    nested: bool,
+    /// Number of events recorded up to the last checkpoint.
+    ///
+    /// The changes found at the next checkpoint are inserted at this
+    /// position.
+    synced: usize,
+    /// Parent and operation of the nodes at the last checkpoint.
+    nodes: BTreeMap<Node, (Option<Node>, OpType)>,
+    /// Links of the HUGR at the last checkpoint.
+    links: BTreeSet<Link>,
+}
+
+impl TraceRecorder {
+    /// A recorder for a region built in a separate HUGR.
+    pub fn nested(&self) -> Self {
+        Self {
+            nested: true,
+            ..Self::default()
+        }
+    }
+
+    /// Record a translation step.
+    pub fn record(&mut self, event: TraceEvent) {
+        self.trace.events.push(event);
+    }
+
+    /// Record the changes to the HUGR since the last checkpoint, followed by
+    /// a translation step.
+    ///
+    /// The changes are placed before the steps recorded since the last
+    /// checkpoint.
+    pub fn checkpoint(&mut self, hugr: &Hugr, event: TraceEvent) {
+        if !self.nested {
+            let changes = self.changes(hugr);
+            self.trace.events.splice(self.synced..self.synced, changes);
+        }
+        self.record(event);
+        self.synced = self.trace.len();
+    }
+
+    /// Append the steps recorded by the recorders of nested regions, once
+    /// their nodes have been inserted in the HUGR of this recorder.
+    pub fn absorb(&mut self, nested: TraceRecorder, node_map: &HashMap<Node, Node>) {
+        self.trace.events.extend(
+            nested
+                .trace
+                .events
+                .into_iter()
+                .filter_map(|event| event.map_nodes(node_map)),
+        );
+    }
+
+    /// Record the last changes to the translated HUGR and return the trace.
+    pub fn finish(mut self, hugr: &Hugr) -> ConversionTrace {
+        let changes = self.changes(hugr);
+        self.trace.events.splice(self.synced..self.synced, changes);
+        self.trace
+    }
+
+    /// The structural events between the last checkpoint and the current
+    /// state of `hugr`, updating the recorded state.
+    fn changes(&mut self, hugr: &Hugr) -> Vec<TraceEvent> {
+        let mut changes = Vec::new();
+        // Parents are visited before their children.
+        let current = hugr.descendants(hugr.module_root()).collect_vec();
+        for &node in &current {
+            let parent = hugr.get_parent(node);
+            let op = hugr.get_optype(node);
+            let Some((old_parent, old_op)) = self.nodes.get_mut(&node) else {
+                changes.push(TraceEvent::NodeAdded {
+                    node,
+                    parent,
+                    op: op.clone(),
+                });
+                self.nodes.insert(node, (parent, op.clone()));
+                continue;
+            };
+            if let Some(parent) = parent
+                && *old_parent != Some(parent)
+            {
+                changes.push(TraceEvent::NodeMoved { node, parent });
+                *old_parent = Some(parent);
+            }
+            if old_op != op {
+                changes.push(TraceEvent::OpReplaced {
+                    node,
+                    op: op.clone(),
+                });
+                *old_op = op.clone();
+            }
+        }
+
+        let links: BTreeSet<Link> = current
+            .iter()
+            .flat_map(|&node| {
+                hugr.node_outputs(node).flat_map(move |port| {
+                    hugr.linked_inputs(node, port)
+                        .map(move |(target, target_port)| {
+                            (node, port.index(), target, target_port.index())
+                        })
+                })
+            })
+            .collect();
+        changes.extend(self.links.difference(&links).map(
+            |&(source, source_port, target, target_port)| TraceEvent::EdgeRemoved {
+                source,
+                source_port,
+                target,
+                target_port,
+            },
+        ));
+
+        // Children are removed before their parents.
+        let current: BTreeSet<Node> = current.into_iter().collect();
+        let depth = |node: Node| {
+            iter::successors(Some(node), |n| self.nodes.get(n).and_then(|(p, _)| *p)).count()
+        };
+        let removed = self
+            .nodes
+            .keys()
+            .copied()
+            .filter(|node| !current.contains(node))
+            .sorted_by_key(|&node| Reverse(depth(node)))
+            .collect_vec();
+        for node in removed {
+            self.nodes.remove(&node);
+            changes.push(TraceEvent::NodeRemoved { node });
+        }
+
+        changes.extend(links.difference(&self.links).map(
+            |&(source, source_port, target, target_port)| TraceEvent::EdgeAdded {
+                source,
+                source_port,
+                target,
+                target_port,
+            },
+        ));
+        self.links = links;
+        changes
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{assert_hugr_equivalent, qubits};
+    use crate::{JeffToHugrConfig, jeff_to_hugr_with_config};
+    use jeff::Jeff;
+    use jeff::reader::Function;
+    use rstest::rstest;
+
+    /// The output of the first `CX` of the `qubits` program, read by the
+    /// second `CX`.
+    fn cx_output(jeff: &Jeff) -> ValueId {
+        let Some(Function::Definition(def)) = jeff.module().functions().next() else {
+            panic!("The program starts with a function definition");
+        };
+        let cx = def.body().operations().nth(3).unwrap();
+        cx.output(0).unwrap().unwrap().id()
+    }
+
+    #[rstest]
+    fn replay(qubits: Jeff<'static>) {
+        let config = JeffToHugrConfig::default().with_record_trace(true);
+        let converted = jeff_to_hugr_with_config(&qubits, &config).unwrap();
+        let trace = converted.trace.unwrap();
+        let again = jeff_to_hugr_with_config(&qubits, &config).unwrap();
+        assert_eq!(again.trace.unwrap().len(), trace.len());
+
+        let replayed = trace.hugr_at(trace.len() - 1);
+        replayed.validate().unwrap_or_else(|e| panic!("{e}"));
+        assert_hugr_equivalent(&replayed, &converted.hugr);
+        // The function body is translated after entering its region.
+        let entered = trace
+            .events()
+            .iter()
+            .position(|event| matches!(event, TraceEvent::RegionEntered { .. }))
+            .unwrap();
+        assert!(trace.hugr_at(entered).num_nodes() < converted.hugr.num_nodes());
+
+        let mut lines = Vec::new();
+        trace.write_json_lines(&mut lines).unwrap();
+        let lines = String::from_utf8(lines).unwrap();
+        assert_eq!(lines.lines().count(), trace.len());
+        let first: JsonValue = serde_json::from_str(lines.lines().next().unwrap()).unwrap();
+        assert_eq!(first["event"], "node_added");
+
+        let untraced = jeff_to_hugr_with_config(&qubits, &JeffToHugrConfig::default()).unwrap();
+        assert!(untraced.trace.is_none());
+    }
+
+    #[rstest]
+    fn value_events(qubits: Jeff<'static>) {
+        let config = JeffToHugrConfig::default().with_record_trace(true);
+        let trace = jeff_to_hugr_with_config(&qubits, &config)
+            .unwrap()
+            .trace
+            .unwrap();
+
+        let events = trace.find_events_for_value(cx_output(&qubits));
+        let steps = events.iter().map(|&(step, _)| step).collect_vec();
+        assert!(steps.is_sorted());
+        let ports = events
+            .iter()
+            .map(|(_, event)| match event {
+                TraceEvent::ValueRegistered { node, port, .. } => vec![(*node, *port)],
+                TraceEvent::HyperedgeConnected { source, target, .. } => {
+                    vec![(source.0, source.1.into()), (target.0, target.1.into())]
+                }
+                _ => panic!("Unexpected event {event:?}"),
+            })
+            .collect_vec();
+        // The output of the first `CX` and the input of the second one are
+        // registered, then connected.
+        assert_eq!(ports.len(), 3);
+        assert_eq!(ports[0][0].1.direction(), Direction::Outgoing);
+        assert_eq!(ports[1][0].1.direction(), Direction::Incoming);
+        assert_eq!(ports[2], [ports[0][0], ports[1][0]]);
+    }
+}
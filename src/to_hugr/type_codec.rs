@@ -0,0 +1,537 @@
+//! User-defined HUGR types for the values of _jeff_ function signatures.
+//!
+//! See [`TypeCodec`].
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::panic::RefUnwindSafe;
+use std::sync::Arc;
+
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::ops::{Call, OpType};
+use hugr::types::{Signature, Type as HugrType};
+use hugr::{Hugr, HugrView, IncomingPort, Node, OutgoingPort};
+use itertools::Itertools;
+use jeff::types::Type as JeffType;
+
+use crate::types::hugr_to_jeff;
+use crate::{HugrToJeffError, JeffToHugrError};
+
+/// The attributes attached to a _jeff_ value, as name-value pairs.
+pub type ValueAttributes = BTreeMap<String, String>;
+
+/// A user-defined HUGR type for some values of _jeff_ function signatures.
+///
+/// Codecs are registered in [`crate::JeffToHugrConfig::type_codecs`], and
+/// tried in order for every input and output of the translated functions.
+/// The first codec claiming a value sets its type in the function signature.
+/// Function bodies keep the built-in translation of the value: the claimed
+/// values are converted with [`TypeCodec::encode`] after the function
+/// inputs, and with [`TypeCodec::decode`] before the function outputs and
+/// around the calls.
+///
+/// # Example
+///
+/// A codec translating the `int(8)` arrays tagged with a `ty` attribute of
+/// `"pulse_schedule"` into a type from another extension.
+///
+/// ```
+/// use hugr_jeff::hugr::ops::OpType;
+/// use hugr_jeff::hugr::types::Type;
+/// use hugr_jeff::{JeffType, TypeCodec, ValueAttributes};
+///
+/// struct PulseCodec {
+///     schedule: Type,
+///     decode: OpType,
+///     encode: OpType,
+/// }
+///
+/// impl TypeCodec for PulseCodec {
+///     fn claim(&self, ty: JeffType, attributes: &ValueAttributes) -> Option<Type> {
+///         let tagged = attributes.get("ty").is_some_and(|ty| ty == "pulse_schedule");
+///         (ty == JeffType::IntArray { bits: 8 } && tagged).then(|| self.schedule.clone())
+///     }
+///
+///     fn decode(&self, _hugr_type: &Type) -> OpType {
+///         self.decode.clone()
+///     }
+///
+///     fn encode(&self, _hugr_type: &Type) -> OpType {
+///         self.encode.clone()
+///     }
+///
+///     fn jeff_type(&self, hugr_type: &Type) -> Option<(JeffType, ValueAttributes)> {
+///         let attributes = [("ty".to_string(), "pulse_schedule".to_string())].into();
+///         (hugr_type == &self.schedule).then_some((JeffType::IntArray { bits: 8 }, attributes))
+///     }
+/// }
+/// ```
+pub trait TypeCodec: Send + Sync + RefUnwindSafe {
+    /// Returns the HUGR type of a value of the _jeff_ type `ty` with the
+    /// given attributes, or `None` to fall back to the next codec and then
+    /// to [`crate::types::jeff_to_hugr`].
+    fn claim(&self, ty: JeffType, attributes: &ValueAttributes) -> Option<HugrType>;
+
+    /// An operation converting the built-in translation of a claimed value
+    /// into its `hugr_type`.
+    ///
+    /// The operation must have a single input and a single output.
+    fn decode(&self, hugr_type: &HugrType) -> OpType;
+
+    /// An operation converting a claimed value of type `hugr_type` back into
+    /// the built-in translation, the inverse of [`TypeCodec::decode`].
+    ///
+    /// The operation must have a single input and a single output.
+    fn encode(&self, hugr_type: &HugrType) -> OpType;
+
+    /// Returns the _jeff_ type and the attributes of the values of a HUGR
+    /// type claimed by the codec, or `None` for the other types.
+    fn jeff_type(&self, hugr_type: &HugrType) -> Option<(JeffType, ValueAttributes)>;
+
+    /// A name identifying the codec in debug output.
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+}
+
+/// The list of [`TypeCodec`]s in a [`crate::JeffToHugrConfig`].
+///
+/// Two lists are equal if they contain the same codec instances.
+#[derive(Clone, Default)]
+pub struct TypeCodecs(Vec<Arc<dyn TypeCodec>>);
+
+impl TypeCodecs {
+    /// Append a codec, tried after the already registered ones.
+    pub fn push(&mut self, codec: impl TypeCodec + 'static) {
+        self.0.push(Arc::new(codec));
+    }
+
+    /// Returns `true` if no codec is registered.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the registered codecs, in the order they are tried.
+    pub fn iter(&self) -> impl Iterator<Item = &dyn TypeCodec> + '_ {
+        self.0.iter().map(|codec| codec.as_ref())
+    }
+
+    /// Returns the first codec claiming a value, with the HUGR type it
+    /// assigns to it.
+    pub fn claim(
+        &self,
+        ty: JeffType,
+        attributes: &ValueAttributes,
+    ) -> Option<(&dyn TypeCodec, HugrType)> {
+        self.iter()
+            .find_map(|codec| Some((codec, codec.claim(ty, attributes)?)))
+    }
+
+    /// Translate a HUGR type to a _jeff_ type and its attributes.
+    ///
+    /// The types claimed by a codec are translated with
+    /// [`TypeCodec::jeff_type`], the others with
+    /// [`crate::types::hugr_to_jeff`] and no attributes.
+    ///
+    /// # Errors
+    ///
+    /// - [`HugrToJeffError::UnsupportedType`] if no codec claims the type
+    ///   and it is not supported by _jeff_.
+    pub fn hugr_to_jeff(
+        &self,
+        hugr_type: &HugrType,
+    ) -> Result<(JeffType, ValueAttributes), HugrToJeffError> {
+        match self.iter().find_map(|codec| codec.jeff_type(hugr_type)) {
+            Some(translated) => Ok(translated),
+            None => Ok((hugr_to_jeff(hugr_type)?, ValueAttributes::new())),
+        }
+    }
+}
+
+impl fmt::Debug for TypeCodecs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.iter().map(|codec| codec.name()))
+            .finish()
+    }
+}
+
+impl PartialEq for TypeCodecs {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.len() == other.0.len()
+            && self.0.iter().zip(&other.0).all(|(a, b)| Arc::ptr_eq(a, b))
+    }
+}
+
+/// The _jeff_ types and attributes of the inputs and outputs of a function.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(super) struct BoundaryTypes {
+    /// The types of the function inputs, in order.
+    pub inputs: Vec<(JeffType, ValueAttributes)>,
+    /// The types of the function outputs, in order.
+    pub outputs: Vec<(JeffType, ValueAttributes)>,
+}
+
+impl BoundaryTypes {
+    /// Read the boundary types of a _jeff_ function.
+    pub fn new(func: &jeff::reader::Function<'_>) -> Result<Self, JeffToHugrError> {
+        // TODO: The _jeff_ reader does not expose the metadata attached to
+        // values yet, so the codecs only see the value types.
+        let inputs = func
+            .input_types()
+            .map(|port| Ok((port?.ty(), ValueAttributes::new())))
+            .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+        let outputs = func
+            .output_types()
+            .map(|port| Ok((port?.ty(), ValueAttributes::new())))
+            .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+        Ok(Self { inputs, outputs })
+    }
+}
+
+/// The codecs claiming the inputs and outputs of a function.
+struct Claims<'a> {
+    inputs: Vec<Option<(&'a dyn TypeCodec, HugrType)>>,
+    outputs: Vec<Option<(&'a dyn TypeCodec, HugrType)>>,
+}
+
+/// Set the types claimed by `codecs` in the signatures of `functions`, and
+/// convert the claimed values at the function boundaries and around the
+/// calls to them.
+///
+/// Functions whose values are not claimed are left unchanged.
+pub(super) fn apply_type_codecs(
+    hugr: &mut Hugr,
+    codecs: &TypeCodecs,
+    functions: &[(Node, BoundaryTypes)],
+) -> Result<(), JeffToHugrError> {
+    if codecs.is_empty() {
+        return Ok(());
+    }
+    let claim = |types: &[(JeffType, ValueAttributes)]| {
+        types
+            .iter()
+            .map(|(ty, attributes)| codecs.claim(*ty, attributes))
+            .collect_vec()
+    };
+    let mut claimed = BTreeMap::new();
+    for (func, boundary) in functions {
+        let claims = Claims {
+            inputs: claim(&boundary.inputs),
+            outputs: claim(&boundary.outputs),
+        };
+        if claims.inputs.iter().chain(&claims.outputs).any(Option::is_some) {
+            claimed.insert(*func, claims);
+        }
+    }
+
+    for (&func, claims) in &claimed {
+        let signature = match hugr.get_optype(func) {
+            OpType::FuncDefn(defn) => defn.signature().body().clone(),
+            OpType::FuncDecl(decl) => decl.signature().body().clone(),
+            _ => continue,
+        };
+        let signature = claimed_signature(&signature, claims);
+        match hugr.optype_mut(func) {
+            OpType::FuncDefn(defn) => *defn.signature_mut() = signature.clone().into(),
+            OpType::FuncDecl(decl) => *decl.signature_mut() = signature.clone().into(),
+            _ => unreachable!(),
+        }
+
+        let Some([input, output]) = hugr.get_io(func) else {
+            continue;
+        };
+        if let OpType::Input(op) = hugr.optype_mut(input) {
+            op.types = signature.input().clone();
+        }
+        if let OpType::Output(op) = hugr.optype_mut(output) {
+            op.types = signature.output().clone();
+        }
+        for (port, (codec, ty)) in claimed_ports(&claims.inputs) {
+            convert_outgoing(hugr, input, port.into(), codec.encode(ty));
+        }
+        for (port, (codec, ty)) in claimed_ports(&claims.outputs) {
+            convert_incoming(hugr, output, port.into(), codec.decode(ty));
+        }
+    }
+
+    let targets: BTreeSet<Node> = claimed.keys().copied().collect();
+    let calls = hugr
+        .nodes()
+        .filter(|&node| hugr.get_optype(node).is_call())
+        .filter_map(|call| Some((call, hugr.static_source(call)?)))
+        .filter(|(_, target)| targets.contains(target))
+        .collect_vec();
+    for (call, target) in calls {
+        let claims = &claimed[&target];
+        let OpType::Call(op) = hugr.get_optype(call) else {
+            continue;
+        };
+        let signature = claimed_signature(&op.instantiation, claims);
+        *hugr.optype_mut(call) = Call::try_new(signature.into(), [])
+            .expect("monomorphic signature")
+            .into();
+        for (port, (codec, ty)) in claimed_ports(&claims.inputs) {
+            convert_incoming(hugr, call, port.into(), codec.decode(ty));
+        }
+        for (port, (codec, ty)) in claimed_ports(&claims.outputs) {
+            convert_outgoing(hugr, call, port.into(), codec.encode(ty));
+        }
+    }
+    Ok(())
+}
+
+/// Replace the claimed types in a function signature.
+///
+/// The ports without a claim keep their type.
+fn claimed_signature(signature: &Signature, claims: &Claims<'_>) -> Signature {
+    let replace = |types: &[HugrType], claims: &[Option<(&dyn TypeCodec, HugrType)>]| {
+        types
+            .iter()
+            .enumerate()
+            .map(|(port, ty)| match claims.get(port) {
+                Some(Some((_, claimed))) => claimed.clone(),
+                _ => ty.clone(),
+            })
+            .collect_vec()
+    };
+    Signature::new(
+        replace(signature.input_types(), &claims.inputs),
+        replace(signature.output_types(), &claims.outputs),
+    )
+}
+
+/// Returns the claimed ports among a function's inputs or outputs.
+fn claimed_ports<'a>(
+    claims: &'a [Option<(&'a dyn TypeCodec, HugrType)>],
+) -> impl Iterator<Item = (usize, (&'a dyn TypeCodec, &'a HugrType))> + 'a {
+    claims
+        .iter()
+        .enumerate()
+        .filter_map(|(port, claim)| Some((port, claim.as_ref().map(|(c, ty)| (*c, ty))?)))
+}
+
+/// Insert a conversion between an output port and all its targets.
+fn convert_outgoing(hugr: &mut Hugr, node: Node, port: OutgoingPort, op: OpType) {
+    let targets = hugr.linked_inputs(node, port).collect_vec();
+    if targets.is_empty() {
+        return;
+    }
+    hugr.disconnect(node, port);
+    let parent = hugr.get_parent(targets[0].0).unwrap();
+    let conversion = hugr.add_node_with_parent(parent, op);
+    hugr.connect(node, port, conversion, 0);
+    for (target, target_port) in targets {
+        hugr.connect(conversion, 0, target, target_port);
+    }
+}
+
+/// Insert a conversion between an input port and its source.
+fn convert_incoming(hugr: &mut Hugr, node: Node, port: IncomingPort, op: OpType) {
+    let Some((source, source_port)) = hugr.single_linked_output(node, port) else {
+        return;
+    };
+    hugr.disconnect(node, port);
+    let parent = hugr.get_parent(node).unwrap();
+    let conversion = hugr.add_node_with_parent(parent, op);
+    hugr.connect(source, source_port, conversion, 0);
+    hugr.connect(conversion, 0, node, port);
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Weak;
+
+    use hugr::Extension;
+    use hugr::builder::{Dataflow, DataflowSubContainer, HugrBuilder, ModuleBuilder};
+    use hugr::extension::prelude::qb_t;
+    use hugr::extension::{ExtensionId, Version};
+    use hugr::ops::ExtensionOp;
+    use hugr::ops::handle::NodeHandle;
+    use hugr::types::{CustomType, TypeBound};
+    use lazy_static::lazy_static;
+    use rstest::rstest;
+
+    use super::*;
+    use crate::extension::intreg_type;
+
+    const PULSE_EXTENSION_ID: ExtensionId = ExtensionId::new_unchecked("test.pulse");
+
+    lazy_static! {
+        /// A test extension with a pulse schedule type, encoded as an
+        /// `int(8)` array.
+        static ref PULSE_EXTENSION: Arc<Extension> = {
+            let version = Version::new(0, 1, 0);
+            Extension::new_arc(PULSE_EXTENSION_ID, version, |extension, extension_ref| {
+                extension
+                    .add_type(
+                        "schedule".into(),
+                        vec![],
+                        "pulse schedule".to_owned(),
+                        TypeBound::Copyable.into(),
+                        extension_ref,
+                    )
+                    .unwrap();
+                let schedule = schedule_type_in(extension_ref);
+                let decode = Signature::new(vec![intreg_type(8)], vec![schedule.clone()]);
+                let encode = Signature::new(vec![schedule], vec![intreg_type(8)]);
+                extension
+                    .add_op("decode".into(), "decode a schedule".to_owned(), decode, extension_ref)
+                    .unwrap();
+                extension
+                    .add_op("encode".into(), "encode a schedule".to_owned(), encode, extension_ref)
+                    .unwrap();
+            })
+        };
+    }
+
+    fn schedule_type_in(extension_ref: &Weak<Extension>) -> HugrType {
+        let bound = TypeBound::Copyable;
+        CustomType::new("schedule", [], PULSE_EXTENSION_ID, bound, extension_ref).into()
+    }
+
+    fn schedule_type() -> HugrType {
+        schedule_type_in(&Arc::downgrade(&PULSE_EXTENSION))
+    }
+
+    fn pulse_op(name: &str) -> OpType {
+        let def = PULSE_EXTENSION.get_op(name).unwrap();
+        ExtensionOp::new(def.clone(), []).unwrap().into()
+    }
+
+    fn tagged() -> ValueAttributes {
+        [("ty".to_string(), "pulse_schedule".to_string())].into()
+    }
+
+    /// Translates the `int(8)` arrays tagged as pulse schedules.
+    struct PulseCodec;
+
+    impl TypeCodec for PulseCodec {
+        fn claim(&self, ty: JeffType, attributes: &ValueAttributes) -> Option<HugrType> {
+            let tagged = attributes.get("ty").is_some_and(|ty| ty == "pulse_schedule");
+            (ty == JeffType::IntArray { bits: 8 } && tagged).then(schedule_type)
+        }
+
+        fn decode(&self, _hugr_type: &HugrType) -> OpType {
+            pulse_op("decode")
+        }
+
+        fn encode(&self, _hugr_type: &HugrType) -> OpType {
+            pulse_op("encode")
+        }
+
+        fn jeff_type(&self, hugr_type: &HugrType) -> Option<(JeffType, ValueAttributes)> {
+            (hugr_type == &schedule_type()).then(|| (JeffType::IntArray { bits: 8 }, tagged()))
+        }
+    }
+
+    fn codecs() -> TypeCodecs {
+        let mut codecs = TypeCodecs::default();
+        codecs.push(PulseCodec);
+        codecs
+    }
+
+    /// A module with a `play` function on a schedule and a qubit, and a
+    /// `main` function calling it.
+    ///
+    /// Returns the module and the function nodes with their boundary types,
+    /// where the schedule of `play` is tagged if `tag` is set.
+    fn pulse_module(tag: bool) -> (Hugr, Vec<(Node, BoundaryTypes)>) {
+        let sig = Signature::new_endo(vec![intreg_type(8), qb_t()]);
+        let mut builder = ModuleBuilder::new();
+        let play = builder.define_function("play", sig.clone()).unwrap();
+        let inputs = play.input_wires();
+        let play = play.finish_with_outputs(inputs).unwrap();
+
+        let mut main = builder.define_function("main", sig).unwrap();
+        let inputs = main.input_wires();
+        let call = main.call(play.handle(), &[], inputs).unwrap();
+        let main = main.finish_with_outputs(call.outputs()).unwrap();
+
+        let boundary = |attributes: ValueAttributes| {
+            let types = vec![
+                (JeffType::IntArray { bits: 8 }, attributes),
+                (JeffType::Qubit, ValueAttributes::new()),
+            ];
+            BoundaryTypes {
+                inputs: types.clone(),
+                outputs: types,
+            }
+        };
+        let play_attributes = match tag {
+            true => tagged(),
+            false => ValueAttributes::new(),
+        };
+        let functions = vec![
+            (play.node(), boundary(play_attributes)),
+            (main.node(), boundary(ValueAttributes::new())),
+        ];
+        (builder.finish_hugr().unwrap(), functions)
+    }
+
+    fn signature(hugr: &Hugr, func: Node) -> Signature {
+        let OpType::FuncDefn(defn) = hugr.get_optype(func) else {
+            panic!("{func} is not a definition");
+        };
+        defn.signature().body().clone()
+    }
+
+    fn count_pulse_ops(hugr: &Hugr, func: Node, name: &str) -> usize {
+        hugr.children(func)
+            .filter(|&node| match hugr.get_optype(node) {
+                OpType::ExtensionOp(op) => {
+                    op.def().extension_id() == &PULSE_EXTENSION_ID && op.def().name() == name
+                }
+                _ => false,
+            })
+            .count()
+    }
+
+    #[rstest]
+    fn tagged_signature() {
+        let (mut hugr, functions) = pulse_module(true);
+        let codecs = codecs();
+        apply_type_codecs(&mut hugr, &codecs, &functions).unwrap();
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        let [(play, _), (main, _)] = functions.try_into().unwrap();
+
+        // Only the tagged schedule is claimed.
+        let claimed = Signature::new_endo(vec![schedule_type(), qb_t()]);
+        assert_eq!(signature(&hugr, play), claimed);
+        let unclaimed = Signature::new_endo(vec![intreg_type(8), qb_t()]);
+        assert_eq!(signature(&hugr, main), unclaimed);
+
+        // The body of `play` works on the array, and the caller converts it
+        // around the call.
+        for func in [play, main] {
+            assert_eq!(count_pulse_ops(&hugr, func, "decode"), 1);
+            assert_eq!(count_pulse_ops(&hugr, func, "encode"), 1);
+        }
+
+        // The exporter recovers the tagged array.
+        let play_signature = signature(&hugr, play);
+        let (inputs, outputs) = play_signature.io();
+        for ty in inputs.iter().chain(outputs.iter()) {
+            let (jeff_type, attributes) = codecs.hugr_to_jeff(ty).unwrap();
+            if jeff_type == (JeffType::IntArray { bits: 8 }) {
+                assert_eq!(attributes, tagged());
+            } else {
+                assert_eq!((jeff_type, attributes), (JeffType::Qubit, ValueAttributes::new()));
+            }
+        }
+    }
+
+    #[rstest]
+    #[case::untagged(true, false)]
+    #[case::no_codecs(false, true)]
+    fn unclaimed(#[case] with_codecs: bool, #[case] tag: bool) {
+        let (mut hugr, functions) = pulse_module(tag);
+        let codecs = match with_codecs {
+            true => codecs(),
+            false => TypeCodecs::default(),
+        };
+        let nodes = hugr.num_nodes();
+        apply_type_codecs(&mut hugr, &codecs, &functions).unwrap();
+        assert_eq!(hugr.num_nodes(), nodes);
+    }
+}
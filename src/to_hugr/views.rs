@@ -0,0 +1,320 @@
+//! Register views, aliasing a slice of a qubit register without moving it.
+//!
+//! Some producers declare two intrinsic functions:
+//!
+//! - `qureg_view(parent: qureg, start: int(32), end: int(32)) -> qureg`
+//!   returns a view of the qubits `start..end` of `parent`. Gates applied to
+//!   the view act on the parent register.
+//! - `qureg_release_view(parent: qureg, view: qureg)` ends the view.
+//!
+//! The parent value stays valid after the `qureg_view` call, which breaks
+//! the linearity of the HUGR registers. The calls are translated with move
+//! semantics instead: the view is a `QuregExtractSlice` of the parent, and the
+//! release a `QuregInsertSlice` of the view at its original offset, whose
+//! result replaces the parent for all the later uses.
+//!
+//! This is only equivalent when the parent is not used while the view is
+//! live, the translation fails with [`JeffToHugrError::AliasedRegisterUse`]
+//! otherwise. Views must be released in the region they are created in.
+
+use std::collections::BTreeMap;
+
+use hugr::builder::{Container, Dataflow};
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::{IncomingPort, OutgoingPort, Wire};
+use jeff::types::Type as JeffType;
+
+use super::BuildContext;
+use crate::JeffToHugrError;
+use crate::extension::JeffOp;
+
+/// A declared _jeff_ function creating or releasing a register view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ViewIntrinsic {
+    /// `qureg_view(parent, start, end) -> view`.
+    View,
+    /// `qureg_release_view(parent, view)`.
+    Release,
+}
+
+impl ViewIntrinsic {
+    /// Returns the view intrinsic of a declared function, given its name,
+    /// input types and output types.
+    pub(crate) fn from_declaration(
+        name: &str,
+        inputs: &[JeffType],
+        outputs: &[JeffType],
+    ) -> Option<Self> {
+        let int32_t = JeffType::Int { bits: 32 };
+        match (name, inputs, outputs) {
+            (
+                "qureg_view",
+                [JeffType::QubitRegister, start, end],
+                [JeffType::QubitRegister],
+            ) if *start == int32_t && *end == int32_t => Some(Self::View),
+            ("qureg_release_view", [JeffType::QubitRegister, JeffType::QubitRegister], []) => {
+                Some(Self::Release)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The live register views of the region being translated, by parent value.
+#[derive(Debug, Clone)]
+pub(crate) struct RegisterViews<V> {
+    live: BTreeMap<V, LiveView<V>>,
+}
+
+/// A register view created and not released yet.
+#[derive(Debug, Clone, Copy)]
+struct LiveView<V> {
+    /// The rest of the parent register, to re-insert the view into.
+    rest: Wire,
+    /// The value of the view offset in the parent.
+    start: V,
+    /// The position of the `qureg_view` call.
+    op_index: usize,
+    /// The position of the first operation using the parent while the view
+    /// is live.
+    aliased_use: Option<usize>,
+}
+
+impl<V> Default for RegisterViews<V> {
+    fn default() -> Self {
+        Self {
+            live: BTreeMap::new(),
+        }
+    }
+}
+
+impl<V: Ord + Copy> RegisterViews<V> {
+    /// Track a new view of `parent`.
+    ///
+    /// # Errors
+    ///
+    /// - [`JeffToHugrError::AliasedRegisterUse`] if `parent` already has a
+    ///   live view.
+    fn open(
+        &mut self,
+        parent: V,
+        start: V,
+        rest: Wire,
+        op_index: usize,
+    ) -> Result<(), JeffToHugrError> {
+        if self.live.contains_key(&parent) {
+            return Err(JeffToHugrError::AliasedRegisterUse { op_index });
+        }
+        let view = LiveView {
+            rest,
+            start,
+            op_index,
+            aliased_use: None,
+        };
+        self.live.insert(parent, view);
+        Ok(())
+    }
+
+    /// Record a use of a value by an operation.
+    ///
+    /// Does nothing unless the value is the parent of a live view.
+    pub(crate) fn record_use(&mut self, value: V, op_index: usize) {
+        if let Some(view) = self.live.get_mut(&value) {
+            view.aliased_use.get_or_insert(op_index);
+        }
+    }
+
+    /// Stop tracking the view of `parent`, returning the rest of the parent
+    /// and the view offset.
+    ///
+    /// # Errors
+    ///
+    /// - [`JeffToHugrError::UnmatchedRegisterView`] if `parent` has no live
+    ///   view.
+    /// - [`JeffToHugrError::AliasedRegisterUse`] if `parent` was used while
+    ///   the view was live.
+    fn release(&mut self, parent: V, op_index: usize) -> Result<(Wire, V), JeffToHugrError> {
+        let Some(view) = self.live.remove(&parent) else {
+            return Err(JeffToHugrError::UnmatchedRegisterView { op_index });
+        };
+        match view.aliased_use {
+            Some(op_index) => Err(JeffToHugrError::AliasedRegisterUse { op_index }),
+            None => Ok((view.rest, view.start)),
+        }
+    }
+
+    /// Check that all the views of the region have been released.
+    ///
+    /// # Errors
+    ///
+    /// - [`JeffToHugrError::UnmatchedRegisterView`] if a view is still live.
+    pub(crate) fn finish(&mut self) -> Result<(), JeffToHugrError> {
+        let unreleased = self.live.values().map(|view| view.op_index).min();
+        self.live.clear();
+        match unreleased {
+            Some(op_index) => Err(JeffToHugrError::UnmatchedRegisterView { op_index }),
+            None => Ok(()),
+        }
+    }
+}
+
+impl BuildContext {
+    /// Translate a call to a [`ViewIntrinsic`].
+    ///
+    /// # Errors
+    ///
+    /// - [`JeffToHugrError::AliasedRegisterUse`] if the parent register is
+    ///   used while a view of it is live.
+    /// - [`JeffToHugrError::UnmatchedRegisterView`] if a release does not
+    ///   match a live view.
+    pub(crate) fn build_view_intrinsic(
+        &mut self,
+        intrinsic: ViewIntrinsic,
+        op: &jeff::reader::Operation<'_>,
+        builder: &mut impl Dataflow,
+    ) -> Result<(), JeffToHugrError> {
+        let op_index = self.current_op.unwrap_or_default();
+        let inputs = op
+            .inputs()
+            .map(|value| Ok(value?.id()))
+            .collect::<Result<Vec<_>, JeffToHugrError>>()?;
+        match (intrinsic, &inputs[..]) {
+            (ViewIntrinsic::View, &[parent, start, end]) => {
+                let parent = self.earliest_id(parent);
+                let extract = JeffOp::QuregExtractSlice.try_into_extension_op()?;
+                let node = builder.add_child_node(extract);
+                self.record_origin(node);
+                let rest = Wire::new(node, 0);
+                self.register_views.open(parent, start, rest, op_index)?;
+
+                // Uses of the parent registered so far must come after the
+                // release, so only the region outputs are allowed.
+                let [_, out_node] = builder.io();
+                let mut uses = self.input_edges.get(&parent).into_iter().flatten();
+                if uses.any(|&(user, _)| user != out_node) {
+                    return Err(JeffToHugrError::AliasedRegisterUse { op_index });
+                }
+                // The parent value is produced again by the release.
+                for (src, src_port) in self.output_edges.remove(&parent).unwrap_or_default() {
+                    builder.hugr_mut().connect(src, src_port, node, 0);
+                }
+                self.register_input(start, node, IncomingPort::from(1));
+                self.register_input(end, node, IncomingPort::from(2));
+                let view = op.output(0).unwrap()?.id();
+                self.register_output(view, node, OutgoingPort::from(1));
+            }
+            (ViewIntrinsic::Release, &[parent, view]) => {
+                let parent = self.earliest_id(parent);
+                let (rest, start) = self.register_views.release(parent, op_index)?;
+                let insert = JeffOp::QuregInsertSlice.try_into_extension_op()?;
+                let node = builder.add_child_node(insert);
+                self.record_origin(node);
+                builder.hugr_mut().connect(rest.node(), rest.source(), node, 0);
+                self.register_input(view, node, IncomingPort::from(1));
+                self.register_input(start, node, IncomingPort::from(2));
+                self.register_output(parent, node, OutgoingPort::from(0));
+            }
+            (ViewIntrinsic::View, _) => {
+                return Err(JeffToHugrError::invalid_op_io("qureg_view", op));
+            }
+            (ViewIntrinsic::Release, _) => {
+                return Err(JeffToHugrError::invalid_op_io("qureg_release_view", op));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hugr::{Hugr, HugrView};
+    use rstest::rstest;
+
+    const PARENT: u32 = 0;
+    const START: u32 = 1;
+
+    fn rest() -> Wire {
+        Wire::new(Hugr::default().module_root(), 0)
+    }
+
+    #[rstest]
+    fn view_pattern() {
+        let mut views = RegisterViews::default();
+        views.open(PARENT, START, rest(), 3).unwrap();
+        // Gates on the view and other registers.
+        views.record_use(5, 4);
+        views.record_use(START, 5);
+        assert_eq!(views.release(PARENT, 6).unwrap(), (rest(), START));
+        // The parent can be used and viewed again after the release.
+        views.record_use(PARENT, 7);
+        views.open(PARENT, START, rest(), 8).unwrap();
+        views.release(PARENT, 9).unwrap();
+        views.finish().unwrap();
+    }
+
+    #[rstest]
+    fn interleaved_parent_use() {
+        let mut views = RegisterViews::default();
+        views.open(PARENT, START, rest(), 3).unwrap();
+        views.record_use(PARENT, 4);
+        views.record_use(PARENT, 5);
+        assert!(matches!(
+            views.release(PARENT, 6),
+            Err(JeffToHugrError::AliasedRegisterUse { op_index: 4 })
+        ));
+    }
+
+    #[rstest]
+    fn nested_view_of_parent() {
+        let mut views = RegisterViews::default();
+        views.open(PARENT, START, rest(), 3).unwrap();
+        assert!(matches!(
+            views.open(PARENT, START, rest(), 4),
+            Err(JeffToHugrError::AliasedRegisterUse { op_index: 4 })
+        ));
+    }
+
+    #[rstest]
+    fn unmatched_views() {
+        let mut views = RegisterViews::default();
+        assert!(matches!(
+            views.release(PARENT, 2),
+            Err(JeffToHugrError::UnmatchedRegisterView { op_index: 2 })
+        ));
+        views.open(PARENT, START, rest(), 3).unwrap();
+        assert!(matches!(
+            views.finish(),
+            Err(JeffToHugrError::UnmatchedRegisterView { op_index: 3 })
+        ));
+        views.finish().unwrap();
+    }
+
+    const INT32: JeffType = JeffType::Int { bits: 32 };
+    const QUREG: JeffType = JeffType::QubitRegister;
+
+    #[rstest]
+    #[case::view("qureg_view", vec![QUREG, INT32, INT32], vec![QUREG], Some(ViewIntrinsic::View))]
+    #[case::release(
+        "qureg_release_view",
+        vec![QUREG, QUREG],
+        vec![],
+        Some(ViewIntrinsic::Release)
+    )]
+    #[case::int64_bounds(
+        "qureg_view",
+        vec![QUREG, JeffType::Int { bits: 64 }, INT32],
+        vec![QUREG],
+        None
+    )]
+    #[case::returned_parent("qureg_release_view", vec![QUREG, QUREG], vec![QUREG], None)]
+    #[case::other_name("qureg_slice", vec![QUREG, INT32, INT32], vec![QUREG], None)]
+    fn view_intrinsics(
+        #[case] name: &str,
+        #[case] inputs: Vec<JeffType>,
+        #[case] outputs: Vec<JeffType>,
+        #[case] expected: Option<ViewIntrinsic>,
+    ) {
+        assert_eq!(ViewIntrinsic::from_declaration(name, &inputs, &outputs), expected);
+    }
+}
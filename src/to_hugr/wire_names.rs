@@ -0,0 +1,396 @@
+//! Stable names for the quantum wires of a translated HUGR.
+//!
+//! See [`WireNames`].
+
+use std::collections::BTreeMap;
+
+use hugr::extension::prelude::qb_t;
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::ops::{OpTrait, OpType};
+use hugr::types::Type as HugrType;
+use hugr::{HugrView, IncomingPort, Node, OutgoingPort};
+use itertools::Itertools;
+
+use crate::extension::register_semantics::constant_input;
+use crate::extension::{JeffOp, qureg_type};
+use crate::metadata;
+use crate::optype::tket_ops::QuantumOp;
+
+/// Names of the qubit, register and measurement wires of a HUGR, by the
+/// output port producing them.
+///
+/// In each function,
+/// - the allocated qubits are named `q0`, `q1`, ... in allocation order,
+/// - the allocated or created registers are named `r0`, `r1`, ...,
+/// - the qubits extracted from a named register at a constant index are
+///   named after the register and the index, as in `r0[3]`,
+/// - the measurement results, of single qubits or whole registers, are named
+///   `m0`, `m1`, ... in program order.
+///
+/// A qubit or register threaded through an operation keeps its name, also
+/// across the boundaries of the control flow regions. The function inputs
+/// are not named.
+///
+/// The names follow the order of the operations in the hierarchy, not the
+/// node indices, so they are the same for every conversion of a program and
+/// are not changed by operations that do not allocate or measure.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WireNames {
+    names: BTreeMap<(Node, OutgoingPort), String>,
+}
+
+impl WireNames {
+    /// Name the wires of the given functions.
+    ///
+    /// The counters of each kind of name restart in every function.
+    pub fn assign(
+        hugr: &impl HugrView<Node = Node>,
+        functions: impl IntoIterator<Item = Node>,
+    ) -> Self {
+        let mut names = Self::default();
+        for func in functions {
+            let mut order = Vec::new();
+            preorder(hugr, func, &mut order);
+            names.name_sources(hugr, &order);
+            while names.propagate(hugr, &order) {}
+        }
+        names
+    }
+
+    /// Read the names stored in the [`metadata::WIRE_NAMES`] entries of a
+    /// HUGR.
+    pub fn from_metadata(hugr: &impl HugrView<Node = Node>) -> Self {
+        let mut names = Self::default();
+        for node in hugr.nodes() {
+            let Some(ports) = hugr
+                .get_metadata(node, metadata::WIRE_NAMES)
+                .and_then(|entry| entry.as_array())
+            else {
+                continue;
+            };
+            for (port, name) in ports.iter().enumerate() {
+                if let Some(name) = name.as_str() {
+                    names.names.insert((node, port.into()), name.to_string());
+                }
+            }
+        }
+        names
+    }
+
+    /// Store the names in the [`metadata::WIRE_NAMES`] entries of the nodes
+    /// producing the wires.
+    pub fn store(&self, hugr: &mut impl HugrMut<Node = Node>) {
+        let by_node = self.names.iter().chunk_by(|((node, _), _)| *node);
+        for (node, ports) in &by_node {
+            let ports = ports.collect_vec();
+            let ((_, last), _) = ports.last().unwrap();
+            let mut entry = vec![serde_json::Value::Null; last.index() + 1];
+            for ((_, port), name) in ports {
+                entry[port.index()] = name.as_str().into();
+            }
+            hugr.set_metadata(node, metadata::WIRE_NAMES, entry);
+        }
+    }
+
+    /// Returns the name of the wire produced by an output port.
+    pub fn get(&self, node: Node, port: impl Into<OutgoingPort>) -> Option<&str> {
+        self.names.get(&(node, port.into())).map(String::as_str)
+    }
+
+    /// Returns an iterator over the named output ports and their names.
+    pub fn iter(&self) -> impl Iterator<Item = (Node, OutgoingPort, &str)> + '_ {
+        self.names
+            .iter()
+            .map(|(&(node, port), name)| (node, port, name.as_str()))
+    }
+
+    /// Returns the number of named wires.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Returns `true` if no wire is named.
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Give fresh names to the allocated qubits and registers, and to the
+    /// measurement results.
+    fn name_sources(&mut self, hugr: &impl HugrView<Node = Node>, order: &[Node]) {
+        let (mut qubits, mut registers, mut measurements) = (0, 0, 0);
+        for &node in order {
+            let optype = hugr.get_optype(node);
+            let (port, name) = match (QuantumOp::of(optype), optype.cast::<JeffOp>()) {
+                (Some(QuantumOp::QAlloc), _) => (0, numbered("q", &mut qubits)),
+                (Some(QuantumOp::Measure), _) => (1, numbered("m", &mut measurements)),
+                (Some(QuantumOp::MeasureDestructive), _) => (0, numbered("m", &mut measurements)),
+                (_, Some(JeffOp::QuregAlloc | JeffOp::QuregCreate { .. })) => {
+                    (0, numbered("r", &mut registers))
+                }
+                (_, Some(JeffOp::QuregMeasure)) => (0, numbered("m", &mut measurements)),
+                (_, Some(JeffOp::QuregMeasureNd)) => (1, numbered("m", &mut measurements)),
+                _ => continue,
+            };
+            self.names.insert((node, port.into()), name);
+        }
+    }
+
+    /// Name the qubits and registers continuing a named wire.
+    ///
+    /// Returns `true` if any wire was named.
+    fn propagate(&mut self, hugr: &impl HugrView<Node = Node>, order: &[Node]) -> bool {
+        let mut changed = false;
+        for &node in order {
+            let Some(signature) = hugr.signature(node) else {
+                continue;
+            };
+            for (port, ty) in signature.output_types().iter().enumerate() {
+                let port = OutgoingPort::from(port);
+                if !is_quantum(ty) || self.names.contains_key(&(node, port)) {
+                    continue;
+                }
+                let extracted = port.index() == 1
+                    && matches!(
+                        hugr.get_optype(node).cast::<JeffOp>(),
+                        Some(JeffOp::QuregExtractIndex)
+                    );
+                let name = match extracted {
+                    true => self.register_element(hugr, node),
+                    false => threaded_from(hugr, node, port)
+                        .into_iter()
+                        .find_map(|(src, src_port)| self.incoming(hugr, src, src_port))
+                        .map(str::to_string),
+                };
+                if let Some(name) = name {
+                    self.names.insert((node, port), name);
+                    changed = true;
+                }
+            }
+        }
+        changed
+    }
+
+    /// Returns the name of the wire connected to an input port.
+    fn incoming(
+        &self,
+        hugr: &impl HugrView<Node = Node>,
+        node: Node,
+        port: IncomingPort,
+    ) -> Option<&str> {
+        let (src, src_port) = hugr.single_linked_output(node, port)?;
+        self.get(src, src_port)
+    }
+
+    /// Returns the name of the qubit extracted by a
+    /// [`JeffOp::QuregExtractIndex`], if the register is named and the index
+    /// is constant.
+    fn register_element(&self, hugr: &impl HugrView<Node = Node>, node: Node) -> Option<String> {
+        let register = self.incoming(hugr, node, 0.into())?;
+        let index = constant_input(hugr, node, 1)?;
+        Some(format!("{register}[{index}]"))
+    }
+}
+
+/// Returns a name with the next number of a counter.
+fn numbered(prefix: &str, counter: &mut usize) -> String {
+    let name = format!("{prefix}{counter}");
+    *counter += 1;
+    name
+}
+
+/// Returns `true` for the qubit and register types.
+fn is_quantum(ty: &HugrType) -> bool {
+    *ty == qb_t() || *ty == qureg_type()
+}
+
+/// Collect the descendants of a node, parents before their children and
+/// siblings in order.
+fn preorder(hugr: &impl HugrView<Node = Node>, node: Node, order: &mut Vec<Node>) {
+    order.push(node);
+    for child in hugr.children(node) {
+        preorder(hugr, child, order);
+    }
+}
+
+/// The input ports carrying the wire an output port continues, in order of
+/// preference.
+///
+/// Operations continue the wire at the same input port, if it has the same
+/// type. The inputs of the control flow regions continue the inputs of their
+/// container, and the outputs of the containers continue the outputs of
+/// their regions.
+fn threaded_from(
+    hugr: &impl HugrView<Node = Node>,
+    node: Node,
+    port: OutgoingPort,
+) -> Vec<(Node, IncomingPort)> {
+    let index = port.index();
+    let region_output = |region: Node, index: usize| {
+        let [_, output] = hugr.get_io(region)?;
+        Some((output, IncomingPort::from(index)))
+    };
+    match hugr.get_optype(node) {
+        OpType::Input(_) => {
+            let Some(parent) = hugr.get_parent(node) else {
+                return vec![];
+            };
+            match hugr.get_optype(parent) {
+                OpType::DFG(_) | OpType::TailLoop(_) => vec![(parent, index.into())],
+                OpType::Case(_) => {
+                    let Some(conditional) = hugr.get_parent(parent) else {
+                        return vec![];
+                    };
+                    let OpType::Conditional(cond) = hugr.get_optype(conditional) else {
+                        return vec![];
+                    };
+                    let case = hugr.children(conditional).position(|n| n == parent);
+                    // The variant values come first, then the other inputs of the
+                    // conditional, which follow its selector.
+                    case.and_then(|case| index.checked_sub(cond.sum_rows[case].len()))
+                        .map(|other| (conditional, IncomingPort::from(other + 1)))
+                        .into_iter()
+                        .collect()
+                }
+                _ => vec![],
+            }
+        }
+        OpType::DFG(_) => region_output(node, index).into_iter().collect(),
+        OpType::Conditional(_) => hugr
+            .children(node)
+            .filter_map(|case| region_output(case, index))
+            .collect(),
+        OpType::TailLoop(tail_loop) => {
+            // The loop outputs its final state, which follows the control
+            // value at the output of the body.
+            let rest = index.checked_sub(tail_loop.just_outputs.len());
+            rest.and_then(|rest| region_output(node, rest + 1))
+                .into_iter()
+                .collect()
+        }
+        OpType::Output(_) | OpType::CFG(_) | OpType::Case(_) => vec![],
+        optype => {
+            let Some(signature) = optype.dataflow_signature() else {
+                return vec![];
+            };
+            let same_type = signature.in_port_type(index).is_some()
+                && signature.in_port_type(index) == signature.out_port_type(index);
+            match same_type {
+                true => vec![(node, index.into())],
+                false => vec![],
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::entangled_qs;
+    use crate::{JeffToHugrConfig, jeff_to_hugr_with_config};
+    use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr};
+    use hugr::extension::prelude::bool_t;
+    use hugr::std_extensions::arithmetic::int_ops::IntOpDef;
+    use hugr::std_extensions::arithmetic::int_types::ConstInt;
+    use hugr::types::Signature;
+    use hugr::{Hugr, Wire};
+    use jeff::Jeff;
+    use rstest::rstest;
+
+    fn add_op(
+        builder: &mut impl Dataflow,
+        op: impl Into<OpType>,
+        inputs: impl IntoIterator<Item = Wire>,
+    ) -> Vec<Wire> {
+        builder
+            .add_dataflow_op(op, inputs)
+            .unwrap()
+            .outputs()
+            .collect()
+    }
+
+    /// Entangle two allocated qubits and measure the first, then extract a
+    /// qubit from a register.
+    ///
+    /// With `extra`, an unrelated integer addition is inserted between the
+    /// two allocations.
+    fn circuit(extra: bool) -> Hugr {
+        let signature = Signature::new(vec![], vec![bool_t(), qb_t(), qureg_type(), qb_t()]);
+        let mut builder = DFGBuilder::new(signature).unwrap();
+        let a = add_op(&mut builder, QuantumOp::QAlloc, [])[0];
+        if extra {
+            let one = builder.add_load_value(ConstInt::new_u(5, 1).unwrap());
+            add_op(&mut builder, IntOpDef::iadd.with_log_width(5), [one, one]);
+        }
+        let b = add_op(&mut builder, QuantumOp::QAlloc, [])[0];
+        let [a, b] = add_op(&mut builder, QuantumOp::CX, [a, b])[..] else {
+            unreachable!()
+        };
+        let [_, m] = add_op(&mut builder, QuantumOp::Measure, [a])[..] else {
+            unreachable!()
+        };
+        let size = builder.add_load_value(ConstInt::new_u(5, 4).unwrap());
+        let reg = add_op(&mut builder, JeffOp::QuregAlloc.into_extension_op(), [size])[0];
+        let index = builder.add_load_value(ConstInt::new_u(5, 3).unwrap());
+        let extract = JeffOp::QuregExtractIndex.into_extension_op();
+        let [reg, e] = add_op(&mut builder, extract, [reg, index])[..] else {
+            unreachable!()
+        };
+        let e = add_op(&mut builder, QuantumOp::H, [e])[0];
+        builder.finish_hugr_with_outputs([m, b, reg, e]).unwrap()
+    }
+
+    /// The names of a HUGR, by operation name and port instead of node.
+    fn names_by_op(hugr: &Hugr, names: &WireNames) -> Vec<(String, usize, String)> {
+        names
+            .iter()
+            .map(|(node, port, name)| {
+                let op = format!("{:?}", hugr.get_optype(node));
+                (op, port.index(), name.to_string())
+            })
+            .sorted()
+            .collect()
+    }
+
+    #[rstest]
+    fn name_circuit() {
+        let hugr = circuit(false);
+        let names = WireNames::assign(&hugr, [hugr.entrypoint()]);
+        let [_, output] = hugr.get_io(hugr.entrypoint()).unwrap();
+        let output_name = |port: usize| names.incoming(&hugr, output, port.into());
+
+        assert_eq!(output_name(0), Some("m0"));
+        assert_eq!(output_name(1), Some("q1"));
+        assert_eq!(output_name(2), Some("r0"));
+        assert_eq!(output_name(3), Some("r0[3]"));
+        assert_eq!(names.len(), 10);
+    }
+
+    #[rstest]
+    fn names_are_local() {
+        let (plain, extra) = (circuit(false), circuit(true));
+        let plain_names = WireNames::assign(&plain, [plain.entrypoint()]);
+        let extra_names = WireNames::assign(&extra, [extra.entrypoint()]);
+
+        assert_ne!(plain.num_nodes(), extra.num_nodes());
+        assert_eq!(
+            names_by_op(&plain, &plain_names),
+            names_by_op(&extra, &extra_names)
+        );
+    }
+
+    #[rstest]
+    fn names_are_deterministic(entangled_qs: Jeff<'static>) {
+        let config = JeffToHugrConfig::default().with_name_wires(true);
+        let first = jeff_to_hugr_with_config(&entangled_qs, &config).unwrap();
+        let second = jeff_to_hugr_with_config(&entangled_qs, &config).unwrap();
+
+        let names = first.map.wire_names();
+        assert!(names.iter().any(|(_, _, name)| name == "q4"));
+        assert!(names.iter().any(|(_, _, name)| name == "m4"));
+        assert_eq!(names, second.map.wire_names());
+        assert_eq!(&WireNames::from_metadata(&first.hugr), names);
+
+        let unnamed = jeff_to_hugr_with_config(&entangled_qs, &JeffToHugrConfig::default());
+        assert!(unnamed.unwrap().map.wire_names().is_empty());
+    }
+}
@@ -1,6 +1,17 @@
 //! HUGR to _jeff_ Translation
 
+mod measure;
+mod protect;
+
+use std::collections::BTreeMap;
+
 use derive_more::{Display, Error, From};
+use hugr::ops::OpType;
+use hugr::{HugrView, Node};
+use jeff::reader::FunctionId;
+
+pub use measure::{JeffMeasure, fuse_destructive_measures};
+pub use protect::protected_ops;
 
 /// Error type for the HUGR to _jeff_ translation.
 #[derive(Debug, Display, From, Error)]
@@ -12,4 +23,199 @@ pub enum HugrToJeffError {
         /// The HUGR type that cannot be converted.
         hugr_type: String,
     },
+    /// A call's static input is not connected to a module-level function.
+    #[display("Call node {node} is not connected to a module-level function")]
+    #[from(ignore)]
+    UnresolvedCall {
+        /// The call node.
+        node: Node,
+    },
+}
+
+/// Assignment of _jeff_ function ids to the functions in a HUGR module.
+///
+/// Function definitions and declarations share the same id space. Ids are
+/// assigned in the order the functions appear as children of the module root,
+/// so that a HUGR imported from _jeff_ keeps its original function ids.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FunctionTable {
+    /// The function nodes, indexed by their _jeff_ id.
+    nodes: Vec<Node>,
+    /// Reverse map from function nodes to their _jeff_ id.
+    ids: BTreeMap<Node, FunctionId>,
+}
+
+impl FunctionTable {
+    /// Enumerate the `FuncDefn` and `FuncDecl` children of the module root.
+    pub fn new(hugr: &impl HugrView<Node = Node>) -> Self {
+        let mut table = Self::default();
+        for node in hugr.children(hugr.module_root()) {
+            if matches!(
+                hugr.get_optype(node),
+                OpType::FuncDefn(_) | OpType::FuncDecl(_)
+            ) {
+                table.ids.insert(node, table.nodes.len() as FunctionId);
+                table.nodes.push(node);
+            }
+        }
+        table
+    }
+
+    /// Returns the _jeff_ id assigned to a function node.
+    pub fn id(&self, node: Node) -> Option<FunctionId> {
+        self.ids.get(&node).copied()
+    }
+
+    /// Returns the function node assigned to a _jeff_ id.
+    pub fn node(&self, id: FunctionId) -> Option<Node> {
+        self.nodes.get(id as usize).copied()
+    }
+
+    /// Returns the function nodes, in _jeff_ id order.
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    /// Returns the number of functions in the table.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns `true` if the module contains no functions.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Resolve the _jeff_ id of the function targeted by a call node.
+    ///
+    /// Follows the call's static input edge back to the function node.
+    ///
+    /// # Errors
+    ///
+    /// - [`HugrToJeffError::UnresolvedCall`] if the static input is not
+    ///   connected to a module-level function.
+    pub fn call_target(
+        &self,
+        hugr: &impl HugrView<Node = Node>,
+        call: Node,
+    ) -> Result<FunctionId, HugrToJeffError> {
+        hugr.static_source(call)
+            .and_then(|func| self.id(func))
+            .ok_or(HugrToJeffError::UnresolvedCall { node: call })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::jeff_to_hugr;
+    use crate::test::{catalyst_simple, entangled_calls};
+    use hugr::builder::{Container, Dataflow, DataflowSubContainer, HugrBuilder, ModuleBuilder};
+    use hugr::extension::prelude::qb_t;
+    use hugr::hugr::hugrmut::HugrMut;
+    use hugr::ops::handle::{FuncID, NodeHandle};
+    use hugr::types::Signature;
+    use itertools::Itertools;
+    use jeff::Jeff;
+    use jeff::reader::optype::OpType as JeffOpType;
+    use rstest::rstest;
+
+    #[rstest]
+    fn imported_function_ids(catalyst_simple: Jeff<'static>) {
+        let hugr = jeff_to_hugr(&catalyst_simple).unwrap();
+        let table = FunctionTable::new(&hugr);
+
+        let names = table
+            .nodes()
+            .iter()
+            .map(|&n| match hugr.get_optype(n) {
+                OpType::FuncDefn(defn) => defn.func_name().clone(),
+                OpType::FuncDecl(decl) => decl.func_name().clone(),
+                _ => unreachable!(),
+            })
+            .collect_vec();
+        let expected = catalyst_simple
+            .module()
+            .functions()
+            .map(|f| f.name().to_string())
+            .collect_vec();
+        assert_eq!(names, expected);
+    }
+
+    /// Calls to declarations, recursive calls, and calls to functions defined
+    /// later in the module all resolve to the shared id space.
+    #[rstest]
+    fn call_targets() {
+        let sig = Signature::new_endo(vec![qb_t()]);
+        let mut module = ModuleBuilder::new();
+        let decl = module.declare("external", sig.clone().into()).unwrap();
+        let later = module.declare("later", sig.clone().into()).unwrap();
+
+        let mut rec = module.define_function("rec", sig.clone()).unwrap();
+        let rec_node = rec.container_node();
+        let rec_handle: FuncID<true> = rec_node.into();
+        let [q] = rec.input_wires_arr();
+        let call_decl = rec.call(&decl, &[], [q]).unwrap();
+        let call_rec = rec.call(&rec_handle, &[], call_decl.outputs()).unwrap();
+        let call_later = rec.call(&later, &[], call_rec.outputs()).unwrap();
+        rec.finish_with_outputs(call_later.outputs()).unwrap();
+
+        // Define the called function after the caller.
+        let later_defn = module.define_declaration(&later).unwrap();
+        let [q] = later_defn.input_wires_arr();
+        later_defn.finish_with_outputs([q]).unwrap();
+
+        let mut hugr = module.finish_hugr().unwrap();
+        hugr.move_after_sibling(later.node(), rec_node);
+        assert!(hugr.get_optype(later.node()).is_func_defn());
+
+        let table = FunctionTable::new(&hugr);
+        assert_eq!(table.len(), 3);
+        assert_eq!(table.call_target(&hugr, call_decl.node()).unwrap(), 0);
+        assert_eq!(table.call_target(&hugr, call_rec.node()).unwrap(), 1);
+        assert_eq!(table.call_target(&hugr, call_later.node()).unwrap(), 2);
+        assert_eq!(table.node(1), Some(rec_node));
+        assert_eq!(table.node(2), Some(later.node()));
+    }
+
+    /// The ids assigned to an imported HUGR match the _jeff_ function ids,
+    /// and each call resolves to the function called in the _jeff_ module.
+    #[rstest]
+    fn round_trip_call_targets(entangled_calls: Jeff<'static>) {
+        let hugr = jeff_to_hugr(&entangled_calls).unwrap();
+        let table = FunctionTable::new(&hugr);
+        let module = entangled_calls.module();
+        assert_eq!(table.len(), module.functions().count());
+
+        for (func_id, func) in module.functions().enumerate() {
+            let jeff::reader::Function::Definition(def) = func else {
+                continue;
+            };
+            let jeff_targets = def
+                .body()
+                .operations()
+                .filter_map(|op| match op.op_type() {
+                    JeffOpType::FuncOp(call) => Some(call.func_idx as FunctionId),
+                    _ => None,
+                })
+                .sorted()
+                .collect_vec();
+            let func_node = table.node(func_id as FunctionId).unwrap();
+            let hugr_targets = hugr
+                .descendants(func_node)
+                .filter(|&n| hugr.get_optype(n).is_call())
+                .map(|call| table.call_target(&hugr, call).unwrap())
+                .sorted()
+                .collect_vec();
+            assert_eq!(hugr_targets, jeff_targets, "calls of function {func_id}");
+        }
+    }
+
+    #[rstest]
+    fn unresolved_call() {
+        let hugr = hugr::Hugr::default();
+        let table = FunctionTable::new(&hugr);
+        let err = table.call_target(&hugr, hugr.module_root()).unwrap_err();
+        assert!(matches!(err, HugrToJeffError::UnresolvedCall { .. }));
+    }
 }
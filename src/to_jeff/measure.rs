@@ -0,0 +1,166 @@
+//! Canonicalization of measurements before a HUGR to _jeff_ translation.
+//!
+//! _jeff_ distinguishes destructive measurements (`Measure`), which consume
+//! the qubit, from non-destructive ones (`MeasureNd`). They correspond to
+//! tket's `MeasureFree` and `Measure` respectively, but optimization passes
+//! may freely split a `MeasureFree` into a `Measure` followed by a `QFree`.
+
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::ops::OpType;
+use hugr::{HugrView, Node};
+use itertools::Itertools;
+
+use crate::optype::tket_ops::{self, QuantumOp};
+use crate::provenance::record_pass;
+
+/// The kind of _jeff_ measurement a tket operation is exported as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum JeffMeasure {
+    /// _jeff_'s destructive `Measure`, from a tket `MeasureFree`.
+    Destructive,
+    /// _jeff_'s non-destructive `MeasureNd`, from a tket `Measure`.
+    NonDestructive,
+}
+
+impl JeffMeasure {
+    /// Returns the _jeff_ measurement corresponding to an operation, if it is
+    /// a tket measurement.
+    pub fn from_optype(op: &OpType) -> Option<Self> {
+        match QuantumOp::of(op)? {
+            QuantumOp::MeasureDestructive => Some(Self::Destructive),
+            QuantumOp::Measure => Some(Self::NonDestructive),
+            _ => None,
+        }
+    }
+}
+
+/// Fuse each `Measure` whose qubit is immediately freed into a single
+/// destructive `MeasureFree`.
+///
+/// This should be run before exporting to _jeff_, so that the pairs produced
+/// by [`crate::JeffToHugrConfig::split_measures`] or by optimization passes
+/// are emitted as a destructive `Measure`. Any remaining `Measure` is
+/// exported as a `MeasureNd`.
+///
+/// The fused node keeps the metadata of the original `Measure`.
+///
+/// Returns the number of fused measurements.
+pub fn fuse_destructive_measures(hugr: &mut impl HugrMut<Node = Node>) -> usize {
+    record_pass(hugr, "fuse_destructive_measures");
+    let pairs = hugr
+        .nodes()
+        .filter(|&node| QuantumOp::of(hugr.get_optype(node)) == Some(QuantumOp::Measure))
+        .filter_map(|measure| {
+            let (free, _) = hugr.single_linked_input(measure, 0)?;
+            let is_free = QuantumOp::of(hugr.get_optype(free)) == Some(QuantumOp::QFree);
+            is_free.then_some((measure, free))
+        })
+        .collect_vec();
+
+    for &(measure, free) in &pairs {
+        let parent = hugr.get_parent(measure).unwrap();
+        let qubit = hugr.single_linked_output(measure, 0);
+        let bit_targets = hugr.linked_inputs(measure, 1).collect_vec();
+        let metadata = hugr.node_metadata_map(measure).clone();
+
+        let fused = hugr.add_node_with_parent(parent, tket_ops::measure_destructive());
+        hugr.overwrite_node_metadata(fused, Some(metadata));
+        hugr.remove_node(free);
+        hugr.remove_node(measure);
+
+        if let Some((src, src_port)) = qubit {
+            hugr.connect(src, src_port, fused, 0);
+        }
+        for (tgt, tgt_port) in bit_targets {
+            hugr.connect(fused, 0, tgt, tgt_port);
+        }
+    }
+    pairs.len()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::catalyst_simple;
+    use crate::{JeffToHugrConfig, jeff_to_hugr, jeff_to_hugr_with_config};
+    use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr};
+    use hugr::extension::prelude::{bool_t, qb_t};
+    use hugr::types::Signature;
+    use hugr::Hugr;
+    use jeff::Jeff;
+    use rstest::rstest;
+
+    fn measure_kinds(hugr: &impl HugrView<Node = Node>) -> Vec<JeffMeasure> {
+        hugr.nodes()
+            .filter_map(|n| JeffMeasure::from_optype(hugr.get_optype(n)))
+            .collect()
+    }
+
+    fn count_op(hugr: &impl HugrView<Node = Node>, op: QuantumOp) -> usize {
+        hugr.nodes()
+            .filter(|&n| QuantumOp::of(hugr.get_optype(n)) == Some(op))
+            .count()
+    }
+
+    /// A DFG measuring its qubit input, and freeing it if `free` is set.
+    fn measure_dfg(free: bool) -> Hugr {
+        let outputs = if free {
+            vec![bool_t()]
+        } else {
+            vec![qb_t(), bool_t()]
+        };
+        let mut builder = DFGBuilder::new(Signature::new(vec![qb_t()], outputs)).unwrap();
+        let [q] = builder.input_wires_arr();
+        let [q, bit] = builder
+            .add_dataflow_op(QuantumOp::Measure, [q])
+            .unwrap()
+            .outputs_arr();
+        if free {
+            builder.add_dataflow_op(QuantumOp::QFree, [q]).unwrap();
+            builder.finish_hugr_with_outputs([bit]).unwrap()
+        } else {
+            builder.finish_hugr_with_outputs([q, bit]).unwrap()
+        }
+    }
+
+    #[rstest]
+    fn fuse_split_measure() {
+        let mut hugr = measure_dfg(true);
+        assert_eq!(measure_kinds(&hugr), [JeffMeasure::NonDestructive]);
+
+        assert_eq!(fuse_destructive_measures(&mut hugr), 1);
+        hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(measure_kinds(&hugr), [JeffMeasure::Destructive]);
+        assert_eq!(count_op(&hugr, QuantumOp::QFree), 0);
+    }
+
+    #[rstest]
+    fn keep_non_destructive_measure() {
+        let mut hugr = measure_dfg(false);
+        assert_eq!(fuse_destructive_measures(&mut hugr), 0);
+        assert_eq!(measure_kinds(&hugr), [JeffMeasure::NonDestructive]);
+    }
+
+    /// `MeasureNd` operations are imported and canonicalized as non-destructive,
+    /// regardless of the measurement splitting option.
+    #[rstest]
+    fn imported_non_destructive(catalyst_simple: Jeff<'static>) {
+        let config = JeffToHugrConfig {
+            split_measures: true,
+            ..Default::default()
+        };
+        for mut hugr in [
+            jeff_to_hugr(&catalyst_simple).unwrap(),
+            jeff_to_hugr_with_config(&catalyst_simple, &config)
+                .unwrap()
+                .hugr,
+        ] {
+            let before = measure_kinds(&hugr);
+            assert!(!before.is_empty());
+            assert!(before.iter().all(|&k| k == JeffMeasure::NonDestructive));
+
+            assert_eq!(fuse_destructive_measures(&mut hugr), 0);
+            assert_eq!(measure_kinds(&hugr), before);
+        }
+    }
+}
@@ -0,0 +1,39 @@
+//! Recovery of the operations protected from optimization before a HUGR to
+//! _jeff_ translation.
+//!
+//! Optimization passes may drop node metadata, so the protection is
+//! recovered from the barriers and containers of either
+//! [`crate::Protection`] encoding as well.
+
+use std::collections::BTreeSet;
+
+use hugr::ops::OpType;
+use hugr::{HugrView, Node};
+
+use crate::metadata;
+
+/// Returns the operations protected by [`crate::passes::protect_ops`].
+///
+/// These are the operations marked with the [`metadata::PROTECTED`]
+/// metadata, the operations inside the protected containers, and the
+/// consumers of the `"begin"` barriers. The barriers and containers are not
+/// included, so an exporter can drop them and mark the returned operations
+/// with the protection attribute instead.
+pub fn protected_ops(hugr: &impl HugrView<Node = Node>) -> BTreeSet<Node> {
+    let mut protected = BTreeSet::new();
+    for node in hugr.nodes() {
+        let Some(marker) = hugr.get_metadata(node, metadata::PROTECTED) else {
+            continue;
+        };
+        if *marker == true {
+            protected.insert(node);
+        } else if *marker == "container" {
+            protected.extend(hugr.children(node).filter(|&child| {
+                !matches!(hugr.get_optype(child), OpType::Input(_) | OpType::Output(_))
+            }));
+        } else if *marker == "begin" {
+            protected.extend(hugr.output_neighbours(node));
+        }
+    }
+    protected
+}
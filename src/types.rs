@@ -5,6 +5,7 @@ use hugr::extension::{ExtensionId, prelude as hugr_prelude};
 use hugr::std_extensions::arithmetic::{
     float_types as hugr_float_types, int_types as hugr_int_types,
 };
+use hugr::std_extensions::collections::list::list_type;
 use hugr::types::{Signature as HugrSignature, Term, Type as HugrType, TypeArg, TypeName};
 use itertools::Itertools;
 use jeff::types::{FloatPrecision, Type as JeffType};
@@ -24,6 +25,21 @@ use crate::extension::{
 /// specified in the _jeff_ type.
 ///
 /// Qubit arrays are translated into `qureg` types from the _jeff_ extension.
+///
+/// # Examples
+///
+/// ```
+/// use hugr_jeff::JeffType;
+/// use hugr_jeff::extension::qureg_type;
+/// use hugr_jeff::hugr::extension::prelude::bool_t;
+/// use hugr_jeff::hugr::std_extensions::arithmetic::int_types::int_type;
+/// use hugr_jeff::types::jeff_to_hugr;
+///
+/// assert_eq!(jeff_to_hugr(JeffType::Int { bits: 1 }), bool_t());
+/// // A 24-bit integer is widened to 32 bits, i.e. a log width of 5.
+/// assert_eq!(jeff_to_hugr(JeffType::Int { bits: 24 }), int_type(5));
+/// assert_eq!(jeff_to_hugr(JeffType::QubitRegister), qureg_type());
+/// ```
 pub fn jeff_to_hugr(jeff_type: JeffType) -> HugrType {
     match jeff_type {
         JeffType::Qubit => qb_t(),
@@ -57,6 +73,23 @@ pub fn jeff_signature_to_hugr(
 /// # Errors
 ///
 /// - [`HugrToJeffError::UnsupportedType`] if the HUGR type is not supported by _jeff_.
+///
+/// # Examples
+///
+/// ```
+/// use hugr_jeff::hugr::extension::prelude::usize_t;
+/// use hugr_jeff::types::{hugr_to_jeff, jeff_to_hugr};
+/// use hugr_jeff::{FloatPrecision, HugrToJeffError, JeffType};
+///
+/// let float = || JeffType::Float {
+///     precision: FloatPrecision::Float64,
+/// };
+/// assert_eq!(hugr_to_jeff(&jeff_to_hugr(float())).unwrap(), float());
+///
+/// // The prelude `usize` type has no _jeff_ equivalent.
+/// let err = hugr_to_jeff(&usize_t()).unwrap_err();
+/// assert!(matches!(err, HugrToJeffError::UnsupportedType { .. }));
+/// ```
 pub fn hugr_to_jeff(hugr_type: &HugrType) -> Result<JeffType, HugrToJeffError> {
     // Error to return when the HUGR type is unsupported
     let unsupported_err = || HugrToJeffError::UnsupportedType {
@@ -67,6 +100,10 @@ pub fn hugr_to_jeff(hugr_type: &HugrType) -> Result<JeffType, HugrToJeffError> {
     if &hugr_prelude::bool_t() == hugr_type {
         return Ok(JeffType::Int { bits: 1 });
     }
+    // Bit arrays translated with `BitArrays::BoolCollection`.
+    if &list_type(hugr_prelude::bool_t()) == hugr_type {
+        return Ok(JeffType::IntArray { bits: 1 });
+    }
 
     // Otherwise, we can assume the type is a custom type.
     let hugr::types::TypeEnum::Extension(custom) = hugr_type.as_type_enum() else {
@@ -205,4 +242,13 @@ mod test {
         assert_eq!(roundtripped_inputs, inputs);
         assert_eq!(roundtripped_outputs, outputs);
     }
+
+    #[rstest]
+    fn bool_list_to_jeff() {
+        let bool_list = list_type(hugr_prelude::bool_t());
+        assert_eq!(
+            hugr_to_jeff(&bool_list).unwrap(),
+            JeffType::IntArray { bits: 1 }
+        );
+    }
 }
@@ -0,0 +1,102 @@
+//! The public API can be used through `hugr_jeff` paths only.
+//!
+//! This test deliberately avoids naming the `hugr`, `jeff` and `tket` crates
+//! directly, to check that their re-exports cover the public signatures.
+//!
+//! It also compares the items exported from the crate root with the
+//! `tests/public_api.txt` snapshot. Run with `UPDATE_PUBLIC_API=1` to update
+//! the snapshot after an intentional change.
+
+use std::fs::File;
+use std::io::BufReader;
+
+use hugr_jeff::extension::{JEFF_EXTENSION, JeffOp};
+use hugr_jeff::hugr::HugrView;
+use hugr_jeff::hugr::ops::{OpTrait, OpType};
+use hugr_jeff::{
+    FloatPrecision, FunctionTable, Hugr, Jeff, JeffToHugrConfig, JeffType, Node, Signature,
+};
+
+fn qubits() -> Jeff<'static> {
+    let file = File::open("test_files/qubits/qubits.jeff").unwrap();
+    Jeff::read(BufReader::new(file)).unwrap()
+}
+
+#[test]
+fn convert() {
+    let jeff = qubits();
+    let converted =
+        hugr_jeff::jeff_to_hugr_with_config(&jeff, &JeffToHugrConfig::default()).unwrap();
+    let hugr: Hugr = converted.hugr;
+    hugr.validate().unwrap();
+
+    let table = FunctionTable::new(&hugr);
+    let first: Node = table.node(0).unwrap();
+    assert_eq!(converted.map.function_node(0), Some(first));
+}
+
+#[test]
+fn extension() {
+    let op: OpType = JeffOp::QuregLength.into_extension_op().into();
+    let signature: Signature = op.dataflow_signature().unwrap().into_owned();
+    assert_eq!(signature.input_count(), 1);
+    assert!(JEFF_EXTENSION.get_op("QuregLength").is_some());
+}
+
+#[test]
+fn types() {
+    let float = || JeffType::Float {
+        precision: FloatPrecision::Float64,
+    };
+    let hugr_type = hugr_jeff::types::jeff_to_hugr(float());
+    assert_eq!(hugr_jeff::types::hugr_to_jeff(&hugr_type).unwrap(), float());
+}
+
+/// Lists the `pub` items declared at the crate root, one per line.
+fn root_exports(lib_rs: &str) -> Vec<String> {
+    let mut exports = Vec::new();
+    let mut statement = String::new();
+    for line in lib_rs.lines().map(str::trim) {
+        if statement.is_empty() && !line.starts_with("pub ") {
+            continue;
+        }
+        statement.push_str(line);
+        statement.push(' ');
+        if !line.ends_with(';') {
+            continue;
+        }
+        let normalized = statement.split_whitespace().collect::<Vec<_>>().join(" ");
+        let normalized = normalized.trim_end_matches(';');
+        match normalized.split_once('{') {
+            Some((prefix, items)) => {
+                for item in items.trim_end_matches('}').split(',') {
+                    let item = item.trim();
+                    if !item.is_empty() {
+                        exports.push(format!("{prefix}{item}"));
+                    }
+                }
+            }
+            None => exports.push(normalized.to_string()),
+        }
+        statement.clear();
+    }
+    exports.sort();
+    exports
+}
+
+#[test]
+fn public_api_snapshot() {
+    let lib_rs = std::fs::read_to_string("src/lib.rs").unwrap();
+    let exports = root_exports(&lib_rs).join("\n") + "\n";
+
+    let snapshot_path = "tests/public_api.txt";
+    if std::env::var_os("UPDATE_PUBLIC_API").is_some() {
+        std::fs::write(snapshot_path, &exports).unwrap();
+        return;
+    }
+    let snapshot = std::fs::read_to_string(snapshot_path).unwrap();
+    assert_eq!(
+        exports, snapshot,
+        "The crate root exports changed. Run with UPDATE_PUBLIC_API=1 to update the snapshot."
+    );
+}
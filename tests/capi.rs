@@ -0,0 +1,102 @@
+//! Tests for the C ABI, calling the exported functions through raw extern
+//! declarations as a C caller would.
+
+use std::ffi::{CStr, c_char};
+
+use hugr_jeff::Hugr;
+use hugr_jeff::capi::{HUGR_JEFF_INVALID_ARGUMENT, HUGR_JEFF_OK, HUGR_JEFF_READ_ERROR};
+use hugr_jeff::hugr::HugrView;
+use hugr_jeff::hugr_importer::extension_registry;
+
+unsafe extern "C" {
+    fn hugr_jeff_convert(
+        jeff_bytes: *const u8,
+        len: usize,
+        out_envelope: *mut *mut u8,
+        out_len: *mut usize,
+        out_error: *mut *mut c_char,
+    ) -> i32;
+    fn hugr_jeff_free_buffer(buffer: *mut u8, len: usize);
+    fn hugr_jeff_free_string(string: *mut c_char);
+}
+
+/// The result of a call to `hugr_jeff_convert`, copied out of the FFI buffers.
+struct Converted {
+    status: i32,
+    envelope: Option<Vec<u8>>,
+    error: Option<String>,
+}
+
+fn convert(input: &[u8]) -> Converted {
+    let mut envelope = std::ptr::null_mut();
+    let mut len = 0;
+    let mut error = std::ptr::null_mut();
+    let status = unsafe {
+        hugr_jeff_convert(input.as_ptr(), input.len(), &mut envelope, &mut len, &mut error)
+    };
+
+    let envelope = (!envelope.is_null()).then(|| {
+        let bytes = unsafe { std::slice::from_raw_parts(envelope, len) }.to_vec();
+        unsafe { hugr_jeff_free_buffer(envelope, len) };
+        bytes
+    });
+    let error = (!error.is_null()).then(|| {
+        let message = unsafe { CStr::from_ptr(error) }
+            .to_string_lossy()
+            .into_owned();
+        unsafe { hugr_jeff_free_string(error) };
+        message
+    });
+    Converted {
+        status,
+        envelope,
+        error,
+    }
+}
+
+#[test]
+fn convert_fixture() {
+    let input = std::fs::read("test_files/catalyst_simple/catalyst_simple.jeff").unwrap();
+    let converted = convert(&input);
+    assert_eq!(converted.status, HUGR_JEFF_OK, "{:?}", converted.error);
+    assert!(converted.error.is_none());
+
+    let envelope = converted.envelope.unwrap();
+    let hugr = Hugr::load(envelope.as_slice(), Some(&extension_registry())).unwrap();
+    hugr.validate().unwrap();
+    assert!(hugr.num_nodes() > 0);
+}
+
+#[test]
+fn convert_garbage() {
+    let converted = convert(b"not a jeff file");
+    assert_eq!(converted.status, HUGR_JEFF_READ_ERROR);
+    assert!(converted.envelope.is_none());
+    assert!(!converted.error.unwrap().is_empty());
+}
+
+#[test]
+fn convert_null_output() {
+    let input = b"not a jeff file";
+    let mut error = std::ptr::null_mut();
+    let status = unsafe {
+        hugr_jeff_convert(
+            input.as_ptr(),
+            input.len(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            &mut error,
+        )
+    };
+    assert_eq!(status, HUGR_JEFF_INVALID_ARGUMENT);
+    assert!(!error.is_null());
+    unsafe { hugr_jeff_free_string(error) };
+}
+
+#[test]
+fn free_null() {
+    unsafe {
+        hugr_jeff_free_buffer(std::ptr::null_mut(), 0);
+        hugr_jeff_free_string(std::ptr::null_mut());
+    }
+}
@@ -0,0 +1,94 @@
+//! End-to-end translation of the QAOA kernel exported from Catalyst.
+//!
+//! The example combines a layer loop, float arrays of angles indexed by the
+//! loop counter, parametric rotations and register operations, see
+//! `test_files/catalyst_qaoa/README.md`.
+
+use std::fs::File;
+use std::io::BufReader;
+
+use hugr_jeff::analysis::{circuit_depth, conversion_footprint};
+use hugr_jeff::extension::{JEFF_EXTENSION_ID, JeffOp};
+use hugr_jeff::hugr::extension::prelude::bool_t;
+use hugr_jeff::hugr::ops::OpType;
+use hugr_jeff::hugr::std_extensions::collections::list::list_type;
+use hugr_jeff::hugr::{Hugr, HugrView};
+use hugr_jeff::tket::TketOp;
+use hugr_jeff::{Jeff, JeffToHugrConfig, jeff_to_hugr, jeff_to_hugr_with_config};
+
+fn catalyst_qaoa() -> Jeff<'static> {
+    let file = File::open("test_files/catalyst_qaoa/catalyst_qaoa.jeff").unwrap();
+    Jeff::read(BufReader::new(file)).unwrap()
+}
+
+fn count(hugr: &Hugr, pred: impl Fn(&OpType) -> bool) -> usize {
+    hugr.nodes().filter(|&n| pred(hugr.get_optype(n))).count()
+}
+
+#[test]
+fn catalyst_preset() {
+    let jeff = catalyst_qaoa();
+    let converted = jeff_to_hugr_with_config(&jeff, &JeffToHugrConfig::catalyst()).unwrap();
+    let hugr = &converted.hugr;
+    hugr.validate().unwrap_or_else(|e| panic!("{e}"));
+
+    // The layers run in a single loop.
+    assert_eq!(count(hugr, OpType::is_tail_loop), 1);
+
+    // The rotations of a layer take their angles from the arrays at runtime.
+    let rotations = hugr
+        .nodes()
+        .filter(|&n| {
+            matches!(
+                hugr.get_optype(n).cast::<TketOp>(),
+                Some(TketOp::Rz | TketOp::Rx)
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(rotations.len(), 3);
+    for rotation in rotations {
+        let (angle, _) = hugr.single_linked_output(rotation, 1).unwrap();
+        assert!(!hugr.get_optype(angle).is_load_constant());
+    }
+    let array_gets = count(hugr, |op| {
+        matches!(op.cast::<JeffOp>(), Some(JeffOp::FloatArrayGet { .. }))
+    });
+    assert_eq!(array_gets, 2);
+
+    // The measured qubits are returned as a list of booleans.
+    assert_eq!(
+        count(hugr, |op| op.cast::<TketOp>() == Some(TketOp::MeasureFree)),
+        2
+    );
+    let qaoa = converted.map.function_by_name("qaoa").unwrap();
+    let OpType::FuncDefn(defn) = hugr.get_optype(qaoa) else {
+        panic!("qaoa is not a function definition");
+    };
+    assert_eq!(
+        defn.signature().body().output_types(),
+        [list_type(bool_t())]
+    );
+}
+
+#[test]
+fn analyses() {
+    let jeff = catalyst_qaoa();
+
+    let depth = circuit_depth(&jeff, "qaoa").unwrap();
+    // The Hadamard layer, two layers of CX, RZ, CX and RX, and the
+    // measurements.
+    assert_eq!(depth.depth, 10);
+    assert_eq!(depth.depth_2q, 4);
+    assert!(!depth.unbounded);
+
+    let footprint = conversion_footprint(&jeff).unwrap();
+    assert_eq!(footprint.unsupported_ops, 0);
+    let jeff_ops = &footprint.extension_ops[&JEFF_EXTENSION_ID.to_string()];
+    assert_eq!(jeff_ops["FloatArrayGet"], 2);
+    assert_eq!(jeff_ops["IntArraySet"], 2);
+
+    // The estimate is within 10% of the translation.
+    let hugr = jeff_to_hugr(&jeff).unwrap();
+    assert!(footprint.nodes.abs_diff(hugr.num_nodes()) * 10 <= hugr.num_nodes());
+    assert!(footprint.edges.abs_diff(hugr.num_edges()) * 10 <= hugr.num_edges());
+}
@@ -0,0 +1,153 @@
+//! Conformance of the translation on collections of _jeff_ programs.
+//!
+//! [`conformance_dir`] translates every `.jeff` program under the directory
+//! named by the `JEFF_CONFORMANCE_DIR` environment variable, such as a
+//! checkout of the _jeff_ specification examples, and writes a summary of
+//! the outcomes to `jeff-conformance.json` in the test target directory. It
+//! fails if any translation panics or produces an invalid HUGR.
+//!
+//! [`vendored_programs`] checks the outcomes of the programs listed in
+//! `test_files/conformance/expectations.json`, so changes in their
+//! classification are caught.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+use hugr_jeff::{Jeff, JeffToHugrConfig, jeff_to_hugr_with_config};
+use serde::{Deserialize, Serialize};
+
+/// The outcome of translating a program with the strict preset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Outcome {
+    /// Translated into a valid HUGR, without warnings.
+    Validated,
+    /// Translated into a valid HUGR, with approximations.
+    Warnings,
+    /// Rejected with a structured error.
+    Error,
+    /// Translated into a HUGR that fails validation.
+    Invalid,
+    /// The translation panicked.
+    Panic,
+}
+
+impl Outcome {
+    /// Whether the outcome is a bug in the translation.
+    fn is_failure(self) -> bool {
+        matches!(self, Self::Invalid | Self::Panic)
+    }
+}
+
+/// The outcome of a program, with the error or panic message.
+#[derive(Debug, Serialize)]
+struct ProgramReport {
+    path: PathBuf,
+    outcome: Outcome,
+    detail: Option<String>,
+}
+
+/// Read and translate a program with
+/// [`JeffToHugrConfig::strict_roundtrip`], and classify the outcome.
+fn classify(path: &Path) -> ProgramReport {
+    let translate = || -> Result<Outcome, (Outcome, String)> {
+        let error = |e: &dyn std::fmt::Display| (Outcome::Error, e.to_string());
+        let file = File::open(path).map_err(|e| error(&e))?;
+        let jeff = Jeff::read(BufReader::new(file)).map_err(|e| error(&e))?;
+        let config = JeffToHugrConfig::strict_roundtrip();
+        let converted = jeff_to_hugr_with_config(&jeff, &config).map_err(|e| error(&e))?;
+        converted
+            .hugr
+            .validate()
+            .map_err(|e| (Outcome::Invalid, e.to_string()))?;
+        Ok(match converted.warnings.is_empty() {
+            true => Outcome::Validated,
+            false => Outcome::Warnings,
+        })
+    };
+    let (outcome, detail) = match panic::catch_unwind(AssertUnwindSafe(translate)) {
+        Ok(Ok(outcome)) => (outcome, None),
+        Ok(Err((outcome, detail))) => (outcome, Some(detail)),
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned());
+            (Outcome::Panic, message)
+        }
+    };
+    ProgramReport {
+        path: path.to_path_buf(),
+        outcome,
+        detail,
+    }
+}
+
+/// Find the `.jeff` files under a directory, sorted by path.
+fn jeff_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let entries =
+            std::fs::read_dir(&dir).unwrap_or_else(|e| panic!("{}: {e}", dir.display()));
+        for entry in entries {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.extension().is_some_and(|ext| ext == "jeff") {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+#[test]
+fn conformance_dir() {
+    let Some(dir) = std::env::var_os("JEFF_CONFORMANCE_DIR") else {
+        eprintln!("Skipping the conformance suite, set JEFF_CONFORMANCE_DIR to run it.");
+        return;
+    };
+    let reports = jeff_files(Path::new(&dir))
+        .iter()
+        .map(PathBuf::as_path)
+        .map(classify)
+        .collect::<Vec<_>>();
+
+    let mut counts = BTreeMap::new();
+    for report in &reports {
+        *counts.entry(report.outcome).or_insert(0usize) += 1;
+    }
+    let summary = serde_json::json!({ "counts": counts, "programs": reports });
+    let summary_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("jeff-conformance.json");
+    std::fs::write(&summary_path, serde_json::to_string_pretty(&summary).unwrap()).unwrap();
+    eprintln!("Conformance summary written to {}", summary_path.display());
+
+    let failures = reports
+        .iter()
+        .filter(|report| report.outcome.is_failure())
+        .map(|report| format!("{}: {:?}", report.path.display(), report.detail))
+        .collect::<Vec<_>>();
+    assert!(failures.is_empty(), "Translation failures:\n{}", failures.join("\n"));
+}
+
+#[test]
+fn vendored_programs() {
+    let file = File::open("test_files/conformance/expectations.json").unwrap();
+    let expected: BTreeMap<PathBuf, Outcome> = serde_json::from_reader(file).unwrap();
+    assert!(!expected.is_empty());
+    for (path, outcome) in expected {
+        let report = classify(&Path::new("test_files").join(&path));
+        assert_eq!(
+            report.outcome,
+            outcome,
+            "{}: {:?}",
+            path.display(),
+            report.detail
+        );
+    }
+}
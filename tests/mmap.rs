@@ -0,0 +1,29 @@
+//! Import of memory-mapped _jeff_ files.
+
+use std::fs::File;
+
+use hugr_jeff::hugr::HugrView;
+use hugr_jeff::hugr_importer::{ImportError, ImportOptions, import, jeff_to_hugr_mmap};
+use hugr_jeff::provenance::Provenance;
+
+#[test]
+fn mapped_import() {
+    for name in ["qubits", "catalyst_simple", "catalyst_tket_opt"] {
+        let path = format!("test_files/{name}/{name}.jeff");
+        let mapped = jeff_to_hugr_mmap(&path, &ImportOptions::default()).unwrap();
+        let read = import(&mut File::open(&path).unwrap(), &ImportOptions::default()).unwrap();
+
+        assert_eq!(mapped.num_nodes(), read.num_nodes());
+        assert_eq!(mapped.num_edges(), read.num_edges());
+        assert_eq!(
+            Provenance::from_hugr(&mapped).unwrap().input_sha256,
+            Provenance::from_hugr(&read).unwrap().input_sha256
+        );
+    }
+}
+
+#[test]
+fn missing_file() {
+    let err = jeff_to_hugr_mmap("test_files/missing.jeff", &ImportOptions::default()).unwrap_err();
+    assert!(matches!(err, ImportError::Io(_)));
+}
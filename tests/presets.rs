@@ -0,0 +1,126 @@
+//! The configuration presets translate the bundled examples they target.
+
+use std::fs::File;
+use std::io::BufReader;
+
+use hugr_jeff::analysis::{GateSet, JeffOpRule, check_gate_set};
+use hugr_jeff::hugr::HugrView;
+use hugr_jeff::hugr::ops::OpType;
+use hugr_jeff::tket::TketOp;
+use hugr_jeff::{
+    ArrayBounds, BitArrays, Jeff, JeffToHugrConfig, JeffToHugrError, jeff_to_hugr_with_config,
+};
+
+const EXAMPLES: [&str; 5] = [
+    "catalyst_simple",
+    "catalyst_tket_opt",
+    "entangled_calls",
+    "entangled_qs",
+    "qubits",
+];
+
+fn example(name: &str) -> Jeff<'static> {
+    let file = File::open(format!("test_files/{name}/{name}.jeff")).unwrap();
+    Jeff::read(BufReader::new(file)).unwrap()
+}
+
+/// The gate set of the `qubits` example, possibly without some gates.
+fn qubits_gate_set(excluded: &[&str]) -> GateSet {
+    let gates = ["H", "X", "CX", "Rx"]
+        .into_iter()
+        .filter(|gate| !excluded.contains(gate));
+    GateSet::from_tket_ops([TketOp::QAlloc])
+        .with_jeff_op(JeffOpRule::new("QGateN").with_gates(gates))
+}
+
+/// The presets only change the options they document.
+#[test]
+fn preset_flags() {
+    let default = JeffToHugrConfig::default();
+    assert_eq!(
+        JeffToHugrConfig::catalyst(),
+        default.clone().with_bit_arrays_as(BitArrays::BoolCollection)
+    );
+    assert_eq!(
+        JeffToHugrConfig::strict_roundtrip(),
+        default
+            .clone()
+            .with_exact_angles(None)
+            .with_strict_modules(true)
+            .with_record_provenance(true)
+    );
+    let gate_set = qubits_gate_set(&[]);
+    assert_eq!(
+        JeffToHugrConfig::hardware(gate_set.clone()),
+        default
+            .with_gate_set(gate_set)
+            .with_array_bounds(ArrayBounds::Panic)
+            .with_bit_arrays_as(BitArrays::BoolCollection)
+    );
+}
+
+#[test]
+fn catalyst_examples() {
+    let config = JeffToHugrConfig::catalyst();
+    for name in ["catalyst_simple", "catalyst_tket_opt"] {
+        let converted = jeff_to_hugr_with_config(&example(name), &config)
+            .unwrap_or_else(|e| panic!("{name}: {e}"));
+        converted
+            .hugr
+            .validate()
+            .unwrap_or_else(|e| panic!("{name}: {e}"));
+    }
+}
+
+/// The translated function signatures translate back to the _jeff_ ones.
+#[test]
+fn strict_roundtrip_signatures() {
+    let config = JeffToHugrConfig::strict_roundtrip();
+    for name in EXAMPLES {
+        let jeff = example(name);
+        let converted = jeff_to_hugr_with_config(&jeff, &config).unwrap();
+        converted.hugr.validate().unwrap();
+
+        for (func_id, func) in jeff.module().functions().enumerate() {
+            let node = converted.map.function_node(func_id as _).unwrap();
+            let signature = match converted.hugr.get_optype(node) {
+                OpType::FuncDefn(defn) => defn.signature().body().clone(),
+                OpType::FuncDecl(decl) => decl.signature().body().clone(),
+                op => panic!("{name}: function {func_id} translated to {op:?}"),
+            };
+            let roundtrip = |types: &[hugr_jeff::hugr::types::Type]| {
+                types
+                    .iter()
+                    .map(|ty| hugr_jeff::types::hugr_to_jeff(ty).unwrap())
+                    .collect::<Vec<_>>()
+            };
+            let inputs = func
+                .input_types()
+                .map(|port| port.unwrap().ty())
+                .collect::<Vec<_>>();
+            let outputs = func
+                .output_types()
+                .map(|port| port.unwrap().ty())
+                .collect::<Vec<_>>();
+            assert_eq!(roundtrip(signature.input_types()), inputs, "{name}: {func_id}");
+            assert_eq!(roundtrip(signature.output_types()), outputs, "{name}: {func_id}");
+        }
+    }
+}
+
+#[test]
+fn hardware_gate_set() {
+    let config = JeffToHugrConfig::hardware(qubits_gate_set(&[]));
+    let converted = jeff_to_hugr_with_config(&example("qubits"), &config).unwrap();
+    converted.hugr.validate().unwrap();
+    assert!(check_gate_set(&converted.hugr, &qubits_gate_set(&[])).is_empty());
+
+    let config = JeffToHugrConfig::hardware(qubits_gate_set(&["H"]));
+    match jeff_to_hugr_with_config(&example("qubits"), &config) {
+        Err(JeffToHugrError::GateSetViolations { violations }) => {
+            assert!(violations.iter().all(|v| v.op_name == "H"));
+        }
+        Err(e) => panic!("unexpected error: {e}"),
+        Ok(_) => panic!("the translation should fail"),
+    }
+}
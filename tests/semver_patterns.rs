@@ -0,0 +1,192 @@
+//! The documented way of consuming the configuration and report types.
+//!
+//! The public structs that may gain fields are `#[non_exhaustive]`, so they
+//! cannot be built with struct literals outside of the crate. They are built
+//! from their `Default` value with the `with_*` methods instead. The public
+//! enums that may gain variants are `#[non_exhaustive]` too, and must be
+//! matched with a wildcard arm.
+//!
+//! [`non_exhaustive_attributes`] checks the attribute on the listed types, so
+//! that new fields and variants can be added without a breaking release.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::ops::ControlFlow;
+
+use hugr_jeff::analysis::{GateSet, JeffOpRule};
+use hugr_jeff::hugr_importer::{ImportError, ImportOptions, import};
+use hugr_jeff::passes::{ResultConvention, RuntimeValues, StripOptions};
+use hugr_jeff::{
+    ArrayBounds, BitArrays, BitOrder, ConversionHooks, ExactAngleConfig, HandleAbi, IntOverflow,
+    Jeff, JeffToHugrConfig, JeffToHugrError, ProgressHook, QuregLowering, ResourceLimit,
+    ResourceLimits,
+};
+
+fn qubits() -> Jeff<'static> {
+    let file = File::open("test_files/qubits/qubits.jeff").unwrap();
+    Jeff::read(BufReader::new(file)).unwrap()
+}
+
+#[test]
+fn config_builders() {
+    let limits = ResourceLimits::default()
+        .with_max_nodes(100_000)
+        .with_max_qubits(1_000)
+        .with_max_const_array_len(100)
+        .with_max_functions(10)
+        .with_max_inline_depth(4)
+        .with_max_string_len(256);
+    let exact_angles = ExactAngleConfig::default()
+        .with_tolerance(1e-9)
+        .with_max_denominator_log(4);
+    let config = JeffToHugrConfig::default()
+        .with_record_provenance(true)
+        .with_exact_angles(exact_angles)
+        .with_array_bounds(ArrayBounds::Panic)
+        .with_int_overflow(IntOverflow::Trap)
+        .with_split_measures(true)
+        .with_symbolize_angle_params(true)
+        .with_inline_calls(true)
+        .with_strict_inline(false)
+        .with_strict_modules(true)
+        .with_strict_names(true)
+        .with_typecheck(true)
+        .with_limits(limits)
+        .with_hooks(ConversionHooks::default())
+        .with_progress(ProgressHook::new(|_| ControlFlow::Continue(())))
+        .with_bit_order(BitOrder::MsbFirst)
+        .with_bit_arrays_as(BitArrays::Opaque)
+        .with_qureg_lowering(QuregLowering::StaticArray)
+        .with_handle_abi(HandleAbi::Entrypoints)
+        .with_annotate_depths(true)
+        .with_result_convention(ResultConvention::Bits)
+        .with_check_extensions(true)
+        .with_gate_set(None)
+        .with_validate(true)
+        .with_debug_snapshots(None);
+    assert_eq!(config.limits.max(ResourceLimit::InlineDepth), 4);
+    assert_eq!(config.exact_angles, Some(exact_angles));
+
+    let converted = hugr_jeff::jeff_to_hugr_with_config(&qubits(), &config).unwrap();
+    converted.hugr.validate().unwrap();
+
+    let opts = ImportOptions::default()
+        .with_translation(JeffToHugrConfig::default().with_exact_angles(None))
+        .with_validate(true);
+    assert_eq!(opts.translation.exact_angles, None);
+}
+
+#[test]
+fn pass_and_analysis_builders() {
+    let strip = StripOptions::default()
+        .with_debug_prints(false)
+        .with_bounds_checks(true);
+    assert!(!strip.debug_prints);
+
+    let values = RuntimeValues::default()
+        .with_num_qubits(20)
+        .with_shot_count(None)
+        .with_query("max_depth", 3);
+    assert_eq!(values.queries.get("max_depth"), Some(&3));
+
+    let gate_set = GateSet::quantinuum_like()
+        .with_jeff_op(JeffOpRule::new("QGateN").with_gates(["U"]).with_max_controls(0));
+    assert_eq!(gate_set.jeff_ops.len(), 1);
+}
+
+#[test]
+fn match_errors() {
+    let config = JeffToHugrConfig::default()
+        .with_limits(ResourceLimits::default().with_max_nodes(1));
+    let err = hugr_jeff::jeff_to_hugr_with_config(&qubits(), &config).unwrap_err();
+    match err {
+        JeffToHugrError::ResourceLimitExceeded { limit, .. } => {
+            assert_eq!(limit, ResourceLimit::Nodes);
+        }
+        other => panic!("unexpected error: {other}"),
+    }
+
+    let err = import(&mut &b"not a jeff program"[..], &ImportOptions::default()).unwrap_err();
+    assert!(matches!(err, ImportError::Read(_)), "unexpected error: {err}");
+}
+
+/// The types checked by [`non_exhaustive_attributes`], by source file.
+const NON_EXHAUSTIVE: &[(&str, &[&str])] = &[
+    (
+        "src/to_hugr.rs",
+        &["ConvertedHugr", "ConversionStats", "JeffToHugrError", "ConversionWarning"],
+    ),
+    (
+        "src/to_hugr/config.rs",
+        &[
+            "JeffToHugrConfig",
+            "ResourceLimits",
+            "ResourceLimit",
+            "ArrayBounds",
+            "IntOverflow",
+            "BitOrder",
+            "BitArrays",
+            "QuregLowering",
+            "ExactAngleConfig",
+            "NanAngles",
+            "HandleAbi",
+            "FunctionDedup",
+            "Protection",
+        ],
+    ),
+    ("src/to_hugr/modules.rs", &["ModuleInfo", "ModuleSelector"]),
+    ("src/to_hugr/progress.rs", &["Progress"]),
+    ("src/to_hugr/split.rs", &["LinkError"]),
+    ("src/to_hugr/target.rs", &["TargetInfo"]),
+    ("src/to_hugr/trace.rs", &["TraceEvent"]),
+    ("src/to_jeff.rs", &["HugrToJeffError"]),
+    (
+        "src/capabilities.rs",
+        &["Support", "OpCategory", "OpKindQuery", "OpSupport", "TypeSupport", "CapabilityManifest"],
+    ),
+    ("src/hugr_importer.rs", &["ImportOptions", "ImportError"]),
+    ("src/provenance.rs", &["Provenance"]),
+    (
+        "src/envelope.rs",
+        &["HugrEnvelopeTarget", "HugrFeature", "CompatibilityError", "StoreCompatError"],
+    ),
+    ("src/extension/jeff_op.rs", &["OpBuildError"]),
+    ("src/extension/register_semantics.rs", &["LengthChange"]),
+    ("src/passes/strip.rs", &["StripOptions"]),
+    ("src/passes/runtime.rs", &["RuntimeValues"]),
+    ("src/passes/switch_ladder.rs", &["SwitchLadderOptions"]),
+    ("src/passes/phase.rs", &["GlobalPhaseLowering"]),
+    ("src/passes/free_zero.rs", &["FreeZeroRepair"]),
+    ("src/passes/results.rs", &["ResultConvention"]),
+    ("src/analysis/ancilla.rs", &["AncillaRegion"]),
+    ("src/analysis/borrow.rs", &["BorrowViolation", "BorrowViolationKind"]),
+    ("src/analysis/depth.rs", &["DepthReport"]),
+    ("src/analysis/diff.rs", &["JeffDiff", "FunctionDiff", "GateEdit", "GateToken"]),
+    ("src/analysis/footprint.rs", &["FootprintReport"]),
+    ("src/analysis/free_zero.rs", &["UnverifiedFreeZero"]),
+    ("src/analysis/gate_set.rs", &["GateSet", "JeffOpRule", "GateSetViolation"]),
+    ("src/analysis/interaction.rs", &["InteractionGraph", "QubitNode"]),
+    ("src/analysis/typecheck.rs", &["TypeCheckError", "TypePosition"]),
+];
+
+#[test]
+fn non_exhaustive_attributes() {
+    for &(path, types) in NON_EXHAUSTIVE {
+        let source = std::fs::read_to_string(path).unwrap();
+        let file = syn::parse_file(&source).unwrap();
+        for &name in types {
+            let attrs = file.items.iter().find_map(|item| match item {
+                syn::Item::Struct(item) if item.ident == name => Some(&item.attrs),
+                syn::Item::Enum(item) if item.ident == name => Some(&item.attrs),
+                _ => None,
+            });
+            let Some(attrs) = attrs else {
+                panic!("{name} is not declared in {path}");
+            };
+            assert!(
+                attrs.iter().any(|attr| attr.path().is_ident("non_exhaustive")),
+                "{name} in {path} must be #[non_exhaustive]"
+            );
+        }
+    }
+}
@@ -0,0 +1,35 @@
+//! Signature introspection without the _jeff_ extension.
+//!
+//! This runs in its own test binary so that nothing else initializes
+//! `JEFF_EXTENSION` before the signatures are computed.
+
+use std::sync::Weak;
+
+use hugr::extension::prelude::{bool_t, qb_t};
+use hugr::types::Signature;
+use hugr_jeff::extension::{intreg_custom_type, signature};
+use jeff::types::Type as JeffType;
+
+#[test]
+fn intarray_signatures() {
+    let ext = Weak::new();
+    let intarr_t = || intreg_custom_type(&ext, 1).into();
+    let int32_t = hugr_jeff::types::jeff_to_hugr(JeffType::Int { bits: 32 });
+
+    assert_eq!(
+        signature::intarray_get_signature(&ext, 1),
+        Signature::new(vec![intarr_t(), int32_t], vec![bool_t()])
+    );
+    assert_eq!(
+        signature::intarray_create_signature(&ext, 1, 3),
+        Signature::new(vec![bool_t(); 3], vec![intarr_t()])
+    );
+}
+
+#[test]
+fn qgate_signatures() {
+    let sig = signature::qgate_signature(1, 2, 1);
+    assert_eq!(sig.input_count(), 4);
+    assert_eq!(sig.output_count(), 2);
+    assert!(sig.output().iter().all(|ty| ty == &qb_t()));
+}
@@ -0,0 +1,150 @@
+//! Translations interrupted by a panic leave no state behind.
+//!
+//! Each test panics within a translation under `catch_unwind`, then checks
+//! that the next translation on the same thread matches a translation done
+//! before the panic.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::ops::ControlFlow;
+use std::panic::{AssertUnwindSafe, RefUnwindSafe, UnwindSafe, catch_unwind};
+
+use hugr_jeff::hugr::builder::{Container, HugrBuilder, ModuleBuilder};
+use hugr_jeff::hugr::envelope::EnvelopeConfig;
+use hugr_jeff::hugr::types::{Signature, Type};
+use hugr_jeff::hugr::HugrView;
+use hugr_jeff::hugr::ops::OpType;
+use hugr_jeff::hugr_importer::ImportOptions;
+use hugr_jeff::{
+    BuildContext, ConversionHook, ConversionHooks, DataflowDyn, Hugr, Jeff, JeffToHugrConfig,
+    JeffToHugrError, JeffType, ProgressHook, TypeCodec, TypeCodecs, ValueAttributes,
+    jeff_into_hugr_module, jeff_to_hugr, jeff_to_hugr_with_config,
+};
+
+fn example(name: &str) -> Jeff<'static> {
+    let file = File::open(format!("test_files/{name}/{name}.jeff")).unwrap();
+    Jeff::read(BufReader::new(file)).unwrap()
+}
+
+/// The text envelope of a HUGR, to compare translations.
+fn envelope(hugr: &Hugr) -> String {
+    hugr.store_str(EnvelopeConfig::text()).unwrap()
+}
+
+/// A hook panicking on the first operation.
+struct PanickingHook;
+
+impl ConversionHook for PanickingHook {
+    fn try_convert(
+        &self,
+        _op: &hugr_jeff::jeff::reader::Operation<'_>,
+        _builder: &mut dyn DataflowDyn,
+        _ctx: &mut BuildContext,
+    ) -> Option<Result<(), JeffToHugrError>> {
+        panic!("injected hook panic")
+    }
+}
+
+/// A codec panicking on the first function signature.
+struct PanickingCodec;
+
+impl TypeCodec for PanickingCodec {
+    fn claim(&self, _ty: JeffType, _attributes: &ValueAttributes) -> Option<Type> {
+        panic!("injected codec panic")
+    }
+
+    fn decode(&self, _hugr_type: &Type) -> OpType {
+        unreachable!()
+    }
+
+    fn encode(&self, _hugr_type: &Type) -> OpType {
+        unreachable!()
+    }
+
+    fn jeff_type(&self, _hugr_type: &Type) -> Option<(JeffType, ValueAttributes)> {
+        None
+    }
+}
+
+fn panicking_hook() -> JeffToHugrConfig {
+    let mut hooks = ConversionHooks::default();
+    hooks.push(PanickingHook);
+    JeffToHugrConfig::default().with_hooks(hooks)
+}
+
+fn panicking_progress() -> JeffToHugrConfig {
+    let hook = ProgressHook::new(|_| -> ControlFlow<()> { panic!("injected progress panic") });
+    JeffToHugrConfig::default().with_progress(hook)
+}
+
+fn panicking_codec() -> JeffToHugrConfig {
+    let mut codecs = TypeCodecs::default();
+    codecs.push(PanickingCodec);
+    JeffToHugrConfig::default().with_type_codecs(codecs)
+}
+
+/// The configuration and options can be shared with a translation under
+/// `catch_unwind` without asserting their unwind safety.
+#[test]
+fn unwind_safe_types() {
+    fn assert_unwind_safe<T: UnwindSafe + RefUnwindSafe>() {}
+    assert_unwind_safe::<JeffToHugrConfig>();
+    assert_unwind_safe::<ImportOptions>();
+}
+
+#[test]
+fn translate_after_panic() {
+    let configs = [
+        ("hook", panicking_hook()),
+        ("progress", panicking_progress()),
+        ("codec", panicking_codec()),
+    ];
+    for name in ["qubits", "entangled_calls", "catalyst_simple"] {
+        let expected = envelope(&jeff_to_hugr(&example(name)).unwrap());
+        for (source, config) in &configs {
+            let result = catch_unwind(|| jeff_to_hugr_with_config(&example(name), config));
+            assert!(result.is_err(), "the {source} did not panic on {name}");
+
+            let hugr = jeff_to_hugr(&example(name)).unwrap();
+            assert_eq!(envelope(&hugr), expected, "{name} after a {source} panic");
+        }
+    }
+}
+
+/// A module with a function named like the last function of the
+/// `entangled_calls` example.
+fn colliding_module() -> Hugr {
+    let mut builder = ModuleBuilder::new();
+    let func = builder
+        .define_function("_ZN3ghzclEv", Signature::new_endo(vec![]))
+        .unwrap();
+    let node = func.container_node();
+    let hugr = builder.finish_hugr().unwrap();
+    assert!(hugr.get_optype(node).is_func_defn());
+    hugr
+}
+
+/// Translations into an existing module are rolled back on failure, leaving
+/// the module ready for another translation.
+#[test]
+fn rollback_into_module() {
+    let jeff = example("entangled_calls");
+    let mut module = colliding_module();
+    let before = envelope(&module);
+
+    let err = jeff_into_hugr_module(&jeff, &mut module, None).unwrap_err();
+    assert!(matches!(err, JeffToHugrError::FunctionNameCollision { .. }));
+    assert_eq!(envelope(&module), before);
+
+    // The module is left intact by a panic in the caller as well.
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        jeff_into_hugr_module(&jeff, &mut module, Some("ok_")).unwrap();
+        panic!("injected caller panic")
+    }));
+    assert!(result.is_err());
+    module.validate().unwrap_or_else(|e| panic!("{e}"));
+
+    let functions = jeff_into_hugr_module(&jeff, &mut module, Some("again_")).unwrap();
+    assert_eq!(functions.len(), jeff.module().functions().count());
+    module.validate().unwrap_or_else(|e| panic!("{e}"));
+}